@@ -115,4 +115,8 @@ unsigned int _rte_ring_dequeue_burst(struct rte_ring *r, void **obj_table,
                                      unsigned int n, unsigned int *available);
 
 /* Dequeue one object from a ring. */
-int _rte_ring_dequeue(struct rte_ring *r, void **obj_p);
\ No newline at end of file
+int _rte_ring_dequeue(struct rte_ring *r, void **obj_p);
+
+/* Enqueue several objects on a ring up to a maximum number. */
+unsigned int _rte_ring_enqueue_burst(struct rte_ring *r, void *const *obj_table,
+                                     unsigned int n, unsigned int *free_space);
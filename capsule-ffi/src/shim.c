@@ -135,4 +135,11 @@ int
 _rte_ring_dequeue(struct rte_ring *r, void **obj_p)
 {
         return rte_ring_dequeue(r, obj_p);
-}
\ No newline at end of file
+}
+
+unsigned int
+_rte_ring_enqueue_burst(struct rte_ring *r, void *const *obj_table,
+                        unsigned int n, unsigned int *free_space)
+{
+        return rte_ring_enqueue_burst(r, obj_table, n, free_space);
+}
@@ -0,0 +1,126 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{Batch, Disposition};
+use crate::packets::ip::ipsec::{Esp, IpsecError, SecurityAssociation};
+use crate::packets::ip::IpPacket;
+use crate::packets::{Ethernet, Packet};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// A batch that encrypts the packets of the underlying batch with ESP.
+///
+/// On error, the packet is marked as `aborted` and will short-circuit the
+/// remainder of the pipeline.
+#[allow(missing_debug_implementations)]
+pub struct EspEncrypt<B: Batch<Item = Ethernet>, E: IpPacket> {
+    batch: B,
+    sa: SecurityAssociation,
+    _phantom: PhantomData<E>,
+}
+
+impl<B: Batch<Item = Ethernet>, E: IpPacket> EspEncrypt<B, E> {
+    /// Creates a new `EspEncrypt` batch.
+    #[inline]
+    pub fn new(batch: B, sa: SecurityAssociation) -> Self {
+        EspEncrypt {
+            batch,
+            sa,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<B: Batch<Item = Ethernet>, E> Batch for EspEncrypt<B, E>
+where
+    E: IpPacket + Packet<Envelope = Ethernet>,
+{
+    type Item = Esp<E>;
+
+    #[inline]
+    fn replenish(&mut self) {
+        self.batch.replenish();
+    }
+
+    #[inline]
+    fn next(&mut self) -> Option<Disposition<Self::Item>> {
+        let sa = &mut self.sa;
+        self.batch.next().map(|disp| {
+            disp.map(|ethernet| match sa.encrypt::<E>(ethernet) {
+                Ok(esp) => Disposition::Act(esp),
+                Err(e) => Disposition::Abort(e),
+            })
+        })
+    }
+}
+
+/// A batch that decrypts the ESP packets of the underlying batch.
+///
+/// Each packet is decrypted with the security association matching its
+/// SPI. If there is no match, or the packet is replayed or fails the
+/// integrity check, the packet is marked as `aborted` and will
+/// short-circuit the remainder of the pipeline.
+#[allow(missing_debug_implementations)]
+pub struct EspDecrypt<B: Batch<Item = Esp<E>>, E: IpPacket> {
+    batch: B,
+    sas: HashMap<u32, SecurityAssociation>,
+}
+
+impl<B: Batch<Item = Esp<E>>, E: IpPacket> EspDecrypt<B, E> {
+    /// Creates a new `EspDecrypt` batch.
+    #[inline]
+    pub fn new<I>(batch: B, sas: I) -> Self
+    where
+        I: IntoIterator<Item = SecurityAssociation>,
+    {
+        EspDecrypt {
+            batch,
+            sas: sas.into_iter().map(|sa| (sa.spi(), sa)).collect(),
+        }
+    }
+}
+
+impl<B: Batch<Item = Esp<E>>, E> Batch for EspDecrypt<B, E>
+where
+    E: IpPacket + Packet<Envelope = Ethernet>,
+{
+    type Item = Ethernet;
+
+    #[inline]
+    fn replenish(&mut self) {
+        self.batch.replenish();
+    }
+
+    #[inline]
+    fn next(&mut self) -> Option<Disposition<Self::Item>> {
+        let sas = &mut self.sas;
+        self.batch.next().map(|disp| {
+            disp.map(|esp| {
+                let result = match sas.get_mut(&esp.spi()) {
+                    Some(sa) => sa.decrypt(esp),
+                    None => Err(IpsecError::UnknownSpi(esp.spi()).into()),
+                };
+
+                match result {
+                    Ok(ethernet) => Disposition::Act(ethernet),
+                    Err(e) => Disposition::Abort(e),
+                }
+            })
+        })
+    }
+}
@@ -19,6 +19,7 @@
 //! Combinators that can be applied to batches of packets within a pipeline.
 
 mod emit;
+mod esp;
 mod filter;
 mod filter_map;
 mod for_each;
@@ -31,6 +32,7 @@ mod rxtx;
 mod send;
 
 pub use self::emit::*;
+pub use self::esp::*;
 pub use self::filter::*;
 pub use self::filter_map::*;
 pub use self::for_each::*;
@@ -42,7 +44,9 @@ pub use self::replace::*;
 pub use self::rxtx::*;
 pub use self::send::*;
 
-use crate::packets::Packet;
+use crate::packets::ip::ipsec::{Esp, SecurityAssociation};
+use crate::packets::ip::IpPacket;
+use crate::packets::{Ethernet, Packet};
 use crate::Mbuf;
 use failure::{Error, Fallible};
 use std::collections::HashMap;
@@ -169,6 +173,56 @@ pub trait Batch {
         Emit::new(self, tx)
     }
 
+    /// Creates a batch that encrypts the IP packets with ESP using the
+    /// security association.
+    ///
+    /// Packets that can't be encrypted are aborted. See
+    /// [`SecurityAssociation::encrypt`] for how the packets are encrypted
+    /// in transport and tunnel modes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let sa = SecurityAssociation::new(spi, EspMode::Transport, &key)?;
+    /// let mut batch = batch
+    ///     .map(|packet| packet.parse::<Ethernet>())
+    ///     .esp_encrypt::<Ipv4>(sa);
+    /// ```
+    ///
+    /// [`SecurityAssociation::encrypt`]: crate::packets::ip::ipsec::SecurityAssociation::encrypt
+    #[inline]
+    fn esp_encrypt<E>(self, sa: SecurityAssociation) -> EspEncrypt<Self, E>
+    where
+        E: IpPacket + Packet<Envelope = Ethernet>,
+        Self: Batch<Item = Ethernet> + Sized,
+    {
+        EspEncrypt::new(self, sa)
+    }
+
+    /// Creates a batch that decrypts the ESP packets with the security
+    /// association matching each packet's SPI.
+    ///
+    /// Packets that are not bound to any of the security associations,
+    /// are replayed or fail the integrity check are aborted. Each security
+    /// association keeps its own anti-replay window.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut batch = batch
+    ///     .map(|packet| packet.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Esp<Ipv4>>())
+    ///     .esp_decrypt(vec![sa1, sa2]);
+    /// ```
+    #[inline]
+    fn esp_decrypt<E, I>(self, sas: I) -> EspDecrypt<Self, E>
+    where
+        E: IpPacket + Packet<Envelope = Ethernet>,
+        I: IntoIterator<Item = SecurityAssociation>,
+        Self: Batch<Item = Esp<E>> + Sized,
+    {
+        EspDecrypt::new(self, sas)
+    }
+
     /// Creates a batch that uses a predicate to determine if a packet
     /// should be processed or dropped. If the predicate evaluates to `false`,
     /// the packet is marked as dropped.
@@ -441,10 +495,12 @@ pub fn splice<Rx: PacketRx + Unpin, Tx: PacketTx + Unpin>(rx: Rx, tx: Tx) -> imp
 mod tests {
     use super::*;
     use crate::compose;
+    use crate::packets::ip::ipsec::EspMode;
     use crate::packets::ip::v4::Ipv4;
     use crate::packets::ip::ProtocolNumbers;
-    use crate::packets::Ethernet;
-    use crate::testils::byte_arrays::{ICMPV4_PACKET, IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+    use crate::testils::byte_arrays::{
+        ICMPV4_PACKET, IPV4_ESP_TRANSPORT_PACKET, IPV4_TCP_PACKET, IPV4_UDP_PACKET,
+    };
     use std::sync::mpsc::{self, TryRecvError};
 
     fn new_batch(data: &[&[u8]]) -> impl Batch<Item = Mbuf> {
@@ -475,6 +531,39 @@ mod tests {
         assert_eq!(1, rx.receive().len());
     }
 
+    #[capsule::test]
+    fn esp_batch() {
+        let key = [0x42; 20];
+        let sa = SecurityAssociation::new(0x1000, EspMode::Transport, &key).unwrap();
+        let mut batch = new_batch(&[&IPV4_UDP_PACKET, &IPV4_TCP_PACKET])
+            .map(|p| p.parse::<Ethernet>())
+            .esp_encrypt::<Ipv4>(sa);
+
+        let mut encrypted = vec![];
+        while let Some(Disposition::Act(esp)) = batch.next() {
+            encrypted.push(esp.reset());
+        }
+        assert_eq!(2, encrypted.len());
+
+        let (mut tx, rx) = mpsc::channel();
+        tx.transmit(encrypted);
+        let sa = SecurityAssociation::new(0x1000, EspMode::Transport, &key).unwrap();
+        let mut batch = Poll::new(rx)
+            .map(|p| p.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Esp<Ipv4>>())
+            .esp_decrypt(vec![sa]);
+        batch.replenish();
+
+        assert!(batch.next().unwrap().is_act());
+        assert!(batch.next().unwrap().is_act());
+        assert!(batch.next().is_none());
+
+        // not bound to any security association
+        let mut batch = new_batch(&[&IPV4_ESP_TRANSPORT_PACKET])
+            .map(|p| p.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Esp<Ipv4>>())
+            .esp_decrypt(vec![]);
+        assert!(batch.next().unwrap().is_abort());
+    }
+
     #[capsule::test]
     fn filter_batch() {
         let mut batch = new_batch(&[&IPV4_UDP_PACKET]).filter(|_| true);
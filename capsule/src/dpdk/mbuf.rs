@@ -74,6 +74,12 @@ impl SizeOf for [u8; 16] {
     }
 }
 
+impl SizeOf for ::std::net::Ipv4Addr {
+    fn size_of() -> usize {
+        std::mem::size_of::<std::net::Ipv4Addr>()
+    }
+}
+
 impl SizeOf for ::std::net::Ipv6Addr {
     fn size_of() -> usize {
         std::mem::size_of::<std::net::Ipv6Addr>()
@@ -0,0 +1,555 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::net::MacAddr;
+use crate::packets::types::u16be;
+use crate::packets::{Datalink, EtherType, EtherTypes, Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// Address Resolution Protocol packet based on [IETF RFC 826].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |         Hardware Type         |         Protocol Type         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Hardware Len | Protocol Len  |           Operation           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Sender Hardware Address                    |
+/// +                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               |  Sender Protocol Address      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+/// |                    Target Hardware Address                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Target Protocol Address                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Hardware Type*:             16-bit network link protocol type. The
+///                                only supported type is Ethernet.
+///
+/// - *Protocol Type*:             16-bit internetwork protocol for which the
+///                                ARP request is intended. Shares the same
+///                                number space as EtherType. The only
+///                                supported type is IPv4.
+///
+/// - *Hardware Length*:           8-bit length in octets of a hardware
+///                                address. Ethernet addresses length is 6.
+///
+/// - *Protocol Length*:           8-bit length in octets of internetwork
+///                                addresses. IPv4 address length is 4.
+///
+/// - *Operation*:                 16-bit operation that the sender is
+///                                performing, 1 for request and 2 for reply.
+///
+/// - *Sender Hardware Address*:   Media address of the sender. In a request,
+///                                it is the address of the host sending the
+///                                request. In a reply, it is the address of
+///                                the host that the request was looking for.
+///
+/// - *Sender Protocol Address*:   Internetwork address of the sender.
+///
+/// - *Target Hardware Address*:   Media address of the intended receiver. In
+///                                a request, this field is ignored. In a
+///                                reply, it is the address of the host that
+///                                originated the request.
+///
+/// - *Target Protocol Address*:   Internetwork address of the intended
+///                                receiver.
+///
+/// [IETF RFC 826]: https://tools.ietf.org/html/rfc826
+pub struct Arp<E: Datalink> {
+    envelope: E,
+    header: NonNull<ArpHeader>,
+    offset: usize,
+}
+
+impl<E: Datalink> Arp<E> {
+    #[inline]
+    fn header(&self) -> &ArpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut ArpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the hardware type.
+    #[inline]
+    pub fn hardware_type(&self) -> HardwareType {
+        HardwareType::new(self.header().hardware_type.into())
+    }
+
+    /// Returns the protocol type.
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    /// Returns the hardware address length.
+    #[inline]
+    pub fn hardware_addr_len(&self) -> u8 {
+        self.header().hardware_addr_len
+    }
+
+    /// Returns the protocol address length.
+    #[inline]
+    pub fn protocol_addr_len(&self) -> u8 {
+        self.header().protocol_addr_len
+    }
+
+    /// Returns the operation code.
+    #[inline]
+    pub fn operation(&self) -> OperationCode {
+        OperationCode::new(self.header().operation.into())
+    }
+
+    /// Sets the operation code.
+    #[inline]
+    pub fn set_operation(&mut self, operation: OperationCode) {
+        self.header_mut().operation = operation.0.into()
+    }
+
+    /// Returns the sender hardware address.
+    #[inline]
+    pub fn sender_hardware_addr(&self) -> MacAddr {
+        self.header().sender_hardware_addr
+    }
+
+    /// Sets the sender hardware address.
+    #[inline]
+    pub fn set_sender_hardware_addr(&mut self, addr: MacAddr) {
+        self.header_mut().sender_hardware_addr = addr
+    }
+
+    /// Returns the sender protocol address.
+    #[inline]
+    pub fn sender_protocol_addr(&self) -> Ipv4Addr {
+        self.header().sender_protocol_addr
+    }
+
+    /// Sets the sender protocol address.
+    #[inline]
+    pub fn set_sender_protocol_addr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().sender_protocol_addr = addr
+    }
+
+    /// Returns the target hardware address.
+    #[inline]
+    pub fn target_hardware_addr(&self) -> MacAddr {
+        self.header().target_hardware_addr
+    }
+
+    /// Sets the target hardware address.
+    #[inline]
+    pub fn set_target_hardware_addr(&mut self, addr: MacAddr) {
+        self.header_mut().target_hardware_addr = addr
+    }
+
+    /// Returns the target protocol address.
+    #[inline]
+    pub fn target_protocol_addr(&self) -> Ipv4Addr {
+        self.header().target_protocol_addr
+    }
+
+    /// Sets the target protocol address.
+    #[inline]
+    pub fn set_target_protocol_addr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().target_protocol_addr = addr
+    }
+
+    /// Turns an ARP request into a reply in place.
+    ///
+    /// `mac` is the hardware address answering for the requested target
+    /// protocol address. The sender of the request becomes the target of
+    /// the reply, and the envelope is addressed from `mac` back to the
+    /// requester. Returns an error if the packet is not a request.
+    #[inline]
+    pub fn reply(&mut self, mac: MacAddr) -> Fallible<()> {
+        let operation = self.operation();
+        ensure!(
+            operation == OperationCodes::Request,
+            NotArpRequestError(operation)
+        );
+
+        let requester_mac = self.sender_hardware_addr();
+        let requester_ip = self.sender_protocol_addr();
+        let requested_ip = self.target_protocol_addr();
+
+        self.set_operation(OperationCodes::Reply);
+        self.set_sender_hardware_addr(mac);
+        self.set_sender_protocol_addr(requested_ip);
+        self.set_target_hardware_addr(requester_mac);
+        self.set_target_protocol_addr(requester_ip);
+
+        self.envelope_mut().set_src(mac);
+        self.envelope_mut().set_dst(requester_mac);
+        Ok(())
+    }
+}
+
+impl<E: Datalink> fmt::Debug for Arp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("arp")
+            .field("hardware_type", &format!("{}", self.hardware_type()))
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("hardware_addr_len", &self.hardware_addr_len())
+            .field("protocol_addr_len", &self.protocol_addr_len())
+            .field("operation", &format!("{}", self.operation()))
+            .field(
+                "sender_hardware_addr",
+                &format!("{}", self.sender_hardware_addr()),
+            )
+            .field(
+                "sender_protocol_addr",
+                &format!("{}", self.sender_protocol_addr()),
+            )
+            .field(
+                "target_hardware_addr",
+                &format!("{}", self.target_hardware_addr()),
+            )
+            .field(
+                "target_protocol_addr",
+                &format!("{}", self.target_protocol_addr()),
+            )
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Datalink> Packet for Arp<E> {
+    /// The preceding type for an ARP packet can be any datalink layer
+    /// protocol, typically [Ethernet].
+    ///
+    /// [Ethernet]: crate::packets::Ethernet
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        ArpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Arp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an ARP packet.
+    ///
+    /// [`ether_type`] must be set to [`EtherTypes::Arp`]. Otherwise a
+    /// parsing error is returned. Only ARP for IPv4 over Ethernet is
+    /// supported, the hardware and protocol types and address lengths are
+    /// validated as well.
+    ///
+    /// [`ether_type`]: crate::packets::EtherTypeEnvelope::ether_type
+    /// [`EtherTypes::Arp`]: crate::packets::EtherTypes::Arp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Arp,
+            ParseError::new("not an ARP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Arp {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.hardware_type() == HardwareTypes::Ethernet
+                && packet.protocol_type() == EtherTypes::Ipv4
+                && packet.hardware_addr_len() == 6
+                && packet.protocol_addr_len() == 4,
+            ParseError::new("not an ARP packet for IPv4 over Ethernet.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends an ARP packet to the beginning of the envelope's payload.
+    ///
+    /// [`ether_type`] is set to [`EtherTypes::Arp`]. The packet is an ARP
+    /// request for IPv4 over Ethernet, with all the addresses unspecified.
+    ///
+    /// [`ether_type`]: crate::packets::EtherTypeEnvelope::ether_type
+    /// [`EtherTypes::Arp`]: crate::packets::EtherTypes::Arp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, ArpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &ArpHeader::default())?;
+
+        envelope.set_ether_type(EtherTypes::Arp);
+
+        Ok(Arp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// The network link protocol type of an ARP packet.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct HardwareType(pub u16);
+
+impl HardwareType {
+    /// Creates an ARP hardware type.
+    pub fn new(value: u16) -> Self {
+        HardwareType(value)
+    }
+}
+
+/// Supported ARP hardware types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod HardwareTypes {
+    use super::HardwareType;
+
+    /// Ethernet.
+    pub const Ethernet: HardwareType = HardwareType(0x0001);
+}
+
+impl fmt::Display for HardwareType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                HardwareTypes::Ethernet => "Ethernet".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("0x{:04x}", t)
+                }
+            }
+        )
+    }
+}
+
+/// The operation that the sender of an ARP packet is performing.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct OperationCode(pub u16);
+
+impl OperationCode {
+    /// Creates an ARP operation code.
+    pub fn new(value: u16) -> Self {
+        OperationCode(value)
+    }
+}
+
+/// Supported ARP operation codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod OperationCodes {
+    use super::OperationCode;
+
+    /// Request.
+    pub const Request: OperationCode = OperationCode(1);
+    /// Reply.
+    pub const Reply: OperationCode = OperationCode(2);
+}
+
+impl fmt::Display for OperationCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                OperationCodes::Request => "Request".to_string(),
+                OperationCodes::Reply => "Reply".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// Error when replying to an ARP packet that is not a request.
+#[derive(Debug, Fail)]
+#[fail(display = "Cannot reply to an ARP {}, not a request.", _0)]
+pub struct NotArpRequestError(OperationCode);
+
+/// ARP header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct ArpHeader {
+    hardware_type: u16be,
+    protocol_type: u16be,
+    hardware_addr_len: u8,
+    protocol_addr_len: u8,
+    operation: u16be,
+    sender_hardware_addr: MacAddr,
+    sender_protocol_addr: Ipv4Addr,
+    target_hardware_addr: MacAddr,
+    target_protocol_addr: Ipv4Addr,
+}
+
+impl Default for ArpHeader {
+    fn default() -> ArpHeader {
+        ArpHeader {
+            hardware_type: u16be::from(HardwareTypes::Ethernet.0),
+            protocol_type: u16be::from(EtherTypes::Ipv4.0),
+            hardware_addr_len: 6,
+            protocol_addr_len: 4,
+            operation: u16be::from(OperationCodes::Request.0),
+            sender_hardware_addr: MacAddr::UNSPECIFIED,
+            sender_protocol_addr: Ipv4Addr::UNSPECIFIED,
+            target_hardware_addr: MacAddr::UNSPECIFIED,
+            target_protocol_addr: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{ARP_REQUEST_PACKET, IPV4_UDP_PACKET, VLAN_DOT1Q_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_arp_header() {
+        assert_eq!(28, ArpHeader::size_of());
+    }
+
+    #[test]
+    fn operation_code_to_string() {
+        assert_eq!("Request", OperationCodes::Request.to_string());
+        assert_eq!("Reply", OperationCodes::Reply.to_string());
+        assert_eq!("3", OperationCode::new(3).to_string());
+    }
+
+    #[capsule::test]
+    fn parse_arp_packet() {
+        let packet = Mbuf::from_bytes(&ARP_REQUEST_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let arp = ethernet.parse::<Arp<Ethernet>>().unwrap();
+
+        assert_eq!(HardwareTypes::Ethernet, arp.hardware_type());
+        assert_eq!(EtherTypes::Ipv4, arp.protocol_type());
+        assert_eq!(6, arp.hardware_addr_len());
+        assert_eq!(4, arp.protocol_addr_len());
+        assert_eq!(OperationCodes::Request, arp.operation());
+        assert_eq!("00:00:00:00:00:02", arp.sender_hardware_addr().to_string());
+        assert_eq!("192.168.1.2", arp.sender_protocol_addr().to_string());
+        assert_eq!("00:00:00:00:00:00", arp.target_hardware_addr().to_string());
+        assert_eq!("192.168.1.1", arp.target_protocol_addr().to_string());
+    }
+
+    #[capsule::test]
+    fn parse_vlan_arp_packet() {
+        let packet = Mbuf::from_bytes(&VLAN_DOT1Q_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let arp = ethernet.parse::<Arp<Ethernet>>().unwrap();
+
+        assert_eq!(OperationCodes::Reply, arp.operation());
+        assert_eq!("00:19:06:ea:b8:c1", arp.sender_hardware_addr().to_string());
+        assert_eq!("192.168.123.1", arp.sender_protocol_addr().to_string());
+    }
+
+    #[capsule::test]
+    fn parse_non_arp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Arp<Ethernet>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_arp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let arp = ethernet.push::<Arp<Ethernet>>().unwrap();
+
+        assert_eq!(ArpHeader::size_of(), arp.len());
+        assert_eq!(HardwareTypes::Ethernet, arp.hardware_type());
+        assert_eq!(EtherTypes::Ipv4, arp.protocol_type());
+        assert_eq!(6, arp.hardware_addr_len());
+        assert_eq!(4, arp.protocol_addr_len());
+        assert_eq!(OperationCodes::Request, arp.operation());
+
+        // make sure the ether type is fixed
+        assert_eq!(EtherTypes::Arp, arp.envelope().ether_type());
+    }
+
+    #[capsule::test]
+    fn reply_to_arp_request() {
+        let packet = Mbuf::from_bytes(&ARP_REQUEST_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut arp = ethernet.parse::<Arp<Ethernet>>().unwrap();
+
+        let mac = MacAddr::new(0, 0, 0, 0, 0, 1);
+        assert!(arp.reply(mac).is_ok());
+
+        assert_eq!(OperationCodes::Reply, arp.operation());
+        assert_eq!(mac, arp.sender_hardware_addr());
+        assert_eq!("192.168.1.1", arp.sender_protocol_addr().to_string());
+        assert_eq!("00:00:00:00:00:02", arp.target_hardware_addr().to_string());
+        assert_eq!("192.168.1.2", arp.target_protocol_addr().to_string());
+        assert_eq!(mac, arp.envelope().src());
+        assert_eq!("00:00:00:00:00:02", arp.envelope().dst().to_string());
+
+        // can't reply to a reply
+        assert!(arp.reply(mac).is_err());
+    }
+}
@@ -21,6 +21,7 @@
 
 use crate::packets::ip::{IpPacketError, ProtocolNumber};
 use failure::Fallible;
+use once_cell::sync::Lazy;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::slice;
 
@@ -215,6 +216,49 @@ pub fn compute_with_ipaddr(
     }
 }
 
+/// The reversed Castagnoli polynomial.
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+/// Lookup table for the byte-wise CRC32c computation.
+static CRC32C_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 > 0 {
+                (crc >> 1) ^ CRC32C_POLY
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+/// Computes the CRC32c checksum as defined in [IETF RFC 3309].
+///
+/// CRC32c uses the Castagnoli polynomial, and is the checksum used by
+/// SCTP in place of the Internet checksum.
+///
+/// [IETF RFC 3309]: https://tools.ietf.org/html/rfc3309
+#[inline]
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32c_update(0, data)
+}
+
+/// Continues a CRC32c checksum computed by [`crc32c`] over more data.
+///
+/// This is useful when the checksummed data is not contiguous.
+///
+/// [`crc32c`]: crc32c
+pub fn crc32c_update(crc: u32, data: &[u8]) -> u32 {
+    let table = &*CRC32C_TABLE;
+    !data.iter().fold(!crc, |crc, &byte| {
+        (crc >> 8) ^ table[((crc ^ u32::from(byte)) & 0xFF) as usize]
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -223,4 +267,17 @@ mod tests {
     fn compute_checksum_incrementally() {
         assert_eq!(0x0000, compute_inc(0xdd2f, &[0x5555], &[0x3285]));
     }
+
+    #[test]
+    fn compute_crc32c() {
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+
+        // test vectors from IETF RFC 3720 appendix B.4
+        assert_eq!(0x8a91_36aa, crc32c(&[0; 32]));
+        assert_eq!(0x62a8_ab43, crc32c(&[0xff; 32]));
+        let data = (0..32).collect::<Vec<u8>>();
+        assert_eq!(0x46dd_794e, crc32c(&data));
+
+        assert_eq!(0xe306_9283, crc32c_update(crc32c(b"1234"), b"56789"));
+    }
 }
@@ -0,0 +1,844 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Dynamic Host Configuration Protocol for IPv4 and options.
+
+mod options;
+
+pub use self::options::*;
+
+use crate::net::MacAddr;
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, ParseError, Udp};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port for the DHCP server.
+pub const DHCPV4_SERVER_PORT: u16 = 67;
+
+/// The IANA assigned UDP port for the DHCP client.
+pub const DHCPV4_CLIENT_PORT: u16 = 68;
+
+/// The magic cookie that marks the beginning of the options.
+const DHCPV4_MAGIC_COOKIE: u32 = 0x6382_5363;
+
+/// The offset of the magic cookie from the start of the message.
+const DHCPV4_MAGIC_COOKIE_OFFSET: usize = 236;
+
+/// The offset of the options from the start of the message.
+const DHCPV4_OPTIONS_OFFSET: usize = 240;
+
+/// The minimum length of a message. Some BOOTP relay agents and clients
+/// drop shorter messages, so messages are padded to this length.
+const DHCPV4_MIN_LEN: usize = 300;
+
+// The broadcast bit of the flags field.
+const DHCPV4_FLAG_BROADCAST: u16 = 0x8000;
+
+/// The hardware type for Ethernet.
+const HTYPE_ETHERNET: u8 = 1;
+
+/// DHCP opcode.
+///
+/// A list of supported opcodes is under [`Dhcpv4Ops`].
+///
+/// [`Dhcpv4Ops`]: Dhcpv4Ops
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Dhcpv4Op(pub u8);
+
+/// Supported DHCP opcodes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Dhcpv4Ops {
+    use super::Dhcpv4Op;
+
+    /// Message sent from a client to a server.
+    pub const BootRequest: Dhcpv4Op = Dhcpv4Op(1);
+
+    /// Message sent from a server to a client.
+    pub const BootReply: Dhcpv4Op = Dhcpv4Op(2);
+}
+
+impl fmt::Display for Dhcpv4Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Dhcpv4Ops::BootRequest => "BOOTREQUEST".to_string(),
+                Dhcpv4Ops::BootReply => "BOOTREPLY".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Dynamic Host Configuration Protocol message based on [IETF RFC 2131].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +---------------+---------------+---------------+---------------+
+/// |     op (1)    |   htype (1)   |   hlen (1)    |   hops (1)    |
+/// +---------------+---------------+---------------+---------------+
+/// |                            xid (4)                            |
+/// +-------------------------------+-------------------------------+
+/// |           secs (2)            |           flags (2)           |
+/// +-------------------------------+-------------------------------+
+/// |                          ciaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          yiaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          siaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          giaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                                                               |
+/// |                          chaddr  (16)                         |
+/// |                                                               |
+/// |                                                               |
+/// +---------------------------------------------------------------+
+/// |                                                               |
+/// |                          sname   (64)                         |
+/// +---------------------------------------------------------------+
+/// |                                                               |
+/// |                          file    (128)                        |
+/// +---------------------------------------------------------------+
+/// |                                                               |
+/// |                          options (variable)                   |
+/// +---------------------------------------------------------------+
+/// ```
+///
+/// - *op*:         Message opcode, either `BOOTREQUEST` or `BOOTREPLY`.
+///
+/// - *htype*:      Hardware address type.
+///
+/// - *hlen*:       Hardware address length.
+///
+/// - *hops*:       Client sets to zero, optionally used by relay agents
+///                 when booting via a relay agent.
+///
+/// - *xid*:        Transaction ID, a random number chosen by the client,
+///                 used to associate messages and responses.
+///
+/// - *secs*:       Seconds elapsed since client began address acquisition
+///                 or renewal process.
+///
+/// - *flags*:      The leftmost bit is the broadcast flag.
+///
+/// - *ciaddr*:     Client IP address; only filled in if client is in
+///                 BOUND, RENEW or REBINDING state.
+///
+/// - *yiaddr*:     'your' (client) IP address.
+///
+/// - *siaddr*:     IP address of next server to use in bootstrap.
+///
+/// - *giaddr*:     Relay agent IP address.
+///
+/// - *chaddr*:     Client hardware address.
+///
+/// - *sname*:      Optional server host name.
+///
+/// - *file*:       Boot file name.
+///
+/// - *options*:    Optional parameters field, starting with the magic
+///                 cookie `99.130.83.99`. Use [`options_iter`] to read the
+///                 options, and [`set_options`] or [`add_option`] to modify
+///                 them.
+///
+/// The options are considered part of the header, so the message has no
+/// payload. Option overloading of the `sname` and `file` fields is not
+/// supported.
+///
+/// [IETF RFC 2131]: https://tools.ietf.org/html/rfc2131#section-2
+/// [`options_iter`]: Dhcpv4::options_iter
+/// [`set_options`]: Dhcpv4::set_options
+/// [`add_option`]: Dhcpv4::add_option
+pub struct Dhcpv4<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<Dhcpv4Header>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Dhcpv4<E> {
+    #[inline]
+    fn header(&self) -> &Dhcpv4Header {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Dhcpv4Header {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message opcode.
+    #[inline]
+    pub fn op(&self) -> Dhcpv4Op {
+        Dhcpv4Op(self.header().op)
+    }
+
+    /// Sets the message opcode.
+    #[inline]
+    pub fn set_op(&mut self, op: Dhcpv4Op) {
+        self.header_mut().op = op.0;
+    }
+
+    /// Returns the hardware address type.
+    #[inline]
+    pub fn htype(&self) -> u8 {
+        self.header().htype
+    }
+
+    /// Returns the hardware address length.
+    #[inline]
+    pub fn hlen(&self) -> u8 {
+        self.header().hlen
+    }
+
+    /// Returns the number of relay agent hops.
+    #[inline]
+    pub fn hops(&self) -> u8 {
+        self.header().hops
+    }
+
+    /// Sets the number of relay agent hops.
+    #[inline]
+    pub fn set_hops(&mut self, hops: u8) {
+        self.header_mut().hops = hops;
+    }
+
+    /// Returns the transaction ID.
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.header().xid.into()
+    }
+
+    /// Sets the transaction ID.
+    #[inline]
+    pub fn set_xid(&mut self, xid: u32) {
+        self.header_mut().xid = xid.into();
+    }
+
+    /// Returns the seconds elapsed since the client began the address
+    /// acquisition or renewal process.
+    #[inline]
+    pub fn secs(&self) -> u16 {
+        self.header().secs.into()
+    }
+
+    /// Sets the seconds elapsed.
+    #[inline]
+    pub fn set_secs(&mut self, secs: u16) {
+        self.header_mut().secs = secs.into();
+    }
+
+    /// Returns whether the broadcast flag is set.
+    #[inline]
+    pub fn broadcast(&self) -> bool {
+        u16::from(self.header().flags) & DHCPV4_FLAG_BROADCAST != 0
+    }
+
+    /// Sets the broadcast flag.
+    #[inline]
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        let flags = u16::from(self.header().flags);
+        let flags = if broadcast {
+            flags | DHCPV4_FLAG_BROADCAST
+        } else {
+            flags & !DHCPV4_FLAG_BROADCAST
+        };
+        self.header_mut().flags = flags.into();
+    }
+
+    /// Returns the client IP address.
+    #[inline]
+    pub fn ciaddr(&self) -> Ipv4Addr {
+        self.header().ciaddr
+    }
+
+    /// Sets the client IP address.
+    #[inline]
+    pub fn set_ciaddr(&mut self, ciaddr: Ipv4Addr) {
+        self.header_mut().ciaddr = ciaddr;
+    }
+
+    /// Returns the IP address assigned to the client.
+    #[inline]
+    pub fn yiaddr(&self) -> Ipv4Addr {
+        self.header().yiaddr
+    }
+
+    /// Sets the IP address assigned to the client.
+    #[inline]
+    pub fn set_yiaddr(&mut self, yiaddr: Ipv4Addr) {
+        self.header_mut().yiaddr = yiaddr;
+    }
+
+    /// Returns the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn siaddr(&self) -> Ipv4Addr {
+        self.header().siaddr
+    }
+
+    /// Sets the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn set_siaddr(&mut self, siaddr: Ipv4Addr) {
+        self.header_mut().siaddr = siaddr;
+    }
+
+    /// Returns the relay agent IP address.
+    #[inline]
+    pub fn giaddr(&self) -> Ipv4Addr {
+        self.header().giaddr
+    }
+
+    /// Sets the relay agent IP address.
+    #[inline]
+    pub fn set_giaddr(&mut self, giaddr: Ipv4Addr) {
+        self.header_mut().giaddr = giaddr;
+    }
+
+    /// Returns the client hardware address.
+    ///
+    /// Only the first 6 octets are read. The address is meaningful only if
+    /// the hardware address type is Ethernet.
+    #[inline]
+    pub fn chaddr(&self) -> MacAddr {
+        let chaddr = &self.header().chaddr;
+        MacAddr::new(
+            chaddr[0], chaddr[1], chaddr[2], chaddr[3], chaddr[4], chaddr[5],
+        )
+    }
+
+    /// Sets the client hardware address.
+    ///
+    /// The hardware address type is set to Ethernet and the length to 6.
+    #[inline]
+    pub fn set_chaddr(&mut self, chaddr: MacAddr) {
+        let header = self.header_mut();
+        header.htype = HTYPE_ETHERNET;
+        header.hlen = 6;
+        header.chaddr = [0; 16];
+        header.chaddr[..6].copy_from_slice(&chaddr.octets());
+    }
+
+    /// Returns the options, excluding the magic cookie.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let offset = self.offset + DHCPV4_OPTIONS_OFFSET;
+        let len = self.len() - DHCPV4_OPTIONS_OFFSET;
+        if len == 0 {
+            return &[];
+        }
+
+        match self.mbuf().read_data_slice::<u8>(offset, len) {
+            Ok(options) => unsafe { options.as_ref() },
+            // the length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let dhcp = udp.parse::<Dhcpv4<Ipv4>>()?;
+    /// let mut iter = dhcp.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> Dhcpv4OptionsIterator<'_> {
+        Dhcpv4OptionsIterator::new(self.options())
+    }
+
+    /// Returns the DHCP message type if the option is present.
+    #[inline]
+    pub fn message_type(&self) -> Option<Dhcpv4MessageType> {
+        let mut iter = self.options_iter();
+        while let Ok(Some(option)) = iter.next() {
+            if let Dhcpv4Option::MessageType(message_type) = option {
+                return Some(message_type);
+            }
+        }
+
+        None
+    }
+
+    /// Replaces the encoded options and pads the message to the minimum
+    /// length.
+    fn set_options_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let new_len = bytes.len().max(DHCPV4_MIN_LEN - DHCPV4_OPTIONS_OFFSET);
+        let old_len = self.len() - DHCPV4_OPTIONS_OFFSET;
+        let offset = self.offset + DHCPV4_OPTIONS_OFFSET;
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        mbuf.write_data_slice(offset, bytes)?;
+        // pads with the pad option.
+        let padding = new_len - bytes.len();
+        if padding > 0 {
+            mbuf.write_data_slice(offset + bytes.len(), &vec![0u8; padding])?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the options.
+    ///
+    /// The end option is appended and the message is padded to the minimum
+    /// length of 300 octets. Call [`reconcile_all`] afterwards to fix the
+    /// lengths and checksums of the envelopes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut dhcp = udp.push::<Dhcpv4<Ipv4>>()?;
+    /// dhcp.set_options(&[
+    ///     Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Offer),
+    ///     Dhcpv4Option::LeaseTime(3600),
+    /// ])?;
+    /// dhcp.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn set_options(&mut self, options: &[Dhcpv4Option<'_>]) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(DHCPV4_MIN_LEN - DHCPV4_OPTIONS_OFFSET);
+        for option in options {
+            if *option != Dhcpv4Option::End {
+                option.encode(&mut bytes)?;
+            }
+        }
+        Dhcpv4Option::End.encode(&mut bytes)?;
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Adds an option after the existing options.
+    ///
+    /// The pad options are dropped, and the end option is moved after the
+    /// new option. Call [`reconcile_all`] afterwards to fix the lengths and
+    /// checksums of the envelopes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut dhcp = udp.parse::<Dhcpv4<Ipv4>>()?;
+    /// dhcp.set_giaddr(Ipv4Addr::new(10, 0, 0, 1));
+    /// dhcp.add_option(Dhcpv4Option::RelayAgentInformation(
+    ///     RelayAgentInformationOption::new(&[0x01, 0x04, 0x65, 0x74, 0x68, 0x30]),
+    /// ))?;
+    /// dhcp.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_option(&mut self, option: Dhcpv4Option<'_>) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(DHCPV4_MIN_LEN - DHCPV4_OPTIONS_OFFSET);
+        let mut iter = self.options_iter();
+        while let Some(existing) = iter.next()? {
+            if existing != Dhcpv4Option::Pad && existing != Dhcpv4Option::End {
+                existing.encode(&mut bytes)?;
+            }
+        }
+        option.encode(&mut bytes)?;
+        Dhcpv4Option::End.encode(&mut bytes)?;
+        self.set_options_bytes(&bytes)
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Dhcpv4<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dhcpv4")
+            .field("op", &format!("{}", self.op()))
+            .field("htype", &self.htype())
+            .field("hlen", &self.hlen())
+            .field("hops", &self.hops())
+            .field("xid", &format!("0x{:08x}", self.xid()))
+            .field("secs", &self.secs())
+            .field("broadcast", &self.broadcast())
+            .field("ciaddr", &self.ciaddr())
+            .field("yiaddr", &self.yiaddr())
+            .field("siaddr", &self.siaddr())
+            .field("giaddr", &self.giaddr())
+            .field("chaddr", &format!("{}", self.chaddr()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Dhcpv4<E> {
+    /// The preceding type for a DHCP packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the DHCP header includes the options and the padding
+    /// that follows.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dhcpv4::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a DHCP packet.
+    ///
+    /// Either [`Udp::src_port`] or [`Udp::dst_port`] must be set to
+    /// [`DHCPV4_SERVER_PORT`] or [`DHCPV4_CLIENT_PORT`], and the message
+    /// must start the options with the magic cookie. Otherwise a parsing
+    /// error is returned.
+    ///
+    /// [`Udp::src_port`]: crate::packets::Udp::src_port
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`DHCPV4_SERVER_PORT`]: DHCPV4_SERVER_PORT
+    /// [`DHCPV4_CLIENT_PORT`]: DHCPV4_CLIENT_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let is_dhcp_port = |port| port == DHCPV4_SERVER_PORT || port == DHCPV4_CLIENT_PORT;
+        ensure!(
+            is_dhcp_port(envelope.src_port()) || is_dhcp_port(envelope.dst_port()),
+            ParseError::new("not a DHCP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let _ = mbuf.read_data_slice::<u8>(offset, DHCPV4_OPTIONS_OFFSET)?;
+        let header = mbuf.read_data(offset)?;
+        let cookie = mbuf.read_data::<u32be>(offset + DHCPV4_MAGIC_COOKIE_OFFSET)?;
+
+        ensure!(
+            u32::from(unsafe { *cookie.as_ref() }) == DHCPV4_MAGIC_COOKIE,
+            ParseError::new("not a DHCP packet, missing the magic cookie.")
+        );
+
+        Ok(Dhcpv4 {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a DHCP packet to the beginning of the UDP packet's payload.
+    ///
+    /// The message is a `BOOTREQUEST` from an Ethernet client, with only
+    /// the end option and padded to the minimum length of 300 octets.
+    /// [`Udp::src_port`] is set to [`DHCPV4_CLIENT_PORT`] and
+    /// [`Udp::dst_port`] is set to [`DHCPV4_SERVER_PORT`]. Swap the ports
+    /// when building a reply.
+    ///
+    /// [`Udp::src_port`]: crate::packets::Udp::src_port
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`DHCPV4_SERVER_PORT`]: DHCPV4_SERVER_PORT
+    /// [`DHCPV4_CLIENT_PORT`]: DHCPV4_CLIENT_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        let mut message = vec![0u8; DHCPV4_MIN_LEN];
+        message[DHCPV4_MAGIC_COOKIE_OFFSET..DHCPV4_OPTIONS_OFFSET]
+            .copy_from_slice(&DHCPV4_MAGIC_COOKIE.to_be_bytes());
+        message[DHCPV4_OPTIONS_OFFSET] = Dhcpv4OptionCodes::End.0;
+
+        mbuf.extend(offset, DHCPV4_MIN_LEN)?;
+        mbuf.write_data_slice(offset, &message)?;
+        let header = mbuf.write_data(offset, &Dhcpv4Header::default())?;
+
+        envelope.set_src_port(DHCPV4_CLIENT_PORT);
+        envelope.set_dst_port(DHCPV4_SERVER_PORT);
+
+        Ok(Dhcpv4 {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// DHCP fixed-format fields, up to the client hardware address.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Dhcpv4Header {
+    op: u8,
+    htype: u8,
+    hlen: u8,
+    hops: u8,
+    xid: u32be,
+    secs: u16be,
+    flags: u16be,
+    ciaddr: Ipv4Addr,
+    yiaddr: Ipv4Addr,
+    siaddr: Ipv4Addr,
+    giaddr: Ipv4Addr,
+    chaddr: [u8; 16],
+}
+
+impl Default for Dhcpv4Header {
+    fn default() -> Dhcpv4Header {
+        Dhcpv4Header {
+            op: Dhcpv4Ops::BootRequest.0,
+            htype: HTYPE_ETHERNET,
+            hlen: 6,
+            hops: 0,
+            xid: u32be::default(),
+            secs: u16be::default(),
+            flags: u16be::default(),
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0; 16],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{
+        DHCPV4_DISCOVER_PACKET, DHCPV4_OFFER_PACKET, IPV4_UDP_PACKET,
+    };
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_dhcpv4_header() {
+        assert_eq!(44, Dhcpv4Header::size_of());
+    }
+
+    #[test]
+    fn op_to_string() {
+        assert_eq!("BOOTREPLY", Dhcpv4Ops::BootReply.to_string());
+        assert_eq!("3", Dhcpv4Op(3).to_string());
+    }
+
+    #[capsule::test]
+    fn parse_dhcpv4_discover() {
+        let packet = Mbuf::from_bytes(&DHCPV4_DISCOVER_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcpv4<Ipv4>>().unwrap();
+
+        assert_eq!(Dhcpv4Ops::BootRequest, dhcp.op());
+        assert_eq!(1, dhcp.htype());
+        assert_eq!(6, dhcp.hlen());
+        assert_eq!(0, dhcp.hops());
+        assert_eq!(0x3d1d, dhcp.xid());
+        assert_eq!(0, dhcp.secs());
+        assert!(!dhcp.broadcast());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.ciaddr());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.yiaddr());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.siaddr());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.giaddr());
+        assert_eq!("00:0b:82:01:fc:42", dhcp.chaddr().to_string());
+        assert_eq!(Some(Dhcpv4MessageTypes::Discover), dhcp.message_type());
+        assert_eq!(0, dhcp.payload_len());
+
+        let mut iter = dhcp.options_iter();
+        assert_eq!(
+            Some(Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Discover)),
+            iter.next().unwrap()
+        );
+        assert_eq!(
+            Some(Dhcpv4Option::Unknown(
+                Dhcpv4OptionCode(61),
+                &[0x01, 0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42]
+            )),
+            iter.next().unwrap()
+        );
+        assert_eq!(
+            Some(Dhcpv4Option::RequestedIpAddress(Ipv4Addr::UNSPECIFIED)),
+            iter.next().unwrap()
+        );
+        assert_eq!(
+            Some(Dhcpv4Option::ParameterRequestList(&[1, 3, 6, 42])),
+            iter.next().unwrap()
+        );
+        assert_eq!(Some(Dhcpv4Option::End), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn parse_dhcpv4_offer() {
+        let packet = Mbuf::from_bytes(&DHCPV4_OFFER_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcpv4<Ipv4>>().unwrap();
+
+        assert_eq!(Dhcpv4Ops::BootReply, dhcp.op());
+        assert_eq!(0x3d1d, dhcp.xid());
+        assert_eq!(Ipv4Addr::new(192, 168, 0, 10), dhcp.yiaddr());
+        assert_eq!(Ipv4Addr::new(192, 168, 0, 1), dhcp.siaddr());
+        assert_eq!(Some(Dhcpv4MessageTypes::Offer), dhcp.message_type());
+
+        let mut lease_time = None;
+        let mut server_id = None;
+        let mut iter = dhcp.options_iter();
+        while let Some(option) = iter.next().unwrap() {
+            match option {
+                Dhcpv4Option::SubnetMask(mask) => {
+                    assert_eq!(Ipv4Addr::new(255, 255, 255, 0), mask)
+                }
+                Dhcpv4Option::LeaseTime(secs) => lease_time = Some(secs),
+                Dhcpv4Option::ServerIdentifier(addr) => server_id = Some(addr),
+                _ => (),
+            }
+        }
+
+        assert_eq!(Some(3600), lease_time);
+        assert_eq!(Some(Ipv4Addr::new(192, 168, 0, 1)), server_id);
+    }
+
+    #[capsule::test]
+    fn parse_non_dhcpv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dhcpv4<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn relay_dhcpv4_discover() {
+        let packet = Mbuf::from_bytes(&DHCPV4_DISCOVER_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut dhcp = udp.parse::<Dhcpv4<Ipv4>>().unwrap();
+
+        let circuit_id = [0x01, 0x04, 0x65, 0x74, 0x68, 0x30];
+        dhcp.set_hops(1);
+        dhcp.set_giaddr(Ipv4Addr::new(10, 0, 0, 1));
+        dhcp.add_option(Dhcpv4Option::RelayAgentInformation(
+            RelayAgentInformationOption::new(&circuit_id),
+        ))
+        .unwrap();
+        dhcp.reconcile_all();
+
+        assert_eq!(1, dhcp.hops());
+        assert_eq!(Ipv4Addr::new(10, 0, 0, 1), dhcp.giaddr());
+        // the options still fit in the minimum length
+        assert_eq!(DHCPV4_MIN_LEN, dhcp.len());
+        assert_eq!(dhcp.len() + 8, dhcp.envelope().length() as usize);
+
+        let mut iter = dhcp.options_iter();
+        let mut info = None;
+        while let Some(option) = iter.next().unwrap() {
+            if let Dhcpv4Option::RelayAgentInformation(option) = option {
+                info = Some(option);
+            }
+        }
+        assert_eq!(Some(&b"eth0"[..]), info.unwrap().circuit_id());
+        assert_eq!(None, info.unwrap().remote_id());
+    }
+
+    #[capsule::test]
+    fn push_dhcpv4_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut dhcp = udp.push::<Dhcpv4<Ipv4>>().unwrap();
+
+        assert_eq!(DHCPV4_MIN_LEN, dhcp.len());
+        assert_eq!(Dhcpv4Ops::BootRequest, dhcp.op());
+        assert_eq!(DHCPV4_CLIENT_PORT, dhcp.envelope().src_port());
+        assert_eq!(DHCPV4_SERVER_PORT, dhcp.envelope().dst_port());
+        assert_eq!(None, dhcp.message_type());
+
+        dhcp.set_op(Dhcpv4Ops::BootReply);
+        dhcp.set_xid(0x3d1d);
+        dhcp.set_broadcast(true);
+        dhcp.set_yiaddr(Ipv4Addr::new(192, 168, 0, 10));
+        dhcp.set_chaddr(MacAddr::new(0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42));
+
+        let dns = [8, 8, 8, 8, 8, 8, 4, 4];
+        dhcp.set_options(&[
+            Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Ack),
+            Dhcpv4Option::Router(AddressListOption::new(&[192, 168, 0, 1])),
+            Dhcpv4Option::DomainNameServer(AddressListOption::new(&dns)),
+            Dhcpv4Option::LeaseTime(86400),
+        ])
+        .unwrap();
+        dhcp.reconcile_all();
+
+        assert!(dhcp.broadcast());
+        assert_eq!("00:0b:82:01:fc:42", dhcp.chaddr().to_string());
+        assert_eq!(Some(Dhcpv4MessageTypes::Ack), dhcp.message_type());
+        assert_eq!(DHCPV4_MIN_LEN, dhcp.len());
+
+        // a long option list grows the message past the minimum length
+        let data = [0xaa; 100];
+        dhcp.add_option(Dhcpv4Option::Unknown(Dhcpv4OptionCode(43), &data))
+            .unwrap();
+        dhcp.reconcile_all();
+        assert_eq!(DHCPV4_OPTIONS_OFFSET + 3 + 6 + 10 + 6 + 102 + 1, dhcp.len());
+
+        // reparse from the bytes to check the magic cookie and lengths
+        let ipv4 = dhcp.deparse().deparse();
+        assert_eq!(ipv4.len(), ipv4.total_length() as usize);
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcpv4<Ipv4>>().unwrap();
+        assert_eq!(0x3d1d, dhcp.xid());
+    }
+}
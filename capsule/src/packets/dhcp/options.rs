@@ -0,0 +1,616 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The maximum length of the option data in octets.
+const DHCPV4_MAX_OPTION_LEN: usize = 255;
+
+/// [IANA] assigned DHCP option code.
+///
+/// A list of supported codes is under [`Dhcpv4OptionCodes`].
+///
+/// [IANA]: https://www.iana.org/assignments/bootp-dhcp-parameters/bootp-dhcp-parameters.xhtml#options
+/// [`Dhcpv4OptionCodes`]: Dhcpv4OptionCodes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Dhcpv4OptionCode(pub u8);
+
+/// Supported DHCP option codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Dhcpv4OptionCodes {
+    use super::Dhcpv4OptionCode;
+
+    /// Pad.
+    pub const Pad: Dhcpv4OptionCode = Dhcpv4OptionCode(0);
+
+    /// Subnet Mask.
+    pub const SubnetMask: Dhcpv4OptionCode = Dhcpv4OptionCode(1);
+
+    /// Router.
+    pub const Router: Dhcpv4OptionCode = Dhcpv4OptionCode(3);
+
+    /// Domain Name Server.
+    pub const DomainNameServer: Dhcpv4OptionCode = Dhcpv4OptionCode(6);
+
+    /// Requested IP Address.
+    pub const RequestedIpAddress: Dhcpv4OptionCode = Dhcpv4OptionCode(50);
+
+    /// IP Address Lease Time.
+    pub const LeaseTime: Dhcpv4OptionCode = Dhcpv4OptionCode(51);
+
+    /// DHCP Message Type.
+    pub const MessageType: Dhcpv4OptionCode = Dhcpv4OptionCode(53);
+
+    /// Server Identifier.
+    pub const ServerIdentifier: Dhcpv4OptionCode = Dhcpv4OptionCode(54);
+
+    /// Parameter Request List.
+    pub const ParameterRequestList: Dhcpv4OptionCode = Dhcpv4OptionCode(55);
+
+    /// Relay Agent Information.
+    pub const RelayAgentInformation: Dhcpv4OptionCode = Dhcpv4OptionCode(82);
+
+    /// End.
+    pub const End: Dhcpv4OptionCode = Dhcpv4OptionCode(255);
+}
+
+impl fmt::Display for Dhcpv4OptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Dhcpv4OptionCodes::Pad => "Pad".to_string(),
+                Dhcpv4OptionCodes::SubnetMask => "Subnet Mask".to_string(),
+                Dhcpv4OptionCodes::Router => "Router".to_string(),
+                Dhcpv4OptionCodes::DomainNameServer => "Domain Name Server".to_string(),
+                Dhcpv4OptionCodes::RequestedIpAddress => "Requested IP Address".to_string(),
+                Dhcpv4OptionCodes::LeaseTime => "IP Address Lease Time".to_string(),
+                Dhcpv4OptionCodes::MessageType => "DHCP Message Type".to_string(),
+                Dhcpv4OptionCodes::ServerIdentifier => "Server Identifier".to_string(),
+                Dhcpv4OptionCodes::ParameterRequestList => "Parameter Request List".to_string(),
+                Dhcpv4OptionCodes::RelayAgentInformation => "Relay Agent Information".to_string(),
+                Dhcpv4OptionCodes::End => "End".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// DHCP message type carried in the [`DHCP Message Type`] option.
+///
+/// A list of supported types is under [`Dhcpv4MessageTypes`].
+///
+/// [`DHCP Message Type`]: Dhcpv4Option::MessageType
+/// [`Dhcpv4MessageTypes`]: Dhcpv4MessageTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Dhcpv4MessageType(pub u8);
+
+/// Supported DHCP message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Dhcpv4MessageTypes {
+    use super::Dhcpv4MessageType;
+
+    /// DHCPDISCOVER.
+    pub const Discover: Dhcpv4MessageType = Dhcpv4MessageType(1);
+
+    /// DHCPOFFER.
+    pub const Offer: Dhcpv4MessageType = Dhcpv4MessageType(2);
+
+    /// DHCPREQUEST.
+    pub const Request: Dhcpv4MessageType = Dhcpv4MessageType(3);
+
+    /// DHCPDECLINE.
+    pub const Decline: Dhcpv4MessageType = Dhcpv4MessageType(4);
+
+    /// DHCPACK.
+    pub const Ack: Dhcpv4MessageType = Dhcpv4MessageType(5);
+
+    /// DHCPNAK.
+    pub const Nak: Dhcpv4MessageType = Dhcpv4MessageType(6);
+
+    /// DHCPRELEASE.
+    pub const Release: Dhcpv4MessageType = Dhcpv4MessageType(7);
+
+    /// DHCPINFORM.
+    pub const Inform: Dhcpv4MessageType = Dhcpv4MessageType(8);
+}
+
+impl fmt::Display for Dhcpv4MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Dhcpv4MessageTypes::Discover => "DHCPDISCOVER".to_string(),
+                Dhcpv4MessageTypes::Offer => "DHCPOFFER".to_string(),
+                Dhcpv4MessageTypes::Request => "DHCPREQUEST".to_string(),
+                Dhcpv4MessageTypes::Decline => "DHCPDECLINE".to_string(),
+                Dhcpv4MessageTypes::Ack => "DHCPACK".to_string(),
+                Dhcpv4MessageTypes::Nak => "DHCPNAK".to_string(),
+                Dhcpv4MessageTypes::Release => "DHCPRELEASE".to_string(),
+                Dhcpv4MessageTypes::Inform => "DHCPINFORM".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// DHCP option based on [IETF RFC 2132].
+///
+/// Except for the pad and end options, all options are encoded as a code
+/// octet, a length octet that excludes the code and length octets, and
+/// the option data.
+///
+/// [IETF RFC 2132]: https://tools.ietf.org/html/rfc2132
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Dhcpv4Option<'a> {
+    /// Pad. Used to align the subsequent fields.
+    Pad,
+
+    /// End. Marks the end of the options, the remaining octets are
+    /// padding.
+    End,
+
+    /// The client's subnet mask.
+    SubnetMask(Ipv4Addr),
+
+    /// The routers on the client's subnet, in order of preference.
+    Router(AddressListOption<'a>),
+
+    /// The domain name servers available to the client, in order of
+    /// preference.
+    DomainNameServer(AddressListOption<'a>),
+
+    /// The IP address the client requests to be assigned.
+    RequestedIpAddress(Ipv4Addr),
+
+    /// The lease time for the IP address in seconds.
+    LeaseTime(u32),
+
+    /// The type of the DHCP message.
+    MessageType(Dhcpv4MessageType),
+
+    /// The IP address of the selected server.
+    ServerIdentifier(Ipv4Addr),
+
+    /// The codes of the options the client requests.
+    ParameterRequestList(&'a [u8]),
+
+    /// The relay agent information, defined in [IETF RFC 3046].
+    ///
+    /// [IETF RFC 3046]: https://tools.ietf.org/html/rfc3046
+    RelayAgentInformation(RelayAgentInformationOption<'a>),
+
+    /// Any other option, with its code and data.
+    Unknown(Dhcpv4OptionCode, &'a [u8]),
+}
+
+impl Dhcpv4Option<'_> {
+    /// Returns the option code.
+    pub fn code(&self) -> Dhcpv4OptionCode {
+        match self {
+            Dhcpv4Option::Pad => Dhcpv4OptionCodes::Pad,
+            Dhcpv4Option::End => Dhcpv4OptionCodes::End,
+            Dhcpv4Option::SubnetMask(_) => Dhcpv4OptionCodes::SubnetMask,
+            Dhcpv4Option::Router(_) => Dhcpv4OptionCodes::Router,
+            Dhcpv4Option::DomainNameServer(_) => Dhcpv4OptionCodes::DomainNameServer,
+            Dhcpv4Option::RequestedIpAddress(_) => Dhcpv4OptionCodes::RequestedIpAddress,
+            Dhcpv4Option::LeaseTime(_) => Dhcpv4OptionCodes::LeaseTime,
+            Dhcpv4Option::MessageType(_) => Dhcpv4OptionCodes::MessageType,
+            Dhcpv4Option::ServerIdentifier(_) => Dhcpv4OptionCodes::ServerIdentifier,
+            Dhcpv4Option::ParameterRequestList(_) => Dhcpv4OptionCodes::ParameterRequestList,
+            Dhcpv4Option::RelayAgentInformation(_) => Dhcpv4OptionCodes::RelayAgentInformation,
+            Dhcpv4Option::Unknown(code, _) => *code,
+        }
+    }
+
+    /// Returns the length of the encoded option in octets.
+    pub fn length(&self) -> usize {
+        match self {
+            Dhcpv4Option::Pad | Dhcpv4Option::End => 1,
+            Dhcpv4Option::SubnetMask(_)
+            | Dhcpv4Option::RequestedIpAddress(_)
+            | Dhcpv4Option::LeaseTime(_)
+            | Dhcpv4Option::ServerIdentifier(_) => 6,
+            Dhcpv4Option::MessageType(_) => 3,
+            Dhcpv4Option::Router(list) | Dhcpv4Option::DomainNameServer(list) => {
+                2 + list.data.len()
+            }
+            Dhcpv4Option::ParameterRequestList(data) => 2 + data.len(),
+            Dhcpv4Option::RelayAgentInformation(info) => 2 + info.data.len(),
+            Dhcpv4Option::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes the option and appends it to the buffer.
+    pub(crate) fn encode(&self, buffer: &mut Vec<u8>) -> Fallible<()> {
+        let len = self.length();
+        ensure!(
+            len <= DHCPV4_MAX_OPTION_LEN + 2,
+            Dhcpv4OptionsError::TooLong(len - 2)
+        );
+
+        buffer.push(self.code().0);
+        match self {
+            Dhcpv4Option::Pad | Dhcpv4Option::End => (),
+            Dhcpv4Option::SubnetMask(addr)
+            | Dhcpv4Option::RequestedIpAddress(addr)
+            | Dhcpv4Option::ServerIdentifier(addr) => {
+                buffer.push(4);
+                buffer.extend_from_slice(&addr.octets());
+            }
+            Dhcpv4Option::LeaseTime(secs) => {
+                buffer.push(4);
+                buffer.extend_from_slice(&secs.to_be_bytes());
+            }
+            Dhcpv4Option::MessageType(message_type) => {
+                buffer.push(1);
+                buffer.push(message_type.0);
+            }
+            Dhcpv4Option::Router(list) | Dhcpv4Option::DomainNameServer(list) => {
+                buffer.push((len - 2) as u8);
+                buffer.extend_from_slice(list.data);
+            }
+            Dhcpv4Option::ParameterRequestList(data) | Dhcpv4Option::Unknown(_, data) => {
+                buffer.push((len - 2) as u8);
+                buffer.extend_from_slice(data);
+            }
+            Dhcpv4Option::RelayAgentInformation(info) => {
+                buffer.push((len - 2) as u8);
+                buffer.extend_from_slice(info.data);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An option with a list of IPv4 addresses, such as the router and the
+/// domain name server options.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AddressListOption<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AddressListOption<'a> {
+    /// Creates a new address list option with the encoded addresses. Each
+    /// address is 4 octets.
+    pub fn new(data: &'a [u8]) -> Self {
+        AddressListOption { data }
+    }
+
+    /// Returns the encoded addresses.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the addresses.
+    pub fn addresses(&self) -> Vec<Ipv4Addr> {
+        self.data
+            .chunks_exact(4)
+            .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+            .collect()
+    }
+}
+
+/// The relay agent information option.
+///
+/// The option data is a list of sub-options, each encoded as a code
+/// octet, a length octet, and the sub-option data.
+///
+/// ```
+///        Code   Len     Agent Information Field
+///       +------+------+------+------+------+------+--...-+------+
+///       |  82  |   N  |  i1  |  i2  |  i3  |  i4  |      |  iN  |
+///       +------+------+------+------+------+------+--...-+------+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RelayAgentInformationOption<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RelayAgentInformationOption<'a> {
+    /// The agent circuit ID sub-option code.
+    pub const CIRCUIT_ID: u8 = 1;
+
+    /// The agent remote ID sub-option code.
+    pub const REMOTE_ID: u8 = 2;
+
+    /// Creates a new relay agent information option with the encoded
+    /// sub-options.
+    pub fn new(data: &'a [u8]) -> Self {
+        RelayAgentInformationOption { data }
+    }
+
+    /// Returns the encoded sub-options.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the sub-options as pairs of code and data.
+    ///
+    /// Stops at the first sub-option that runs past the end of the option.
+    pub fn sub_options(&self) -> Vec<(u8, &'a [u8])> {
+        let mut sub_options = vec![];
+        let mut data = self.data;
+        while data.len() >= 2 && 2 + data[1] as usize <= data.len() {
+            let len = 2 + data[1] as usize;
+            sub_options.push((data[0], &data[2..len]));
+            data = &data[len..];
+        }
+        sub_options
+    }
+
+    #[inline]
+    fn find(&self, code: u8) -> Option<&'a [u8]> {
+        self.sub_options()
+            .into_iter()
+            .find(|&(sub_code, _)| sub_code == code)
+            .map(|(_, data)| data)
+    }
+
+    /// Returns the agent circuit ID if present.
+    pub fn circuit_id(&self) -> Option<&'a [u8]> {
+        self.find(Self::CIRCUIT_ID)
+    }
+
+    /// Returns the agent remote ID if present.
+    pub fn remote_id(&self) -> Option<&'a [u8]> {
+        self.find(Self::REMOTE_ID)
+    }
+}
+
+/// An iterator that iterates through the options in the DHCP message.
+///
+/// Iteration stops after the end option.
+#[derive(Debug)]
+pub struct Dhcpv4OptionsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Dhcpv4OptionsIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Dhcpv4OptionsIterator { data }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<Dhcpv4Option<'a>>> {
+        let data = self.data;
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let code = Dhcpv4OptionCode(data[0]);
+        match code {
+            Dhcpv4OptionCodes::End => {
+                // the rest is padding
+                self.data = &[];
+                return Ok(Some(Dhcpv4Option::End));
+            }
+            Dhcpv4OptionCodes::Pad => {
+                self.data = &data[1..];
+                return Ok(Some(Dhcpv4Option::Pad));
+            }
+            _ => (),
+        }
+
+        ensure!(
+            data.len() >= 2 && 2 + data[1] as usize <= data.len(),
+            ParseError::new("Invalid DHCP option length.")
+        );
+
+        let len = 2 + data[1] as usize;
+        let value = &data[2..len];
+        self.data = &data[len..];
+
+        let read_addr = |value: &[u8]| Ipv4Addr::new(value[0], value[1], value[2], value[3]);
+
+        let option = match code {
+            Dhcpv4OptionCodes::SubnetMask => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid subnet mask option length.")
+                );
+                Dhcpv4Option::SubnetMask(read_addr(value))
+            }
+            Dhcpv4OptionCodes::Router | Dhcpv4OptionCodes::DomainNameServer => {
+                ensure!(
+                    !value.is_empty() && value.len() % 4 == 0,
+                    ParseError::new("Invalid address list option length.")
+                );
+                let list = AddressListOption::new(value);
+                if code == Dhcpv4OptionCodes::Router {
+                    Dhcpv4Option::Router(list)
+                } else {
+                    Dhcpv4Option::DomainNameServer(list)
+                }
+            }
+            Dhcpv4OptionCodes::RequestedIpAddress => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid requested IP address option length.")
+                );
+                Dhcpv4Option::RequestedIpAddress(read_addr(value))
+            }
+            Dhcpv4OptionCodes::LeaseTime => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid lease time option length.")
+                );
+                Dhcpv4Option::LeaseTime(u32::from_be_bytes([
+                    value[0], value[1], value[2], value[3],
+                ]))
+            }
+            Dhcpv4OptionCodes::MessageType => {
+                ensure!(
+                    value.len() == 1,
+                    ParseError::new("Invalid message type option length.")
+                );
+                Dhcpv4Option::MessageType(Dhcpv4MessageType(value[0]))
+            }
+            Dhcpv4OptionCodes::ServerIdentifier => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid server identifier option length.")
+                );
+                Dhcpv4Option::ServerIdentifier(read_addr(value))
+            }
+            Dhcpv4OptionCodes::ParameterRequestList => Dhcpv4Option::ParameterRequestList(value),
+            Dhcpv4OptionCodes::RelayAgentInformation => {
+                let info = RelayAgentInformationOption::new(value);
+                ensure!(
+                    info.sub_options()
+                        .iter()
+                        .map(|(_, data)| 2 + data.len())
+                        .sum::<usize>()
+                        == value.len(),
+                    ParseError::new("Invalid relay agent information option.")
+                );
+                Dhcpv4Option::RelayAgentInformation(info)
+            }
+            _ => Dhcpv4Option::Unknown(code, value),
+        };
+
+        Ok(Some(option))
+    }
+}
+
+/// DHCP options related errors.
+#[derive(Debug, Fail)]
+pub enum Dhcpv4OptionsError {
+    /// Error indicating the option data exceeds the maximum length.
+    #[fail(display = "Option data length {} exceeds the maximum of 255.", _0)]
+    TooLong(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_code_to_string() {
+        assert_eq!("Router", Dhcpv4OptionCodes::Router.to_string());
+        assert_eq!("61", Dhcpv4OptionCode(61).to_string());
+    }
+
+    #[test]
+    fn message_type_to_string() {
+        assert_eq!("DHCPOFFER", Dhcpv4MessageTypes::Offer.to_string());
+        assert_eq!("18", Dhcpv4MessageType(18).to_string());
+    }
+
+    #[test]
+    fn encode_and_iterate_options() {
+        #[rustfmt::skip]
+        let info = [
+            // circuit id = "eth0"
+            0x01, 0x04, 0x65, 0x74, 0x68, 0x30,
+            // remote id = 00:0b:82:01:fc:42
+            0x02, 0x06, 0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+        ];
+        let routers = [192, 168, 0, 1, 192, 168, 0, 2];
+        let options = [
+            Dhcpv4Option::MessageType(Dhcpv4MessageTypes::Ack),
+            Dhcpv4Option::Router(AddressListOption::new(&routers)),
+            Dhcpv4Option::DomainNameServer(AddressListOption::new(&[8, 8, 8, 8])),
+            Dhcpv4Option::LeaseTime(3600),
+            Dhcpv4Option::RequestedIpAddress(Ipv4Addr::new(192, 168, 0, 10)),
+            Dhcpv4Option::RelayAgentInformation(RelayAgentInformationOption::new(&info)),
+            Dhcpv4Option::Unknown(Dhcpv4OptionCode(61), &[0x01]),
+            Dhcpv4Option::Pad,
+            Dhcpv4Option::End,
+        ];
+
+        let mut bytes = vec![];
+        for option in options.iter() {
+            option.encode(&mut bytes).unwrap();
+        }
+        assert_eq!(
+            options.iter().map(Dhcpv4Option::length).sum::<usize>(),
+            bytes.len()
+        );
+        // trailing padding is skipped
+        bytes.extend_from_slice(&[0, 0, 0]);
+
+        let mut iter = Dhcpv4OptionsIterator::new(&bytes);
+        for option in options.iter() {
+            assert_eq!(Some(*option), iter.next().unwrap());
+        }
+        assert_eq!(None, iter.next().unwrap());
+
+        match options[1] {
+            Dhcpv4Option::Router(list) => assert_eq!(
+                vec![Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 2)],
+                list.addresses()
+            ),
+            _ => unreachable!(),
+        }
+
+        match options[5] {
+            Dhcpv4Option::RelayAgentInformation(info) => {
+                assert_eq!(Some(&b"eth0"[..]), info.circuit_id());
+                assert_eq!(
+                    Some(&[0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42][..]),
+                    info.remote_id()
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn encode_option_too_long() {
+        let data = [0; 256];
+        let mut bytes = vec![];
+        assert!(Dhcpv4Option::Unknown(Dhcpv4OptionCode(61), &data)
+            .encode(&mut bytes)
+            .is_err());
+    }
+
+    #[test]
+    fn iterate_invalid_options() {
+        // length runs past the end
+        let mut iter = Dhcpv4OptionsIterator::new(&[0x35, 0x02, 0x01]);
+        assert!(iter.next().is_err());
+
+        // bad message type length
+        let mut iter = Dhcpv4OptionsIterator::new(&[0x35, 0x02, 0x01, 0x01]);
+        assert!(iter.next().is_err());
+
+        // router list not a multiple of 4
+        let mut iter = Dhcpv4OptionsIterator::new(&[0x03, 0x03, 0x0a, 0x00, 0x00]);
+        assert!(iter.next().is_err());
+
+        // relay agent sub-option runs past the end
+        let mut iter = Dhcpv4OptionsIterator::new(&[0x52, 0x03, 0x01, 0x04, 0x65]);
+        assert!(iter.next().is_err());
+    }
+}
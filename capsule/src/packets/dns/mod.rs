@@ -0,0 +1,967 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Domain Name System messages and resource records.
+
+mod records;
+
+pub use self::records::*;
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet, ParseError, Udp};
+use crate::{ensure, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port for DNS.
+pub const DNS_PORT: u16 = 53;
+
+/// The length of the fixed DNS header.
+const DNS_HEADER_LEN: usize = 12;
+
+// Masks of the flags field.
+const FLAG_QR: u16 = 0x8000;
+const FLAG_OPCODE: u16 = 0x7800;
+const FLAG_AA: u16 = 0x0400;
+const FLAG_TC: u16 = 0x0200;
+const FLAG_RD: u16 = 0x0100;
+const FLAG_RA: u16 = 0x0080;
+const FLAG_RCODE: u16 = 0x000f;
+
+/// [IANA] assigned DNS response code.
+///
+/// A list of supported codes is under [`DnsRcodes`].
+///
+/// [IANA]: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-6
+/// [`DnsRcodes`]: DnsRcodes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct DnsRcode(pub u8);
+
+/// Supported DNS response codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DnsRcodes {
+    use super::DnsRcode;
+
+    /// No error condition.
+    pub const NoError: DnsRcode = DnsRcode(0);
+
+    /// The server was unable to interpret the query.
+    pub const FormErr: DnsRcode = DnsRcode(1);
+
+    /// The server was unable to process the query.
+    pub const ServFail: DnsRcode = DnsRcode(2);
+
+    /// The domain name referenced in the query does not exist.
+    pub const NxDomain: DnsRcode = DnsRcode(3);
+
+    /// The server does not support the kind of query.
+    pub const NotImp: DnsRcode = DnsRcode(4);
+
+    /// The server refuses to perform the operation for policy reasons.
+    pub const Refused: DnsRcode = DnsRcode(5);
+}
+
+impl fmt::Display for DnsRcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DnsRcodes::NoError => "NOERROR".to_string(),
+                DnsRcodes::FormErr => "FORMERR".to_string(),
+                DnsRcodes::ServFail => "SERVFAIL".to_string(),
+                DnsRcodes::NxDomain => "NXDOMAIN".to_string(),
+                DnsRcodes::NotImp => "NOTIMP".to_string(),
+                DnsRcodes::Refused => "REFUSED".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Domain Name System message based on [IETF RFC 1035].
+///
+/// ```
+///  0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                      ID                       |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |QR|   Opcode  |AA|TC|RD|RA|   Z    |   RCODE   |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    QDCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    ANCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    NSCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    ARCOUNT                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                   Question                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    Answer                     |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                   Authority                   |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                  Additional                   |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+///
+/// - *ID*:         Identifier assigned by the program that generates the
+///                 query, copied to the response.
+///
+/// - *QR*:         Whether the message is a query (0) or a response (1).
+///
+/// - *Opcode*:     Kind of query, 0 for a standard query.
+///
+/// - *AA*:         Authoritative answer.
+///
+/// - *TC*:         Whether the message was truncated.
+///
+/// - *RD*:         Recursion desired.
+///
+/// - *RA*:         Recursion available.
+///
+/// - *Z*:          Reserved for future use.
+///
+/// - *RCODE*:      Response code.
+///
+/// - *QDCOUNT*:    Number of entries in the question section.
+///
+/// - *ANCOUNT*:    Number of resource records in the answer section.
+///
+/// - *NSCOUNT*:    Number of resource records in the authority section.
+///
+/// - *ARCOUNT*:    Number of resource records in the additional section.
+///
+/// The sections are considered the payload of the message. Use
+/// [`questions_iter`], [`answers_iter`], [`authorities_iter`] and
+/// [`additionals_iter`] to read them. Names are decoded in place, following
+/// the compression pointers.
+///
+/// Questions and records are appended in section order. Names written are
+/// compressed against the names already in the message.
+///
+/// [IETF RFC 1035]: https://tools.ietf.org/html/rfc1035#section-4.1
+/// [`questions_iter`]: Dns::questions_iter
+/// [`answers_iter`]: Dns::answers_iter
+/// [`authorities_iter`]: Dns::authorities_iter
+/// [`additionals_iter`]: Dns::additionals_iter
+pub struct Dns<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<DnsHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Dns<E> {
+    #[inline]
+    fn header(&self) -> &DnsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut DnsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    #[inline]
+    fn flags(&self) -> u16 {
+        self.header().flags.into()
+    }
+
+    #[inline]
+    fn set_flag(&mut self, mask: u16, value: bool) {
+        let flags = if value {
+            self.flags() | mask
+        } else {
+            self.flags() & !mask
+        };
+        self.header_mut().flags = flags.into();
+    }
+
+    /// Returns the identifier of the message.
+    #[inline]
+    pub fn id(&self) -> u16 {
+        self.header().id.into()
+    }
+
+    /// Sets the identifier of the message.
+    #[inline]
+    pub fn set_id(&mut self, id: u16) {
+        self.header_mut().id = id.into();
+    }
+
+    /// Returns whether the message is a response.
+    #[inline]
+    pub fn is_response(&self) -> bool {
+        self.flags() & FLAG_QR != 0
+    }
+
+    /// Sets whether the message is a response.
+    #[inline]
+    pub fn set_response(&mut self, response: bool) {
+        self.set_flag(FLAG_QR, response);
+    }
+
+    /// Returns the kind of query.
+    #[inline]
+    pub fn opcode(&self) -> u8 {
+        ((self.flags() & FLAG_OPCODE) >> 11) as u8
+    }
+
+    /// Sets the kind of query.
+    #[inline]
+    pub fn set_opcode(&mut self, opcode: u8) {
+        let flags = (self.flags() & !FLAG_OPCODE) | ((u16::from(opcode) << 11) & FLAG_OPCODE);
+        self.header_mut().flags = flags.into();
+    }
+
+    /// Returns whether the response is authoritative.
+    #[inline]
+    pub fn authoritative(&self) -> bool {
+        self.flags() & FLAG_AA != 0
+    }
+
+    /// Sets whether the response is authoritative.
+    #[inline]
+    pub fn set_authoritative(&mut self, authoritative: bool) {
+        self.set_flag(FLAG_AA, authoritative);
+    }
+
+    /// Returns whether the message is truncated.
+    #[inline]
+    pub fn truncated(&self) -> bool {
+        self.flags() & FLAG_TC != 0
+    }
+
+    /// Sets whether the message is truncated.
+    #[inline]
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.set_flag(FLAG_TC, truncated);
+    }
+
+    /// Returns whether recursion is desired.
+    #[inline]
+    pub fn recursion_desired(&self) -> bool {
+        self.flags() & FLAG_RD != 0
+    }
+
+    /// Sets whether recursion is desired.
+    #[inline]
+    pub fn set_recursion_desired(&mut self, desired: bool) {
+        self.set_flag(FLAG_RD, desired);
+    }
+
+    /// Returns whether recursion is available.
+    #[inline]
+    pub fn recursion_available(&self) -> bool {
+        self.flags() & FLAG_RA != 0
+    }
+
+    /// Sets whether recursion is available.
+    #[inline]
+    pub fn set_recursion_available(&mut self, available: bool) {
+        self.set_flag(FLAG_RA, available);
+    }
+
+    /// Returns the response code.
+    #[inline]
+    pub fn rcode(&self) -> DnsRcode {
+        DnsRcode((self.flags() & FLAG_RCODE) as u8)
+    }
+
+    /// Sets the response code.
+    #[inline]
+    pub fn set_rcode(&mut self, rcode: DnsRcode) {
+        let flags = (self.flags() & !FLAG_RCODE) | (u16::from(rcode.0) & FLAG_RCODE);
+        self.header_mut().flags = flags.into();
+    }
+
+    /// Returns the number of entries in the question section.
+    #[inline]
+    pub fn question_count(&self) -> u16 {
+        self.header().qdcount.into()
+    }
+
+    /// Returns the number of resource records in the answer section.
+    #[inline]
+    pub fn answer_count(&self) -> u16 {
+        self.header().ancount.into()
+    }
+
+    /// Returns the number of resource records in the authority section.
+    #[inline]
+    pub fn authority_count(&self) -> u16 {
+        self.header().nscount.into()
+    }
+
+    /// Returns the number of resource records in the additional section.
+    #[inline]
+    pub fn additional_count(&self) -> u16 {
+        self.header().arcount.into()
+    }
+
+    /// Returns the whole message, including the header. Compression
+    /// pointers are offsets from the start of the message.
+    #[inline]
+    pub fn message(&self) -> &[u8] {
+        match self.mbuf().read_data_slice::<u8>(self.offset, self.len()) {
+            Ok(message) => unsafe { message.as_ref() },
+            // the length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the question section.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let dns = udp.parse::<Dns<Ipv4>>()?;
+    /// let mut iter = dns.questions_iter();
+    ///
+    /// while let Some(question) = iter.next()? {
+    ///     println!("{} {}", question.name(), question.qtype());
+    /// }
+    /// ```
+    #[inline]
+    pub fn questions_iter(&self) -> DnsQuestionsIterator<'_> {
+        DnsQuestionsIterator::new(
+            self.message(),
+            DNS_HEADER_LEN,
+            self.question_count() as usize,
+        )
+    }
+
+    /// Returns an iterator to read the answer section.
+    #[inline]
+    pub fn answers_iter(&self) -> DnsRecordsIterator<'_> {
+        DnsRecordsIterator::new(
+            self.message(),
+            DNS_HEADER_LEN,
+            (self.question_count() as usize, 0),
+            self.answer_count() as usize,
+        )
+    }
+
+    /// Returns an iterator to read the authority section.
+    #[inline]
+    pub fn authorities_iter(&self) -> DnsRecordsIterator<'_> {
+        DnsRecordsIterator::new(
+            self.message(),
+            DNS_HEADER_LEN,
+            (self.question_count() as usize, self.answer_count() as usize),
+            self.authority_count() as usize,
+        )
+    }
+
+    /// Returns an iterator to read the additional section.
+    #[inline]
+    pub fn additionals_iter(&self) -> DnsRecordsIterator<'_> {
+        DnsRecordsIterator::new(
+            self.message(),
+            DNS_HEADER_LEN,
+            (
+                self.question_count() as usize,
+                self.answer_count() as usize + self.authority_count() as usize,
+            ),
+            self.additional_count() as usize,
+        )
+    }
+
+    /// Returns the total number of resource records in all sections.
+    #[inline]
+    fn record_count(&self) -> usize {
+        self.answer_count() as usize
+            + self.authority_count() as usize
+            + self.additional_count() as usize
+    }
+
+    /// Removes the resource records of all sections, keeping the
+    /// questions.
+    ///
+    /// Call [`reconcile_all`] afterwards to fix the lengths and checksums
+    /// of the envelopes.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn remove_records(&mut self) -> Fallible<()> {
+        let end = skip_sections(
+            self.message(),
+            DNS_HEADER_LEN,
+            self.question_count() as usize,
+            0,
+        )?;
+
+        let len = self.len();
+        if len > end {
+            let offset = self.offset + end;
+            self.mbuf_mut().shrink(offset, len - end)?;
+        }
+
+        let header = self.header_mut();
+        header.ancount = u16be::default();
+        header.nscount = u16be::default();
+        header.arcount = u16be::default();
+        Ok(())
+    }
+
+    /// Encodes a question or resource record after the last section of the
+    /// message with `encode`.
+    fn append<F>(&mut self, encode: F) -> Fallible<()>
+    where
+        F: FnOnce(&mut NameCompressor, &mut Vec<u8>) -> Fallible<()>,
+    {
+        let questions = self.question_count() as usize;
+        let records = self.record_count();
+        let message = self.message();
+        // anything after the last section, i.e. the Ethernet padding, is
+        // left after the new entry.
+        let end = skip_sections(message, DNS_HEADER_LEN, questions, records)?;
+
+        let mut bytes = message[..end].to_vec();
+        let mut compressor = NameCompressor::new(&bytes, DNS_HEADER_LEN, questions, records)?;
+        encode(&mut compressor, &mut bytes)?;
+
+        let offset = self.offset + end;
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, bytes.len() - end)?;
+        mbuf.write_data_slice(offset, &bytes[end..])?;
+        Ok(())
+    }
+
+    /// Appends a resource record with `encode` and increments the section
+    /// count with `increment`.
+    fn append_record<F>(
+        &mut self,
+        name: &str,
+        class: DnsClass,
+        ttl: u32,
+        value: DnsRecordValue<'_>,
+        increment: F,
+    ) -> Fallible<()>
+    where
+        F: FnOnce(&mut DnsHeader),
+    {
+        self.append(|compressor, bytes| {
+            compressor.encode(name, bytes)?;
+            bytes.extend_from_slice(&value.rtype().0.to_be_bytes());
+            bytes.extend_from_slice(&class.0.to_be_bytes());
+            bytes.extend_from_slice(&ttl.to_be_bytes());
+            value.encode(compressor, bytes)
+        })?;
+
+        increment(self.header_mut());
+        Ok(())
+    }
+
+    /// Adds an entry to the question section.
+    ///
+    /// Returns an error if the message already has resource records. Call
+    /// [`reconcile_all`] afterwards to fix the lengths and checksums of the
+    /// envelopes.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_question(&mut self, name: &str, qtype: DnsType, qclass: DnsClass) -> Fallible<()> {
+        ensure!(self.record_count() == 0, DnsError::OutOfOrder("question"));
+
+        self.append(|compressor, bytes| {
+            compressor.encode(name, bytes)?;
+            bytes.extend_from_slice(&qtype.0.to_be_bytes());
+            bytes.extend_from_slice(&qclass.0.to_be_bytes());
+            Ok(())
+        })?;
+
+        let header = self.header_mut();
+        header.qdcount = (u16::from(header.qdcount) + 1).into();
+        Ok(())
+    }
+
+    /// Adds a resource record to the answer section.
+    ///
+    /// Returns an error if the message already has authority or additional
+    /// records. Call [`reconcile_all`] afterwards to fix the lengths and
+    /// checksums of the envelopes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut dns = udp.parse::<Dns<Ipv4>>()?;
+    /// dns.remove_records()?;
+    /// dns.set_response(true);
+    /// dns.set_recursion_available(true);
+    /// dns.add_answer(
+    ///     "ads.example.com",
+    ///     DnsClasses::In,
+    ///     300,
+    ///     DnsRecordValue::A(Ipv4Addr::UNSPECIFIED),
+    /// )?;
+    /// dns.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_answer(
+        &mut self,
+        name: &str,
+        class: DnsClass,
+        ttl: u32,
+        value: DnsRecordValue<'_>,
+    ) -> Fallible<()> {
+        ensure!(
+            self.authority_count() == 0 && self.additional_count() == 0,
+            DnsError::OutOfOrder("answer")
+        );
+
+        self.append_record(name, class, ttl, value, |header| {
+            header.ancount = (u16::from(header.ancount) + 1).into();
+        })
+    }
+
+    /// Adds a resource record to the authority section.
+    ///
+    /// Returns an error if the message already has additional records.
+    /// Call [`reconcile_all`] afterwards to fix the lengths and checksums
+    /// of the envelopes.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_authority(
+        &mut self,
+        name: &str,
+        class: DnsClass,
+        ttl: u32,
+        value: DnsRecordValue<'_>,
+    ) -> Fallible<()> {
+        ensure!(
+            self.additional_count() == 0,
+            DnsError::OutOfOrder("authority")
+        );
+
+        self.append_record(name, class, ttl, value, |header| {
+            header.nscount = (u16::from(header.nscount) + 1).into();
+        })
+    }
+
+    /// Adds a resource record to the additional section.
+    ///
+    /// Call [`reconcile_all`] afterwards to fix the lengths and checksums
+    /// of the envelopes.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_additional(
+        &mut self,
+        name: &str,
+        class: DnsClass,
+        ttl: u32,
+        value: DnsRecordValue<'_>,
+    ) -> Fallible<()> {
+        self.append_record(name, class, ttl, value, |header| {
+            header.arcount = (u16::from(header.arcount) + 1).into();
+        })
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Dns<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dns")
+            .field("id", &format!("0x{:04x}", self.id()))
+            .field("response", &self.is_response())
+            .field("opcode", &self.opcode())
+            .field("authoritative", &self.authoritative())
+            .field("truncated", &self.truncated())
+            .field("recursion_desired", &self.recursion_desired())
+            .field("recursion_available", &self.recursion_available())
+            .field("rcode", &format!("{}", self.rcode()))
+            .field("question_count", &self.question_count())
+            .field("answer_count", &self.answer_count())
+            .field("authority_count", &self.authority_count())
+            .field("additional_count", &self.additional_count())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Dns<E> {
+    /// The preceding type for a DNS packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        DnsHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dns::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a DNS packet.
+    ///
+    /// Either [`Udp::src_port`] or [`Udp::dst_port`] must be set to
+    /// [`DNS_PORT`]. Otherwise a parsing error is returned. The sections
+    /// are not validated until they are read.
+    ///
+    /// [`Udp::src_port`]: crate::packets::Udp::src_port
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`DNS_PORT`]: DNS_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.src_port() == DNS_PORT || envelope.dst_port() == DNS_PORT,
+            ParseError::new("not a DNS packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Dns {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a DNS packet to the beginning of the UDP packet's payload.
+    ///
+    /// The message is an empty standard query. [`Udp::dst_port`] is set to
+    /// [`DNS_PORT`]. Set the ports accordingly when building a response.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`DNS_PORT`]: DNS_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, DnsHeader::size_of())?;
+        let header = mbuf.write_data(offset, &DnsHeader::default())?;
+
+        envelope.set_dst_port(DNS_PORT);
+
+        Ok(Dns {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// DNS message header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C)]
+struct DnsHeader {
+    id: u16be,
+    flags: u16be,
+    qdcount: u16be,
+    ancount: u16be,
+    nscount: u16be,
+    arcount: u16be,
+}
+
+/// DNS message related errors.
+#[derive(Debug, Fail)]
+pub enum DnsError {
+    /// Error indicating the domain name is not valid.
+    #[fail(display = "Invalid domain name '{}'.", _0)]
+    InvalidName(String),
+
+    /// Error indicating a character string exceeds the maximum length.
+    #[fail(display = "Character string length {} exceeds the maximum of 255.", _0)]
+    StringTooLong(usize),
+
+    /// Error indicating the record data exceeds the maximum length.
+    #[fail(display = "Record data length {} exceeds the maximum of 65535.", _0)]
+    RecordTooLong(usize),
+
+    /// Error indicating a later section already has entries.
+    #[fail(display = "Can't add to the {} section after a later section.", _0)]
+    OutOfOrder(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{DNS_QUERY_PACKET, DNS_RESPONSE_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn size_of_dns_header() {
+        assert_eq!(12, DnsHeader::size_of());
+    }
+
+    #[test]
+    fn rcode_to_string() {
+        assert_eq!("NXDOMAIN", DnsRcodes::NxDomain.to_string());
+        assert_eq!("11", DnsRcode(11).to_string());
+    }
+
+    #[capsule::test]
+    fn parse_dns_query() {
+        let packet = Mbuf::from_bytes(&DNS_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dns = udp.parse::<Dns<Ipv4>>().unwrap();
+
+        assert_eq!(0xbeef, dns.id());
+        assert!(!dns.is_response());
+        assert_eq!(0, dns.opcode());
+        assert!(dns.recursion_desired());
+        assert_eq!(DnsRcodes::NoError, dns.rcode());
+        assert_eq!(1, dns.question_count());
+        assert_eq!(0, dns.answer_count());
+
+        let mut iter = dns.questions_iter();
+        let question = iter.next().unwrap().unwrap();
+        assert!(question.name().matches("www.example.com"));
+        assert_eq!(DnsTypes::A, question.qtype());
+        assert_eq!(DnsClasses::In, question.qclass());
+        assert!(iter.next().unwrap().is_none());
+
+        assert!(dns.answers_iter().next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_dns_response() {
+        let packet = Mbuf::from_bytes(&DNS_RESPONSE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dns = udp.parse::<Dns<Ipv4>>().unwrap();
+
+        assert!(dns.is_response());
+        assert!(dns.recursion_available());
+
+        let mut iter = dns.answers_iter();
+        let cname = iter.next().unwrap().unwrap();
+        assert_eq!("www.example.com", cname.name().to_string());
+        assert_eq!(DnsTypes::Cname, cname.rtype());
+        assert_eq!(3600, cname.ttl());
+        match cname.data().unwrap() {
+            DnsRData::Cname(name) => assert_eq!("example.com", name.to_string()),
+            data => panic!("unexpected record data {:?}", data),
+        }
+
+        let a = iter.next().unwrap().unwrap();
+        assert_eq!("example.com", a.name().to_string());
+        assert_eq!(86400, a.ttl());
+        assert_eq!(
+            DnsRData::A(Ipv4Addr::new(93, 184, 216, 34)),
+            a.data().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+
+        let mut iter = dns.authorities_iter();
+        let ns = iter.next().unwrap().unwrap();
+        match ns.data().unwrap() {
+            DnsRData::Ns(name) => assert_eq!("a.iana-servers.net", name.to_string()),
+            data => panic!("unexpected record data {:?}", data),
+        }
+        assert!(iter.next().unwrap().is_none());
+
+        let mut iter = dns.additionals_iter();
+        let opt = iter.next().unwrap().unwrap();
+        assert_eq!(".", opt.name().to_string());
+        assert_eq!(DnsTypes::Opt, opt.rtype());
+        assert_eq!(DnsClass(4096), opt.class());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_non_dns_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dns<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn sinkhole_dns_query() {
+        let packet = Mbuf::from_bytes(&DNS_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut dns = udp.parse::<Dns<Ipv4>>().unwrap();
+
+        dns.remove_records().unwrap();
+        dns.set_response(true);
+        dns.set_recursion_available(true);
+
+        dns.add_answer(
+            "www.example.com",
+            DnsClasses::In,
+            60,
+            DnsRecordValue::Cname("sinkhole.example.com"),
+        )
+        .unwrap();
+        dns.add_answer(
+            "sinkhole.example.com",
+            DnsClasses::In,
+            60,
+            DnsRecordValue::A(Ipv4Addr::new(10, 0, 0, 254)),
+        )
+        .unwrap();
+        dns.add_answer(
+            "sinkhole.example.com",
+            DnsClasses::In,
+            60,
+            DnsRecordValue::Aaaa(Ipv6Addr::LOCALHOST),
+        )
+        .unwrap();
+        dns.add_additional(
+            "sinkhole.example.com",
+            DnsClasses::In,
+            60,
+            DnsRecordValue::Txt(&["blocked by policy"]),
+        )
+        .unwrap();
+        dns.reconcile_all();
+
+        // an answer can't follow the additional record
+        assert!(dns
+            .add_answer(
+                "www.example.com",
+                DnsClasses::In,
+                60,
+                DnsRecordValue::A(Ipv4Addr::LOCALHOST),
+            )
+            .is_err());
+        assert!(dns
+            .add_question("www.example.com", DnsTypes::A, DnsClasses::In)
+            .is_err());
+
+        let message = dns.message();
+        // the owner of the cname points to the question
+        assert_eq!(&[0xc0, 0x0c], &message[33..35]);
+        // the cname points to example.com in the question
+        assert_eq!(
+            &[0x08, b's', b'i', b'n', b'k', b'h', b'o', b'l', b'e', 0xc0, 0x10],
+            &message[45..56]
+        );
+        // the owner of the address points to the cname
+        assert_eq!(&[0xc0, 0x2d], &message[56..58]);
+
+        let ipv4 = dns.deparse().deparse();
+        assert_eq!(ipv4.len(), ipv4.total_length() as usize);
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(udp.len(), udp.length() as usize);
+        let dns = udp.parse::<Dns<Ipv4>>().unwrap();
+
+        assert_eq!(0xbeef, dns.id());
+        assert!(dns.is_response());
+        assert_eq!(1, dns.question_count());
+        assert_eq!(3, dns.answer_count());
+        assert_eq!(1, dns.additional_count());
+
+        let mut iter = dns.answers_iter();
+        let cname = iter.next().unwrap().unwrap();
+        assert!(cname.name().matches("www.example.com"));
+        match cname.data().unwrap() {
+            DnsRData::Cname(name) => assert!(name.matches("sinkhole.example.com")),
+            data => panic!("unexpected record data {:?}", data),
+        }
+        let a = iter.next().unwrap().unwrap();
+        assert!(a.name().matches("sinkhole.example.com"));
+        assert_eq!(DnsRData::A(Ipv4Addr::new(10, 0, 0, 254)), a.data().unwrap());
+        let aaaa = iter.next().unwrap().unwrap();
+        assert_eq!(DnsRData::Aaaa(Ipv6Addr::LOCALHOST), aaaa.data().unwrap());
+
+        let txt = dns.additionals_iter().next().unwrap().unwrap();
+        match txt.data().unwrap() {
+            DnsRData::Txt(txt) => assert_eq!(vec![&b"blocked by policy"[..]], txt.strings()),
+            data => panic!("unexpected record data {:?}", data),
+        }
+    }
+
+    #[capsule::test]
+    fn push_dns_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut dns = udp.push::<Dns<Ipv4>>().unwrap();
+
+        assert_eq!(DnsHeader::size_of(), dns.len());
+        assert_eq!(DNS_PORT, dns.envelope().dst_port());
+        assert!(!dns.is_response());
+
+        dns.set_id(0x1234);
+        dns.set_recursion_desired(true);
+        dns.add_question("example.com", DnsTypes::Aaaa, DnsClasses::In)
+            .unwrap();
+        dns.add_question("www.example.com", DnsTypes::Aaaa, DnsClasses::In)
+            .unwrap();
+        dns.reconcile_all();
+
+        // the second name is compressed
+        assert_eq!(12 + 13 + 4 + 6 + 4, dns.len());
+        assert_eq!(
+            &[0x03, b'w', b'w', b'w', 0xc0, 0x0c],
+            &dns.message()[29..35]
+        );
+
+        dns.set_rcode(DnsRcodes::NxDomain);
+        dns.set_opcode(2);
+        assert_eq!(DnsRcodes::NxDomain, dns.rcode());
+        assert_eq!(2, dns.opcode());
+        assert!(dns.recursion_desired());
+
+        let mut iter = dns.questions_iter();
+        assert!(iter.next().unwrap().unwrap().name().matches("example.com"));
+        assert!(iter
+            .next()
+            .unwrap()
+            .unwrap()
+            .name()
+            .matches("www.example.com"));
+        assert!(iter.next().unwrap().is_none());
+    }
+}
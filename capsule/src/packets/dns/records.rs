@@ -0,0 +1,972 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::DnsError;
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::Fallible;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The maximum length of an encoded domain name in octets.
+const MAX_NAME_LEN: usize = 255;
+
+/// The maximum length of a label in octets.
+const MAX_LABEL_LEN: usize = 63;
+
+/// The maximum length of a character string in octets.
+const MAX_STRING_LEN: usize = 255;
+
+/// The two high bits that mark a compression pointer.
+const POINTER_MASK: u8 = 0xc0;
+
+/// Compression pointers can only address the first 16K of the message.
+const MAX_POINTER_OFFSET: usize = 0x4000;
+
+/// [IANA] assigned DNS resource record type.
+///
+/// A list of supported types is under [`DnsTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-4
+/// [`DnsTypes`]: DnsTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct DnsType(pub u16);
+
+/// Supported DNS resource record types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DnsTypes {
+    use super::DnsType;
+
+    /// Host address.
+    pub const A: DnsType = DnsType(1);
+
+    /// Authoritative name server.
+    pub const Ns: DnsType = DnsType(2);
+
+    /// Canonical name for an alias.
+    pub const Cname: DnsType = DnsType(5);
+
+    /// Start of a zone of authority.
+    pub const Soa: DnsType = DnsType(6);
+
+    /// Domain name pointer.
+    pub const Ptr: DnsType = DnsType(12);
+
+    /// Mail exchange.
+    pub const Mx: DnsType = DnsType(15);
+
+    /// Text strings.
+    pub const Txt: DnsType = DnsType(16);
+
+    /// IPv6 host address.
+    pub const Aaaa: DnsType = DnsType(28);
+
+    /// Service locator.
+    pub const Srv: DnsType = DnsType(33);
+
+    /// EDNS option pseudo-record.
+    pub const Opt: DnsType = DnsType(41);
+
+    /// Request for all records.
+    pub const Any: DnsType = DnsType(255);
+}
+
+impl fmt::Display for DnsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DnsTypes::A => "A".to_string(),
+                DnsTypes::Ns => "NS".to_string(),
+                DnsTypes::Cname => "CNAME".to_string(),
+                DnsTypes::Soa => "SOA".to_string(),
+                DnsTypes::Ptr => "PTR".to_string(),
+                DnsTypes::Mx => "MX".to_string(),
+                DnsTypes::Txt => "TXT".to_string(),
+                DnsTypes::Aaaa => "AAAA".to_string(),
+                DnsTypes::Srv => "SRV".to_string(),
+                DnsTypes::Opt => "OPT".to_string(),
+                DnsTypes::Any => "ANY".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("TYPE{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// [IANA] assigned DNS class.
+///
+/// A list of supported classes is under [`DnsClasses`].
+///
+/// [IANA]: https://www.iana.org/assignments/dns-parameters/dns-parameters.xhtml#dns-parameters-2
+/// [`DnsClasses`]: DnsClasses
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct DnsClass(pub u16);
+
+/// Supported DNS classes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DnsClasses {
+    use super::DnsClass;
+
+    /// Internet.
+    pub const In: DnsClass = DnsClass(1);
+
+    /// Chaos.
+    pub const Ch: DnsClass = DnsClass(3);
+
+    /// Any class.
+    pub const Any: DnsClass = DnsClass(255);
+}
+
+impl fmt::Display for DnsClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DnsClasses::In => "IN".to_string(),
+                DnsClasses::Ch => "CH".to_string(),
+                DnsClasses::Any => "ANY".to_string(),
+                _ => {
+                    let c = self.0;
+                    format!("CLASS{}", c)
+                }
+            }
+        )
+    }
+}
+
+#[inline]
+fn read_u16(message: &[u8], offset: usize) -> Fallible<u16> {
+    ensure!(
+        offset + 2 <= message.len(),
+        ParseError::new("DNS message is truncated.")
+    );
+    Ok(u16::from_be_bytes([message[offset], message[offset + 1]]))
+}
+
+#[inline]
+fn read_u32(message: &[u8], offset: usize) -> Fallible<u32> {
+    ensure!(
+        offset + 4 <= message.len(),
+        ParseError::new("DNS message is truncated.")
+    );
+    Ok(u32::from_be_bytes([
+        message[offset],
+        message[offset + 1],
+        message[offset + 2],
+        message[offset + 3],
+    ]))
+}
+
+/// A domain name in the DNS message.
+///
+/// The name is not copied out of the message. Compression pointers are
+/// followed when the labels are read.
+#[derive(Clone, Copy)]
+pub struct DnsName<'a> {
+    message: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DnsName<'a> {
+    /// Parses the name at the offset of the message.
+    ///
+    /// Returns the name and the offset immediately after its encoding in
+    /// place, which is either after the terminating zero-length label or
+    /// after the first compression pointer.
+    pub(crate) fn parse(message: &'a [u8], offset: usize) -> Fallible<(Self, usize)> {
+        let mut pos = offset;
+        // a pointer must point before the start of the labels that lead
+        // to it, which guarantees the walk terminates.
+        let mut limit = offset;
+        let mut end = None;
+        let mut len = 1;
+
+        loop {
+            ensure!(
+                pos < message.len(),
+                ParseError::new("DNS message is truncated.")
+            );
+
+            let byte = message[pos];
+            if byte & POINTER_MASK == POINTER_MASK {
+                let target = read_u16(message, pos)? as usize & (MAX_POINTER_OFFSET - 1);
+                ensure!(
+                    target < limit,
+                    ParseError::new("Invalid DNS name compression pointer.")
+                );
+                end = end.or(Some(pos + 2));
+                pos = target;
+                limit = target;
+            } else if byte & POINTER_MASK != 0 {
+                return Err(ParseError::new("Invalid DNS label type.").into());
+            } else if byte == 0 {
+                end = end.or(Some(pos + 1));
+                break;
+            } else {
+                len += 1 + byte as usize;
+                ensure!(
+                    len <= MAX_NAME_LEN,
+                    ParseError::new("DNS name is too long.")
+                );
+                pos += 1 + byte as usize;
+            }
+        }
+
+        Ok((DnsName { message, offset }, end.unwrap_or_default()))
+    }
+
+    /// Returns an iterator over the labels of the name, starting with the
+    /// leftmost label. The root name has no labels.
+    #[inline]
+    pub fn labels(&self) -> DnsLabels<'a> {
+        DnsLabels {
+            message: self.message,
+            pos: self.offset,
+        }
+    }
+
+    /// Returns whether the name is equal to the dot-separated `name`,
+    /// ignoring ASCII case and the trailing dot.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.');
+        let mut labels = self.labels();
+        if !name.is_empty() {
+            for part in name.split('.') {
+                match labels.next() {
+                    Some(label) if label.eq_ignore_ascii_case(part.as_bytes()) => (),
+                    _ => return false,
+                }
+            }
+        }
+        labels.next().is_none()
+    }
+
+    /// Returns the offsets of the labels before the first compression
+    /// pointer.
+    fn label_offsets(&self) -> Vec<usize> {
+        let mut offsets = vec![];
+        let mut pos = self.offset;
+        while let Some(&byte) = self.message.get(pos) {
+            if byte == 0 || byte & POINTER_MASK != 0 {
+                break;
+            }
+            offsets.push(pos);
+            pos += 1 + byte as usize;
+        }
+        offsets
+    }
+}
+
+impl fmt::Display for DnsName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut labels = self.labels().peekable();
+        if labels.peek().is_none() {
+            return write!(f, ".");
+        }
+
+        while let Some(label) = labels.next() {
+            write!(f, "{}", String::from_utf8_lossy(label))?;
+            if labels.peek().is_some() {
+                write!(f, ".")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DnsName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+impl PartialEq for DnsName<'_> {
+    /// Compares the labels of the two names, ignoring ASCII case.
+    fn eq(&self, other: &Self) -> bool {
+        let mut labels = self.labels();
+        let mut other_labels = other.labels();
+        loop {
+            match (labels.next(), other_labels.next()) {
+                (None, None) => return true,
+                (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => (),
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl Eq for DnsName<'_> {}
+
+/// An iterator over the labels of a domain name.
+#[derive(Debug)]
+pub struct DnsLabels<'a> {
+    message: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for DnsLabels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let byte = *self.message.get(self.pos)?;
+            if byte & POINTER_MASK == POINTER_MASK {
+                let low = *self.message.get(self.pos + 1)?;
+                self.pos = (((byte & !POINTER_MASK) as usize) << 8) | low as usize;
+            } else if byte == 0 {
+                return None;
+            } else {
+                let start = self.pos + 1;
+                let end = start + byte as usize;
+                let label = self.message.get(start..end)?;
+                self.pos = end;
+                return Some(label);
+            }
+        }
+    }
+}
+
+/// An entry in the question section.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DnsQuestion<'a> {
+    name: DnsName<'a>,
+    qtype: DnsType,
+    qclass: DnsClass,
+}
+
+impl<'a> DnsQuestion<'a> {
+    /// Returns the domain name queried.
+    #[inline]
+    pub fn name(&self) -> DnsName<'a> {
+        self.name
+    }
+
+    /// Returns the type of the query.
+    #[inline]
+    pub fn qtype(&self) -> DnsType {
+        self.qtype
+    }
+
+    /// Returns the class of the query.
+    #[inline]
+    pub fn qclass(&self) -> DnsClass {
+        self.qclass
+    }
+}
+
+/// A resource record in the answer, authority or additional section.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct DnsRecord<'a> {
+    name: DnsName<'a>,
+    rtype: DnsType,
+    class: DnsClass,
+    ttl: u32,
+    message: &'a [u8],
+    rdata_offset: usize,
+    rdata_len: usize,
+}
+
+impl<'a> DnsRecord<'a> {
+    /// Returns the domain name the record pertains to.
+    #[inline]
+    pub fn name(&self) -> DnsName<'a> {
+        self.name
+    }
+
+    /// Returns the type of the record.
+    #[inline]
+    pub fn rtype(&self) -> DnsType {
+        self.rtype
+    }
+
+    /// Returns the class of the record.
+    #[inline]
+    pub fn class(&self) -> DnsClass {
+        self.class
+    }
+
+    /// Returns the time to live of the record in seconds.
+    #[inline]
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    /// Returns the raw record data.
+    #[inline]
+    pub fn rdata(&self) -> &'a [u8] {
+        &self.message[self.rdata_offset..self.rdata_offset + self.rdata_len]
+    }
+
+    /// Parses a domain name that must end exactly at the end of the
+    /// record data.
+    fn rdata_name(&self, offset: usize) -> Fallible<DnsName<'a>> {
+        let (name, end) = DnsName::parse(self.message, offset)?;
+        ensure!(
+            end == self.rdata_offset + self.rdata_len,
+            ParseError::new("Invalid DNS record data length.")
+        );
+        Ok(name)
+    }
+
+    /// Returns the parsed record data.
+    pub fn data(&self) -> Fallible<DnsRData<'a>> {
+        let rdata = self.rdata();
+        let data = match self.rtype {
+            DnsTypes::A => {
+                ensure!(
+                    rdata.len() == 4,
+                    ParseError::new("Invalid A record length.")
+                );
+                DnsRData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]))
+            }
+            DnsTypes::Aaaa => {
+                ensure!(
+                    rdata.len() == 16,
+                    ParseError::new("Invalid AAAA record length.")
+                );
+                let mut octets = [0; 16];
+                octets.copy_from_slice(rdata);
+                DnsRData::Aaaa(Ipv6Addr::from(octets))
+            }
+            DnsTypes::Cname => DnsRData::Cname(self.rdata_name(self.rdata_offset)?),
+            DnsTypes::Ns => DnsRData::Ns(self.rdata_name(self.rdata_offset)?),
+            DnsTypes::Ptr => DnsRData::Ptr(self.rdata_name(self.rdata_offset)?),
+            DnsTypes::Mx => {
+                let preference = read_u16(rdata, 0)?;
+                let exchange = self.rdata_name(self.rdata_offset + 2)?;
+                DnsRData::Mx {
+                    preference,
+                    exchange,
+                }
+            }
+            DnsTypes::Txt => {
+                let mut pos = 0;
+                while pos < rdata.len() {
+                    pos += 1 + rdata[pos] as usize;
+                }
+                ensure!(
+                    pos == rdata.len(),
+                    ParseError::new("Invalid TXT record length.")
+                );
+                DnsRData::Txt(DnsTxt { data: rdata })
+            }
+            _ => DnsRData::Unknown(self.rtype, rdata),
+        };
+
+        Ok(data)
+    }
+
+    /// Returns the offsets of the labels in the names of the record that
+    /// can be the target of compression pointers.
+    fn label_offsets(&self) -> Vec<usize> {
+        let mut offsets = self.name.label_offsets();
+        if let Ok(data) = self.data() {
+            match data {
+                DnsRData::Cname(name) | DnsRData::Ns(name) | DnsRData::Ptr(name) => {
+                    offsets.extend(name.label_offsets())
+                }
+                DnsRData::Mx { exchange, .. } => offsets.extend(exchange.label_offsets()),
+                _ => (),
+            }
+        }
+        offsets
+    }
+}
+
+impl fmt::Debug for DnsRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsRecord")
+            .field("name", &self.name)
+            .field("rtype", &format!("{}", self.rtype))
+            .field("class", &format!("{}", self.class))
+            .field("ttl", &self.ttl)
+            .field("rdata_len", &self.rdata_len)
+            .finish()
+    }
+}
+
+/// Parsed resource record data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DnsRData<'a> {
+    /// IPv4 host address.
+    A(Ipv4Addr),
+
+    /// IPv6 host address.
+    Aaaa(Ipv6Addr),
+
+    /// Canonical name of the alias.
+    Cname(DnsName<'a>),
+
+    /// Authoritative name server.
+    Ns(DnsName<'a>),
+
+    /// Domain name pointer.
+    Ptr(DnsName<'a>),
+
+    /// Mail exchange.
+    Mx {
+        /// The preference of the exchange, lower values are preferred.
+        preference: u16,
+        /// The domain name of the exchange.
+        exchange: DnsName<'a>,
+    },
+
+    /// One or more character strings.
+    Txt(DnsTxt<'a>),
+
+    /// Any other record, with its type and raw data.
+    Unknown(DnsType, &'a [u8]),
+}
+
+/// The character strings of a TXT record.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DnsTxt<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DnsTxt<'a> {
+    /// Returns the character strings.
+    pub fn strings(&self) -> Vec<&'a [u8]> {
+        let mut strings = vec![];
+        let mut data = self.data;
+        while let Some((&len, rest)) = data.split_first() {
+            let (string, rest) = rest.split_at(len as usize);
+            strings.push(string);
+            data = rest;
+        }
+        strings
+    }
+}
+
+/// Resource record data to add to a DNS message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DnsRecordValue<'a> {
+    /// IPv4 host address.
+    A(Ipv4Addr),
+
+    /// IPv6 host address.
+    Aaaa(Ipv6Addr),
+
+    /// Canonical name of the alias. The name is compressed.
+    Cname(&'a str),
+
+    /// Character strings, each up to 255 octets.
+    Txt(&'a [&'a str]),
+
+    /// Any other record, with its type and raw data.
+    Other(DnsType, &'a [u8]),
+}
+
+impl DnsRecordValue<'_> {
+    /// Returns the type of the record.
+    pub fn rtype(&self) -> DnsType {
+        match self {
+            DnsRecordValue::A(_) => DnsTypes::A,
+            DnsRecordValue::Aaaa(_) => DnsTypes::Aaaa,
+            DnsRecordValue::Cname(_) => DnsTypes::Cname,
+            DnsRecordValue::Txt(_) => DnsTypes::Txt,
+            DnsRecordValue::Other(rtype, _) => *rtype,
+        }
+    }
+
+    /// Encodes the record data length and the record data, and appends
+    /// them to the message.
+    pub(crate) fn encode(
+        &self,
+        compressor: &mut NameCompressor,
+        message: &mut Vec<u8>,
+    ) -> Fallible<()> {
+        let len_offset = message.len();
+        message.extend_from_slice(&[0, 0]);
+
+        match self {
+            DnsRecordValue::A(addr) => message.extend_from_slice(&addr.octets()),
+            DnsRecordValue::Aaaa(addr) => message.extend_from_slice(&addr.octets()),
+            DnsRecordValue::Cname(name) => compressor.encode(name, message)?,
+            DnsRecordValue::Txt(strings) => {
+                if strings.is_empty() {
+                    message.push(0);
+                }
+                for string in strings.iter() {
+                    ensure!(
+                        string.len() <= MAX_STRING_LEN,
+                        DnsError::StringTooLong(string.len())
+                    );
+                    message.push(string.len() as u8);
+                    message.extend_from_slice(string.as_bytes());
+                }
+            }
+            DnsRecordValue::Other(_, data) => message.extend_from_slice(data),
+        }
+
+        let len = message.len() - len_offset - 2;
+        ensure!(
+            len <= u16::max_value() as usize,
+            DnsError::RecordTooLong(len)
+        );
+        message[len_offset..len_offset + 2].copy_from_slice(&(len as u16).to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Skips over a question and returns the offset after it.
+#[inline]
+fn question_end(message: &[u8], offset: usize) -> Fallible<usize> {
+    let (_, end) = DnsName::parse(message, offset)?;
+    ensure!(
+        end + 4 <= message.len(),
+        ParseError::new("DNS message is truncated.")
+    );
+    Ok(end + 4)
+}
+
+/// Parses the resource record at the offset of the message. Returns the
+/// record and the offset after it.
+#[inline]
+fn parse_record(message: &[u8], offset: usize) -> Fallible<(DnsRecord<'_>, usize)> {
+    let (name, end) = DnsName::parse(message, offset)?;
+    let rtype = DnsType(read_u16(message, end)?);
+    let class = DnsClass(read_u16(message, end + 2)?);
+    let ttl = read_u32(message, end + 4)?;
+    let rdata_len = read_u16(message, end + 8)? as usize;
+    let rdata_offset = end + 10;
+    ensure!(
+        rdata_offset + rdata_len <= message.len(),
+        ParseError::new("DNS message is truncated.")
+    );
+
+    let record = DnsRecord {
+        name,
+        rtype,
+        class,
+        ttl,
+        message,
+        rdata_offset,
+        rdata_len,
+    };
+
+    Ok((record, rdata_offset + rdata_len))
+}
+
+/// Skips over the questions and the resource records. Returns the offset
+/// after them.
+pub(crate) fn skip_sections(
+    message: &[u8],
+    mut offset: usize,
+    questions: usize,
+    records: usize,
+) -> Fallible<usize> {
+    for _ in 0..questions {
+        offset = question_end(message, offset)?;
+    }
+    for _ in 0..records {
+        let (_, end) = parse_record(message, offset)?;
+        offset = end;
+    }
+    Ok(offset)
+}
+
+/// An iterator that iterates through the question section.
+#[derive(Debug)]
+pub struct DnsQuestionsIterator<'a> {
+    message: &'a [u8],
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> DnsQuestionsIterator<'a> {
+    pub(crate) fn new(message: &'a [u8], offset: usize, count: usize) -> Self {
+        DnsQuestionsIterator {
+            message,
+            offset,
+            remaining: count,
+        }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<DnsQuestion<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let (name, end) = DnsName::parse(self.message, self.offset)?;
+        let qtype = DnsType(read_u16(self.message, end)?);
+        let qclass = DnsClass(read_u16(self.message, end + 2)?);
+        self.offset = end + 4;
+        self.remaining -= 1;
+
+        Ok(Some(DnsQuestion {
+            name,
+            qtype,
+            qclass,
+        }))
+    }
+}
+
+/// An iterator that iterates through the resource records of the answer,
+/// authority or additional section.
+#[derive(Debug)]
+pub struct DnsRecordsIterator<'a> {
+    message: &'a [u8],
+    offset: usize,
+    // the preceding questions and records to skip before the first record
+    skip: Option<(usize, usize)>,
+    remaining: usize,
+}
+
+impl<'a> DnsRecordsIterator<'a> {
+    pub(crate) fn new(
+        message: &'a [u8],
+        offset: usize,
+        skip: (usize, usize),
+        count: usize,
+    ) -> Self {
+        DnsRecordsIterator {
+            message,
+            offset,
+            skip: Some(skip),
+            remaining: count,
+        }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<DnsRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        if let Some((questions, records)) = self.skip {
+            self.offset = skip_sections(self.message, self.offset, questions, records)?;
+            self.skip = None;
+        }
+
+        let (record, end) = parse_record(self.message, self.offset)?;
+        self.offset = end;
+        self.remaining -= 1;
+
+        Ok(Some(record))
+    }
+}
+
+/// Encodes domain names with message compression as described in
+/// [IETF RFC 1035].
+///
+/// [IETF RFC 1035]: https://tools.ietf.org/html/rfc1035#section-4.1.4
+pub(crate) struct NameCompressor {
+    // offsets of the labels that can be pointed to
+    offsets: Vec<usize>,
+}
+
+impl NameCompressor {
+    /// Creates a new compressor with the names in the questions and the
+    /// resource records of the message.
+    pub(crate) fn new(
+        message: &[u8],
+        offset: usize,
+        questions: usize,
+        records: usize,
+    ) -> Fallible<Self> {
+        let mut offsets = vec![];
+
+        let mut iter = DnsQuestionsIterator::new(message, offset, questions);
+        while let Some(question) = iter.next()? {
+            offsets.extend(question.name.label_offsets());
+        }
+
+        let mut iter = DnsRecordsIterator::new(message, offset, (questions, 0), records);
+        while let Some(record) = iter.next()? {
+            offsets.extend(record.label_offsets());
+        }
+
+        offsets.retain(|&offset| offset < MAX_POINTER_OFFSET);
+        Ok(NameCompressor { offsets })
+    }
+
+    /// Encodes the dot-separated `name` and appends it to the message.
+    ///
+    /// The longest suffix of the name that is already in the message is
+    /// replaced with a pointer.
+    pub(crate) fn encode(&mut self, name: &str, message: &mut Vec<u8>) -> Fallible<()> {
+        let trimmed = name.trim_end_matches('.');
+        let labels = if trimmed.is_empty() {
+            vec![]
+        } else {
+            trimmed.split('.').map(str::as_bytes).collect::<Vec<_>>()
+        };
+
+        ensure!(
+            labels
+                .iter()
+                .all(|label| !label.is_empty() && label.len() <= MAX_LABEL_LEN)
+                && labels.iter().map(|label| label.len() + 1).sum::<usize>() < MAX_NAME_LEN,
+            DnsError::InvalidName(name.to_string())
+        );
+
+        // the labels of this name can't be pointed to until it's complete.
+        let mut new_offsets = vec![];
+        let mut pointer = None;
+
+        for (i, label) in labels.iter().enumerate() {
+            let suffix = &labels[i..];
+            pointer = self.offsets.iter().copied().find(|&offset| {
+                let mut existing = DnsLabels {
+                    message,
+                    pos: offset,
+                };
+                suffix.iter().all(
+                    |label| matches!(existing.next(), Some(l) if l.eq_ignore_ascii_case(label)),
+                ) && existing.next().is_none()
+            });
+
+            if pointer.is_some() {
+                break;
+            }
+
+            new_offsets.push(message.len());
+            message.push(label.len() as u8);
+            message.extend_from_slice(label);
+        }
+
+        match pointer {
+            Some(offset) => message.extend_from_slice(&(0xc000 | offset as u16).to_be_bytes()),
+            None => message.push(0),
+        }
+
+        new_offsets.retain(|&offset| offset < MAX_POINTER_OFFSET);
+        self.offsets.extend(new_offsets);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_and_class_to_string() {
+        assert_eq!("AAAA", DnsTypes::Aaaa.to_string());
+        assert_eq!("TYPE65", DnsType(65).to_string());
+        assert_eq!("IN", DnsClasses::In.to_string());
+        assert_eq!("CLASS4096", DnsClass(4096).to_string());
+    }
+
+    #[test]
+    fn parse_compressed_name() {
+        #[rustfmt::skip]
+        let message = [
+            // www.example.com at 0
+            0x03, 0x77, 0x77, 0x77,
+            0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+            0x03, 0x63, 0x6f, 0x6d,
+            0x00,
+            // mail + pointer to example.com at 17
+            0x04, 0x6d, 0x61, 0x69, 0x6c,
+            0xc0, 0x04,
+            // pointer to mail.example.com at 24
+            0xc0, 0x11,
+            // root at 26
+            0x00,
+        ];
+
+        let (name, end) = DnsName::parse(&message, 0).unwrap();
+        assert_eq!(17, end);
+        assert_eq!("www.example.com", name.to_string());
+        assert!(name.matches("WWW.Example.com."));
+        assert!(!name.matches("example.com"));
+
+        let (name, end) = DnsName::parse(&message, 17).unwrap();
+        assert_eq!(24, end);
+        assert_eq!("mail.example.com", name.to_string());
+
+        let (pointer, end) = DnsName::parse(&message, 24).unwrap();
+        assert_eq!(26, end);
+        assert_eq!(name, pointer);
+
+        let (root, end) = DnsName::parse(&message, 26).unwrap();
+        assert_eq!(27, end);
+        assert_eq!(".", root.to_string());
+        assert!(root.matches("."));
+        assert!(root.matches(""));
+    }
+
+    #[test]
+    fn parse_invalid_name() {
+        // truncated label
+        assert!(DnsName::parse(&[0x03, 0x77, 0x77], 0).is_err());
+
+        // pointer to itself
+        assert!(DnsName::parse(&[0x00, 0xc0, 0x01], 1).is_err());
+
+        // pointer loop within the name
+        assert!(DnsName::parse(&[0x01, 0x61, 0xc0, 0x00], 0).is_err());
+
+        // forward pointer
+        assert!(DnsName::parse(&[0xc0, 0x02, 0x00], 0).is_err());
+
+        // reserved label type
+        assert!(DnsName::parse(&[0x40, 0x00], 0).is_err());
+    }
+
+    #[test]
+    fn compress_names() {
+        let mut message = vec![];
+        let mut compressor = NameCompressor { offsets: vec![] };
+
+        compressor.encode("www.example.com", &mut message).unwrap();
+        assert_eq!(17, message.len());
+
+        // the whole name is a pointer
+        compressor.encode("WWW.EXAMPLE.COM.", &mut message).unwrap();
+        assert_eq!(&[0xc0, 0x00], &message[17..]);
+
+        // only the suffix is a pointer
+        compressor.encode("mail.example.com", &mut message).unwrap();
+        assert_eq!(&[0x04, b'm', b'a', b'i', b'l', 0xc0, 0x04], &message[19..]);
+
+        // a repeated label isn't a suffix
+        compressor.encode("a.a", &mut message).unwrap();
+        assert_eq!(&[0x01, b'a', 0x01, b'a', 0x00], &message[26..]);
+
+        compressor.encode(".", &mut message).unwrap();
+        assert_eq!(0x00, message[31]);
+
+        let (name, _) = DnsName::parse(&message, 19).unwrap();
+        assert_eq!("mail.example.com", name.to_string());
+
+        assert!(compressor.encode("bad..name", &mut message).is_err());
+        let label = "a".repeat(64);
+        assert!(compressor.encode(&label, &mut message).is_err());
+    }
+}
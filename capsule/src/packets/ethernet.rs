@@ -21,7 +21,7 @@ use crate::net::MacAddr;
 use crate::packets::types::u16be;
 use crate::packets::{Internal, Packet};
 use crate::{ensure, Mbuf, SizeOf};
-use failure::Fallible;
+use failure::{Fail, Fallible};
 use std::fmt;
 use std::ptr::NonNull;
 
@@ -116,15 +116,22 @@ const VLAN_802_1AD: u16 = 0x88a8;
 /// S-TAG, or service tag, comes first, followed by the inner C-TAG, or customer
 /// tag. In such cases, 802.1ad specifies a TPID of `0x88a8` for S-TAG.
 ///
+/// # Inner frames
+///
+/// The frame is usually at the start of the `Mbuf`. It may also be an inner
+/// frame carried as the payload of a tunnel protocol such as [VXLAN], in
+/// which case the envelope is the tunnel packet.
+///
 /// [IEEE 802.1Q]: https://en.wikipedia.org/wiki/IEEE_802.1Q
 /// [IEEE 802.1ad]: https://en.wikipedia.org/wiki/IEEE_802.1ad
-pub struct Ethernet {
-    envelope: Mbuf,
+/// [VXLAN]: crate::packets::Vxlan
+pub struct Ethernet<E: Packet = Mbuf> {
+    envelope: E,
     header: NonNull<EthernetHeader>,
     offset: usize,
 }
 
-impl Ethernet {
+impl<E: Packet> Ethernet<E> {
     #[inline]
     fn header(&self) -> &EthernetHeader {
         unsafe { self.header.as_ref() }
@@ -211,9 +218,92 @@ impl Ethernet {
         self.set_src(dst);
         self.set_dst(src);
     }
+
+    /// Returns the outer VLAN tag. It's the only tag of a Dot1q frame or
+    /// the S-TAG of a QinQ frame.
+    #[inline]
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        let header = self.header();
+        unsafe {
+            match self.vlan_marker() {
+                VLAN_802_1Q => Some(header.chunk.dot1q.tag),
+                VLAN_802_1AD => Some(header.chunk.qinq.stag),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn inner_vlan_tag(&self) -> Option<VlanTag> {
+        if self.is_qinq() {
+            unsafe { Some(self.header().chunk.qinq.ctag) }
+        } else {
+            None
+        }
+    }
+
+    /// Sets the VLAN identifier of the outer VLAN tag.
+    #[inline]
+    pub fn set_vlan_id(&mut self, identifier: u16) -> Fallible<()> {
+        let mut tag = self.vlan_tag().ok_or(VlanError::NotTagged)?;
+        tag.set_identifier(identifier);
+        match self.vlan_marker() {
+            VLAN_802_1Q => self.header_mut().chunk.dot1q.tag = tag,
+            _ => self.header_mut().chunk.qinq.stag = tag,
+        }
+        Ok(())
+    }
+
+    /// Sets the VLAN identifier of the inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn set_inner_vlan_id(&mut self, identifier: u16) -> Fallible<()> {
+        let mut tag = self.inner_vlan_tag().ok_or(VlanError::NotTagged)?;
+        tag.set_identifier(identifier);
+        self.header_mut().chunk.qinq.ctag = tag;
+        Ok(())
+    }
+
+    /// Pushes a new outer VLAN tag onto the frame.
+    ///
+    /// An untagged frame becomes a Dot1q frame. A Dot1q frame becomes a
+    /// QinQ frame, with the existing tag as the C-TAG and `tag` as the
+    /// S-TAG. The tag protocol identifier of `tag` is set accordingly.
+    /// Returns an error if the frame is already QinQ tagged.
+    #[inline]
+    pub fn push_vlan(&mut self, mut tag: VlanTag) -> Fallible<()> {
+        let tpid = match self.vlan_marker() {
+            VLAN_802_1Q => VLAN_802_1AD,
+            VLAN_802_1AD => return Err(VlanError::AlreadyDoubleTagged.into()),
+            _ => VLAN_802_1Q,
+        };
+        tag.tpid = tpid.into();
+
+        // the tags follow the source MAC, where the tag protocol identifier
+        // takes the place of the ether type.
+        let offset = self.offset + ETH_HEADER_SIZE - 2;
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, VlanTag::size_of())?;
+        let _ = mbuf.write_data(offset, &tag)?;
+        Ok(())
+    }
+
+    /// Pops the outer VLAN tag off the frame and returns it.
+    ///
+    /// A Dot1q frame becomes untagged. A QinQ frame becomes a Dot1q frame,
+    /// tagged with its former C-TAG. Returns an error if the frame is not
+    /// VLAN tagged.
+    #[inline]
+    pub fn pop_vlan(&mut self) -> Fallible<VlanTag> {
+        let tag = self.vlan_tag().ok_or(VlanError::NotTagged)?;
+
+        let offset = self.offset + ETH_HEADER_SIZE - 2;
+        self.mbuf_mut().shrink(offset, VlanTag::size_of())?;
+        Ok(tag)
+    }
 }
 
-impl fmt::Debug for Ethernet {
+impl<E: Packet> fmt::Debug for Ethernet<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ethernet")
             .field("src", &format!("{}", self.src()))
@@ -227,9 +317,10 @@ impl fmt::Debug for Ethernet {
     }
 }
 
-impl Packet for Ethernet {
-    /// The preceding type for Ethernet must be `Mbuf`.
-    type Envelope = Mbuf;
+impl<E: Packet> Packet for Ethernet<E> {
+    /// The preceding type for Ethernet is `Mbuf` for the outermost frame,
+    /// or a tunnel packet for an inner frame.
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -286,8 +377,8 @@ impl Packet for Ethernet {
         // header including tags, otherwise accessing the union type in the
         // header will cause a panic.
         ensure!(
-            packet.mbuf().data_len() >= packet.header_len(),
-            BufferError::OutOfBuffer(packet.header_len(), packet.mbuf().data_len())
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
         );
 
         Ok(packet)
@@ -319,6 +410,79 @@ impl Packet for Ethernet {
     }
 }
 
+/// A packet that identifies the protocol of its payload with an EtherType.
+///
+/// Besides Ethernet, tunnel protocols like [GRE] carry their payload this
+/// way, as do [IPv4] and [IPv6] themselves for IP-in-IP tunnels. Network
+/// layer protocols such as IPv4 and IPv6 can be the payload of any envelope
+/// that implements this trait.
+///
+/// [GRE]: crate::packets::ip::Gre
+/// [IPv4]: crate::packets::ip::v4::Ipv4
+/// [IPv6]: crate::packets::ip::v6::Ipv6
+pub trait EtherTypeEnvelope: Packet {
+    /// Returns the protocol identifier of the payload.
+    fn ether_type(&self) -> EtherType;
+
+    /// Sets the protocol identifier of the payload.
+    fn set_ether_type(&mut self, ether_type: EtherType);
+}
+
+/// Common behaviors shared by datalink layer protocols.
+///
+/// Protocols that sit directly on top of the datalink layer, such as
+/// [ARP], use this trait to read and write the link layer addresses and
+/// the payload protocol identifier of their envelope.
+///
+/// [ARP]: crate::packets::Arp
+pub trait Datalink: EtherTypeEnvelope {
+    /// Returns the source MAC address.
+    fn src(&self) -> MacAddr;
+
+    /// Sets the source MAC address.
+    fn set_src(&mut self, src: MacAddr);
+
+    /// Returns the destination MAC address.
+    fn dst(&self) -> MacAddr;
+
+    /// Sets the destination MAC address.
+    fn set_dst(&mut self, dst: MacAddr);
+}
+
+impl<E: Packet> EtherTypeEnvelope for Ethernet<E> {
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        Ethernet::ether_type(self)
+    }
+
+    #[inline]
+    fn set_ether_type(&mut self, ether_type: EtherType) {
+        Ethernet::set_ether_type(self, ether_type)
+    }
+}
+
+impl<E: Packet> Datalink for Ethernet<E> {
+    #[inline]
+    fn src(&self) -> MacAddr {
+        Ethernet::src(self)
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: MacAddr) {
+        Ethernet::set_src(self, src)
+    }
+
+    #[inline]
+    fn dst(&self) -> MacAddr {
+        Ethernet::dst(self)
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: MacAddr) {
+        Ethernet::set_dst(self, dst)
+    }
+}
+
 /// The protocol identifier of the Ethernet frame payload.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 #[repr(C, packed)]
@@ -343,6 +507,14 @@ pub mod EtherTypes {
     pub const Ipv4: EtherType = EtherType(0x0800);
     /// Internet Protocol version 6.
     pub const Ipv6: EtherType = EtherType(0x86DD);
+    /// Transparent Ethernet bridging, used by NVGRE.
+    pub const Teb: EtherType = EtherType(0x6558);
+    /// MPLS unicast.
+    pub const Mpls: EtherType = EtherType(0x8847);
+    /// MPLS multicast.
+    pub const MplsMulticast: EtherType = EtherType(0x8848);
+    /// Link layer discovery protocol.
+    pub const Lldp: EtherType = EtherType(0x88CC);
 }
 
 impl fmt::Display for EtherType {
@@ -354,6 +526,10 @@ impl fmt::Display for EtherType {
                 EtherTypes::Arp => "ARP".to_string(),
                 EtherTypes::Ipv4 => "IPv4".to_string(),
                 EtherTypes::Ipv6 => "IPv6".to_string(),
+                EtherTypes::Teb => "TEB".to_string(),
+                EtherTypes::Mpls => "MPLS".to_string(),
+                EtherTypes::MplsMulticast => "MPLS Multicast".to_string(),
+                EtherTypes::Lldp => "LLDP".to_string(),
                 _ => {
                     let t = self.0;
                     format!("0x{:04x}", t)
@@ -364,44 +540,87 @@ impl fmt::Display for EtherType {
 }
 
 /// VLAN tag.
+///
+/// The tag protocol identifier is managed by [`Ethernet::push_vlan`] based
+/// on the position of the tag in the frame.
+///
+/// [`Ethernet::push_vlan`]: Ethernet::push_vlan
 #[derive(Clone, Copy, Debug, Default, SizeOf)]
 #[repr(C, packed)]
-struct VlanTag {
+pub struct VlanTag {
     tpid: u16be,
     tci: u16be,
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
 impl VlanTag {
+    /// Creates a new VLAN tag with the VLAN identifier. The priority code
+    /// point and the drop eligible indicator are unset.
+    pub fn new(identifier: u16) -> Self {
+        let mut tag = VlanTag::default();
+        tag.set_identifier(identifier);
+        tag
+    }
+
     /// Returns the tag protocol identifier, either 802.1q (Dot1q) or 802.1ad (QinQ).
-    #[allow(dead_code)]
     #[inline]
-    fn tag_id(&self) -> u16 {
+    pub fn tag_id(&self) -> u16 {
         self.tpid.into()
     }
 
     /// Returns the priority code point.
-    #[allow(dead_code)]
     #[inline]
-    fn priority(&self) -> u8 {
+    pub fn priority(&self) -> u8 {
         let tci: u16 = self.tci.into();
         (tci >> 13) as u8
     }
 
+    /// Sets the priority code point. Only the lower 3 bits are used.
+    #[inline]
+    pub fn set_priority(&mut self, priority: u8) {
+        self.tci = (self.tci & u16be::from(0x1fff)) | u16be::from(u16::from(priority & 0x07) << 13);
+    }
+
     /// Returns whether the frame is eligible to be dropped in the presence
     /// of congestion.
-    #[allow(dead_code)]
     #[inline]
-    fn drop_eligible(&self) -> bool {
+    pub fn drop_eligible(&self) -> bool {
         self.tci & u16be::from(0x1000) > u16be::MIN
     }
 
+    /// Sets the drop eligible indicator.
+    #[inline]
+    pub fn set_drop_eligible(&mut self, drop_eligible: bool) {
+        if drop_eligible {
+            self.tci |= u16be::from(0x1000);
+        } else {
+            self.tci &= u16be::from(!0x1000);
+        }
+    }
+
     /// Returns the VLAN identifier.
-    #[allow(dead_code)]
     #[inline]
-    fn identifier(&self) -> u16 {
+    pub fn identifier(&self) -> u16 {
         (self.tci & u16be::from(0x0fff)).into()
     }
+
+    /// Sets the VLAN identifier. Only the lower 12 bits are used.
+    #[inline]
+    pub fn set_identifier(&mut self, identifier: u16) {
+        self.tci = (self.tci & u16be::from(0xf000)) | u16be::from(identifier & 0x0fff);
+    }
+}
+
+/// VLAN tagging related errors.
+#[derive(Debug, Fail)]
+pub enum VlanError {
+    /// Error indicating the frame has no VLAN tag.
+    #[fail(display = "Frame is not VLAN tagged.")]
+    NotTagged,
+
+    /// Error indicating the frame already has both a S-TAG and a C-TAG.
+    #[fail(display = "Frame is already QinQ tagged.")]
+    AlreadyDoubleTagged,
 }
 
 /// Dot1q chunk for a VLAN header.
@@ -479,6 +698,9 @@ mod tests {
         assert_eq!("ARP", EtherTypes::Arp.to_string());
         assert_eq!("IPv4", EtherTypes::Ipv4.to_string());
         assert_eq!("IPv6", EtherTypes::Ipv6.to_string());
+        assert_eq!("TEB", EtherTypes::Teb.to_string());
+        assert_eq!("MPLS", EtherTypes::Mpls.to_string());
+        assert_eq!("LLDP", EtherTypes::Lldp.to_string());
         assert_eq!("0x0000", EtherType::new(0).to_string());
     }
 
@@ -516,6 +738,109 @@ mod tests {
         assert_eq!(22, ethernet.header_len());
     }
 
+    #[capsule::test]
+    fn dot1q_vlan_tag() {
+        let packet = Mbuf::from_bytes(&VLAN_DOT1Q_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let tag = ethernet.vlan_tag().unwrap();
+
+        assert_eq!(VLAN_802_1Q, tag.tag_id());
+        assert_eq!(0, tag.priority());
+        assert!(!tag.drop_eligible());
+        assert_eq!(123, tag.identifier());
+        assert!(ethernet.inner_vlan_tag().is_none());
+    }
+
+    #[capsule::test]
+    fn qinq_vlan_tags() {
+        let packet = Mbuf::from_bytes(&VLAN_QINQ_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        let stag = ethernet.vlan_tag().unwrap();
+        assert_eq!(VLAN_802_1AD, stag.tag_id());
+        assert_eq!(30, stag.identifier());
+
+        let ctag = ethernet.inner_vlan_tag().unwrap();
+        assert_eq!(VLAN_802_1Q, ctag.tag_id());
+        assert_eq!(1, ctag.priority());
+        assert!(!ctag.drop_eligible());
+        assert_eq!(101, ctag.identifier());
+    }
+
+    #[test]
+    fn set_vlan_tag_fields() {
+        let mut tag = VlanTag::new(100);
+        tag.set_priority(5);
+        tag.set_drop_eligible(true);
+
+        assert_eq!(100, tag.identifier());
+        assert_eq!(5, tag.priority());
+        assert!(tag.drop_eligible());
+
+        tag.set_identifier(0x1fff);
+        tag.set_drop_eligible(false);
+        assert_eq!(0x0fff, tag.identifier());
+        assert_eq!(5, tag.priority());
+        assert!(!tag.drop_eligible());
+    }
+
+    #[capsule::test]
+    fn set_vlan_ids() {
+        let packet = Mbuf::from_bytes(&VLAN_QINQ_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.set_vlan_id(200).is_ok());
+        assert!(ethernet.set_inner_vlan_id(300).is_ok());
+        assert_eq!(200, ethernet.vlan_tag().unwrap().identifier());
+        assert_eq!(300, ethernet.inner_vlan_tag().unwrap().identifier());
+        // the rest of the tag is untouched
+        assert_eq!(1, ethernet.inner_vlan_tag().unwrap().priority());
+        assert_eq!(EtherTypes::Arp, ethernet.ether_type());
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.set_vlan_id(200).is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_pop_vlan() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+        let len = ethernet.len();
+
+        assert!(ethernet.push_vlan(VlanTag::new(10)).is_ok());
+        assert!(ethernet.is_dot1q());
+        assert_eq!(18, ethernet.header_len());
+        assert_eq!(len + 4, ethernet.len());
+        assert_eq!(10, ethernet.vlan_tag().unwrap().identifier());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+
+        assert!(ethernet.push_vlan(VlanTag::new(20)).is_ok());
+        assert!(ethernet.is_qinq());
+        assert_eq!(22, ethernet.header_len());
+        assert_eq!(20, ethernet.vlan_tag().unwrap().identifier());
+        assert_eq!(10, ethernet.inner_vlan_tag().unwrap().identifier());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+
+        // can't have more than 2 tags
+        assert!(ethernet.push_vlan(VlanTag::new(30)).is_err());
+
+        assert_eq!(20, ethernet.pop_vlan().unwrap().identifier());
+        assert!(ethernet.is_dot1q());
+        assert_eq!(10, ethernet.vlan_tag().unwrap().identifier());
+
+        assert_eq!(10, ethernet.pop_vlan().unwrap().identifier());
+        assert!(!ethernet.is_dot1q() && !ethernet.is_qinq());
+        assert_eq!(len, ethernet.len());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert!(ethernet.pop_vlan().is_err());
+
+        // the payload is intact
+        let ethernet = ethernet.reset().parse::<Ethernet>().unwrap();
+        let bytes = ethernet.mbuf().read_data_slice::<u8>(0, len).unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+
     #[capsule::test]
     fn swap_addresses() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -0,0 +1,1046 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::dpdk::BufferError;
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{
+    EtherType, EtherTypeEnvelope, EtherTypes, Ethernet, Immutable, Internal, Packet, ParseError,
+    Udp,
+};
+use crate::{ensure, Mbuf, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP destination port for Geneve.
+pub const GENEVE_PORT: u16 = 6081;
+
+// Masks for the fields in the first two octets of the header.
+const GENEVE_VER_MASK: u8 = 0xc0;
+const GENEVE_OPT_LEN_MASK: u8 = 0x3f;
+const GENEVE_FLAG_O: u8 = 0x80;
+const GENEVE_FLAG_C: u8 = 0x40;
+
+// The high-order bit of the option type marks a critical option.
+const GENEVE_OPTION_CRITICAL: u8 = 0x80;
+const GENEVE_OPTION_LEN_MASK: u8 = 0x1f;
+
+// The maximum lengths in octets, as limited by the size of the length fields.
+const GENEVE_MAX_OPTIONS_LEN: usize = GENEVE_OPT_LEN_MASK as usize * 4;
+const GENEVE_MAX_OPTION_DATA_LEN: usize = GENEVE_OPTION_LEN_MASK as usize * 4;
+
+/// Generic Network Virtualization Encapsulation packet based on
+/// [IETF RFC 8926].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Ver|  Opt Len  |O|C|    Rsvd.  |          Protocol Type        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Virtual Network Identifier (VNI)       |    Reserved   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// ~                    Variable-Length Options                    ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*:        The current version number is 0.
+///
+/// - *Opt Len*:        The length of the options fields, expressed in
+///                     4-byte multiples, not including the 8-byte fixed
+///                     tunnel header.
+///
+/// - *O*:              OAM packet. The packet contains a control message
+///                     instead of a data payload.
+///
+/// - *C*:              Critical options present. One or more options has
+///                     the critical bit set.
+///
+/// - *Protocol Type*:  The type of the protocol data unit appearing after
+///                     the Geneve header, using EtherType values.
+///
+/// - *VNI*:            24-bit identifier for a unique element of a
+///                     virtual network.
+///
+/// - *Options*:        Zero or more variable-length TLV options. Use
+///                     [`options_iter`] and [`options_mut`] to read and
+///                     modify them.
+///
+/// # Payload
+///
+/// The payload is identified by the protocol type. An Ethernet payload,
+/// indicated by [`EtherTypes::Teb`], can be parsed with
+/// `geneve.parse::<Ethernet<Geneve<E>>>()`. IPv4 and IPv6 payloads can be
+/// parsed directly, for example with `geneve.parse::<Ipv4<Geneve<E>>>()`.
+///
+/// [IETF RFC 8926]: https://tools.ietf.org/html/rfc8926
+/// [`options_iter`]: Geneve::options_iter
+/// [`options_mut`]: Geneve::options_mut
+/// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+pub struct Geneve<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GeneveHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Geneve<E> {
+    #[inline]
+    fn header(&self) -> &GeneveHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GeneveHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().ver_opt_len & GENEVE_VER_MASK) >> 6
+    }
+
+    /// Returns the length of the options in octets.
+    #[inline]
+    pub fn options_len(&self) -> usize {
+        (self.header().ver_opt_len & GENEVE_OPT_LEN_MASK) as usize * 4
+    }
+
+    /// Returns a flag indicating whether the packet is an OAM packet.
+    #[inline]
+    pub fn oam(&self) -> bool {
+        self.header().flags & GENEVE_FLAG_O != 0
+    }
+
+    /// Sets the OAM flag.
+    #[inline]
+    pub fn set_oam(&mut self, oam: bool) {
+        if oam {
+            self.header_mut().flags |= GENEVE_FLAG_O;
+        } else {
+            self.header_mut().flags &= !GENEVE_FLAG_O;
+        }
+    }
+
+    /// Returns a flag indicating whether critical options are present.
+    #[inline]
+    pub fn critical(&self) -> bool {
+        self.header().flags & GENEVE_FLAG_C != 0
+    }
+
+    /// Sets the critical options present flag.
+    ///
+    /// The flag is also fixed when the packet is reconciled.
+    #[inline]
+    pub fn set_critical(&mut self, critical: bool) {
+        if critical {
+            self.header_mut().flags |= GENEVE_FLAG_C;
+        } else {
+            self.header_mut().flags &= !GENEVE_FLAG_C;
+        }
+    }
+
+    /// Returns the protocol type of the payload.
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    /// Sets the protocol type of the payload.
+    #[inline]
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        self.header_mut().protocol_type = protocol_type.0.into()
+    }
+
+    /// Returns the virtual network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        let vni: u32 = self.header().vni.into();
+        vni >> 8
+    }
+
+    /// Sets the virtual network identifier. Only the lower 24 bits are used.
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) {
+        self.header_mut().vni = ((vni & 0x00ff_ffff) << 8).into();
+    }
+
+    /// Returns an iterator to read the options in the packet.
+    ///
+    /// The options cannot be modified. To modify the values while iterating
+    /// over the options, use [`options_mut().iter()`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let mut iter = geneve.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    ///
+    /// [`options_mut().iter()`]: GeneveOptions::iter
+    #[inline]
+    pub fn options_iter(&self) -> ImmutableGeneveOptionsIterator<'_> {
+        let mbuf = unsafe { self.mbuf().clone(Internal(())) };
+        let offset = self.offset + GeneveHeader::size_of();
+        ImmutableGeneveOptionsIterator {
+            mbuf,
+            offset,
+            end: offset + self.options_len(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns a mutable reference to the options in the packet.
+    #[inline]
+    pub fn options_mut(&mut self) -> GeneveOptions<'_> {
+        let header = self.header;
+        let offset = self.offset + GeneveHeader::size_of();
+        GeneveOptions {
+            mbuf: self.mbuf_mut(),
+            header,
+            offset,
+        }
+    }
+
+    /// Removes the outer Ethernet, IP, UDP and Geneve headers from the
+    /// message buffer, and returns the inner Ethernet frame.
+    ///
+    /// The protocol type must be [`EtherTypes::Teb`]. Otherwise a parsing
+    /// error is returned.
+    ///
+    /// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+    #[inline]
+    pub fn decap(self) -> Fallible<Ethernet> {
+        ensure!(
+            self.protocol_type() == EtherTypes::Teb,
+            ParseError::new("not a Geneve packet carrying an Ethernet frame.")
+        );
+
+        let len = self.payload_offset();
+        let mut mbuf = self.reset();
+        mbuf.shrink(0, len)?;
+        mbuf.parse::<Ethernet>()
+    }
+}
+
+impl<E: IpPacket + Packet<Envelope = Ethernet>> Geneve<E> {
+    /// Encapsulates the Ethernet frame in the message buffer with outer
+    /// Ethernet, IP, UDP and Geneve headers.
+    ///
+    /// The VNI is set, the protocol type is set to [`EtherTypes::Teb`] and
+    /// the lengths and checksums of the outer headers are reconciled. The
+    /// outer addresses and the UDP source port are left unset. After
+    /// setting them or adding options, call [`reconcile_all`] again.
+    ///
+    /// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(frame: Mbuf, vni: u32) -> Fallible<Self> {
+        let ethernet = frame.push::<Ethernet>()?;
+        let ip = ethernet.push::<E>()?;
+        let udp = ip.push::<Udp<E>>()?;
+        let mut geneve = udp.push::<Geneve<E>>()?;
+        geneve.set_vni(vni);
+        geneve.reconcile_all();
+        Ok(geneve)
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Geneve<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("geneve")
+            .field("version", &self.version())
+            .field("options_len", &self.options_len())
+            .field("oam", &self.oam())
+            .field("critical", &self.critical())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Geneve<E> {
+    /// The preceding type for a Geneve packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the Geneve header includes the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        GeneveHeader::size_of() + self.options_len()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Geneve::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a Geneve packet.
+    ///
+    /// [`Udp::dst_port`] must be set to [`GENEVE_PORT`] and the version
+    /// must be 0. Otherwise a parsing error is returned.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`GENEVE_PORT`]: GENEVE_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.dst_port() == GENEVE_PORT,
+            ParseError::new("not a Geneve packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Geneve {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.version() == 0,
+            ParseError::new("not a Geneve version 0 packet.")
+        );
+
+        // we've only parsed the fixed 8 bytes, make sure there's enough
+        // data for the options as well.
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a Geneve packet to the beginning of the UDP packet's
+    /// payload.
+    ///
+    /// [`Udp::dst_port`] is set to [`GENEVE_PORT`] and the protocol type is
+    /// set to [`EtherTypes::Teb`]. The packet has no options.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`GENEVE_PORT`]: GENEVE_PORT
+    /// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GeneveHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GeneveHeader::default())?;
+
+        envelope.set_dst_port(GENEVE_PORT);
+
+        Ok(Geneve {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * The [critical] flag is set if any of the options is critical, and
+    /// cleared otherwise.
+    ///
+    /// [critical]: Geneve::critical
+    #[inline]
+    fn reconcile(&mut self) {
+        let critical = {
+            let mut critical = false;
+            let mut iter = self.options_iter();
+            while let Ok(Some(option)) = iter.next() {
+                critical |= option.critical();
+            }
+            critical
+        };
+        self.set_critical(critical);
+    }
+}
+
+impl<E: IpPacket> EtherTypeEnvelope for Geneve<E> {
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        self.protocol_type()
+    }
+
+    #[inline]
+    fn set_ether_type(&mut self, ether_type: EtherType) {
+        self.set_protocol_type(ether_type)
+    }
+}
+
+/// A Geneve TLV option.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Option Class         |      Type     |R|R|R| Length  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// ~                  Variable-Length Option Data                  ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Option Class*:   Namespace for the type field.
+///
+/// - *Type*:           Format of the data contained in the option. The
+///                     high-order bit indicates a critical option.
+///
+/// - *Length*:         Length of the option data, expressed in 4-byte
+///                     multiples, excluding the 4-byte option header.
+pub struct GeneveOption<'a> {
+    mbuf: &'a mut Mbuf,
+    tuple: NonNull<GeneveOptionHeader>,
+    offset: usize,
+}
+
+impl<'a> GeneveOption<'a> {
+    /// Creates a new Geneve option, bounded by the end of the options.
+    #[inline]
+    fn new(mbuf: &'a mut Mbuf, offset: usize, end: usize) -> Fallible<Self> {
+        let tuple = mbuf.read_data(offset)?;
+        let option = GeneveOption {
+            mbuf,
+            tuple,
+            offset,
+        };
+
+        // makes sure that the option doesn't run past the end of the
+        // options as indicated by the length field of the Geneve header
+        ensure!(
+            end >= option.end_offset(),
+            BufferError::OutOfBuffer(option.end_offset(), end)
+        );
+
+        Ok(option)
+    }
+
+    #[inline]
+    fn tuple(&self) -> &GeneveOptionHeader {
+        unsafe { self.tuple.as_ref() }
+    }
+
+    #[inline]
+    fn tuple_mut(&mut self) -> &mut GeneveOptionHeader {
+        unsafe { self.tuple.as_mut() }
+    }
+
+    /// Returns the option class.
+    #[inline]
+    pub fn option_class(&self) -> u16 {
+        self.tuple().option_class.into()
+    }
+
+    /// Sets the option class.
+    #[inline]
+    pub fn set_option_class(&mut self, option_class: u16) {
+        self.tuple_mut().option_class = option_class.into();
+    }
+
+    /// Returns the option type.
+    #[inline]
+    pub fn option_type(&self) -> u8 {
+        self.tuple().option_type
+    }
+
+    /// Sets the option type.
+    #[inline]
+    pub fn set_option_type(&mut self, option_type: u8) {
+        self.tuple_mut().option_type = option_type;
+    }
+
+    /// Returns a flag indicating whether the option is critical.
+    #[inline]
+    pub fn critical(&self) -> bool {
+        self.option_type() & GENEVE_OPTION_CRITICAL != 0
+    }
+
+    /// Returns the length of the option data in units of 4 octets.
+    #[inline]
+    pub fn length(&self) -> u8 {
+        self.tuple().length & GENEVE_OPTION_LEN_MASK
+    }
+
+    #[inline]
+    fn data_offset(&self) -> usize {
+        self.offset + GeneveOptionHeader::size_of()
+    }
+
+    #[inline]
+    fn end_offset(&self) -> usize {
+        self.data_offset() + self.length() as usize * 4
+    }
+
+    /// Returns the option data.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        let len = self.length() as usize * 4;
+        if len == 0 {
+            return &[];
+        }
+
+        match self.mbuf.read_data_slice::<u8>(self.data_offset(), len) {
+            Ok(data) => unsafe { data.as_ref() },
+            // the option length is checked when created, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the option data mutably.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let len = self.length() as usize * 4;
+        if len == 0 {
+            return &mut [];
+        }
+
+        match self.mbuf.read_data_slice::<u8>(self.data_offset(), len) {
+            Ok(mut data) => unsafe { data.as_mut() },
+            // the option length is checked when created, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Debug for GeneveOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOption")
+            .field("option_class", &format!("0x{:04x}", self.option_class()))
+            .field("option_type", &format!("0x{:02x}", self.option_type()))
+            .field("length", &self.length())
+            .field("data", &self.data())
+            .field("$offset", &self.offset)
+            .field("$len", &(self.end_offset() - self.offset))
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the options in the Geneve header
+/// immutably.
+pub struct ImmutableGeneveOptionsIterator<'a> {
+    mbuf: Mbuf,
+    offset: usize,
+    end: usize,
+    _phantom: PhantomData<&'a Mbuf>,
+}
+
+impl ImmutableGeneveOptionsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<Immutable<'_, GeneveOption<'_>>>> {
+        if self.end > self.offset {
+            match GeneveOption::new(&mut self.mbuf, self.offset, self.end) {
+                Ok(option) => {
+                    // advances the offset to the next option
+                    self.offset = option.end_offset();
+                    Ok(Some(Immutable::new(option)))
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Debug for ImmutableGeneveOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImmutableGeneveOptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the options in the Geneve header
+/// mutably.
+pub struct MutableGeneveOptionsIterator<'a> {
+    mbuf: &'a mut Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl MutableGeneveOptionsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<GeneveOption<'_>>> {
+        if self.end > self.offset {
+            match GeneveOption::new(&mut self.mbuf, self.offset, self.end) {
+                Ok(option) => {
+                    // advances the offset to the next option
+                    self.offset = option.end_offset();
+                    Ok(Some(option))
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Debug for MutableGeneveOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MutableGeneveOptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Options in the Geneve header.
+///
+/// Adding or removing options also updates the options length in the
+/// Geneve header.
+pub struct GeneveOptions<'a> {
+    mbuf: &'a mut Mbuf,
+    header: NonNull<GeneveHeader>,
+    offset: usize,
+}
+
+impl GeneveOptions<'_> {
+    #[inline]
+    fn header_mut(&mut self) -> &mut GeneveHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        let header = unsafe { self.header.as_ref() };
+        (header.ver_opt_len & GENEVE_OPT_LEN_MASK) as usize * 4
+    }
+
+    #[inline]
+    fn set_len(&mut self, len: usize) {
+        let header = self.header_mut();
+        header.ver_opt_len =
+            (header.ver_opt_len & GENEVE_VER_MASK) | ((len / 4) as u8 & GENEVE_OPT_LEN_MASK);
+    }
+
+    /// Returns an iterator that allows modifying each option.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let mut options = geneve.options_mut();
+    /// let mut iter = options.iter();
+    ///
+    /// while let Some(mut option) = iter.next()? {
+    ///     option.data_mut()[0] = 0;
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter(&mut self) -> MutableGeneveOptionsIterator<'_> {
+        let end = self.offset + self.len();
+        MutableGeneveOptionsIterator {
+            mbuf: self.mbuf,
+            offset: self.offset,
+            end,
+        }
+    }
+
+    /// Inserts a new option at offset.
+    fn insert(
+        &mut self,
+        offset: usize,
+        option_class: u16,
+        option_type: u8,
+        data: &[u8],
+    ) -> Fallible<GeneveOption<'_>> {
+        ensure!(
+            data.len() % 4 == 0 && data.len() <= GENEVE_MAX_OPTION_DATA_LEN,
+            GeneveError::InvalidOptionLength(data.len())
+        );
+
+        let len = GeneveOptionHeader::size_of() + data.len();
+        let options_len = self.len() + len;
+        ensure!(
+            options_len <= GENEVE_MAX_OPTIONS_LEN,
+            GeneveError::OptionsTooLong(options_len)
+        );
+
+        self.mbuf.extend(offset, len)?;
+        let tuple = self.mbuf.write_data(
+            offset,
+            &GeneveOptionHeader {
+                option_class: option_class.into(),
+                option_type,
+                length: (data.len() / 4) as u8,
+            },
+        )?;
+        if !data.is_empty() {
+            self.mbuf
+                .write_data_slice(offset + GeneveOptionHeader::size_of(), data)?;
+        }
+        self.set_len(options_len);
+
+        Ok(GeneveOption {
+            mbuf: self.mbuf,
+            tuple,
+            offset,
+        })
+    }
+
+    /// Prepends a new option at the beginning of the options.
+    ///
+    /// The length of `data` must be a multiple of 4 octets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let mut options = geneve.options_mut();
+    /// let _ = options.prepend(0x0102, 0x80, &[0xde, 0xad, 0xbe, 0xef])?;
+    /// ```
+    #[inline]
+    pub fn prepend(
+        &mut self,
+        option_class: u16,
+        option_type: u8,
+        data: &[u8],
+    ) -> Fallible<GeneveOption<'_>> {
+        self.insert(self.offset, option_class, option_type, data)
+    }
+
+    /// Appends a new option at the end of the options.
+    ///
+    /// The length of `data` must be a multiple of 4 octets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let mut options = geneve.options_mut();
+    /// let _ = options.append(0x0102, 0x80, &[0xde, 0xad, 0xbe, 0xef])?;
+    /// ```
+    #[inline]
+    pub fn append(
+        &mut self,
+        option_class: u16,
+        option_type: u8,
+        data: &[u8],
+    ) -> Fallible<GeneveOption<'_>> {
+        self.insert(self.offset + self.len(), option_class, option_type, data)
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// If an error occurs, all removals done prior to the error cannot be
+    /// undone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut geneve = udp.parse::<Geneve<Ipv4>>()?;
+    /// let mut options = geneve.options_mut();
+    /// let _ = options.retain(|option| !option.critical());
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F) -> Fallible<()>
+    where
+        F: FnMut(&GeneveOption<'_>) -> bool,
+    {
+        let mut offset = self.offset;
+        while self.offset + self.len() > offset {
+            let end = self.offset + self.len();
+            let option = GeneveOption::new(self.mbuf, offset, end)?;
+            if !f(&option) {
+                let len = option.end_offset() - offset;
+                self.mbuf.shrink(offset, len)?;
+                let options_len = self.len() - len;
+                self.set_len(options_len);
+            } else {
+                offset = option.end_offset();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GeneveOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOptions")
+            .field("offset", &self.offset)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Geneve related errors.
+#[derive(Debug, Fail)]
+pub enum GeneveError {
+    /// Error indicating the option data length is not a multiple of 4
+    /// octets or exceeds the maximum.
+    #[fail(
+        display = "Option data length {} is not a multiple of 4 or exceeds 124.",
+        _0
+    )]
+    InvalidOptionLength(usize),
+
+    /// Error indicating the options exceed the maximum length.
+    #[fail(display = "Options length {} exceeds the maximum of 252.", _0)]
+    OptionsTooLong(usize),
+}
+
+/// Geneve header.
+///
+/// The header only include the fixed portion of the Geneve header.
+/// The options are parsed separately.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GeneveHeader {
+    ver_opt_len: u8,
+    flags: u8,
+    protocol_type: u16be,
+    vni: u32be,
+}
+
+impl Default for GeneveHeader {
+    fn default() -> GeneveHeader {
+        GeneveHeader {
+            ver_opt_len: 0,
+            flags: 0,
+            protocol_type: EtherTypes::Teb.0.into(),
+            vni: u32be::default(),
+        }
+    }
+}
+
+/// Geneve option header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GeneveOptionHeader {
+    option_class: u16be,
+    option_type: u8,
+    length: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::{Arp, OperationCodes};
+    use crate::testils::byte_arrays::{ARP_REQUEST_PACKET, GENEVE_PACKET, IPV4_UDP_PACKET};
+
+    #[test]
+    fn size_of_geneve_header() {
+        assert_eq!(8, GeneveHeader::size_of());
+        assert_eq!(4, GeneveOptionHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_packet() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+
+        assert_eq!(0, geneve.version());
+        assert_eq!(8, geneve.options_len());
+        assert_eq!(16, geneve.header_len());
+        assert!(!geneve.oam());
+        assert!(geneve.critical());
+        assert_eq!(EtherTypes::Teb, geneve.protocol_type());
+        assert_eq!(100, geneve.vni());
+
+        // the payload is the inner frame
+        let inner = geneve.parse::<Ethernet<Geneve<Ipv4>>>().unwrap();
+        assert_eq!(EtherTypes::Arp, inner.ether_type());
+        let arp = inner.parse::<Arp<Ethernet<Geneve<Ipv4>>>>().unwrap();
+        assert_eq!(OperationCodes::Request, arp.operation());
+    }
+
+    #[capsule::test]
+    fn parse_non_geneve_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Geneve<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn iterate_geneve_options() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+
+        let mut iter = geneve.options_iter();
+        let option = iter.next().unwrap().unwrap();
+        assert_eq!(0x0102, option.option_class());
+        assert_eq!(0x80, option.option_type());
+        assert!(option.critical());
+        assert_eq!(1, option.length());
+        assert_eq!(&[0xde, 0xad, 0xbe, 0xef], option.data());
+
+        // stops at the end of the options, not the end of the packet
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn modify_geneve_option() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+
+        let mut options = geneve.options_mut();
+        let mut iter = options.iter();
+        let mut option = iter.next().unwrap().unwrap();
+        option.set_option_type(0x01);
+        option.data_mut().copy_from_slice(&[1, 2, 3, 4]);
+
+        let mut iter = geneve.options_iter();
+        let option = iter.next().unwrap().unwrap();
+        assert!(!option.critical());
+        assert_eq!(&[1, 2, 3, 4], option.data());
+    }
+
+    #[capsule::test]
+    fn add_and_remove_geneve_options() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let mut geneve = udp.parse::<Geneve<Ipv4>>().unwrap();
+
+        let mut options = geneve.options_mut();
+        assert!(options.prepend(0x0103, 0x01, &[]).is_ok());
+        assert!(options.append(0x0104, 0x02, &[0; 8]).is_ok());
+        assert!(options.append(0x0104, 0x02, &[0; 3]).is_err());
+        assert_eq!(24, geneve.options_len());
+
+        let mut iter = geneve.options_iter();
+        assert_eq!(0x0103, iter.next().unwrap().unwrap().option_class());
+        assert_eq!(0x0102, iter.next().unwrap().unwrap().option_class());
+        assert_eq!(0x0104, iter.next().unwrap().unwrap().option_class());
+        assert!(iter.next().unwrap().is_none());
+
+        // the payload is still intact
+        let inner = geneve.peek::<Ethernet<Geneve<Ipv4>>>().unwrap();
+        assert_eq!(EtherTypes::Arp, inner.ether_type());
+
+        let mut options = geneve.options_mut();
+        assert!(options.retain(|option| !option.critical()).is_ok());
+        assert_eq!(16, geneve.options_len());
+
+        geneve.reconcile_all();
+        assert!(!geneve.critical());
+    }
+
+    #[capsule::test]
+    fn push_geneve_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut geneve = udp.push::<Geneve<Ipv4>>().unwrap();
+
+        assert_eq!(GeneveHeader::size_of(), geneve.len());
+        assert_eq!(0, geneve.options_len());
+        assert_eq!(EtherTypes::Teb, geneve.protocol_type());
+
+        geneve.set_vni(0x0123_4567);
+        assert_eq!(0x23_4567, geneve.vni());
+        geneve.set_oam(true);
+        assert!(geneve.oam());
+
+        // make sure the port is fixed
+        assert_eq!(GENEVE_PORT, geneve.envelope().dst_port());
+    }
+
+    #[capsule::test]
+    fn encap_and_decap() {
+        let frame = Mbuf::from_bytes(&ARP_REQUEST_PACKET).unwrap();
+        let mut geneve = Geneve::<Ipv4>::encap(frame, 100).unwrap();
+
+        let mut options = geneve.options_mut();
+        assert!(options
+            .append(0x0102, 0x80, &[0xde, 0xad, 0xbe, 0xef])
+            .is_ok());
+        geneve.reconcile_all();
+
+        assert!(geneve.critical());
+        assert_eq!(ARP_REQUEST_PACKET.len(), geneve.payload_len());
+        let udp = geneve.envelope();
+        assert_eq!(udp.len() as u16, udp.length());
+        assert_eq!(udp.envelope().len() as u16, udp.envelope().total_length());
+        assert_eq!(GENEVE_PACKET.len(), geneve.mbuf().data_len());
+
+        let inner = geneve.decap().unwrap();
+        assert_eq!(ARP_REQUEST_PACKET.len(), inner.len());
+        let bytes = inner
+            .mbuf()
+            .read_data_slice::<u8>(0, ARP_REQUEST_PACKET.len())
+            .unwrap();
+        assert_eq!(&ARP_REQUEST_PACKET[..], unsafe { bytes.as_ref() });
+    }
+}
@@ -0,0 +1,1067 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{
+    EtherType, EtherTypeEnvelope, EtherTypes, Ethernet, Internal, Packet, ParseError, Udp,
+};
+use crate::{ensure, Mbuf, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port for GTP-U.
+pub const GTPU_PORT: u16 = 2152;
+
+// The version and flags in the first octet of the header.
+const GTPU_VERSION_MASK: u8 = 0xe0;
+const GTPU_FLAG_PT: u8 = 0x10;
+const GTPU_FLAG_E: u8 = 0x04;
+const GTPU_FLAG_S: u8 = 0x02;
+const GTPU_FLAG_PN: u8 = 0x01;
+
+// The sequence number, N-PDU number and next extension header type are
+// present when any one of the E, S or PN flags is set.
+const GTPU_OPTIONAL_FLAGS: u8 = GTPU_FLAG_E | GTPU_FLAG_S | GTPU_FLAG_PN;
+
+/// GPRS Tunnelling Protocol User Plane packet based on [3GPP TS 29.281].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Version|P|R|E|S|N| Message Type  |            Length             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                Tunnel Endpoint Identifier (TEID)              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Sequence Number        | N-PDU Number  | Next Ext Type |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*:        3-bit version number, must be 1.
+///
+/// - *PT*:             Protocol type, must be 1 for GTP.
+///
+/// - *E*:              Extension header flag. The next extension header
+///                     type field is meaningful if set.
+///
+/// - *S*:              Sequence number flag. The sequence number field
+///                     is meaningful if set.
+///
+/// - *PN*:             N-PDU number flag. The N-PDU number field is
+///                     meaningful if set.
+///
+/// - *Message Type*:   Type of the GTP-U message.
+///
+/// - *Length*:         Length of the payload in octets, not including the
+///                     mandatory 8-byte header. The optional fields and the
+///                     extension headers are part of the payload.
+///
+/// - *TEID*:           Tunnel endpoint identifier of the receiving GTP-U
+///                     protocol entity.
+///
+/// The last 4 octets are present if any one of the E, S or PN flags is
+/// set. They may be followed by a chain of extension headers, each
+/// starting with a length in units of 4 octets and ending with the type
+/// of the next extension header.
+///
+/// # Payload
+///
+/// The payload of a [`G-PDU`] is an IPv4 or IPv6 packet. The IP version is
+/// inferred from the first nibble of the payload, so it can be parsed with
+/// `gtpu.parse::<Ipv4<GtpU<E>>>()` or `gtpu.parse::<Ipv6<GtpU<E>>>()`.
+///
+/// [3GPP TS 29.281]: https://www.3gpp.org/ftp/Specs/archive/29_series/29.281/
+/// [`G-PDU`]: GtpUMessageTypes::GPdu
+pub struct GtpU<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GtpUHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> GtpU<E> {
+    #[inline]
+    fn header(&self) -> &GtpUHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GtpUHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().flags & GTPU_VERSION_MASK) >> 5
+    }
+
+    /// Returns the flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags & !GTPU_VERSION_MASK
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn message_type(&self) -> GtpUMessageType {
+        GtpUMessageType(self.header().message_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_message_type(&mut self, message_type: GtpUMessageType) {
+        self.header_mut().message_type = message_type.0;
+    }
+
+    /// Returns the length of the payload, including the optional fields
+    /// and the extension headers.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header().length.into()
+    }
+
+    /// Returns the tunnel endpoint identifier.
+    #[inline]
+    pub fn teid(&self) -> u32 {
+        self.header().teid.into()
+    }
+
+    /// Sets the tunnel endpoint identifier.
+    #[inline]
+    pub fn set_teid(&mut self, teid: u32) {
+        self.header_mut().teid = teid.into();
+    }
+
+    #[inline]
+    fn optional_fields_offset(&self) -> usize {
+        self.offset + GtpUHeader::size_of()
+    }
+
+    #[inline]
+    fn optional_fields(&self) -> Option<NonNull<GtpUOptionalFields>> {
+        if self.flags() & GTPU_OPTIONAL_FLAGS != 0 {
+            match self.mbuf().read_data(self.optional_fields_offset()) {
+                Ok(fields) => Some(fields),
+                // the header length is checked when parsed, should never run out
+                Err(_) => unreachable!(),
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Sets or clears one of the E, S or PN flags, adding or removing the
+    /// optional fields as needed.
+    #[inline]
+    fn set_optional_flag(&mut self, flag: u8, on: bool) -> Fallible<()> {
+        let flags = if on {
+            self.header().flags | flag
+        } else {
+            self.header().flags & !flag
+        };
+
+        let had_fields = self.flags() & GTPU_OPTIONAL_FLAGS != 0;
+        let has_fields = flags & GTPU_OPTIONAL_FLAGS != 0;
+        let offset = self.optional_fields_offset();
+
+        if !had_fields && has_fields {
+            let mbuf = self.mbuf_mut();
+            mbuf.extend(offset, GtpUOptionalFields::size_of())?;
+            let _ = mbuf.write_data(offset, &GtpUOptionalFields::default())?;
+        } else if had_fields && !has_fields {
+            self.mbuf_mut()
+                .shrink(offset, GtpUOptionalFields::size_of())?;
+        }
+
+        self.header_mut().flags = flags;
+        Ok(())
+    }
+
+    /// Returns the sequence number if the S flag is set.
+    #[inline]
+    pub fn sequence_no(&self) -> Option<u16> {
+        if self.flags() & GTPU_FLAG_S != 0 {
+            self.optional_fields()
+                .map(|fields| unsafe { fields.as_ref() }.sequence_no.into())
+        } else {
+            None
+        }
+    }
+
+    /// Sets the sequence number. The S flag is set or cleared depending on
+    /// whether `sequence_no` is `Some` or `None`.
+    #[inline]
+    pub fn set_sequence_no(&mut self, sequence_no: Option<u16>) -> Fallible<()> {
+        self.set_optional_flag(GTPU_FLAG_S, sequence_no.is_some())?;
+        if let Some(mut fields) = self.optional_fields() {
+            unsafe { fields.as_mut() }.sequence_no = sequence_no.unwrap_or_default().into();
+        }
+        Ok(())
+    }
+
+    /// Returns the N-PDU number if the PN flag is set.
+    #[inline]
+    pub fn n_pdu_no(&self) -> Option<u8> {
+        if self.flags() & GTPU_FLAG_PN != 0 {
+            self.optional_fields()
+                .map(|fields| unsafe { fields.as_ref() }.n_pdu_no)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the N-PDU number. The PN flag is set or cleared depending on
+    /// whether `n_pdu_no` is `Some` or `None`.
+    #[inline]
+    pub fn set_n_pdu_no(&mut self, n_pdu_no: Option<u8>) -> Fallible<()> {
+        self.set_optional_flag(GTPU_FLAG_PN, n_pdu_no.is_some())?;
+        if let Some(mut fields) = self.optional_fields() {
+            unsafe { fields.as_mut() }.n_pdu_no = n_pdu_no.unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    /// Walks the chain of extension headers. Returns the offset of the
+    /// last next extension header type field and the end offset of the
+    /// chain.
+    ///
+    /// Should only be invoked if the E flag is set.
+    fn walk_extension_headers(&self) -> Fallible<(usize, usize)> {
+        let mbuf = self.mbuf();
+        let mut next_type_offset = self.optional_fields_offset() + 3;
+        let mut offset = next_type_offset + 1;
+
+        while read_u8(mbuf, next_type_offset)? != 0 {
+            let len = read_u8(mbuf, offset)? as usize * 4;
+            ensure!(
+                len > 0,
+                ParseError::new("Invalid GTP-U extension header length.")
+            );
+            next_type_offset = offset + len - 1;
+            offset += len;
+        }
+
+        Ok((next_type_offset, offset))
+    }
+
+    /// Returns an iterator to read the extension headers in the packet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let gtpu = udp.parse::<GtpU<Ipv4>>()?;
+    /// let mut iter = gtpu.extension_headers_iter();
+    ///
+    /// while let Some(extension) = iter.next()? {
+    ///     println!("{:?}", extension);
+    /// }
+    /// ```
+    #[inline]
+    pub fn extension_headers_iter(&self) -> GtpUExtensionHeadersIterator<'_> {
+        let next_type = if self.flags() & GTPU_FLAG_E != 0 {
+            self.optional_fields()
+                .map(|fields| unsafe { fields.as_ref() }.next_ext_type)
+                .unwrap_or_default()
+        } else {
+            0
+        };
+
+        GtpUExtensionHeadersIterator {
+            mbuf: unsafe { self.mbuf().clone(Internal(())) },
+            offset: self.optional_fields_offset() + GtpUOptionalFields::size_of(),
+            next_type,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Appends an extension header to the end of the extension header
+    /// chain.
+    ///
+    /// The `content` excludes the length and the next extension header
+    /// type fields, so its length plus 2 must be a multiple of 4 octets.
+    /// The E flag is set.
+    pub fn add_extension_header(
+        &mut self,
+        ext_type: GtpUExtensionHeaderType,
+        content: &[u8],
+    ) -> Fallible<()> {
+        let len = content.len() + 2;
+        ensure!(
+            len % 4 == 0 && len <= u8::max_value() as usize * 4,
+            GtpUError::InvalidExtensionHeaderLength(content.len())
+        );
+
+        if self.flags() & GTPU_FLAG_E == 0 {
+            self.set_optional_flag(GTPU_FLAG_E, true)?;
+            // the field is ignored when the E flag is not set, clear it
+            // in case it has some garbage value.
+            if let Some(mut fields) = self.optional_fields() {
+                unsafe { fields.as_mut() }.next_ext_type = 0;
+            }
+        }
+
+        let (next_type_offset, offset) = self.walk_extension_headers()?;
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, len)?;
+        let _ = mbuf.write_data(offset, &((len / 4) as u8))?;
+        if !content.is_empty() {
+            let _ = mbuf.write_data_slice(offset + 1, content)?;
+        }
+        let _ = mbuf.write_data(offset + len - 1, &0u8)?;
+        let _ = mbuf.write_data(next_type_offset, &ext_type.0)?;
+
+        Ok(())
+    }
+
+    /// Removes all the extension headers. The E flag is cleared.
+    pub fn remove_extension_headers(&mut self) -> Fallible<()> {
+        if self.flags() & GTPU_FLAG_E != 0 {
+            let (_, end) = self.walk_extension_headers()?;
+            let offset = self.optional_fields_offset() + GtpUOptionalFields::size_of();
+            self.mbuf_mut().shrink(offset, end - offset)?;
+            if let Some(mut fields) = self.optional_fields() {
+                unsafe { fields.as_mut() }.next_ext_type = 0;
+            }
+            self.set_optional_flag(GTPU_FLAG_E, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the PDU session container extension header if present.
+    #[inline]
+    pub fn pdu_session_container(&self) -> Option<PduSessionContainer> {
+        let mut iter = self.extension_headers_iter();
+        while let Ok(Some(extension)) = iter.next() {
+            if extension.ext_type() == GtpUExtensionHeaderTypes::PduSessionContainer {
+                return PduSessionContainer::from_content(extension.content());
+            }
+        }
+
+        None
+    }
+
+    /// Appends a PDU session container extension header with the PDU type
+    /// and the QoS flow identifier.
+    #[inline]
+    pub fn add_pdu_session_container(&mut self, container: PduSessionContainer) -> Fallible<()> {
+        self.add_extension_header(
+            GtpUExtensionHeaderTypes::PduSessionContainer,
+            &container.to_content(),
+        )
+    }
+}
+
+impl<E: IpPacket + Packet<Envelope = Ethernet>> GtpU<E> {
+    /// Encapsulates the IP packet in the Ethernet frame in outer IP, UDP
+    /// and GTP-U headers.
+    ///
+    /// The outer headers are inserted between the Ethernet header and the
+    /// IP packet. The message type is set to [`G-PDU`] and the lengths and
+    /// checksums are reconciled. The outer addresses are left unset. After
+    /// setting them, call [`reconcile_all`] again to fix the checksums.
+    ///
+    /// [`G-PDU`]: GtpUMessageTypes::GPdu
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(ethernet: Ethernet, teid: u32) -> Fallible<Self> {
+        ensure!(
+            ethernet.ether_type() == EtherTypes::Ipv4 || ethernet.ether_type() == EtherTypes::Ipv6,
+            ParseError::new("not an IP packet.")
+        );
+
+        let ip = ethernet.push::<E>()?;
+        let udp = ip.push::<Udp<E>>()?;
+        let mut gtpu = udp.push::<GtpU<E>>()?;
+        gtpu.set_teid(teid);
+        gtpu.reconcile_all();
+        Ok(gtpu)
+    }
+
+    /// Removes the outer IP, UDP and GTP-U headers, and returns the
+    /// Ethernet frame with the inner IP packet as its payload.
+    ///
+    /// The frame's ether type is set based on the inner IP version. The
+    /// inner packet is untouched and doesn't need to be reconciled.
+    #[inline]
+    pub fn decap(self) -> Fallible<Ethernet> {
+        let ether_type = self.ether_type();
+        ensure!(
+            ether_type == EtherTypes::Ipv4 || ether_type == EtherTypes::Ipv6,
+            ParseError::new("not a GTP-U packet carrying an IP packet.")
+        );
+
+        let offset = self.envelope().envelope().offset();
+        let payload_offset = self.payload_offset();
+        let mut ethernet = self.deparse().deparse().deparse();
+        ethernet
+            .mbuf_mut()
+            .shrink(offset, payload_offset - offset)?;
+        ethernet.set_ether_type(ether_type);
+        Ok(ethernet)
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for GtpU<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gtpu")
+            .field("version", &self.version())
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("message_type", &format!("{}", self.message_type()))
+            .field("length", &self.length())
+            .field("teid", &format!("0x{:08x}", self.teid()))
+            .field("sequence_no", &self.sequence_no())
+            .field("n_pdu_no", &self.n_pdu_no())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for GtpU<E> {
+    /// The preceding type for a GTP-U packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the GTP-U header includes the optional fields and
+    /// the extension headers.
+    #[inline]
+    fn header_len(&self) -> usize {
+        if self.flags() & GTPU_FLAG_E != 0 {
+            match self.walk_extension_headers() {
+                Ok((_, end)) => end - self.offset,
+                // the extension headers are checked when parsed
+                Err(_) => unreachable!(),
+            }
+        } else if self.flags() & GTPU_OPTIONAL_FLAGS != 0 {
+            GtpUHeader::size_of() + GtpUOptionalFields::size_of()
+        } else {
+            GtpUHeader::size_of()
+        }
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        GtpU::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a GTP-U packet.
+    ///
+    /// [`Udp::dst_port`] must be set to [`GTPU_PORT`], the version must be
+    /// 1 and the PT flag must be set. Otherwise a parsing error is
+    /// returned.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`GTPU_PORT`]: GTPU_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.dst_port() == GTPU_PORT,
+            ParseError::new("not a GTP-U packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = GtpU {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.version() == 1 && packet.flags() & GTPU_FLAG_PT != 0,
+            ParseError::new("not a GTPv1-U packet.")
+        );
+
+        // makes sure the optional fields and the extension headers are
+        // all within the buffer.
+        if packet.flags() & GTPU_FLAG_E != 0 {
+            let _ = packet.walk_extension_headers()?;
+        } else if packet.flags() & GTPU_OPTIONAL_FLAGS != 0 {
+            let _ = packet
+                .mbuf()
+                .read_data::<GtpUOptionalFields>(packet.optional_fields_offset())?;
+        }
+
+        Ok(packet)
+    }
+
+    /// Prepends a GTP-U packet to the beginning of the UDP packet's payload.
+    ///
+    /// [`Udp::dst_port`] is set to [`GTPU_PORT`] and the message type is
+    /// set to [`G-PDU`]. None of the optional fields are present.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`GTPU_PORT`]: GTPU_PORT
+    /// [`G-PDU`]: GtpUMessageTypes::GPdu
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GtpUHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GtpUHeader::default())?;
+
+        envelope.set_dst_port(GTPU_PORT);
+
+        Ok(GtpU {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`length`] is set to the length of everything after the mandatory
+    /// 8-byte header.
+    ///
+    /// [`length`]: GtpU::length
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = (self.len() - GtpUHeader::size_of()) as u16;
+        self.header_mut().length = len.into();
+    }
+}
+
+impl<E: IpPacket> EtherTypeEnvelope for GtpU<E> {
+    /// Returns the ether type of the payload, inferred from the IP version
+    /// in the first nibble of the payload.
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        match read_u8(self.mbuf(), self.payload_offset()) {
+            Ok(byte) if byte >> 4 == 4 => EtherTypes::Ipv4,
+            Ok(byte) if byte >> 4 == 6 => EtherTypes::Ipv6,
+            _ => EtherType::new(0),
+        }
+    }
+
+    /// GTP-U has no protocol type field. The ether type is always inferred
+    /// from the payload, so this is a no-op.
+    #[inline]
+    fn set_ether_type(&mut self, _ether_type: EtherType) {}
+}
+
+/// Reads a single octet from the buffer at offset.
+#[inline]
+fn read_u8(mbuf: &Mbuf, offset: usize) -> Fallible<u8> {
+    let value = mbuf.read_data::<u8>(offset)?;
+    Ok(unsafe { *value.as_ref() })
+}
+
+/// GTP-U message type.
+///
+/// A list of supported types is under [`GtpUMessageTypes`].
+///
+/// [`GtpUMessageTypes`]: GtpUMessageTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct GtpUMessageType(pub u8);
+
+/// Supported GTP-U message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GtpUMessageTypes {
+    use super::GtpUMessageType;
+
+    /// Echo Request.
+    pub const EchoRequest: GtpUMessageType = GtpUMessageType(1);
+    /// Echo Response.
+    pub const EchoResponse: GtpUMessageType = GtpUMessageType(2);
+    /// Error Indication.
+    pub const ErrorIndication: GtpUMessageType = GtpUMessageType(26);
+    /// Supported Extension Headers Notification.
+    pub const SupportedExtensionHeadersNotification: GtpUMessageType = GtpUMessageType(31);
+    /// End Marker.
+    pub const EndMarker: GtpUMessageType = GtpUMessageType(254);
+    /// G-PDU, carrying a user plane packet.
+    pub const GPdu: GtpUMessageType = GtpUMessageType(255);
+}
+
+impl fmt::Display for GtpUMessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GtpUMessageTypes::EchoRequest => "Echo Request".to_string(),
+                GtpUMessageTypes::EchoResponse => "Echo Response".to_string(),
+                GtpUMessageTypes::ErrorIndication => "Error Indication".to_string(),
+                GtpUMessageTypes::SupportedExtensionHeadersNotification =>
+                    "Supported Extension Headers Notification".to_string(),
+                GtpUMessageTypes::EndMarker => "End Marker".to_string(),
+                GtpUMessageTypes::GPdu => "G-PDU".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// GTP-U extension header type.
+///
+/// A list of supported types is under [`GtpUExtensionHeaderTypes`].
+///
+/// [`GtpUExtensionHeaderTypes`]: GtpUExtensionHeaderTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct GtpUExtensionHeaderType(pub u8);
+
+/// Supported GTP-U extension header types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GtpUExtensionHeaderTypes {
+    use super::GtpUExtensionHeaderType;
+
+    /// UDP Port.
+    pub const UdpPort: GtpUExtensionHeaderType = GtpUExtensionHeaderType(0x40);
+    /// PDU Session Container.
+    pub const PduSessionContainer: GtpUExtensionHeaderType = GtpUExtensionHeaderType(0x85);
+    /// PDCP PDU Number.
+    pub const PdcpPduNumber: GtpUExtensionHeaderType = GtpUExtensionHeaderType(0xc0);
+}
+
+impl fmt::Display for GtpUExtensionHeaderType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GtpUExtensionHeaderTypes::UdpPort => "UDP Port".to_string(),
+                GtpUExtensionHeaderTypes::PduSessionContainer =>
+                    "PDU Session Container".to_string(),
+                GtpUExtensionHeaderTypes::PdcpPduNumber => "PDCP PDU Number".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// A GTP-U extension header.
+pub struct GtpUExtensionHeader<'a> {
+    mbuf: &'a Mbuf,
+    ext_type: GtpUExtensionHeaderType,
+    offset: usize,
+    len: usize,
+}
+
+impl GtpUExtensionHeader<'_> {
+    /// Returns the extension header type.
+    #[inline]
+    pub fn ext_type(&self) -> GtpUExtensionHeaderType {
+        self.ext_type
+    }
+
+    /// Returns the length of the extension header in units of 4 octets.
+    #[inline]
+    pub fn length(&self) -> u8 {
+        (self.len / 4) as u8
+    }
+
+    /// Returns the content of the extension header, excluding the length
+    /// and the next extension header type fields.
+    #[inline]
+    pub fn content(&self) -> &[u8] {
+        match self
+            .mbuf
+            .read_data_slice::<u8>(self.offset + 1, self.len - 2)
+        {
+            Ok(content) => unsafe { content.as_ref() },
+            // the length is checked when the iterator advances
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the type of the next extension header, or 0 if this is the
+    /// last one.
+    #[inline]
+    pub fn next_type(&self) -> GtpUExtensionHeaderType {
+        match read_u8(self.mbuf, self.offset + self.len - 1) {
+            Ok(next_type) => GtpUExtensionHeaderType(next_type),
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Debug for GtpUExtensionHeader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GtpUExtensionHeader")
+            .field("ext_type", &format!("{}", self.ext_type()))
+            .field("length", &self.length())
+            .field("content", &self.content())
+            .field("$offset", &self.offset)
+            .field("$len", &self.len)
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the GTP-U extension headers.
+pub struct GtpUExtensionHeadersIterator<'a> {
+    mbuf: Mbuf,
+    offset: usize,
+    next_type: u8,
+    _phantom: PhantomData<&'a Mbuf>,
+}
+
+impl GtpUExtensionHeadersIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<GtpUExtensionHeader<'_>>> {
+        if self.next_type == 0 {
+            return Ok(None);
+        }
+
+        let len = read_u8(&self.mbuf, self.offset)? as usize * 4;
+        ensure!(
+            len > 0,
+            ParseError::new("Invalid GTP-U extension header length.")
+        );
+        let next_type = read_u8(&self.mbuf, self.offset + len - 1)?;
+
+        let extension = GtpUExtensionHeader {
+            mbuf: &self.mbuf,
+            ext_type: GtpUExtensionHeaderType(self.next_type),
+            offset: self.offset,
+            len,
+        };
+
+        // advances to the next extension header
+        self.offset += len;
+        self.next_type = next_type;
+
+        Ok(Some(extension))
+    }
+}
+
+impl fmt::Debug for GtpUExtensionHeadersIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GtpUExtensionHeadersIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// PDU type of a PDU session container.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PduType {
+    /// Downlink PDU session information.
+    Downlink,
+    /// Uplink PDU session information.
+    Uplink,
+}
+
+/// PDU session container extension header based on [3GPP TS 38.415].
+///
+/// Only the PDU type and the QoS flow identifier are supported.
+///
+/// [3GPP TS 38.415]: https://www.3gpp.org/ftp/Specs/archive/38_series/38.415/
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PduSessionContainer {
+    pdu_type: PduType,
+    qfi: u8,
+}
+
+impl PduSessionContainer {
+    /// Creates a new PDU session container. Only the lower 6 bits of `qfi`
+    /// are used.
+    #[inline]
+    pub fn new(pdu_type: PduType, qfi: u8) -> Self {
+        PduSessionContainer {
+            pdu_type,
+            qfi: qfi & 0x3f,
+        }
+    }
+
+    /// Returns the PDU type.
+    #[inline]
+    pub fn pdu_type(&self) -> PduType {
+        self.pdu_type
+    }
+
+    /// Returns the QoS flow identifier.
+    #[inline]
+    pub fn qfi(&self) -> u8 {
+        self.qfi
+    }
+
+    #[inline]
+    fn from_content(content: &[u8]) -> Option<Self> {
+        if content.len() < 2 {
+            return None;
+        }
+
+        let pdu_type = match content[0] >> 4 {
+            0 => PduType::Downlink,
+            1 => PduType::Uplink,
+            _ => return None,
+        };
+
+        Some(PduSessionContainer::new(pdu_type, content[1]))
+    }
+
+    #[inline]
+    fn to_content(self) -> [u8; 2] {
+        let pdu_type = match self.pdu_type {
+            PduType::Downlink => 0,
+            PduType::Uplink => 1,
+        };
+
+        [pdu_type << 4, self.qfi]
+    }
+}
+
+/// GTP-U related errors.
+#[derive(Debug, Fail)]
+pub enum GtpUError {
+    /// Error indicating the extension header content length is invalid.
+    #[fail(
+        display = "Extension header content length {} plus 2 is not a multiple of 4.",
+        _0
+    )]
+    InvalidExtensionHeaderLength(usize),
+}
+
+/// GTP-U header.
+///
+/// The header only include the mandatory 8 bytes of the GTP-U header.
+/// The optional fields and extension headers are parsed separately.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GtpUHeader {
+    flags: u8,
+    message_type: u8,
+    length: u16be,
+    teid: u32be,
+}
+
+impl Default for GtpUHeader {
+    fn default() -> GtpUHeader {
+        GtpUHeader {
+            // version 1 and PT set
+            flags: 0x30,
+            message_type: GtpUMessageTypes::GPdu.0,
+            length: u16be::default(),
+            teid: u32be::default(),
+        }
+    }
+}
+
+/// GTP-U optional fields.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GtpUOptionalFields {
+    sequence_no: u16be,
+    n_pdu_no: u8,
+    next_ext_type: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::testils::byte_arrays::{GTPU_PACKET, IPV4_UDP_PACKET};
+
+    #[test]
+    fn size_of_gtpu_header() {
+        assert_eq!(8, GtpUHeader::size_of());
+        assert_eq!(4, GtpUOptionalFields::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_gtpu_packet() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let gtpu = udp.parse::<GtpU<Ipv4>>().unwrap();
+
+        assert_eq!(1, gtpu.version());
+        assert_eq!(GtpUMessageTypes::GPdu, gtpu.message_type());
+        assert_eq!(46, gtpu.length());
+        assert_eq!(0x1234_5678, gtpu.teid());
+        assert_eq!(None, gtpu.sequence_no());
+        assert_eq!(None, gtpu.n_pdu_no());
+        assert_eq!(16, gtpu.header_len());
+
+        let container = gtpu.pdu_session_container().unwrap();
+        assert_eq!(PduType::Uplink, container.pdu_type());
+        assert_eq!(9, container.qfi());
+
+        // the payload is the inner IP packet
+        assert_eq!(EtherTypes::Ipv4, gtpu.ether_type());
+        let inner = gtpu.parse::<Ipv4<GtpU<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+        let udp = inner.parse::<Udp<Ipv4<GtpU<Ipv4>>>>().unwrap();
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_gtpu_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<GtpU<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn iterate_extension_headers() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let gtpu = udp.parse::<GtpU<Ipv4>>().unwrap();
+
+        let mut iter = gtpu.extension_headers_iter();
+        let extension = iter.next().unwrap().unwrap();
+        assert_eq!(
+            GtpUExtensionHeaderTypes::PduSessionContainer,
+            extension.ext_type()
+        );
+        assert_eq!(1, extension.length());
+        assert_eq!(&[0x10, 0x09], extension.content());
+        assert_eq!(GtpUExtensionHeaderType(0), extension.next_type());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn push_gtpu_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut gtpu = udp.push::<GtpU<Ipv4>>().unwrap();
+
+        assert_eq!(GtpUHeader::size_of(), gtpu.len());
+        assert_eq!(1, gtpu.version());
+        assert_eq!(GtpUMessageTypes::GPdu, gtpu.message_type());
+
+        gtpu.set_teid(42);
+        assert_eq!(42, gtpu.teid());
+
+        // make sure the port is fixed
+        assert_eq!(GTPU_PORT, gtpu.envelope().dst_port());
+    }
+
+    #[capsule::test]
+    fn set_optional_fields() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut gtpu = udp.push::<GtpU<Ipv4>>().unwrap();
+
+        assert!(gtpu.set_sequence_no(Some(1000)).is_ok());
+        assert_eq!(12, gtpu.header_len());
+        assert_eq!(Some(1000), gtpu.sequence_no());
+        assert_eq!(None, gtpu.n_pdu_no());
+
+        assert!(gtpu.set_n_pdu_no(Some(7)).is_ok());
+        assert_eq!(12, gtpu.header_len());
+        assert_eq!(Some(7), gtpu.n_pdu_no());
+
+        let container = PduSessionContainer::new(PduType::Downlink, 5);
+        assert!(gtpu.add_pdu_session_container(container).is_ok());
+        assert!(gtpu
+            .add_extension_header(GtpUExtensionHeaderTypes::UdpPort, &[0x08, 0x68])
+            .is_ok());
+        assert!(gtpu
+            .add_extension_header(GtpUExtensionHeaderTypes::UdpPort, &[0x08])
+            .is_err());
+        assert_eq!(20, gtpu.header_len());
+        assert_eq!(Some(container), gtpu.pdu_session_container());
+
+        let mut iter = gtpu.extension_headers_iter();
+        let first = iter.next().unwrap().unwrap();
+        assert_eq!(GtpUExtensionHeaderTypes::UdpPort, first.next_type());
+        let second = iter.next().unwrap().unwrap();
+        assert_eq!(GtpUExtensionHeaderTypes::UdpPort, second.ext_type());
+        assert!(iter.next().unwrap().is_none());
+
+        assert!(gtpu.remove_extension_headers().is_ok());
+        assert_eq!(12, gtpu.header_len());
+        assert_eq!(None, gtpu.pdu_session_container());
+
+        assert!(gtpu.set_sequence_no(None).is_ok());
+        assert!(gtpu.set_n_pdu_no(None).is_ok());
+        assert_eq!(8, gtpu.header_len());
+
+        gtpu.reconcile_all();
+        assert_eq!(0, gtpu.length());
+    }
+
+    #[capsule::test]
+    fn encap_and_decap() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut gtpu = GtpU::<Ipv4>::encap(ethernet, 0x1234_5678).unwrap();
+        let container = PduSessionContainer::new(PduType::Uplink, 9);
+        assert!(gtpu.add_pdu_session_container(container).is_ok());
+        gtpu.reconcile_all();
+
+        assert_eq!(0x1234_5678, gtpu.teid());
+        assert_eq!(gtpu.len() as u16 - 8, gtpu.length());
+        let udp = gtpu.envelope();
+        assert_eq!(udp.len() as u16, udp.length());
+        assert_eq!(udp.envelope().len() as u16, udp.envelope().total_length());
+        assert_eq!(GTPU_PACKET.len(), gtpu.mbuf().data_len());
+
+        let ethernet = gtpu.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let bytes = ethernet
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_UDP_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+}
@@ -0,0 +1,295 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v4::{
+    error_reply, Icmpv4, Icmpv4Message, Icmpv4Packet, Icmpv4Type, Icmpv4Types,
+};
+use crate::packets::ip::v4::{Ipv4, IPV4_MIN_MTU};
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet};
+use crate::SizeOf;
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Code for fragmentation needed and don't fragment was set.
+pub const FRAGMENTATION_NEEDED: u8 = 4;
+
+/// Destination Unreachable Message defined in [IETF RFC 792].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Code      |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           unused = 0          |         Next-Hop MTU          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Internet Header + 64 bits of Original Data Datagram        |
+/// ```
+///
+/// - *Code*:           0 = net unreachable;
+///                     1 = host unreachable;
+///                     2 = protocol unreachable;
+///                     3 = port unreachable;
+///                     4 = fragmentation needed and DF set;
+///                     5 = source route failed.
+///
+/// - *Next-Hop MTU*:   The MTU of the next-hop network when the code is
+///                     fragmentation needed, defined in [IETF RFC 1191].
+///                     Otherwise unused.
+///
+/// [IETF RFC 792]: https://tools.ietf.org/html/rfc792
+/// [IETF RFC 1191]: https://tools.ietf.org/html/rfc1191#section-4
+#[derive(Icmpv4Packet)]
+pub struct DestinationUnreachable {
+    icmp: Icmpv4,
+    body: NonNull<DestinationUnreachableBody>,
+}
+
+impl DestinationUnreachable {
+    /// Generates a fragmentation needed message in response to an invoking
+    /// packet that is too big for the next-hop network and has the don't
+    /// fragment flag set.
+    ///
+    /// `mtu` is the MTU of the next-hop network, used by the originator
+    /// for path MTU discovery. See [`error_reply`] for how the message is
+    /// addressed and when it is not generated.
+    ///
+    /// [`error_reply`]: crate::packets::icmp::v4::error_reply
+    pub fn fragmentation_needed(invoking: &Ipv4, mtu: u16) -> Fallible<Self> {
+        let mut message = error_reply::<DestinationUnreachable>(invoking, FRAGMENTATION_NEEDED)?;
+        message.set_next_hop_mtu(mtu);
+        message.reconcile_all();
+        Ok(message)
+    }
+
+    #[inline]
+    fn body(&self) -> &DestinationUnreachableBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut DestinationUnreachableBody {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the MTU of the next-hop network.
+    #[inline]
+    pub fn next_hop_mtu(&self) -> u16 {
+        self.body().next_hop_mtu.into()
+    }
+
+    /// Sets the MTU of the next-hop network.
+    #[inline]
+    pub fn set_next_hop_mtu(&mut self, mtu: u16) {
+        self.body_mut().next_hop_mtu = mtu.into();
+    }
+
+    /// Returns the offset where the data field in the message body starts.
+    #[inline]
+    fn data_offset(&self) -> usize {
+        self.payload_offset() + DestinationUnreachableBody::size_of()
+    }
+
+    /// Returns the length of the data field in the message body.
+    #[inline]
+    fn data_len(&self) -> usize {
+        self.payload_len() - DestinationUnreachableBody::size_of()
+    }
+
+    /// Returns the invoking packet as a `u8` slice.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        if let Ok(data) = self
+            .icmp()
+            .mbuf()
+            .read_data_slice(self.data_offset(), self.data_len())
+        {
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+}
+
+impl fmt::Debug for DestinationUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DestinationUnreachable")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("next_hop_mtu", &self.next_hop_mtu())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Icmpv4Message for DestinationUnreachable {
+    #[inline]
+    fn msg_type() -> Icmpv4Type {
+        Icmpv4Types::DestinationUnreachable
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv4 {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv4 {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv4 {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        DestinationUnreachable {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv4, _internal: Internal) -> Fallible<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(DestinationUnreachable { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv4, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, DestinationUnreachableBody::size_of())?;
+        let body = mbuf.write_data(offset, &DestinationUnreachableBody::default())?;
+
+        Ok(DestinationUnreachable { icmp, body })
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * the data field in the message body is trimmed if it exceeds the
+    /// [minimum IPV4 MTU], as we only need enough for port information.
+    /// * [`checksum`] is computed based on the `DestinationUnreachable`
+    /// message.
+    ///
+    /// [minimum IPv4 MTU]: IPV4_MIN_MTU
+    /// [`checksum`]: Icmpv4::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.data_len();
+        let offset = self.data_offset();
+
+        if len > IPV4_MIN_MTU {
+            let _ = self
+                .mbuf_mut()
+                .shrink(offset + IPV4_MIN_MTU, len - IPV4_MIN_MTU);
+        }
+
+        self.icmp_mut().compute_checksum();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct DestinationUnreachableBody {
+    _unused: u16be,
+    next_hop_mtu: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_TCP_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_destination_unreachable_body() {
+        assert_eq!(4, DestinationUnreachableBody::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_destination_unreachable() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp_len = ipv4.payload_len();
+
+        let mut unreachable = ipv4.push::<DestinationUnreachable>().unwrap();
+
+        assert_eq!(4, unreachable.header_len());
+        assert_eq!(
+            DestinationUnreachableBody::size_of() + tcp_len,
+            unreachable.payload_len()
+        );
+        assert_eq!(Icmpv4Types::DestinationUnreachable, unreachable.msg_type());
+        assert_eq!(0, unreachable.code());
+        assert_eq!(0, unreachable.next_hop_mtu());
+        assert_eq!(tcp_len, unreachable.data().len());
+
+        unreachable.set_code(FRAGMENTATION_NEEDED);
+        unreachable.set_next_hop_mtu(1400);
+        assert_eq!(FRAGMENTATION_NEEDED, unreachable.code());
+        assert_eq!(1400, unreachable.next_hop_mtu());
+
+        unreachable.reconcile_all();
+        assert!(unreachable.checksum() != 0);
+    }
+
+    #[capsule::test]
+    fn generate_fragmentation_needed() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let unreachable = DestinationUnreachable::fragmentation_needed(&ipv4, 1400).unwrap();
+
+        assert_eq!(FRAGMENTATION_NEEDED, unreachable.code());
+        assert_eq!(1400, unreachable.next_hop_mtu());
+        assert_eq!(ipv4.src(), unreachable.envelope().dst());
+        assert_eq!(ipv4.dst(), unreachable.envelope().src());
+        // quotes the ipv4 header and 64 bits of the tcp header.
+        assert_eq!(ipv4.header_len() + 8, unreachable.data().len());
+    }
+
+    #[capsule::test]
+    fn shrinks_to_ipv4_min_mtu() {
+        // starts with a buffer with a message body larger than min MTU.
+        let packet = Mbuf::from_bytes(&[42; 100]).unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut unreachable = ipv4.push::<DestinationUnreachable>().unwrap();
+        assert!(unreachable.data_len() > IPV4_MIN_MTU);
+
+        unreachable.reconcile_all();
+        assert_eq!(IPV4_MIN_MTU, unreachable.data_len());
+    }
+}
@@ -18,13 +18,17 @@
 
 //! Internet Control Message Protocol for IPv4.
 
+mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
+mod parameter_problem;
 mod redirect;
 mod time_exceeded;
 
+pub use self::destination_unreachable::*;
 pub use self::echo_reply::*;
 pub use self::echo_request::*;
+pub use self::parameter_problem::*;
 pub use self::redirect::*;
 pub use self::time_exceeded::*;
 pub use capsule_macros::Icmpv4Packet;
@@ -32,8 +36,8 @@ pub use capsule_macros::Icmpv4Packet;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::ProtocolNumbers;
 use crate::packets::types::u16be;
-use crate::packets::{checksum, Internal, Packet, ParseError};
-use crate::{ensure, SizeOf};
+use crate::packets::{checksum, Ethernet, Internal, Packet, ParseError};
+use crate::{ensure, Mbuf, SizeOf};
 use failure::{Fail, Fallible};
 use std::fmt;
 use std::ptr::NonNull;
@@ -290,6 +294,16 @@ pub mod Icmpv4Types {
     ///
     /// [Redirect]: crate::packets::icmp::v4::Redirect
     pub const Redirect: Icmpv4Type = Icmpv4Type(5);
+
+    /// Message type for [Destination Unreachable].
+    ///
+    /// [Destination Unreachable]: crate::packets::icmp::v4::DestinationUnreachable
+    pub const DestinationUnreachable: Icmpv4Type = Icmpv4Type(3);
+
+    /// Message type for [Parameter Problem].
+    ///
+    /// [Parameter Problem]: crate::packets::icmp::v4::ParameterProblem
+    pub const ParameterProblem: Icmpv4Type = Icmpv4Type(12);
 }
 
 impl fmt::Display for Icmpv4Type {
@@ -302,12 +316,117 @@ impl fmt::Display for Icmpv4Type {
                 Icmpv4Types::EchoReply => "Echo Reply".to_string(),
                 Icmpv4Types::TimeExceeded => "Time Exceeded".to_string(),
                 Icmpv4Types::Redirect => "Redirect".to_string(),
+                Icmpv4Types::DestinationUnreachable => "Destination Unreachable".to_string(),
+                Icmpv4Types::ParameterProblem => "Parameter Problem".to_string(),
                 _ => format!("{}", self.0),
             }
         )
     }
 }
 
+impl Icmpv4Type {
+    /// Returns a flag indicating whether the message type is an error
+    /// message, as opposed to an informational message.
+    #[inline]
+    pub fn is_error(self) -> bool {
+        matches!(
+            self,
+            Icmpv4Types::DestinationUnreachable
+                | Icmpv4Types::Redirect
+                | Icmpv4Types::TimeExceeded
+                | Icmpv4Types::ParameterProblem
+        )
+    }
+}
+
+/// The number of octets of the invoking packet's payload quoted in an
+/// ICMPv4 error message, defined in [IETF RFC 792].
+///
+/// [IETF RFC 792]: https://tools.ietf.org/html/rfc792
+const QUOTED_DATA_LEN: usize = 8;
+
+/// Generates an ICMPv4 error message of type `T` in response to the
+/// invoking packet.
+///
+/// The message is written into a new `Mbuf`. The Ethernet and IPv4
+/// addresses are the reverse of the invoking packet's, so the error is
+/// sent back to the originator from the address it tried to reach. The
+/// message quotes the invoking IPv4 header and the first 64 bits of its
+/// payload. The returned message is already reconciled; callers that
+/// change other fields, such as the source address of a router's own
+/// interface, should reconcile it again.
+///
+/// Returns an error if [IETF RFC 1122] forbids sending an ICMPv4 error
+/// in response to the invoking packet. That is when the invoking packet
+/// is itself an ICMPv4 error message, a non-initial fragment, or is
+/// addressed to a broadcast or multicast address.
+///
+/// # Example
+///
+/// ```
+/// let ipv4 = ethernet.parse::<Ipv4>()?;
+/// let unreachable = icmp::v4::error_reply::<DestinationUnreachable>(&ipv4, 3)?;
+/// ```
+///
+/// [IETF RFC 1122]: https://tools.ietf.org/html/rfc1122#section-3.2.2
+pub fn error_reply<T>(invoking: &Ipv4, code: u8) -> Fallible<T>
+where
+    T: Icmpv4Message + Icmpv4Packet + Packet<Envelope = Ipv4>,
+{
+    ensure!(
+        invoking.fragment_offset() == 0,
+        SuppressedIcmpv4Error("a non-initial fragment")
+    );
+
+    let dst = invoking.dst();
+    ensure!(
+        !dst.is_broadcast() && !dst.is_multicast(),
+        SuppressedIcmpv4Error("a broadcast or multicast packet")
+    );
+
+    if invoking.protocol() == ProtocolNumbers::Icmpv4 {
+        if let Ok(header) = invoking
+            .mbuf()
+            .read_data::<Icmpv4Header>(invoking.payload_offset())
+        {
+            let msg_type = Icmpv4Type::new(unsafe { header.as_ref() }.msg_type);
+            ensure!(
+                !msg_type.is_error(),
+                SuppressedIcmpv4Error("an ICMPv4 error message")
+            );
+        }
+    }
+
+    let len = (invoking.header_len() + QUOTED_DATA_LEN).min(invoking.len());
+    let quoted = invoking
+        .mbuf()
+        .read_data_slice::<u8>(invoking.offset(), len)?;
+    let quoted = unsafe { quoted.as_ref() };
+
+    let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+    ethernet.set_src(invoking.envelope().dst());
+    ethernet.set_dst(invoking.envelope().src());
+
+    let mut ipv4 = ethernet.push::<Ipv4>()?;
+    ipv4.set_src(invoking.dst());
+    ipv4.set_dst(invoking.src());
+
+    let offset = ipv4.payload_offset();
+    ipv4.mbuf_mut().extend(offset, len)?;
+    ipv4.mbuf_mut().write_data_slice(offset, quoted)?;
+
+    let mut message = ipv4.push::<T>()?;
+    message.set_code(code);
+    message.reconcile_all();
+    Ok(message)
+}
+
+/// Error when an ICMPv4 error message must not be generated in response
+/// to the invoking packet.
+#[derive(Debug, Fail)]
+#[fail(display = "Cannot generate an ICMPv4 error in response to {}.", _0)]
+pub struct SuppressedIcmpv4Error(&'static str);
+
 /// ICMPv4 header.
 #[doc(hidden)]
 #[derive(Clone, Copy, Debug, Default, SizeOf)]
@@ -448,8 +567,8 @@ mod tests {
     use super::*;
     use crate::packets::ip::v4::Ipv4;
     use crate::packets::Ethernet;
-    use crate::testils::byte_arrays::{ICMPV4_PACKET, IPV4_UDP_PACKET};
-    use crate::Mbuf;
+    use crate::testils::byte_arrays::{ICMPV4_PACKET, IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+    use std::net::Ipv4Addr;
 
     #[test]
     fn size_of_icmpv4_header() {
@@ -521,4 +640,57 @@ mod tests {
 
         assert!(ipv4.push::<Icmpv4>().is_err());
     }
+
+    #[capsule::test]
+    fn generate_error_reply() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let mut problem = error_reply::<ParameterProblem>(&ipv4, 0).unwrap();
+        problem.set_pointer(9);
+        problem.reconcile_all();
+
+        assert_eq!(Icmpv4Types::ParameterProblem, problem.msg_type());
+        assert_eq!(9, problem.pointer());
+        assert_eq!(ipv4.src(), problem.envelope().dst());
+        assert_eq!(ipv4.dst(), problem.envelope().src());
+        assert_eq!(ipv4.envelope().src(), problem.envelope().envelope().dst());
+        // quotes the ipv4 header and the 64 bits of udp header.
+        assert_eq!(ipv4.header_len() + 8, problem.data().len());
+        assert_eq!(&IPV4_UDP_PACKET[14..42], problem.data());
+
+        // the generated message passes a checksum recompute.
+        let checksum = problem.checksum();
+        problem.reconcile_all();
+        assert_eq!(checksum, problem.checksum());
+    }
+
+    #[capsule::test]
+    fn suppress_error_reply() {
+        // no error in response to an icmp error.
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let unreachable = error_reply::<DestinationUnreachable>(&ipv4, 3).unwrap();
+        assert!(error_reply::<DestinationUnreachable>(unreachable.envelope(), 3).is_err());
+
+        // no error in response to a non-initial fragment.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        ipv4.set_fragment_offset(10);
+        assert!(error_reply::<DestinationUnreachable>(&ipv4, 3).is_err());
+
+        // no error in response to a broadcast.
+        ipv4.set_fragment_offset(0);
+        ipv4.set_dst(Ipv4Addr::BROADCAST);
+        assert!(error_reply::<DestinationUnreachable>(&ipv4, 3).is_err());
+
+        // informational icmp messages are fine.
+        let packet = Mbuf::from_bytes(&ICMPV4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(error_reply::<DestinationUnreachable>(&ipv4, 3).is_ok());
+    }
 }
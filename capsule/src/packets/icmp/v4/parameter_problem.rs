@@ -0,0 +1,236 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v4::{Icmpv4, Icmpv4Message, Icmpv4Packet, Icmpv4Type, Icmpv4Types};
+use crate::packets::ip::v4::IPV4_MIN_MTU;
+use crate::packets::{Internal, Packet};
+use crate::SizeOf;
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Parameter Problem Message defined in [IETF RFC 792].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Code      |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Pointer    |                   unused                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Internet Header + 64 bits of Original Data Datagram      |
+/// ```
+///
+/// - *Code*:       0 = pointer indicates the error;
+///                 1 = missing a required option;
+///                 2 = bad length.
+///
+/// - *Pointer*:    Identifies the octet of the original datagram's header
+///                 where the error was detected.
+///
+/// [IETF RFC 792]: https://tools.ietf.org/html/rfc792
+#[derive(Icmpv4Packet)]
+pub struct ParameterProblem {
+    icmp: Icmpv4,
+    body: NonNull<ParameterProblemBody>,
+}
+
+impl ParameterProblem {
+    #[inline]
+    fn body(&self) -> &ParameterProblemBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut ParameterProblemBody {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the pointer to the octet where the error was detected.
+    #[inline]
+    pub fn pointer(&self) -> u8 {
+        self.body().pointer
+    }
+
+    /// Sets the pointer to the octet where the error was detected.
+    #[inline]
+    pub fn set_pointer(&mut self, pointer: u8) {
+        self.body_mut().pointer = pointer;
+    }
+
+    /// Returns the offset where the data field in the message body starts.
+    #[inline]
+    fn data_offset(&self) -> usize {
+        self.payload_offset() + ParameterProblemBody::size_of()
+    }
+
+    /// Returns the length of the data field in the message body.
+    #[inline]
+    fn data_len(&self) -> usize {
+        self.payload_len() - ParameterProblemBody::size_of()
+    }
+
+    /// Returns the invoking packet as a `u8` slice.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        if let Ok(data) = self
+            .icmp()
+            .mbuf()
+            .read_data_slice(self.data_offset(), self.data_len())
+        {
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+}
+
+impl fmt::Debug for ParameterProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParameterProblem")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("pointer", &self.pointer())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Icmpv4Message for ParameterProblem {
+    #[inline]
+    fn msg_type() -> Icmpv4Type {
+        Icmpv4Types::ParameterProblem
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv4 {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv4 {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv4 {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        ParameterProblem {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv4, _internal: Internal) -> Fallible<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(ParameterProblem { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv4, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, ParameterProblemBody::size_of())?;
+        let body = mbuf.write_data(offset, &ParameterProblemBody::default())?;
+
+        Ok(ParameterProblem { icmp, body })
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * the data field in the message body is trimmed if it exceeds the
+    /// [minimum IPV4 MTU], as we only need enough for port information.
+    /// * [`checksum`] is computed based on the `ParameterProblem` message.
+    ///
+    /// [minimum IPv4 MTU]: IPV4_MIN_MTU
+    /// [`checksum`]: Icmpv4::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.data_len();
+        let offset = self.data_offset();
+
+        if len > IPV4_MIN_MTU {
+            let _ = self
+                .mbuf_mut()
+                .shrink(offset + IPV4_MIN_MTU, len - IPV4_MIN_MTU);
+        }
+
+        self.icmp_mut().compute_checksum();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct ParameterProblemBody {
+    pointer: u8,
+    _unused: [u8; 3],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_TCP_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_parameter_problem_body() {
+        assert_eq!(4, ParameterProblemBody::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_parameter_problem() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp_len = ipv4.payload_len();
+
+        let mut problem = ipv4.push::<ParameterProblem>().unwrap();
+
+        assert_eq!(4, problem.header_len());
+        assert_eq!(
+            ParameterProblemBody::size_of() + tcp_len,
+            problem.payload_len()
+        );
+        assert_eq!(Icmpv4Types::ParameterProblem, problem.msg_type());
+        assert_eq!(0, problem.code());
+        assert_eq!(0, problem.pointer());
+        assert_eq!(tcp_len, problem.data().len());
+
+        problem.set_pointer(20);
+        assert_eq!(20, problem.pointer());
+
+        problem.reconcile_all();
+        assert!(problem.checksum() != 0);
+    }
+}
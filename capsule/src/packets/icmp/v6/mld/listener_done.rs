@@ -0,0 +1,198 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet};
+use crate::SizeOf;
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// Multicast Listener Done Message defined in [IETF RFC 2710].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Code      |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Maximum Response Delay    |          Reserved             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                                                               +
+/// |                                                               |
+/// +                       Multicast Address                       +
+/// |                                                               |
+/// +                                                               +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Maximum Response Delay*:
+///                     Unused, set to zero by the sender.
+///
+/// - *Multicast Address*:
+///                     The multicast address the sender is no longer listening to.
+///
+/// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerDone<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerDoneBody>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerDone<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerDoneBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerDoneBody {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the multicast address.
+    #[inline]
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        self.body().multicast_address
+    }
+
+    /// Sets the multicast address.
+    #[inline]
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        self.body_mut().multicast_address = multicast_address;
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerDone<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerDone")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("multicast_address", &self.multicast_address())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerDone<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerDone
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerDone {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(MulticastListenerDone { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerDoneBody::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerDoneBody::default())?;
+
+        Ok(MulticastListenerDone { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C)]
+struct MulticastListenerDoneBody {
+    max_resp_delay: u16be,
+    reserved: u16be,
+    multicast_address: Ipv6Addr,
+}
+
+impl Default for MulticastListenerDoneBody {
+    fn default() -> Self {
+        MulticastListenerDoneBody {
+            max_resp_delay: u16be::default(),
+            reserved: u16be::default(),
+            multicast_address: Ipv6Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_done_body() {
+        assert_eq!(20, MulticastListenerDoneBody::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_multicast_listener_done() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut done = ipv6.push::<MulticastListenerDone<Ipv6>>().unwrap();
+
+        assert_eq!(4, done.header_len());
+        assert_eq!(MulticastListenerDoneBody::size_of(), done.payload_len());
+        assert_eq!(Icmpv6Types::MulticastListenerDone, done.msg_type());
+        assert_eq!(0, done.code());
+
+        let addr = "ff3e::8000:1".parse().unwrap();
+        done.set_multicast_address(addr);
+        assert_eq!(addr, done.multicast_address());
+
+        done.reconcile_all();
+        assert!(done.checksum() != 0);
+    }
+}
@@ -0,0 +1,240 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// Multicast Listener Query Message defined in [IETF RFC 2710].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Type      |     Code      |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Maximum Response Delay    |          Reserved             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                                                               +
+/// |                                                               |
+/// +                       Multicast Address                       +
+/// |                                                               |
+/// +                                                               +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Maximum Response Delay*:
+///                     The maximum allowed delay before sending a
+///                     responding report, in units of milliseconds.
+///
+/// - *Multicast Address*:
+///                     Zero in a general query, otherwise the multicast
+///                     address being queried.
+///
+/// An MLDv1 query is exactly 24 octets long. Longer queries are parsed
+/// as [`MulticastListenerQueryV2`].
+///
+/// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+/// [`MulticastListenerQueryV2`]: crate::packets::icmp::v6::mld::MulticastListenerQueryV2
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerQuery<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerQueryBody>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerQuery<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerQueryBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerQueryBody {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the maximum response delay in milliseconds.
+    #[inline]
+    pub fn max_resp_delay(&self) -> u16 {
+        self.body().max_resp_delay.into()
+    }
+
+    /// Sets the maximum response delay in milliseconds.
+    #[inline]
+    pub fn set_max_resp_delay(&mut self, max_resp_delay: u16) {
+        self.body_mut().max_resp_delay = max_resp_delay.into();
+    }
+
+    /// Returns the multicast address.
+    #[inline]
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        self.body().multicast_address
+    }
+
+    /// Sets the multicast address.
+    #[inline]
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        self.body_mut().multicast_address = multicast_address;
+    }
+
+    /// Returns whether the message is a general query for all addresses.
+    #[inline]
+    pub fn is_general_query(&self) -> bool {
+        self.multicast_address().is_unspecified()
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerQuery<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerQuery")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("max_resp_delay", &self.max_resp_delay())
+            .field("multicast_address", &self.multicast_address())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerQuery<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerQuery
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerQuery {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            icmp.payload_len() == MulticastListenerQueryBody::size_of(),
+            ParseError::new("not an MLDv1 query.")
+        );
+
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(MulticastListenerQuery { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerQueryBody::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerQueryBody::default())?;
+
+        Ok(MulticastListenerQuery { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C)]
+struct MulticastListenerQueryBody {
+    max_resp_delay: u16be,
+    reserved: u16be,
+    multicast_address: Ipv6Addr,
+}
+
+impl Default for MulticastListenerQueryBody {
+    fn default() -> Self {
+        MulticastListenerQueryBody {
+            max_resp_delay: u16be::default(),
+            reserved: u16be::default(),
+            multicast_address: Ipv6Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::MLDV2_QUERY_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_query_body() {
+        assert_eq!(20, MulticastListenerQueryBody::size_of());
+    }
+
+    #[capsule::test]
+    fn push_and_set_multicast_listener_query() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut query = ipv6.push::<MulticastListenerQuery<Ipv6>>().unwrap();
+
+        assert_eq!(4, query.header_len());
+        assert_eq!(MulticastListenerQueryBody::size_of(), query.payload_len());
+        assert_eq!(Icmpv6Types::MulticastListenerQuery, query.msg_type());
+        assert!(query.is_general_query());
+
+        query.set_max_resp_delay(10000);
+        assert_eq!(10000, query.max_resp_delay());
+        let addr = "ff02::1:3".parse().unwrap();
+        query.set_multicast_address(addr);
+        assert_eq!(addr, query.multicast_address());
+
+        query.reconcile_all();
+        assert!(query.checksum() != 0);
+    }
+
+    #[capsule::test]
+    fn parse_mldv2_as_mldv1_query() {
+        let packet = Mbuf::from_bytes(&MLDV2_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.parse::<MulticastListenerQuery<Ipv6>>().is_err());
+    }
+}
@@ -0,0 +1,430 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::decode_max_resp_code;
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::igmp::decode_float_code;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+// The suppress router-side processing bit of the flags field.
+const FLAG_S: u8 = 0x08;
+
+// The querier's robustness variable bits of the flags field.
+const QRV_MASK: u8 = 0x07;
+
+/// Version 2 Multicast Listener Query Message defined in [IETF RFC 3810].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 130   |      Code     |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Maximum Response Code      |           Reserved            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                                                               *
+/// |                                                               |
+/// *                       Multicast Address                       *
+/// |                                                               |
+/// *                                                               *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Resv  |S| QRV |     QQIC      |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                                                               *
+/// |                                                               |
+/// *                       Source Address [1]                      *
+/// |                                                               |
+/// *                                                               *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                               .                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Source Address [N]                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Maximum Response Code*:
+///                     The maximum allowed delay before sending a
+///                     responding report, in units of milliseconds. Values
+///                     of 32768 and above are in a floating point format.
+///
+/// - *Multicast Address*:
+///                     Zero in a general query, otherwise the multicast
+///                     address being queried.
+///
+/// - *S*:              Suppress router-side processing, whether receiving
+///                     routers should suppress their timer updates.
+///
+/// - *QRV*:            Querier's robustness variable.
+///
+/// - *QQIC*:           Querier's query interval code, in seconds. Values
+///                     of 128 and above are in a floating point format.
+///
+/// - *Number of Sources (N)*:
+///                     The number of source addresses present in the
+///                     query.
+///
+/// - *Source Address [i]*:
+///                     The unicast source addresses of a multicast address
+///                     and source specific query.
+///
+/// An MLDv2 query is at least 28 octets long. Shorter queries are parsed
+/// as [`MulticastListenerQuery`].
+///
+/// [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810#section-5.1
+/// [`MulticastListenerQuery`]: crate::packets::icmp::v6::mld::MulticastListenerQuery
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerQueryV2<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerQueryV2Body>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerQueryV2<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerQueryV2Body {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerQueryV2Body {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the encoded maximum response code.
+    #[inline]
+    pub fn max_resp_code(&self) -> u16 {
+        self.body().max_resp_code.into()
+    }
+
+    /// Sets the encoded maximum response code.
+    #[inline]
+    pub fn set_max_resp_code(&mut self, max_resp_code: u16) {
+        self.body_mut().max_resp_code = max_resp_code.into();
+    }
+
+    /// Returns the decoded maximum response delay in milliseconds.
+    #[inline]
+    pub fn max_resp_delay(&self) -> u32 {
+        decode_max_resp_code(self.max_resp_code())
+    }
+
+    /// Returns the multicast address.
+    #[inline]
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        self.body().multicast_address
+    }
+
+    /// Sets the multicast address.
+    #[inline]
+    pub fn set_multicast_address(&mut self, multicast_address: Ipv6Addr) {
+        self.body_mut().multicast_address = multicast_address;
+    }
+
+    /// Returns whether router-side processing is suppressed.
+    #[inline]
+    pub fn suppress_router_processing(&self) -> bool {
+        self.body().flags & FLAG_S != 0
+    }
+
+    /// Sets whether router-side processing is suppressed.
+    #[inline]
+    pub fn set_suppress_router_processing(&mut self, suppress: bool) {
+        if suppress {
+            self.body_mut().flags |= FLAG_S;
+        } else {
+            self.body_mut().flags &= !FLAG_S;
+        }
+    }
+
+    /// Returns the querier's robustness variable.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        self.body().flags & QRV_MASK
+    }
+
+    /// Sets the querier's robustness variable. Only the lowest 3 bits are
+    /// used.
+    #[inline]
+    pub fn set_qrv(&mut self, qrv: u8) {
+        let flags = (self.body().flags & !QRV_MASK) | (qrv & QRV_MASK);
+        self.body_mut().flags = flags;
+    }
+
+    /// Returns the encoded querier's query interval code.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        self.body().qqic
+    }
+
+    /// Sets the encoded querier's query interval code.
+    #[inline]
+    pub fn set_qqic(&mut self, qqic: u8) {
+        self.body_mut().qqic = qqic;
+    }
+
+    /// Returns the decoded querier's query interval in seconds.
+    #[inline]
+    pub fn query_interval(&self) -> u32 {
+        decode_float_code(self.qqic())
+    }
+
+    /// Returns whether the message is a general query for all addresses.
+    #[inline]
+    pub fn is_general_query(&self) -> bool {
+        self.multicast_address().is_unspecified()
+    }
+
+    /// Returns the number of source addresses.
+    #[inline]
+    pub fn source_count(&self) -> u16 {
+        self.body().num_sources.into()
+    }
+
+    /// Returns the offset where the source addresses start.
+    #[inline]
+    fn sources_offset(&self) -> usize {
+        self.payload_offset() + MulticastListenerQueryV2Body::size_of()
+    }
+
+    /// Returns the source addresses.
+    pub fn sources(&self) -> Vec<Ipv6Addr> {
+        let count = self.source_count() as usize;
+        if count == 0 {
+            return vec![];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<Ipv6Addr>(self.sources_offset(), count)
+        {
+            Ok(sources) => unsafe { sources.as_ref() }.to_vec(),
+            // the sources are checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Replaces the source addresses.
+    pub fn set_sources(&mut self, sources: &[Ipv6Addr]) -> Fallible<()> {
+        let old_len = self.source_count() as usize * 16;
+        let new_len = sources.len() * 16;
+        let offset = self.sources_offset();
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        if !sources.is_empty() {
+            mbuf.write_data_slice(offset, sources)?;
+        }
+
+        self.body_mut().num_sources = (sources.len() as u16).into();
+        Ok(())
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerQueryV2<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerQueryV2")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("max_resp_code", &self.max_resp_code())
+            .field("multicast_address", &self.multicast_address())
+            .field("s", &self.suppress_router_processing())
+            .field("qrv", &self.qrv())
+            .field("qqic", &self.qqic())
+            .field("sources", &self.sources())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerQueryV2<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerQuery
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerQueryV2 {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            icmp.payload_len() >= MulticastListenerQueryV2Body::size_of(),
+            ParseError::new("not an MLDv2 query.")
+        );
+
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data::<MulticastListenerQueryV2Body>(offset)?;
+
+        let count = u16::from(unsafe { body.as_ref() }.num_sources) as usize;
+        if count > 0 {
+            let _ = mbuf.read_data_slice::<Ipv6Addr>(
+                offset + MulticastListenerQueryV2Body::size_of(),
+                count,
+            )?;
+        }
+
+        Ok(MulticastListenerQueryV2 { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerQueryV2Body::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerQueryV2Body::default())?;
+
+        Ok(MulticastListenerQueryV2 { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C)]
+struct MulticastListenerQueryV2Body {
+    max_resp_code: u16be,
+    reserved: u16be,
+    multicast_address: Ipv6Addr,
+    flags: u8,
+    qqic: u8,
+    num_sources: u16be,
+}
+
+impl Default for MulticastListenerQueryV2Body {
+    fn default() -> Self {
+        MulticastListenerQueryV2Body {
+            max_resp_code: u16be::default(),
+            reserved: u16be::default(),
+            multicast_address: Ipv6Addr::UNSPECIFIED,
+            flags: 0,
+            qqic: 0,
+            num_sources: u16be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::MLDV2_QUERY_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_query_v2_body() {
+        assert_eq!(24, MulticastListenerQueryV2Body::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_multicast_listener_query_v2() {
+        let packet = Mbuf::from_bytes(&MLDV2_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let query = ipv6.parse::<MulticastListenerQueryV2<Ipv6>>().unwrap();
+
+        assert_eq!(Icmpv6Types::MulticastListenerQuery, query.msg_type());
+        assert_eq!(10000, query.max_resp_delay());
+        assert_eq!(
+            "ff3e::8000:1".parse::<Ipv6Addr>().unwrap(),
+            query.multicast_address()
+        );
+        assert!(!query.is_general_query());
+        assert!(!query.suppress_router_processing());
+        assert_eq!(2, query.qrv());
+        assert_eq!(125, query.query_interval());
+        assert_eq!(
+            vec!["2001:db8::1".parse::<Ipv6Addr>().unwrap()],
+            query.sources()
+        );
+    }
+
+    #[capsule::test]
+    fn push_and_set_multicast_listener_query_v2() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut query = ipv6.push::<MulticastListenerQueryV2<Ipv6>>().unwrap();
+
+        assert_eq!(4, query.header_len());
+        assert_eq!(MulticastListenerQueryV2Body::size_of(), query.payload_len());
+        assert!(query.is_general_query());
+
+        query.set_suppress_router_processing(true);
+        assert!(query.suppress_router_processing());
+        query.set_qrv(2);
+        assert_eq!(2, query.qrv());
+        assert!(query.suppress_router_processing());
+
+        let sources = [
+            "2001:db8::1".parse().unwrap(),
+            "2001:db8::2".parse().unwrap(),
+        ];
+        query.set_sources(&sources).unwrap();
+        assert_eq!(2, query.source_count());
+        assert_eq!(sources.to_vec(), query.sources());
+        assert_eq!(
+            MulticastListenerQueryV2Body::size_of() + 32,
+            query.payload_len()
+        );
+
+        query.set_sources(&[]).unwrap();
+        assert_eq!(0, query.source_count());
+        assert_eq!(MulticastListenerQueryV2Body::size_of(), query.payload_len());
+
+        query.reconcile_all();
+        assert!(query.checksum() != 0);
+    }
+}
@@ -0,0 +1,433 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{GroupRecordType, Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// Version 2 Multicast Listener Report Message defined in [IETF RFC 3810].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 143   |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |Nr of Mcast Address Records (M)|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                  Multicast Address Record [1]                 .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                               .                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                  Multicast Address Record [M]                 .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Nr of Mcast Address Records (M)*:
+///                     The number of multicast address records present in
+///                     the report.
+///
+/// - *Multicast Address Record*:
+///                     A block of fields containing information about the
+///                     sender's listening to a single multicast address.
+///                     Use [`address_records_iter`] to read the records.
+///
+/// [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810#section-5.2
+/// [`address_records_iter`]: MulticastListenerReportV2::address_records_iter
+#[derive(Icmpv6Packet)]
+pub struct MulticastListenerReportV2<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<MulticastListenerReportV2Body>,
+}
+
+impl<E: Ipv6Packet> MulticastListenerReportV2<E> {
+    #[inline]
+    fn body(&self) -> &MulticastListenerReportV2Body {
+        unsafe { self.body.as_ref() }
+    }
+
+    #[inline]
+    fn body_mut(&mut self) -> &mut MulticastListenerReportV2Body {
+        unsafe { self.body.as_mut() }
+    }
+
+    /// Returns the number of multicast address records.
+    #[inline]
+    pub fn address_record_count(&self) -> u16 {
+        self.body().num_records.into()
+    }
+
+    /// Returns the offset where the multicast address records start.
+    #[inline]
+    fn records_offset(&self) -> usize {
+        self.payload_offset() + MulticastListenerReportV2Body::size_of()
+    }
+
+    /// Returns the multicast address records as a `u8` slice.
+    #[inline]
+    pub fn address_records(&self) -> &[u8] {
+        let len = self.payload_len() - MulticastListenerReportV2Body::size_of();
+        if len == 0 {
+            return &[];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<u8>(self.records_offset(), len)
+        {
+            Ok(records) => unsafe { records.as_ref() },
+            // we are reading till the end of buffer, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the multicast address records.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let report = ipv6.parse::<MulticastListenerReportV2<Ipv6>>()?;
+    /// let mut iter = report.address_records_iter();
+    ///
+    /// while let Some(record) = iter.next()? {
+    ///     println!("{} {}", record.record_type(), record.multicast_address());
+    /// }
+    /// ```
+    #[inline]
+    pub fn address_records_iter(&self) -> MulticastAddressRecordsIterator<'_> {
+        MulticastAddressRecordsIterator::new(self.address_records(), self.address_record_count())
+    }
+
+    /// Adds a multicast address record without auxiliary data after the
+    /// existing records.
+    ///
+    /// Call [`reconcile_all`] afterwards to fix the checksum and the
+    /// payload length of the envelope.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_address_record(
+        &mut self,
+        record_type: GroupRecordType,
+        multicast_address: Ipv6Addr,
+        sources: &[Ipv6Addr],
+    ) -> Fallible<()> {
+        let mut iter = self.address_records_iter();
+        while iter.next()?.is_some() {}
+        let offset = self.records_offset() + iter.offset;
+
+        let mut bytes = Vec::with_capacity(20 + sources.len() * 16);
+        bytes.push(record_type.0);
+        bytes.push(0);
+        bytes.extend_from_slice(&(sources.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&multicast_address.octets());
+        for source in sources {
+            bytes.extend_from_slice(&source.octets());
+        }
+
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, bytes.len())?;
+        mbuf.write_data_slice(offset, &bytes)?;
+
+        let count = self.address_record_count() + 1;
+        self.body_mut().num_records = count.into();
+        Ok(())
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for MulticastListenerReportV2<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastListenerReportV2")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("address_record_count", &self.address_record_count())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for MulticastListenerReportV2<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::MulticastListenerReportV2
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        MulticastListenerReportV2 {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(MulticastListenerReportV2 { icmp, body })
+    }
+
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Fallible<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, MulticastListenerReportV2Body::size_of())?;
+        let body = mbuf.write_data(offset, &MulticastListenerReportV2Body::default())?;
+
+        Ok(MulticastListenerReportV2 { icmp, body })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct MulticastListenerReportV2Body {
+    reserved: u16be,
+    num_records: u16be,
+}
+
+/// A multicast address record in an MLDv2 report.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Record Type  |  Aux Data Len |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Multicast Address                       *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Source Address [1]                      *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                               .                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// *                       Source Address [N]                      *
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                         Auxiliary Data                        .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MulticastAddressRecord<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> MulticastAddressRecord<'a> {
+    /// Returns the type of the record.
+    #[inline]
+    pub fn record_type(&self) -> GroupRecordType {
+        GroupRecordType(self.data[0])
+    }
+
+    /// Returns the multicast address the record pertains to.
+    #[inline]
+    pub fn multicast_address(&self) -> Ipv6Addr {
+        let mut octets = [0; 16];
+        octets.copy_from_slice(&self.data[4..20]);
+        Ipv6Addr::from(octets)
+    }
+
+    /// Returns the source addresses.
+    pub fn sources(&self) -> Vec<Ipv6Addr> {
+        let count = u16::from_be_bytes([self.data[2], self.data[3]]) as usize;
+        self.data[20..20 + count * 16]
+            .chunks_exact(16)
+            .map(|chunk| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(chunk);
+                Ipv6Addr::from(octets)
+            })
+            .collect()
+    }
+
+    /// Returns the auxiliary data.
+    #[inline]
+    pub fn aux_data(&self) -> &'a [u8] {
+        let len = self.data[1] as usize * 4;
+        &self.data[self.data.len() - len..]
+    }
+}
+
+/// An iterator that iterates through the multicast address records of an
+/// MLDv2 report.
+#[derive(Debug)]
+pub struct MulticastAddressRecordsIterator<'a> {
+    data: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> MulticastAddressRecordsIterator<'a> {
+    fn new(data: &'a [u8], count: u16) -> Self {
+        MulticastAddressRecordsIterator {
+            data,
+            offset: 0,
+            remaining: count,
+        }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<MulticastAddressRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let data = &self.data[self.offset..];
+        ensure!(
+            data.len() >= 20,
+            ParseError::new("MLDv2 multicast address record is truncated.")
+        );
+
+        let sources = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let len = 20 + sources * 16 + data[1] as usize * 4;
+        ensure!(
+            data.len() >= len,
+            ParseError::new("MLDv2 multicast address record is truncated.")
+        );
+
+        self.offset += len;
+        self.remaining -= 1;
+
+        Ok(Some(MulticastAddressRecord { data: &data[..len] }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, GroupRecordTypes};
+    use crate::testils::byte_arrays::MLDV2_REPORT_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_multicast_listener_report_v2_body() {
+        assert_eq!(4, MulticastListenerReportV2Body::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_multicast_listener_report_v2() {
+        let packet = Mbuf::from_bytes(&MLDV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut report = ipv6.parse::<MulticastListenerReportV2<Ipv6>>().unwrap();
+
+        assert_eq!(2, report.address_record_count());
+
+        let mut iter = report.address_records_iter();
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::ChangeToExcludeMode, record.record_type());
+        assert_eq!(
+            "ff02::1:ff00:1".parse::<Ipv6Addr>().unwrap(),
+            record.multicast_address()
+        );
+        assert!(record.sources().is_empty());
+        assert!(record.aux_data().is_empty());
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::AllowNewSources, record.record_type());
+        assert_eq!(
+            "ff3e::8000:1".parse::<Ipv6Addr>().unwrap(),
+            record.multicast_address()
+        );
+        assert_eq!(
+            vec!["2001:db8::1".parse::<Ipv6Addr>().unwrap()],
+            record.sources()
+        );
+
+        assert!(iter.next().unwrap().is_none());
+
+        report.reconcile_all();
+        assert_eq!(0xbee6, report.checksum());
+    }
+
+    #[capsule::test]
+    fn add_multicast_address_records() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut report = ipv6.push::<MulticastListenerReportV2<Ipv6>>().unwrap();
+
+        assert_eq!(Icmpv6Types::MulticastListenerReportV2, report.msg_type());
+        assert_eq!(0, report.address_record_count());
+        assert!(report.address_records_iter().next().unwrap().is_none());
+
+        let group = "ff3e::8000:1".parse().unwrap();
+        let source = "2001:db8::1".parse().unwrap();
+        report
+            .add_address_record(GroupRecordTypes::ModeIsInclude, group, &[source])
+            .unwrap();
+        report
+            .add_address_record(GroupRecordTypes::BlockOldSources, group, &[])
+            .unwrap();
+
+        assert_eq!(2, report.address_record_count());
+        assert_eq!(
+            MulticastListenerReportV2Body::size_of() + 36 + 20,
+            report.payload_len()
+        );
+
+        let mut iter = report.address_records_iter();
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::ModeIsInclude, record.record_type());
+        assert_eq!(vec![source], record.sources());
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::BlockOldSources, record.record_type());
+        assert_eq!(group, record.multicast_address());
+
+        report.reconcile_all();
+        assert!(report.checksum() != 0);
+    }
+}
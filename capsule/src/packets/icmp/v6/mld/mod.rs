@@ -0,0 +1,71 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Multicast Listener Discovery
+//!
+//! MLD is used by IPv6 routers to discover the multicast listeners on
+//! their directly attached links, using ICMPv6 messages. Version 1 is
+//! defined in [IETF RFC 2710] and version 2, which adds source filtering,
+//! in [IETF RFC 3810]. Both versions share the same query message type;
+//! the versions are told apart by the length of the message.
+//!
+//! All MLD messages are sent with a link-local source address, a hop
+//! limit of 1 and a router alert option in a hop-by-hop options header.
+//!
+//! [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710
+//! [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810
+
+mod listener_done;
+mod listener_query;
+mod listener_query_v2;
+mod listener_report;
+mod listener_report_v2;
+
+pub use self::listener_done::*;
+pub use self::listener_query::*;
+pub use self::listener_query_v2::*;
+pub use self::listener_report::*;
+pub use self::listener_report_v2::*;
+
+/// Decodes the 16-bit floating point representation of the MLDv2 Maximum
+/// Response Code. Values below 32768 are literal, otherwise the field is
+/// `1 | exp (3) | mant (12)` for `(mant | 0x1000) << (exp + 3)`.
+#[inline]
+fn decode_max_resp_code(code: u16) -> u32 {
+    if code < 0x8000 {
+        u32::from(code)
+    } else {
+        let exp = (code >> 12) & 0x07;
+        let mant = code & 0x0fff;
+        u32::from(mant | 0x1000) << (exp + 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_mldv2_max_resp_code() {
+        assert_eq!(10000, decode_max_resp_code(10000));
+        // exp = 0, mant = 0
+        assert_eq!(32768, decode_max_resp_code(0x8000));
+        // exp = 7, mant = 4095
+        assert_eq!(8_387_584, decode_max_resp_code(0xffff));
+    }
+}
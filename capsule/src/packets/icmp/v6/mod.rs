@@ -18,23 +18,28 @@
 
 //! Internet Control Message Protocol for IPv6.
 
+mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
+pub mod mld;
 pub mod ndp;
+mod parameter_problem;
 mod time_exceeded;
 mod too_big;
 
+pub use self::destination_unreachable::*;
 pub use self::echo_reply::*;
 pub use self::echo_request::*;
+pub use self::parameter_problem::*;
 pub use self::time_exceeded::*;
 pub use self::too_big::*;
 pub use capsule_macros::Icmpv6Packet;
 
-use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet};
 use crate::packets::ip::ProtocolNumbers;
 use crate::packets::types::u16be;
-use crate::packets::{checksum, Internal, Packet, ParseError};
-use crate::{ensure, SizeOf};
+use crate::packets::{checksum, Ethernet, Internal, Packet, ParseError};
+use crate::{ensure, Mbuf, SizeOf};
 use failure::{Fail, Fallible};
 use std::fmt;
 use std::ptr::NonNull;
@@ -283,6 +288,11 @@ impl Icmpv6Type {
 pub mod Icmpv6Types {
     use super::Icmpv6Type;
 
+    /// Message type for [Destination Unreachable].
+    ///
+    /// [Destination Unreachable]: crate::packets::icmp::v6::DestinationUnreachable
+    pub const DestinationUnreachable: Icmpv6Type = Icmpv6Type(1);
+
     /// Message type for [Packet Too Big].
     ///
     /// [Packet Too Big]: crate::packets::icmp::v6::PacketTooBig
@@ -293,6 +303,11 @@ pub mod Icmpv6Types {
     /// [Time Exceeded]: crate::packets::icmp::v6::TimeExceeded
     pub const TimeExceeded: Icmpv6Type = Icmpv6Type(3);
 
+    /// Message type for [Parameter Problem].
+    ///
+    /// [Parameter Problem]: crate::packets::icmp::v6::ParameterProblem
+    pub const ParameterProblem: Icmpv6Type = Icmpv6Type(4);
+
     /// Message type for [Echo Request].
     ///
     /// [Echo Request]: crate::packets::icmp::v6::EchoRequest
@@ -303,6 +318,25 @@ pub mod Icmpv6Types {
     /// [Echo Reply]: crate::packets::icmp::v6::EchoReply
     pub const EchoReply: Icmpv6Type = Icmpv6Type(129);
 
+    /// Message type for [Multicast Listener Query].
+    ///
+    /// Both [MLDv1] and [MLDv2] queries use this type.
+    ///
+    /// [Multicast Listener Query]: crate::packets::icmp::v6::mld::MulticastListenerQuery
+    /// [MLDv1]: crate::packets::icmp::v6::mld::MulticastListenerQuery
+    /// [MLDv2]: crate::packets::icmp::v6::mld::MulticastListenerQueryV2
+    pub const MulticastListenerQuery: Icmpv6Type = Icmpv6Type(130);
+
+    /// Message type for [Multicast Listener Report].
+    ///
+    /// [Multicast Listener Report]: crate::packets::icmp::v6::mld::MulticastListenerReport
+    pub const MulticastListenerReport: Icmpv6Type = Icmpv6Type(131);
+
+    /// Message type for [Multicast Listener Done].
+    ///
+    /// [Multicast Listener Done]: crate::packets::icmp::v6::mld::MulticastListenerDone
+    pub const MulticastListenerDone: Icmpv6Type = Icmpv6Type(132);
+
     /// Message type for [Router Solicitation].
     ///
     /// [Router Solicitation]: crate::packets::icmp::v6::ndp::RouterSolicitation
@@ -327,6 +361,11 @@ pub mod Icmpv6Types {
     ///
     /// [Redirect]: crate::packets::icmp::v6::ndp::Redirect
     pub const Redirect: Icmpv6Type = Icmpv6Type(137);
+
+    /// Message type for [Version 2 Multicast Listener Report].
+    ///
+    /// [Version 2 Multicast Listener Report]: crate::packets::icmp::v6::mld::MulticastListenerReportV2
+    pub const MulticastListenerReportV2: Icmpv6Type = Icmpv6Type(143);
 }
 
 impl fmt::Display for Icmpv6Type {
@@ -335,21 +374,125 @@ impl fmt::Display for Icmpv6Type {
             f,
             "{}",
             match *self {
+                Icmpv6Types::DestinationUnreachable => "Destination Unreachable".to_string(),
                 Icmpv6Types::PacketTooBig => "Packet Too Big".to_string(),
                 Icmpv6Types::TimeExceeded => "Time Exceeded".to_string(),
+                Icmpv6Types::ParameterProblem => "Parameter Problem".to_string(),
                 Icmpv6Types::EchoRequest => "Echo Request".to_string(),
                 Icmpv6Types::EchoReply => "Echo Reply".to_string(),
+                Icmpv6Types::MulticastListenerQuery => "Multicast Listener Query".to_string(),
+                Icmpv6Types::MulticastListenerReport => "Multicast Listener Report".to_string(),
+                Icmpv6Types::MulticastListenerDone => "Multicast Listener Done".to_string(),
                 Icmpv6Types::RouterSolicitation => "Router Solicitation".to_string(),
                 Icmpv6Types::RouterAdvertisement => "Router Advertisement".to_string(),
                 Icmpv6Types::NeighborSolicitation => "Neighbor Solicitation".to_string(),
                 Icmpv6Types::NeighborAdvertisement => "Neighbor Advertisement".to_string(),
                 Icmpv6Types::Redirect => "Redirect".to_string(),
+                Icmpv6Types::MulticastListenerReportV2 => {
+                    "Version 2 Multicast Listener Report".to_string()
+                }
                 _ => format!("{}", self.0),
             }
         )
     }
 }
 
+impl Icmpv6Type {
+    /// Returns a flag indicating whether the message type is an error
+    /// message. Error messages have a type value in the range of 0 to 127.
+    #[inline]
+    pub fn is_error(self) -> bool {
+        self.0 < 128
+    }
+}
+
+/// Generates an ICMPv6 error message of type `T` in response to the
+/// invoking packet.
+///
+/// The message is written into a new `Mbuf`. The Ethernet and IPv6
+/// addresses are the reverse of the invoking packet's, so the error is
+/// sent back to the originator from the address it tried to reach. The
+/// message quotes as much of the invoking packet as possible without the
+/// error exceeding the [minimum IPv6 MTU]. The returned message is already
+/// reconciled; callers that change other fields, such as the source
+/// address of a router's own interface, should reconcile it again so the
+/// checksum covers the new pseudo-header.
+///
+/// Returns an error if [IETF RFC 4443] forbids sending an ICMPv6 error
+/// in response to the invoking packet. That is when the invoking packet
+/// is itself an ICMPv6 error message, has an unspecified or multicast
+/// source, or is addressed to a multicast address. The last rule does
+/// not apply to [Packet Too Big] messages.
+///
+/// # Example
+///
+/// ```
+/// let ipv6 = ethernet.parse::<Ipv6>()?;
+/// let unreachable = icmp::v6::error_reply::<DestinationUnreachable<Ipv6>>(&ipv6, 4)?;
+/// ```
+///
+/// [minimum IPv6 MTU]: crate::packets::ip::v6::IPV6_MIN_MTU
+/// [IETF RFC 4443]: https://tools.ietf.org/html/rfc4443#section-2.4
+/// [Packet Too Big]: PacketTooBig
+pub fn error_reply<T>(invoking: &Ipv6, code: u8) -> Fallible<T>
+where
+    T: Icmpv6Message<Envelope = Ipv6> + Icmpv6Packet + Packet<Envelope = Ipv6>,
+{
+    let src = invoking.src();
+    ensure!(
+        !src.is_unspecified() && !src.is_multicast(),
+        SuppressedIcmpv6Error("a packet without a unicast source")
+    );
+
+    ensure!(
+        <T as Icmpv6Message>::msg_type() == Icmpv6Types::PacketTooBig
+            || !invoking.dst().is_multicast(),
+        SuppressedIcmpv6Error("a multicast packet")
+    );
+
+    if invoking.next_header() == ProtocolNumbers::Icmpv6 {
+        if let Ok(header) = invoking
+            .mbuf()
+            .read_data::<Icmpv6Header>(invoking.payload_offset())
+        {
+            let msg_type = Icmpv6Type::new(unsafe { header.as_ref() }.msg_type);
+            ensure!(
+                !msg_type.is_error(),
+                SuppressedIcmpv6Error("an ICMPv6 error message")
+            );
+        }
+    }
+
+    // quotes the whole packet, `reconcile` truncates it to the minimum MTU.
+    let quoted = invoking
+        .mbuf()
+        .read_data_slice::<u8>(invoking.offset(), invoking.len())?;
+    let quoted = unsafe { quoted.as_ref() };
+
+    let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+    ethernet.set_src(invoking.envelope().dst());
+    ethernet.set_dst(invoking.envelope().src());
+
+    let mut ipv6 = ethernet.push::<Ipv6>()?;
+    ipv6.set_src(invoking.dst());
+    ipv6.set_dst(src);
+
+    let offset = ipv6.payload_offset();
+    ipv6.mbuf_mut().extend(offset, quoted.len())?;
+    ipv6.mbuf_mut().write_data_slice(offset, quoted)?;
+
+    let mut message = ipv6.push::<T>()?;
+    message.set_code(code);
+    message.reconcile_all();
+    Ok(message)
+}
+
+/// Error when an ICMPv6 error message must not be generated in response
+/// to the invoking packet.
+#[derive(Debug, Fail)]
+#[fail(display = "Cannot generate an ICMPv6 error in response to {}.", _0)]
+pub struct SuppressedIcmpv6Error(&'static str);
+
 /// ICMPv6 header.
 #[doc(hidden)]
 #[derive(Clone, Copy, Debug, Default, SizeOf)]
@@ -492,10 +635,8 @@ pub trait Icmpv6Packet {
 mod tests {
     use super::*;
     use crate::packets::icmp::v6::ndp::RouterAdvertisement;
-    use crate::packets::ip::v6::Ipv6;
-    use crate::packets::Ethernet;
     use crate::testils::byte_arrays::{ICMPV6_PACKET, IPV6_TCP_PACKET, ROUTER_ADVERT_PACKET};
-    use crate::Mbuf;
+    use std::net::Ipv6Addr;
 
     #[test]
     fn size_of_icmpv6_header() {
@@ -571,4 +712,52 @@ mod tests {
 
         assert!(ipv6.push::<Icmpv6<Ipv6>>().is_err());
     }
+
+    #[capsule::test]
+    fn generate_error_reply() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let mut unreachable = error_reply::<DestinationUnreachable<Ipv6>>(&ipv6, 4).unwrap();
+
+        assert_eq!(Icmpv6Types::DestinationUnreachable, unreachable.msg_type());
+        assert_eq!(4, unreachable.code());
+        assert_eq!(ipv6.src(), unreachable.envelope().dst());
+        assert_eq!(ipv6.dst(), unreachable.envelope().src());
+        assert_eq!(
+            ipv6.envelope().src(),
+            unreachable.envelope().envelope().dst()
+        );
+        // the whole invoking packet fits within the minimum mtu.
+        assert_eq!(ipv6.len(), unreachable.data().len());
+        assert_eq!(&IPV6_TCP_PACKET[14..], unreachable.data());
+
+        // the generated message passes a checksum recompute.
+        let checksum = unreachable.checksum();
+        unreachable.reconcile_all();
+        assert_eq!(checksum, unreachable.checksum());
+    }
+
+    #[capsule::test]
+    fn suppress_error_reply() {
+        // no error in response to an icmp error.
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let problem = error_reply::<ParameterProblem<Ipv6>>(&ipv6, 0).unwrap();
+        assert!(error_reply::<ParameterProblem<Ipv6>>(problem.envelope(), 0).is_err());
+
+        // no error in response to a multicast, except for packet too big.
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        ipv6.set_dst("ff02::1".parse().unwrap());
+        assert!(error_reply::<DestinationUnreachable<Ipv6>>(&ipv6, 0).is_err());
+        assert!(error_reply::<PacketTooBig<Ipv6>>(&ipv6, 0).is_ok());
+
+        // no error in response to an unspecified source.
+        ipv6.set_src(Ipv6Addr::UNSPECIFIED);
+        assert!(error_reply::<PacketTooBig<Ipv6>>(&ipv6, 0).is_err());
+    }
 }
@@ -0,0 +1,190 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Internet Group Management Protocol.
+//!
+//! IGMP is used by IPv4 hosts and multicast routers to report and query
+//! multicast group memberships. [`Igmpv2`] covers the fixed 8-octet
+//! messages of [IETF RFC 2236], and [`Igmpv3Query`] and [`Igmpv3Report`]
+//! the variable length messages of [IETF RFC 3376].
+//!
+//! [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236
+//! [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376
+//! [`Igmpv2`]: Igmpv2
+//! [`Igmpv3Query`]: Igmpv3Query
+//! [`Igmpv3Report`]: Igmpv3Report
+
+mod v2;
+mod v3;
+
+pub use self::v2::*;
+pub use self::v3::*;
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::Packet;
+use std::fmt;
+
+/// The time to live of all IGMP messages, which are never forwarded
+/// beyond the local network.
+const IGMP_TTL: u8 = 1;
+
+/// [IANA] assigned IGMP message type.
+///
+/// A list of supported types is under [`IgmpTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/igmp-type-numbers/igmp-type-numbers.xhtml
+/// [`IgmpTypes`]: IgmpTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct IgmpType(pub u8);
+
+/// Supported IGMP message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod IgmpTypes {
+    use super::IgmpType;
+
+    /// Membership query of all versions.
+    pub const MembershipQuery: IgmpType = IgmpType(0x11);
+
+    /// Version 1 membership report.
+    pub const V1MembershipReport: IgmpType = IgmpType(0x12);
+
+    /// Version 2 membership report.
+    pub const V2MembershipReport: IgmpType = IgmpType(0x16);
+
+    /// Version 2 leave group.
+    pub const LeaveGroup: IgmpType = IgmpType(0x17);
+
+    /// Version 3 membership report.
+    pub const V3MembershipReport: IgmpType = IgmpType(0x22);
+}
+
+impl fmt::Display for IgmpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                IgmpTypes::MembershipQuery => "Membership Query".to_string(),
+                IgmpTypes::V1MembershipReport => "Version 1 Membership Report".to_string(),
+                IgmpTypes::V2MembershipReport => "Version 2 Membership Report".to_string(),
+                IgmpTypes::LeaveGroup => "Leave Group".to_string(),
+                IgmpTypes::V3MembershipReport => "Version 3 Membership Report".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// Type of a group record in an IGMPv3 or MLDv2 report.
+///
+/// A list of supported types is under [`GroupRecordTypes`].
+///
+/// [`GroupRecordTypes`]: GroupRecordTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct GroupRecordType(pub u8);
+
+/// Supported group record types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GroupRecordTypes {
+    use super::GroupRecordType;
+
+    /// Current-state record, the filter mode is include.
+    pub const ModeIsInclude: GroupRecordType = GroupRecordType(1);
+
+    /// Current-state record, the filter mode is exclude.
+    pub const ModeIsExclude: GroupRecordType = GroupRecordType(2);
+
+    /// Filter-mode-change record, the filter mode changed to include.
+    pub const ChangeToIncludeMode: GroupRecordType = GroupRecordType(3);
+
+    /// Filter-mode-change record, the filter mode changed to exclude.
+    pub const ChangeToExcludeMode: GroupRecordType = GroupRecordType(4);
+
+    /// Source-list-change record, the sources are newly allowed.
+    pub const AllowNewSources: GroupRecordType = GroupRecordType(5);
+
+    /// Source-list-change record, the sources are no longer allowed.
+    pub const BlockOldSources: GroupRecordType = GroupRecordType(6);
+}
+
+impl fmt::Display for GroupRecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GroupRecordTypes::ModeIsInclude => "MODE_IS_INCLUDE".to_string(),
+                GroupRecordTypes::ModeIsExclude => "MODE_IS_EXCLUDE".to_string(),
+                GroupRecordTypes::ChangeToIncludeMode => "CHANGE_TO_INCLUDE_MODE".to_string(),
+                GroupRecordTypes::ChangeToExcludeMode => "CHANGE_TO_EXCLUDE_MODE".to_string(),
+                GroupRecordTypes::AllowNewSources => "ALLOW_NEW_SOURCES".to_string(),
+                GroupRecordTypes::BlockOldSources => "BLOCK_OLD_SOURCES".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// Decodes the 8-bit floating point representation of the Max Resp Code
+/// and QQIC fields. Values below 128 are literal, otherwise the field is
+/// `1 | exp (3) | mant (4)` for `(mant | 0x10) << (exp + 3)`.
+#[inline]
+pub(crate) fn decode_float_code(code: u8) -> u32 {
+    if code < 0x80 {
+        u32::from(code)
+    } else {
+        let exp = (code >> 4) & 0x07;
+        let mant = code & 0x0f;
+        u32::from(mant | 0x10) << (exp + 3)
+    }
+}
+
+/// Returns the length of the IGMP message from the envelope's total
+/// length, excluding the Ethernet padding of short frames.
+#[inline]
+fn igmp_len(envelope: &Ipv4) -> usize {
+    (envelope.total_length() as usize).saturating_sub(envelope.header_len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn igmp_type_to_string() {
+        assert_eq!("Leave Group", IgmpTypes::LeaveGroup.to_string());
+        assert_eq!("0x30", IgmpType(0x30).to_string());
+        assert_eq!(
+            "CHANGE_TO_EXCLUDE_MODE",
+            GroupRecordTypes::ChangeToExcludeMode.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_max_resp_code() {
+        assert_eq!(100, decode_float_code(100));
+        // exp = 0, mant = 0
+        assert_eq!(128, decode_float_code(0x80));
+        // exp = 7, mant = 15
+        assert_eq!(31744, decode_float_code(0xff));
+    }
+}
@@ -0,0 +1,348 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{igmp_len, IgmpType, IgmpTypes, IGMP_TTL};
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::ProtocolNumbers;
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// Internet Group Management Protocol v2 message based on [IETF RFC 2236].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Type     | Max Resp Time |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Group Address                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*:           Membership Query, Version 2 Membership Report,
+///                     Leave Group, or Version 1 Membership Report for
+///                     backward compatibility.
+///
+/// - *Max Resp Time*:  The maximum time allowed before sending a
+///                     responding report, in units of 1/10 second. Only
+///                     meaningful in membership query messages.
+///
+/// - *Checksum*:       The 16-bit one's complement of the one's complement
+///                     sum of the whole IGMP message.
+///
+/// - *Group Address*:  Zero in a general query, otherwise the multicast
+///                     group address being queried, reported or left.
+///
+/// A membership query that is longer than 8 octets is an IGMPv3 query and
+/// must be parsed as [`Igmpv3Query`] instead.
+///
+/// [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236#section-2
+/// [`Igmpv3Query`]: crate::packets::Igmpv3Query
+pub struct Igmpv2 {
+    envelope: Ipv4,
+    header: NonNull<Igmpv2Header>,
+    offset: usize,
+}
+
+impl Igmpv2 {
+    #[inline]
+    fn header(&self) -> &Igmpv2Header {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Igmpv2Header {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn msg_type(&self) -> IgmpType {
+        IgmpType(self.header().msg_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_msg_type(&mut self, msg_type: IgmpType) {
+        self.header_mut().msg_type = msg_type.0;
+    }
+
+    /// Returns the maximum response time in units of 1/10 second.
+    #[inline]
+    pub fn max_resp_time(&self) -> u8 {
+        self.header().max_resp_time
+    }
+
+    /// Sets the maximum response time in units of 1/10 second.
+    #[inline]
+    pub fn set_max_resp_time(&mut self, max_resp_time: u8) {
+        self.header_mut().max_resp_time = max_resp_time;
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the group address.
+    #[inline]
+    pub fn group(&self) -> Ipv4Addr {
+        self.header().group
+    }
+
+    /// Sets the group address.
+    #[inline]
+    pub fn set_group(&mut self, group: Ipv4Addr) {
+        self.header_mut().group = group;
+    }
+
+    /// Returns whether the message is a general query for all groups.
+    #[inline]
+    pub fn is_general_query(&self) -> bool {
+        self.msg_type() == IgmpTypes::MembershipQuery && self.group().is_unspecified()
+    }
+
+    #[inline]
+    fn compute_checksum(&mut self) {
+        self.header_mut().checksum = u16be::default();
+
+        // the message is fixed length, the rest of the frame is padding.
+        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.header_len()) {
+            let data = unsafe { data.as_ref() };
+            let checksum = checksum::compute(0, data);
+            self.header_mut().checksum = checksum.into();
+        } else {
+            // we are reading the fixed header, should never run out
+            unreachable!()
+        }
+    }
+}
+
+impl fmt::Debug for Igmpv2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmpv2")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("max_resp_time", &self.max_resp_time())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("group", &self.group())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Igmpv2 {
+    /// The preceding type for an IGMP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        Igmpv2Header::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Igmpv2 {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an IGMPv2 packet.
+    ///
+    /// [`Ipv4::protocol`] must be set to [`ProtocolNumbers::Igmp`], and the
+    /// message must be one of the IGMPv2 types. A membership query must be
+    /// exactly 8 octets long. Otherwise, a parsing error is returned.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Igmp,
+            ParseError::new("not an IGMP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<Igmpv2Header>(offset)?;
+
+        let msg_type = IgmpType(unsafe { header.as_ref() }.msg_type);
+        let is_v2 = match msg_type {
+            IgmpTypes::MembershipQuery => igmp_len(&envelope) == Igmpv2Header::size_of(),
+            IgmpTypes::V1MembershipReport
+            | IgmpTypes::V2MembershipReport
+            | IgmpTypes::LeaveGroup => true,
+            _ => false,
+        };
+        ensure!(is_v2, ParseError::new("not an IGMPv2 packet."));
+
+        Ok(Igmpv2 {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IGMPv2 packet to the beginning of the envelope's payload.
+    ///
+    /// The message is a general query. [`Ipv4::protocol`] is set to
+    /// [`ProtocolNumbers::Igmp`] and [`Ipv4::ttl`] is set to 1. The router
+    /// alert option is not added to the envelope.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`Ipv4::ttl`]: crate::packets::ip::v4::Ipv4::ttl
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, Igmpv2Header::size_of())?;
+        let header = mbuf.write_data(offset, &Igmpv2Header::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Igmp);
+        envelope.set_ttl(IGMP_TTL);
+
+        Ok(Igmpv2 {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the 8-octet message.
+    ///
+    /// [`checksum`]: Igmpv2::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// IGMPv2 message.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Igmpv2Header {
+    msg_type: u8,
+    max_resp_time: u8,
+    checksum: u16be,
+    group: Ipv4Addr,
+}
+
+impl Default for Igmpv2Header {
+    fn default() -> Igmpv2Header {
+        Igmpv2Header {
+            msg_type: IgmpTypes::MembershipQuery.0,
+            max_resp_time: 0,
+            checksum: u16be::default(),
+            group: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IGMPV2_REPORT_PACKET, IGMPV3_REPORT_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_igmpv2_header() {
+        assert_eq!(8, Igmpv2Header::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv2_packet() {
+        let packet = Mbuf::from_bytes(&IGMPV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut igmp = ipv4.parse::<Igmpv2>().unwrap();
+
+        assert_eq!(IgmpTypes::V2MembershipReport, igmp.msg_type());
+        assert_eq!(0, igmp.max_resp_time());
+        assert_eq!(0xf9fc, igmp.checksum());
+        assert_eq!(Ipv4Addr::new(239, 1, 1, 1), igmp.group());
+        assert!(!igmp.is_general_query());
+
+        // the ethernet padding is not part of the checksum
+        igmp.reconcile();
+        assert_eq!(0xf9fc, igmp.checksum());
+    }
+
+    #[capsule::test]
+    fn parse_non_igmpv2_packet() {
+        let packet = Mbuf::from_bytes(&IGMPV3_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Igmpv2>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_igmpv2_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut igmp = ipv4.push::<Igmpv2>().unwrap();
+
+        assert_eq!(8, igmp.len());
+        assert_eq!(ProtocolNumbers::Igmp, igmp.envelope().protocol());
+        assert_eq!(1, igmp.envelope().ttl());
+        assert!(igmp.is_general_query());
+
+        igmp.set_msg_type(IgmpTypes::LeaveGroup);
+        igmp.set_group(Ipv4Addr::new(239, 1, 1, 1));
+        igmp.reconcile_all();
+
+        assert_eq!(IgmpTypes::LeaveGroup, igmp.msg_type());
+        assert_eq!(0xf8fc, igmp.checksum());
+    }
+}
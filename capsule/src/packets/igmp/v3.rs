@@ -0,0 +1,933 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{decode_float_code, igmp_len, GroupRecordType, IgmpType, IgmpTypes, IGMP_TTL};
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::ProtocolNumbers;
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+// The suppress router-side processing bit of the flags field.
+const FLAG_S: u8 = 0x08;
+
+// The querier's robustness variable bits of the flags field.
+const QRV_MASK: u8 = 0x07;
+
+/// Computes the IGMP checksum over the whole message.
+#[inline]
+fn compute_checksum<T: Packet>(packet: &T) -> u16 {
+    match packet
+        .mbuf()
+        .read_data_slice::<u8>(packet.offset(), packet.len())
+    {
+        Ok(data) => checksum::compute(0, unsafe { data.as_ref() }),
+        // we are reading till the end of buffer, should never run out
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Internet Group Management Protocol v3 membership query based on
+/// [IETF RFC 3376].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 0x11  | Max Resp Code |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Group Address                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Resv  |S| QRV |     QQIC      |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Source Address [1]                      |
+/// +-                                                             -+
+/// .                               .                               .
+/// +-                                                             -+
+/// |                       Source Address [N]                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Max Resp Code*:  The maximum time allowed before sending a
+///                     responding report, in units of 1/10 second. Values
+///                     of 128 and above are in a floating point format.
+///
+/// - *Group Address*:  Zero in a general query, otherwise the multicast
+///                     group address being queried.
+///
+/// - *S*:              Suppress router-side processing, whether receiving
+///                     routers should suppress their timer updates.
+///
+/// - *QRV*:            Querier's robustness variable.
+///
+/// - *QQIC*:           Querier's query interval code, in seconds. Values
+///                     of 128 and above are in a floating point format.
+///
+/// - *Number of Sources (N)*:
+///                     The number of source addresses present in the
+///                     query.
+///
+/// - *Source Address [i]*:
+///                     The unicast source addresses of a group-and-source
+///                     specific query.
+///
+/// The source addresses are considered part of the header.
+///
+/// [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376#section-4.1
+pub struct Igmpv3Query {
+    envelope: Ipv4,
+    header: NonNull<Igmpv3QueryHeader>,
+    offset: usize,
+}
+
+impl Igmpv3Query {
+    #[inline]
+    fn header(&self) -> &Igmpv3QueryHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Igmpv3QueryHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the encoded maximum response code.
+    #[inline]
+    pub fn max_resp_code(&self) -> u8 {
+        self.header().max_resp_code
+    }
+
+    /// Sets the encoded maximum response code.
+    #[inline]
+    pub fn set_max_resp_code(&mut self, max_resp_code: u8) {
+        self.header_mut().max_resp_code = max_resp_code;
+    }
+
+    /// Returns the decoded maximum response time in units of 1/10 second.
+    #[inline]
+    pub fn max_resp_time(&self) -> u32 {
+        decode_float_code(self.max_resp_code())
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the group address.
+    #[inline]
+    pub fn group(&self) -> Ipv4Addr {
+        self.header().group
+    }
+
+    /// Sets the group address.
+    #[inline]
+    pub fn set_group(&mut self, group: Ipv4Addr) {
+        self.header_mut().group = group;
+    }
+
+    /// Returns whether router-side processing is suppressed.
+    #[inline]
+    pub fn suppress_router_processing(&self) -> bool {
+        self.header().flags & FLAG_S != 0
+    }
+
+    /// Sets whether router-side processing is suppressed.
+    #[inline]
+    pub fn set_suppress_router_processing(&mut self, suppress: bool) {
+        if suppress {
+            self.header_mut().flags |= FLAG_S;
+        } else {
+            self.header_mut().flags &= !FLAG_S;
+        }
+    }
+
+    /// Returns the querier's robustness variable.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        self.header().flags & QRV_MASK
+    }
+
+    /// Sets the querier's robustness variable. Only the lowest 3 bits are
+    /// used.
+    #[inline]
+    pub fn set_qrv(&mut self, qrv: u8) {
+        let flags = (self.header().flags & !QRV_MASK) | (qrv & QRV_MASK);
+        self.header_mut().flags = flags;
+    }
+
+    /// Returns the encoded querier's query interval code.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        self.header().qqic
+    }
+
+    /// Sets the encoded querier's query interval code.
+    #[inline]
+    pub fn set_qqic(&mut self, qqic: u8) {
+        self.header_mut().qqic = qqic;
+    }
+
+    /// Returns the decoded querier's query interval in seconds.
+    #[inline]
+    pub fn query_interval(&self) -> u32 {
+        decode_float_code(self.qqic())
+    }
+
+    /// Returns whether the message is a general query for all groups.
+    #[inline]
+    pub fn is_general_query(&self) -> bool {
+        self.group().is_unspecified()
+    }
+
+    /// Returns the number of source addresses.
+    #[inline]
+    pub fn source_count(&self) -> u16 {
+        self.header().num_sources.into()
+    }
+
+    /// Returns the source addresses.
+    pub fn sources(&self) -> Vec<Ipv4Addr> {
+        let count = self.source_count() as usize;
+        if count == 0 {
+            return vec![];
+        }
+
+        let offset = self.offset + Igmpv3QueryHeader::size_of();
+        match self.mbuf().read_data_slice::<Ipv4Addr>(offset, count) {
+            Ok(sources) => unsafe { sources.as_ref() }.to_vec(),
+            // the sources are checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Replaces the source addresses.
+    pub fn set_sources(&mut self, sources: &[Ipv4Addr]) -> Fallible<()> {
+        let old_len = self.source_count() as usize * 4;
+        let new_len = sources.len() * 4;
+        let offset = self.offset + Igmpv3QueryHeader::size_of();
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        if !sources.is_empty() {
+            mbuf.write_data_slice(offset, sources)?;
+        }
+
+        self.header_mut().num_sources = (sources.len() as u16).into();
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Igmpv3Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmpv3_query")
+            .field("max_resp_code", &self.max_resp_code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("group", &self.group())
+            .field("s", &self.suppress_router_processing())
+            .field("qrv", &self.qrv())
+            .field("qqic", &self.qqic())
+            .field("sources", &self.sources())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Igmpv3Query {
+    /// The preceding type for an IGMP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the query, including the source addresses.
+    #[inline]
+    fn header_len(&self) -> usize {
+        Igmpv3QueryHeader::size_of() + self.source_count() as usize * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Igmpv3Query {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an IGMPv3 membership query.
+    ///
+    /// [`Ipv4::protocol`] must be set to [`ProtocolNumbers::Igmp`], and the
+    /// message must be a membership query of at least 12 octets. Otherwise,
+    /// a parsing error is returned.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Igmp,
+            ParseError::new("not an IGMP packet.")
+        );
+        ensure!(
+            igmp_len(&envelope) >= Igmpv3QueryHeader::size_of(),
+            ParseError::new("not an IGMPv3 query.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<Igmpv3QueryHeader>(offset)?;
+
+        let header_ref = unsafe { header.as_ref() };
+        ensure!(
+            IgmpType(header_ref.msg_type) == IgmpTypes::MembershipQuery,
+            ParseError::new("not an IGMPv3 query.")
+        );
+
+        let count = u16::from(header_ref.num_sources) as usize;
+        if count > 0 {
+            let _ =
+                mbuf.read_data_slice::<Ipv4Addr>(offset + Igmpv3QueryHeader::size_of(), count)?;
+        }
+
+        Ok(Igmpv3Query {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IGMPv3 membership query to the beginning of the
+    /// envelope's payload.
+    ///
+    /// The message is a general query without source addresses.
+    /// [`Ipv4::protocol`] is set to [`ProtocolNumbers::Igmp`] and
+    /// [`Ipv4::ttl`] is set to 1. The router alert option is not added to
+    /// the envelope.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`Ipv4::ttl`]: crate::packets::ip::v4::Ipv4::ttl
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, Igmpv3QueryHeader::size_of())?;
+        let header = mbuf.write_data(offset, &Igmpv3QueryHeader::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Igmp);
+        envelope.set_ttl(IGMP_TTL);
+
+        Ok(Igmpv3Query {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the whole message.
+    ///
+    /// [`checksum`]: Igmpv3Query::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.header_mut().checksum = u16be::default();
+        let checksum = compute_checksum(self);
+        self.header_mut().checksum = checksum.into();
+    }
+}
+
+/// IGMPv3 query fixed fields.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Igmpv3QueryHeader {
+    msg_type: u8,
+    max_resp_code: u8,
+    checksum: u16be,
+    group: Ipv4Addr,
+    flags: u8,
+    qqic: u8,
+    num_sources: u16be,
+}
+
+impl Default for Igmpv3QueryHeader {
+    fn default() -> Igmpv3QueryHeader {
+        Igmpv3QueryHeader {
+            msg_type: IgmpTypes::MembershipQuery.0,
+            max_resp_code: 0,
+            checksum: u16be::default(),
+            group: Ipv4Addr::UNSPECIFIED,
+            flags: 0,
+            qqic: 0,
+            num_sources: u16be::default(),
+        }
+    }
+}
+
+/// Internet Group Management Protocol v3 membership report based on
+/// [IETF RFC 3376].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 0x22  |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |  Number of Group Records (M)  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                        Group Record [1]                       .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                               .                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                        Group Record [M]                       .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Number of Group Records (M)*:
+///                     The number of group records present in the report.
+///
+/// - *Group Record*:   A block of fields containing information about
+///                     the sender's membership in a single multicast group.
+///                     Use [`group_records_iter`] to read the records.
+///
+/// The group records are the payload of the report.
+///
+/// [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376#section-4.2
+/// [`group_records_iter`]: Igmpv3Report::group_records_iter
+pub struct Igmpv3Report {
+    envelope: Ipv4,
+    header: NonNull<Igmpv3ReportHeader>,
+    offset: usize,
+}
+
+impl Igmpv3Report {
+    #[inline]
+    fn header(&self) -> &Igmpv3ReportHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Igmpv3ReportHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the number of group records.
+    #[inline]
+    pub fn group_record_count(&self) -> u16 {
+        self.header().num_records.into()
+    }
+
+    /// Returns the group records, which may be followed by the Ethernet
+    /// padding of a short frame.
+    #[inline]
+    pub fn group_records(&self) -> &[u8] {
+        let len = self.payload_len();
+        if len == 0 {
+            return &[];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<u8>(self.payload_offset(), len)
+        {
+            Ok(records) => unsafe { records.as_ref() },
+            // we are reading till the end of buffer, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the group records.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let report = ipv4.parse::<Igmpv3Report>()?;
+    /// let mut iter = report.group_records_iter();
+    ///
+    /// while let Some(record) = iter.next()? {
+    ///     println!("{} {}", record.record_type(), record.group());
+    /// }
+    /// ```
+    #[inline]
+    pub fn group_records_iter(&self) -> Igmpv3GroupRecordsIterator<'_> {
+        Igmpv3GroupRecordsIterator::new(self.group_records(), self.group_record_count())
+    }
+
+    /// Adds a group record without auxiliary data after the existing
+    /// records.
+    ///
+    /// Call [`reconcile_all`] afterwards to fix the checksum and the
+    /// lengths of the envelope.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_group_record(
+        &mut self,
+        record_type: GroupRecordType,
+        group: Ipv4Addr,
+        sources: &[Ipv4Addr],
+    ) -> Fallible<()> {
+        // skips to the end of the records, before any padding.
+        let mut iter = self.group_records_iter();
+        while iter.next()?.is_some() {}
+        let offset = self.payload_offset() + iter.offset;
+
+        let mut bytes = Vec::with_capacity(8 + sources.len() * 4);
+        bytes.push(record_type.0);
+        bytes.push(0);
+        bytes.extend_from_slice(&(sources.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&group.octets());
+        for source in sources {
+            bytes.extend_from_slice(&source.octets());
+        }
+
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, bytes.len())?;
+        mbuf.write_data_slice(offset, &bytes)?;
+
+        let count = self.group_record_count() + 1;
+        self.header_mut().num_records = count.into();
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Igmpv3Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmpv3_report")
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("group_record_count", &self.group_record_count())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Igmpv3Report {
+    /// The preceding type for an IGMP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        Igmpv3ReportHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Igmpv3Report {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an IGMPv3 membership report.
+    ///
+    /// [`Ipv4::protocol`] must be set to [`ProtocolNumbers::Igmp`], and the
+    /// message type must be [`IgmpTypes::V3MembershipReport`]. Otherwise,
+    /// a parsing error is returned.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    /// [`IgmpTypes::V3MembershipReport`]: IgmpTypes::V3MembershipReport
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Igmp,
+            ParseError::new("not an IGMP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<Igmpv3ReportHeader>(offset)?;
+
+        ensure!(
+            IgmpType(unsafe { header.as_ref() }.msg_type) == IgmpTypes::V3MembershipReport,
+            ParseError::new("not an IGMPv3 report.")
+        );
+
+        Ok(Igmpv3Report {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IGMPv3 membership report without group records to the
+    /// beginning of the envelope's payload.
+    ///
+    /// [`Ipv4::protocol`] is set to [`ProtocolNumbers::Igmp`] and
+    /// [`Ipv4::ttl`] is set to 1. The router alert option is not added to
+    /// the envelope.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`Ipv4::ttl`]: crate::packets::ip::v4::Ipv4::ttl
+    /// [`ProtocolNumbers::Igmp`]: crate::packets::ip::ProtocolNumbers::Igmp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, Igmpv3ReportHeader::size_of())?;
+        let header = mbuf.write_data(offset, &Igmpv3ReportHeader::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Igmp);
+        envelope.set_ttl(IGMP_TTL);
+
+        Ok(Igmpv3Report {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the whole message.
+    ///
+    /// [`checksum`]: Igmpv3Report::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.header_mut().checksum = u16be::default();
+        let checksum = compute_checksum(self);
+        self.header_mut().checksum = checksum.into();
+    }
+}
+
+/// IGMPv3 report fixed fields.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Igmpv3ReportHeader {
+    msg_type: u8,
+    reserved1: u8,
+    checksum: u16be,
+    reserved2: u16be,
+    num_records: u16be,
+}
+
+impl Default for Igmpv3ReportHeader {
+    fn default() -> Igmpv3ReportHeader {
+        Igmpv3ReportHeader {
+            msg_type: IgmpTypes::V3MembershipReport.0,
+            reserved1: 0,
+            checksum: u16be::default(),
+            reserved2: u16be::default(),
+            num_records: u16be::default(),
+        }
+    }
+}
+
+/// A group record in an IGMPv3 membership report.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Record Type  |  Aux Data Len |     Number of Sources (N)     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Multicast Address                       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Source Address [1]                      |
+/// .                               .                               .
+/// |                       Source Address [N]                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                         Auxiliary Data                        .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Igmpv3GroupRecord<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Igmpv3GroupRecord<'a> {
+    /// Returns the type of the record.
+    #[inline]
+    pub fn record_type(&self) -> GroupRecordType {
+        GroupRecordType(self.data[0])
+    }
+
+    /// Returns the multicast address the record pertains to.
+    #[inline]
+    pub fn group(&self) -> Ipv4Addr {
+        Ipv4Addr::new(self.data[4], self.data[5], self.data[6], self.data[7])
+    }
+
+    /// Returns the source addresses.
+    pub fn sources(&self) -> Vec<Ipv4Addr> {
+        let count = u16::from_be_bytes([self.data[2], self.data[3]]) as usize;
+        self.data[8..8 + count * 4]
+            .chunks_exact(4)
+            .map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+            .collect()
+    }
+
+    /// Returns the auxiliary data.
+    #[inline]
+    pub fn aux_data(&self) -> &'a [u8] {
+        let len = self.data[1] as usize * 4;
+        &self.data[self.data.len() - len..]
+    }
+}
+
+/// An iterator that iterates through the group records of an IGMPv3
+/// membership report.
+#[derive(Debug)]
+pub struct Igmpv3GroupRecordsIterator<'a> {
+    data: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> Igmpv3GroupRecordsIterator<'a> {
+    fn new(data: &'a [u8], count: u16) -> Self {
+        Igmpv3GroupRecordsIterator {
+            data,
+            offset: 0,
+            remaining: count,
+        }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<Igmpv3GroupRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let data = &self.data[self.offset..];
+        ensure!(
+            data.len() >= 8,
+            ParseError::new("IGMPv3 group record is truncated.")
+        );
+
+        let sources = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let len = 8 + sources * 4 + data[1] as usize * 4;
+        ensure!(
+            data.len() >= len,
+            ParseError::new("IGMPv3 group record is truncated.")
+        );
+
+        self.offset += len;
+        self.remaining -= 1;
+
+        Ok(Some(Igmpv3GroupRecord { data: &data[..len] }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{
+        IGMPV2_REPORT_PACKET, IGMPV3_QUERY_PACKET, IGMPV3_REPORT_PACKET,
+    };
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_igmpv3_headers() {
+        assert_eq!(12, Igmpv3QueryHeader::size_of());
+        assert_eq!(8, Igmpv3ReportHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv3_query() {
+        let packet = Mbuf::from_bytes(&IGMPV3_QUERY_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let query = ipv4.parse::<Igmpv3Query>().unwrap();
+
+        assert_eq!(100, query.max_resp_code());
+        assert_eq!(100, query.max_resp_time());
+        assert_eq!(Ipv4Addr::new(232, 1, 1, 1), query.group());
+        assert!(!query.is_general_query());
+        assert!(!query.suppress_router_processing());
+        assert_eq!(2, query.qrv());
+        assert_eq!(125, query.query_interval());
+        assert_eq!(
+            vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+            query.sources()
+        );
+        assert_eq!(20, query.header_len());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv2_as_igmpv3_query() {
+        let packet = Mbuf::from_bytes(&IGMPV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Igmpv3Query>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_igmpv3_query() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut query = ipv4.push::<Igmpv3Query>().unwrap();
+
+        assert!(query.is_general_query());
+
+        query.set_max_resp_code(100);
+        query.set_group(Ipv4Addr::new(232, 1, 1, 1));
+        query.set_suppress_router_processing(true);
+        query.set_qrv(2);
+        query.set_qqic(125);
+        query
+            .set_sources(&[Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)])
+            .unwrap();
+        query.reconcile_all();
+
+        assert_eq!(20, query.len());
+        assert!(query.suppress_router_processing());
+        assert_eq!(2, query.qrv());
+        assert_eq!(2, query.sources().len());
+
+        // parses back from the reconciled envelope
+        let ipv4 = query.deparse();
+        let query = ipv4.parse::<Igmpv3Query>().unwrap();
+        assert_eq!(Ipv4Addr::new(10, 0, 0, 2), query.sources()[1]);
+
+        let mut query = query;
+        query.set_sources(&[]).unwrap();
+        assert_eq!(12, query.len());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv3_report() {
+        let packet = Mbuf::from_bytes(&IGMPV3_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut report = ipv4.parse::<Igmpv3Report>().unwrap();
+
+        assert_eq!(2, report.group_record_count());
+
+        let mut iter = report.group_records_iter();
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::ChangeToExcludeMode, record.record_type());
+        assert_eq!(Ipv4Addr::new(239, 1, 1, 1), record.group());
+        assert!(record.sources().is_empty());
+        assert!(record.aux_data().is_empty());
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::AllowNewSources, record.record_type());
+        assert_eq!(Ipv4Addr::new(232, 1, 1, 1), record.group());
+        assert_eq!(vec![Ipv4Addr::new(10, 0, 0, 1)], record.sources());
+
+        assert!(iter.next().unwrap().is_none());
+
+        let expected = report.checksum();
+        report.reconcile();
+        assert_eq!(expected, report.checksum());
+    }
+
+    #[capsule::test]
+    fn push_igmpv3_report() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut report = ipv4.push::<Igmpv3Report>().unwrap();
+
+        report
+            .add_group_record(
+                GroupRecordTypes::ChangeToExcludeMode,
+                Ipv4Addr::new(239, 1, 1, 1),
+                &[],
+            )
+            .unwrap();
+        report
+            .add_group_record(
+                GroupRecordTypes::AllowNewSources,
+                Ipv4Addr::new(232, 1, 1, 1),
+                &[Ipv4Addr::new(10, 0, 0, 1)],
+            )
+            .unwrap();
+        report.reconcile_all();
+
+        assert_eq!(2, report.group_record_count());
+        assert_eq!(8 + 8 + 12, report.len());
+
+        // the same message as the captured report
+        assert_eq!(0xf1f5, report.checksum());
+    }
+}
@@ -0,0 +1,682 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::dpdk::BufferError;
+use crate::packets::checksum;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{
+    EtherType, EtherTypeEnvelope, EtherTypes, Ethernet, Internal, Packet, ParseError,
+};
+use crate::{ensure, Mbuf, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+// Flags indicating the presence of the optional fields.
+const GRE_FLAG_C: u16 = 0x8000;
+const GRE_FLAG_K: u16 = 0x2000;
+const GRE_FLAG_S: u16 = 0x1000;
+const GRE_VERSION_MASK: u16 = 0x0007;
+
+// Each optional field is 4 octets long.
+const GRE_FIELD_LEN: usize = 4;
+
+/// Generic Routing Encapsulation packet based on [IETF RFC 2784] and
+/// [IETF RFC 2890].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |C| |K|S| Reserved0       | Ver |         Protocol Type         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Checksum (optional)      |       Reserved1 (Optional)    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Key (optional)                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Sequence Number (Optional)                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *C*:                 Checksum Present. The checksum and the reserved1
+///                        fields are present if set.
+///
+/// - *K*:                 Key Present. The key field is present if set.
+///
+/// - *S*:                 Sequence Number Present. The sequence number field
+///                        is present if set.
+///
+/// - *Version*:           3-bit version number, must be 0.
+///
+/// - *Protocol Type*:     16-bit EtherType of the payload packet.
+///
+/// - *Checksum*:          The one's complement of the one's complement sum
+///                        of the GRE header and the payload packet.
+///
+/// - *Key*:               32-bit number identifying an individual traffic
+///                        flow within a tunnel.
+///
+/// - *Sequence Number*:   32-bit number used to order the packets of a
+///                        flow.
+///
+/// # Payload
+///
+/// The payload is identified by the protocol type. [IPv4] and [IPv6]
+/// payloads can be parsed directly, for example with
+/// `gre.parse::<Ipv4<Gre<E>>>()`. When the protocol type is
+/// [`EtherTypes::Teb`], the payload is an Ethernet frame, as used by NVGRE
+/// per [IETF RFC 7637], and can be parsed with `gre.parse::<Ethernet<Gre<E>>>()`.
+///
+/// [IETF RFC 2784]: https://tools.ietf.org/html/rfc2784
+/// [IETF RFC 2890]: https://tools.ietf.org/html/rfc2890
+/// [IETF RFC 7637]: https://tools.ietf.org/html/rfc7637
+/// [IPv4]: crate::packets::ip::v4::Ipv4
+/// [IPv6]: crate::packets::ip::v6::Ipv6
+/// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+pub struct Gre<E: IpPacket> {
+    envelope: E,
+    header: NonNull<GreHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Gre<E> {
+    #[inline]
+    fn header(&self) -> &GreHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GreHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    #[inline]
+    fn flag(&self, flag: u16) -> bool {
+        self.header().flags_version & u16be::from(flag) > u16be::MIN
+    }
+
+    #[inline]
+    fn set_flag(&mut self, flag: u16, on: bool) {
+        if on {
+            self.header_mut().flags_version |= u16be::from(flag);
+        } else {
+            self.header_mut().flags_version &= u16be::from(!flag);
+        }
+    }
+
+    /// Returns the buffer offset of an optional field. The optional fields
+    /// are in the order of checksum, key and sequence number.
+    #[inline]
+    fn field_offset(&self, flag: u16) -> usize {
+        let mut offset = self.offset + GreHeader::size_of();
+        for &f in &[GRE_FLAG_C, GRE_FLAG_K, GRE_FLAG_S] {
+            if f == flag {
+                break;
+            }
+            if self.flag(f) {
+                offset += GRE_FIELD_LEN;
+            }
+        }
+        offset
+    }
+
+    #[inline]
+    fn read_field(&self, flag: u16) -> Option<u32> {
+        if self.flag(flag) {
+            match self.mbuf().read_data::<u32be>(self.field_offset(flag)) {
+                Ok(field) => Some(unsafe { *field.as_ref() }.into()),
+                // the header length is checked when parsed, should never run out
+                Err(_) => unreachable!(),
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Writes the optional field, adding or removing it from the header
+    /// as needed.
+    #[inline]
+    fn write_field(&mut self, flag: u16, value: Option<u32>) -> Fallible<()> {
+        let offset = self.field_offset(flag);
+
+        match (self.flag(flag), value) {
+            (false, Some(_)) => {
+                self.mbuf_mut().extend(offset, GRE_FIELD_LEN)?;
+                self.set_flag(flag, true);
+            }
+            (true, None) => {
+                self.mbuf_mut().shrink(offset, GRE_FIELD_LEN)?;
+                self.set_flag(flag, false);
+            }
+            _ => (),
+        }
+
+        if let Some(value) = value {
+            let _ = self.mbuf_mut().write_data(offset, &u32be::from(value))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the checksum field is present.
+    #[inline]
+    pub fn has_checksum(&self) -> bool {
+        self.flag(GRE_FLAG_C)
+    }
+
+    /// Returns whether the key field is present.
+    #[inline]
+    pub fn has_key(&self) -> bool {
+        self.flag(GRE_FLAG_K)
+    }
+
+    /// Returns whether the sequence number field is present.
+    #[inline]
+    pub fn has_sequence_no(&self) -> bool {
+        self.flag(GRE_FLAG_S)
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let flags_version: u16 = self.header().flags_version.into();
+        (flags_version & GRE_VERSION_MASK) as u8
+    }
+
+    /// Returns the protocol type of the payload.
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    /// Sets the protocol type of the payload.
+    #[inline]
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        self.header_mut().protocol_type = protocol_type.0.into()
+    }
+
+    /// Returns the checksum if present.
+    #[inline]
+    pub fn checksum(&self) -> Option<u16> {
+        // the checksum shares the field with the 16-bit reserved1
+        self.read_field(GRE_FLAG_C)
+            .map(|field| (field >> 16) as u16)
+    }
+
+    /// Adds the checksum field to the header. The checksum is computed
+    /// when the packet is reconciled.
+    #[inline]
+    pub fn enable_checksum(&mut self) -> Fallible<()> {
+        let field = self.read_field(GRE_FLAG_C).unwrap_or_default();
+        self.write_field(GRE_FLAG_C, Some(field))
+    }
+
+    /// Removes the checksum field from the header.
+    #[inline]
+    pub fn disable_checksum(&mut self) -> Fallible<()> {
+        self.write_field(GRE_FLAG_C, None)
+    }
+
+    /// Returns the key if present.
+    #[inline]
+    pub fn key(&self) -> Option<u32> {
+        self.read_field(GRE_FLAG_K)
+    }
+
+    /// Sets the key. The key field is added to or removed from the header
+    /// depending on whether `key` is `Some` or `None`.
+    #[inline]
+    pub fn set_key(&mut self, key: Option<u32>) -> Fallible<()> {
+        self.write_field(GRE_FLAG_K, key)
+    }
+
+    /// Returns the sequence number if present.
+    #[inline]
+    pub fn sequence_no(&self) -> Option<u32> {
+        self.read_field(GRE_FLAG_S)
+    }
+
+    /// Sets the sequence number. The sequence number field is added to or
+    /// removed from the header depending on whether `sequence_no` is `Some`
+    /// or `None`.
+    #[inline]
+    pub fn set_sequence_no(&mut self, sequence_no: Option<u32>) -> Fallible<()> {
+        self.write_field(GRE_FLAG_S, sequence_no)
+    }
+
+    /// Returns the NVGRE virtual subnet ID, which is the upper 24 bits of
+    /// the key.
+    #[inline]
+    pub fn vsid(&self) -> Option<u32> {
+        self.key().map(|key| key >> 8)
+    }
+
+    /// Returns the NVGRE flow ID, which is the lower 8 bits of the key.
+    #[inline]
+    pub fn flow_id(&self) -> Option<u8> {
+        self.key().map(|key| key as u8)
+    }
+
+    /// Sets the key to the NVGRE virtual subnet ID and flow ID. Only the
+    /// lower 24 bits of `vsid` are used.
+    #[inline]
+    pub fn set_vsid(&mut self, vsid: u32, flow_id: u8) -> Fallible<()> {
+        self.set_key(Some((vsid & 0x00ff_ffff) << 8 | u32::from(flow_id)))
+    }
+
+    #[inline]
+    fn compute_checksum(&mut self) {
+        if !self.has_checksum() {
+            return;
+        }
+
+        let offset = self.field_offset(GRE_FLAG_C);
+        let _ = self.mbuf_mut().write_data(offset, &u32be::default());
+
+        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
+            let data = unsafe { data.as_ref() };
+            let checksum = checksum::compute(0, data);
+            let _ = self
+                .mbuf_mut()
+                .write_data(offset, &u32be::from(u32::from(checksum) << 16));
+        } else {
+            // we are reading till the end of buffer, should never run out
+            unreachable!()
+        }
+    }
+}
+
+impl<E: IpPacket + Packet<Envelope = Ethernet>> Gre<E> {
+    /// Encapsulates the payload of the Ethernet frame in outer IP and GRE
+    /// headers.
+    ///
+    /// The outer headers are inserted between the Ethernet header and its
+    /// payload, and the protocol type is set to the frame's ether type. The
+    /// lengths and checksums are reconciled. The outer addresses are left
+    /// unset. After setting them, call [`reconcile_all`] again to fix the
+    /// checksums.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(ethernet: Ethernet) -> Fallible<Self> {
+        let protocol_type = ethernet.ether_type();
+        let ip = ethernet.push::<E>()?;
+        let mut gre = ip.push::<Gre<E>>()?;
+        gre.set_protocol_type(protocol_type);
+        gre.reconcile_all();
+        Ok(gre)
+    }
+
+    /// Encapsulates the Ethernet frame in the message buffer with outer
+    /// Ethernet, IP and GRE headers, with the protocol type set to
+    /// [`EtherTypes::Teb`].
+    ///
+    /// Use [`set_vsid`] to set the NVGRE virtual subnet ID. The lengths and
+    /// checksums are reconciled. The outer addresses are left unset.
+    ///
+    /// [`EtherTypes::Teb`]: crate::packets::EtherTypes::Teb
+    /// [`set_vsid`]: Gre::set_vsid
+    #[inline]
+    pub fn encap_frame(frame: Mbuf) -> Fallible<Self> {
+        let ethernet = frame.push::<Ethernet>()?;
+        let ip = ethernet.push::<E>()?;
+        let mut gre = ip.push::<Gre<E>>()?;
+        gre.set_protocol_type(EtherTypes::Teb);
+        gre.reconcile_all();
+        Ok(gre)
+    }
+
+    /// Removes the outer headers and returns the inner packet as the
+    /// payload of an Ethernet frame.
+    ///
+    /// If the payload is an Ethernet frame, the outer Ethernet, IP and GRE
+    /// headers are removed and the inner frame is returned. Otherwise, the
+    /// outer IP and GRE headers are removed, and the outer Ethernet frame's
+    /// ether type is set to the protocol type.
+    #[inline]
+    pub fn decap(self) -> Fallible<Ethernet> {
+        let protocol_type = self.protocol_type();
+        let payload_offset = self.payload_offset();
+
+        if protocol_type == EtherTypes::Teb {
+            let mut mbuf = self.reset();
+            mbuf.shrink(0, payload_offset)?;
+            mbuf.parse::<Ethernet>()
+        } else {
+            let offset = self.envelope().offset();
+            let mut ethernet = self.deparse().deparse();
+            ethernet
+                .mbuf_mut()
+                .shrink(offset, payload_offset - offset)?;
+            ethernet.set_ether_type(protocol_type);
+            Ok(ethernet)
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Gre<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gre")
+            .field("version", &self.version())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field(
+                "checksum",
+                &self
+                    .checksum()
+                    .map(|checksum| format!("0x{:04x}", checksum)),
+            )
+            .field("key", &self.key())
+            .field("sequence_no", &self.sequence_no())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Gre<E> {
+    /// The preceding type for a GRE packet can be either an [IPv4] packet,
+    /// an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the GRE header depends on the optional fields present.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.field_offset(GRE_FLAG_S) - self.offset
+            + if self.has_sequence_no() {
+                GRE_FIELD_LEN
+            } else {
+                0
+            }
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Gre::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as a GRE packet.
+    ///
+    /// The envelope's [`next_protocol`] must be set to [`ProtocolNumbers::Gre`]
+    /// and the version must be 0. Otherwise a parsing error is returned.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Gre`]: crate::packets::ip::ProtocolNumbers::Gre
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Gre,
+            ParseError::new("not a GRE packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Gre {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.version() == 0,
+            ParseError::new("not a GRE version 0 packet.")
+        );
+
+        // we've only parsed the fixed 4 bytes, make sure there's enough
+        // data for the optional fields as well.
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a GRE packet to the beginning of the envelope's payload.
+    ///
+    /// The envelope's [`next_protocol`] is set to [`ProtocolNumbers::Gre`].
+    /// None of the optional fields are present.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Gre`]: crate::packets::ip::ProtocolNumbers::Gre
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GreHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GreHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Gre);
+
+        Ok(Gre {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed over the GRE header and the payload if
+    /// the checksum field is present.
+    ///
+    /// [`checksum`]: Gre::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+impl<E: IpPacket> EtherTypeEnvelope for Gre<E> {
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        self.protocol_type()
+    }
+
+    #[inline]
+    fn set_ether_type(&mut self, ether_type: EtherType) {
+        self.set_protocol_type(ether_type)
+    }
+}
+
+/// GRE header.
+///
+/// The header only include the fixed portion of the GRE header. The
+/// optional fields are parsed separately.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GreHeader {
+    flags_version: u16be,
+    protocol_type: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{ARP_REQUEST_PACKET, GRE_PACKET, IPV4_UDP_PACKET};
+
+    #[test]
+    fn size_of_gre_header() {
+        assert_eq!(4, GreHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_gre_packet() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+
+        assert_eq!(0, gre.version());
+        assert_eq!(EtherTypes::Ipv4, gre.protocol_type());
+        assert!(!gre.has_checksum());
+        assert_eq!(None, gre.checksum());
+        assert_eq!(Some(0x0102_0304), gre.key());
+        assert_eq!(None, gre.sequence_no());
+        assert_eq!(8, gre.header_len());
+
+        // dispatches to the inner IPv4 packet
+        let inner = gre.parse::<Ipv4<Gre<Ipv4>>>().unwrap();
+        assert_eq!("139.133.217.110", inner.src().to_string());
+        let udp = inner.parse::<Udp<Ipv4<Gre<Ipv4>>>>().unwrap();
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_gre_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Gre<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_gre_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let gre = ipv4.push::<Gre<Ipv4>>().unwrap();
+
+        assert_eq!(GreHeader::size_of(), gre.len());
+        assert_eq!(0, gre.version());
+
+        // make sure the next protocol is fixed
+        assert_eq!(ProtocolNumbers::Gre, gre.envelope().next_protocol());
+    }
+
+    #[capsule::test]
+    fn add_and_remove_optional_fields() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut gre = ipv4.push::<Gre<Ipv4>>().unwrap();
+
+        assert!(gre.set_sequence_no(Some(7)).is_ok());
+        assert!(gre.set_key(Some(42)).is_ok());
+        assert!(gre.enable_checksum().is_ok());
+        assert_eq!(16, gre.header_len());
+        assert_eq!(16, gre.len());
+        assert_eq!(Some(42), gre.key());
+        assert_eq!(Some(7), gre.sequence_no());
+
+        assert!(gre.set_key(None).is_ok());
+        assert_eq!(12, gre.header_len());
+        assert_eq!(None, gre.key());
+        assert_eq!(Some(7), gre.sequence_no());
+
+        assert!(gre.set_vsid(0x12_3456, 9).is_ok());
+        assert_eq!(Some(0x12_3456), gre.vsid());
+        assert_eq!(Some(9), gre.flow_id());
+        assert_eq!(Some(7), gre.sequence_no());
+    }
+
+    #[capsule::test]
+    fn compute_checksum() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut gre = ipv4.parse::<Gre<Ipv4>>().unwrap();
+
+        assert!(gre.enable_checksum().is_ok());
+        gre.reconcile_all();
+        assert!(gre.checksum().unwrap() != 0);
+
+        // the checksum over a packet with a valid checksum is 0
+        let data = gre.mbuf().read_data_slice(gre.offset(), gre.len()).unwrap();
+        assert_eq!(0, checksum::compute(0, unsafe { data.as_ref() }));
+        assert_eq!(gre.len() as u16, gre.envelope().total_length() - 20);
+    }
+
+    #[capsule::test]
+    fn encap_and_decap_ip() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let gre = Gre::<Ipv4>::encap(ethernet).unwrap();
+
+        assert_eq!(EtherTypes::Ipv4, gre.protocol_type());
+        assert_eq!(EtherTypes::Ipv4, gre.envelope().envelope().ether_type());
+        assert_eq!(ProtocolNumbers::Gre, gre.envelope().protocol());
+        assert_eq!(gre.envelope().len() as u16, gre.envelope().total_length());
+
+        let ethernet = gre.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let bytes = ethernet
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_UDP_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+
+    #[capsule::test]
+    fn encap_and_decap_frame() {
+        let frame = Mbuf::from_bytes(&ARP_REQUEST_PACKET).unwrap();
+        let mut gre = Gre::<Ipv4>::encap_frame(frame).unwrap();
+        assert!(gre.set_vsid(5000, 0).is_ok());
+
+        assert_eq!(EtherTypes::Teb, gre.protocol_type());
+        assert_eq!(Some(5000), gre.vsid());
+
+        let inner = gre.peek::<Ethernet<Gre<Ipv4>>>().unwrap();
+        assert_eq!(EtherTypes::Arp, inner.ether_type());
+
+        let ethernet = gre.decap().unwrap();
+        assert_eq!(ARP_REQUEST_PACKET.len(), ethernet.len());
+        let bytes = ethernet
+            .mbuf()
+            .read_data_slice::<u8>(0, ARP_REQUEST_PACKET.len())
+            .unwrap();
+        assert_eq!(&ARP_REQUEST_PACKET[..], unsafe { bytes.as_ref() });
+    }
+}
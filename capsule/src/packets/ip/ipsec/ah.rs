@@ -0,0 +1,539 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::IpsecError;
+use crate::dpdk::BufferError;
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// IP Authentication Header packet based on [IETF RFC 4302].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Next Header   |  Payload Len  |          RESERVED             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Security Parameters Index (SPI)               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Sequence Number Field                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                Integrity Check Value-ICV (variable)           |
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*:       Identifies the type of the next payload after
+///                        the authentication header.
+///
+/// - *Payload Len*:       The length of AH in 32-bit words (4-byte units),
+///                        minus 2.
+///
+/// - *SPI*:               32-bit value used by the receiver to identify the
+///                        security association to which the packet is bound.
+///
+/// - *Sequence Number*:   Unsigned 32-bit monotonically increasing counter
+///                        used for anti-replay protection.
+///
+/// - *ICV*:               Variable-length field that contains the Integrity
+///                        Check Value for the packet. The length must be a
+///                        multiple of 32 bits for IPv4 and 64 bits for IPv6
+///                        when combined with the fixed fields.
+///
+/// The authentication header can follow either an [IPv4] packet, an [IPv6]
+/// packet or other IPv6 extension packets. The ICV is not computed when
+/// the packet is reconciled. It must be computed and set by the caller.
+///
+/// [IETF RFC 4302]: https://tools.ietf.org/html/rfc4302
+/// [IPv4]: crate::packets::ip::v4::Ipv4
+/// [IPv6]: crate::packets::ip::v6::Ipv6
+pub struct Ah<E: IpPacket> {
+    envelope: E,
+    header: NonNull<AhHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Ah<E> {
+    #[inline]
+    fn header(&self) -> &AhHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut AhHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the header in 4-octet units, minus 2.
+    #[inline]
+    pub fn payload_len(&self) -> u8 {
+        self.header().payload_len
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence_no(&self) -> u32 {
+        self.header().sequence_no.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence_no(&mut self, sequence_no: u32) {
+        self.header_mut().sequence_no = sequence_no.into();
+    }
+
+    /// Returns the integrity check value.
+    #[inline]
+    pub fn icv(&self) -> &[u8] {
+        match self.mbuf().read_data_slice::<u8>(
+            self.offset + AhHeader::size_of(),
+            self.header_len() - AhHeader::size_of(),
+        ) {
+            Ok(icv) => unsafe { icv.as_ref() },
+            // the header length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Sets the integrity check value.
+    ///
+    /// The header is resized to fit the ICV, and the payload length is
+    /// updated. The length of the ICV must be a multiple of 4 octets.
+    #[inline]
+    pub fn set_icv(&mut self, icv: &[u8]) -> Fallible<()> {
+        ensure!(
+            icv.len() % 4 == 0 && icv.len() <= AH_MAX_ICV_LEN,
+            IpsecError::InvalidIcvLength(icv.len())
+        );
+
+        let offset = self.offset + AhHeader::size_of();
+        let old_len = self.header_len() - AhHeader::size_of();
+        if icv.len() != old_len {
+            self.mbuf_mut()
+                .resize(offset, icv.len() as isize - old_len as isize)?;
+        }
+        if !icv.is_empty() {
+            self.mbuf_mut().write_data_slice(offset, icv)?;
+        }
+
+        self.header_mut().payload_len = ((AhHeader::size_of() + icv.len()) / 4 - 2) as u8;
+        Ok(())
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Ah<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ah")
+            .field("next_header", &format!("{}", self.next_protocol()))
+            .field("payload_len", &self.payload_len())
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence_no", &self.sequence_no())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Ah<E> {
+    /// The preceding type for an authentication header can be either an
+    /// [IPv4] packet, an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the authentication header includes the ICV.
+    #[inline]
+    fn header_len(&self) -> usize {
+        (self.payload_len() as usize + 2) * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ah::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an authentication header.
+    ///
+    /// The envelope's [`next_protocol`] must be set to [`ProtocolNumbers::Ah`].
+    /// Otherwise a parsing error is returned.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Ah`]: crate::packets::ip::ProtocolNumbers::Ah
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Ah,
+            ParseError::new("not an AH packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.header_len() >= AhHeader::size_of(),
+            ParseError::new("invalid AH payload length.")
+        );
+
+        // we've only parsed the fixed 12 bytes, make sure there's enough
+        // data for the ICV as well.
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends an authentication header to the beginning of the envelope's
+    /// payload.
+    ///
+    /// The next header is set to the envelope's [`next_protocol`], and the
+    /// envelope's [`next_protocol`] is changed to [`ProtocolNumbers::Ah`].
+    /// The header has an empty ICV.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Ah`]: crate::packets::ip::ProtocolNumbers::Ah
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, AhHeader::size_of())?;
+        let header = mbuf.write_data(offset, &AhHeader::default())?;
+
+        let mut packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        packet.set_next_protocol(packet.envelope().next_protocol());
+        packet.envelope_mut().set_next_protocol(ProtocolNumbers::Ah);
+
+        Ok(packet)
+    }
+
+    /// Removes the authentication header from the message buffer.
+    ///
+    /// The envelope's [`next_protocol`] is set to the value of the
+    /// next header field on the authentication header.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    #[inline]
+    fn remove(mut self) -> Fallible<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_protocol();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_protocol(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: IpPacket> IpPacket for Ah<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.header_mut().next_header = proto.0;
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Fallible<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.envelope().dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.envelope_mut().set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.envelope().ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.envelope_mut().set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.envelope().ttl()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.envelope_mut().set_ttl(ttl);
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for Ah<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        self.next_protocol()
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.set_next_protocol(next_header);
+    }
+}
+
+/// The maximum length of the ICV, limited by the 8-bit payload length.
+const AH_MAX_ICV_LEN: usize = (255 + 2) * 4 - 12;
+
+/// Authentication header.
+///
+/// The header only include the fixed portion of the authentication header.
+/// The ICV is parsed separately.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct AhHeader {
+    next_header: u8,
+    payload_len: u8,
+    reserved: u16be,
+    spi: u32be,
+    sequence_no: u32be,
+}
+
+impl Default for AhHeader {
+    fn default() -> AhHeader {
+        AhHeader {
+            next_header: 0,
+            // the header has no ICV.
+            payload_len: 1,
+            reserved: u16be::default(),
+            spi: u32be::default(),
+            sequence_no: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::{IPV4_AH_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_ah_header() {
+        assert_eq!(12, AhHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_AH_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ah = ipv4.parse::<Ah<Ipv4>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, ah.next_protocol());
+        assert_eq!(4, ah.payload_len());
+        assert_eq!(24, ah.header_len());
+        assert_eq!(0x100, ah.spi());
+        assert_eq!(5, ah.sequence_no());
+        assert_eq!(&IPV4_AH_PACKET[46..58], ah.icv());
+
+        // dispatches to the UDP packet
+        let udp = ah.parse::<Udp<Ah<Ipv4>>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Ah<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_truncated_ah_packet() {
+        // the ICV runs past the end of the buffer.
+        let packet = Mbuf::from_bytes(&IPV4_AH_PACKET[..50]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Ah<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_set_icv() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut ah = ipv4.push::<Ah<Ipv4>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Ah, ah.envelope().protocol());
+        assert_eq!(ProtocolNumbers::Udp, ah.next_protocol());
+        assert_eq!(12, ah.header_len());
+        assert!(ah.icv().is_empty());
+
+        ah.set_spi(0x100);
+        ah.set_sequence_no(5);
+        assert!(ah.set_icv(&IPV4_AH_PACKET[46..58]).is_ok());
+        assert_eq!(4, ah.payload_len());
+        assert_eq!(&IPV4_AH_PACKET[46..58], ah.icv());
+
+        // not a multiple of 4 octets
+        assert!(ah.set_icv(&[0; 10]).is_err());
+
+        ah.reconcile_all();
+        let bytes = ah
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_AH_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_AH_PACKET[..], unsafe { bytes.as_ref() });
+
+        // shrinks back to an empty ICV
+        assert!(ah.set_icv(&[]).is_ok());
+        assert_eq!(1, ah.payload_len());
+        assert_eq!(12, ah.header_len());
+    }
+
+    #[capsule::test]
+    fn remove_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_AH_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ah = ipv4.parse::<Ah<Ipv4>>().unwrap();
+
+        let mut ipv4 = ah.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+
+        ipv4.reconcile_all();
+        let bytes = ipv4
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_UDP_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+
+    #[capsule::test]
+    fn walk_ipv6_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut ah = ipv6.push::<Ah<Ipv6>>().unwrap();
+        assert!(ah.set_icv(&[0; 12]).is_ok());
+
+        let ipv6 = ah.deparse();
+        assert_eq!(ProtocolNumbers::Ah, ipv6.next_header());
+        assert_eq!(ProtocolNumbers::Tcp, ipv6.upper_layer_protocol().unwrap());
+        assert_eq!(
+            ipv6.payload_offset() + 24,
+            ipv6.upper_layer_offset().unwrap()
+        );
+    }
+}
@@ -0,0 +1,332 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::dpdk::BufferError;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::types::u32be;
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// IP Encapsulating Security Payload packet based on [IETF RFC 4303].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               Security Parameters Index (SPI)                 |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Sequence Number                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Payload Data* (variable)                   |
+/// ~                                                               ~
+/// |                                                               |
+/// +               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               |     Padding (0-255 bytes)                     |
+/// +-+-+-+-+-+-+-+-+               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               |  Pad Length   | Next Header   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |         Integrity Check Value-ICV   (variable)                |
+/// ~                                                               ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *SPI*:               32-bit value used by the receiver to identify the
+///                        security association to which the packet is bound.
+///
+/// - *Sequence Number*:   Unsigned 32-bit monotonically increasing counter
+///                        used for anti-replay protection.
+///
+/// - *Payload Data*:      The encrypted data, including any cryptographic
+///                        synchronization data such as the IV.
+///
+/// - *Padding*:           Padding for the encryption and the alignment of
+///                        the trailer.
+///
+/// - *Pad Length*:        The number of pad bytes immediately preceding it.
+///
+/// - *Next Header*:       Identifies the type of data contained in the
+///                        payload data field.
+///
+/// - *ICV*:               Variable-length field that contains the Integrity
+///                        Check Value computed over the ESP header, payload
+///                        and trailer.
+///
+/// Only the header is parsed. The payload, the trailer and the ICV are
+/// the payload of the packet. Because the trailer is encrypted, the next
+/// header is not known until the payload is decrypted with the
+/// [`SecurityAssociation`] the packet is bound to.
+///
+/// [IETF RFC 4303]: https://tools.ietf.org/html/rfc4303
+/// [`SecurityAssociation`]: crate::packets::ip::ipsec::SecurityAssociation
+pub struct Esp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<EspHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Esp<E> {
+    #[inline]
+    fn header(&self) -> &EspHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut EspHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence_no(&self) -> u32 {
+        self.header().sequence_no.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence_no(&mut self, sequence_no: u32) {
+        self.header_mut().sequence_no = sequence_no.into();
+    }
+
+    /// Returns the integrity check value.
+    ///
+    /// The length of the ICV is determined by the integrity algorithm of
+    /// the security association, so it must be provided by the caller.
+    #[inline]
+    pub fn icv(&self, icv_len: usize) -> Fallible<&[u8]> {
+        let len = self.payload_len();
+        ensure!(icv_len <= len, BufferError::OutOfBuffer(icv_len, len));
+
+        let icv = self
+            .mbuf()
+            .read_data_slice::<u8>(self.payload_offset() + len - icv_len, icv_len)?;
+        Ok(unsafe { icv.as_ref() })
+    }
+
+    /// Sets the integrity check value.
+    ///
+    /// The ICV overwrites the last `icv.len()` bytes of the payload. The
+    /// space for the ICV must already be allocated.
+    #[inline]
+    pub fn set_icv(&mut self, icv: &[u8]) -> Fallible<()> {
+        let len = self.payload_len();
+        ensure!(icv.len() <= len, BufferError::OutOfBuffer(icv.len(), len));
+
+        let offset = self.payload_offset() + len - icv.len();
+        self.mbuf_mut().write_data_slice(offset, icv)?;
+        Ok(())
+    }
+
+    /// Returns the ESP header as the additional authenticated data.
+    #[inline]
+    pub(crate) fn aad(&self) -> [u8; 8] {
+        let mut aad = [0; 8];
+        aad[..4].copy_from_slice(&self.spi().to_be_bytes());
+        aad[4..].copy_from_slice(&self.sequence_no().to_be_bytes());
+        aad
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Esp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("esp")
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence_no", &self.sequence_no())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Esp<E> {
+    /// The preceding type for an ESP packet can be either an [IPv4] packet,
+    /// an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        EspHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Esp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an ESP packet.
+    ///
+    /// The envelope's [`next_protocol`] must be set to [`ProtocolNumbers::Esp`].
+    /// Otherwise a parsing error is returned.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Esp`]: crate::packets::ip::ProtocolNumbers::Esp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Esp,
+            ParseError::new("not an ESP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an ESP header to the beginning of the envelope's payload.
+    ///
+    /// The envelope's [`next_protocol`] is set to [`ProtocolNumbers::Esp`].
+    /// The payload is not encrypted and there is no trailer. Use a
+    /// [`SecurityAssociation`] to encrypt the payload instead.
+    ///
+    /// [`next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    /// [`ProtocolNumbers::Esp`]: crate::packets::ip::ProtocolNumbers::Esp
+    /// [`SecurityAssociation`]: crate::packets::ip::ipsec::SecurityAssociation
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, EspHeader::size_of())?;
+        let header = mbuf.write_data(offset, &EspHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Esp);
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// ESP header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct EspHeader {
+    spi: u32be,
+    sequence_no: u32be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{
+        IPV4_ESP_TRANSPORT_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET,
+    };
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_esp_header() {
+        assert_eq!(8, EspHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_esp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_ESP_TRANSPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+
+        assert_eq!(0xa5f8, esp.spi());
+        assert_eq!(1, esp.sequence_no());
+        assert_eq!(44, esp.payload_len());
+        assert_eq!(&IPV4_ESP_TRANSPORT_PACKET[70..], esp.icv(16).unwrap());
+        assert!(esp.icv(45).is_err());
+        assert_eq!([0, 0, 0xa5, 0xf8, 0, 0, 0, 1], esp.aad());
+    }
+
+    #[capsule::test]
+    fn parse_non_esp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Esp<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_esp_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let mut esp = ipv6.push::<Esp<Ipv6>>().unwrap();
+
+        assert_eq!(EspHeader::size_of(), esp.header_len());
+        assert_eq!(ProtocolNumbers::Esp, esp.envelope().next_protocol());
+
+        esp.set_spi(0x1234);
+        esp.set_sequence_no(42);
+        assert_eq!(0x1234, esp.spi());
+        assert_eq!(42, esp.sequence_no());
+
+        assert!(esp.set_icv(&[0xff; 4]).is_ok());
+        assert_eq!(&[0xff; 4], esp.icv(4).unwrap());
+    }
+}
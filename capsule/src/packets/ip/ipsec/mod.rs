@@ -0,0 +1,80 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! IP Security Authentication Header and Encapsulating Security Payload.
+//!
+//! ESP payloads are encrypted and decrypted in software with AES-GCM as
+//! described in [IETF RFC 4106]. See [`SecurityAssociation`].
+//!
+//! [IETF RFC 4106]: https://tools.ietf.org/html/rfc4106
+//! [`SecurityAssociation`]: crate::packets::ip::ipsec::SecurityAssociation
+
+mod ah;
+mod esp;
+mod sa;
+
+pub use self::ah::*;
+pub use self::esp::*;
+pub use self::sa::*;
+
+use crate::packets::ip::ProtocolNumber;
+use failure::Fail;
+
+/// IPsec related errors.
+#[derive(Debug, Fail)]
+pub enum IpsecError {
+    /// Error indicating the key is not a valid AES-GCM key with salt.
+    #[fail(display = "Invalid AES-GCM key length {}.", _0)]
+    InvalidKeyLength(usize),
+
+    /// Error indicating the ICV length is invalid.
+    #[fail(display = "Invalid ICV length {}.", _0)]
+    InvalidIcvLength(usize),
+
+    /// Error indicating the packet is not bound to any known security
+    /// association.
+    #[fail(display = "Unknown SPI 0x{:08x}.", _0)]
+    UnknownSpi(u32),
+
+    /// Error indicating the sequence number of the security association
+    /// would cycle.
+    #[fail(display = "Sequence number overflowed for SPI 0x{:08x}.", _0)]
+    SequenceOverflow(u32),
+
+    /// Error indicating the packet is a replay or is outside of the
+    /// anti-replay window.
+    #[fail(display = "Sequence number {} is replayed.", _0)]
+    Replayed(u32),
+
+    /// Error indicating the packet failed the integrity check.
+    #[fail(display = "Integrity check failed.")]
+    IntegrityCheckFailed,
+
+    /// Error indicating the padding of the decrypted payload is invalid.
+    #[fail(display = "Invalid ESP padding.")]
+    InvalidPadding,
+
+    /// Error indicating the payload can't be encapsulated in tunnel mode.
+    #[fail(display = "Cannot tunnel {} payload.", _0)]
+    UnsupportedPayload(String),
+
+    /// Error indicating the decrypted payload is not an IP packet in
+    /// tunnel mode.
+    #[fail(display = "Unsupported next header {}.", _0)]
+    UnsupportedNextHeader(ProtocolNumber),
+}
@@ -0,0 +1,647 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{Esp, IpsecError};
+use crate::dpdk::BufferError;
+use crate::ensure;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{EtherTypes, Ethernet, Packet};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::{AeadInPlace, Aes128Gcm, Aes256Gcm, KeyInit};
+use failure::Fallible;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Length of the explicit IV carried in each ESP packet.
+const IV_LEN: usize = 8;
+
+/// Length of the salt appended to the AES key.
+const SALT_LEN: usize = 4;
+
+/// Length of the AES-GCM ICV.
+const ICV_LEN: usize = 16;
+
+/// Number of sequence numbers tracked by the anti-replay window.
+const REPLAY_WINDOW_SIZE: u32 = 64;
+
+/// The ESP mode of a security association.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EspMode {
+    /// Only the payload of the IP packet is protected. The ESP header is
+    /// inserted after the IP header.
+    Transport,
+
+    /// The entire IP packet is protected, and encapsulated in an outer IP
+    /// header with the tunnel endpoint addresses.
+    Tunnel {
+        /// The source address of the outer IP header.
+        src: IpAddr,
+        /// The destination address of the outer IP header.
+        dst: IpAddr,
+    },
+}
+
+/// Sliding anti-replay window based on [IETF RFC 4303] appendix A.
+///
+/// The window tracks the highest sequence number received and which of
+/// the preceding 63 sequence numbers have been received.
+///
+/// [IETF RFC 4303]: https://tools.ietf.org/html/rfc4303#appendix-A
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReplayWindow {
+    top: u32,
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    /// Creates a new empty anti-replay window.
+    pub fn new() -> Self {
+        ReplayWindow::default()
+    }
+
+    /// Returns the highest sequence number received.
+    #[inline]
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+
+    /// Returns whether the sequence number is acceptable. A sequence number
+    /// is not acceptable if it's a duplicate, or if it's too old to fit in
+    /// the window.
+    #[inline]
+    pub fn check(&self, sequence_no: u32) -> bool {
+        if sequence_no == 0 {
+            // the first packet sent has the sequence number of 1.
+            false
+        } else if sequence_no > self.top {
+            true
+        } else {
+            let diff = self.top - sequence_no;
+            diff < REPLAY_WINDOW_SIZE && self.bitmap & (1 << diff) == 0
+        }
+    }
+
+    /// Marks the sequence number as received, sliding the window forward
+    /// if it's higher than any received so far.
+    ///
+    /// The window should only be updated after the packet passes the
+    /// integrity check.
+    #[inline]
+    pub fn update(&mut self, sequence_no: u32) {
+        if sequence_no > self.top {
+            let shift = sequence_no - self.top;
+            self.bitmap = if shift < REPLAY_WINDOW_SIZE {
+                (self.bitmap << shift) | 1
+            } else {
+                1
+            };
+            self.top = sequence_no;
+        } else {
+            let diff = self.top - sequence_no;
+            if diff < REPLAY_WINDOW_SIZE {
+                self.bitmap |= 1 << diff;
+            }
+        }
+    }
+}
+
+/// AES-GCM cipher with the key expanded.
+enum Cipher {
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+}
+
+impl Cipher {
+    /// Encrypts the buffer in place and returns the ICV.
+    fn seal(&self, nonce: &[u8], aad: &[u8], buffer: &mut [u8]) -> [u8; ICV_LEN] {
+        let nonce = GenericArray::from_slice(nonce);
+        let tag = match self {
+            Cipher::Aes128(cipher) => cipher.encrypt_in_place_detached(nonce, aad, buffer),
+            Cipher::Aes256(cipher) => cipher.encrypt_in_place_detached(nonce, aad, buffer),
+        };
+
+        match tag {
+            Ok(tag) => {
+                let mut icv = [0; ICV_LEN];
+                icv.copy_from_slice(&tag);
+                icv
+            }
+            // the buffer is bound by the mbuf size, should never be too long
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Verifies the ICV and decrypts the buffer in place. The buffer is
+    /// left untouched if the integrity check fails.
+    fn open(&self, nonce: &[u8], aad: &[u8], buffer: &mut [u8], icv: &[u8]) -> Fallible<()> {
+        let nonce = GenericArray::from_slice(nonce);
+        let tag = GenericArray::from_slice(icv);
+        let result = match self {
+            Cipher::Aes128(cipher) => cipher.decrypt_in_place_detached(nonce, aad, buffer, tag),
+            Cipher::Aes256(cipher) => cipher.decrypt_in_place_detached(nonce, aad, buffer, tag),
+        };
+
+        result.map_err(|_| IpsecError::IntegrityCheckFailed.into())
+    }
+}
+
+/// An ESP security association using AES-GCM based on [IETF RFC 4106].
+///
+/// The security association holds the state for one direction of the
+/// traffic. The outbound sequence number is incremented for each packet
+/// encrypted, and the anti-replay window is advanced for each packet
+/// decrypted. The explicit IV is the 64-bit sequence number, so it never
+/// repeats for the lifetime of the key.
+///
+/// # Example
+///
+/// ```
+/// let mut outbound = SecurityAssociation::new(0x1000, EspMode::Transport, &key)?;
+/// let esp = outbound.encrypt::<Ipv4>(ethernet)?;
+///
+/// let mut inbound = SecurityAssociation::new(0x1000, EspMode::Transport, &key)?;
+/// let ethernet = inbound.decrypt(esp)?;
+/// ```
+///
+/// [IETF RFC 4106]: https://tools.ietf.org/html/rfc4106
+pub struct SecurityAssociation {
+    spi: u32,
+    mode: EspMode,
+    cipher: Cipher,
+    salt: [u8; SALT_LEN],
+    sequence_no: u32,
+    replay_window: ReplayWindow,
+}
+
+impl SecurityAssociation {
+    /// Creates a new security association.
+    ///
+    /// `key` is the keying material of either AES-128-GCM or AES-256-GCM,
+    /// which is the 16 or 32 octets AES key followed by the 4 octets salt.
+    pub fn new(spi: u32, mode: EspMode, key: &[u8]) -> Fallible<Self> {
+        let (key, salt) = match key.len() {
+            20 | 36 => key.split_at(key.len() - SALT_LEN),
+            len => return Err(IpsecError::InvalidKeyLength(len).into()),
+        };
+
+        let cipher = if key.len() == 16 {
+            Cipher::Aes128(Box::new(Aes128Gcm::new(GenericArray::from_slice(key))))
+        } else {
+            Cipher::Aes256(Box::new(Aes256Gcm::new(GenericArray::from_slice(key))))
+        };
+
+        let mut sa_salt = [0; SALT_LEN];
+        sa_salt.copy_from_slice(salt);
+
+        Ok(SecurityAssociation {
+            spi,
+            mode,
+            cipher,
+            salt: sa_salt,
+            sequence_no: 0,
+            replay_window: ReplayWindow::new(),
+        })
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.spi
+    }
+
+    /// Returns the ESP mode.
+    #[inline]
+    pub fn mode(&self) -> EspMode {
+        self.mode
+    }
+
+    /// Returns the sequence number of the last packet encrypted.
+    #[inline]
+    pub fn sequence_no(&self) -> u32 {
+        self.sequence_no
+    }
+
+    /// Returns the anti-replay window of the packets decrypted.
+    #[inline]
+    pub fn replay_window(&self) -> &ReplayWindow {
+        &self.replay_window
+    }
+
+    /// Returns the nonce, which is the salt followed by the explicit IV.
+    #[inline]
+    fn nonce(&self, iv: &[u8]) -> [u8; SALT_LEN + IV_LEN] {
+        let mut nonce = [0; SALT_LEN + IV_LEN];
+        nonce[..SALT_LEN].copy_from_slice(&self.salt);
+        nonce[SALT_LEN..].copy_from_slice(iv);
+        nonce
+    }
+
+    /// Encrypts the IP packet in the Ethernet frame.
+    ///
+    /// In transport mode, the payload of the IP packet `E` is encrypted,
+    /// and the ESP header is inserted after the IP header. In tunnel mode,
+    /// the entire IP packet is encrypted and encapsulated in a new outer
+    /// IP packet `E` with the tunnel endpoint addresses. The lengths and
+    /// checksums of the outer IP packet are reconciled.
+    ///
+    /// Returns an error if the sequence number would cycle. The security
+    /// association must be rekeyed before that happens.
+    pub fn encrypt<E>(&mut self, ethernet: Ethernet) -> Fallible<Esp<E>>
+    where
+        E: IpPacket + Packet<Envelope = Ethernet>,
+    {
+        ensure!(
+            self.sequence_no < u32::MAX,
+            IpsecError::SequenceOverflow(self.spi)
+        );
+
+        let (ip, next_header) = match self.mode {
+            EspMode::Transport => {
+                let ip = ethernet.parse::<E>()?;
+                let next_header = ip.next_protocol();
+                (ip, next_header)
+            }
+            EspMode::Tunnel { src, dst } => {
+                let next_header = match ethernet.ether_type() {
+                    EtherTypes::Ipv4 => ProtocolNumbers::Ipv4,
+                    EtherTypes::Ipv6 => ProtocolNumbers::Ipv6,
+                    other => return Err(IpsecError::UnsupportedPayload(other.to_string()).into()),
+                };
+                let mut ip = ethernet.push::<E>()?;
+                ip.set_src(src)?;
+                ip.set_dst(dst)?;
+                (ip, next_header)
+            }
+        };
+
+        self.sequence_no += 1;
+        let mut esp = ip.push::<Esp<E>>()?;
+        esp.set_spi(self.spi);
+        esp.set_sequence_no(self.sequence_no);
+
+        // pads so the trailer ends on a 4-octet boundary. the padding
+        // bytes are the monotonic sequence of 1, 2, 3...
+        let offset = esp.payload_offset();
+        let payload_len = esp.payload_len();
+        let pad_len = (4 - (payload_len + 2) % 4) % 4;
+        let mut trailer = (1..=pad_len as u8).collect::<Vec<_>>();
+        trailer.push(pad_len as u8);
+        trailer.push(next_header.0);
+        trailer.extend_from_slice(&[0; ICV_LEN]);
+
+        let mbuf = esp.mbuf_mut();
+        mbuf.extend(offset + payload_len, trailer.len())?;
+        mbuf.write_data_slice(offset + payload_len, &trailer)?;
+
+        let iv = u64::from(self.sequence_no).to_be_bytes();
+        mbuf.extend(offset, IV_LEN)?;
+        mbuf.write_data_slice(offset, &iv)?;
+
+        let plaintext_len = payload_len + pad_len + 2;
+        let mut plaintext = mbuf.read_data_slice::<u8>(offset + IV_LEN, plaintext_len)?;
+        let icv = self
+            .cipher
+            .seal(&self.nonce(&iv), &esp.aad(), unsafe { plaintext.as_mut() });
+        esp.set_icv(&icv)?;
+
+        esp.reconcile_all();
+        Ok(esp)
+    }
+
+    /// Decrypts the ESP packet and returns the IP packet in the Ethernet
+    /// frame.
+    ///
+    /// The packet is rejected if it's a replay or fails the integrity
+    /// check. Only packets that pass the integrity check advance the
+    /// anti-replay window. In transport mode, the ESP header and trailer
+    /// are removed, and the lengths and checksums of the IP packet are
+    /// reconciled. In tunnel mode, the outer IP packet is removed as well,
+    /// and the Ethernet frame's ether type is set to the inner IP version.
+    pub fn decrypt<E>(&mut self, esp: Esp<E>) -> Fallible<Ethernet>
+    where
+        E: IpPacket + Packet<Envelope = Ethernet>,
+    {
+        ensure!(esp.spi() == self.spi, IpsecError::UnknownSpi(esp.spi()));
+
+        let sequence_no = esp.sequence_no();
+        ensure!(
+            self.replay_window.check(sequence_no),
+            IpsecError::Replayed(sequence_no)
+        );
+
+        let offset = esp.payload_offset();
+        let len = esp.payload_len();
+        let min_len = IV_LEN + 2 + ICV_LEN;
+        ensure!(len >= min_len, BufferError::OutOfBuffer(min_len, len));
+        let ciphertext_len = len - IV_LEN - ICV_LEN;
+
+        let mbuf = esp.mbuf();
+        let iv = mbuf.read_data_slice::<u8>(offset, IV_LEN)?;
+        let icv = mbuf.read_data_slice::<u8>(offset + IV_LEN + ciphertext_len, ICV_LEN)?;
+        let mut ciphertext = mbuf.read_data_slice::<u8>(offset + IV_LEN, ciphertext_len)?;
+        let plaintext = unsafe {
+            self.cipher.open(
+                &self.nonce(iv.as_ref()),
+                &esp.aad(),
+                ciphertext.as_mut(),
+                icv.as_ref(),
+            )?;
+            ciphertext.as_ref()
+        };
+
+        self.replay_window.update(sequence_no);
+
+        let pad_len = plaintext[ciphertext_len - 2] as usize;
+        let next_header = ProtocolNumber::new(plaintext[ciphertext_len - 1]);
+        ensure!(pad_len + 2 <= ciphertext_len, IpsecError::InvalidPadding);
+        let payload_len = ciphertext_len - pad_len - 2;
+        ensure!(
+            plaintext[payload_len..payload_len + pad_len]
+                .iter()
+                .enumerate()
+                .all(|(i, &pad)| pad as usize == i + 1),
+            IpsecError::InvalidPadding
+        );
+
+        match self.mode {
+            EspMode::Transport => {
+                let esp_offset = esp.offset();
+                let mut ip = esp.deparse();
+                let mbuf = ip.mbuf_mut();
+                mbuf.truncate(offset + IV_LEN + payload_len)?;
+                mbuf.shrink(esp_offset, offset + IV_LEN - esp_offset)?;
+                ip.set_next_protocol(next_header);
+                ip.reconcile_all();
+                Ok(ip.deparse())
+            }
+            EspMode::Tunnel { .. } => {
+                let ether_type = match next_header {
+                    ProtocolNumbers::Ipv4 => EtherTypes::Ipv4,
+                    ProtocolNumbers::Ipv6 => EtherTypes::Ipv6,
+                    _ => return Err(IpsecError::UnsupportedNextHeader(next_header).into()),
+                };
+                let ip_offset = esp.envelope().offset();
+                let mut ethernet = esp.deparse().deparse();
+                let mbuf = ethernet.mbuf_mut();
+                mbuf.truncate(offset + IV_LEN + payload_len)?;
+                mbuf.shrink(ip_offset, offset + IV_LEN - ip_offset)?;
+                ethernet.set_ether_type(ether_type);
+                Ok(ethernet)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SecurityAssociation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("security_association")
+            .field("spi", &format!("0x{:08x}", self.spi))
+            .field("mode", &self.mode)
+            .field("sequence_no", &self.sequence_no)
+            .field("replay_window", &self.replay_window)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{
+        IPV4_ESP_TRANSPORT_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET,
+    };
+    use crate::Mbuf;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308cafebabe";
+
+    /// Creates a security association from the keying material in hex.
+    fn new_sa(spi: u32, mode: EspMode, key: &str) -> SecurityAssociation {
+        let key = (0..key.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&key[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+        SecurityAssociation::new(spi, mode, &key).unwrap()
+    }
+
+    fn read_bytes(mbuf: &Mbuf) -> &[u8] {
+        let bytes = mbuf.read_data_slice::<u8>(0, mbuf.data_len()).unwrap();
+        unsafe { bytes.as_ref() }
+    }
+
+    #[test]
+    fn aes_gcm_known_answer() {
+        // test case 4 of the GCM specification.
+        let sa = new_sa(
+            0,
+            EspMode::Transport,
+            "feffe9928665731c6d6a8f9467308308cafebabe",
+        );
+        let aad = [
+            0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad, 0xbe, 0xef, 0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad,
+            0xbe, 0xef, 0xab, 0xad, 0xda, 0xd2,
+        ];
+        let mut buffer = [
+            0xd9, 0x31, 0x32, 0x25, 0xf8, 0x84, 0x06, 0xe5, 0xa5, 0x59, 0x09, 0xc5, 0xaf, 0xf5,
+            0x26, 0x9a, 0x86, 0xa7, 0xa9, 0x53, 0x15, 0x34, 0xf7, 0xda, 0x2e, 0x4c, 0x30, 0x3d,
+            0x8a, 0x31, 0x8a, 0x72, 0x1c, 0x3c, 0x0c, 0x95, 0x95, 0x68, 0x09, 0x53, 0x2f, 0xcf,
+            0x0e, 0x24, 0x49, 0xa6, 0xb5, 0x25, 0xb1, 0x6a, 0xed, 0xf5, 0xaa, 0x0d, 0xe6, 0x57,
+            0xba, 0x63, 0x7b, 0x39,
+        ];
+        let plaintext = buffer;
+        let ciphertext = [
+            0x42, 0x83, 0x1e, 0xc2, 0x21, 0x77, 0x74, 0x24, 0x4b, 0x72, 0x21, 0xb7, 0x84, 0xd0,
+            0xd4, 0x9c, 0xe3, 0xaa, 0x21, 0x2f, 0x2c, 0x02, 0xa4, 0xe0, 0x35, 0xc1, 0x7e, 0x23,
+            0x29, 0xac, 0xa1, 0x2e, 0x21, 0xd5, 0x14, 0xb2, 0x54, 0x66, 0x93, 0x1c, 0x7d, 0x8f,
+            0x6a, 0x5a, 0xac, 0x84, 0xaa, 0x05, 0x1b, 0xa3, 0x0b, 0x39, 0x6a, 0x0a, 0xac, 0x97,
+            0x3d, 0x58, 0xe0, 0x91,
+        ];
+        let tag = [
+            0x5b, 0xc9, 0x4f, 0xbc, 0x32, 0x21, 0xa5, 0xdb, 0x94, 0xfa, 0xe9, 0x5a, 0xe7, 0x12,
+            0x1a, 0x47,
+        ];
+        let nonce = sa.nonce(&[0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88]);
+
+        assert_eq!(tag, sa.cipher.seal(&nonce, &aad, &mut buffer));
+        assert_eq!(&ciphertext[..], &buffer[..]);
+
+        assert!(sa.cipher.open(&nonce, &aad, &mut buffer, &tag).is_ok());
+        assert_eq!(&plaintext[..], &buffer[..]);
+    }
+
+    #[test]
+    fn invalid_key_length() {
+        assert!(SecurityAssociation::new(1, EspMode::Transport, &[0; 16]).is_err());
+        assert!(SecurityAssociation::new(1, EspMode::Transport, &[0; 20]).is_ok());
+        assert!(SecurityAssociation::new(1, EspMode::Transport, &[0; 36]).is_ok());
+    }
+
+    #[test]
+    fn replay_window() {
+        let mut window = ReplayWindow::new();
+
+        // 0 is never a valid sequence number
+        assert!(!window.check(0));
+
+        assert!(window.check(1));
+        window.update(1);
+        assert!(!window.check(1));
+
+        // out of order but inside the window
+        window.update(10);
+        assert!(window.check(5));
+        window.update(5);
+        assert!(!window.check(5));
+        assert!(!window.check(10));
+        assert_eq!(10, window.top());
+
+        // slides the window past the old sequence numbers
+        window.update(100);
+        assert!(!window.check(36));
+        assert!(window.check(37));
+        assert!(!window.check(100));
+        assert!(window.check(101));
+    }
+
+    #[capsule::test]
+    fn encrypt_transport_known_answer() {
+        let mut sa = new_sa(0xa5f8, EspMode::Transport, KEY);
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let esp = sa.encrypt::<Ipv4>(ethernet).unwrap();
+
+        assert_eq!(1, sa.sequence_no());
+        assert_eq!(ProtocolNumbers::Esp, esp.envelope().protocol());
+        assert_eq!(&IPV4_ESP_TRANSPORT_PACKET[..], read_bytes(esp.mbuf()));
+    }
+
+    #[capsule::test]
+    fn decrypt_transport_known_answer() {
+        let mut sa = new_sa(0xa5f8, EspMode::Transport, KEY);
+
+        let packet = Mbuf::from_bytes(&IPV4_ESP_TRANSPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+        let ethernet = sa.decrypt(esp).unwrap();
+
+        assert_eq!(1, sa.replay_window().top());
+        assert_eq!(&IPV4_UDP_PACKET[..], read_bytes(ethernet.mbuf()));
+
+        // the same packet again is a replay
+        let packet = Mbuf::from_bytes(&IPV4_ESP_TRANSPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+        assert!(sa.decrypt(esp).is_err());
+    }
+
+    #[capsule::test]
+    fn decrypt_tampered_packet() {
+        let mut sa = new_sa(0xa5f8, EspMode::Transport, KEY);
+
+        let mut bytes = IPV4_ESP_TRANSPORT_PACKET;
+        bytes[60] ^= 0x01;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+
+        assert!(sa.decrypt(esp).is_err());
+        // failed packets don't advance the window
+        assert_eq!(0, sa.replay_window().top());
+
+        // bound to a different security association
+        let mut sa = new_sa(0x1000, EspMode::Transport, KEY);
+        let packet = Mbuf::from_bytes(&IPV4_ESP_TRANSPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp<Ipv4>>().unwrap();
+
+        assert!(sa.decrypt(esp).is_err());
+    }
+
+    #[capsule::test]
+    fn encrypt_and_decrypt_transport() {
+        let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f01020304";
+        let mut outbound = new_sa(0x1000, EspMode::Transport, key);
+        let mut inbound = new_sa(0x1000, EspMode::Transport, key);
+
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let esp = outbound.encrypt::<Ipv6>(ethernet).unwrap();
+
+        assert_eq!(ProtocolNumbers::Esp, esp.envelope().next_protocol());
+        // iv + payload + trailer aligned to 4 octets + icv
+        assert_eq!(0, (esp.payload_len() - IV_LEN - ICV_LEN) % 4);
+        assert_eq!(
+            esp.envelope().payload_len() as u16,
+            esp.envelope().payload_length()
+        );
+
+        let ethernet = inbound.decrypt(esp).unwrap();
+        assert_eq!(&IPV6_TCP_PACKET[..], read_bytes(ethernet.mbuf()));
+    }
+
+    #[capsule::test]
+    fn encrypt_and_decrypt_tunnel() {
+        let mode = EspMode::Tunnel {
+            src: "10.0.0.1".parse().unwrap(),
+            dst: "10.0.0.2".parse().unwrap(),
+        };
+        let mut outbound = new_sa(0x2000, mode, KEY);
+        let mut inbound = new_sa(0x2000, mode, KEY);
+
+        for sequence_no in 1..=3 {
+            let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+            let ethernet = packet.parse::<Ethernet>().unwrap();
+            let esp = outbound.encrypt::<Ipv4>(ethernet).unwrap();
+
+            assert_eq!(sequence_no, esp.sequence_no());
+            assert_eq!("10.0.0.1", esp.envelope().src().to_string());
+            assert_eq!("10.0.0.2", esp.envelope().dst().to_string());
+            assert_eq!(esp.envelope().len() as u16, esp.envelope().total_length());
+
+            let ethernet = inbound.decrypt(esp).unwrap();
+            assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+            assert_eq!(&IPV4_UDP_PACKET[..], read_bytes(ethernet.mbuf()));
+
+            let udp = ethernet
+                .parse::<Ipv4>()
+                .unwrap()
+                .parse::<Udp<Ipv4>>()
+                .unwrap();
+            assert_eq!(1087, udp.dst_port());
+        }
+
+        assert_eq!(3, inbound.replay_window().top());
+    }
+
+    #[capsule::test]
+    fn sequence_no_overflow() {
+        let mut sa = new_sa(0xa5f8, EspMode::Transport, KEY);
+        sa.sequence_no = u32::MAX;
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(sa.encrypt::<Ipv4>(ethernet).is_err());
+    }
+}
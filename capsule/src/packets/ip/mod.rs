@@ -18,9 +18,16 @@
 
 //! Internet Protocol v4 and v6.
 
+mod gre;
+pub mod ipsec;
+mod reassembly;
+mod tunnel;
 pub mod v4;
 pub mod v6;
 
+pub use self::gre::*;
+pub use self::reassembly::*;
+
 use crate::packets::checksum::PseudoHeader;
 use crate::packets::Packet;
 use failure::{Fail, Fallible};
@@ -32,6 +39,21 @@ use std::net::{IpAddr, Ipv4Addr};
 /// [IANA]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xml#ip-parameters-2
 pub const DEFAULT_IP_TTL: u8 = 64;
 
+/// ECN codepoint for a packet from a transport that is not ECN-capable,
+/// defined in [IETF RFC 3168].
+///
+/// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168#section-5
+pub const ECN_NOT_ECT: u8 = 0b00;
+
+/// ECN codepoint ECT(1) for a packet from an ECN-capable transport.
+pub const ECN_ECT1: u8 = 0b01;
+
+/// ECN codepoint ECT(0) for a packet from an ECN-capable transport.
+pub const ECN_ECT0: u8 = 0b10;
+
+/// ECN codepoint for a packet that experienced congestion.
+pub const ECN_CE: u8 = 0b11;
+
 /// [IANA] assigned Internet protocol number.
 ///
 /// See [`ProtocolNumbers`] for which are current supported.
@@ -61,17 +83,47 @@ pub mod ProtocolNumbers {
     /// User Datagram Protocol.
     pub const Udp: ProtocolNumber = ProtocolNumber(0x11);
 
+    /// Hop-by-Hop Options Header for IPv6.
+    pub const Ipv6HopByHop: ProtocolNumber = ProtocolNumber(0x00);
+
     /// Routing Header for IPv6.
     pub const Ipv6Route: ProtocolNumber = ProtocolNumber(0x2B);
 
     /// Fragment Header for IPv6.
     pub const Ipv6Frag: ProtocolNumber = ProtocolNumber(0x2C);
 
+    /// No Next Header for IPv6.
+    pub const Ipv6NoNext: ProtocolNumber = ProtocolNumber(0x3B);
+
+    /// Destination Options Header for IPv6.
+    pub const Ipv6Opts: ProtocolNumber = ProtocolNumber(0x3C);
+
     /// Internet Control Message Protocol for IPv6.
     pub const Icmpv6: ProtocolNumber = ProtocolNumber(0x3A);
 
     /// Internet Control Message Protocol for IPv4.
     pub const Icmpv4: ProtocolNumber = ProtocolNumber(0x01);
+
+    /// Generic Routing Encapsulation.
+    pub const Gre: ProtocolNumber = ProtocolNumber(0x2F);
+
+    /// IPsec Encapsulating Security Payload.
+    pub const Esp: ProtocolNumber = ProtocolNumber(0x32);
+
+    /// IPsec Authentication Header.
+    pub const Ah: ProtocolNumber = ProtocolNumber(0x33);
+
+    /// Stream Control Transmission Protocol.
+    pub const Sctp: ProtocolNumber = ProtocolNumber(0x84);
+
+    /// Internet Group Management Protocol.
+    pub const Igmp: ProtocolNumber = ProtocolNumber(0x02);
+
+    /// IPv4 encapsulation, or IP-in-IP.
+    pub const Ipv4: ProtocolNumber = ProtocolNumber(0x04);
+
+    /// IPv6 encapsulation, or 6in4.
+    pub const Ipv6: ProtocolNumber = ProtocolNumber(0x29);
 }
 
 impl fmt::Display for ProtocolNumber {
@@ -82,10 +134,20 @@ impl fmt::Display for ProtocolNumber {
             match *self {
                 ProtocolNumbers::Tcp => "TCP".to_string(),
                 ProtocolNumbers::Udp => "UDP".to_string(),
+                ProtocolNumbers::Ipv6HopByHop => "IPv6 Hop-by-Hop".to_string(),
                 ProtocolNumbers::Ipv6Route => "IPv6 Route".to_string(),
                 ProtocolNumbers::Ipv6Frag => "IPv6 Frag".to_string(),
+                ProtocolNumbers::Ipv6NoNext => "IPv6 No Next".to_string(),
+                ProtocolNumbers::Ipv6Opts => "IPv6 Opts".to_string(),
                 ProtocolNumbers::Icmpv6 => "ICMPv6".to_string(),
                 ProtocolNumbers::Icmpv4 => "ICMPv4".to_string(),
+                ProtocolNumbers::Gre => "GRE".to_string(),
+                ProtocolNumbers::Esp => "ESP".to_string(),
+                ProtocolNumbers::Ah => "AH".to_string(),
+                ProtocolNumbers::Sctp => "SCTP".to_string(),
+                ProtocolNumbers::Igmp => "IGMP".to_string(),
+                ProtocolNumbers::Ipv4 => "IPv4".to_string(),
+                ProtocolNumbers::Ipv6 => "IPv6".to_string(),
                 _ => format!("0x{:02x}", self.0),
             }
         )
@@ -135,6 +197,71 @@ pub trait IpPacket: Packet {
 
     /// Truncates the IP packet to MTU. The data exceeds MTU is lost.
     fn truncate(&mut self, mtu: usize) -> Fallible<()>;
+
+    /// Returns the differentiated services codepoint.
+    ///
+    /// For IPv6 extension packets, this is the codepoint of the IPv6
+    /// header.
+    fn dscp(&self) -> u8;
+
+    /// Sets the differentiated services codepoint. Only the lowest 6 bits
+    /// are used.
+    ///
+    /// For IPv4 packets, the header checksum is updated.
+    fn set_dscp(&mut self, dscp: u8);
+
+    /// Returns the explicit congestion notification codepoint.
+    ///
+    /// For IPv6 extension packets, this is the codepoint of the IPv6
+    /// header.
+    fn ecn(&self) -> u8;
+
+    /// Sets the explicit congestion notification codepoint. Only the
+    /// lowest 2 bits are used.
+    ///
+    /// For IPv4 packets, the header checksum is updated.
+    fn set_ecn(&mut self, ecn: u8);
+
+    /// Returns the time-to-live.
+    ///
+    /// For IPv6 packets, this is the hop limit. For IPv6 extension packets,
+    /// this is the hop limit of the IPv6 header.
+    fn ttl(&self) -> u8;
+
+    /// Sets the time-to-live.
+    ///
+    /// For IPv6 packets, this is the hop limit. For IPv6 extension packets,
+    /// this is the hop limit of the IPv6 header.
+    fn set_ttl(&mut self, ttl: u8);
+
+    /// Marks the packet as having experienced congestion.
+    ///
+    /// Only packets from ECN-capable transports can be marked. Returns
+    /// `false` if the packet is [`ECN_NOT_ECT`], in which case an active
+    /// queue management should drop the packet instead. Returns `true` if
+    /// the packet is marked or was already marked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// if queue_delay > target && !ip.mark_ce() {
+    ///     // not from an ECN-capable transport, drops the packet instead.
+    ///     return None;
+    /// }
+    /// ```
+    ///
+    /// [`ECN_NOT_ECT`]: ECN_NOT_ECT
+    #[inline]
+    fn mark_ce(&mut self) -> bool {
+        match self.ecn() {
+            ECN_NOT_ECT => false,
+            ECN_CE => true,
+            _ => {
+                self.set_ecn(ECN_CE);
+                true
+            }
+        }
+    }
 }
 
 /// The common attributes (5-tuple) used to identify an IP based network
@@ -274,11 +401,21 @@ pub enum IpPacketError {
     /// Error indicating the MTU is less than the minimum MTU size.
     #[fail(display = "{} is less than the minimum MTU of {}.", _0, _1)]
     MtuTooSmall(usize, usize),
+
+    /// Error indicating a tunneled packet experienced congestion, but is
+    /// not from an ECN-capable transport.
+    #[fail(display = "Cannot mark a packet not from an ECN-capable transport.")]
+    NotEcnCapable,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
 
     #[test]
     fn protocol_number_to_string() {
@@ -286,6 +423,54 @@ mod tests {
         assert_eq!("UDP", ProtocolNumbers::Udp.to_string());
         assert_eq!("IPv6 Route", ProtocolNumbers::Ipv6Route.to_string());
         assert_eq!("ICMPv6", ProtocolNumbers::Icmpv6.to_string());
+        assert_eq!("GRE", ProtocolNumbers::Gre.to_string());
+        assert_eq!("ESP", ProtocolNumbers::Esp.to_string());
+        assert_eq!("AH", ProtocolNumbers::Ah.to_string());
+        assert_eq!("SCTP", ProtocolNumbers::Sctp.to_string());
+        assert_eq!("IGMP", ProtocolNumbers::Igmp.to_string());
+        assert_eq!("IPv4", ProtocolNumbers::Ipv4.to_string());
+        assert_eq!("IPv6", ProtocolNumbers::Ipv6.to_string());
         assert_eq!("0x00", ProtocolNumber::new(0).to_string());
     }
+
+    fn assert_mark_ce<T: IpPacket>(packet: &mut T) {
+        packet.set_ecn(ECN_NOT_ECT);
+        assert!(!packet.mark_ce());
+        assert_eq!(ECN_NOT_ECT, packet.ecn());
+
+        packet.set_ecn(ECN_ECT0);
+        assert!(packet.mark_ce());
+        assert_eq!(ECN_CE, packet.ecn());
+
+        // already marked.
+        assert!(packet.mark_ce());
+        assert_eq!(ECN_CE, packet.ecn());
+    }
+
+    #[capsule::test]
+    fn mark_ce_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        IpPacket::set_dscp(&mut ipv4, 46);
+        assert_mark_ce(&mut ipv4);
+        assert_eq!(46, IpPacket::dscp(&ipv4));
+
+        // the checksum is kept correct without reconciling.
+        let checksum = ipv4.checksum();
+        ipv4.reconcile();
+        assert_eq!(checksum, ipv4.checksum());
+    }
+
+    #[capsule::test]
+    fn mark_ce_ipv6_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        IpPacket::set_dscp(&mut ipv6, 46);
+        assert_mark_ce(&mut ipv6);
+        assert_eq!(46, IpPacket::dscp(&ipv6));
+    }
 }
@@ -0,0 +1,722 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Reassembly of fragmented IPv4 and IPv6 packets.
+
+use crate::batch::Either;
+use crate::ensure;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::{Fragment, Ipv6Packet};
+use crate::packets::ip::{IpPacket, ProtocolNumber};
+use crate::packets::{EtherTypeEnvelope, Packet};
+use failure::{Fail, Fallible};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+/// The default maximum number of datagrams being reassembled at once.
+pub const DEFAULT_REASSEMBLY_CAPACITY: usize = 1024;
+
+/// The default maximum length of a reassembled datagram's payload.
+pub const DEFAULT_REASSEMBLY_MAX_LEN: usize = 65_535;
+
+/// The default time to wait for all the fragments of an IPv4 datagram.
+pub const DEFAULT_IPV4_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default time to wait for all the fragments of an IPv6 datagram,
+/// defined in [IETF RFC 8200].
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.5
+pub const DEFAULT_IPV6_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A datagram being reassembled.
+struct Datagram<H> {
+    created: Instant,
+    data: Vec<u8>,
+    ranges: Vec<(usize, usize)>,
+    received: usize,
+    total_len: Option<usize>,
+    // header info from the first fragment.
+    first: Option<H>,
+}
+
+impl<H> Datagram<H> {
+    fn new(now: Instant) -> Self {
+        Datagram {
+            created: now,
+            data: Vec::new(),
+            ranges: Vec::new(),
+            received: 0,
+            total_len: None,
+            first: None,
+        }
+    }
+
+    /// Adds the fragment data at `start`. An exact duplicate of a received
+    /// fragment is ignored if `allow_duplicates` is set, otherwise it is an
+    /// overlap.
+    fn insert(
+        &mut self,
+        start: usize,
+        data: &[u8],
+        more: bool,
+        allow_duplicates: bool,
+    ) -> Fallible<()> {
+        let end = start + data.len();
+
+        // all fragments except the last carry multiples of 8 octets.
+        ensure!(
+            !more || data.len() % 8 == 0,
+            ReassemblyError::InvalidFragment
+        );
+        match self.total_len {
+            Some(total_len) => ensure!(
+                end <= total_len && (more || end == total_len),
+                ReassemblyError::InvalidFragment
+            ),
+            None if !more => ensure!(
+                self.ranges.iter().all(|&(_, e)| e <= end),
+                ReassemblyError::InvalidFragment
+            ),
+            None => (),
+        }
+        // a duplicate has the range and the data of a received fragment,
+        // and is the last fragment only if the received one was.
+        if allow_duplicates
+            && self.ranges.contains(&(start, end))
+            && more != (self.total_len == Some(end))
+            && self.data[start..end] == *data
+        {
+            return Ok(());
+        }
+        ensure!(
+            self.ranges.iter().all(|&(s, e)| end <= s || e <= start),
+            ReassemblyError::Overlap
+        );
+
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[start..end].copy_from_slice(data);
+        self.ranges.push((start, end));
+        self.received += data.len();
+        if !more {
+            self.total_len = Some(end);
+        }
+
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.total_len == Some(self.received) && self.first.is_some()
+    }
+}
+
+/// The datagrams being reassembled, keyed by `K`.
+struct ReassemblyTable<K, H> {
+    capacity: usize,
+    max_len: usize,
+    timeout: Duration,
+    // whether exact duplicates of received fragments are ignored.
+    allow_duplicates: bool,
+    datagrams: HashMap<K, Datagram<H>>,
+}
+
+impl<K: Copy + Eq + Hash, H> ReassemblyTable<K, H> {
+    fn new(capacity: usize, max_len: usize, timeout: Duration, allow_duplicates: bool) -> Self {
+        ReassemblyTable {
+            capacity,
+            max_len,
+            timeout,
+            allow_duplicates,
+            datagrams: HashMap::new(),
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        self.datagrams
+            .retain(|_, datagram| now.duration_since(datagram.created) < timeout);
+    }
+
+    /// Adds the fragment to its datagram. Returns the datagram when all
+    /// the fragments are received. On error, the datagram is discarded.
+    fn insert(
+        &mut self,
+        key: K,
+        start: usize,
+        data: &[u8],
+        more: bool,
+        first: Option<H>,
+    ) -> Fallible<Option<Datagram<H>>> {
+        let now = Instant::now();
+        self.expire(now);
+
+        let end = start + data.len();
+        if end > self.max_len {
+            self.datagrams.remove(&key);
+            return Err(ReassemblyError::TooLarge(end, self.max_len).into());
+        }
+
+        if !self.datagrams.contains_key(&key) {
+            ensure!(
+                self.datagrams.len() < self.capacity,
+                ReassemblyError::TableFull(self.capacity)
+            );
+        }
+
+        let datagram = self
+            .datagrams
+            .entry(key)
+            .or_insert_with(|| Datagram::new(now));
+
+        if let Err(err) = datagram.insert(start, data, more, self.allow_duplicates) {
+            self.datagrams.remove(&key);
+            return Err(err);
+        }
+
+        if first.is_some() {
+            datagram.first = first;
+        }
+
+        if datagram.is_complete() {
+            Ok(self.datagrams.remove(&key))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Replaces the payload of the packet with the reassembled data.
+fn replace_payload<T: Packet>(packet: &mut T, data: &[u8]) -> Fallible<()> {
+    let offset = packet.payload_offset();
+    // also discards any trailing padding.
+    let len = packet.mbuf().data_len() - offset;
+    let mbuf = packet.mbuf_mut();
+
+    if data.len() > len {
+        mbuf.extend(offset + len, data.len() - len)?;
+    } else if data.len() < len {
+        mbuf.shrink(offset + data.len(), len - data.len())?;
+    }
+
+    if !data.is_empty() {
+        mbuf.write_data_slice(offset, data)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the payload of the packet up to `len` octets.
+fn read_payload<T: Packet>(packet: &T, len: usize) -> Fallible<&[u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+
+    let data = packet
+        .mbuf()
+        .read_data_slice::<u8>(packet.payload_offset(), len)?;
+    Ok(unsafe { data.as_ref() })
+}
+
+/// The key identifying the fragments of an IPv4 datagram.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Ipv4DatagramKey {
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    protocol: ProtocolNumber,
+    identification: u16,
+}
+
+/// A reassembly table for fragmented IPv4 packets.
+///
+/// The table keeps a copy of the fragment data until all the fragments of
+/// a datagram are received. The datagram is then reassembled into the
+/// `Mbuf` of the last received fragment, so it must fit within a single
+/// `Mbuf`. The table holds at most `capacity` datagrams at once, and
+/// datagrams not completed within `timeout` are discarded.
+///
+/// The table is not thread-safe. Use one table per pipeline, so each core
+/// has its own table.
+///
+/// # Example
+///
+/// ```
+/// let mut table = Ipv4ReassemblyTable::default();
+///
+/// let mut batch = batch.filter_map(move |packet| {
+///     let v4 = packet.parse::<Ethernet>()?.parse::<Ipv4>()?;
+///     table.reassemble(v4)
+/// });
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct Ipv4ReassemblyTable {
+    table: ReassemblyTable<Ipv4DatagramKey, Vec<u8>>,
+}
+
+impl Ipv4ReassemblyTable {
+    /// Creates a new reassembly table.
+    ///
+    /// `max_len` is the maximum length of a reassembled datagram's payload.
+    pub fn new(capacity: usize, max_len: usize, timeout: Duration) -> Self {
+        Ipv4ReassemblyTable {
+            // the network may duplicate a fragment, the copy is ignored.
+            table: ReassemblyTable::new(capacity, max_len, timeout, true),
+        }
+    }
+
+    /// Returns the number of datagrams being reassembled.
+    pub fn len(&self) -> usize {
+        self.table.datagrams.len()
+    }
+
+    /// Returns whether no datagram is being reassembled.
+    pub fn is_empty(&self) -> bool {
+        self.table.datagrams.is_empty()
+    }
+
+    /// Discards the datagrams that timed out.
+    ///
+    /// Expired datagrams are also discarded every time a fragment is
+    /// added. Call periodically to free the memory when no fragments are
+    /// received.
+    pub fn expire(&mut self) {
+        self.table.expire(Instant::now());
+    }
+
+    /// Adds the packet to the table.
+    ///
+    /// If the packet is not a fragment, `Keep` is returned with the packet
+    /// as is. If the packet is a fragment, its data is copied into the
+    /// table. When the datagram is complete, `Keep` is returned with the
+    /// reassembled packet. Otherwise `Drop` is returned with the fragment's
+    /// `Mbuf`.
+    ///
+    /// The reassembled packet has the header and the options of the first
+    /// fragment. The total length and the checksum are fixed.
+    ///
+    /// An exact duplicate of a received fragment is dropped and doesn't
+    /// affect the datagram. Returns an error and discards the datagram if
+    /// the fragments otherwise overlap or the datagram exceeds the maximum
+    /// length.
+    pub fn reassemble<E: EtherTypeEnvelope>(
+        &mut self,
+        mut packet: Ipv4<E>,
+    ) -> Fallible<Either<Ipv4<E>>> {
+        if !packet.is_fragment() {
+            return Ok(Either::Keep(packet));
+        }
+
+        let key = Ipv4DatagramKey {
+            src: packet.src(),
+            dst: packet.dst(),
+            protocol: packet.protocol(),
+            identification: packet.identification(),
+        };
+        let start = packet.fragment_offset() as usize * 8;
+        let more = packet.more_fragments();
+        let first = if start == 0 {
+            Some(packet.options().to_vec())
+        } else {
+            None
+        };
+
+        let len = (packet.total_length() as usize).saturating_sub(packet.header_len());
+        let len = len.min(packet.payload_len());
+        let data = read_payload(&packet, len)?;
+
+        match self.table.insert(key, start, data, more, first)? {
+            Some(datagram) => {
+                if let Some(options) = datagram.first {
+                    packet.set_options_bytes(&options)?;
+                }
+                replace_payload(&mut packet, &datagram.data)?;
+                packet.unset_more_fragments();
+                packet.set_fragment_offset(0);
+                packet.reconcile_all();
+                Ok(Either::Keep(packet))
+            }
+            None => Ok(Either::Drop(packet.reset())),
+        }
+    }
+}
+
+impl Default for Ipv4ReassemblyTable {
+    fn default() -> Self {
+        Ipv4ReassemblyTable::new(
+            DEFAULT_REASSEMBLY_CAPACITY,
+            DEFAULT_REASSEMBLY_MAX_LEN,
+            DEFAULT_IPV4_REASSEMBLY_TIMEOUT,
+        )
+    }
+}
+
+/// The key identifying the fragments of an IPv6 datagram.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Ipv6DatagramKey {
+    src: Ipv6Addr,
+    dst: Ipv6Addr,
+    identification: u32,
+}
+
+/// A reassembly table for fragmented IPv6 packets.
+///
+/// The table keeps a copy of the fragment data until all the fragments of
+/// a datagram are received. The datagram is then reassembled into the
+/// `Mbuf` of the last received fragment, so it must fit within a single
+/// `Mbuf`. The table holds at most `capacity` datagrams at once, and
+/// datagrams not completed within `timeout` are discarded.
+///
+/// The table is not thread-safe. Use one table per pipeline, so each core
+/// has its own table.
+///
+/// # Example
+///
+/// ```
+/// let mut table = Ipv6ReassemblyTable::default();
+///
+/// let mut batch = batch.filter_map(move |packet| {
+///     let v6 = packet.parse::<Ethernet>()?.parse::<Ipv6>()?;
+///     if v6.next_header() == ProtocolNumbers::Ipv6Frag {
+///         table.reassemble(v6.parse::<Fragment<Ipv6>>()?)
+///     } else {
+///         Ok(Either::Keep(v6))
+///     }
+/// });
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct Ipv6ReassemblyTable {
+    table: ReassemblyTable<Ipv6DatagramKey, ProtocolNumber>,
+}
+
+impl Ipv6ReassemblyTable {
+    /// Creates a new reassembly table.
+    ///
+    /// `max_len` is the maximum length of a reassembled datagram's
+    /// fragmentable part.
+    pub fn new(capacity: usize, max_len: usize, timeout: Duration) -> Self {
+        Ipv6ReassemblyTable {
+            // RFC 5722 discards the datagram when any fragments overlap.
+            table: ReassemblyTable::new(capacity, max_len, timeout, false),
+        }
+    }
+
+    /// Returns the number of datagrams being reassembled.
+    pub fn len(&self) -> usize {
+        self.table.datagrams.len()
+    }
+
+    /// Returns whether no datagram is being reassembled.
+    pub fn is_empty(&self) -> bool {
+        self.table.datagrams.is_empty()
+    }
+
+    /// Discards the datagrams that timed out.
+    ///
+    /// Expired datagrams are also discarded every time a fragment is
+    /// added. Call periodically to free the memory when no fragments are
+    /// received.
+    pub fn expire(&mut self) {
+        self.table.expire(Instant::now());
+    }
+
+    /// Adds the fragment to the table.
+    ///
+    /// The fragment's data is copied into the table. When the datagram is
+    /// complete, `Keep` is returned with the reassembled packet, which is
+    /// the fragment's envelope without the fragment header. Otherwise
+    /// `Drop` is returned with the fragment's `Mbuf`.
+    ///
+    /// The reassembled packet has the unfragmentable part of the last
+    /// received fragment. The payload length is fixed.
+    ///
+    /// Returns an error and discards the datagram if the fragments overlap,
+    /// even if a fragment is an exact duplicate, or the datagram exceeds the
+    /// maximum length.
+    pub fn reassemble<E: Ipv6Packet>(&mut self, fragment: Fragment<E>) -> Fallible<Either<E>> {
+        let (src, dst) = match (fragment.src(), fragment.dst()) {
+            (IpAddr::V6(src), IpAddr::V6(dst)) => (src, dst),
+            // an IPv6 packet always has v6 addresses
+            _ => unreachable!(),
+        };
+
+        let key = Ipv6DatagramKey {
+            src,
+            dst,
+            identification: fragment.identification(),
+        };
+        let start = fragment.fragment_offset() as usize * 8;
+        let more = fragment.more_fragments();
+        let first = if start == 0 {
+            Some(fragment.next_header())
+        } else {
+            None
+        };
+
+        let data = read_payload(&fragment, fragment.payload_len())?;
+
+        match self.table.insert(key, start, data, more, first)? {
+            Some(datagram) => {
+                let mut packet = fragment.remove()?;
+                if let Some(next_header) = datagram.first {
+                    packet.set_next_header(next_header);
+                }
+                replace_payload(&mut packet, &datagram.data)?;
+                packet.reconcile_all();
+                Ok(Either::Keep(packet))
+            }
+            None => Ok(Either::Drop(fragment.reset())),
+        }
+    }
+}
+
+impl Default for Ipv6ReassemblyTable {
+    fn default() -> Self {
+        Ipv6ReassemblyTable::new(
+            DEFAULT_REASSEMBLY_CAPACITY,
+            DEFAULT_REASSEMBLY_MAX_LEN,
+            DEFAULT_IPV6_REASSEMBLY_TIMEOUT,
+        )
+    }
+}
+
+/// Reassembly related errors.
+#[derive(Debug, Fail)]
+pub enum ReassemblyError {
+    /// Error indicating the fragment overlaps with a received fragment.
+    #[fail(display = "Fragment overlaps with a received fragment.")]
+    Overlap,
+
+    /// Error indicating the fragment's offset or length is invalid.
+    #[fail(display = "Invalid fragment offset or length.")]
+    InvalidFragment,
+
+    /// Error indicating the datagram exceeds the maximum length.
+    #[fail(display = "Datagram length {} exceeds the maximum of {}.", _0, _1)]
+    TooLarge(usize, usize),
+
+    /// Error indicating the table is full.
+    #[fail(display = "Reassembly table is full with {} datagrams.", _0)]
+    TableFull(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::{Ethernet, Udp};
+    use crate::Mbuf;
+
+    /// Creates the IPv4 fragments of a UDP packet with a payload of 1000
+    /// octets.
+    fn ipv4_fragments(identification: u16, mtu: usize) -> Vec<Ipv4> {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let offset = udp.payload_offset();
+        udp.mbuf_mut().extend(offset, 1000).unwrap();
+        udp.mbuf_mut()
+            .write_data_slice(offset + 999, &[0xff])
+            .unwrap();
+        udp.reconcile_all();
+        let mut ipv4 = udp.deparse();
+        ipv4.set_identification(identification);
+
+        ipv4.fragment(mtu)
+            .unwrap()
+            .into_iter()
+            .map(|mbuf| mbuf.parse::<Ethernet>().unwrap().parse::<Ipv4>().unwrap())
+            .collect()
+    }
+
+    /// Creates an IPv6 fragment with the data.
+    fn ipv6_fragment(offset: u16, more: bool, data: &[u8]) -> Fragment<Ipv6> {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut fragment = ipv6.push::<Fragment<Ipv6>>().unwrap();
+        fragment.set_next_header(ProtocolNumbers::Udp);
+        fragment.set_identification(7);
+        fragment.set_fragment_offset(offset);
+        if more {
+            fragment.set_more_fragments();
+        }
+
+        let payload_offset = fragment.payload_offset();
+        fragment
+            .mbuf_mut()
+            .extend(payload_offset, data.len())
+            .unwrap();
+        fragment
+            .mbuf_mut()
+            .write_data_slice(payload_offset, data)
+            .unwrap();
+        fragment.reconcile_all();
+        fragment
+    }
+
+    #[capsule::test]
+    fn reassemble_ipv4_packet() {
+        let mut table = Ipv4ReassemblyTable::default();
+        let mut fragments = ipv4_fragments(1, 576);
+
+        // out of order
+        let last = fragments.pop().unwrap();
+        assert!(match table.reassemble(last).unwrap() {
+            Either::Drop(_) => true,
+            Either::Keep(_) => false,
+        });
+        assert_eq!(1, table.len());
+
+        let first = fragments.pop().unwrap();
+        let mut ipv4 = match table.reassemble(first).unwrap() {
+            Either::Keep(ipv4) => ipv4,
+            Either::Drop(_) => panic!("datagram not reassembled."),
+        };
+        assert!(table.is_empty());
+
+        assert!(!ipv4.is_fragment());
+        assert_eq!(1028, ipv4.total_length());
+
+        let checksum = ipv4.checksum();
+        ipv4.reconcile();
+        assert_eq!(checksum, ipv4.checksum());
+
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(1008, udp.length());
+        let offset = udp.payload_offset();
+        let last = udp.mbuf().read_data::<u8>(offset + 999).unwrap();
+        assert_eq!(0xff, unsafe { *last.as_ref() });
+    }
+
+    #[capsule::test]
+    fn reassemble_non_fragment() {
+        let mut table = Ipv4ReassemblyTable::default();
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+
+        assert!(match table.reassemble(ipv4).unwrap() {
+            Either::Keep(_) => true,
+            Either::Drop(_) => false,
+        });
+        assert!(table.is_empty());
+    }
+
+    #[capsule::test]
+    fn reassemble_overlapping_fragments() {
+        let mut table = Ipv4ReassemblyTable::default();
+        let mut first = ipv4_fragments(1, 576);
+        // fragmented differently, the first fragments overlap but differ.
+        let mut again = ipv4_fragments(1, 400);
+
+        assert!(table.reassemble(first.remove(0)).is_ok());
+        assert!(table.reassemble(again.remove(0)).is_err());
+        assert!(table.is_empty());
+    }
+
+    #[capsule::test]
+    fn reassemble_duplicate_fragment() {
+        let mut table = Ipv4ReassemblyTable::default();
+        let mut fragments = ipv4_fragments(1, 400);
+        let mut again = ipv4_fragments(1, 400);
+        assert_eq!(3, fragments.len());
+
+        assert!(table.reassemble(fragments.remove(0)).is_ok());
+        assert!(table.reassemble(fragments.remove(0)).is_ok());
+        // the duplicated middle fragment is dropped
+        assert!(match table.reassemble(again.remove(1)).unwrap() {
+            Either::Drop(_) => true,
+            Either::Keep(_) => false,
+        });
+        assert_eq!(1, table.len());
+
+        let ipv4 = match table.reassemble(fragments.remove(0)).unwrap() {
+            Either::Keep(ipv4) => ipv4,
+            Either::Drop(_) => panic!("datagram not reassembled."),
+        };
+        assert!(table.is_empty());
+        assert_eq!(1028, ipv4.total_length());
+    }
+
+    #[capsule::test]
+    fn reassemble_duplicate_ipv6_fragment() {
+        let mut table = Ipv6ReassemblyTable::default();
+
+        assert!(table.reassemble(ipv6_fragment(0, true, &[1; 16])).is_ok());
+        assert!(table.reassemble(ipv6_fragment(0, true, &[1; 16])).is_err());
+        assert!(table.is_empty());
+    }
+
+    #[capsule::test]
+    fn reassembly_table_limits() {
+        let mut table = Ipv4ReassemblyTable::new(1, 65_535, Duration::from_secs(30));
+        let mut one = ipv4_fragments(1, 576);
+        let mut two = ipv4_fragments(2, 576);
+
+        assert!(table.reassemble(one.remove(0)).is_ok());
+        assert!(table.reassemble(two.remove(0)).is_err());
+        assert_eq!(1, table.len());
+
+        let mut table = Ipv4ReassemblyTable::new(16, 512, Duration::from_secs(30));
+        let mut one = ipv4_fragments(1, 576);
+        assert!(table.reassemble(one.remove(0)).is_err());
+        assert!(table.is_empty());
+    }
+
+    #[capsule::test]
+    fn reassembly_timeout() {
+        let mut table = Ipv4ReassemblyTable::new(16, 65_535, Duration::from_secs(0));
+        let mut fragments = ipv4_fragments(1, 576);
+
+        assert!(table.reassemble(fragments.remove(0)).is_ok());
+        table.expire();
+        assert!(table.is_empty());
+
+        // the first fragment is discarded, can't complete the datagram
+        assert!(match table.reassemble(fragments.remove(0)).unwrap() {
+            Either::Drop(_) => true,
+            Either::Keep(_) => false,
+        });
+    }
+
+    #[capsule::test]
+    fn reassemble_ipv6_packet() {
+        let mut table = Ipv6ReassemblyTable::default();
+
+        let first = ipv6_fragment(0, true, &[1; 16]);
+        assert!(match table.reassemble(first).unwrap() {
+            Either::Drop(_) => true,
+            Either::Keep(_) => false,
+        });
+
+        let last = ipv6_fragment(2, false, &[2; 4]);
+        let ipv6 = match table.reassemble(last).unwrap() {
+            Either::Keep(ipv6) => ipv6,
+            Either::Drop(_) => panic!("datagram not reassembled."),
+        };
+        assert!(table.is_empty());
+
+        assert_eq!(ProtocolNumbers::Udp, ipv6.next_header());
+        assert_eq!(20, ipv6.payload_length());
+        assert_eq!(20, ipv6.payload_len());
+    }
+}
@@ -0,0 +1,361 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! IP-in-IP tunneling, defined in [IETF RFC 2003] and [IETF RFC 4213].
+//!
+//! An IPv4 or IPv6 packet can be the envelope of another IPv4 or IPv6
+//! packet. The outer packet's [`protocol`] or [`next header`] identifies
+//! the version of the inner packet, using protocol number [`Ipv4`] or
+//! [`Ipv6`].
+//!
+//! [IETF RFC 2003]: https://tools.ietf.org/html/rfc2003
+//! [IETF RFC 4213]: https://tools.ietf.org/html/rfc4213#section-3
+//! [`protocol`]: crate::packets::ip::v4::Ipv4::protocol
+//! [`next header`]: crate::packets::ip::v6::Ipv6Packet::next_header
+//! [`Ipv4`]: crate::packets::ip::ProtocolNumbers::Ipv4
+//! [`Ipv6`]: crate::packets::ip::ProtocolNumbers::Ipv6
+
+use crate::ensure;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet};
+use crate::packets::ip::{IpPacket, IpPacketError, ProtocolNumber, ProtocolNumbers, ECN_CE};
+use crate::packets::{EtherType, EtherTypeEnvelope, EtherTypes, Packet};
+use failure::Fallible;
+use std::net::IpAddr;
+
+/// Returns the ether type of the IP-in-IP payload identified by the
+/// protocol number.
+#[inline]
+fn to_ether_type(protocol: ProtocolNumber) -> EtherType {
+    match protocol {
+        ProtocolNumbers::Ipv4 => EtherTypes::Ipv4,
+        ProtocolNumbers::Ipv6 => EtherTypes::Ipv6,
+        // 0x0000 is an 802.3 length, never an ether type.
+        _ => EtherType::new(0),
+    }
+}
+
+/// Returns the protocol number of the IP-in-IP payload identified by the
+/// ether type.
+#[inline]
+fn to_protocol(ether_type: EtherType) -> Option<ProtocolNumber> {
+    match ether_type {
+        EtherTypes::Ipv4 => Some(ProtocolNumbers::Ipv4),
+        EtherTypes::Ipv6 => Some(ProtocolNumbers::Ipv6),
+        _ => None,
+    }
+}
+
+/// An IPv4 packet can carry an IPv4 or IPv6 packet as its payload.
+///
+/// The ether type of any other [`protocol`] is `0x0000`. Setting the ether
+/// type to anything other than IPv4 or IPv6 leaves the protocol unchanged.
+///
+/// [`protocol`]: Ipv4::protocol
+impl<E: EtherTypeEnvelope> EtherTypeEnvelope for Ipv4<E> {
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        to_ether_type(self.protocol())
+    }
+
+    #[inline]
+    fn set_ether_type(&mut self, ether_type: EtherType) {
+        if let Some(protocol) = to_protocol(ether_type) {
+            self.set_protocol(protocol);
+        }
+    }
+}
+
+/// An IPv6 packet can carry an IPv4 or IPv6 packet as its payload.
+///
+/// The ether type of any other [`next header`] is `0x0000`. Setting the
+/// ether type to anything other than IPv4 or IPv6 leaves the next header
+/// unchanged.
+///
+/// [`next header`]: Ipv6Packet::next_header
+impl<E: EtherTypeEnvelope> EtherTypeEnvelope for Ipv6<E> {
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        to_ether_type(self.next_header())
+    }
+
+    #[inline]
+    fn set_ether_type(&mut self, ether_type: EtherType) {
+        if let Some(protocol) = to_protocol(ether_type) {
+            self.set_next_header(protocol);
+        }
+    }
+}
+
+impl<E: EtherTypeEnvelope> Ipv4<E> {
+    /// Encapsulates the packet in a new outer IP packet `O` with the tunnel
+    /// endpoint addresses.
+    ///
+    /// The TTL, DSCP and ECN of the packet are copied to the outer header.
+    /// The lengths and checksums of the outer packet are reconciled. Returns
+    /// an error if the tunnel endpoint addresses are not of `O`'s version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let ipip = ipv4.encap::<Ipv4>(tunnel_src, tunnel_dst)?;
+    /// ```
+    pub fn encap<O>(self, src: IpAddr, dst: IpAddr) -> Fallible<Ipv4<O>>
+    where
+        O: IpPacket + EtherTypeEnvelope + Packet<Envelope = E>,
+    {
+        let ttl = self.ttl();
+        let dscp = self.dscp();
+        let ecn = self.ecn();
+
+        let mut outer = self.deparse().push::<O>()?;
+        outer.set_src(src)?;
+        outer.set_dst(dst)?;
+        outer.set_ttl(ttl);
+        outer.set_dscp(dscp);
+        outer.set_ecn(ecn);
+        outer.set_ether_type(EtherTypes::Ipv4);
+
+        let mut inner = outer.parse::<Ipv4<O>>()?;
+        inner.reconcile_all();
+        Ok(inner)
+    }
+}
+
+impl<O> Ipv4<O>
+where
+    O: IpPacket + EtherTypeEnvelope,
+    O::Envelope: EtherTypeEnvelope,
+{
+    /// Removes the outer IP packet, the packet becomes the payload of the
+    /// outer packet's envelope.
+    ///
+    /// If the outer header is marked as having experienced congestion, the
+    /// mark is propagated to the packet. Returns an error if the packet is
+    /// not from an ECN-capable transport, in which case it should be
+    /// dropped instead.
+    pub fn decap(self) -> Fallible<Ipv4<O::Envelope>> {
+        let outer_ecn = self.envelope().ecn();
+        let mut envelope = self.deparse().remove()?;
+        envelope.set_ether_type(EtherTypes::Ipv4);
+        let mut inner = envelope.parse::<Ipv4<O::Envelope>>()?;
+        if outer_ecn == ECN_CE {
+            ensure!(inner.mark_ce(), IpPacketError::NotEcnCapable);
+        }
+        inner.reconcile_all();
+        Ok(inner)
+    }
+}
+
+impl<E: EtherTypeEnvelope> Ipv6<E> {
+    /// Encapsulates the packet in a new outer IP packet `O` with the tunnel
+    /// endpoint addresses.
+    ///
+    /// The hop limit, DSCP and ECN of the packet are copied to the outer
+    /// header. The lengths and checksums of the outer packet are reconciled.
+    /// Returns an error if the tunnel endpoint addresses are not of `O`'s
+    /// version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv6 = ethernet.parse::<Ipv6>()?;
+    /// let sixin4 = ipv6.encap::<Ipv4>(tunnel_src, tunnel_dst)?;
+    /// ```
+    pub fn encap<O>(self, src: IpAddr, dst: IpAddr) -> Fallible<Ipv6<O>>
+    where
+        O: IpPacket + EtherTypeEnvelope + Packet<Envelope = E>,
+    {
+        let ttl = self.hop_limit();
+        let dscp = self.dscp();
+        let ecn = self.ecn();
+
+        let mut outer = self.deparse().push::<O>()?;
+        outer.set_src(src)?;
+        outer.set_dst(dst)?;
+        outer.set_ttl(ttl);
+        outer.set_dscp(dscp);
+        outer.set_ecn(ecn);
+        outer.set_ether_type(EtherTypes::Ipv6);
+
+        let mut inner = outer.parse::<Ipv6<O>>()?;
+        inner.reconcile_all();
+        Ok(inner)
+    }
+}
+
+impl<O> Ipv6<O>
+where
+    O: IpPacket + EtherTypeEnvelope,
+    O::Envelope: EtherTypeEnvelope,
+{
+    /// Removes the outer IP packet, the packet becomes the payload of the
+    /// outer packet's envelope.
+    ///
+    /// If the outer header is marked as having experienced congestion, the
+    /// mark is propagated to the packet. Returns an error if the packet is
+    /// not from an ECN-capable transport, in which case it should be
+    /// dropped instead.
+    pub fn decap(self) -> Fallible<Ipv6<O::Envelope>> {
+        let outer_ecn = self.envelope().ecn();
+        let mut envelope = self.deparse().remove()?;
+        envelope.set_ether_type(EtherTypes::Ipv6);
+        let mut inner = envelope.parse::<Ipv6<O::Envelope>>()?;
+        if outer_ecn == ECN_CE {
+            ensure!(inner.mark_ce(), IpPacketError::NotEcnCapable);
+        }
+        inner.reconcile_all();
+        Ok(inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::{ECN_ECT0, ECN_NOT_ECT};
+    use crate::packets::{Ethernet, Tcp, Udp};
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_IN_IPV4_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    fn read_bytes(mbuf: &Mbuf) -> &[u8] {
+        let bytes = mbuf.read_data_slice::<u8>(0, mbuf.data_len()).unwrap();
+        unsafe { bytes.as_ref() }
+    }
+
+    #[capsule::test]
+    fn parse_ipv6_in_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_IN_IPV4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Ipv6, ipv4.protocol());
+        assert_eq!(EtherTypes::Ipv6, ipv4.ether_type());
+
+        let ipv6 = ipv4.parse::<Ipv6<Ipv4>>().unwrap();
+        assert_eq!("2001:db8:85a3::1", ipv6.src().to_string());
+        assert_eq!("2001:db8:85a3::8a2e:370:7334", ipv6.dst().to_string());
+
+        let tcp = ipv6.parse::<Tcp<Ipv6<Ipv4>>>().unwrap();
+        assert_eq!(36869, tcp.src_port());
+        assert_eq!(23, tcp.dst_port());
+
+        // not an IPv4 payload.
+        let ipv6 = tcp.deparse();
+        assert!(ipv6.deparse().parse::<Ipv4<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn encap_ipv6_in_ipv4() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let ipv6 = ipv6
+            .encap::<Ipv4>(
+                "192.0.2.1".parse().unwrap(),
+                "198.51.100.1".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(&IPV6_IN_IPV4_PACKET[..], read_bytes(ipv6.mbuf()));
+    }
+
+    #[capsule::test]
+    fn encap_with_mismatched_addresses() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6
+            .encap::<Ipv4>("::1".parse().unwrap(), "::2".parse().unwrap())
+            .is_err());
+    }
+
+    #[capsule::test]
+    fn decap_ipv6_in_ipv4() {
+        let packet = Mbuf::from_bytes(&IPV6_IN_IPV4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ipv6 = ipv4.parse::<Ipv6<Ipv4>>().unwrap();
+
+        let ipv6 = ipv6.decap().unwrap();
+
+        assert_eq!(EtherTypes::Ipv6, ipv6.envelope().ether_type());
+        assert_eq!(&IPV6_TCP_PACKET[..], read_bytes(ipv6.mbuf()));
+    }
+
+    #[capsule::test]
+    fn encap_and_decap_ipv4_in_ipv4() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let ipv4 = ipv4
+            .encap::<Ipv4>("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap())
+            .unwrap();
+
+        let outer = ipv4.envelope();
+        assert_eq!(ProtocolNumbers::Ipv4, outer.protocol());
+        assert_eq!(20 + 38, outer.total_length());
+        assert_eq!(255, outer.ttl());
+
+        // the checksum is already reconciled.
+        let mut outer = ipv4.deparse();
+        let checksum = outer.checksum();
+        outer.reconcile();
+        assert_eq!(checksum, outer.checksum());
+
+        let ipv4 = outer.parse::<Ipv4<Ipv4>>().unwrap();
+        let udp = ipv4.decap().unwrap().parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(&IPV4_UDP_PACKET[..], read_bytes(udp.mbuf()));
+    }
+
+    #[capsule::test]
+    fn decap_propagates_congestion() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        ipv4.set_ecn(ECN_ECT0);
+
+        let mut ipv4 = ipv4
+            .encap::<Ipv6>("::1".parse().unwrap(), "::2".parse().unwrap())
+            .unwrap();
+        assert_eq!(ECN_ECT0, ipv4.envelope().ecn());
+
+        ipv4.envelope_mut().set_ecn(ECN_CE);
+        let ipv4 = ipv4.decap().unwrap();
+        assert_eq!(ECN_CE, ipv4.ecn());
+    }
+
+    #[capsule::test]
+    fn decap_congestion_not_ecn_capable() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(ECN_NOT_ECT, ipv4.ecn());
+
+        let mut ipv4 = ipv4
+            .encap::<Ipv6>("::1".parse().unwrap(), "::2".parse().unwrap())
+            .unwrap();
+
+        ipv4.envelope_mut().set_ecn(ECN_CE);
+        assert!(ipv4.decap().is_err());
+    }
+}
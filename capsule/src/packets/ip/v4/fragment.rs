@@ -0,0 +1,201 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{Ipv4, IPV4_MIN_MTU};
+use crate::packets::{EtherTypeEnvelope, Packet};
+use crate::{ensure, Mbuf};
+use failure::{Fail, Fallible};
+
+/// The copied flag of the option type. Options with the flag set are
+/// copied into all fragments.
+const OPTION_COPIED: u8 = 0b1000_0000;
+
+impl<E: EtherTypeEnvelope> Ipv4<E> {
+    /// Returns a flag indicating whether the packet is a fragment of a
+    /// larger datagram.
+    #[inline]
+    pub fn is_fragment(&self) -> bool {
+        self.more_fragments() || self.fragment_offset() != 0
+    }
+
+    /// Splits the packet into fragments that fit within the MTU.
+    ///
+    /// `mtu` is the maximum total length of each fragment's IPv4 packet.
+    /// Each fragment is a new `Mbuf` with a copy of the headers preceding
+    /// the IPv4 packet, followed by the fragment's IPv4 header and a slice
+    /// of the payload. All fragments but the first only keep the options
+    /// with the copied flag set. If the packet already fits within the MTU,
+    /// it is returned as the only fragment.
+    ///
+    /// Returns an error if the don't fragment flag is set or if the MTU is
+    /// below the minimum IPv4 MTU.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let fragments = ipv4.fragment(1500)?;
+    /// ```
+    pub fn fragment(mut self, mtu: usize) -> Fallible<Vec<Mbuf>> {
+        let total_length = self.total_length() as usize;
+        if total_length <= mtu {
+            return Ok(vec![self.reset()]);
+        }
+
+        ensure!(!self.dont_fragment(), FragmentationError::DontFragment);
+        ensure!(mtu >= IPV4_MIN_MTU, FragmentationError::MtuTooSmall(mtu));
+
+        let payload_len = total_length - self.header_len();
+        // the packet may already be a fragment.
+        let base_offset = self.fragment_offset() as usize * 8;
+        let more_fragments = self.more_fragments();
+
+        let mut fragments = Vec::new();
+        let mut consumed = 0;
+
+        while consumed < payload_len {
+            if fragments.len() == 1 {
+                self.retain_options(|option| option.option_type().0 & OPTION_COPIED != 0)?;
+            }
+
+            // the fragment data must be a multiple of 8 octets, except for
+            // the last fragment.
+            let chunk = ((mtu - self.header_len()) & !7).min(payload_len - consumed);
+            let last = consumed + chunk == payload_len;
+
+            self.set_fragment_offset(((base_offset + consumed) / 8) as u16);
+            if last && !more_fragments {
+                self.unset_more_fragments();
+            } else {
+                self.set_more_fragments();
+            }
+            self.set_total_length((self.header_len() + chunk) as u16);
+            self.compute_checksum();
+
+            let payload_offset = self.payload_offset();
+            let mut bytes = Vec::with_capacity(payload_offset + chunk);
+            let headers = self.mbuf().read_data_slice::<u8>(0, payload_offset)?;
+            bytes.extend_from_slice(unsafe { headers.as_ref() });
+            let data = self
+                .mbuf()
+                .read_data_slice::<u8>(payload_offset + consumed, chunk)?;
+            bytes.extend_from_slice(unsafe { data.as_ref() });
+
+            fragments.push(Mbuf::from_bytes(&bytes)?);
+            consumed += chunk;
+        }
+
+        Ok(fragments)
+    }
+}
+
+/// IPv4 fragmentation related errors.
+#[derive(Debug, Fail)]
+pub enum FragmentationError {
+    /// Error indicating the packet must not be fragmented.
+    #[fail(display = "Packet with the don't fragment flag can't be fragmented.")]
+    DontFragment,
+
+    /// Error indicating the MTU is too small.
+    #[fail(display = "MTU {} is below the minimum of 68.", _0)]
+    MtuTooSmall(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    /// Creates an IPv4 UDP packet with a payload of `len` octets.
+    fn large_packet(len: usize) -> Ipv4 {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let offset = udp.payload_offset();
+        udp.mbuf_mut().extend(offset, len).unwrap();
+        udp.reconcile_all();
+        udp.deparse()
+    }
+
+    #[capsule::test]
+    fn is_fragment() {
+        let mut ipv4 = large_packet(100);
+        assert!(!ipv4.is_fragment());
+
+        ipv4.set_more_fragments();
+        assert!(ipv4.is_fragment());
+
+        ipv4.unset_more_fragments();
+        ipv4.set_fragment_offset(10);
+        assert!(ipv4.is_fragment());
+    }
+
+    #[capsule::test]
+    fn fragment_ipv4_packet() {
+        // payload is 8 octets of udp header and 1000 octets of data
+        let ipv4 = large_packet(1000);
+        let fragments = ipv4.fragment(576).unwrap();
+
+        assert_eq!(2, fragments.len());
+
+        let mut iter = fragments.into_iter();
+
+        let first = iter.next().unwrap();
+        let first = first.parse::<Ethernet>().unwrap();
+        let mut first = first.parse::<Ipv4>().unwrap();
+        assert_eq!(572, first.total_length());
+        assert_eq!(0, first.fragment_offset());
+        assert!(first.more_fragments());
+        assert_eq!(ProtocolNumbers::Udp, first.protocol());
+
+        let checksum = first.checksum();
+        first.reconcile();
+        assert_eq!(checksum, first.checksum());
+
+        let second = iter.next().unwrap();
+        let second = second.parse::<Ethernet>().unwrap();
+        let second = second.parse::<Ipv4>().unwrap();
+        assert_eq!(476, second.total_length());
+        assert_eq!(69, second.fragment_offset());
+        assert!(!second.more_fragments());
+    }
+
+    #[capsule::test]
+    fn fragment_small_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let fragments = ipv4.fragment(1500).unwrap();
+        assert_eq!(1, fragments.len());
+        assert_eq!(IPV4_UDP_PACKET.len(), fragments[0].data_len());
+    }
+
+    #[capsule::test]
+    fn cannot_fragment_ipv4_packet() {
+        let mut ipv4 = large_packet(1000);
+        ipv4.set_dont_fragment();
+        assert!(ipv4.fragment(576).is_err());
+
+        let ipv4 = large_packet(1000);
+        assert!(ipv4.fragment(60).is_err());
+    }
+}
@@ -16,12 +16,19 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
-//! Internet Protocol v4.
+//! Internet Protocol v4, options and fragmentation.
 
+mod fragment;
+mod options;
+
+pub use self::fragment::*;
+pub use self::options::*;
+
+use crate::dpdk::BufferError;
 use crate::packets::checksum::{self, PseudoHeader};
 use crate::packets::ip::{IpPacket, IpPacketError, ProtocolNumber, DEFAULT_IP_TTL};
 use crate::packets::types::u16be;
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet, ParseError};
+use crate::packets::{EtherTypeEnvelope, EtherTypes, Ethernet, Internal, Packet, ParseError};
 use crate::{ensure, SizeOf};
 use failure::Fallible;
 use std::fmt;
@@ -137,18 +144,24 @@ const FLAGS_MF: u16be = u16be(u16::to_be(0b0010_0000_0000_0000));
 ///      The options may appear or not in datagrams. They must be
 ///      implemented by all IP modules (host and gateways). What is optional
 ///      is their transmission in any particular datagram, not their
-///      implementation.
+///      implementation. Use [`options_iter`] to read the options, and
+///      [`add_option`], [`retain_options`] or [`strip_options`] to modify
+///      them.
 ///
 /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.1
 /// [IETF RFC 2474]: https://tools.ietf.org/html/rfc2474
 /// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168
-pub struct Ipv4 {
-    envelope: Ethernet,
+/// [`options_iter`]: Ipv4::options_iter
+/// [`add_option`]: Ipv4::add_option
+/// [`retain_options`]: Ipv4::retain_options
+/// [`strip_options`]: Ipv4::strip_options
+pub struct Ipv4<E: EtherTypeEnvelope = Ethernet> {
+    envelope: E,
     header: NonNull<Ipv4Header>,
     offset: usize,
 }
 
-impl Ipv4 {
+impl<E: EtherTypeEnvelope> Ipv4<E> {
     #[inline]
     fn header(&self) -> &Ipv4Header {
         unsafe { self.header.as_ref() }
@@ -172,10 +185,9 @@ impl Ipv4 {
         self.header().version_ihl & 0x0f
     }
 
-    #[allow(dead_code)]
     #[inline]
     fn set_ihl(&mut self, ihl: u8) {
-        self.header_mut().version_ihl = (self.header().version_ihl & 0x0f) | (ihl & 0x0f);
+        self.header_mut().version_ihl = (self.header().version_ihl & 0xf0) | (ihl & 0x0f);
     }
 
     /// Returns the differentiated services codepoint.
@@ -184,10 +196,11 @@ impl Ipv4 {
         self.header().dscp_ecn >> 2
     }
 
-    /// Sets the differentiated services codepoint.
+    /// Sets the differentiated services codepoint. The header checksum is
+    /// updated.
     #[inline]
     pub fn set_dscp(&mut self, dscp: u8) {
-        self.header_mut().dscp_ecn = (self.header().dscp_ecn & ECN) | (dscp << 2);
+        self.set_dscp_ecn((self.header().dscp_ecn & ECN) | (dscp << 2));
     }
 
     /// Returns the explicit congestion notification codepoint.
@@ -196,10 +209,23 @@ impl Ipv4 {
         self.header().dscp_ecn & ECN
     }
 
-    /// Sets the explicit congestion notification codepoint.
+    /// Sets the explicit congestion notification codepoint. The header
+    /// checksum is updated.
     #[inline]
     pub fn set_ecn(&mut self, ecn: u8) {
-        self.header_mut().dscp_ecn = (self.header().dscp_ecn & DSCP) | (ecn & ECN);
+        self.set_dscp_ecn((self.header().dscp_ecn & DSCP) | (ecn & ECN));
+    }
+
+    /// Sets the DSCP and ECN octet, and incrementally updates the checksum
+    /// so it stays correct without a full recompute.
+    #[inline]
+    fn set_dscp_ecn(&mut self, dscp_ecn: u8) {
+        let version_ihl = u16::from(self.header().version_ihl) << 8;
+        let old = version_ihl | u16::from(self.header().dscp_ecn);
+        let new = version_ihl | u16::from(dscp_ecn);
+        let checksum = checksum::compute_inc(self.checksum(), &[old], &[new]);
+        self.header_mut().dscp_ecn = dscp_ecn;
+        self.set_checksum(checksum);
     }
 
     /// Returns the length of the packet, measured in octets, including
@@ -355,9 +381,150 @@ impl Ipv4 {
     pub fn set_dst(&mut self, dst: Ipv4Addr) {
         self.header_mut().dst = dst;
     }
+
+    /// Returns the raw options, including any padding.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let len = self.header_len() - Ipv4Header::size_of();
+        if len == 0 {
+            return &[];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<u8>(self.offset + Ipv4Header::size_of(), len)
+        {
+            Ok(options) => unsafe { options.as_ref() },
+            // the header length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the options in the header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv4 = ethernet.parse::<Ipv4>()?;
+    /// let mut iter = ipv4.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> Ipv4OptionsIterator<'_> {
+        Ipv4OptionsIterator::new(self.options())
+    }
+
+    /// Replaces the options with the encoded bytes. The options are padded
+    /// to a 32-bit boundary and `ihl` is updated. The total length and the
+    /// checksum are fixed when the packet is reconciled.
+    pub(crate) fn set_options_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let new_len = (bytes.len() + 3) / 4 * 4;
+        ensure!(
+            new_len <= IPV4_MAX_OPTIONS_LEN,
+            Ipv4OptionsError::TooLong(bytes.len())
+        );
+
+        let old_len = self.header_len() - Ipv4Header::size_of();
+        let offset = self.offset + Ipv4Header::size_of();
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        if !bytes.is_empty() {
+            mbuf.write_data_slice(offset, bytes)?;
+        }
+        // pads with end of option list.
+        let padding = new_len - bytes.len();
+        if padding > 0 {
+            mbuf.write_data_slice(offset + bytes.len(), &[0u8; 3][..padding])?;
+        }
+
+        self.set_ihl(((Ipv4Header::size_of() + new_len) / 4) as u8);
+        Ok(())
+    }
+
+    /// Replaces the options in the header.
+    ///
+    /// The header is resized and `ihl` is updated. Call [`reconcile`]
+    /// afterwards to fix the total length and the checksum.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    pub fn set_options(&mut self, options: &[Ipv4Option<'_>]) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(IPV4_MAX_OPTIONS_LEN);
+        for option in options {
+            option.encode(&mut bytes)?;
+        }
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Adds an option after the existing options.
+    ///
+    /// The header is resized and `ihl` is updated. Call [`reconcile`]
+    /// afterwards to fix the total length and the checksum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut ipv4 = ethernet.parse::<Ipv4>()?;
+    /// ipv4.add_option(Ipv4Option::RouterAlert(0))?;
+    /// ipv4.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    pub fn add_option(&mut self, option: Ipv4Option<'_>) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(IPV4_MAX_OPTIONS_LEN);
+        let mut iter = self.options_iter();
+        while let Some(existing) = iter.next()? {
+            // drops the end of list, a new one is added as padding
+            if existing != Ipv4Option::EndOfList {
+                existing.encode(&mut bytes)?;
+            }
+        }
+        option.encode(&mut bytes)?;
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// The header is resized and `ihl` is updated. Call [`reconcile`]
+    /// afterwards to fix the total length and the checksum.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    pub fn retain_options<F>(&mut self, mut f: F) -> Fallible<()>
+    where
+        F: FnMut(&Ipv4Option<'_>) -> bool,
+    {
+        let mut bytes = Vec::with_capacity(IPV4_MAX_OPTIONS_LEN);
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if option != Ipv4Option::EndOfList && f(&option) {
+                option.encode(&mut bytes)?;
+            }
+        }
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Removes all the options.
+    ///
+    /// The header is resized and `ihl` is set to `5`. Call [`reconcile`]
+    /// afterwards to fix the total length and the checksum.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    #[inline]
+    pub fn strip_options(&mut self) -> Fallible<()> {
+        self.set_options_bytes(&[])
+    }
 }
 
-impl fmt::Debug for Ipv4 {
+impl<E: EtherTypeEnvelope> fmt::Debug for Ipv4<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ipv4")
             .field("src", &format!("{}", self.src()))
@@ -380,9 +547,13 @@ impl fmt::Debug for Ipv4 {
     }
 }
 
-impl Packet for Ipv4 {
-    /// The preceding type for an IPv4 packet must be Ethernet.
-    type Envelope = Ethernet;
+impl<E: EtherTypeEnvelope> Packet for Ipv4<E> {
+    /// The preceding type for an IPv4 packet is usually Ethernet. It can also
+    /// be any other packet that identifies its payload with an EtherType,
+    /// for example [GRE].
+    ///
+    /// [GRE]: crate::packets::ip::Gre
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -399,9 +570,12 @@ impl Packet for Ipv4 {
         self.offset
     }
 
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the IPv4 header includes the options.
     #[inline]
     fn header_len(&self) -> usize {
-        Ipv4Header::size_of()
+        self.ihl() as usize * 4
     }
 
     #[inline]
@@ -413,12 +587,12 @@ impl Packet for Ipv4 {
         }
     }
 
-    /// Parses the Ethernet's payload as an IPv4 packet.
+    /// Parses the envelope's payload as an IPv4 packet.
     ///
     /// [`ether_type`] must be set to [`EtherTypes::Ipv4`]. Otherwise a parsing
     /// error is returned.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`ether_type`]: EtherTypeEnvelope::ether_type
     /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
@@ -431,18 +605,32 @@ impl Packet for Ipv4 {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Ipv4 {
+        let packet = Ipv4 {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header_len() >= Ipv4Header::size_of(),
+            ParseError::new("Invalid IPv4 header length.")
+        );
+
+        // we've only parsed the fixed 20 bytes, make sure there's enough
+        // data for the options as well.
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
     }
 
-    /// Prepends an IPv4 packet to the beginning of the Ethernet's payload.
+    /// Prepends an IPv4 packet to the beginning of the envelope's payload.
     ///
     /// [`ether_type`] is set to [`EtherTypes::Ipv4`].
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`ether_type`]: EtherTypeEnvelope::ether_type
     /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
     #[inline]
     fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
@@ -482,7 +670,7 @@ impl Packet for Ipv4 {
     }
 }
 
-impl IpPacket for Ipv4 {
+impl<E: EtherTypeEnvelope> IpPacket for Ipv4<E> {
     #[inline]
     fn next_protocol(&self) -> ProtocolNumber {
         self.protocol()
@@ -542,10 +730,40 @@ impl IpPacket for Ipv4 {
             IpPacketError::MtuTooSmall(mtu, IPV4_MIN_MTU)
         );
 
-        // accounts for the Ethernet frame length.
+        // accounts for the length of the envelopes.
         let to_len = mtu + self.offset();
         self.mbuf_mut().truncate(to_len)
     }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.ttl()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.set_ttl(ttl);
+    }
 }
 
 /// IPv4 header.
@@ -588,7 +806,8 @@ impl Default for Ipv4Header {
 mod tests {
     use super::*;
     use crate::packets::ip::ProtocolNumbers;
-    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{IPV4_OPTIONS_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
     use crate::Mbuf;
 
     #[test]
@@ -659,6 +878,14 @@ mod tests {
         ipv4.set_ecn(3);
         assert_eq!(10, ipv4.dscp());
         assert_eq!(3, ipv4.ecn());
+
+        // the checksum is incrementally updated.
+        ipv4.compute_checksum();
+        ipv4.set_dscp(46);
+        ipv4.set_ecn(1);
+        let checksum = ipv4.checksum();
+        ipv4.compute_checksum();
+        assert_eq!(checksum, ipv4.checksum());
     }
 
     #[capsule::test]
@@ -702,4 +929,72 @@ mod tests {
         ipv4.reconcile_all();
         assert_eq!(expected, ipv4.checksum());
     }
+
+    #[capsule::test]
+    fn parse_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert_eq!(9, ipv4.ihl());
+        assert_eq!(36, ipv4.header_len());
+        assert_eq!(16, ipv4.options().len());
+
+        let mut iter = ipv4.options_iter();
+        assert_eq!(Ipv4Option::RouterAlert(0), iter.next().unwrap().unwrap());
+        assert_eq!(Ipv4Option::NoOperation, iter.next().unwrap().unwrap());
+        match iter.next().unwrap().unwrap() {
+            Ipv4Option::RecordRoute(route) => assert_eq!(8, route.pointer()),
+            _ => panic!("not a record route option."),
+        }
+        assert!(iter.next().unwrap().is_none());
+
+        // the payload starts after the options
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_truncated_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET[..40]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Ipv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn add_and_strip_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.add_option(Ipv4Option::RouterAlert(0)).is_ok());
+        assert!(ipv4.add_option(Ipv4Option::NoOperation).is_ok());
+        let route = [10, 0, 0, 1, 0, 0, 0, 0];
+        let option = Ipv4Option::RecordRoute(RouteOption::new(8, &route));
+        assert!(ipv4.add_option(option).is_ok());
+        ipv4.reconcile_all();
+
+        // same as the packet with options
+        let bytes = ipv4
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_OPTIONS_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_OPTIONS_PACKET[..], unsafe { bytes.as_ref() });
+
+        let large = [0; 40];
+        assert!(ipv4.add_option(Ipv4Option::Security(&large)).is_err());
+
+        assert!(ipv4
+            .retain_options(|option| option.option_type() == Ipv4OptionTypes::RouterAlert)
+            .is_ok());
+        assert_eq!(6, ipv4.ihl());
+        assert_eq!(&[0x94, 0x04, 0x00, 0x00], ipv4.options());
+
+        assert!(ipv4.strip_options().is_ok());
+        ipv4.reconcile_all();
+        assert_eq!(5, ipv4.ihl());
+        assert_eq!(38, ipv4.total_length());
+        assert_eq!(0xf700, ipv4.checksum());
+    }
 }
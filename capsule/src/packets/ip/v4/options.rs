@@ -0,0 +1,449 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The maximum length of the IPv4 options in octets.
+///
+/// The IHL field is 4 bits, so the header is at most 60 octets, of which
+/// 20 are the fixed portion.
+pub const IPV4_MAX_OPTIONS_LEN: usize = 40;
+
+/// [IANA] assigned IPv4 option type.
+///
+/// A list of supported types is under [`Ipv4OptionTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml#ip-parameters-1
+/// [`Ipv4OptionTypes`]: Ipv4OptionTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv4OptionType(pub u8);
+
+/// Supported IPv4 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv4OptionTypes {
+    use super::Ipv4OptionType;
+
+    /// End of Option List.
+    pub const EndOfList: Ipv4OptionType = Ipv4OptionType(0);
+
+    /// No Operation.
+    pub const NoOperation: Ipv4OptionType = Ipv4OptionType(1);
+
+    /// Record Route.
+    pub const RecordRoute: Ipv4OptionType = Ipv4OptionType(7);
+
+    /// Internet Timestamp.
+    pub const Timestamp: Ipv4OptionType = Ipv4OptionType(68);
+
+    /// Security.
+    pub const Security: Ipv4OptionType = Ipv4OptionType(130);
+
+    /// Router Alert.
+    pub const RouterAlert: Ipv4OptionType = Ipv4OptionType(148);
+}
+
+impl fmt::Display for Ipv4OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv4OptionTypes::EndOfList => "End of Option List".to_string(),
+                Ipv4OptionTypes::NoOperation => "No Operation".to_string(),
+                Ipv4OptionTypes::RecordRoute => "Record Route".to_string(),
+                Ipv4OptionTypes::Timestamp => "Timestamp".to_string(),
+                Ipv4OptionTypes::Security => "Security".to_string(),
+                Ipv4OptionTypes::RouterAlert => "Router Alert".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// An IPv4 option based on [IETF RFC 791].
+///
+/// Except for the single octet end of option list and no operation
+/// options, all options are encoded as a type octet, a length octet
+/// that includes the type and length octets, and the option data.
+///
+/// The options borrow their data from the packet buffer, or from the
+/// caller when used to write new options.
+///
+/// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ipv4Option<'a> {
+    /// End of option list. Marks the end of all options, the remaining
+    /// octets of the header are padding.
+    EndOfList,
+
+    /// No operation. Used between options to align the next option.
+    NoOperation,
+
+    /// Record route option.
+    RecordRoute(RouteOption<'a>),
+
+    /// Internet timestamp option, defined in [IETF RFC 791].
+    ///
+    /// [IETF RFC 791]: https://tools.ietf.org/html/rfc791#section-3.1
+    Timestamp(TimestampOption<'a>),
+
+    /// Security option, defined in [IETF RFC 1108]. The data is opaque.
+    ///
+    /// [IETF RFC 1108]: https://tools.ietf.org/html/rfc1108
+    Security(&'a [u8]),
+
+    /// Router alert option with its 16-bit value, defined in
+    /// [IETF RFC 2113].
+    ///
+    /// [IETF RFC 2113]: https://tools.ietf.org/html/rfc2113
+    RouterAlert(u16),
+
+    /// Any other option, with its type and data.
+    Unknown(Ipv4OptionType, &'a [u8]),
+}
+
+impl Ipv4Option<'_> {
+    /// Returns the option type.
+    pub fn option_type(&self) -> Ipv4OptionType {
+        match self {
+            Ipv4Option::EndOfList => Ipv4OptionTypes::EndOfList,
+            Ipv4Option::NoOperation => Ipv4OptionTypes::NoOperation,
+            Ipv4Option::RecordRoute(_) => Ipv4OptionTypes::RecordRoute,
+            Ipv4Option::Timestamp(_) => Ipv4OptionTypes::Timestamp,
+            Ipv4Option::Security(_) => Ipv4OptionTypes::Security,
+            Ipv4Option::RouterAlert(_) => Ipv4OptionTypes::RouterAlert,
+            Ipv4Option::Unknown(option_type, _) => *option_type,
+        }
+    }
+
+    /// Returns the length of the encoded option in octets.
+    pub fn length(&self) -> usize {
+        match self {
+            Ipv4Option::EndOfList | Ipv4Option::NoOperation => 1,
+            Ipv4Option::RecordRoute(route) => 3 + route.data.len(),
+            Ipv4Option::Timestamp(timestamp) => 4 + timestamp.data.len(),
+            Ipv4Option::Security(data) | Ipv4Option::Unknown(_, data) => 2 + data.len(),
+            Ipv4Option::RouterAlert(_) => 4,
+        }
+    }
+
+    /// Encodes the option and appends it to the buffer.
+    pub(crate) fn encode(&self, buffer: &mut Vec<u8>) -> Fallible<()> {
+        let len = self.length();
+        ensure!(len <= IPV4_MAX_OPTIONS_LEN, Ipv4OptionsError::TooLong(len));
+
+        buffer.push(self.option_type().0);
+        match self {
+            Ipv4Option::EndOfList | Ipv4Option::NoOperation => (),
+            Ipv4Option::RecordRoute(route) => {
+                buffer.push(len as u8);
+                buffer.push(route.pointer);
+                buffer.extend_from_slice(route.data);
+            }
+            Ipv4Option::Timestamp(timestamp) => {
+                buffer.push(len as u8);
+                buffer.push(timestamp.pointer);
+                buffer.push((timestamp.overflow << 4) | timestamp.flag);
+                buffer.extend_from_slice(timestamp.data);
+            }
+            Ipv4Option::Security(data) | Ipv4Option::Unknown(_, data) => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(data);
+            }
+            Ipv4Option::RouterAlert(value) => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The record route option.
+///
+/// ```
+/// +--------+--------+--------+---------//--------+
+/// |00000111| length | pointer|     route data    |
+/// +--------+--------+--------+---------//--------+
+/// ```
+///
+/// The pointer is the octet offset, relative to the start of the option,
+/// of the next area to record a route address. The smallest legal value
+/// is 4.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RouteOption<'a> {
+    pointer: u8,
+    data: &'a [u8],
+}
+
+impl<'a> RouteOption<'a> {
+    /// Creates a new record route option with the route data. For an
+    /// empty route, `data` should be zeroed slots of 4 octets each and
+    /// `pointer` should be 4.
+    pub fn new(pointer: u8, data: &'a [u8]) -> Self {
+        RouteOption { pointer, data }
+    }
+
+    /// Returns the pointer.
+    pub fn pointer(&self) -> u8 {
+        self.pointer
+    }
+
+    /// Returns the route data, including the slots not recorded yet.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the addresses already recorded, those before the pointer.
+    pub fn addresses(&self) -> Vec<Ipv4Addr> {
+        let recorded = (self.pointer as usize)
+            .saturating_sub(4)
+            .min(self.data.len());
+        self.data[..recorded]
+            .chunks_exact(4)
+            .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+            .collect()
+    }
+}
+
+/// The internet timestamp option.
+///
+/// ```
+/// +--------+--------+--------+--------+
+/// |01000100| length | pointer|oflw|flg|
+/// +--------+--------+--------+--------+
+/// |         internet address          |
+/// +--------+--------+--------+--------+
+/// |             timestamp             |
+/// +--------+--------+--------+--------+
+/// |                 .                 |
+/// ```
+///
+/// The format of the data depends on the flag. The overflow is the number
+/// of hosts that couldn't register timestamps due to lack of space.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimestampOption<'a> {
+    pointer: u8,
+    overflow: u8,
+    flag: u8,
+    data: &'a [u8],
+}
+
+impl<'a> TimestampOption<'a> {
+    /// Creates a new timestamp option. Only the lower 4 bits of `overflow`
+    /// and `flag` are used.
+    pub fn new(pointer: u8, overflow: u8, flag: u8, data: &'a [u8]) -> Self {
+        TimestampOption {
+            pointer,
+            overflow: overflow & 0x0f,
+            flag: flag & 0x0f,
+            data,
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn pointer(&self) -> u8 {
+        self.pointer
+    }
+
+    /// Returns the overflow count.
+    pub fn overflow(&self) -> u8 {
+        self.overflow
+    }
+
+    /// Returns the flag. `0` for timestamps only, `1` for addresses and
+    /// timestamps, and `3` for prespecified addresses.
+    pub fn flag(&self) -> u8 {
+        self.flag
+    }
+
+    /// Returns the timestamp data.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// An iterator that iterates through the options in the IPv4 header.
+///
+/// Iteration stops after the end of option list option.
+#[derive(Debug)]
+pub struct Ipv4OptionsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Ipv4OptionsIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Ipv4OptionsIterator { data }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<Ipv4Option<'a>>> {
+        let data = self.data;
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let option_type = Ipv4OptionType(data[0]);
+        match option_type {
+            Ipv4OptionTypes::EndOfList => {
+                // the rest is padding
+                self.data = &[];
+                return Ok(Some(Ipv4Option::EndOfList));
+            }
+            Ipv4OptionTypes::NoOperation => {
+                self.data = &data[1..];
+                return Ok(Some(Ipv4Option::NoOperation));
+            }
+            _ => (),
+        }
+
+        ensure!(
+            data.len() >= 2 && data[1] >= 2 && data[1] as usize <= data.len(),
+            ParseError::new("Invalid IPv4 option length.")
+        );
+
+        let len = data[1] as usize;
+        let value = &data[2..len];
+        self.data = &data[len..];
+
+        let option = match option_type {
+            Ipv4OptionTypes::RecordRoute => {
+                ensure!(len >= 3, ParseError::new("Invalid record route length."));
+                Ipv4Option::RecordRoute(RouteOption::new(value[0], &value[1..]))
+            }
+            Ipv4OptionTypes::Timestamp => {
+                ensure!(len >= 4, ParseError::new("Invalid timestamp length."));
+                Ipv4Option::Timestamp(TimestampOption::new(
+                    value[0],
+                    value[1] >> 4,
+                    value[1],
+                    &value[2..],
+                ))
+            }
+            Ipv4OptionTypes::Security => Ipv4Option::Security(value),
+            Ipv4OptionTypes::RouterAlert => {
+                ensure!(len == 4, ParseError::new("Invalid router alert length."));
+                Ipv4Option::RouterAlert(u16::from_be_bytes([value[0], value[1]]))
+            }
+            _ => Ipv4Option::Unknown(option_type, value),
+        };
+
+        Ok(Some(option))
+    }
+}
+
+/// IPv4 options related errors.
+#[derive(Debug, Fail)]
+pub enum Ipv4OptionsError {
+    /// Error indicating the options exceed the maximum length.
+    #[fail(display = "Options length {} exceeds the maximum of 40.", _0)]
+    TooLong(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_type_to_string() {
+        assert_eq!("Router Alert", Ipv4OptionTypes::RouterAlert.to_string());
+        assert_eq!("25", Ipv4OptionType(25).to_string());
+    }
+
+    #[test]
+    fn iterate_ipv4_options() {
+        let data = [
+            0x94, 0x04, 0x00, 0x00, 0x01, 0x07, 0x0b, 0x08, 0x0a, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00, 0x44, 0x08, 0x05, 0x10, 0x00, 0x00, 0x00, 0x01, 0x19, 0x02, 0x00, 0x00,
+        ];
+        let mut iter = Ipv4OptionsIterator::new(&data);
+
+        assert_eq!(Ipv4Option::RouterAlert(0), iter.next().unwrap().unwrap());
+        assert_eq!(Ipv4Option::NoOperation, iter.next().unwrap().unwrap());
+
+        match iter.next().unwrap().unwrap() {
+            Ipv4Option::RecordRoute(route) => {
+                assert_eq!(8, route.pointer());
+                assert_eq!(vec![Ipv4Addr::new(10, 0, 0, 1)], route.addresses());
+            }
+            _ => panic!("not a record route option."),
+        }
+
+        match iter.next().unwrap().unwrap() {
+            Ipv4Option::Timestamp(timestamp) => {
+                assert_eq!(5, timestamp.pointer());
+                assert_eq!(1, timestamp.overflow());
+                assert_eq!(0, timestamp.flag());
+                assert_eq!(&[0, 0, 0, 1], timestamp.data());
+            }
+            _ => panic!("not a timestamp option."),
+        }
+
+        assert_eq!(
+            Ipv4Option::Unknown(Ipv4OptionType(0x19), &[]),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(Ipv4Option::EndOfList, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_ipv4_option_length() {
+        let data = [0x07, 0x0b, 0x04, 0x00];
+        let mut iter = Ipv4OptionsIterator::new(&data);
+        assert!(iter.next().is_err());
+
+        let data = [0x94, 0x03, 0x00, 0x00];
+        let mut iter = Ipv4OptionsIterator::new(&data);
+        assert!(iter.next().is_err());
+    }
+
+    #[test]
+    fn encode_ipv4_options() {
+        let route = [0; 8];
+        let options = [
+            Ipv4Option::RouterAlert(0),
+            Ipv4Option::NoOperation,
+            Ipv4Option::RecordRoute(RouteOption::new(4, &route)),
+            Ipv4Option::Timestamp(TimestampOption::new(5, 1, 0, &[0, 0, 0, 1])),
+            Ipv4Option::Security(&[1, 2]),
+        ];
+
+        let mut buffer = vec![];
+        for option in options.iter() {
+            option.encode(&mut buffer).unwrap();
+        }
+
+        let mut iter = Ipv4OptionsIterator::new(&buffer);
+        for option in options.iter() {
+            assert_eq!(*option, iter.next().unwrap().unwrap());
+        }
+        assert!(iter.next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,477 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::options::{options_header_len, write_options, OptionsHeader, EMPTY_OPTIONS};
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::{Ipv6Option, Ipv6OptionsEncoder, Ipv6OptionsIterator, Ipv6Packet};
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// IPv6 Destination Options Extension packet based on [IETF RFC 8200].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Next Header  |  Hdr Ext Len  |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+/// |                                                               |
+/// .                                                               .
+/// .                            Options                            .
+/// .                                                               .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*:      8-bit selector.  Identifies the type of header
+///                       immediately following the Destination Options
+///                       header. Uses the same values as the IPv4
+///                       Protocol field [IANA-PN].
+///
+/// - *Hdr Ext Len*:      8-bit unsigned integer.  Length of the Destination
+///                       Options header in 8-octet units, not including
+///                       the first 8 octets.
+///
+/// - *Options*:          Variable-length field, of length such that the
+///                       complete Destination Options header is an integer
+///                       multiple of 8 octets long. Use [`options_iter`]
+///                       to read the options, and [`set_options`] or
+///                       [`add_option`] to modify them.
+///
+/// The options carry information that need be examined only by the
+/// packet's destination node(s). When followed by a routing header, the
+/// options are also examined by each node listed in the routing header.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.6
+/// [`options_iter`]: DestinationOptions::options_iter
+/// [`set_options`]: DestinationOptions::set_options
+/// [`add_option`]: DestinationOptions::add_option
+pub struct DestinationOptions<E: Ipv6Packet> {
+    envelope: E,
+    header: NonNull<OptionsHeader>,
+    offset: usize,
+}
+
+impl<E: Ipv6Packet> DestinationOptions<E> {
+    #[inline]
+    fn header(&self) -> &OptionsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut OptionsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the header in 8-octet units, not including
+    /// the first 8 octets.
+    #[inline]
+    pub fn hdr_ext_len(&self) -> u8 {
+        self.header().hdr_ext_len
+    }
+
+    /// Returns the raw options, including any padding.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        match self.mbuf().read_data_slice::<u8>(
+            self.offset + OptionsHeader::size_of(),
+            self.header_len() - OptionsHeader::size_of(),
+        ) {
+            Ok(options) => unsafe { options.as_ref() },
+            // the header length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the options in the header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let destination = ipv6.parse::<DestinationOptions<Ipv6>>()?;
+    /// let mut iter = destination.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> Ipv6OptionsIterator<'_> {
+        Ipv6OptionsIterator::new(self.options())
+    }
+
+    /// Replaces the encoded options and updates `hdr_ext_len`.
+    fn set_options_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let offset = self.offset;
+        let old_len = self.header_len();
+        let hdr_ext_len = write_options(self.mbuf_mut(), offset, old_len, bytes)?;
+        self.header_mut().hdr_ext_len = hdr_ext_len;
+        Ok(())
+    }
+
+    /// Replaces the options in the header.
+    ///
+    /// Padding is added to align the options and to make the header a
+    /// multiple of 8 octets long. The header is resized and `hdr_ext_len`
+    /// is updated. Call [`reconcile_all`] afterwards to fix the payload
+    /// length of the IPv6 packet.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn set_options(&mut self, options: &[Ipv6Option<'_>]) -> Fallible<()> {
+        let mut encoder = Ipv6OptionsEncoder::new();
+        for option in options {
+            encoder.push(option)?;
+        }
+        self.set_options_bytes(&encoder.finish()?)
+    }
+
+    /// Adds an option after the existing options.
+    ///
+    /// The existing padding is recomputed. The header is resized and
+    /// `hdr_ext_len` is updated. Call [`reconcile_all`] afterwards to fix
+    /// the payload length of the IPv6 packet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut destination = ipv6.push::<DestinationOptions<Ipv6>>()?;
+    /// destination.add_option(Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[0; 4]))?;
+    /// destination.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_option(&mut self, option: Ipv6Option<'_>) -> Fallible<()> {
+        let mut encoder = Ipv6OptionsEncoder::new();
+        let mut iter = self.options_iter();
+        while let Some(existing) = iter.next()? {
+            if !existing.is_padding() {
+                encoder.push(&existing)?;
+            }
+        }
+        encoder.push(&option)?;
+        self.set_options_bytes(&encoder.finish()?)
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for DestinationOptions<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("destination options")
+            .field("next_header", &format!("{}", self.next_header()))
+            .field("hdr_ext_len", &self.hdr_ext_len())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Packet for DestinationOptions<E> {
+    /// The preceding type for an IPv6 destination options packet can be
+    /// either an IPv6 packet or any possible IPv6 extension packets.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the destination options header includes the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        (self.hdr_ext_len() as usize + 1) * 8
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        DestinationOptions::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an IPv6 destination options packet.
+    ///
+    /// [`next_header`] of the envelope must be set to
+    /// [`ProtocolNumbers::Ipv6Opts`]. Otherwise a parsing error is
+    /// returned.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    /// [`ProtocolNumbers::Ipv6Opts`]: ProtocolNumbers::Ipv6Opts
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_header() == ProtocolNumbers::Ipv6Opts,
+            ParseError::new("not an IPv6 destination options packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+        // makes sure there's enough data for the options as well.
+        options_header_len(mbuf, offset)?;
+
+        Ok(DestinationOptions {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IPv6 destination options packet without options to the
+    /// beginning of the envelope's payload.
+    ///
+    /// [`next_header`] is set to the value of the `next_header` field of the
+    /// envelope, and the envelope is set to [`ProtocolNumbers::Ipv6Opts`].
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    /// [`ProtocolNumbers::Ipv6Opts`]: ProtocolNumbers::Ipv6Opts
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, OptionsHeader::size_of() + EMPTY_OPTIONS.len())?;
+        let header = mbuf.write_data(offset, &OptionsHeader::default())?;
+        mbuf.write_data_slice(offset + OptionsHeader::size_of(), &EMPTY_OPTIONS)?;
+
+        let mut packet = DestinationOptions {
+            envelope,
+            header,
+            offset,
+        };
+
+        packet.set_next_header(packet.envelope().next_header());
+        packet
+            .envelope_mut()
+            .set_next_header(ProtocolNumbers::Ipv6Opts);
+
+        Ok(packet)
+    }
+
+    /// Removes IPv6 destination options packet from the message buffer.
+    ///
+    /// The envelope's [`next_header`] field is set to the value of the
+    /// `next_header` field on the destination options packet.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn remove(mut self) -> Fallible<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_header();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_header(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: Ipv6Packet> IpPacket for DestinationOptions<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        self.next_header()
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.set_next_header(proto);
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Fallible<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.envelope().dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.envelope_mut().set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.envelope().ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.envelope_mut().set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.envelope().ttl()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.envelope_mut().set_ttl(ttl);
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for DestinationOptions<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.header_mut().next_header = next_header.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{HopByHopOptions, Ipv6, Ipv6OptionType};
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::{IPV6_EXT_OPTIONS_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    #[capsule::test]
+    fn parse_destination_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_EXT_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>().unwrap();
+        let destination = hop_by_hop
+            .parse::<DestinationOptions<HopByHopOptions<Ipv6>>>()
+            .unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, destination.next_header());
+        assert_eq!(0, destination.hdr_ext_len());
+        assert_eq!(8, destination.header_len());
+
+        let mut iter = destination.options_iter();
+        assert_eq!(Ipv6Option::PadN(4), iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+
+        let udp = destination
+            .parse::<Udp<DestinationOptions<HopByHopOptions<Ipv6>>>>()
+            .unwrap();
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_destination_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.parse::<DestinationOptions<Ipv6>>().is_err());
+    }
+
+    #[capsule::test]
+    fn insert_destination_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let next_header = ipv6.next_header();
+        let payload_len = ipv6.payload_len();
+        let mut destination = ipv6.push::<DestinationOptions<Ipv6>>().unwrap();
+
+        assert_eq!(8, destination.header_len());
+        assert_eq!(
+            ProtocolNumbers::Ipv6Opts,
+            destination.envelope().next_header()
+        );
+        assert_eq!(next_header, destination.next_header());
+        assert_eq!(payload_len, destination.payload_len());
+
+        let data = [0xaa; 10];
+        destination
+            .add_option(Ipv6Option::Unknown(Ipv6OptionType(0x1e), &data))
+            .unwrap();
+        assert_eq!(1, destination.hdr_ext_len());
+        assert_eq!(payload_len, destination.payload_len());
+
+        destination.reconcile_all();
+        assert_eq!(
+            (payload_len + 16) as u16,
+            destination.envelope().payload_length()
+        );
+    }
+
+    #[capsule::test]
+    fn remove_destination_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_EXT_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>().unwrap();
+        let destination = hop_by_hop
+            .parse::<DestinationOptions<HopByHopOptions<Ipv6>>>()
+            .unwrap();
+
+        let hop_by_hop = destination.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, hop_by_hop.next_header());
+        assert_eq!(18, hop_by_hop.payload_len());
+    }
+}
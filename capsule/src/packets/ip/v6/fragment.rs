@@ -290,6 +290,36 @@ impl<E: Ipv6Packet> IpPacket for Fragment<E> {
     fn truncate(&mut self, mtu: usize) -> Fallible<()> {
         self.envelope_mut().truncate(mtu)
     }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.envelope().dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.envelope_mut().set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.envelope().ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.envelope_mut().set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.envelope().ttl()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.envelope_mut().set_ttl(ttl);
+    }
 }
 
 impl<E: Ipv6Packet> Ipv6Packet for Fragment<E> {
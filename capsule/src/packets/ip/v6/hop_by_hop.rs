@@ -0,0 +1,521 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::options::{options_header_len, write_options, OptionsHeader, EMPTY_OPTIONS};
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::{Ipv6Option, Ipv6OptionsEncoder, Ipv6OptionsIterator, Ipv6Packet};
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// IPv6 Hop-by-Hop Options Extension packet based on [IETF RFC 8200].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Next Header  |  Hdr Ext Len  |                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+/// |                                                               |
+/// .                                                               .
+/// .                            Options                            .
+/// .                                                               .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*:      8-bit selector.  Identifies the type of header
+///                       immediately following the Hop-by-Hop Options
+///                       header. Uses the same values as the IPv4
+///                       Protocol field [IANA-PN].
+///
+/// - *Hdr Ext Len*:      8-bit unsigned integer.  Length of the Hop-by-Hop
+///                       Options header in 8-octet units, not including
+///                       the first 8 octets.
+///
+/// - *Options*:          Variable-length field, of length such that the
+///                       complete Hop-by-Hop Options header is an integer
+///                       multiple of 8 octets long. Use [`options_iter`]
+///                       to read the options, and [`set_options`] or
+///                       [`add_option`] to modify them.
+///
+/// The options carry information that must be examined by every node
+/// along the packet's delivery path. The header must immediately follow
+/// the IPv6 header.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.3
+/// [`options_iter`]: HopByHopOptions::options_iter
+/// [`set_options`]: HopByHopOptions::set_options
+/// [`add_option`]: HopByHopOptions::add_option
+pub struct HopByHopOptions<E: Ipv6Packet> {
+    envelope: E,
+    header: NonNull<OptionsHeader>,
+    offset: usize,
+}
+
+impl<E: Ipv6Packet> HopByHopOptions<E> {
+    #[inline]
+    fn header(&self) -> &OptionsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut OptionsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the length of the header in 8-octet units, not including
+    /// the first 8 octets.
+    #[inline]
+    pub fn hdr_ext_len(&self) -> u8 {
+        self.header().hdr_ext_len
+    }
+
+    /// Returns the raw options, including any padding.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        match self.mbuf().read_data_slice::<u8>(
+            self.offset + OptionsHeader::size_of(),
+            self.header_len() - OptionsHeader::size_of(),
+        ) {
+            Ok(options) => unsafe { options.as_ref() },
+            // the header length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the options in the header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>()?;
+    /// let mut iter = hop_by_hop.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> Ipv6OptionsIterator<'_> {
+        Ipv6OptionsIterator::new(self.options())
+    }
+
+    /// Replaces the encoded options and updates `hdr_ext_len`.
+    fn set_options_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let offset = self.offset;
+        let old_len = self.header_len();
+        let hdr_ext_len = write_options(self.mbuf_mut(), offset, old_len, bytes)?;
+        self.header_mut().hdr_ext_len = hdr_ext_len;
+        Ok(())
+    }
+
+    /// Replaces the options in the header.
+    ///
+    /// Padding is added to align the options and to make the header a
+    /// multiple of 8 octets long. The header is resized and `hdr_ext_len`
+    /// is updated. Call [`reconcile_all`] afterwards to fix the payload
+    /// length of the IPv6 packet.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn set_options(&mut self, options: &[Ipv6Option<'_>]) -> Fallible<()> {
+        let mut encoder = Ipv6OptionsEncoder::new();
+        for option in options {
+            encoder.push(option)?;
+        }
+        self.set_options_bytes(&encoder.finish()?)
+    }
+
+    /// Adds an option after the existing options.
+    ///
+    /// The existing padding is recomputed. The header is resized and
+    /// `hdr_ext_len` is updated. Call [`reconcile_all`] afterwards to fix
+    /// the payload length of the IPv6 packet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut hop_by_hop = ipv6.push::<HopByHopOptions<Ipv6>>()?;
+    /// hop_by_hop.add_option(Ipv6Option::RouterAlert(0))?;
+    /// hop_by_hop.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_option(&mut self, option: Ipv6Option<'_>) -> Fallible<()> {
+        let mut encoder = Ipv6OptionsEncoder::new();
+        let mut iter = self.options_iter();
+        while let Some(existing) = iter.next()? {
+            if !existing.is_padding() {
+                encoder.push(&existing)?;
+            }
+        }
+        encoder.push(&option)?;
+        self.set_options_bytes(&encoder.finish()?)
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for HopByHopOptions<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("hop-by-hop options")
+            .field("next_header", &format!("{}", self.next_header()))
+            .field("hdr_ext_len", &self.hdr_ext_len())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Packet for HopByHopOptions<E> {
+    /// The preceding type for an IPv6 hop-by-hop options packet should
+    /// be the IPv6 packet.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the hop-by-hop options header includes the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        (self.hdr_ext_len() as usize + 1) * 8
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        HopByHopOptions::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an IPv6 hop-by-hop options packet.
+    ///
+    /// [`next_header`] of the envelope must be set to
+    /// [`ProtocolNumbers::Ipv6HopByHop`]. Otherwise a parsing error is
+    /// returned.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    /// [`ProtocolNumbers::Ipv6HopByHop`]: ProtocolNumbers::Ipv6HopByHop
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_header() == ProtocolNumbers::Ipv6HopByHop,
+            ParseError::new("not an IPv6 hop-by-hop options packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+        // makes sure there's enough data for the options as well.
+        options_header_len(mbuf, offset)?;
+
+        Ok(HopByHopOptions {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IPv6 hop-by-hop options packet without options to the
+    /// beginning of the envelope's payload.
+    ///
+    /// [`next_header`] is set to the value of the `next_header` field of the
+    /// envelope, and the envelope is set to [`ProtocolNumbers::Ipv6HopByHop`].
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    /// [`ProtocolNumbers::Ipv6HopByHop`]: ProtocolNumbers::Ipv6HopByHop
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, OptionsHeader::size_of() + EMPTY_OPTIONS.len())?;
+        let header = mbuf.write_data(offset, &OptionsHeader::default())?;
+        mbuf.write_data_slice(offset + OptionsHeader::size_of(), &EMPTY_OPTIONS)?;
+
+        let mut packet = HopByHopOptions {
+            envelope,
+            header,
+            offset,
+        };
+
+        packet.set_next_header(packet.envelope().next_header());
+        packet
+            .envelope_mut()
+            .set_next_header(ProtocolNumbers::Ipv6HopByHop);
+
+        Ok(packet)
+    }
+
+    /// Removes IPv6 hop-by-hop options packet from the message buffer.
+    ///
+    /// The envelope's [`next_header`] field is set to the value of the
+    /// `next_header` field on the hop-by-hop options packet.
+    ///
+    /// [`next_header`]: Ipv6Packet::next_header
+    #[inline]
+    fn remove(mut self) -> Fallible<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_header();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_header(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: Ipv6Packet> IpPacket for HopByHopOptions<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        self.next_header()
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.set_next_header(proto);
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Fallible<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Fallible<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.envelope().dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.envelope_mut().set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.envelope().ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.envelope_mut().set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.envelope().ttl()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.envelope_mut().set_ttl(ttl);
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for HopByHopOptions<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.header_mut().next_header = next_header.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::{Ipv6, Ipv6OptionType};
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::{IPV6_EXT_OPTIONS_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    #[capsule::test]
+    fn parse_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_EXT_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Ipv6Opts, hop_by_hop.next_header());
+        assert_eq!(0, hop_by_hop.hdr_ext_len());
+        assert_eq!(8, hop_by_hop.header_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(Ipv6Option::RouterAlert(0), iter.next().unwrap().unwrap());
+        assert_eq!(Ipv6Option::PadN(0), iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_non_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.parse::<HopByHopOptions<Ipv6>>().is_err());
+    }
+
+    #[capsule::test]
+    fn insert_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let next_header = ipv6.next_header();
+        let payload_len = ipv6.payload_len();
+        let mut hop_by_hop = ipv6.push::<HopByHopOptions<Ipv6>>().unwrap();
+
+        assert_eq!(8, hop_by_hop.header_len());
+        assert_eq!(
+            ProtocolNumbers::Ipv6HopByHop,
+            hop_by_hop.envelope().next_header()
+        );
+        assert_eq!(next_header, hop_by_hop.next_header());
+        assert_eq!(payload_len, hop_by_hop.payload_len());
+        assert!(hop_by_hop
+            .options_iter()
+            .next()
+            .unwrap()
+            .unwrap()
+            .is_padding());
+
+        hop_by_hop.reconcile_all();
+        assert_eq!(
+            (payload_len + 8) as u16,
+            hop_by_hop.envelope().payload_length()
+        );
+    }
+
+    #[capsule::test]
+    fn add_and_set_options() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let payload_len = ipv6.payload_len();
+        let mut hop_by_hop = ipv6.push::<HopByHopOptions<Ipv6>>().unwrap();
+
+        hop_by_hop.add_option(Ipv6Option::RouterAlert(2)).unwrap();
+        hop_by_hop
+            .add_option(Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[1, 2, 3, 4]))
+            .unwrap();
+        assert_eq!(1, hop_by_hop.hdr_ext_len());
+        assert_eq!(payload_len, hop_by_hop.payload_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(Ipv6Option::RouterAlert(2), iter.next().unwrap().unwrap());
+        assert_eq!(
+            Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[1, 2, 3, 4]),
+            iter.next().unwrap().unwrap()
+        );
+
+        hop_by_hop
+            .set_options(&[Ipv6Option::JumboPayload(70000)])
+            .unwrap();
+        assert_eq!(0, hop_by_hop.hdr_ext_len());
+        assert_eq!(payload_len, hop_by_hop.payload_len());
+
+        let mut iter = hop_by_hop.options_iter();
+        assert_eq!(
+            Ipv6Option::JumboPayload(70000),
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn push_hop_by_hop_before_udp() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut hop_by_hop = ipv6.push::<HopByHopOptions<Ipv6>>().unwrap();
+        hop_by_hop.add_option(Ipv6Option::RouterAlert(0)).unwrap();
+        let udp = hop_by_hop.push::<Udp<HopByHopOptions<Ipv6>>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, udp.envelope().next_header());
+        assert_eq!(
+            ProtocolNumbers::Ipv6HopByHop,
+            udp.envelope().envelope().next_header()
+        );
+    }
+
+    #[capsule::test]
+    fn remove_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_EXT_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>().unwrap();
+
+        let next_header = hop_by_hop.next_header();
+        let payload_len = hop_by_hop.payload_len();
+        let ipv6 = hop_by_hop.remove().unwrap();
+
+        assert_eq!(next_header, ipv6.next_header());
+        assert_eq!(payload_len, ipv6.payload_len());
+    }
+}
@@ -18,17 +18,25 @@
 
 //! Internet Protocol v6 and extension headers.
 
+mod destination;
 mod fragment;
+mod hop_by_hop;
+mod options;
 mod srh;
 
+pub use self::destination::*;
 pub use self::fragment::*;
+pub use self::hop_by_hop::*;
+pub use self::options::*;
 pub use self::srh::*;
 
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::ip::{IpPacket, IpPacketError, ProtocolNumber, DEFAULT_IP_TTL};
+use crate::packets::ip::{
+    IpPacket, IpPacketError, ProtocolNumber, ProtocolNumbers, DEFAULT_IP_TTL,
+};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet, ParseError};
-use crate::{ensure, SizeOf};
+use crate::packets::{EtherTypeEnvelope, EtherTypes, Ethernet, Internal, Packet, ParseError};
+use crate::{ensure, Mbuf, SizeOf};
 use failure::Fallible;
 use std::fmt;
 use std::net::{IpAddr, Ipv6Addr};
@@ -95,13 +103,13 @@ const FLOW: u32be = u32be(u32::to_be(0xfffff));
 /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-3
 /// [IETF RFC 2474]: https://tools.ietf.org/html/rfc2474
 /// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168
-pub struct Ipv6 {
-    envelope: Ethernet,
+pub struct Ipv6<E: EtherTypeEnvelope = Ethernet> {
+    envelope: E,
     header: NonNull<Ipv6Header>,
     offset: usize,
 }
 
-impl Ipv6 {
+impl<E: EtherTypeEnvelope> Ipv6<E> {
     #[inline]
     fn header(&self) -> &Ipv6Header {
         unsafe { self.header.as_ref() }
@@ -209,7 +217,7 @@ impl Ipv6 {
     }
 }
 
-impl fmt::Debug for Ipv6 {
+impl<E: EtherTypeEnvelope> fmt::Debug for Ipv6<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ipv6")
             .field("src", &format!("{}", self.src()))
@@ -227,9 +235,13 @@ impl fmt::Debug for Ipv6 {
     }
 }
 
-impl Packet for Ipv6 {
-    /// The preceding type for IPv6 packet must be Ethernet.
-    type Envelope = Ethernet;
+impl<E: EtherTypeEnvelope> Packet for Ipv6<E> {
+    /// The preceding type for an IPv6 packet is usually Ethernet. It can also
+    /// be any other packet that identifies its payload with an EtherType,
+    /// for example [GRE].
+    ///
+    /// [GRE]: crate::packets::ip::Gre
+    type Envelope = E;
 
     #[inline]
     fn envelope(&self) -> &Self::Envelope {
@@ -260,12 +272,12 @@ impl Packet for Ipv6 {
         }
     }
 
-    /// Parses the Ethernet's payload as an IPv6 packet.
+    /// Parses the envelope's payload as an IPv6 packet.
     ///
     /// [`ether_type`] must be set to [`EtherTypes::Ipv6`]. Otherwise a
     /// parsing error is returned.
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`ether_type`]: EtherTypeEnvelope::ether_type
     /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
@@ -285,11 +297,11 @@ impl Packet for Ipv6 {
         })
     }
 
-    /// Prepends an IPv6 packet to the beginning of the Ethernet's payload.
+    /// Prepends an IPv6 packet to the beginning of the envelope's payload.
     ///
     /// [`ether_type`] is set to [`EtherTypes::Ipv6`].
     ///
-    /// [`ether_type`]: Ethernet::ether_type
+    /// [`ether_type`]: EtherTypeEnvelope::ether_type
     /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
     #[inline]
     fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
@@ -327,7 +339,7 @@ impl Packet for Ipv6 {
     }
 }
 
-impl IpPacket for Ipv6 {
+impl<E: EtherTypeEnvelope> IpPacket for Ipv6<E> {
     #[inline]
     fn next_protocol(&self) -> ProtocolNumber {
         self.next_header()
@@ -387,13 +399,43 @@ impl IpPacket for Ipv6 {
             IpPacketError::MtuTooSmall(mtu, IPV6_MIN_MTU)
         );
 
-        // accounts for the Ethernet frame length.
+        // accounts for the length of the envelopes.
         let to_len = mtu + self.offset();
         self.mbuf_mut().truncate(to_len)
     }
+
+    #[inline]
+    fn dscp(&self) -> u8 {
+        self.dscp()
+    }
+
+    #[inline]
+    fn set_dscp(&mut self, dscp: u8) {
+        self.set_dscp(dscp);
+    }
+
+    #[inline]
+    fn ecn(&self) -> u8 {
+        self.ecn()
+    }
+
+    #[inline]
+    fn set_ecn(&mut self, ecn: u8) {
+        self.set_ecn(ecn);
+    }
+
+    #[inline]
+    fn ttl(&self) -> u8 {
+        self.hop_limit()
+    }
+
+    #[inline]
+    fn set_ttl(&mut self, ttl: u8) {
+        self.set_hop_limit(ttl);
+    }
 }
 
-impl Ipv6Packet for Ipv6 {
+impl<E: EtherTypeEnvelope> Ipv6Packet for Ipv6<E> {
     #[inline]
     fn next_header(&self) -> ProtocolNumber {
         ProtocolNumber::new(self.header().next_header)
@@ -412,6 +454,74 @@ pub trait Ipv6Packet: IpPacket {
 
     /// Sets the next header type.
     fn set_next_header(&mut self, next_header: ProtocolNumber);
+
+    /// Returns the upper-layer protocol of the packet.
+    ///
+    /// Walks the chain of extension headers following this packet, without
+    /// parsing them, until it reaches a header that is not an IPv6
+    /// extension header. The upper-layer protocol can be a transport
+    /// protocol like TCP, or [`ProtocolNumbers::Ipv6NoNext`] when there
+    /// is nothing following the last extension header.
+    ///
+    /// Returns an error if an extension header runs past the end of the
+    /// buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let ipv6 = ethernet.parse::<Ipv6>()?;
+    /// if ipv6.upper_layer_protocol()? == ProtocolNumbers::Udp {
+    ///     ...
+    /// }
+    /// ```
+    ///
+    /// [`ProtocolNumbers::Ipv6NoNext`]: ProtocolNumbers::Ipv6NoNext
+    fn upper_layer_protocol(&self) -> Fallible<ProtocolNumber> {
+        walk_extension_headers(self.mbuf(), self.next_header(), self.payload_offset())
+            .map(|(protocol, _)| protocol)
+    }
+
+    /// Returns the buffer offset where the upper-layer header starts,
+    /// after all the extension headers following this packet.
+    ///
+    /// See [`upper_layer_protocol`] for how the extension headers are
+    /// walked.
+    ///
+    /// [`upper_layer_protocol`]: Ipv6Packet::upper_layer_protocol
+    fn upper_layer_offset(&self) -> Fallible<usize> {
+        walk_extension_headers(self.mbuf(), self.next_header(), self.payload_offset())
+            .map(|(_, offset)| offset)
+    }
+}
+
+/// Walks the extension headers starting at `offset` and returns the
+/// upper-layer protocol and the offset of its header.
+fn walk_extension_headers(
+    mbuf: &Mbuf,
+    mut next_header: ProtocolNumber,
+    mut offset: usize,
+) -> Fallible<(ProtocolNumber, usize)> {
+    loop {
+        let len = match next_header {
+            ProtocolNumbers::Ipv6HopByHop
+            | ProtocolNumbers::Ipv6Route
+            | ProtocolNumbers::Ipv6Opts => options_header_len(mbuf, offset)?,
+            // the fragment header has a fixed length.
+            ProtocolNumbers::Ipv6Frag => 8,
+            // the authentication header length is in 4-octet units, not
+            // counting the first 8 octets. the encrypted payload of an ESP
+            // header can't be walked, so it's the upper layer.
+            ProtocolNumbers::Ah => {
+                let payload_len = mbuf.read_data::<u8>(offset + 1)?;
+                (usize::from(unsafe { *payload_len.as_ref() }) + 2) * 4
+            }
+            _ => return Ok((next_header, offset)),
+        };
+
+        let header = mbuf.read_data::<u8>(offset)?;
+        next_header = ProtocolNumber::new(unsafe { *header.as_ref() });
+        offset += len;
+    }
 }
 
 /// IPv6 header.
@@ -443,7 +553,9 @@ impl Default for Ipv6Header {
 mod tests {
     use super::*;
     use crate::packets::ip::ProtocolNumbers;
-    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::testils::byte_arrays::{
+        IPV4_UDP_PACKET, IPV6_EXT_OPTIONS_PACKET, IPV6_FRAGMENT_PACKET, IPV6_TCP_PACKET,
+    };
     use crate::Mbuf;
 
     #[test]
@@ -523,4 +635,44 @@ mod tests {
         let _ = ipv6.truncate(1500);
         assert_eq!(1500, ipv6.len());
     }
+
+    #[capsule::test]
+    fn walk_extension_headers_to_upper_layer() {
+        let packet = Mbuf::from_bytes(&IPV6_EXT_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, ipv6.upper_layer_protocol().unwrap());
+        // ethernet + ipv6 + hop-by-hop + destination options
+        assert_eq!(14 + 40 + 8 + 8, ipv6.upper_layer_offset().unwrap());
+
+        let hop_by_hop = ipv6.parse::<HopByHopOptions<Ipv6>>().unwrap();
+        assert_eq!(
+            ProtocolNumbers::Udp,
+            hop_by_hop.upper_layer_protocol().unwrap()
+        );
+
+        let packet = Mbuf::from_bytes(&IPV6_FRAGMENT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv6.upper_layer_protocol().unwrap());
+
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        assert_eq!(ProtocolNumbers::Tcp, ipv6.upper_layer_protocol().unwrap());
+        assert_eq!(ipv6.payload_offset(), ipv6.upper_layer_offset().unwrap());
+    }
+
+    #[capsule::test]
+    fn walk_truncated_extension_headers() {
+        // the hop-by-hop header claims more octets than the buffer has.
+        let mut bytes = IPV6_EXT_OPTIONS_PACKET;
+        bytes[55] = 10;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.upper_layer_protocol().is_err());
+    }
 }
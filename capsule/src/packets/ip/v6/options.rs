@@ -0,0 +1,453 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::dpdk::BufferError;
+use crate::packets::ParseError;
+use crate::{ensure, Mbuf, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+
+/// The maximum length of the options in a hop-by-hop or destination
+/// options header in octets.
+///
+/// The header length is 8-bit in units of 8 octets, not including the
+/// first 8 octets, of which 2 are the next header and length fields.
+pub const IPV6_MAX_OPTIONS_LEN: usize = 256 * 8 - 2;
+
+/// [IANA] assigned IPv6 option type.
+///
+/// The two highest-order bits specify the action taken by a node that
+/// does not recognize the option. The third-highest-order bit specifies
+/// whether the option data can change en route.
+///
+/// A list of supported types is under [`Ipv6OptionTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/ipv6-parameters/ipv6-parameters.xhtml#ipv6-parameters-2
+/// [`Ipv6OptionTypes`]: Ipv6OptionTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv6OptionType(pub u8);
+
+impl Ipv6OptionType {
+    /// Returns the action to take when the option is not recognized.
+    ///
+    /// - `0`: skip over this option and continue processing the header.
+    /// - `1`: discard the packet.
+    /// - `2`: discard the packet and send an ICMP Parameter Problem.
+    /// - `3`: same as `2`, but only if the destination is not multicast.
+    #[inline]
+    pub fn action(self) -> u8 {
+        self.0 >> 6
+    }
+
+    /// Returns a flag indicating whether the option data may change en
+    /// route to the packet's final destination.
+    #[inline]
+    pub fn may_change(self) -> bool {
+        self.0 & 0b0010_0000 != 0
+    }
+}
+
+/// Supported IPv6 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv6OptionTypes {
+    use super::Ipv6OptionType;
+
+    /// Pad1.
+    pub const Pad1: Ipv6OptionType = Ipv6OptionType(0x00);
+
+    /// PadN.
+    pub const PadN: Ipv6OptionType = Ipv6OptionType(0x01);
+
+    /// Router Alert.
+    pub const RouterAlert: Ipv6OptionType = Ipv6OptionType(0x05);
+
+    /// Jumbo Payload.
+    pub const JumboPayload: Ipv6OptionType = Ipv6OptionType(0xC2);
+}
+
+impl fmt::Display for Ipv6OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv6OptionTypes::Pad1 => "Pad1".to_string(),
+                Ipv6OptionTypes::PadN => "PadN".to_string(),
+                Ipv6OptionTypes::RouterAlert => "Router Alert".to_string(),
+                Ipv6OptionTypes::JumboPayload => "Jumbo Payload".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// An option in the IPv6 hop-by-hop or destination options header based
+/// on [IETF RFC 8200].
+///
+/// Except for the single octet Pad1 option, all options are encoded as a
+/// type octet, a length octet that excludes the type and length octets,
+/// and the option data.
+///
+/// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ipv6Option<'a> {
+    /// Pad1 option. Inserts one octet of padding.
+    Pad1,
+
+    /// PadN option with the number of zero octets of option data. Inserts
+    /// two or more octets of padding.
+    PadN(u8),
+
+    /// Router alert option with its 16-bit value, defined in
+    /// [IETF RFC 2711].
+    ///
+    /// [IETF RFC 2711]: https://tools.ietf.org/html/rfc2711
+    RouterAlert(u16),
+
+    /// Jumbo payload option with the length of the packet excluding the
+    /// IPv6 header, defined in [IETF RFC 2675].
+    ///
+    /// [IETF RFC 2675]: https://tools.ietf.org/html/rfc2675
+    JumboPayload(u32),
+
+    /// Any other option, with its type and data.
+    Unknown(Ipv6OptionType, &'a [u8]),
+}
+
+impl Ipv6Option<'_> {
+    /// Returns the option type.
+    pub fn option_type(&self) -> Ipv6OptionType {
+        match self {
+            Ipv6Option::Pad1 => Ipv6OptionTypes::Pad1,
+            Ipv6Option::PadN(_) => Ipv6OptionTypes::PadN,
+            Ipv6Option::RouterAlert(_) => Ipv6OptionTypes::RouterAlert,
+            Ipv6Option::JumboPayload(_) => Ipv6OptionTypes::JumboPayload,
+            Ipv6Option::Unknown(option_type, _) => *option_type,
+        }
+    }
+
+    /// Returns the length of the encoded option in octets.
+    pub fn length(&self) -> usize {
+        match self {
+            Ipv6Option::Pad1 => 1,
+            Ipv6Option::PadN(len) => 2 + *len as usize,
+            Ipv6Option::RouterAlert(_) => 4,
+            Ipv6Option::JumboPayload(_) => 6,
+            Ipv6Option::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Returns a flag indicating whether the option is padding.
+    pub fn is_padding(&self) -> bool {
+        matches!(self, Ipv6Option::Pad1 | Ipv6Option::PadN(_))
+    }
+
+    /// Returns the alignment requirement of the option as `(x, y)`,
+    /// meaning the option type must start at an offset of `x * n + y`
+    /// octets from the start of the header.
+    fn alignment(&self) -> (usize, usize) {
+        match self {
+            Ipv6Option::RouterAlert(_) => (2, 0),
+            Ipv6Option::JumboPayload(_) => (4, 2),
+            _ => (1, 0),
+        }
+    }
+
+    /// Encodes the option and appends it to the buffer.
+    fn encode(&self, buffer: &mut Vec<u8>) -> Fallible<()> {
+        let len = self.length();
+        ensure!(len <= 257, Ipv6OptionsError::TooLong(len));
+
+        buffer.push(self.option_type().0);
+        match self {
+            Ipv6Option::Pad1 => (),
+            Ipv6Option::PadN(data_len) => {
+                buffer.push(*data_len);
+                buffer.resize(buffer.len() + *data_len as usize, 0);
+            }
+            Ipv6Option::RouterAlert(value) => {
+                buffer.push(2);
+                buffer.extend_from_slice(&value.to_be_bytes());
+            }
+            Ipv6Option::JumboPayload(value) => {
+                buffer.push(4);
+                buffer.extend_from_slice(&value.to_be_bytes());
+            }
+            Ipv6Option::Unknown(_, data) => {
+                buffer.push(data.len() as u8);
+                buffer.extend_from_slice(data);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An iterator that iterates through the options in a hop-by-hop or
+/// destination options header.
+#[derive(Debug)]
+pub struct Ipv6OptionsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Ipv6OptionsIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Ipv6OptionsIterator { data }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<Ipv6Option<'a>>> {
+        let data = self.data;
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let option_type = Ipv6OptionType(data[0]);
+        if option_type == Ipv6OptionTypes::Pad1 {
+            self.data = &data[1..];
+            return Ok(Some(Ipv6Option::Pad1));
+        }
+
+        ensure!(
+            data.len() >= 2 && data[1] as usize + 2 <= data.len(),
+            ParseError::new("Invalid IPv6 option length.")
+        );
+
+        let len = data[1] as usize + 2;
+        let value = &data[2..len];
+        self.data = &data[len..];
+
+        let option = match option_type {
+            Ipv6OptionTypes::PadN => Ipv6Option::PadN(value.len() as u8),
+            Ipv6OptionTypes::RouterAlert => {
+                ensure!(
+                    value.len() == 2,
+                    ParseError::new("Invalid router alert length.")
+                );
+                Ipv6Option::RouterAlert(u16::from_be_bytes([value[0], value[1]]))
+            }
+            Ipv6OptionTypes::JumboPayload => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid jumbo payload length.")
+                );
+                Ipv6Option::JumboPayload(u32::from_be_bytes([
+                    value[0], value[1], value[2], value[3],
+                ]))
+            }
+            _ => Ipv6Option::Unknown(option_type, value),
+        };
+
+        Ok(Some(option))
+    }
+}
+
+/// Encodes options into the wire format of a hop-by-hop or destination
+/// options header.
+///
+/// Padding is inserted in front of the options with an alignment
+/// requirement, and after the last option so the header is a multiple
+/// of 8 octets long.
+#[derive(Debug)]
+pub(crate) struct Ipv6OptionsEncoder {
+    buffer: Vec<u8>,
+}
+
+impl Ipv6OptionsEncoder {
+    pub(crate) fn new() -> Self {
+        Ipv6OptionsEncoder { buffer: Vec::new() }
+    }
+
+    /// Appends padding of `len` octets.
+    fn pad(&mut self, len: usize) -> Fallible<()> {
+        match len {
+            0 => Ok(()),
+            1 => Ipv6Option::Pad1.encode(&mut self.buffer),
+            _ => Ipv6Option::PadN((len - 2) as u8).encode(&mut self.buffer),
+        }
+    }
+
+    /// Appends the option, preceded by the padding needed to align it.
+    pub(crate) fn push(&mut self, option: &Ipv6Option<'_>) -> Fallible<()> {
+        let (x, y) = option.alignment();
+        // the options start after the next header and length fields.
+        let offset = OptionsHeader::size_of() + self.buffer.len();
+        self.pad((x + y - offset % x) % x)?;
+        option.encode(&mut self.buffer)?;
+
+        ensure!(
+            self.buffer.len() <= IPV6_MAX_OPTIONS_LEN,
+            Ipv6OptionsError::TooLong(self.buffer.len())
+        );
+        Ok(())
+    }
+
+    /// Pads the options to a multiple of 8 octets and returns the bytes.
+    pub(crate) fn finish(mut self) -> Fallible<Vec<u8>> {
+        let len = OptionsHeader::size_of() + self.buffer.len();
+        self.pad((8 - len % 8) % 8)?;
+        Ok(self.buffer)
+    }
+}
+
+/// The fixed portion of the hop-by-hop and destination options headers.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+pub(crate) struct OptionsHeader {
+    pub(crate) next_header: u8,
+    pub(crate) hdr_ext_len: u8,
+}
+
+/// The padding of an options header without options.
+pub(crate) const EMPTY_OPTIONS: [u8; 6] = [0x01, 0x04, 0x00, 0x00, 0x00, 0x00];
+
+/// Returns the length of the options header at the offset, after making
+/// sure the buffer holds all of it.
+pub(crate) fn options_header_len(mbuf: &Mbuf, offset: usize) -> Fallible<usize> {
+    let header = mbuf.read_data::<OptionsHeader>(offset)?;
+    let len = (unsafe { header.as_ref() }.hdr_ext_len as usize + 1) * 8;
+    ensure!(
+        offset + len <= mbuf.data_len(),
+        BufferError::OutOfBuffer(len, mbuf.data_len() - offset)
+    );
+    Ok(len)
+}
+
+/// Replaces the options of the header at the offset with the encoded
+/// bytes, resizing the buffer as needed. `bytes` must be padded so the
+/// header is a multiple of 8 octets long. Returns the new header length.
+pub(crate) fn write_options(
+    mbuf: &mut Mbuf,
+    offset: usize,
+    old_len: usize,
+    bytes: &[u8],
+) -> Fallible<u8> {
+    let new_len = OptionsHeader::size_of() + bytes.len();
+    let options_offset = offset + OptionsHeader::size_of();
+
+    if new_len > old_len {
+        mbuf.extend(offset + old_len, new_len - old_len)?;
+    } else if new_len < old_len {
+        mbuf.shrink(offset + new_len, old_len - new_len)?;
+    }
+
+    mbuf.write_data_slice(options_offset, bytes)?;
+    Ok((new_len / 8 - 1) as u8)
+}
+
+/// IPv6 options related errors.
+#[derive(Debug, Fail)]
+pub enum Ipv6OptionsError {
+    /// Error indicating the options exceed the maximum length.
+    #[fail(display = "Options length {} exceeds the maximum of 2046.", _0)]
+    TooLong(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_type_to_string() {
+        assert_eq!("Router Alert", Ipv6OptionTypes::RouterAlert.to_string());
+        assert_eq!("0x1e", Ipv6OptionType(0x1e).to_string());
+    }
+
+    #[test]
+    fn option_type_action() {
+        assert_eq!(0, Ipv6OptionTypes::RouterAlert.action());
+        assert_eq!(3, Ipv6OptionTypes::JumboPayload.action());
+        assert!(!Ipv6OptionTypes::JumboPayload.may_change());
+        assert!(Ipv6OptionType(0x3e).may_change());
+    }
+
+    #[test]
+    fn iterate_ipv6_options() {
+        let data = [
+            0x05, 0x02, 0x00, 0x00, 0x00, 0xc2, 0x04, 0x00, 0x01, 0x00, 0x00, 0x1e, 0x01, 0xff,
+            0x01, 0x00,
+        ];
+        let mut iter = Ipv6OptionsIterator::new(&data);
+
+        assert_eq!(Ipv6Option::RouterAlert(0), iter.next().unwrap().unwrap());
+        assert_eq!(Ipv6Option::Pad1, iter.next().unwrap().unwrap());
+        assert_eq!(
+            Ipv6Option::JumboPayload(0x10000),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[0xff]),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(Ipv6Option::PadN(0), iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_ipv6_option_length() {
+        let data = [0x1e, 0x04, 0x00, 0x00];
+        let mut iter = Ipv6OptionsIterator::new(&data);
+        assert!(iter.next().is_err());
+
+        let data = [0x05, 0x01, 0x00, 0x00];
+        let mut iter = Ipv6OptionsIterator::new(&data);
+        assert!(iter.next().is_err());
+    }
+
+    #[test]
+    fn encode_and_align_ipv6_options() {
+        let mut encoder = Ipv6OptionsEncoder::new();
+        encoder
+            .push(&Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[0xff]))
+            .unwrap();
+        encoder.push(&Ipv6Option::RouterAlert(2)).unwrap();
+        encoder.push(&Ipv6Option::JumboPayload(70000)).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        // header is a multiple of 8 octets.
+        assert_eq!(14, bytes.len());
+
+        let mut iter = Ipv6OptionsIterator::new(&bytes);
+        assert_eq!(
+            Ipv6Option::Unknown(Ipv6OptionType(0x1e), &[0xff]),
+            iter.next().unwrap().unwrap()
+        );
+        // the router alert option is aligned at 2n.
+        assert_eq!(Ipv6Option::Pad1, iter.next().unwrap().unwrap());
+        assert_eq!(Ipv6Option::RouterAlert(2), iter.next().unwrap().unwrap());
+        // the jumbo payload option is aligned at 4n + 2.
+        assert_eq!(0xc2, bytes[8]);
+        assert_eq!(
+            Ipv6Option::JumboPayload(70000),
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn encode_empty_ipv6_options() {
+        let bytes = Ipv6OptionsEncoder::new().finish().unwrap();
+        assert_eq!(&EMPTY_OPTIONS[..], &bytes[..]);
+    }
+}
@@ -0,0 +1,452 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Link Layer Discovery Protocol and TLVs.
+
+mod tlvs;
+
+pub use self::tlvs::*;
+
+use crate::dpdk::Port;
+use crate::net::MacAddr;
+use crate::packets::{Datalink, EtherTypes, Ethernet, Internal, Packet, ParseError};
+use crate::{ensure, Mbuf};
+use failure::Fallible;
+use std::fmt;
+
+/// The nearest bridge group address, LLDP frames sent to it are never
+/// forwarded by bridges.
+const LLDP_NEAREST_BRIDGE: [u8; 6] = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e];
+
+/// The recommended time to live of the advertisements in seconds. It is
+/// four times the recommended transmit interval of 30 seconds.
+pub const LLDP_DEFAULT_TTL: u16 = 120;
+
+/// Link Layer Discovery Protocol data unit based on IEEE 802.1AB.
+///
+/// ```
+/// +-----------+---------+---------+-----------+----------+----------+
+/// | Chassis ID| Port ID |   TTL   | Optional  |   ...    |   End    |
+/// |    TLV    |   TLV   |   TLV   |    TLV    |          |   TLV    |
+/// +-----------+---------+---------+-----------+----------+----------+
+/// ```
+///
+/// An LLDPDU is a sequence of TLVs, starting with the mandatory chassis
+/// ID, port ID and time to live TLVs, in that order, and ending with the
+/// end TLV. Use [`tlvs_iter`] to read the TLVs. Any octets after the end
+/// TLV are the padding of a short frame.
+///
+/// # Example
+///
+/// ```
+/// if ethernet.ether_type() == EtherTypes::Lldp {
+///     let lldp = ethernet.parse::<Lldp>()?;
+///     let mut iter = lldp.tlvs_iter();
+///
+///     while let Some(tlv) = iter.next()? {
+///         println!("{:?}", tlv);
+///     }
+/// }
+/// ```
+///
+/// [`tlvs_iter`]: Lldp::tlvs_iter
+pub struct Lldp<E: Datalink = Ethernet> {
+    envelope: E,
+    offset: usize,
+}
+
+impl<E: Datalink> Lldp<E> {
+    /// Returns the TLVs as a `u8` slice.
+    #[inline]
+    pub fn tlvs(&self) -> &[u8] {
+        let len = self.len();
+        if len == 0 {
+            return &[];
+        }
+
+        match self.mbuf().read_data_slice::<u8>(self.offset, len) {
+            Ok(tlvs) => unsafe { tlvs.as_ref() },
+            // we are reading till the end of buffer, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the TLVs.
+    #[inline]
+    pub fn tlvs_iter(&self) -> LldpTlvsIterator<'_> {
+        LldpTlvsIterator::new(self.tlvs())
+    }
+
+    /// Returns the chassis ID and its subtype.
+    pub fn chassis_id(&self) -> Option<(ChassisIdSubtype, &[u8])> {
+        match self.tlvs_iter().next() {
+            Ok(Some(LldpTlv::ChassisId(subtype, id))) => Some((subtype, id)),
+            _ => None,
+        }
+    }
+
+    /// Returns the port ID and its subtype.
+    pub fn port_id(&self) -> Option<(PortIdSubtype, &[u8])> {
+        let mut iter = self.tlvs_iter();
+        let _ = iter.next();
+        match iter.next() {
+            Ok(Some(LldpTlv::PortId(subtype, id))) => Some((subtype, id)),
+            _ => None,
+        }
+    }
+
+    /// Returns the time to live in seconds.
+    pub fn ttl(&self) -> Option<u16> {
+        let mut iter = self.tlvs_iter();
+        let _ = iter.next();
+        let _ = iter.next();
+        match iter.next() {
+            Ok(Some(LldpTlv::Ttl(ttl))) => Some(ttl),
+            _ => None,
+        }
+    }
+
+    /// Replaces the TLVs with the encoded bytes.
+    fn set_tlvs_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let new_len = bytes.len();
+        let old_len = self.len();
+        let offset = self.offset;
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        mbuf.write_data_slice(offset, bytes)?;
+        Ok(())
+    }
+
+    /// Replaces the TLVs.
+    ///
+    /// The end TLV is appended, and any padding of the frame is removed.
+    /// The TLVs are written in the given order; it's the caller's
+    /// responsibility to start with the chassis ID, port ID and time to
+    /// live TLVs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut lldp = ethernet.push::<Lldp>()?;
+    /// lldp.set_tlvs(&[
+    ///     LldpTlv::ChassisId(ChassisIdSubtypes::MacAddress, &mac.octets()),
+    ///     LldpTlv::PortId(PortIdSubtypes::InterfaceName, b"eth0"),
+    ///     LldpTlv::Ttl(LLDP_DEFAULT_TTL),
+    /// ])?;
+    /// ```
+    pub fn set_tlvs(&mut self, tlvs: &[LldpTlv<'_>]) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(tlvs.iter().map(LldpTlv::length).sum::<usize>() + 2);
+        for tlv in tlvs {
+            if *tlv != LldpTlv::End {
+                tlv.encode(&mut bytes)?;
+            }
+        }
+        LldpTlv::End.encode(&mut bytes)?;
+        self.set_tlvs_bytes(&bytes)
+    }
+
+    /// Adds a TLV after the existing TLVs.
+    ///
+    /// The end TLV is moved after the new TLV, and any padding of the
+    /// frame is removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut lldp = Lldp::advertisement(mac, "eth0", LLDP_DEFAULT_TTL)?;
+    /// lldp.add_tlv(LldpTlv::SystemName("switch"))?;
+    /// ```
+    pub fn add_tlv(&mut self, tlv: LldpTlv<'_>) -> Fallible<()> {
+        let mut iter = self.tlvs_iter();
+        let mut len = 0;
+        while let Some(existing) = iter.next()? {
+            if existing == LldpTlv::End {
+                break;
+            }
+            len = iter.offset();
+        }
+
+        let mut bytes = self.tlvs()[..len].to_vec();
+        tlv.encode(&mut bytes)?;
+        LldpTlv::End.encode(&mut bytes)?;
+        self.set_tlvs_bytes(&bytes)
+    }
+}
+
+impl Lldp {
+    /// Creates an LLDP advertisement in a new `Mbuf`.
+    ///
+    /// The frame is sent from `mac_addr` to the nearest bridge group
+    /// address. The chassis is identified by `mac_addr` and the port by
+    /// `port_name`. A `ttl` of zero tells the neighbors to remove the
+    /// information, for example when the port shuts down.
+    ///
+    /// The advertisements should be retransmitted every 30 seconds to keep
+    /// the neighbors' information from expiring.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// fn install(qs: HashMap<String, PortQueue>) -> impl Pipeline {
+    ///     let (name, q) = qs.into_iter().next().unwrap();
+    ///     batch::poll_fn(move || {
+    ///         Lldp::advertisement(q.mac_addr(), &name, LLDP_DEFAULT_TTL)
+    ///             .map(|lldp| vec![lldp.reset()])
+    ///             .unwrap_or_default()
+    ///     })
+    ///     .send(q.clone())
+    /// }
+    ///
+    /// Runtime::build(config)?
+    ///     .add_periodic_pipeline_to_core(0, install, Duration::from_secs(30))?
+    ///     .execute()
+    /// ```
+    pub fn advertisement(mac_addr: MacAddr, port_name: &str, ttl: u16) -> Fallible<Self> {
+        let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+        ethernet.set_src(mac_addr);
+        ethernet.set_dst(LLDP_NEAREST_BRIDGE.into());
+
+        let mut lldp = ethernet.push::<Lldp>()?;
+        lldp.set_tlvs(&[
+            LldpTlv::ChassisId(ChassisIdSubtypes::MacAddress, &mac_addr.octets()),
+            LldpTlv::PortId(PortIdSubtypes::InterfaceName, port_name.as_bytes()),
+            LldpTlv::Ttl(ttl),
+        ])?;
+        Ok(lldp)
+    }
+
+    /// Creates an LLDP advertisement for the port in a new `Mbuf`.
+    ///
+    /// Uses the port's MAC address and name. See [`advertisement`] for
+    /// details.
+    ///
+    /// [`advertisement`]: Lldp::advertisement
+    pub fn port_advertisement(port: &Port, ttl: u16) -> Fallible<Self> {
+        Lldp::advertisement(port.mac_addr(), port.name(), ttl)
+    }
+}
+
+impl<E: Datalink> fmt::Debug for Lldp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lldp")
+            .field("chassis_id", &self.chassis_id())
+            .field("port_id", &self.port_id())
+            .field("ttl", &self.ttl())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Datalink> Packet for Lldp<E> {
+    /// The preceding type for an LLDP packet must be a datalink layer
+    /// packet, such as Ethernet.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the LLDP header includes all the TLVs and the padding
+    /// that follows.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Lldp {
+            envelope: self.envelope.clone(internal),
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an LLDP packet.
+    ///
+    /// [`ether_type`] must be set to [`EtherTypes::Lldp`], and the LLDPDU
+    /// must start with the chassis ID, port ID and time to live TLVs.
+    /// Otherwise a parsing error is returned.
+    ///
+    /// [`ether_type`]: crate::packets::EtherTypeEnvelope::ether_type
+    /// [`EtherTypes::Lldp`]: crate::packets::EtherTypes::Lldp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Lldp,
+            ParseError::new("not an LLDP packet.")
+        );
+
+        let offset = envelope.payload_offset();
+        let packet = Lldp { envelope, offset };
+
+        let mut iter = packet.tlvs_iter();
+        let mandatory = [
+            LldpTlvTypes::ChassisId,
+            LldpTlvTypes::PortId,
+            LldpTlvTypes::Ttl,
+        ];
+        for tlv_type in mandatory.iter() {
+            match iter.next()? {
+                Some(tlv) if tlv.tlv_type() == *tlv_type => (),
+                _ => {
+                    return Err(ParseError::new(&format!(
+                        "LLDP packet is missing the {} TLV.",
+                        tlv_type
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        Ok(packet)
+    }
+
+    /// Prepends an LLDP packet to the beginning of the envelope's payload.
+    ///
+    /// [`ether_type`] is set to [`EtherTypes::Lldp`]. The LLDPDU only has
+    /// the end TLV. Use [`set_tlvs`] to add the mandatory TLVs.
+    ///
+    /// [`ether_type`]: crate::packets::EtherTypeEnvelope::ether_type
+    /// [`EtherTypes::Lldp`]: crate::packets::EtherTypes::Lldp
+    /// [`set_tlvs`]: Lldp::set_tlvs
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        let mut bytes = Vec::with_capacity(2);
+        LldpTlv::End.encode(&mut bytes)?;
+        mbuf.extend(offset, bytes.len())?;
+        mbuf.write_data_slice(offset, &bytes)?;
+
+        envelope.set_ether_type(EtherTypes::Lldp);
+
+        Ok(Lldp { envelope, offset })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, LLDP_PACKET};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[capsule::test]
+    fn parse_lldp_packet() {
+        let packet = Mbuf::from_bytes(&LLDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let lldp = ethernet.parse::<Lldp>().unwrap();
+
+        assert_eq!(
+            Some((ChassisIdSubtypes::MacAddress, &[0, 0, 0, 0, 0, 1][..])),
+            lldp.chassis_id()
+        );
+        assert_eq!(
+            Some((PortIdSubtypes::InterfaceName, &b"eth0"[..])),
+            lldp.port_id()
+        );
+        assert_eq!(Some(120), lldp.ttl());
+
+        let mut iter = lldp.tlvs_iter();
+        let _ = iter.next().unwrap();
+        let _ = iter.next().unwrap();
+        let _ = iter.next().unwrap();
+        assert_eq!(Some(LldpTlv::SystemName("switch")), iter.next().unwrap());
+        assert_eq!(
+            Some(LldpTlv::ManagementAddress(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                1
+            )),
+            iter.next().unwrap()
+        );
+        assert_eq!(Some(LldpTlv::End), iter.next().unwrap());
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[capsule::test]
+    fn parse_non_lldp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Lldp>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_lldp_advertisement() {
+        let mac = MacAddr::new(0, 0, 0, 0, 0, 1);
+        let mut lldp = Lldp::advertisement(mac, "eth0", LLDP_DEFAULT_TTL).unwrap();
+
+        assert_eq!(EtherTypes::Lldp, lldp.envelope().ether_type());
+        assert_eq!(mac, lldp.envelope().src());
+        assert_eq!(
+            MacAddr::new(0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e),
+            lldp.envelope().dst()
+        );
+
+        lldp.add_tlv(LldpTlv::SystemName("switch")).unwrap();
+        lldp.add_tlv(LldpTlv::ManagementAddress(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            1,
+        ))
+        .unwrap();
+
+        // the same TLVs as the fixture, without the padding.
+        assert_eq!(&LLDP_PACKET[14..58], lldp.tlvs());
+
+        let packet = lldp.reset();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<Lldp>().is_ok());
+    }
+
+    #[capsule::test]
+    fn parse_lldp_missing_mandatory_tlvs() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let lldp = ethernet.push::<Lldp>().unwrap();
+        let packet = lldp.reset();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Lldp>().is_err());
+    }
+}
@@ -0,0 +1,465 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The maximum length of the TLV information string in octets.
+const LLDP_MAX_TLV_LEN: usize = 511;
+
+/// The IANA address family numbers of the management addresses.
+const ADDRESS_FAMILY_IPV4: u8 = 1;
+const ADDRESS_FAMILY_IPV6: u8 = 2;
+
+/// The interface numbering subtype for the interface index.
+const INTERFACE_NUMBERING_IFINDEX: u8 = 2;
+
+/// LLDP TLV type.
+///
+/// A list of supported types is under [`LldpTlvTypes`].
+///
+/// [`LldpTlvTypes`]: LldpTlvTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct LldpTlvType(pub u8);
+
+/// Supported LLDP TLV types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod LldpTlvTypes {
+    use super::LldpTlvType;
+
+    /// End Of LLDPDU.
+    pub const End: LldpTlvType = LldpTlvType(0);
+
+    /// Chassis ID.
+    pub const ChassisId: LldpTlvType = LldpTlvType(1);
+
+    /// Port ID.
+    pub const PortId: LldpTlvType = LldpTlvType(2);
+
+    /// Time To Live.
+    pub const Ttl: LldpTlvType = LldpTlvType(3);
+
+    /// System Name.
+    pub const SystemName: LldpTlvType = LldpTlvType(5);
+
+    /// Management Address.
+    pub const ManagementAddress: LldpTlvType = LldpTlvType(8);
+}
+
+impl fmt::Display for LldpTlvType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                LldpTlvTypes::End => "End Of LLDPDU".to_string(),
+                LldpTlvTypes::ChassisId => "Chassis ID".to_string(),
+                LldpTlvTypes::PortId => "Port ID".to_string(),
+                LldpTlvTypes::Ttl => "Time To Live".to_string(),
+                LldpTlvTypes::SystemName => "System Name".to_string(),
+                LldpTlvTypes::ManagementAddress => "Management Address".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// The type of the chassis identifier.
+///
+/// A list of supported subtypes is under [`ChassisIdSubtypes`].
+///
+/// [`ChassisIdSubtypes`]: ChassisIdSubtypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct ChassisIdSubtype(pub u8);
+
+/// Supported chassis ID subtypes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod ChassisIdSubtypes {
+    use super::ChassisIdSubtype;
+
+    /// Entity physical alias of a chassis component.
+    pub const ChassisComponent: ChassisIdSubtype = ChassisIdSubtype(1);
+
+    /// Interface alias.
+    pub const InterfaceAlias: ChassisIdSubtype = ChassisIdSubtype(2);
+
+    /// Entity physical alias of a port or backplane component.
+    pub const PortComponent: ChassisIdSubtype = ChassisIdSubtype(3);
+
+    /// MAC address.
+    pub const MacAddress: ChassisIdSubtype = ChassisIdSubtype(4);
+
+    /// Network address, prefixed with the IANA address family number.
+    pub const NetworkAddress: ChassisIdSubtype = ChassisIdSubtype(5);
+
+    /// Interface name.
+    pub const InterfaceName: ChassisIdSubtype = ChassisIdSubtype(6);
+
+    /// Locally assigned.
+    pub const Local: ChassisIdSubtype = ChassisIdSubtype(7);
+}
+
+/// The type of the port identifier.
+///
+/// A list of supported subtypes is under [`PortIdSubtypes`].
+///
+/// [`PortIdSubtypes`]: PortIdSubtypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct PortIdSubtype(pub u8);
+
+/// Supported port ID subtypes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod PortIdSubtypes {
+    use super::PortIdSubtype;
+
+    /// Interface alias.
+    pub const InterfaceAlias: PortIdSubtype = PortIdSubtype(1);
+
+    /// Entity physical alias of a port component.
+    pub const PortComponent: PortIdSubtype = PortIdSubtype(2);
+
+    /// MAC address.
+    pub const MacAddress: PortIdSubtype = PortIdSubtype(3);
+
+    /// Network address, prefixed with the IANA address family number.
+    pub const NetworkAddress: PortIdSubtype = PortIdSubtype(4);
+
+    /// Interface name.
+    pub const InterfaceName: PortIdSubtype = PortIdSubtype(5);
+
+    /// Agent circuit ID, defined in [IETF RFC 3046].
+    ///
+    /// [IETF RFC 3046]: https://tools.ietf.org/html/rfc3046
+    pub const AgentCircuitId: PortIdSubtype = PortIdSubtype(6);
+
+    /// Locally assigned.
+    pub const Local: PortIdSubtype = PortIdSubtype(7);
+}
+
+/// LLDP TLV based on IEEE 802.1AB.
+///
+/// Each TLV is encoded as a 7-bit type and a 9-bit length that excludes
+/// the 2-octet type and length header, followed by the information string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LldpTlv<'a> {
+    /// End Of LLDPDU. Marks the end of the TLVs, the remaining octets are
+    /// padding.
+    End,
+
+    /// The identifier of the chassis, with its subtype.
+    ChassisId(ChassisIdSubtype, &'a [u8]),
+
+    /// The identifier of the port, with its subtype.
+    PortId(PortIdSubtype, &'a [u8]),
+
+    /// The number of seconds the receiver should regard the information
+    /// as valid. Zero tells the receiver to delete the information
+    /// immediately.
+    Ttl(u16),
+
+    /// The administratively assigned name of the system.
+    SystemName(&'a str),
+
+    /// An IPv4 or IPv6 address to reach the management entity, and the
+    /// interface index associated with it.
+    ///
+    /// When encoding, the interface is numbered by its interface index and
+    /// the object identifier is omitted. When parsing, the numbering
+    /// subtype and the object identifier are ignored, and management
+    /// addresses of other families are read as [`Unknown`].
+    ///
+    /// [`Unknown`]: LldpTlv::Unknown
+    ManagementAddress(IpAddr, u32),
+
+    /// Any other TLV, with its type and information string.
+    Unknown(LldpTlvType, &'a [u8]),
+}
+
+impl LldpTlv<'_> {
+    /// Returns the TLV type.
+    pub fn tlv_type(&self) -> LldpTlvType {
+        match self {
+            LldpTlv::End => LldpTlvTypes::End,
+            LldpTlv::ChassisId(..) => LldpTlvTypes::ChassisId,
+            LldpTlv::PortId(..) => LldpTlvTypes::PortId,
+            LldpTlv::Ttl(_) => LldpTlvTypes::Ttl,
+            LldpTlv::SystemName(_) => LldpTlvTypes::SystemName,
+            LldpTlv::ManagementAddress(..) => LldpTlvTypes::ManagementAddress,
+            LldpTlv::Unknown(tlv_type, _) => *tlv_type,
+        }
+    }
+
+    /// Returns the length of the encoded TLV in octets.
+    pub fn length(&self) -> usize {
+        match self {
+            LldpTlv::End => 2,
+            LldpTlv::ChassisId(_, id) => 3 + id.len(),
+            LldpTlv::PortId(_, id) => 3 + id.len(),
+            LldpTlv::Ttl(_) => 4,
+            LldpTlv::SystemName(name) => 2 + name.len(),
+            LldpTlv::ManagementAddress(IpAddr::V4(_), _) => 2 + 12,
+            LldpTlv::ManagementAddress(IpAddr::V6(_), _) => 2 + 24,
+            LldpTlv::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes the TLV and appends it to the buffer.
+    pub(crate) fn encode(&self, buffer: &mut Vec<u8>) -> Fallible<()> {
+        let len = self.length() - 2;
+        ensure!(len <= LLDP_MAX_TLV_LEN, LldpTlvError::TooLong(len));
+        ensure!(
+            self.tlv_type().0 < 0x80,
+            LldpTlvError::InvalidType(self.tlv_type())
+        );
+
+        let header = (u16::from(self.tlv_type().0) << 9) | len as u16;
+        buffer.extend_from_slice(&header.to_be_bytes());
+        match *self {
+            LldpTlv::End => (),
+            LldpTlv::ChassisId(ChassisIdSubtype(subtype), id)
+            | LldpTlv::PortId(PortIdSubtype(subtype), id) => {
+                buffer.push(subtype);
+                buffer.extend_from_slice(id);
+            }
+            LldpTlv::Ttl(ttl) => buffer.extend_from_slice(&ttl.to_be_bytes()),
+            LldpTlv::SystemName(name) => buffer.extend_from_slice(name.as_bytes()),
+            LldpTlv::ManagementAddress(addr, if_number) => {
+                match addr {
+                    IpAddr::V4(addr) => {
+                        buffer.extend_from_slice(&[5, ADDRESS_FAMILY_IPV4]);
+                        buffer.extend_from_slice(&addr.octets());
+                    }
+                    IpAddr::V6(addr) => {
+                        buffer.extend_from_slice(&[17, ADDRESS_FAMILY_IPV6]);
+                        buffer.extend_from_slice(&addr.octets());
+                    }
+                }
+                buffer.push(INTERFACE_NUMBERING_IFINDEX);
+                buffer.extend_from_slice(&if_number.to_be_bytes());
+                // an empty object identifier.
+                buffer.push(0);
+            }
+            LldpTlv::Unknown(_, data) => buffer.extend_from_slice(data),
+        }
+
+        Ok(())
+    }
+}
+
+/// An iterator that iterates through the TLVs in the LLDPDU.
+///
+/// Iteration stops after the end TLV.
+#[derive(Debug)]
+pub struct LldpTlvsIterator<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> LldpTlvsIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        LldpTlvsIterator { data, offset: 0 }
+    }
+
+    /// Returns the offset of the next TLV from the start of the LLDPDU.
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<LldpTlv<'a>>> {
+        let data = &self.data[self.offset..];
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        ensure!(
+            data.len() >= 2,
+            ParseError::new("LLDP TLV header is truncated.")
+        );
+
+        let header = u16::from_be_bytes([data[0], data[1]]);
+        let tlv_type = LldpTlvType((header >> 9) as u8);
+        let len = 2 + (header & 0x01ff) as usize;
+        ensure!(
+            len <= data.len(),
+            ParseError::new("Invalid LLDP TLV length.")
+        );
+
+        let value = &data[2..len];
+        let tlv = match tlv_type {
+            LldpTlvTypes::End => {
+                // the rest is padding
+                self.offset = self.data.len();
+                return Ok(Some(LldpTlv::End));
+            }
+            LldpTlvTypes::ChassisId => {
+                ensure!(
+                    value.len() >= 2,
+                    ParseError::new("Invalid chassis ID TLV length.")
+                );
+                LldpTlv::ChassisId(ChassisIdSubtype(value[0]), &value[1..])
+            }
+            LldpTlvTypes::PortId => {
+                ensure!(
+                    value.len() >= 2,
+                    ParseError::new("Invalid port ID TLV length.")
+                );
+                LldpTlv::PortId(PortIdSubtype(value[0]), &value[1..])
+            }
+            LldpTlvTypes::Ttl => {
+                ensure!(
+                    value.len() == 2,
+                    ParseError::new("Invalid time to live TLV length.")
+                );
+                LldpTlv::Ttl(u16::from_be_bytes([value[0], value[1]]))
+            }
+            LldpTlvTypes::SystemName => match std::str::from_utf8(value) {
+                Ok(name) => LldpTlv::SystemName(name),
+                Err(_) => return Err(ParseError::new("Invalid system name TLV.").into()),
+            },
+            LldpTlvTypes::ManagementAddress => parse_management_address(value)?
+                .map(|(addr, if_number)| LldpTlv::ManagementAddress(addr, if_number))
+                .unwrap_or(LldpTlv::Unknown(tlv_type, value)),
+            _ => LldpTlv::Unknown(tlv_type, value),
+        };
+
+        self.offset += len;
+        Ok(Some(tlv))
+    }
+}
+
+/// Parses the management address TLV. Returns `None` if the address is
+/// neither an IPv4 nor an IPv6 address.
+fn parse_management_address(value: &[u8]) -> Fallible<Option<(IpAddr, u32)>> {
+    // the address string length includes the address family octet.
+    ensure!(
+        !value.is_empty() && value[0] >= 2 && value.len() >= 1 + value[0] as usize + 6,
+        ParseError::new("Invalid management address TLV length.")
+    );
+
+    let addr_len = value[0] as usize;
+    let addr = &value[2..1 + addr_len];
+    let if_offset = 1 + addr_len + 1;
+    let if_number = u32::from_be_bytes([
+        value[if_offset],
+        value[if_offset + 1],
+        value[if_offset + 2],
+        value[if_offset + 3],
+    ]);
+
+    let addr = match (value[1], addr.len()) {
+        (ADDRESS_FAMILY_IPV4, 4) => IpAddr::V4(Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3])),
+        (ADDRESS_FAMILY_IPV6, 16) => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(addr);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some((addr, if_number)))
+}
+
+/// LLDP TLV related errors.
+#[derive(Debug, Fail)]
+pub enum LldpTlvError {
+    /// Error indicating the information string exceeds the maximum length.
+    #[fail(
+        display = "TLV information string length {} exceeds the maximum of 511.",
+        _0
+    )]
+    TooLong(usize),
+
+    /// Error indicating the TLV type does not fit in 7 bits.
+    #[fail(display = "TLV type {} is out of range.", _0)]
+    InvalidType(LldpTlvType),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_type_to_string() {
+        assert_eq!("Chassis ID", LldpTlvTypes::ChassisId.to_string());
+        assert_eq!("127", LldpTlvType(127).to_string());
+    }
+
+    #[test]
+    fn encode_and_iterate_tlvs() {
+        let tlvs = [
+            LldpTlv::ChassisId(ChassisIdSubtypes::MacAddress, &[0, 0, 0, 0, 0, 1]),
+            LldpTlv::PortId(PortIdSubtypes::InterfaceName, b"eth0"),
+            LldpTlv::Ttl(120),
+            LldpTlv::SystemName("switch"),
+            LldpTlv::ManagementAddress(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 1),
+            LldpTlv::ManagementAddress(IpAddr::V6(Ipv6Addr::LOCALHOST), 2),
+            LldpTlv::Unknown(LldpTlvType(127), &[0x00, 0x80, 0xc2, 0x01, 0x00, 0x01]),
+            LldpTlv::End,
+        ];
+
+        let mut bytes = vec![];
+        for tlv in tlvs.iter() {
+            tlv.encode(&mut bytes).unwrap();
+        }
+        assert_eq!(tlvs.iter().map(LldpTlv::length).sum::<usize>(), bytes.len());
+
+        // padding after the end TLV is skipped.
+        bytes.extend_from_slice(&[0; 4]);
+
+        let mut iter = LldpTlvsIterator::new(&bytes);
+        for tlv in tlvs.iter() {
+            assert_eq!(Some(*tlv), iter.next().unwrap());
+        }
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[test]
+    fn encode_too_long_tlv() {
+        let name = "a".repeat(LLDP_MAX_TLV_LEN + 1);
+        let mut bytes = vec![];
+        assert!(LldpTlv::SystemName(&name).encode(&mut bytes).is_err());
+        assert!(LldpTlv::Unknown(LldpTlvType(128), &[])
+            .encode(&mut bytes)
+            .is_err());
+    }
+
+    #[test]
+    fn iterate_invalid_tlvs() {
+        // length exceeds the remaining data.
+        let mut iter = LldpTlvsIterator::new(&[0x06, 0x02, 0x00]);
+        assert!(iter.next().is_err());
+
+        // time to live must be 2 octets.
+        let mut iter = LldpTlvsIterator::new(&[0x06, 0x01, 0x00]);
+        assert!(iter.next().is_err());
+    }
+}
@@ -18,17 +18,37 @@
 
 //! Packet types for reading and writing various network protocols.
 
+mod arp;
 pub mod checksum;
+mod dhcp;
+mod dns;
 mod ethernet;
+mod geneve;
+mod gtpu;
 pub mod icmp;
+mod igmp;
 pub mod ip;
+mod lldp;
+mod mpls;
+mod sctp;
 mod tcp;
 pub mod types;
 mod udp;
+mod vxlan;
 
+pub use self::arp::*;
+pub use self::dhcp::*;
+pub use self::dns::*;
 pub use self::ethernet::*;
+pub use self::geneve::*;
+pub use self::gtpu::*;
+pub use self::igmp::*;
+pub use self::lldp::*;
+pub use self::mpls::*;
+pub use self::sctp::*;
 pub use self::tcp::*;
 pub use self::udp::*;
+pub use self::vxlan::*;
 
 use crate::Mbuf;
 use failure::{Fail, Fallible};
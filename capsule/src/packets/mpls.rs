@@ -0,0 +1,591 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::DEFAULT_IP_TTL;
+use crate::packets::types::u32be;
+use crate::packets::{
+    EtherType, EtherTypeEnvelope, EtherTypes, Ethernet, Internal, Packet, ParseError,
+};
+use crate::{ensure, Mbuf, SizeOf};
+use failure::{Fail, Fallible};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Multiprotocol Label Switching packet based on [IETF RFC 3032].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                Label                  | TC  |S|       TTL     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              ...                              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// The header is a stack of one or more label stack entries.
+///
+/// - *Label*:          20-bit label value.
+///
+/// - *TC*:             3-bit traffic class, used for QoS priority and
+///                     ECN, as per [IETF RFC 5462].
+///
+/// - *S*:              Bottom of stack. Set for the last entry in the
+///                     label stack.
+///
+/// - *TTL*:            8-bit time to live.
+///
+/// The accessors on the packet read and modify the top of the stack. Use
+/// [`labels`] to iterate through the whole stack.
+///
+/// # Payload
+///
+/// MPLS has no field identifying the payload. The payload is inferred
+/// as an IPv4 or IPv6 packet from the first nibble after the bottom of
+/// the stack, so it can be parsed with `mpls.parse::<Ipv4<Mpls>>()` or
+/// `mpls.parse::<Ipv6<Mpls>>()`.
+///
+/// [IETF RFC 3032]: https://tools.ietf.org/html/rfc3032
+/// [IETF RFC 5462]: https://tools.ietf.org/html/rfc5462
+/// [`labels`]: Mpls::labels
+pub struct Mpls<E: EtherTypeEnvelope = Ethernet> {
+    envelope: E,
+    header: NonNull<MplsLabel>,
+    offset: usize,
+}
+
+impl<E: EtherTypeEnvelope> Mpls<E> {
+    #[inline]
+    fn header(&self) -> &MplsLabel {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut MplsLabel {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the label stack entry at the top of the stack.
+    #[inline]
+    pub fn top(&self) -> MplsLabel {
+        *self.header()
+    }
+
+    /// Returns the label at the top of the stack.
+    #[inline]
+    pub fn label(&self) -> u32 {
+        self.header().label()
+    }
+
+    /// Returns the traffic class at the top of the stack.
+    #[inline]
+    pub fn traffic_class(&self) -> u8 {
+        self.header().traffic_class()
+    }
+
+    /// Sets the traffic class at the top of the stack.
+    #[inline]
+    pub fn set_traffic_class(&mut self, traffic_class: u8) {
+        self.header_mut().set_traffic_class(traffic_class);
+    }
+
+    /// Returns whether the top of the stack is also the bottom of the stack.
+    #[inline]
+    pub fn bottom_of_stack(&self) -> bool {
+        self.header().bottom_of_stack()
+    }
+
+    /// Returns the time to live at the top of the stack.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        self.header().ttl()
+    }
+
+    /// Sets the time to live at the top of the stack.
+    #[inline]
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.header_mut().set_ttl(ttl);
+    }
+
+    /// Returns an iterator over the label stack entries, from the top to
+    /// the bottom of the stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mpls = ethernet.parse::<Mpls>()?;
+    ///
+    /// for label in mpls.labels() {
+    ///     println!("{:?}", label);
+    /// }
+    /// ```
+    #[inline]
+    pub fn labels(&self) -> MplsLabelsIterator<'_> {
+        MplsLabelsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset,
+            done: false,
+        }
+    }
+
+    /// Returns the number of entries in the label stack.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.labels().count()
+    }
+
+    /// Pushes a new entry onto the top of the label stack.
+    ///
+    /// The bottom of stack bit of the new entry is cleared since the stack
+    /// is never empty.
+    #[inline]
+    pub fn push_label(&mut self, mut label: MplsLabel) -> Fallible<()> {
+        label.set_bottom_of_stack(false);
+        let offset = self.offset;
+        let mbuf = self.mbuf_mut();
+        mbuf.extend(offset, MplsLabel::size_of())?;
+        let _ = mbuf.write_data(offset, &label)?;
+        Ok(())
+    }
+
+    /// Pops the entry off the top of the label stack and returns it.
+    ///
+    /// Returns an error if the top of the stack is the bottom of the stack.
+    /// To remove the last entry, use [`decap`] instead.
+    ///
+    /// [`decap`]: Mpls::decap
+    #[inline]
+    pub fn pop_label(&mut self) -> Fallible<MplsLabel> {
+        ensure!(!self.bottom_of_stack(), MplsError::BottomOfStack);
+
+        let label = self.top();
+        let offset = self.offset;
+        self.mbuf_mut().shrink(offset, MplsLabel::size_of())?;
+        Ok(label)
+    }
+
+    /// Swaps the label at the top of the stack. The traffic class, bottom
+    /// of stack and time to live are unchanged.
+    #[inline]
+    pub fn swap_label(&mut self, label: u32) {
+        self.header_mut().set_label(label);
+    }
+
+    /// Removes the whole label stack and returns the envelope.
+    ///
+    /// The envelope's ether type is set to the inferred type of the payload.
+    /// Returns an error if the payload is not an IPv4 or IPv6 packet.
+    #[inline]
+    pub fn decap(self) -> Fallible<E> {
+        let ether_type = self.ether_type();
+        ensure!(
+            ether_type == EtherTypes::Ipv4 || ether_type == EtherTypes::Ipv6,
+            ParseError::new("not an MPLS packet carrying an IP packet.")
+        );
+
+        let offset = self.offset;
+        let len = self.header_len();
+        let mut envelope = self.deparse();
+        envelope.mbuf_mut().shrink(offset, len)?;
+        envelope.set_ether_type(ether_type);
+        Ok(envelope)
+    }
+}
+
+impl<E: EtherTypeEnvelope> fmt::Debug for Mpls<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("mpls")
+            .field("label", &self.label())
+            .field("traffic_class", &self.traffic_class())
+            .field("bottom_of_stack", &self.bottom_of_stack())
+            .field("ttl", &self.ttl())
+            .field("depth", &self.depth())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: EtherTypeEnvelope> Packet for Mpls<E> {
+    /// The preceding type for an MPLS packet is typically an Ethernet frame.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the packet header.
+    ///
+    /// The length of the MPLS header is the length of the whole label stack.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.depth() * MplsLabel::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Mpls::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an MPLS packet.
+    ///
+    /// The envelope's ether type must be set to either [`EtherTypes::Mpls`]
+    /// or [`EtherTypes::MplsMulticast`]. Otherwise a parsing error is
+    /// returned.
+    ///
+    /// [`EtherTypes::Mpls`]: crate::packets::EtherTypes::Mpls
+    /// [`EtherTypes::MplsMulticast`]: crate::packets::EtherTypes::MplsMulticast
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Mpls
+                || envelope.ether_type() == EtherTypes::MplsMulticast,
+            ParseError::new("not an MPLS packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        // makes sure the whole label stack is within the buffer.
+        let mut entry = offset;
+        while !unsafe { mbuf.read_data::<MplsLabel>(entry)?.as_ref() }.bottom_of_stack() {
+            entry += MplsLabel::size_of();
+        }
+
+        Ok(Mpls {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an MPLS packet with a single label stack entry to the
+    /// beginning of the envelope's payload.
+    ///
+    /// The envelope's ether type is set to [`EtherTypes::Mpls`]. The label
+    /// is 0, the bottom of stack bit is set and the time to live is set
+    /// to the default of 64.
+    ///
+    /// [`EtherTypes::Mpls`]: crate::packets::EtherTypes::Mpls
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, MplsLabel::size_of())?;
+        let header = mbuf.write_data(offset, &MplsLabel::new(0, 0, true, DEFAULT_IP_TTL))?;
+
+        envelope.set_ether_type(EtherTypes::Mpls);
+
+        Ok(Mpls {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: EtherTypeEnvelope> EtherTypeEnvelope for Mpls<E> {
+    /// Returns the ether type of the payload, inferred from the IP version
+    /// in the first nibble after the bottom of the stack.
+    #[inline]
+    fn ether_type(&self) -> EtherType {
+        match self.mbuf().read_data::<u8>(self.payload_offset()) {
+            Ok(byte) => match unsafe { *byte.as_ref() } >> 4 {
+                4 => EtherTypes::Ipv4,
+                6 => EtherTypes::Ipv6,
+                _ => EtherType::new(0),
+            },
+            Err(_) => EtherType::new(0),
+        }
+    }
+
+    /// MPLS has no protocol type field. The ether type is always inferred
+    /// from the payload, so this is a no-op.
+    #[inline]
+    fn set_ether_type(&mut self, _ether_type: EtherType) {}
+}
+
+/// MPLS label stack entry.
+#[derive(Clone, Copy, Default, SizeOf)]
+#[repr(C, packed)]
+pub struct MplsLabel {
+    entry: u32be,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+impl MplsLabel {
+    /// Creates a new label stack entry. Only the lower 20 bits of `label`
+    /// and the lower 3 bits of `traffic_class` are used.
+    pub fn new(label: u32, traffic_class: u8, bottom_of_stack: bool, ttl: u8) -> Self {
+        let mut entry = MplsLabel::default();
+        entry.set_label(label);
+        entry.set_traffic_class(traffic_class);
+        entry.set_bottom_of_stack(bottom_of_stack);
+        entry.set_ttl(ttl);
+        entry
+    }
+
+    /// Returns the label value.
+    #[inline]
+    pub fn label(&self) -> u32 {
+        let entry: u32 = self.entry.into();
+        entry >> 12
+    }
+
+    /// Sets the label value. Only the lower 20 bits are used.
+    #[inline]
+    pub fn set_label(&mut self, label: u32) {
+        self.entry =
+            (self.entry & u32be::from(0x0000_0fff)) | u32be::from((label & 0x000f_ffff) << 12);
+    }
+
+    /// Returns the traffic class.
+    #[inline]
+    pub fn traffic_class(&self) -> u8 {
+        let entry: u32 = self.entry.into();
+        ((entry >> 9) & 0x07) as u8
+    }
+
+    /// Sets the traffic class. Only the lower 3 bits are used.
+    #[inline]
+    pub fn set_traffic_class(&mut self, traffic_class: u8) {
+        self.entry = (self.entry & u32be::from(!0x0000_0e00))
+            | u32be::from(u32::from(traffic_class & 0x07) << 9);
+    }
+
+    /// Returns whether the entry is the bottom of the stack.
+    #[inline]
+    pub fn bottom_of_stack(&self) -> bool {
+        self.entry & u32be::from(0x0000_0100) > u32be::MIN
+    }
+
+    /// Sets the bottom of stack bit.
+    #[inline]
+    pub fn set_bottom_of_stack(&mut self, bottom_of_stack: bool) {
+        if bottom_of_stack {
+            self.entry |= u32be::from(0x0000_0100);
+        } else {
+            self.entry &= u32be::from(!0x0000_0100);
+        }
+    }
+
+    /// Returns the time to live.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        let entry: u32 = self.entry.into();
+        entry as u8
+    }
+
+    /// Sets the time to live.
+    #[inline]
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.entry = (self.entry & u32be::from(0xffff_ff00)) | u32be::from(u32::from(ttl));
+    }
+}
+
+impl fmt::Debug for MplsLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MplsLabel")
+            .field("label", &self.label())
+            .field("traffic_class", &self.traffic_class())
+            .field("bottom_of_stack", &self.bottom_of_stack())
+            .field("ttl", &self.ttl())
+            .finish()
+    }
+}
+
+/// An iterator over the entries in an MPLS label stack.
+pub struct MplsLabelsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    done: bool,
+}
+
+impl Iterator for MplsLabelsIterator<'_> {
+    type Item = MplsLabel;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.mbuf.read_data::<MplsLabel>(self.offset) {
+            Ok(label) => {
+                let label = unsafe { *label.as_ref() };
+                self.offset += MplsLabel::size_of();
+                self.done = label.bottom_of_stack();
+                Some(label)
+            }
+            Err(_) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MplsLabelsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MplsLabelsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// MPLS label stack related errors.
+#[derive(Debug, Fail)]
+pub enum MplsError {
+    /// Error indicating the label stack entry is the bottom of the stack.
+    #[fail(display = "Cannot pop the bottom of the label stack.")]
+    BottomOfStack,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, MPLS_PACKET};
+
+    #[test]
+    fn size_of_mpls_label() {
+        assert_eq!(4, MplsLabel::size_of());
+    }
+
+    #[test]
+    fn mpls_label_fields() {
+        let mut label = MplsLabel::new(0x12345, 5, true, 64);
+        assert_eq!(0x12345, label.label());
+        assert_eq!(5, label.traffic_class());
+        assert!(label.bottom_of_stack());
+        assert_eq!(64, label.ttl());
+
+        label.set_label(0xfff_ffff);
+        label.set_bottom_of_stack(false);
+        assert_eq!(0xf_ffff, label.label());
+        assert_eq!(5, label.traffic_class());
+        assert!(!label.bottom_of_stack());
+        assert_eq!(64, label.ttl());
+    }
+
+    #[capsule::test]
+    fn parse_mpls_packet() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mpls = ethernet.parse::<Mpls>().unwrap();
+
+        assert_eq!(100, mpls.label());
+        assert_eq!(0, mpls.traffic_class());
+        assert!(!mpls.bottom_of_stack());
+        assert_eq!(64, mpls.ttl());
+        assert_eq!(2, mpls.depth());
+        assert_eq!(8, mpls.header_len());
+
+        let labels = mpls.labels().map(|l| l.label()).collect::<Vec<_>>();
+        assert_eq!(vec![100, 200], labels);
+        assert!(mpls.labels().last().unwrap().bottom_of_stack());
+
+        // the payload is inferred as IPv4
+        assert_eq!(EtherTypes::Ipv4, mpls.ether_type());
+        let ipv4 = mpls.parse::<Ipv4<Mpls>>().unwrap();
+        assert_eq!("139.133.217.110", ipv4.src().to_string());
+        let udp = ipv4.parse::<Udp<Ipv4<Mpls>>>().unwrap();
+        assert_eq!(1087, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_mpls_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Mpls>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_pop_and_swap_labels() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut mpls = ethernet.parse::<Mpls>().unwrap();
+
+        assert!(mpls.push_label(MplsLabel::new(300, 1, true, 32)).is_ok());
+        assert_eq!(3, mpls.depth());
+        assert_eq!(300, mpls.label());
+        assert_eq!(1, mpls.traffic_class());
+        assert!(!mpls.bottom_of_stack());
+        assert_eq!(MPLS_PACKET.len() + 4, mpls.mbuf().data_len());
+
+        mpls.swap_label(400);
+        assert_eq!(400, mpls.label());
+        assert_eq!(32, mpls.ttl());
+
+        assert_eq!(400, mpls.pop_label().unwrap().label());
+        assert_eq!(100, mpls.pop_label().unwrap().label());
+        assert_eq!(200, mpls.label());
+        assert!(mpls.bottom_of_stack());
+        assert!(mpls.pop_label().is_err());
+        assert_eq!(1, mpls.depth());
+
+        // the payload is untouched
+        let ipv4 = mpls.peek::<Ipv4<Mpls>>().unwrap();
+        assert_eq!("139.133.233.2", ipv4.dst().to_string());
+    }
+
+    #[capsule::test]
+    fn push_and_decap_mpls_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut mpls = ethernet.push::<Mpls>().unwrap();
+
+        assert_eq!(EtherTypes::Mpls, mpls.envelope().ether_type());
+        assert!(mpls.bottom_of_stack());
+        assert_eq!(DEFAULT_IP_TTL, mpls.ttl());
+        mpls.swap_label(100);
+        assert_eq!(EtherTypes::Ipv4, mpls.ether_type());
+
+        let ethernet = mpls.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let bytes = ethernet
+            .mbuf()
+            .read_data_slice::<u8>(0, IPV4_UDP_PACKET.len())
+            .unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], unsafe { bytes.as_ref() });
+    }
+}
@@ -0,0 +1,613 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::Fallible;
+use std::fmt;
+
+/// The length of the chunk header in octets.
+const CHUNK_HEADER_LEN: usize = 4;
+
+/// [IANA] assigned SCTP chunk type.
+///
+/// A list of supported types is under [`SctpChunkTypes`].
+///
+/// [IANA]: https://www.iana.org/assignments/sctp-parameters/sctp-parameters.xhtml#sctp-parameters-1
+/// [`SctpChunkTypes`]: SctpChunkTypes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct SctpChunkType(pub u8);
+
+/// Supported SCTP chunk types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod SctpChunkTypes {
+    use super::SctpChunkType;
+
+    /// Payload Data.
+    pub const Data: SctpChunkType = SctpChunkType(0);
+
+    /// Initiation.
+    pub const Init: SctpChunkType = SctpChunkType(1);
+
+    /// Initiation Acknowledgement.
+    pub const InitAck: SctpChunkType = SctpChunkType(2);
+
+    /// Selective Acknowledgement.
+    pub const Sack: SctpChunkType = SctpChunkType(3);
+
+    /// Heartbeat Request.
+    pub const Heartbeat: SctpChunkType = SctpChunkType(4);
+
+    /// Heartbeat Acknowledgement.
+    pub const HeartbeatAck: SctpChunkType = SctpChunkType(5);
+
+    /// Abort.
+    pub const Abort: SctpChunkType = SctpChunkType(6);
+
+    /// Shutdown.
+    pub const Shutdown: SctpChunkType = SctpChunkType(7);
+
+    /// Shutdown Acknowledgement.
+    pub const ShutdownAck: SctpChunkType = SctpChunkType(8);
+
+    /// Operation Error.
+    pub const Error: SctpChunkType = SctpChunkType(9);
+
+    /// State Cookie.
+    pub const CookieEcho: SctpChunkType = SctpChunkType(10);
+
+    /// Cookie Acknowledgement.
+    pub const CookieAck: SctpChunkType = SctpChunkType(11);
+
+    /// Shutdown Complete.
+    pub const ShutdownComplete: SctpChunkType = SctpChunkType(14);
+}
+
+impl fmt::Display for SctpChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                SctpChunkTypes::Data => "DATA".to_string(),
+                SctpChunkTypes::Init => "INIT".to_string(),
+                SctpChunkTypes::InitAck => "INIT ACK".to_string(),
+                SctpChunkTypes::Sack => "SACK".to_string(),
+                SctpChunkTypes::Heartbeat => "HEARTBEAT".to_string(),
+                SctpChunkTypes::HeartbeatAck => "HEARTBEAT ACK".to_string(),
+                SctpChunkTypes::Abort => "ABORT".to_string(),
+                SctpChunkTypes::Shutdown => "SHUTDOWN".to_string(),
+                SctpChunkTypes::ShutdownAck => "SHUTDOWN ACK".to_string(),
+                SctpChunkTypes::Error => "ERROR".to_string(),
+                SctpChunkTypes::CookieEcho => "COOKIE ECHO".to_string(),
+                SctpChunkTypes::CookieAck => "COOKIE ACK".to_string(),
+                SctpChunkTypes::ShutdownComplete => "SHUTDOWN COMPLETE".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// An SCTP chunk based on [IETF RFC 4960].
+///
+/// Each chunk is encoded as a type octet, a flags octet, a 16-bit length
+/// that includes the 4 octets chunk header, and the chunk value. Chunks
+/// are padded to a multiple of 4 octets. The padding is not included in
+/// the length.
+///
+/// [IETF RFC 4960]: https://tools.ietf.org/html/rfc4960#section-3.2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SctpChunk<'a> {
+    /// Payload data.
+    Data(DataChunk<'a>),
+
+    /// Initiation of an association.
+    Init(InitChunk<'a>),
+
+    /// Acknowledgement of the initiation of an association.
+    InitAck(InitChunk<'a>),
+
+    /// Selective acknowledgement of the received data.
+    Sack(SackChunk<'a>),
+
+    /// Heartbeat request with the sender-specific heartbeat information.
+    Heartbeat(&'a [u8]),
+
+    /// Heartbeat acknowledgement with the heartbeat information copied
+    /// from the request.
+    HeartbeatAck(&'a [u8]),
+
+    /// Abort of an association.
+    Abort {
+        /// Whether the verification tag is reflected. This is the T bit.
+        tag_reflected: bool,
+        /// The encoded error causes.
+        causes: &'a [u8],
+    },
+
+    /// Graceful shutdown of an association, with the last TSN received in
+    /// sequence.
+    Shutdown(u32),
+
+    /// Acknowledgement of a shutdown.
+    ShutdownAck,
+
+    /// Operation error with the encoded error causes.
+    Error(&'a [u8]),
+
+    /// State cookie echoed from the initiation acknowledgement.
+    CookieEcho(&'a [u8]),
+
+    /// Acknowledgement of the state cookie.
+    CookieAck,
+
+    /// Completion of the shutdown procedure.
+    ShutdownComplete {
+        /// Whether the verification tag is reflected. This is the T bit.
+        tag_reflected: bool,
+    },
+
+    /// Any other chunk, with its type, flags and value.
+    Unknown(SctpChunkType, u8, &'a [u8]),
+}
+
+impl SctpChunk<'_> {
+    /// Returns the chunk type.
+    pub fn chunk_type(&self) -> SctpChunkType {
+        match self {
+            SctpChunk::Data(_) => SctpChunkTypes::Data,
+            SctpChunk::Init(_) => SctpChunkTypes::Init,
+            SctpChunk::InitAck(_) => SctpChunkTypes::InitAck,
+            SctpChunk::Sack(_) => SctpChunkTypes::Sack,
+            SctpChunk::Heartbeat(_) => SctpChunkTypes::Heartbeat,
+            SctpChunk::HeartbeatAck(_) => SctpChunkTypes::HeartbeatAck,
+            SctpChunk::Abort { .. } => SctpChunkTypes::Abort,
+            SctpChunk::Shutdown(_) => SctpChunkTypes::Shutdown,
+            SctpChunk::ShutdownAck => SctpChunkTypes::ShutdownAck,
+            SctpChunk::Error(_) => SctpChunkTypes::Error,
+            SctpChunk::CookieEcho(_) => SctpChunkTypes::CookieEcho,
+            SctpChunk::CookieAck => SctpChunkTypes::CookieAck,
+            SctpChunk::ShutdownComplete { .. } => SctpChunkTypes::ShutdownComplete,
+            SctpChunk::Unknown(chunk_type, _, _) => *chunk_type,
+        }
+    }
+}
+
+#[inline]
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// The DATA chunk.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Type = 0    | Reserved|U|B|E|    Length                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              TSN                              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Stream Identifier S      |   Stream Sequence Number n    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  Payload Protocol Identifier                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// \                                                               \
+/// /                 User Data (seq n of Stream S)                 /
+/// \                                                               \
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DataChunk<'a> {
+    flags: u8,
+    value: &'a [u8],
+}
+
+impl<'a> DataChunk<'a> {
+    /// The fixed length of the chunk value before the user data.
+    const FIXED_LEN: usize = 12;
+
+    /// Returns whether the data is unordered. This is the U bit.
+    pub fn unordered(&self) -> bool {
+        self.flags & 0x04 > 0
+    }
+
+    /// Returns whether the data is the first fragment of a user message.
+    /// This is the B bit.
+    pub fn beginning(&self) -> bool {
+        self.flags & 0x02 > 0
+    }
+
+    /// Returns whether the data is the last fragment of a user message.
+    /// This is the E bit.
+    pub fn ending(&self) -> bool {
+        self.flags & 0x01 > 0
+    }
+
+    /// Returns the transmission sequence number.
+    pub fn tsn(&self) -> u32 {
+        read_u32(self.value, 0)
+    }
+
+    /// Returns the stream identifier.
+    pub fn stream_id(&self) -> u16 {
+        read_u16(self.value, 4)
+    }
+
+    /// Returns the stream sequence number.
+    pub fn stream_seq(&self) -> u16 {
+        read_u16(self.value, 6)
+    }
+
+    /// Returns the payload protocol identifier.
+    pub fn ppid(&self) -> u32 {
+        read_u32(self.value, 8)
+    }
+
+    /// Returns the user data.
+    pub fn user_data(&self) -> &'a [u8] {
+        &self.value[Self::FIXED_LEN..]
+    }
+}
+
+/// The INIT and INIT ACK chunk.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Type = 1/2  |  Chunk Flags  |      Chunk Length             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Initiate Tag                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Advertised Receiver Window Credit (a_rwnd)          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Number of Outbound Streams   |  Number of Inbound Streams    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          Initial TSN                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// \                                                               \
+/// /              Optional/Variable-Length Parameters              /
+/// \                                                               \
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitChunk<'a> {
+    value: &'a [u8],
+}
+
+impl<'a> InitChunk<'a> {
+    /// The fixed length of the chunk value before the parameters.
+    const FIXED_LEN: usize = 16;
+
+    /// Returns the initiate tag.
+    pub fn initiate_tag(&self) -> u32 {
+        read_u32(self.value, 0)
+    }
+
+    /// Returns the advertised receiver window credit.
+    pub fn a_rwnd(&self) -> u32 {
+        read_u32(self.value, 4)
+    }
+
+    /// Returns the number of outbound streams.
+    pub fn outbound_streams(&self) -> u16 {
+        read_u16(self.value, 8)
+    }
+
+    /// Returns the number of inbound streams.
+    pub fn inbound_streams(&self) -> u16 {
+        read_u16(self.value, 10)
+    }
+
+    /// Returns the initial transmission sequence number.
+    pub fn initial_tsn(&self) -> u32 {
+        read_u32(self.value, 12)
+    }
+
+    /// Returns the encoded optional and variable-length parameters. For
+    /// INIT ACK, this includes the mandatory state cookie.
+    pub fn parameters(&self) -> &'a [u8] {
+        &self.value[Self::FIXED_LEN..]
+    }
+}
+
+/// The SACK chunk.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Type = 3    |Chunk  Flags   |      Chunk Length             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Cumulative TSN Ack                       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Advertised Receiver Window Credit (a_rwnd)           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Number of Gap Ack Blocks = N  |  Number of Duplicate TSNs = X |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Gap Ack Block #1 Start       |   Gap Ack Block #1 End        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// \                              ...                              \
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Duplicate TSN 1                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// \                              ...                              \
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SackChunk<'a> {
+    value: &'a [u8],
+}
+
+impl<'a> SackChunk<'a> {
+    /// The fixed length of the chunk value before the gap ack blocks.
+    const FIXED_LEN: usize = 12;
+
+    /// Returns the TSN of the last chunk received in sequence.
+    pub fn cumulative_tsn_ack(&self) -> u32 {
+        read_u32(self.value, 0)
+    }
+
+    /// Returns the advertised receiver window credit.
+    pub fn a_rwnd(&self) -> u32 {
+        read_u32(self.value, 4)
+    }
+
+    #[inline]
+    fn num_gap_blocks(&self) -> usize {
+        read_u16(self.value, 8) as usize
+    }
+
+    #[inline]
+    fn num_duplicate_tsns(&self) -> usize {
+        read_u16(self.value, 10) as usize
+    }
+
+    /// Returns the gap ack blocks as pairs of start and end offsets
+    /// relative to the cumulative TSN ack.
+    pub fn gap_blocks(&self) -> Vec<(u16, u16)> {
+        self.value[Self::FIXED_LEN..Self::FIXED_LEN + self.num_gap_blocks() * 4]
+            .chunks_exact(4)
+            .map(|block| (read_u16(block, 0), read_u16(block, 2)))
+            .collect()
+    }
+
+    /// Returns the TSNs received more than once.
+    pub fn duplicate_tsns(&self) -> Vec<u32> {
+        let offset = Self::FIXED_LEN + self.num_gap_blocks() * 4;
+        self.value[offset..offset + self.num_duplicate_tsns() * 4]
+            .chunks_exact(4)
+            .map(|tsn| read_u32(tsn, 0))
+            .collect()
+    }
+}
+
+/// An iterator that iterates through the chunks of an SCTP packet.
+#[derive(Debug)]
+pub struct SctpChunksIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SctpChunksIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        SctpChunksIterator { data }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<SctpChunk<'a>>> {
+        let data = self.data;
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        ensure!(
+            data.len() >= CHUNK_HEADER_LEN,
+            ParseError::new("Invalid SCTP chunk length.")
+        );
+
+        let chunk_type = SctpChunkType(data[0]);
+        let flags = data[1];
+        let len = read_u16(data, 2) as usize;
+        ensure!(
+            len >= CHUNK_HEADER_LEN && len <= data.len(),
+            ParseError::new("Invalid SCTP chunk length.")
+        );
+
+        let value = &data[CHUNK_HEADER_LEN..len];
+        // skips the padding, which may be omitted on the last chunk.
+        let padded_len = (len + 3) & !3;
+        self.data = &data[padded_len.min(data.len())..];
+
+        let chunk = match chunk_type {
+            SctpChunkTypes::Data => {
+                ensure!(
+                    value.len() >= DataChunk::FIXED_LEN,
+                    ParseError::new("Invalid DATA chunk length.")
+                );
+                SctpChunk::Data(DataChunk { flags, value })
+            }
+            SctpChunkTypes::Init | SctpChunkTypes::InitAck => {
+                ensure!(
+                    value.len() >= InitChunk::FIXED_LEN,
+                    ParseError::new("Invalid INIT chunk length.")
+                );
+                let init = InitChunk { value };
+                if chunk_type == SctpChunkTypes::Init {
+                    SctpChunk::Init(init)
+                } else {
+                    SctpChunk::InitAck(init)
+                }
+            }
+            SctpChunkTypes::Sack => {
+                ensure!(
+                    value.len() >= SackChunk::FIXED_LEN,
+                    ParseError::new("Invalid SACK chunk length.")
+                );
+                let sack = SackChunk { value };
+                ensure!(
+                    value.len()
+                        == SackChunk::FIXED_LEN
+                            + (sack.num_gap_blocks() + sack.num_duplicate_tsns()) * 4,
+                    ParseError::new("Invalid SACK chunk length.")
+                );
+                SctpChunk::Sack(sack)
+            }
+            SctpChunkTypes::Heartbeat => SctpChunk::Heartbeat(value),
+            SctpChunkTypes::HeartbeatAck => SctpChunk::HeartbeatAck(value),
+            SctpChunkTypes::Abort => SctpChunk::Abort {
+                tag_reflected: flags & 0x01 > 0,
+                causes: value,
+            },
+            SctpChunkTypes::Shutdown => {
+                ensure!(
+                    value.len() == 4,
+                    ParseError::new("Invalid SHUTDOWN chunk length.")
+                );
+                SctpChunk::Shutdown(read_u32(value, 0))
+            }
+            SctpChunkTypes::ShutdownAck => SctpChunk::ShutdownAck,
+            SctpChunkTypes::Error => SctpChunk::Error(value),
+            SctpChunkTypes::CookieEcho => SctpChunk::CookieEcho(value),
+            SctpChunkTypes::CookieAck => SctpChunk::CookieAck,
+            SctpChunkTypes::ShutdownComplete => SctpChunk::ShutdownComplete {
+                tag_reflected: flags & 0x01 > 0,
+            },
+            _ => SctpChunk::Unknown(chunk_type, flags, value),
+        };
+
+        Ok(Some(chunk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_type_to_string() {
+        assert_eq!("DATA", SctpChunkTypes::Data.to_string());
+        assert_eq!("INIT ACK", SctpChunkTypes::InitAck.to_string());
+        assert_eq!(
+            "SHUTDOWN COMPLETE",
+            SctpChunkTypes::ShutdownComplete.to_string()
+        );
+        assert_eq!("200", SctpChunkType(200).to_string());
+    }
+
+    #[test]
+    fn iterate_init_and_heartbeat_chunks() {
+        #[rustfmt::skip]
+        let data = [
+            // INIT, len = 20
+            0x01, 0x00, 0x00, 0x14,
+            0x1a, 0x2b, 0x3c, 0x4d,
+            0x00, 0x01, 0x00, 0x00,
+            0x00, 0x0a, 0xff, 0xff,
+            0x00, 0x00, 0x00, 0x64,
+            // HEARTBEAT, len = 6, padded to 8
+            0x04, 0x00, 0x00, 0x06,
+            0xab, 0xcd, 0x00, 0x00,
+            // ABORT with T bit, len = 4
+            0x06, 0x01, 0x00, 0x04,
+            // unknown, len = 5 without the padding
+            0xc0, 0x02, 0x00, 0x05,
+            0x99,
+        ];
+
+        let mut iter = SctpChunksIterator::new(&data);
+
+        match iter.next().unwrap() {
+            Some(SctpChunk::Init(init)) => {
+                assert_eq!(0x1a2b_3c4d, init.initiate_tag());
+                assert_eq!(65536, init.a_rwnd());
+                assert_eq!(10, init.outbound_streams());
+                assert_eq!(65535, init.inbound_streams());
+                assert_eq!(100, init.initial_tsn());
+                assert!(init.parameters().is_empty());
+            }
+            _ => panic!("not an INIT chunk."),
+        }
+
+        assert_eq!(
+            Some(SctpChunk::Heartbeat(&[0xab, 0xcd])),
+            iter.next().unwrap()
+        );
+        assert_eq!(
+            Some(SctpChunk::Abort {
+                tag_reflected: true,
+                causes: &[]
+            }),
+            iter.next().unwrap()
+        );
+
+        let chunk = iter.next().unwrap().unwrap();
+        assert_eq!(SctpChunkType(0xc0), chunk.chunk_type());
+        assert_eq!(
+            SctpChunk::Unknown(SctpChunkType(0xc0), 0x02, &[0x99]),
+            chunk
+        );
+
+        assert_eq!(None, iter.next().unwrap());
+    }
+
+    #[test]
+    fn iterate_invalid_chunks() {
+        // length runs past the end
+        let mut iter = SctpChunksIterator::new(&[0x04, 0x00, 0x00, 0x08, 0x00]);
+        assert!(iter.next().is_err());
+
+        // length less than the chunk header
+        let mut iter = SctpChunksIterator::new(&[0x0b, 0x00, 0x00, 0x02]);
+        assert!(iter.next().is_err());
+
+        // DATA without the fixed fields
+        let mut iter = SctpChunksIterator::new(&[0x00, 0x03, 0x00, 0x08, 0, 0, 0, 1]);
+        assert!(iter.next().is_err());
+
+        // SACK claims more gap blocks than it has
+        #[rustfmt::skip]
+        let mut iter = SctpChunksIterator::new(&[
+            0x03, 0x00, 0x00, 0x10,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0xff, 0xff,
+            0x00, 0x02, 0x00, 0x00,
+        ]);
+        assert!(iter.next().is_err());
+    }
+}
@@ -0,0 +1,462 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Stream Control Transmission Protocol and chunks.
+
+mod chunks;
+
+pub use self::chunks::*;
+
+use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{checksum, Internal, Packet, ParseError};
+use crate::{ensure, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Stream Control Transmission Protocol packet based on [IETF RFC 4960].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Source Port Number        |     Destination Port Number   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Verification Tag                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Checksum                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        Chunk #1 ...                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Source Port Number*: (16 bits)
+///      This is the SCTP sender's port number.
+///
+/// - *Destination Port Number*: (16 bits)
+///      This is the SCTP port number to which this packet is destined.
+///
+/// - *Verification Tag*: (32 bits)
+///      The receiver of this packet uses the Verification Tag to validate
+///      the sender of this SCTP packet.
+///
+/// - *Checksum*: (32 bits)
+///      This field contains the CRC32c checksum of this SCTP packet. Unlike
+///      TCP and UDP, the checksum does not cover a pseudo-header.
+///
+/// - *Chunks*: (variable)
+///      One or more chunks of control or data information. Use
+///      [`chunks_iter`] to read the chunks.
+///
+/// [IETF RFC 4960]: https://tools.ietf.org/html/rfc4960#section-3.1
+/// [`chunks_iter`]: Sctp::chunks_iter
+pub struct Sctp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<SctpHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Sctp<E> {
+    #[inline]
+    fn header(&self) -> &SctpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut SctpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the source port.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        self.header().src_port.into()
+    }
+
+    /// Sets the source port.
+    #[inline]
+    pub fn set_src_port(&mut self, src_port: u16) {
+        self.header_mut().src_port = src_port.into();
+    }
+
+    /// Returns the destination port.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        self.header().dst_port.into()
+    }
+
+    /// Sets the destination port.
+    #[inline]
+    pub fn set_dst_port(&mut self, dst_port: u16) {
+        self.header_mut().dst_port = dst_port.into();
+    }
+
+    /// Returns the verification tag.
+    #[inline]
+    pub fn verification_tag(&self) -> u32 {
+        self.header().verification_tag.into()
+    }
+
+    /// Sets the verification tag.
+    #[inline]
+    pub fn set_verification_tag(&mut self, verification_tag: u32) {
+        self.header_mut().verification_tag = verification_tag.into();
+    }
+
+    /// Returns the CRC32c checksum.
+    ///
+    /// The checksum is transmitted in the reflected bit order of CRC32c,
+    /// which puts the least significant byte first on the wire.
+    #[inline]
+    pub fn checksum(&self) -> u32 {
+        u32::from_le(self.header().checksum)
+    }
+
+    #[inline]
+    fn set_checksum(&mut self, checksum: u32) {
+        self.header_mut().checksum = checksum.to_le();
+    }
+
+    /// Returns the 5-tuple that uniquely identifies an SCTP association.
+    #[inline]
+    pub fn flow(&self) -> Flow {
+        Flow::new(
+            self.envelope().src(),
+            self.envelope().dst(),
+            self.src_port(),
+            self.dst_port(),
+            ProtocolNumbers::Sctp,
+        )
+    }
+
+    /// Returns the encoded chunks following the common header.
+    #[inline]
+    pub fn chunks(&self) -> &[u8] {
+        let len = self.payload_len();
+        if len == 0 {
+            return &[];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<u8>(self.payload_offset(), len)
+        {
+            Ok(chunks) => unsafe { chunks.as_ref() },
+            // we are reading till the end of buffer, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the chunks in the packet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let sctp = ipv4.parse::<Sctp<Ipv4>>()?;
+    /// let mut iter = sctp.chunks_iter();
+    ///
+    /// while let Some(chunk) = iter.next()? {
+    ///     println!("{:?}", chunk);
+    /// }
+    /// ```
+    #[inline]
+    pub fn chunks_iter(&self) -> SctpChunksIterator<'_> {
+        SctpChunksIterator::new(self.chunks())
+    }
+
+    /// Computes the CRC32c checksum over the full packet, with the
+    /// checksum field itself set to zero.
+    fn compute_checksum(&self) -> u32 {
+        match self.mbuf().read_data_slice::<u8>(self.offset, self.len()) {
+            Ok(data) => {
+                let data = unsafe { data.as_ref() };
+                let mut header = [0u8; 12];
+                header[..8].copy_from_slice(&data[..8]);
+                let crc = checksum::crc32c(&header);
+                checksum::crc32c_update(crc, &data[SctpHeader::size_of()..])
+            }
+            // we are reading till the end of buffer, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns whether the checksum matches the packet content.
+    #[inline]
+    pub fn verify_checksum(&self) -> bool {
+        self.checksum() == self.compute_checksum()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Sctp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("sctp")
+            .field("src_port", &self.src_port())
+            .field("dst_port", &self.dst_port())
+            .field(
+                "verification_tag",
+                &format!("0x{:08x}", self.verification_tag()),
+            )
+            .field("checksum", &format!("0x{:08x}", self.checksum()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Sctp<E> {
+    /// The preceding packet type for an SCTP packet can be either an [IPv4]
+    /// packet, an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        SctpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Sctp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an SCTP packet.
+    ///
+    /// If the envelope is IPv4, then [`Ipv4::protocol`] must be set to
+    /// [`ProtocolNumbers::Sctp`]. If the envelope is IPv6 or an extension
+    /// header, then [`next_header`] must be set to `ProtocolNumbers::Sctp`.
+    /// Otherwise, a parsing error is returned.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Sctp`]: crate::packets::ip::ProtocolNumbers::Sctp
+    /// [`next_header`]: crate::packets::ip::v6::Ipv6Packet::next_header
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Sctp,
+            ParseError::new("not an SCTP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an SCTP packet to the beginning of the envelope's payload.
+    ///
+    /// If the envelope is IPv4, then [`Ipv4::protocol`] is set to
+    /// [`ProtocolNumbers::Sctp`]. If the envelope is IPv6 or an extension
+    /// header, then [`next_header`] is set to `ProtocolNumbers::Sctp`.
+    ///
+    /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
+    /// [`ProtocolNumbers::Sctp`]: crate::packets::ip::ProtocolNumbers::Sctp
+    /// [`next_header`]: crate::packets::ip::v6::Ipv6Packet::next_header
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, SctpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &SctpHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Sctp);
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed over the full packet using CRC32c.
+    ///
+    /// [`checksum`]: Sctp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        let checksum = self.compute_checksum();
+        self.set_checksum(checksum);
+    }
+}
+
+/// SCTP common header.
+///
+/// The checksum is kept in wire order and converted by the accessor.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C)]
+struct SctpHeader {
+    src_port: u16be,
+    dst_port: u16be,
+    verification_tag: u32be,
+    checksum: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_SCTP_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_sctp_header() {
+        assert_eq!(12, SctpHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_sctp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+
+        assert_eq!(5000, sctp.src_port());
+        assert_eq!(36412, sctp.dst_port());
+        assert_eq!(0x1a2b_3c4d, sctp.verification_tag());
+        assert_eq!(0x7499_d955, sctp.checksum());
+        assert_eq!(48, sctp.chunks().len());
+    }
+
+    #[capsule::test]
+    fn parse_non_sctp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Sctp<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn sctp_flow_v4() {
+        let packet = Mbuf::from_bytes(&IPV4_SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+        let flow = sctp.flow();
+
+        assert_eq!("10.0.0.1", flow.src_ip().to_string());
+        assert_eq!("10.0.0.2", flow.dst_ip().to_string());
+        assert_eq!(5000, flow.src_port());
+        assert_eq!(36412, flow.dst_port());
+        assert_eq!(ProtocolNumbers::Sctp, flow.protocol());
+    }
+
+    #[capsule::test]
+    fn iterate_sctp_chunks() {
+        let packet = Mbuf::from_bytes(&IPV4_SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+        let mut iter = sctp.chunks_iter();
+
+        match iter.next().unwrap() {
+            Some(SctpChunk::Data(data)) => {
+                assert!(!data.unordered());
+                assert!(data.beginning());
+                assert!(data.ending());
+                assert_eq!(1, data.tsn());
+                assert_eq!(0, data.stream_id());
+                assert_eq!(0, data.stream_seq());
+                assert_eq!(18, data.ppid());
+                assert_eq!(b"hello", data.user_data());
+            }
+            _ => panic!("not a DATA chunk."),
+        }
+
+        match iter.next().unwrap() {
+            Some(SctpChunk::Sack(sack)) => {
+                assert_eq!(17, sack.cumulative_tsn_ack());
+                assert_eq!(65536, sack.a_rwnd());
+                assert_eq!(vec![(2, 3)], sack.gap_blocks());
+                assert_eq!(vec![9], sack.duplicate_tsns());
+            }
+            _ => panic!("not a SACK chunk."),
+        }
+
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn compute_checksum() {
+        let packet = Mbuf::from_bytes(&IPV4_SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut sctp = ipv4.parse::<Sctp<Ipv4>>().unwrap();
+
+        assert!(sctp.verify_checksum());
+
+        sctp.set_verification_tag(0);
+        assert!(!sctp.verify_checksum());
+
+        sctp.reconcile_all();
+        assert!(sctp.verify_checksum());
+
+        sctp.set_verification_tag(0x1a2b_3c4d);
+        sctp.reconcile_all();
+        assert_eq!(0x7499_d955, sctp.checksum());
+    }
+
+    #[capsule::test]
+    fn push_sctp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let sctp = ipv4.push::<Sctp<Ipv4>>().unwrap();
+
+        assert_eq!(SctpHeader::size_of(), sctp.len());
+
+        // make sure the next protocol is fixed
+        assert_eq!(ProtocolNumbers::Sctp, sctp.envelope().next_protocol());
+    }
+}
@@ -16,6 +16,13 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
+//! Transmission Control Protocol and options.
+
+mod options;
+
+pub use self::options::*;
+
+use crate::dpdk::BufferError;
 use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
 use crate::packets::types::{u16be, u32be};
 use crate::packets::{checksum, Internal, Packet, ParseError};
@@ -110,11 +117,17 @@ const FIN: u8 = 0b0000_0001;
 /// - *Options*: (variable)
 ///     Options may occupy space at the end of the TCP header and are a
 ///     multiple of 8 bits in length.  All options are included in the
-///     checksum.
+///     checksum. Use [`options_iter`] to read the options, and
+///     [`add_option`], [`retain_options`] or [`strip_options`] to modify
+///     them.
 ///
 /// [IETF RFC 793]: https://tools.ietf.org/html/rfc793#section-3.1
 /// [IETF RFC 3540]: https://tools.ietf.org/html/rfc3540
 /// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168
+/// [`options_iter`]: Tcp::options_iter
+/// [`add_option`]: Tcp::add_option
+/// [`retain_options`]: Tcp::retain_options
+/// [`strip_options`]: Tcp::strip_options
 pub struct Tcp<E: IpPacket> {
     envelope: E,
     header: NonNull<TcpHeader>,
@@ -187,8 +200,6 @@ impl<E: IpPacket> Tcp<E> {
         (self.header().offset_to_ns & 0xf0) >> 4
     }
 
-    // TODO: support tcp header options.
-    #[allow(dead_code)]
     #[inline]
     fn set_data_offset(&mut self, data_offset: u8) {
         self.header_mut().offset_to_ns = (self.header().offset_to_ns & 0x0f) | (data_offset << 4);
@@ -442,6 +453,234 @@ impl<E: IpPacket> Tcp<E> {
         Ok(())
     }
 
+    /// Returns the raw options, including any padding.
+    #[inline]
+    pub fn options(&self) -> &[u8] {
+        let len = self.header_len() - TcpHeader::size_of();
+        if len == 0 {
+            return &[];
+        }
+
+        match self
+            .mbuf()
+            .read_data_slice::<u8>(self.offset + TcpHeader::size_of(), len)
+        {
+            Ok(options) => unsafe { options.as_ref() },
+            // the header length is checked when parsed, should never run out
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator to read the options in the header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let tcp = ipv4.parse::<Tcp<Ipv4>>()?;
+    /// let mut iter = tcp.options_iter();
+    ///
+    /// while let Some(option) = iter.next()? {
+    ///     println!("{:?}", option);
+    /// }
+    /// ```
+    #[inline]
+    pub fn options_iter(&self) -> TcpOptionsIterator<'_> {
+        TcpOptionsIterator::new(self.options())
+    }
+
+    /// Returns the offset of the first option of `kind` in the packet.
+    fn find_option(&self, kind: TcpOptionKind) -> Fallible<Option<usize>> {
+        let mut iter = self.options_iter();
+        let mut offset = self.offset + TcpHeader::size_of();
+        while let Some(option) = iter.next()? {
+            if option.kind() == kind {
+                return Ok(Some(offset));
+            }
+            offset += option.length();
+        }
+        Ok(None)
+    }
+
+    /// Returns the maximum segment size if the option is present.
+    pub fn mss(&self) -> Fallible<Option<u16>> {
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if let TcpOption::MaxSegmentSize(mss) = option {
+                return Ok(Some(mss));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sets the maximum segment size.
+    ///
+    /// An existing option is rewritten in place, otherwise the option is
+    /// added. The checksum is recomputed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // clamps the MSS of SYN packets
+    /// if tcp.syn() {
+    ///     if let Some(mss) = tcp.mss()? {
+    ///         if mss > 1400 {
+    ///             tcp.set_mss(1400)?;
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn set_mss(&mut self, mss: u16) -> Fallible<()> {
+        match self.find_option(TcpOptionKinds::MaxSegmentSize)? {
+            Some(offset) => {
+                self.mbuf_mut()
+                    .write_data_slice(offset + 2, &mss.to_be_bytes())?;
+                self.compute_checksum();
+                Ok(())
+            }
+            None => self.add_option(TcpOption::MaxSegmentSize(mss)),
+        }
+    }
+
+    /// Returns the timestamp value and the echo reply if the option is
+    /// present.
+    pub fn timestamps(&self) -> Fallible<Option<(u32, u32)>> {
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if let TcpOption::Timestamps { value, echo_reply } = option {
+                return Ok(Some((value, echo_reply)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sets the timestamp value and the echo reply.
+    ///
+    /// An existing option is rewritten in place, otherwise the option is
+    /// added. The checksum is recomputed.
+    pub fn set_timestamps(&mut self, value: u32, echo_reply: u32) -> Fallible<()> {
+        match self.find_option(TcpOptionKinds::Timestamps)? {
+            Some(offset) => {
+                let mbuf = self.mbuf_mut();
+                mbuf.write_data_slice(offset + 2, &value.to_be_bytes())?;
+                mbuf.write_data_slice(offset + 6, &echo_reply.to_be_bytes())?;
+                self.compute_checksum();
+                Ok(())
+            }
+            None => self.add_option(TcpOption::Timestamps { value, echo_reply }),
+        }
+    }
+
+    /// Replaces the options with the encoded bytes. The options are padded
+    /// to a 32-bit boundary, the payload is moved accordingly, the data
+    /// offset is updated and the checksum is recomputed.
+    fn set_options_bytes(&mut self, bytes: &[u8]) -> Fallible<()> {
+        let new_len = (bytes.len() + 3) / 4 * 4;
+        ensure!(
+            new_len <= TCP_MAX_OPTIONS_LEN,
+            TcpOptionsError::TooLong(bytes.len())
+        );
+
+        let old_len = self.header_len() - TcpHeader::size_of();
+        let offset = self.offset + TcpHeader::size_of();
+        let mbuf = self.mbuf_mut();
+
+        if new_len > old_len {
+            mbuf.extend(offset + old_len, new_len - old_len)?;
+        } else if new_len < old_len {
+            mbuf.shrink(offset + new_len, old_len - new_len)?;
+        }
+
+        if !bytes.is_empty() {
+            mbuf.write_data_slice(offset, bytes)?;
+        }
+        // pads with end of option list.
+        let padding = new_len - bytes.len();
+        if padding > 0 {
+            mbuf.write_data_slice(offset + bytes.len(), &[0u8; 3][..padding])?;
+        }
+
+        self.set_data_offset(((TcpHeader::size_of() + new_len) / 4) as u8);
+        self.compute_checksum();
+        Ok(())
+    }
+
+    /// Replaces the options in the header.
+    ///
+    /// The header is resized, `data_offset` is updated and the checksum is
+    /// recomputed. Call [`reconcile_all`] afterwards to fix the lengths of
+    /// the envelope.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn set_options(&mut self, options: &[TcpOption<'_>]) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(TCP_MAX_OPTIONS_LEN);
+        for option in options {
+            option.encode(&mut bytes)?;
+        }
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Adds an option after the existing options.
+    ///
+    /// The header is resized, `data_offset` is updated and the checksum is
+    /// recomputed. Call [`reconcile_all`] afterwards to fix the lengths of
+    /// the envelope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut tcp = ipv4.parse::<Tcp<Ipv4>>()?;
+    /// tcp.add_option(TcpOption::WindowScale(7))?;
+    /// tcp.reconcile_all();
+    /// ```
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn add_option(&mut self, option: TcpOption<'_>) -> Fallible<()> {
+        let mut bytes = Vec::with_capacity(TCP_MAX_OPTIONS_LEN);
+        let mut iter = self.options_iter();
+        while let Some(existing) = iter.next()? {
+            // drops the end of list, a new one is added as padding
+            if existing != TcpOption::EndOfList {
+                existing.encode(&mut bytes)?;
+            }
+        }
+        option.encode(&mut bytes)?;
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Retains only the options specified by the predicate.
+    ///
+    /// In other words, remove all options `o` such that `f(o)` returns false.
+    /// The header is resized, `data_offset` is updated and the checksum is
+    /// recomputed. Call [`reconcile_all`] afterwards to fix the lengths of
+    /// the envelope.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    pub fn retain_options<F>(&mut self, mut f: F) -> Fallible<()>
+    where
+        F: FnMut(&TcpOption<'_>) -> bool,
+    {
+        let mut bytes = Vec::with_capacity(TCP_MAX_OPTIONS_LEN);
+        let mut iter = self.options_iter();
+        while let Some(option) = iter.next()? {
+            if option != TcpOption::EndOfList && f(&option) {
+                option.encode(&mut bytes)?;
+            }
+        }
+        self.set_options_bytes(&bytes)
+    }
+
+    /// Removes all the options.
+    ///
+    /// The header is resized, `data_offset` is set to `5` and the checksum
+    /// is recomputed. Call [`reconcile_all`] afterwards to fix the lengths
+    /// of the envelope.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn strip_options(&mut self) -> Fallible<()> {
+        self.set_options_bytes(&[])
+    }
+
     #[inline]
     fn compute_checksum(&mut self) {
         self.set_checksum(0);
@@ -511,9 +750,10 @@ impl<E: IpPacket> Packet for Tcp<E> {
         self.offset
     }
 
+    /// Returns the length of the TCP header, including the options.
     #[inline]
     fn header_len(&self) -> usize {
-        TcpHeader::size_of()
+        self.data_offset() as usize * 4
     }
 
     #[inline]
@@ -546,11 +786,23 @@ impl<E: IpPacket> Packet for Tcp<E> {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Tcp {
+        let packet = Tcp {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header_len() >= TcpHeader::size_of(),
+            ParseError::new("Invalid TCP data offset.")
+        );
+
+        ensure!(
+            packet.len() >= packet.header_len(),
+            BufferError::OutOfBuffer(packet.header_len(), packet.len())
+        );
+
+        Ok(packet)
     }
 
     /// Prepends a TCP packet to the beginning of the envelope's payload.
@@ -762,4 +1014,104 @@ mod tests {
         // make sure the next protocol is fixed
         assert_eq!(ProtocolNumbers::Tcp, tcp.envelope().next_protocol());
     }
+
+    #[capsule::test]
+    fn parse_tcp_options() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp<Ipv4>>().unwrap();
+
+        assert_eq!(24, tcp.header_len());
+        assert_eq!(0, tcp.payload_len());
+        assert_eq!(&[0x02, 0x04, 0x05, 0xb4], tcp.options());
+
+        let mut iter = tcp.options_iter();
+        assert_eq!(
+            TcpOption::MaxSegmentSize(1460),
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().unwrap().is_none());
+
+        assert_eq!(Some(1460), tcp.mss().unwrap());
+        assert_eq!(None, tcp.timestamps().unwrap());
+    }
+
+    #[capsule::test]
+    fn parse_invalid_data_offset() {
+        let mut bytes = IPV4_TCP_PACKET;
+        // data offset = 4
+        bytes[46] = 0x40;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.parse::<Tcp<Ipv4>>().is_err());
+
+        // data offset = 7, runs past the end of the buffer
+        bytes[46] = 0x70;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.parse::<Tcp<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn clamp_mss() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp<Ipv4>>().unwrap();
+
+        let old_checksum = tcp.checksum();
+        assert!(tcp.set_mss(1400).is_ok());
+        assert_eq!(Some(1400), tcp.mss().unwrap());
+        assert_eq!(24, tcp.header_len());
+        assert!(tcp.checksum() != old_checksum);
+
+        let expected = tcp.checksum();
+        tcp.reconcile_all();
+        assert_eq!(expected, tcp.checksum());
+    }
+
+    #[capsule::test]
+    fn add_and_strip_tcp_options() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp<Ipv4>>().unwrap();
+
+        // not present, the option is added
+        assert!(tcp.set_timestamps(1, 0).is_ok());
+        assert_eq!(9, tcp.data_offset());
+        assert_eq!(36, tcp.len());
+        assert_eq!(Some((1, 0)), tcp.timestamps().unwrap());
+
+        // present, the option is rewritten in place
+        assert!(tcp.set_timestamps(2, 1).is_ok());
+        assert_eq!(9, tcp.data_offset());
+        assert_eq!(Some((2, 1)), tcp.timestamps().unwrap());
+
+        tcp.reconcile_all();
+        assert_eq!(56, tcp.envelope().total_length());
+
+        let large = [0; 30];
+        assert!(tcp
+            .add_option(TcpOption::Unknown(TcpOptionKind(30), &large))
+            .is_err());
+
+        assert!(tcp
+            .retain_options(|option| option.kind() == TcpOptionKinds::MaxSegmentSize)
+            .is_ok());
+        assert_eq!(6, tcp.data_offset());
+        assert_eq!(&[0x02, 0x04, 0x05, 0xb4], tcp.options());
+
+        assert!(tcp.strip_options().is_ok());
+        assert_eq!(5, tcp.data_offset());
+        assert_eq!(20, tcp.len());
+
+        let expected = tcp.checksum();
+        tcp.reconcile_all();
+        assert_eq!(40, tcp.envelope().total_length());
+        assert_eq!(expected, tcp.checksum());
+    }
 }
@@ -0,0 +1,437 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::ensure;
+use crate::packets::ParseError;
+use failure::{Fail, Fallible};
+use std::fmt;
+
+/// The maximum length of the TCP options in octets.
+///
+/// The data offset field is 4 bits, so the header is at most 60 octets,
+/// of which 20 are the fixed portion.
+pub const TCP_MAX_OPTIONS_LEN: usize = 40;
+
+/// [IANA] assigned TCP option kind.
+///
+/// A list of supported kinds is under [`TcpOptionKinds`].
+///
+/// [IANA]: https://www.iana.org/assignments/tcp-parameters/tcp-parameters.xhtml#tcp-parameters-1
+/// [`TcpOptionKinds`]: TcpOptionKinds
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct TcpOptionKind(pub u8);
+
+/// Supported TCP option kinds.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod TcpOptionKinds {
+    use super::TcpOptionKind;
+
+    /// End of Option List.
+    pub const EndOfList: TcpOptionKind = TcpOptionKind(0);
+
+    /// No Operation.
+    pub const NoOperation: TcpOptionKind = TcpOptionKind(1);
+
+    /// Maximum Segment Size.
+    pub const MaxSegmentSize: TcpOptionKind = TcpOptionKind(2);
+
+    /// Window Scale.
+    pub const WindowScale: TcpOptionKind = TcpOptionKind(3);
+
+    /// SACK Permitted.
+    pub const SackPermitted: TcpOptionKind = TcpOptionKind(4);
+
+    /// SACK.
+    pub const Sack: TcpOptionKind = TcpOptionKind(5);
+
+    /// Timestamps.
+    pub const Timestamps: TcpOptionKind = TcpOptionKind(8);
+}
+
+impl fmt::Display for TcpOptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                TcpOptionKinds::EndOfList => "End of Option List".to_string(),
+                TcpOptionKinds::NoOperation => "No Operation".to_string(),
+                TcpOptionKinds::MaxSegmentSize => "Maximum Segment Size".to_string(),
+                TcpOptionKinds::WindowScale => "Window Scale".to_string(),
+                TcpOptionKinds::SackPermitted => "SACK Permitted".to_string(),
+                TcpOptionKinds::Sack => "SACK".to_string(),
+                TcpOptionKinds::Timestamps => "Timestamps".to_string(),
+                _ => format!("{}", self.0),
+            }
+        )
+    }
+}
+
+/// A TCP option based on [IETF RFC 793].
+///
+/// Except for the single octet end of option list and no operation
+/// options, all options are encoded as a kind octet, a length octet
+/// that includes the kind and length octets, and the option data.
+///
+/// [IETF RFC 793]: https://tools.ietf.org/html/rfc793#section-3.1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TcpOption<'a> {
+    /// End of option list. Marks the end of all options, the remaining
+    /// octets of the header are padding.
+    EndOfList,
+
+    /// No operation. Used between options to align the next option.
+    NoOperation,
+
+    /// Maximum segment size the sender can receive. Only sent in the
+    /// initial connection request.
+    MaxSegmentSize(u16),
+
+    /// Window scale shift count, defined in [IETF RFC 7323].
+    ///
+    /// [IETF RFC 7323]: https://tools.ietf.org/html/rfc7323#section-2
+    WindowScale(u8),
+
+    /// SACK permitted, defined in [IETF RFC 2018].
+    ///
+    /// [IETF RFC 2018]: https://tools.ietf.org/html/rfc2018#section-2
+    SackPermitted,
+
+    /// SACK with the blocks of noncontiguous data received, defined in
+    /// [IETF RFC 2018].
+    ///
+    /// [IETF RFC 2018]: https://tools.ietf.org/html/rfc2018#section-3
+    Sack(SackOption<'a>),
+
+    /// Timestamps, defined in [IETF RFC 7323].
+    ///
+    /// [IETF RFC 7323]: https://tools.ietf.org/html/rfc7323#section-3
+    Timestamps {
+        /// The current value of the sender's timestamp clock.
+        value: u32,
+        /// The timestamp value echoed from the remote TCP.
+        echo_reply: u32,
+    },
+
+    /// Any other option, with its kind and data.
+    Unknown(TcpOptionKind, &'a [u8]),
+}
+
+impl TcpOption<'_> {
+    /// Returns the option kind.
+    pub fn kind(&self) -> TcpOptionKind {
+        match self {
+            TcpOption::EndOfList => TcpOptionKinds::EndOfList,
+            TcpOption::NoOperation => TcpOptionKinds::NoOperation,
+            TcpOption::MaxSegmentSize(_) => TcpOptionKinds::MaxSegmentSize,
+            TcpOption::WindowScale(_) => TcpOptionKinds::WindowScale,
+            TcpOption::SackPermitted => TcpOptionKinds::SackPermitted,
+            TcpOption::Sack(_) => TcpOptionKinds::Sack,
+            TcpOption::Timestamps { .. } => TcpOptionKinds::Timestamps,
+            TcpOption::Unknown(kind, _) => *kind,
+        }
+    }
+
+    /// Returns the length of the encoded option in octets.
+    pub fn length(&self) -> usize {
+        match self {
+            TcpOption::EndOfList | TcpOption::NoOperation => 1,
+            TcpOption::MaxSegmentSize(_) => 4,
+            TcpOption::WindowScale(_) => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack(sack) => 2 + sack.data.len(),
+            TcpOption::Timestamps { .. } => 10,
+            TcpOption::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    /// Encodes the option and appends it to the buffer.
+    pub(crate) fn encode(&self, buffer: &mut Vec<u8>) -> Fallible<()> {
+        let len = self.length();
+        ensure!(len <= TCP_MAX_OPTIONS_LEN, TcpOptionsError::TooLong(len));
+
+        buffer.push(self.kind().0);
+        match self {
+            TcpOption::EndOfList | TcpOption::NoOperation => (),
+            TcpOption::MaxSegmentSize(mss) => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(&mss.to_be_bytes());
+            }
+            TcpOption::WindowScale(shift) => {
+                buffer.push(len as u8);
+                buffer.push(*shift);
+            }
+            TcpOption::SackPermitted => buffer.push(len as u8),
+            TcpOption::Sack(sack) => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(sack.data);
+            }
+            TcpOption::Timestamps { value, echo_reply } => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(&value.to_be_bytes());
+                buffer.extend_from_slice(&echo_reply.to_be_bytes());
+            }
+            TcpOption::Unknown(_, data) => {
+                buffer.push(len as u8);
+                buffer.extend_from_slice(data);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The SACK option.
+///
+/// ```
+///                   +--------+--------+
+///                   | Kind=5 | Length |
+/// +--------+--------+--------+--------+
+/// |      Left Edge of 1st Block       |
+/// +--------+--------+--------+--------+
+/// |      Right Edge of 1st Block      |
+/// +--------+--------+--------+--------+
+/// |                                   |
+/// /            . . .                  /
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |      Left Edge of nth Block       |
+/// +--------+--------+--------+--------+
+/// |      Right Edge of nth Block      |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SackOption<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SackOption<'a> {
+    /// Creates a new SACK option with the encoded blocks. Each block is
+    /// 8 octets, the left edge followed by the right edge.
+    pub fn new(data: &'a [u8]) -> Self {
+        SackOption { data }
+    }
+
+    /// Returns the encoded blocks.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the blocks as pairs of left and right edges.
+    pub fn blocks(&self) -> Vec<(u32, u32)> {
+        self.data
+            .chunks_exact(8)
+            .map(|block| {
+                (
+                    u32::from_be_bytes([block[0], block[1], block[2], block[3]]),
+                    u32::from_be_bytes([block[4], block[5], block[6], block[7]]),
+                )
+            })
+            .collect()
+    }
+}
+
+/// An iterator that iterates through the options in the TCP header.
+///
+/// Iteration stops after the end of option list option.
+#[derive(Debug)]
+pub struct TcpOptionsIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TcpOptionsIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        TcpOptionsIterator { data }
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Fallible<Option<TcpOption<'a>>> {
+        let data = self.data;
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let kind = TcpOptionKind(data[0]);
+        match kind {
+            TcpOptionKinds::EndOfList => {
+                // the rest is padding
+                self.data = &[];
+                return Ok(Some(TcpOption::EndOfList));
+            }
+            TcpOptionKinds::NoOperation => {
+                self.data = &data[1..];
+                return Ok(Some(TcpOption::NoOperation));
+            }
+            _ => (),
+        }
+
+        ensure!(
+            data.len() >= 2 && data[1] >= 2 && data[1] as usize <= data.len(),
+            ParseError::new("Invalid TCP option length.")
+        );
+
+        let len = data[1] as usize;
+        let value = &data[2..len];
+        self.data = &data[len..];
+
+        let option = match kind {
+            TcpOptionKinds::MaxSegmentSize => {
+                ensure!(len == 4, ParseError::new("Invalid MSS option length."));
+                TcpOption::MaxSegmentSize(u16::from_be_bytes([value[0], value[1]]))
+            }
+            TcpOptionKinds::WindowScale => {
+                ensure!(
+                    len == 3,
+                    ParseError::new("Invalid window scale option length.")
+                );
+                TcpOption::WindowScale(value[0])
+            }
+            TcpOptionKinds::SackPermitted => {
+                ensure!(
+                    len == 2,
+                    ParseError::new("Invalid SACK permitted option length.")
+                );
+                TcpOption::SackPermitted
+            }
+            TcpOptionKinds::Sack => {
+                ensure!(
+                    value.len() % 8 == 0,
+                    ParseError::new("Invalid SACK option length.")
+                );
+                TcpOption::Sack(SackOption::new(value))
+            }
+            TcpOptionKinds::Timestamps => {
+                ensure!(
+                    len == 10,
+                    ParseError::new("Invalid timestamps option length.")
+                );
+                TcpOption::Timestamps {
+                    value: u32::from_be_bytes([value[0], value[1], value[2], value[3]]),
+                    echo_reply: u32::from_be_bytes([value[4], value[5], value[6], value[7]]),
+                }
+            }
+            _ => TcpOption::Unknown(kind, value),
+        };
+
+        Ok(Some(option))
+    }
+}
+
+/// TCP options related errors.
+#[derive(Debug, Fail)]
+pub enum TcpOptionsError {
+    /// Error indicating the options exceed the maximum length.
+    #[fail(display = "Options length {} exceeds the maximum of 40.", _0)]
+    TooLong(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_kind_to_string() {
+        assert_eq!("Window Scale", TcpOptionKinds::WindowScale.to_string());
+        assert_eq!("30", TcpOptionKind(30).to_string());
+    }
+
+    #[test]
+    fn iterate_tcp_options() {
+        let data = [
+            0x02, 0x04, 0x05, 0xb4, 0x04, 0x02, 0x08, 0x0a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x03, 0x03, 0x07, 0x05, 0x0a, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x20, 0x1e, 0x02, 0x00, 0x00,
+        ];
+        let mut iter = TcpOptionsIterator::new(&data);
+
+        assert_eq!(
+            TcpOption::MaxSegmentSize(1460),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(TcpOption::SackPermitted, iter.next().unwrap().unwrap());
+        assert_eq!(
+            TcpOption::Timestamps {
+                value: 1,
+                echo_reply: 0
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(TcpOption::NoOperation, iter.next().unwrap().unwrap());
+        assert_eq!(TcpOption::WindowScale(7), iter.next().unwrap().unwrap());
+
+        match iter.next().unwrap().unwrap() {
+            TcpOption::Sack(sack) => assert_eq!(vec![(0x10, 0x20)], sack.blocks()),
+            _ => panic!("not a SACK option."),
+        }
+
+        assert_eq!(
+            TcpOption::Unknown(TcpOptionKind(0x1e), &[]),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(TcpOption::EndOfList, iter.next().unwrap().unwrap());
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_tcp_option_length() {
+        // length runs past the end of the options
+        let data = [0x08, 0x0a, 0x00, 0x00];
+        assert!(TcpOptionsIterator::new(&data).next().is_err());
+
+        // MSS with the wrong length
+        let data = [0x02, 0x03, 0x05, 0x00];
+        assert!(TcpOptionsIterator::new(&data).next().is_err());
+
+        // length shorter than the kind and length octets
+        let data = [0x1e, 0x01, 0x00, 0x00];
+        assert!(TcpOptionsIterator::new(&data).next().is_err());
+    }
+
+    #[test]
+    fn encode_tcp_options() {
+        let mut bytes = vec![];
+        TcpOption::MaxSegmentSize(1460).encode(&mut bytes).unwrap();
+        TcpOption::NoOperation.encode(&mut bytes).unwrap();
+        TcpOption::WindowScale(7).encode(&mut bytes).unwrap();
+        TcpOption::Timestamps {
+            value: 1,
+            echo_reply: 2,
+        }
+        .encode(&mut bytes)
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                0x02, 0x04, 0x05, 0xb4, 0x01, 0x03, 0x03, 0x07, 0x08, 0x0a, 0x00, 0x00, 0x00, 0x01,
+                0x00, 0x00, 0x00, 0x02
+            ],
+            bytes
+        );
+
+        let large = [0; 40];
+        assert!(TcpOption::Unknown(TcpOptionKind(30), &large)
+            .encode(&mut bytes)
+            .is_err());
+    }
+}
@@ -19,6 +19,7 @@
 //! Custom primitive wrapper types for converting data to/from network byte
 //! order.
 
+use crate::SizeOf;
 use std::convert::From;
 use std::fmt;
 use std::ops;
@@ -28,7 +29,7 @@ use std::ops;
 /// Used to convert packet fields to host byte order on get and network byte
 /// order on set.
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, SizeOf)]
 #[repr(C, packed)]
 pub struct u16be(pub u16);
 
@@ -112,7 +113,7 @@ impl fmt::Display for u16be {
 /// Used to convert packet fields to host byte order on get and network byte
 /// order on set.
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, SizeOf)]
 #[repr(C, packed)]
 pub struct u32be(pub u32);
 
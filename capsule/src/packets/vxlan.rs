@@ -0,0 +1,334 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::u32be;
+use crate::packets::{Ethernet, Internal, Packet, ParseError, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use failure::Fallible;
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP destination port for VXLAN.
+pub const VXLAN_PORT: u16 = 4789;
+
+// The I flag, set for a valid VNI.
+const VXLAN_FLAG_I: u8 = 0x08;
+
+/// Virtual eXtensible Local Area Network packet based on [IETF RFC 7348].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|R|R|R|I|R|R|R|            Reserved                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                VXLAN Network Identifier (VNI) |   Reserved    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Flags*:          8 bits, the I flag must be set to 1 for a valid VNI.
+///                     The other 7 bits are reserved and set to 0.
+///
+/// - *VNI*:            24-bit VXLAN network identifier designating the
+///                     individual overlay network on which the communicating
+///                     VMs are situated.
+///
+/// - *Reserved*:       The 24-bit and 8-bit reserved fields are set to 0.
+///
+/// The payload of a VXLAN packet is an inner Ethernet frame, which can be
+/// parsed with `vxlan.parse::<Ethernet<Vxlan<E>>>()`.
+///
+/// [IETF RFC 7348]: https://tools.ietf.org/html/rfc7348
+pub struct Vxlan<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<VxlanHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Vxlan<E> {
+    #[inline]
+    fn header(&self) -> &VxlanHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut VxlanHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags
+    }
+
+    /// Returns the VXLAN network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        let vni: u32 = self.header().vni.into();
+        vni >> 8
+    }
+
+    /// Sets the VXLAN network identifier. Only the lower 24 bits are used.
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) {
+        self.header_mut().vni = ((vni & 0x00ff_ffff) << 8).into();
+    }
+
+    /// Removes the outer Ethernet, IP, UDP and VXLAN headers from the
+    /// message buffer, and returns the inner Ethernet frame.
+    ///
+    /// The inner frame is untouched and doesn't need to be reconciled.
+    #[inline]
+    pub fn decap(self) -> Fallible<Ethernet> {
+        let len = self.payload_offset();
+        let mut mbuf = self.reset();
+        mbuf.shrink(0, len)?;
+        mbuf.parse::<Ethernet>()
+    }
+}
+
+impl<E: IpPacket + Packet<Envelope = Ethernet>> Vxlan<E> {
+    /// Encapsulates the Ethernet frame in the message buffer with outer
+    /// Ethernet, IP, UDP and VXLAN headers.
+    ///
+    /// The VNI is set and the lengths and checksums of the outer headers
+    /// are reconciled. The outer addresses and the UDP source port are
+    /// left unset. After setting them, call [`reconcile_all`] again to
+    /// fix the checksums.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(frame: Mbuf, vni: u32) -> Fallible<Self> {
+        let ethernet = frame.push::<Ethernet>()?;
+        let ip = ethernet.push::<E>()?;
+        let udp = ip.push::<Udp<E>>()?;
+        let mut vxlan = udp.push::<Vxlan<E>>()?;
+        vxlan.set_vni(vni);
+        vxlan.reconcile_all();
+        Ok(vxlan)
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Vxlan<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("vxlan")
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Vxlan<E> {
+    /// The preceding type for a VXLAN packet must be UDP.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        VxlanHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Vxlan::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP packet's payload as a VXLAN packet.
+    ///
+    /// [`Udp::dst_port`] must be set to [`VXLAN_PORT`] and the I flag must
+    /// be set. Otherwise a parsing error is returned.
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`VXLAN_PORT`]: VXLAN_PORT
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        ensure!(
+            envelope.dst_port() == VXLAN_PORT,
+            ParseError::new("not a VXLAN packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Vxlan {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.flags() & VXLAN_FLAG_I != 0,
+            ParseError::new("not a VXLAN packet with a valid VNI.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a VXLAN packet to the beginning of the UDP packet's payload.
+    ///
+    /// [`Udp::dst_port`] is set to [`VXLAN_PORT`].
+    ///
+    /// [`Udp::dst_port`]: crate::packets::Udp::dst_port
+    /// [`VXLAN_PORT`]: VXLAN_PORT
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Fallible<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, VxlanHeader::size_of())?;
+        let header = mbuf.write_data(offset, &VxlanHeader::default())?;
+
+        envelope.set_dst_port(VXLAN_PORT);
+
+        Ok(Vxlan {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// VXLAN header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct VxlanHeader {
+    flags: u8,
+    reserved: [u8; 3],
+    vni: u32be,
+}
+
+impl Default for VxlanHeader {
+    fn default() -> VxlanHeader {
+        VxlanHeader {
+            flags: VXLAN_FLAG_I,
+            reserved: [0; 3],
+            vni: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::{Arp, EtherTypes, OperationCodes};
+    use crate::testils::byte_arrays::{ARP_REQUEST_PACKET, IPV4_UDP_PACKET, VXLAN_PACKET};
+
+    #[test]
+    fn size_of_vxlan_header() {
+        assert_eq!(8, VxlanHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&VXLAN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let vxlan = udp.parse::<Vxlan<Ipv4>>().unwrap();
+
+        assert_eq!(VXLAN_FLAG_I, vxlan.flags());
+        assert_eq!(100, vxlan.vni());
+
+        // the payload is the inner frame
+        let inner = vxlan.parse::<Ethernet<Vxlan<Ipv4>>>().unwrap();
+        assert_eq!("ff:ff:ff:ff:ff:ff", inner.dst().to_string());
+        assert_eq!(EtherTypes::Arp, inner.ether_type());
+        let arp = inner.parse::<Arp<Ethernet<Vxlan<Ipv4>>>>().unwrap();
+        assert_eq!(OperationCodes::Request, arp.operation());
+    }
+
+    #[capsule::test]
+    fn parse_non_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Vxlan<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_vxlan_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut vxlan = udp.push::<Vxlan<Ipv4>>().unwrap();
+
+        assert_eq!(VxlanHeader::size_of(), vxlan.len());
+        assert_eq!(VXLAN_FLAG_I, vxlan.flags());
+
+        vxlan.set_vni(0x0123_4567);
+        assert_eq!(0x23_4567, vxlan.vni());
+
+        // make sure the port is fixed
+        assert_eq!(VXLAN_PORT, vxlan.envelope().dst_port());
+    }
+
+    #[capsule::test]
+    fn encap_and_decap() {
+        let frame = Mbuf::from_bytes(&ARP_REQUEST_PACKET).unwrap();
+        let vxlan = Vxlan::<Ipv4>::encap(frame, 100).unwrap();
+
+        assert_eq!(100, vxlan.vni());
+        assert_eq!(ARP_REQUEST_PACKET.len(), vxlan.payload_len());
+        let udp = vxlan.envelope();
+        assert_eq!(VXLAN_PORT, udp.dst_port());
+        assert_eq!(udp.len() as u16, udp.length());
+        assert_eq!(udp.envelope().len() as u16, udp.envelope().total_length());
+        assert_eq!(VXLAN_PACKET.len(), vxlan.mbuf().data_len());
+
+        let inner = vxlan.decap().unwrap();
+        assert_eq!(ARP_REQUEST_PACKET.len(), inner.len());
+        let bytes = inner
+            .mbuf()
+            .read_data_slice::<u8>(0, ARP_REQUEST_PACKET.len())
+            .unwrap();
+        assert_eq!(&ARP_REQUEST_PACKET[..], unsafe { bytes.as_ref() });
+    }
+}
@@ -62,6 +62,28 @@ pub const VLAN_QINQ_PACKET: [u8; 68] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00
 ];
 
+/// An ARP request packet.
+#[rustfmt::skip]
+pub const ARP_REQUEST_PACKET: [u8; 42] = [
+// Ethernet header
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x06,
+// ARP header
+    // hardware type = Ethernet, protocol type = IPv4
+    0x00, 0x01, 0x08, 0x00,
+    // hardware addr len = 6, protocol addr len = 4, operation = request
+    0x06, 0x04, 0x00, 0x01,
+    // sender hardware addr = 00:00:00:00:00:02
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // sender protocol addr = 192.168.1.2
+    0xc0, 0xa8, 0x01, 0x02,
+    // target hardware addr = 00:00:00:00:00:00
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // target protocol addr = 192.168.1.1
+    0xc0, 0xa8, 0x01, 0x01
+];
+
 /// An IPv4 TCP packet.
 #[rustfmt::skip]
 pub const IPV4_TCP_PACKET: [u8; 58] = [
@@ -124,6 +146,865 @@ pub const IPV4_UDP_PACKET: [u8; 52] = [
     0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
 ];
 
+/// An IPv4 UDP packet with the router alert and record route options.
+#[rustfmt::skip]
+pub const IPV4_OPTIONS_PACKET: [u8; 68] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    // version = 4, ihl = 9
+    0x49, 0x00,
+    // IPv4 payload length
+    0x00, 0x36,
+    // ident = 43849, flags = 4, frag_offset = 0
+    0xab, 0x49, 0x40, 0x00,
+    // ttl = 255, protocol = UDP, checksum = 0x48dc
+    0xff, 0x11, 0x48, 0xdc,
+    // src = 139.133.217.110
+    0x8b, 0x85, 0xd9, 0x6e,
+    // dst = 139.133.233.2
+    0x8b, 0x85, 0xe9, 0x02,
+// IPv4 options
+    // router alert, value = 0
+    0x94, 0x04, 0x00, 0x00,
+    // no operation
+    0x01,
+    // record route, length = 11, pointer = 8, route = [10.0.0.1, 0.0.0.0]
+    0x07, 0x0b, 0x08,
+    0x0a, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00,
+// UDP header
+    // src_port = 39376, dst_port = 1087
+    0x99, 0xd0, 0x04, 0x3f,
+    // UDP length = 18, checksum = 0x7228
+    0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A VXLAN packet carrying an ARP request.
+#[rustfmt::skip]
+pub const VXLAN_PACKET: [u8; 92] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x4e,
+    // ident = 1, flags = 2, frag_offset = 0
+    0x00, 0x01, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0x269c
+    0x40, 0x11, 0x26, 0x9c,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 4789
+    0xc0, 0x00, 0x12, 0xb5,
+    // UDP length = 58, no checksum
+    0x00, 0x3a, 0x00, 0x00,
+// VXLAN header
+    // flags = I, reserved
+    0x08, 0x00, 0x00, 0x00,
+    // vni = 100, reserved
+    0x00, 0x00, 0x64, 0x00,
+// Inner Ethernet header
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x06,
+// Inner ARP header
+    0x00, 0x01, 0x08, 0x00,
+    0x06, 0x04, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0xc0, 0xa8, 0x01, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xc0, 0xa8, 0x01, 0x01
+];
+
+/// A Geneve packet with a critical option, carrying an ARP request.
+#[rustfmt::skip]
+pub const GENEVE_PACKET: [u8; 100] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x56,
+    // ident = 1, flags = 2, frag_offset = 0
+    0x00, 0x01, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0x2694
+    0x40, 0x11, 0x26, 0x94,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 6081
+    0xc0, 0x00, 0x17, 0xc1,
+    // UDP length = 66, no checksum
+    0x00, 0x42, 0x00, 0x00,
+// Geneve header
+    // version = 0, opt_len = 2, flags = C, protocol_type = TEB
+    0x02, 0x40, 0x65, 0x58,
+    // vni = 100, reserved
+    0x00, 0x00, 0x64, 0x00,
+// Geneve option
+    // class = 0x0102, type = 0x80 (critical), length = 1
+    0x01, 0x02, 0x80, 0x01,
+    // data
+    0xde, 0xad, 0xbe, 0xef,
+// Inner Ethernet header
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x06,
+// Inner ARP header
+    0x00, 0x01, 0x08, 0x00,
+    0x06, 0x04, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0xc0, 0xa8, 0x01, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xc0, 0xa8, 0x01, 0x01
+];
+
+/// A GTP-U packet with a PDU session container, carrying an IPv4 UDP
+/// packet.
+#[rustfmt::skip]
+pub const GTPU_PACKET: [u8; 96] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x52,
+    // ident = 1, flags = 2, frag_offset = 0
+    0x00, 0x01, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0x2698
+    0x40, 0x11, 0x26, 0x98,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// UDP header
+    // src_port = 2152, dst_port = 2152
+    0x08, 0x68, 0x08, 0x68,
+    // UDP length = 62, no checksum
+    0x00, 0x3e, 0x00, 0x00,
+// GTP-U header
+    // version = 1, PT = 1, E = 1, message type = G-PDU, length = 46
+    0x34, 0xff, 0x00, 0x2e,
+    // teid = 0x12345678
+    0x12, 0x34, 0x56, 0x78,
+    // sequence = 0, N-PDU = 0, next extension = PDU session container
+    0x00, 0x00, 0x00, 0x85,
+// PDU session container
+    // length = 1, PDU type = UL, QFI = 9, no more extensions
+    0x01, 0x10, 0x09, 0x00,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26,
+    0xab, 0x49, 0x40, 0x00,
+    0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e,
+    0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f,
+    0x00, 0x12, 0x72, 0x28,
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A GRE packet with a key, carrying an IPv4 UDP packet.
+#[rustfmt::skip]
+pub const GRE_PACKET: [u8; 80] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x42,
+    // ident = 2, flags = 2, frag_offset = 0
+    0x00, 0x02, 0x40, 0x00,
+    // ttl = 64, protocol = GRE, checksum = 0x2689
+    0x40, 0x2f, 0x26, 0x89,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// GRE header
+    // flags = K, version = 0, protocol type = IPv4
+    0x20, 0x00, 0x08, 0x00,
+    // key = 0x01020304
+    0x01, 0x02, 0x03, 0x04,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26,
+    0xab, 0x49, 0x40, 0x00,
+    0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e,
+    0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f,
+    0x00, 0x12, 0x72, 0x28,
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// An IPv4 packet with an authentication header carrying a UDP packet.
+#[rustfmt::skip]
+pub const IPV4_AH_PACKET: [u8; 76] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x46,
+    // ident = 43849, flags = 4, frag_offset = 0
+    0xab, 0x49, 0x40, 0x00,
+    // ttl = 255, protocol = AH, checksum = 0xf6be
+    0xff, 0x33, 0xf6, 0xbe,
+    // src = 139.133.217.110
+    0x8b, 0x85, 0xd9, 0x6e,
+    // dst = 139.133.233.2
+    0x8b, 0x85, 0xe9, 0x02,
+// AH header
+    // next header = UDP, payload len = 4, reserved
+    0x11, 0x04, 0x00, 0x00,
+    // spi = 0x100
+    0x00, 0x00, 0x01, 0x00,
+    // sequence number = 5
+    0x00, 0x00, 0x00, 0x05,
+    // 96-bit ICV
+    0x0d, 0x3a, 0x6b, 0x71, 0x4e, 0x9f,
+    0x20, 0xc4, 0x81, 0x57, 0xe2, 0x03,
+// UDP header
+    0x99, 0xd0, 0x04, 0x3f,
+    0x00, 0x12, 0x72, 0x28,
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// The IPv4 UDP packet encrypted with ESP in transport mode, using
+/// AES-128-GCM with key `feffe9928665731c6d6a8f9467308308` and salt
+/// `cafebabe`.
+#[rustfmt::skip]
+pub const IPV4_ESP_TRANSPORT_PACKET: [u8; 86] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x48,
+    // ident = 43849, flags = 4, frag_offset = 0
+    0xab, 0x49, 0x40, 0x00,
+    // ttl = 255, protocol = ESP, checksum = 0xf6bd
+    0xff, 0x32, 0xf6, 0xbd,
+    // src = 139.133.217.110
+    0x8b, 0x85, 0xd9, 0x6e,
+    // dst = 139.133.233.2
+    0x8b, 0x85, 0xe9, 0x02,
+// ESP header
+    // spi = 0xa5f8
+    0x00, 0x00, 0xa5, 0xf8,
+    // sequence number = 1
+    0x00, 0x00, 0x00, 0x01,
+// ESP payload
+    // iv = 1
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // encrypted UDP packet, pad length = 0, next header = UDP
+    0x15, 0x94, 0xae, 0xa6, 0xf4, 0x5c, 0x0e, 0x91,
+    0x6c, 0xdf, 0xdd, 0x06, 0x8d, 0xe9, 0x04, 0x2e,
+    0x33, 0xbd, 0xd4, 0x86,
+    // ICV
+    0x2a, 0x80, 0x93, 0xf6, 0x2d, 0x2a, 0x15, 0x01,
+    0x44, 0x02, 0x59, 0x23, 0xad, 0x25, 0x37, 0x4c
+];
+
+/// SCTP packet with a DATA chunk and a SACK chunk.
+#[rustfmt::skip]
+pub const IPV4_SCTP_PACKET: [u8; 94] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x50,
+    // ident = 1, flags = 4, frag_offset = 0
+    0x00, 0x01, 0x40, 0x00,
+    // ttl = 64, protocol = SCTP, checksum = 0x2627
+    0x40, 0x84, 0x26, 0x27,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// SCTP header
+    // src_port = 5000, dst_port = 36412
+    0x13, 0x88, 0x8e, 0x3c,
+    // verification tag = 0x1a2b3c4d
+    0x1a, 0x2b, 0x3c, 0x4d,
+    // CRC32c checksum = 0x7499d955
+    0x55, 0xd9, 0x99, 0x74,
+// DATA chunk
+    // flags = B|E, length = 21
+    0x00, 0x03, 0x00, 0x15,
+    // tsn = 1
+    0x00, 0x00, 0x00, 0x01,
+    // stream id = 0, stream seq = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ppid = 18
+    0x00, 0x00, 0x00, 0x12,
+    // user data = "hello", padding
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x00, 0x00, 0x00,
+// SACK chunk
+    // flags = 0, length = 24
+    0x03, 0x00, 0x00, 0x18,
+    // cumulative tsn ack = 17
+    0x00, 0x00, 0x00, 0x11,
+    // a_rwnd = 65536
+    0x00, 0x01, 0x00, 0x00,
+    // gap blocks = 1, duplicate tsns = 1
+    0x00, 0x01, 0x00, 0x01,
+    // gap block start = 2, end = 3
+    0x00, 0x02, 0x00, 0x03,
+    // duplicate tsn = 9
+    0x00, 0x00, 0x00, 0x09
+];
+
+/// DHCPDISCOVER message from the Wireshark sample DHCP capture.
+#[rustfmt::skip]
+pub const DHCPV4_DISCOVER_PACKET: [u8; 314] = [
+// Ethernet header
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x01, 0x2c,
+    // ident = 43062, flags = 0, frag_offset = 0
+    0xa8, 0x36, 0x00, 0x00,
+    // ttl = 250, protocol = UDP, checksum = 0x178b
+    0xfa, 0x11, 0x17, 0x8b,
+    // src = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // dst = 255.255.255.255
+    0xff, 0xff, 0xff, 0xff,
+// UDP header
+    // src_port = 68, dst_port = 67, length = 280, checksum = 0x591f
+    0x00, 0x44, 0x00, 0x43, 0x01, 0x18, 0x59, 0x1f,
+// DHCP message
+    // op = BOOTREQUEST, htype = 1, hlen = 6, hops = 0
+    0x01, 0x01, 0x06, 0x00,
+    // xid = 0x00003d1d
+    0x00, 0x00, 0x3d, 0x1d,
+    // secs = 0, flags = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ciaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // yiaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // siaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // giaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // chaddr = 00:0b:82:01:fc:42
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // sname and file
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // magic cookie
+    0x63, 0x82, 0x53, 0x63,
+// DHCP options
+    // message type = DHCPDISCOVER
+    0x35, 0x01, 0x01,
+    // client identifier = 01:00:0b:82:01:fc:42
+    0x3d, 0x07, 0x01, 0x00, 0x0b, 0x82, 0x01, 0xfc,
+    0x42,
+    // requested ip address = 0.0.0.0
+    0x32, 0x04, 0x00, 0x00, 0x00, 0x00,
+    // parameter request list = 1, 3, 6, 42
+    0x37, 0x04, 0x01, 0x03, 0x06, 0x2a,
+    // end and padding
+    0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+];
+
+/// DHCPOFFER message in response to the `DHCPV4_DISCOVER_PACKET`.
+#[rustfmt::skip]
+pub const DHCPV4_OFFER_PACKET: [u8; 342] = [
+// Ethernet header
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+    0x00, 0x08, 0x74, 0xad, 0xf1, 0x9b,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x01, 0x48,
+    // ident = 1093, flags = 0, frag_offset = 0
+    0x04, 0x45, 0x00, 0x00,
+    // ttl = 128, protocol = UDP, checksum = 0xb404
+    0x80, 0x11, 0xb4, 0x04,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.10
+    0xc0, 0xa8, 0x00, 0x0a,
+// UDP header
+    // src_port = 67, dst_port = 68, length = 308, checksum = 0x2233
+    0x00, 0x43, 0x00, 0x44, 0x01, 0x34, 0x22, 0x33,
+// DHCP message
+    // op = BOOTREPLY, htype = 1, hlen = 6, hops = 0
+    0x02, 0x01, 0x06, 0x00,
+    // xid = 0x00003d1d
+    0x00, 0x00, 0x3d, 0x1d,
+    // secs = 0, flags = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ciaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // yiaddr = 192.168.0.10
+    0xc0, 0xa8, 0x00, 0x0a,
+    // siaddr = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // giaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // chaddr = 00:0b:82:01:fc:42
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // sname and file
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // magic cookie
+    0x63, 0x82, 0x53, 0x63,
+// DHCP options
+    // message type = DHCPOFFER
+    0x35, 0x01, 0x02,
+    // subnet mask = 255.255.255.0
+    0x01, 0x04, 0xff, 0xff, 0xff, 0x00,
+    // renewal time = 1800
+    0x3a, 0x04, 0x00, 0x00, 0x07, 0x08,
+    // rebinding time = 3150
+    0x3b, 0x04, 0x00, 0x00, 0x0c, 0x4e,
+    // lease time = 3600
+    0x33, 0x04, 0x00, 0x00, 0x0e, 0x10,
+    // server identifier = 192.168.0.1
+    0x36, 0x04, 0xc0, 0xa8, 0x00, 0x01,
+    // end and padding
+    0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00
+];
+
+/// DNS query for the A record of `www.example.com`.
+#[rustfmt::skip]
+pub const DNS_QUERY_PACKET: [u8; 75] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x3d,
+    // ident = 1, flags = 0, frag_offset = 0
+    0x00, 0x01, 0x00, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0x66ad
+    0x40, 0x11, 0x66, 0xad,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 53, length = 41, checksum = 0x2218
+    0xc0, 0x00, 0x00, 0x35, 0x00, 0x29, 0x22, 0x18,
+// DNS header
+    // id = 0xbeef, flags = RD
+    0xbe, 0xef, 0x01, 0x00,
+    // qdcount = 1, ancount = 0, nscount = 0, arcount = 0
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+// Question section
+    // qname = www.example.com
+    0x03, 0x77, 0x77, 0x77,
+    0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+    0x03, 0x63, 0x6f, 0x6d,
+    0x00,
+    // qtype = A, qclass = IN
+    0x00, 0x01, 0x00, 0x01,
+];
+
+/// DNS response to the `DNS_QUERY_PACKET`, with compressed names in all
+/// sections and an EDNS record.
+#[rustfmt::skip]
+pub const DNS_RESPONSE_PACKET: [u8; 148] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x86,
+    // ident = 2, flags = 0, frag_offset = 0
+    0x00, 0x02, 0x00, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0x6663
+    0x40, 0x11, 0x66, 0x63,
+    // src = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+    // dst = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+// UDP header
+    // src_port = 53, dst_port = 49152, length = 114, checksum = 0xa02e
+    0x00, 0x35, 0xc0, 0x00, 0x00, 0x72, 0xa0, 0x2e,
+// DNS header
+    // id = 0xbeef, flags = QR | RD | RA, rcode = NOERROR
+    0xbe, 0xef, 0x81, 0x80,
+    // qdcount = 1, ancount = 2, nscount = 1, arcount = 1
+    0x00, 0x01, 0x00, 0x02, 0x00, 0x01, 0x00, 0x01,
+// Question section
+    // qname = www.example.com
+    0x03, 0x77, 0x77, 0x77,
+    0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65,
+    0x03, 0x63, 0x6f, 0x6d,
+    0x00,
+    // qtype = A, qclass = IN
+    0x00, 0x01, 0x00, 0x01,
+// Answer section
+    // name = pointer to www.example.com, type = CNAME, class = IN
+    0xc0, 0x0c, 0x00, 0x05, 0x00, 0x01,
+    // ttl = 3600, rdlength = 2
+    0x00, 0x00, 0x0e, 0x10, 0x00, 0x02,
+    // cname = pointer to example.com
+    0xc0, 0x10,
+    // name = pointer to example.com, type = A, class = IN
+    0xc0, 0x10, 0x00, 0x01, 0x00, 0x01,
+    // ttl = 86400, rdlength = 4
+    0x00, 0x01, 0x51, 0x80, 0x00, 0x04,
+    // address = 93.184.216.34
+    0x5d, 0xb8, 0xd8, 0x22,
+// Authority section
+    // name = pointer to example.com, type = NS, class = IN
+    0xc0, 0x10, 0x00, 0x02, 0x00, 0x01,
+    // ttl = 172800, rdlength = 20
+    0x00, 0x02, 0xa3, 0x00, 0x00, 0x14,
+    // nsdname = a.iana-servers.net
+    0x01, 0x61,
+    0x0c, 0x69, 0x61, 0x6e, 0x61, 0x2d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x73,
+    0x03, 0x6e, 0x65, 0x74,
+    0x00,
+// Additional section
+    // name = root, type = OPT, udp payload size = 4096
+    0x00, 0x00, 0x29, 0x10, 0x00,
+    // extended rcode and flags = 0, rdlength = 0
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// IGMPv2 membership report for group `239.1.1.1`, padded to the minimum
+/// Ethernet frame size.
+#[rustfmt::skip]
+pub const IGMPV2_REPORT_PACKET: [u8; 60] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x08, 0x00,
+// IPv4 header
+    0x46, 0xc0,
+    // IPv4 payload length
+    0x00, 0x20,
+    // ident = 1, flags = 0, frag_offset = 0
+    0x00, 0x01, 0x00, 0x00,
+    // ttl = 1, protocol = IGMP, checksum = 0x2a14
+    0x01, 0x02, 0x2a, 0x14,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 239.1.1.1
+    0xef, 0x01, 0x01, 0x01,
+    // router alert option
+    0x94, 0x04, 0x00, 0x00,
+// IGMPv2 message
+    // type = v2 membership report, max resp time = 0, checksum = 0xf9fc
+    0x16, 0x00, 0xf9, 0xfc,
+    // group = 239.1.1.1
+    0xef, 0x01, 0x01, 0x01,
+// Ethernet padding
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// IGMPv3 group-and-source specific query for group `232.1.1.1`, padded
+/// to the minimum Ethernet frame size.
+#[rustfmt::skip]
+pub const IGMPV3_QUERY_PACKET: [u8; 60] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x01, 0x01, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xfe,
+    0x08, 0x00,
+// IPv4 header
+    0x46, 0xc0,
+    // IPv4 payload length
+    0x00, 0x2c,
+    // ident = 2, flags = 0, frag_offset = 0
+    0x00, 0x02, 0x00, 0x00,
+    // ttl = 1, protocol = IGMP, checksum = 0x300a
+    0x01, 0x02, 0x30, 0x0a,
+    // src = 10.0.0.254
+    0x0a, 0x00, 0x00, 0xfe,
+    // dst = 232.1.1.1
+    0xe8, 0x01, 0x01, 0x01,
+    // router alert option
+    0x94, 0x04, 0x00, 0x00,
+// IGMPv3 query
+    // type = membership query, max resp code = 100, checksum = 0xef16
+    0x11, 0x64, 0xef, 0x16,
+    // group = 232.1.1.1
+    0xe8, 0x01, 0x01, 0x01,
+    // s = 0, qrv = 2, qqic = 125, number of sources = 2
+    0x02, 0x7d, 0x00, 0x02,
+    // sources = 10.0.0.1, 10.0.0.2
+    0x0a, 0x00, 0x00, 0x01,
+    0x0a, 0x00, 0x00, 0x02,
+// Ethernet padding
+    0x00, 0x00,
+];
+
+/// IGMPv3 membership report with two group records.
+#[rustfmt::skip]
+pub const IGMPV3_REPORT_PACKET: [u8; 66] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x00, 0x00, 0x16,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x08, 0x00,
+// IPv4 header
+    0x46, 0xc0,
+    // IPv4 payload length
+    0x00, 0x34,
+    // ident = 3, flags = 0, frag_offset = 0
+    0x00, 0x03, 0x00, 0x00,
+    // ttl = 1, protocol = IGMP, checksum = 0x39ea
+    0x01, 0x02, 0x39, 0xea,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 224.0.0.22
+    0xe0, 0x00, 0x00, 0x16,
+    // router alert option
+    0x94, 0x04, 0x00, 0x00,
+// IGMPv3 report
+    // type = v3 membership report, checksum = 0xf1f5
+    0x22, 0x00, 0xf1, 0xf5,
+    // number of group records = 2
+    0x00, 0x00, 0x00, 0x02,
+    // record type = CHANGE_TO_EXCLUDE_MODE, aux data len = 0, number of sources = 0
+    0x04, 0x00, 0x00, 0x00,
+    // group = 239.1.1.1
+    0xef, 0x01, 0x01, 0x01,
+    // record type = ALLOW_NEW_SOURCES, aux data len = 0, number of sources = 1
+    0x05, 0x00, 0x00, 0x01,
+    // group = 232.1.1.1
+    0xe8, 0x01, 0x01, 0x01,
+    // source = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+];
+
+/// MLDv2 multicast address and source specific query for `ff3e::8000:1`.
+#[rustfmt::skip]
+pub const MLDV2_QUERY_PACKET: [u8; 98] = [
+// Ethernet header
+    0x33, 0x33, 0x80, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x86, 0xdd,
+// IPv6 header
+    // version, traffic class, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length = 44, next header = ICMPv6, hop limit = 1
+    0x00, 0x2c, 0x3a, 0x01,
+    // src = fe80::1
+    0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // dst = ff3e::8000:1
+    0xff, 0x3e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x01,
+// ICMPv6 message
+    // type = multicast listener query, code = 0, checksum = 0x294e
+    0x82, 0x00, 0x29, 0x4e,
+    // max resp code = 10000, reserved
+    0x27, 0x10, 0x00, 0x00,
+    // multicast address = ff3e::8000:1
+    0xff, 0x3e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x01,
+    // s = 0, qrv = 2, qqic = 125, number of sources = 1
+    0x02, 0x7d, 0x00, 0x01,
+    // source = 2001:db8::1
+    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// MLDv2 report with two multicast address records.
+#[rustfmt::skip]
+pub const MLDV2_REPORT_PACKET: [u8; 118] = [
+// Ethernet header
+    0x33, 0x33, 0x00, 0x00, 0x00, 0x16,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x86, 0xdd,
+// IPv6 header
+    // version, traffic class, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length = 64, next header = ICMPv6, hop limit = 1
+    0x00, 0x40, 0x3a, 0x01,
+    // src = fe80::1
+    0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // dst = ff02::16
+    0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x16,
+// ICMPv6 message
+    // type = version 2 multicast listener report, code = 0, checksum = 0xbee6
+    0x8f, 0x00, 0xbe, 0xe6,
+    // reserved, number of records = 2
+    0x00, 0x00, 0x00, 0x02,
+    // record type = CHANGE_TO_EXCLUDE_MODE, aux data len = 0, number of sources = 0
+    0x04, 0x00, 0x00, 0x00,
+    // multicast address = ff02::1:ff00:1
+    0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x01,
+    // record type = ALLOW_NEW_SOURCES, aux data len = 0, number of sources = 1
+    0x05, 0x00, 0x00, 0x01,
+    // multicast address = ff3e::8000:1
+    0xff, 0x3e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x01,
+    // source = 2001:db8::1
+    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// LLDP advertisement with the system name and management address TLVs,
+/// padded to the minimum Ethernet frame size.
+#[rustfmt::skip]
+pub const LLDP_PACKET: [u8; 60] = [
+// Ethernet header
+    0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x88, 0xcc,
+// LLDPDU
+    // chassis id, subtype = mac address, 00:00:00:00:00:01
+    0x02, 0x07, 0x04,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // port id, subtype = interface name, "eth0"
+    0x04, 0x05, 0x05,
+    0x65, 0x74, 0x68, 0x30,
+    // time to live = 120
+    0x06, 0x02, 0x00, 0x78,
+    // system name = "switch"
+    0x0a, 0x06,
+    0x73, 0x77, 0x69, 0x74, 0x63, 0x68,
+    // management address, address string length = 5, family = IPv4
+    0x10, 0x0c, 0x05, 0x01,
+    // address = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // interface numbering = ifIndex, interface number = 1, OID string length = 0
+    0x02, 0x00, 0x00, 0x00, 0x01, 0x00,
+    // end
+    0x00, 0x00,
+// Ethernet padding
+    0x00, 0x00,
+];
+
+/// A 6in4 packet carrying the IPv6 TCP packet.
+#[rustfmt::skip]
+pub const IPV6_IN_IPV4_PACKET: [u8; 98] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x54,
+    // ident = 0, flags = 0, frag_offset = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ttl = 2, protocol = IPv6, checksum = 0xcc4b
+    0x02, 0x29, 0xcc, 0x4b,
+    // src = 192.0.2.1
+    0xc0, 0x00, 0x02, 0x01,
+    // dst = 198.51.100.1
+    0xc6, 0x33, 0x64, 0x01,
+// IPv6 header
+    // version, dscp, ecn, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length
+    0x00, 0x18,
+    // next Header
+    0x06,
+    // hop limit
+    0x02,
+    // src addr
+    0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // dst addr
+    0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34,
+// TCP header
+    // src_port = 36869, dst_port = 23
+    0x90, 0x05, 0x00, 0x17,
+    // seq_no = 1913975060
+    0x72, 0x14, 0xf1, 0x14,
+    // ack_no = 0
+    0x00, 0x00, 0x00, 0x00,
+    // data_offset = 24, flags = 0x02
+    0x60, 0x02,
+    // window = 8760, checksum = 0xa92c, urgent = 0
+    0x22, 0x38, 0xa9, 0x2c, 0x00, 0x00,
+    // options
+    0x02, 0x04, 0x05, 0xb4
+];
+
+/// An MPLS packet with a two label stack, carrying an IPv4 UDP packet.
+#[rustfmt::skip]
+pub const MPLS_PACKET: [u8; 60] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x88, 0x47,
+// MPLS label stack
+    // label = 100, tc = 0, bos = 0, ttl = 64
+    0x00, 0x06, 0x40, 0x40,
+    // label = 200, tc = 0, bos = 1, ttl = 64
+    0x00, 0x0c, 0x81, 0x40,
+// IPv4 header
+    0x45, 0x00, 0x00, 0x26,
+    0xab, 0x49, 0x40, 0x00,
+    0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e,
+    0x8b, 0x85, 0xe9, 0x02,
+// UDP header
+    0x99, 0xd0, 0x04, 0x3f,
+    0x00, 0x12, 0x72, 0x28,
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
 /// An IPv6 TCP packet.
 #[rustfmt::skip]
 pub const IPV6_TCP_PACKET: [u8; 78] = [
@@ -247,6 +1128,44 @@ pub const IPV6_FRAGMENT_PACKET: [u8; 72] = [
     0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
 ];
 
+/// An IPv6 UDP packet with hop-by-hop and destination options.
+#[rustfmt::skip]
+pub const IPV6_EXT_OPTIONS_PACKET: [u8; 88] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x86, 0xdd,
+// IPv6 header
+    0x60, 0x00, 0x00, 0x00,
+    // payload length
+    0x00, 0x22,
+    // next header (hop-by-hop options)
+    0x00,
+    0x40,
+    0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x20, 0x01, 0x0d, 0xb8, 0x85, 0xa3, 0x00, 0x00, 0x00, 0x00, 0x8a, 0x2e, 0x03, 0x70, 0x73, 0x34,
+// Hop-by-hop options header
+    // next header (destination options)
+    0x3c,
+    // hdr ext len
+    0x00,
+    // router alert = 0 (MLD)
+    0x05, 0x02, 0x00, 0x00,
+    // padN
+    0x01, 0x00,
+// Destination options header
+    // next header (udp)
+    0x11,
+    // hdr ext len
+    0x00,
+    // padN
+    0x01, 0x04, 0x00, 0x00, 0x00, 0x00,
+// UDP header
+    0x99, 0xd0, 0x04, 0x3f,
+    0x00, 0x12, 0x00, 0x00,
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
 /// An ICMPv4 packet.
 #[rustfmt::skip]
 pub const ICMPV4_PACKET: [u8; 74] = [
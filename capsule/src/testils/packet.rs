@@ -18,7 +18,7 @@
 
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::v6::{Ipv6, SegmentRouting};
-use crate::packets::{Ethernet, Packet, Tcp, Udp};
+use crate::packets::{Arp, Ethernet, Packet, Tcp, Udp};
 
 /// [`Packet`] extension trait.
 ///
@@ -33,6 +33,11 @@ pub trait PacketExt: Packet + Sized {
         self.reset().parse::<Ethernet>().unwrap()
     }
 
+    /// Converts the packet into an ARP packet.
+    fn into_arp(self) -> Arp<Ethernet> {
+        self.into_eth().parse::<Arp<Ethernet>>().unwrap()
+    }
+
     /// Converts the packet into an IPv4 packet.
     fn into_v4(self) -> Ipv4 {
         self.into_eth().parse::<Ipv4>().unwrap()
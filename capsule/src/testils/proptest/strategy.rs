@@ -22,7 +22,9 @@ use crate::net::MacAddr;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::v6::{Ipv6, Ipv6Packet, SegmentRouting};
 use crate::packets::ip::{Flow, IpPacket, ProtocolNumber, ProtocolNumbers};
-use crate::packets::{EtherType, EtherTypes, Ethernet, Packet, Tcp, Udp};
+use crate::packets::{
+    Arp, EtherType, EtherTypes, Ethernet, OperationCode, OperationCodes, Packet, Tcp, Udp,
+};
 use crate::testils::Rvg;
 use crate::Mbuf;
 use proptest::arbitrary::{any, Arbitrary};
@@ -42,6 +44,12 @@ pub enum field {
     // Ethernet
     eth_src,
     eth_dst,
+    // ARP
+    arp_operation,
+    arp_sender_mac,
+    arp_sender_ip,
+    arp_target_mac,
+    arp_target_ip,
     // IPv4
     ipv4_src,
     ipv4_dst,
@@ -158,6 +166,14 @@ impl StrategyMap {
         self.get::<Ipv6Addr>(key)
     }
 
+    fn arp_operation(&self) -> impl Strategy<Value = OperationCode> {
+        if let Some(v) = self.checked_value::<u16>(&field::arp_operation) {
+            Just(OperationCode::new(v)).boxed()
+        } else {
+            prop_oneof![Just(OperationCodes::Request), Just(OperationCodes::Reply)].boxed()
+        }
+    }
+
     fn sr_segments(&self) -> impl Strategy<Value = (Vec<Ipv6Addr>, u8)> {
         let mut rvg = Rvg::new();
 
@@ -399,6 +415,60 @@ fn udp<E: Debug + IpPacket>(
         })
 }
 
+/// Returns a strategy to generate ARP packets.
+///
+/// All settable fields are randomly generated. Some field values are implied
+/// in order for the packet to be internally consistent. For example,
+/// `ether_type` is always `EtherTypes::Arp` and `operation` is either
+/// `OperationCodes::Request` or `OperationCodes::Reply`.
+pub fn arp() -> impl Strategy<Value = Mbuf> {
+    arp_with(fieldmap! {})
+}
+
+/// Returns a strategy to generate ARP packets.
+///
+/// Similar to `arp`. Some fields can be explicitly set through `fieldmap!`.
+/// All other fields are randomly generated. See the `field` enum for a list
+/// of fields that can be set explicitly.
+///
+/// # Example
+///
+/// ```
+/// #[capsule::test]
+/// fn arp_packet() {
+///     proptest!(|(packet in arp_with(fieldmap! {
+///         field::arp_operation => 1u16,
+///         field::arp_target_ip => "10.0.0.1".parse::<Ipv4Addr>().unwrap(),
+///     }))| {
+///         let packet = packet.parse::<Ethernet>().unwrap();
+///         let arp = packet.parse::<Arp<Ethernet>>().unwrap();
+///         prop_assert_eq!(OperationCodes::Request, arp.operation());
+///         prop_assert_eq!("10.0.0.1".parse::<Ipv4Addr>().unwrap(), arp.target_protocol_addr());
+///     });
+/// }
+/// ```
+pub fn arp_with(map: StrategyMap) -> impl Strategy<Value = Mbuf> {
+    (
+        ethernet(EtherTypes::Arp, &map),
+        map.arp_operation(),
+        map.mac_addr(&field::arp_sender_mac),
+        map.ipv4_addr(&field::arp_sender_ip),
+        map.mac_addr(&field::arp_target_mac),
+        map.ipv4_addr(&field::arp_target_ip),
+    )
+        .prop_map(
+            |(packet, operation, sender_mac, sender_ip, target_mac, target_ip)| {
+                let mut packet = packet.push::<Arp<Ethernet>>().unwrap();
+                packet.set_operation(operation);
+                packet.set_sender_hardware_addr(sender_mac);
+                packet.set_sender_protocol_addr(sender_ip);
+                packet.set_target_hardware_addr(target_mac);
+                packet.set_target_protocol_addr(target_ip);
+                packet.reset()
+            },
+        )
+}
+
 /// Returns a strategy to generate IPv4 TCP packets.
 ///
 /// All settable fields are randomly generated. Some field values are implied
@@ -8,24 +8,32 @@
 pub mod error_handling;
 pub mod mgr;
 pub mod nflib;
+#[cfg(feature = "sim")]
+pub mod sim;
 
 // #[allow(unused_imports)] // remove when code stabilises
 // use mgr::get_args;
-use std::ffi::{c_void, CString};
-use std::mem;
-use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
 // use std::sync::Arc;
 use std::{thread, time};
 
+use mgr::backend::{Backend, LOG_ERR, LOG_INFO};
+#[cfg(feature = "dpdk")]
+use mgr::pkt_funcs::PktBufs;
+#[cfg(feature = "dpdk")]
+use std::os::raw::{c_int, c_void};
+
 // DPDK functions
-use capsule_ffi::{_rte_get_timer_hz, _rte_get_tsc_cycles, _rte_lcore_id, rte_log};
-// DPDK constants
-use capsule_ffi::{RTE_LOGTYPE_USER1, RTE_LOG_ERR, RTE_LOG_INFO};
+#[cfg(feature = "dpdk")]
+use capsule_ffi::{rte_eal_mp_wait_lcore, rte_eal_remote_launch};
 
 // use nflib::{common, msg_common};
 
 const MAX_SHUTDOWN_ITERS: u8 = 10;
 
+// Cleared by the master thread to stop the RX and TX threads
+static WORKER_KEEP_RUNNING: AtomicBool = AtomicBool::new(true);
+
 #[derive(Default)]
 pub struct MgrState {
     pub global_stats_sleep_time: u8, // also used to run the main thread of onvm
@@ -35,68 +43,49 @@ pub struct MgrState {
 }
 
 /// Stats thread periodically prints per-port and per-NF stats.
-pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
+pub fn master_thread_main<B: Backend>(global_state: &mgr::global::GlobalNFState<B>) {
     // pub fn master_thread_main() {
     // True as long as the main thread loop should keep running
     let mut main_keep_running = 1;
     // We'll want to shut down the TX/RX threads second so that we don't
     // race the stats display to be able to print, so keep this varable separate
-    let mut worker_keep_running = 1;
-    let mut thread_state: MgrState = Default::default();
-    thread_state.global_stats_sleep_time = 1;
+    let thread_state = MgrState {
+        global_stats_sleep_time: 1,
+        ..Default::default()
+    };
 
-    let i: usize;
-    let shutdown_iter_count: u8;
+    let backend = &global_state.backend;
     let sleeptime = thread_state.global_stats_sleep_time;
     let verbosity_level = thread_state.global_verbosity_level;
     let time_to_live = thread_state.global_time_to_live;
     let pkt_limit = thread_state.global_pkt_limit;
-    let start_time = unsafe { _rte_get_tsc_cycles() };
+    let start_time = backend.timer_cycles();
     let mut total_rx_pkts: u32;
 
-    let f = format!("Core {}: Running master thread\n", unsafe {
-        _rte_lcore_id()
-    });
-    unsafe {
-        rte_log(
-            RTE_LOG_INFO,
-            RTE_LOGTYPE_USER1,
-            &f[..] as *const _ as *const i8,
-        )
-    };
-
-    let f = format!("Stats verbosity level = {}\n", verbosity_level);
-    unsafe {
-        rte_log(
-            RTE_LOG_INFO,
-            RTE_LOGTYPE_USER1,
-            &f[..] as *const _ as *const i8,
-        )
-    };
+    backend.log(
+        LOG_INFO,
+        &format!("Core {}: Running master thread\n", backend.lcore_id()),
+    );
+    backend.log(
+        LOG_INFO,
+        &format!("Stats verbosity level = {}\n", verbosity_level),
+    );
 
     if time_to_live > 0 {
-        let f = format!(
-            "Manager time to live = {}\n",
-            thread_state.global_time_to_live
+        backend.log(
+            LOG_INFO,
+            &format!(
+                "Manager time to live = {}\n",
+                thread_state.global_time_to_live
+            ),
         );
-        unsafe {
-            rte_log(
-                RTE_LOG_INFO,
-                RTE_LOGTYPE_USER1,
-                &f[..] as *const _ as *const i8,
-            )
-        };
     }
 
     if pkt_limit > 0 {
-        let f = format!("Manager packet limit = {}\n", thread_state.global_pkt_limit);
-        unsafe {
-            rte_log(
-                RTE_LOG_INFO,
-                RTE_LOGTYPE_USER1,
-                &f[..] as *const _ as *const i8,
-            )
-        };
+        backend.log(
+            LOG_INFO,
+            &format!("Manager packet limit = {}\n", thread_state.global_pkt_limit),
+        );
     }
     /* Initial pause so above printf is seen */
     thread::sleep(time::Duration::from_secs(5));
@@ -108,11 +97,13 @@ pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
         // let now = time::Instant::now();
         thread::sleep(sleeptime);
         mgr::net_funcs::onvm_nf_check_status(global_state);
+        mgr::net_funcs::onvm_nf_check_liveness(global_state);
+        mgr::net_funcs::onvm_nf_check_relocations(global_state);
 
         // NOTE: The next if loop is more wordy than required since Rust needs type annotations to perform the math ops
-        let cur: u64 = unsafe { _rte_get_tsc_cycles() };
+        let cur: u64 = backend.timer_cycles();
         let t: u64 = (cur - start_time) * nflib::constants::TIME_TTL_MULTIPLIER as u64;
-        let t1: u64 = unsafe { _rte_get_timer_hz() };
+        let t1: u64 = backend.timer_hz();
         if time_to_live > 0 && t / t1 >= time_to_live as u64 {
             println!("Time to live exceeded, shutting down");
             main_keep_running = 0;
@@ -120,10 +111,10 @@ pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
 
         if pkt_limit > 0 {
             total_rx_pkts = 0;
-            for i in 0..*global_state.ports.num_ports.borrow() as usize {
-                total_rx_pkts += global_state.ports.clone().rx_stats.rx
-                    [global_state.ports.id.borrow()[i as usize] as usize]
-                    as u32;
+            for i in 0..global_state.ports().num_ports.load(Ordering::SeqCst) as usize {
+                let port_id = global_state.ports().id[i].load(Ordering::SeqCst) as usize;
+                total_rx_pkts +=
+                    global_state.ports().rx_stats.rx[port_id].load(Ordering::SeqCst) as u32;
             }
             let lim: u32 = pkt_limit as u32 * nflib::constants::PKT_TTL_MULTIPLIER;
             if total_rx_pkts >= lim {
@@ -137,46 +128,36 @@ pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
     // #ifdef RTE_LIBRTE_PDUMP
     //         rte_pdump_uninit();
     // #endif
-    unsafe {
-        let f = format!("Core {}: Initiating shutdown sequence\n", _rte_lcore_id());
-        rte_log(
-            RTE_LOG_INFO,
-            RTE_LOGTYPE_USER1,
-            &f[..] as *const _ as *const i8,
-        );
-    }
+    backend.log(
+        LOG_INFO,
+        &format!(
+            "Core {}: Initiating shutdown sequence\n",
+            backend.lcore_id()
+        ),
+    );
     /* Stop all RX and TX threads */
-    worker_keep_running = 0;
+    WORKER_KEEP_RUNNING.store(false, Ordering::SeqCst);
 
-    /* Tell all NFs to stop */
+    /* Tell all running NFs to stop */
     for i in 0..nflib::constants::MAX_NFS as usize {
-        let status = unsafe { *(*(*global_state.nfs[i].clone())).status.borrow() };
-        // let status = unsafe { *(*global_state.nfs.clone()[i]).status.borrow() };
-        // let status = unsafe { (**global_state.nfs.clone()[i].borrow()).status };
-        if status == nflib::constants::NF_RUNNING as u16 {
+        let nf = *global_state.nfs[i].clone();
+        let status = unsafe { (*nf).status.load(Ordering::SeqCst) };
+        if status != nflib::constants::NF_RUNNING {
             continue;
         }
-        unsafe {
-            let f = format!(
+        backend.log(
+            LOG_INFO,
+            &format!(
                 "Core {}: Notifying NF {} to shut down\n",
-                _rte_lcore_id(),
+                backend.lcore_id(),
                 i
-            );
-            rte_log(
-                RTE_LOG_INFO,
-                RTE_LOGTYPE_USER1,
-                &f[..] as *const _ as *const i8,
-            );
-        }
-        let ret = unsafe {
-            // let a = Arc::get_mut(&mut global_state.nfs.clone()[i]);
-            let a = *global_state.nfs[i].clone();
-            mgr::net_funcs::onvm_nf_send_msg(
-                i as u16,
-                nflib::structs::OnvmNFMsg::NfStopping(a),
-                global_state.clone(),
-            )
-        };
+            ),
+        );
+        mgr::net_funcs::onvm_nf_send_msg(
+            i as u16,
+            nflib::structs::OnvmNFMsg::NfStopping(nf),
+            global_state,
+        );
 
         /* If in shared core mode NFs might be sleeping */
         // REVIEW: Shared cores not implemented yet
@@ -190,26 +171,26 @@ pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
     /* Wait to process all exits */
     for _ in 0..MAX_SHUTDOWN_ITERS as usize {
         mgr::net_funcs::onvm_nf_check_status(global_state);
-        unsafe {
-            let f = &format!(
+        backend.log(
+            LOG_ERR,
+            &format!(
                 "Core {}: Waiting for {} NFs to exit\n",
-                _rte_lcore_id(),
-                *global_state.num_nfs.borrow()
-            )[..] as *const _ as *const i8;
-            rte_log(RTE_LOG_ERR, RTE_LOGTYPE_USER1, f);
-        }
+                backend.lcore_id(),
+                global_state.num_nfs.load(Ordering::SeqCst)
+            ),
+        );
         thread::sleep(sleeptime);
     }
 
-    if *global_state.num_nfs.borrow() > 0 {
-        unsafe {
-            let f = &format!(
+    if global_state.num_nfs.load(Ordering::SeqCst) > 0 {
+        backend.log(
+            LOG_ERR,
+            &format!(
                 "Core {}: Up to {} NFs may still be running and must be killed manually\n",
-                _rte_lcore_id(),
-                *global_state.num_nfs.borrow()
-            )[..] as *const _ as *const i8;
-            rte_log(RTE_LOG_ERR, RTE_LOGTYPE_USER1, f);
-        }
+                backend.lcore_id(),
+                global_state.num_nfs.load(Ordering::SeqCst)
+            ),
+        );
     }
 
     /* Clean up the shared memory */
@@ -220,26 +201,105 @@ pub fn master_thread_main(global_state: &mgr::global::GlobalNFState) {
     //                     sem_unlink(nf_wakeup_infos[i].sem_name);
     //             }
     //     }
-    unsafe {
-        let f =
-            &format!("Core {}: Master thread done\n", _rte_lcore_id())[..] as *const _ as *const i8;
-        rte_log(RTE_LOG_ERR, RTE_LOGTYPE_USER1, f);
+    backend.log(
+        LOG_ERR,
+        &format!("Core {}: Master thread done\n", backend.lcore_id()),
+    );
+}
+
+/// Work of a worker thread launched on an EAL lcore
+#[cfg(feature = "dpdk")]
+enum Worker {
+    Rx(mgr::threads::RxThreadInfo),
+    Tx(mgr::threads::TxThreadInfo),
+}
+
+#[cfg(feature = "dpdk")]
+struct WorkerArgs {
+    worker: Worker,
+    global_state: &'static mgr::global::GlobalNFState<mgr::dpdk::DpdkBackend>,
+}
+
+/*
+ * Entry point of the worker lcores
+ * The EAL runs it on the lcore's own thread, already pinned to the lcore's CPU
+ */
+#[cfg(feature = "dpdk")]
+unsafe extern "C" fn worker_main(arg: *mut c_void) -> c_int {
+    let args = Box::from_raw(arg as *mut WorkerArgs);
+    match args.worker {
+        Worker::Rx(info) => rx_thread_main(info, args.global_state),
+        Worker::Tx(info) => tx_thread_main(info, args.global_state),
     }
+    0
 }
 
 /*
  * Function to receive packets from the NIC
- * and distribute them to the default service
+ * and distribute them along the default service chain
  */
-fn rx_thread_main() {}
-fn tx_thread_main() {}
+#[cfg(feature = "dpdk")]
+fn rx_thread_main<B: Backend>(
+    info: mgr::threads::RxThreadInfo,
+    global_state: &mgr::global::GlobalNFState<B>,
+) {
+    let backend = &global_state.backend;
+    backend.log(
+        LOG_INFO,
+        &format!(
+            "Core {}: Running RX thread for RX queue {}\n",
+            info.lcore, info.queue_id
+        ),
+    );
+
+    // NOTE: the TX queues after the TX threads' ones belong to the RX threads
+    let mut bufs = PktBufs::new(global_state.num_tx_threads + info.queue_id);
+    while WORKER_KEEP_RUNNING.load(Ordering::Relaxed) {
+        /* Read ports, RSS spreads the flows of a port over the RX queues */
+        mgr::pkt_funcs::onvm_pkt_rx_poll(info.queue_id, &mut bufs, global_state);
+    }
+
+    backend.log(LOG_INFO, &format!("Core {}: RX thread done\n", info.lcore));
+}
+
+/*
+ * Function to send packets to the NIC
+ * Each TX thread only serves the NFs in its range of instance IDs
+ */
+#[cfg(feature = "dpdk")]
+fn tx_thread_main<B: Backend>(
+    info: mgr::threads::TxThreadInfo,
+    global_state: &mgr::global::GlobalNFState<B>,
+) {
+    let backend = &global_state.backend;
+    backend.log(
+        LOG_INFO,
+        &format!(
+            "Core {}: Running TX thread for NFs {} to {}\n",
+            info.lcore,
+            info.first_nf,
+            info.last_nf - 1
+        ),
+    );
+
+    // NOTE: the buffers belong to this thread, packets are batched per NF and per port and sent in one burst
+    let mut bufs = PktBufs::new(info.queue_id);
+    while WORKER_KEEP_RUNNING.load(Ordering::Relaxed) {
+        mgr::pkt_funcs::onvm_pkt_tx_poll(info.first_nf, info.last_nf, &mut bufs, global_state);
+    }
+
+    backend.log(LOG_INFO, &format!("Core {}: TX thread done\n", info.lcore));
+}
 
-fn handle_signal(sig: i32) {}
+#[allow(dead_code)] // remove once code stabilizes
+fn handle_signal(_sig: i32) {}
 
 // fn wakeup_client(nf_wakeup_info: nflib::structs::nf_wakeup_info) {}
 
+#[allow(dead_code)] // remove once code stabilizes
 fn wakeup_thread_main() {}
 
+#[cfg(feature = "dpdk")]
 pub fn main_run(args: Vec<String>) {
     // let args = std::env::args();
     // let mut _v: Vec<*mut c_char> = args
@@ -248,45 +308,77 @@ pub fn main_run(args: Vec<String>) {
     // let argc = (_v.len() + 1) as c_int;
     // let argv = _v.as_mut_ptr();
     // mem::forget(_v);
-    let cur_lcore: u32;
-    let rx_lcores: u32;
-    let tx_lcores: u32;
-    let wakeup_lcores: u32;
 
     /* initialise the system */
-    mgr::init::init(args);
+    // NOTE: the global state lives until the manager exits, leaking it lets the worker threads borrow it
+    let global_state: &'static mgr::global::GlobalNFState<mgr::dpdk::DpdkBackend> =
+        match mgr::init::init(args) {
+            Ok(global_state) => Box::leak(Box::new(global_state)),
+            Err(e) => {
+                println!("Init failed: {:?}", e);
+                return;
+            }
+        };
+
+    /* Assign each RX and TX thread its own lcore */
+    let (rx_lcores, tx_lcores) = match mgr::threads::onvm_threads_plan(global_state) {
+        Ok(plan) => plan,
+        Err(e) => {
+            println!("Unable to lay out the RX and TX threads: {:?}", e);
+            return;
+        }
+    };
+
+    /* Launch the RX and TX threads on their lcores */
+    let workers = rx_lcores
+        .into_iter()
+        .map(|info| (info.lcore, Worker::Rx(info)))
+        .chain(
+            tx_lcores
+                .into_iter()
+                .map(|info| (info.lcore, Worker::Tx(info))),
+        );
+    for (lcore, worker) in workers {
+        let args = Box::into_raw(Box::new(WorkerArgs {
+            worker,
+            global_state,
+        }));
+        if unsafe { rte_eal_remote_launch(Some(worker_main), args as *mut c_void, lcore) } != 0 {
+            unsafe { drop(Box::from_raw(args)) };
+            global_state.backend.log(
+                LOG_ERR,
+                &format!(
+                    "Core {} is already busy, can't launch a worker on it\n",
+                    lcore
+                ),
+            );
+            WORKER_KEEP_RUNNING.store(false, Ordering::SeqCst);
+            unsafe { rte_eal_mp_wait_lcore() };
+            return;
+        }
+    }
+
+    master_thread_main(global_state);
+
+    unsafe { rte_eal_mp_wait_lcore() };
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "dpdk")]
     use crate::mgr;
-    use std::ffi::CString;
-    use std::mem;
-    use std::os::raw::{c_char, c_int};
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
     #[test]
+    #[cfg(feature = "dpdk")]
     fn onvm_run_init() {
         // FIXME: How to get environment arguments in a tes?
         println!("Starting test init"); // DEBUG
                                         // let mut args = vec!["-p", "1"];
-        let args = std::env::args();
-        println!("Environment args: {:?}", &args);
-        let mut _v: Vec<*mut c_char> = args
-            .map(|arg| CString::new(&arg[..]).unwrap().into_raw())
-            .collect();
-        let argc = (_v.len() + 1) as c_int;
-        let argv = _v.as_mut_ptr();
-        mem::forget(_v);
-        unsafe {
-            println!(
-                "Calling init with argc: {} and argv: {:?}",
-                &argc,
-                &*(*argv)
-            )
-        }; // DEBUG
-        let _ = mgr::init::init(argc, argv);
+        let args: Vec<String> = std::env::args().collect();
+        println!("Calling init with args: {:?}", &args); // DEBUG
+        let _ = mgr::init::init(args);
     }
 }
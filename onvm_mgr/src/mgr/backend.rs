@@ -0,0 +1,104 @@
+/*
+ * Created on Thu Oct 22 2020:09:12:47
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use crate::nflib::structs::{OnvmNFMsg, OnvmNfInitCfg, OnvmPktMeta};
+use exitfailure::ExitFailure;
+
+/* The memory the manager shares with the NFs
+The manager only moves packets and messages through the rings, mempools and memzones of a backend,
+so the same manager runs on top of DPDK or, with the "sim" feature, on ordinary Rust memory.
+*/
+
+// Log levels, the values match RTE_LOG_ERR and RTE_LOG_INFO
+pub const LOG_ERR: u32 = 4;
+pub const LOG_INFO: u32 = 7;
+
+pub trait Backend: Send + Sync + Sized + 'static {
+	/// A packet buffer taken from the pktmbuf pool
+	type Pkt: Send;
+	/// A ring of packets, shared by the manager and a NF
+	type PktRing: Send + Sync;
+	/// A ring of messages between the manager and the NFs
+	type MsgRing: Send + Sync;
+
+	/* rings */
+	fn pkt_ring_create(&self, name: &str, size: usize) -> Result<Self::PktRing, ExitFailure>;
+
+	fn msg_ring_create(&self, name: &str, size: usize) -> Result<Self::MsgRing, ExitFailure>;
+
+	/// Enqueues as many packets as fit, the enqueued packets are taken out of pkts
+	/// Output : the number of packets enqueued
+	fn pkt_enqueue_burst(&self, ring: &Self::PktRing, pkts: &mut Vec<Self::Pkt>) -> usize;
+
+	/// Dequeues up to n packets and appends them to pkts
+	/// Output : the number of packets dequeued
+	fn pkt_dequeue_burst(&self, ring: &Self::PktRing, pkts: &mut Vec<Self::Pkt>, n: usize)
+		-> usize;
+
+	/// Enqueues a message, the message is dropped on failure
+	/// Output : 0 on success, an error code otherwise
+	fn msg_enqueue(&self, ring: &Self::MsgRing, msg: OnvmNFMsg<Self>) -> i32;
+
+	fn msg_dequeue(&self, ring: &Self::MsgRing) -> Option<OnvmNFMsg<Self>>;
+
+	/* mempools */
+	/// Returns a packet to the pktmbuf pool
+	fn pkt_free(&self, pkt: Self::Pkt);
+
+	/// The manager's metadata of a packet
+	fn pkt_meta<'a>(&self, pkt: &'a mut Self::Pkt) -> &'a mut OnvmPktMeta;
+
+	/// The bytes of a packet, starting with the Ethernet header
+	fn pkt_data<'a>(&self, pkt: &'a mut Self::Pkt) -> &'a mut [u8];
+
+	/// The RSS hash of a packet, packets of a flow have the same hash
+	fn pkt_hash(&self, pkt: &Self::Pkt) -> u32;
+
+	/// Takes a default config from the NF init config pool, NFs fill it in before sending NfStarting
+	fn nf_init_cfg_get(&self) -> Option<*mut OnvmNfInitCfg>;
+
+	/// Returns a config to the NF init config pool
+	/// # Safety
+	/// cfg must come from nf_init_cfg_get and must not be used afterwards
+	unsafe fn nf_init_cfg_put(&self, cfg: *mut OnvmNfInitCfg);
+
+	/* memzones */
+	/// Reserves a named memzone holding objs, NFs look the shared state up by name
+	/// Output : a pointer to the first object, valid as long as the backend
+	fn memzone_reserve<T: Send + Sync + 'static>(
+		&self,
+		name: &str,
+		objs: Vec<T>,
+	) -> Result<*mut T, ExitFailure>;
+
+	fn memzone_lookup<T: Send + Sync + 'static>(&self, name: &str) -> Option<*mut T>;
+
+	/* ports */
+	/// Receives up to n packets from a queue of a port and appends them to pkts
+	/// Output : the number of packets received
+	fn port_rx_burst(&self, port: u16, queue_id: u16, pkts: &mut Vec<Self::Pkt>, n: usize)
+		-> usize;
+
+	/// Sends packets on a queue of a port, the sent packets are taken out of pkts
+	/// Output : the number of packets sent
+	fn port_tx_burst(&self, port: u16, queue_id: u16, pkts: &mut Vec<Self::Pkt>) -> usize;
+
+	/* threads and time */
+	/// Number of cores NFs can be placed on
+	fn num_cores(&self) -> usize;
+
+	/// The core the calling thread runs on
+	fn lcore_id(&self) -> u32;
+
+	/// Pins the calling thread to a core
+	fn thread_set_affinity(&self, core: u16) -> Result<(), ExitFailure>;
+
+	/// A cycle counter, timer_hz cycles make a second
+	fn timer_cycles(&self) -> u64;
+
+	fn timer_hz(&self) -> u64;
+
+	fn log(&self, level: u32, msg: &str);
+}
@@ -3,23 +3,32 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+#[cfg(feature = "dpdk")]
+use super::dpdk::DpdkBackend;
 use crate::nflib;
+#[cfg(feature = "dpdk")]
 use capsule_ffi::rte_mbuf;
 use std::mem;
 
 /* Manager constants */
 pub const MBUF_CACHE_SIZE: usize = 512;
+#[cfg(feature = "dpdk")]
 pub const MBUF_OVERHEAD: usize = mem::size_of::<rte_mbuf>() + mem::size_of::<u32>(); // RTE_PKTMBUF_HEADROOM is of type u32
 pub const RX_MBUF_DATA_SIZE: usize = 2048;
+#[cfg(feature = "dpdk")]
 pub const MBUF_SIZE: usize = RX_MBUF_DATA_SIZE + MBUF_OVERHEAD;
 pub const NF_INFO_SIZE: usize = mem::size_of::<nflib::structs::OnvmNfInitCfg>();
-pub const NF_MSG_SIZE: usize = mem::size_of::<nflib::structs::OnvmNFMsg>();
+#[cfg(feature = "dpdk")]
+pub const NF_MSG_SIZE: usize = mem::size_of::<nflib::structs::OnvmNFMsg<DpdkBackend>>();
 pub const NF_MSG_CACHE_SIZE: u8 = 8;
 pub const RTE_MP_RX_DESC_DEFAULT: u16 = 512;
 pub const RTE_MP_TX_DESC_DEFAULT: u16 = 512;
 pub const NF_MSG_QUEUE_SIZE: u8 = 128;
 pub const NO_FLAGS: u8 = 0;
+// Default number of RX threads, can be changed with the -R switch
 pub const ONVM_NUM_RX_THREADS: u8 = 1;
+// Default number of TX threads, 0 uses every lcore left after the RX and auxiliary threads
+pub const ONVM_NUM_TX_THREADS: u8 = 0;
 // Number of auxiliary threads in manager, 1 reserved for stats
 pub const ONVM_NUM_MGR_AUX_THREADS: u8 = 1;
 pub const ONVM_NUM_WAKEUP_THREADS: u8 = 1; // Enabled when using shared core mode
@@ -0,0 +1,454 @@
+/*
+ * Created on Thu Oct 22 2020:09:40:21
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use super::backend::{Backend, LOG_ERR};
+use super::constants;
+use crate::error_handling::exit_on_failure;
+use crate::nflib;
+use crate::nflib::structs::{OnvmNFMsg, OnvmNfInitCfg, OnvmPktMeta};
+use capsule::dpdk::CoreId;
+use exitfailure::ExitFailure;
+use num_cpus;
+use std::ffi::{c_void, CString};
+use std::{mem, ptr, slice};
+
+// DPDK functions
+use capsule_ffi::{
+	_rte_eth_rx_burst, _rte_eth_tx_burst, _rte_get_timer_hz, _rte_get_tsc_cycles, _rte_lcore_id,
+	_rte_mempool_get, _rte_mempool_put, _rte_pktmbuf_free, _rte_ring_dequeue,
+	_rte_ring_dequeue_burst, _rte_ring_enqueue, _rte_ring_enqueue_burst, rte_log,
+	rte_mempool_create, rte_memzone_lookup, rte_memzone_reserve, rte_pktmbuf_init,
+	rte_pktmbuf_pool_init, rte_ring_create, rte_socket_id,
+};
+// DPDK structures
+use capsule_ffi::{
+	rte_eth_conf, rte_eth_conf__bindgen_ty_1, rte_eth_rss_conf, rte_eth_rxmode, rte_eth_tx_mq_mode,
+	rte_eth_txmode, rte_mbuf, rte_mempool, rte_pktmbuf_pool_private, rte_ring,
+};
+// DPDK constants
+use capsule_ffi::{
+	DEV_RX_OFFLOAD_IPV4_CKSUM, DEV_RX_OFFLOAD_TCP_CKSUM, DEV_RX_OFFLOAD_UDP_CKSUM,
+	DEV_TX_OFFLOAD_IPV4_CKSUM, DEV_TX_OFFLOAD_TCP_CKSUM, DEV_TX_OFFLOAD_UDP_CKSUM,
+	ETH_MQ_RX_RSS_FLAG, ETH_RSS_IP, ETH_RSS_L2_PAYLOAD, ETH_RSS_TCP, ETH_RSS_UDP,
+	RTE_ETHER_MAX_LEN, RTE_LOGTYPE_USER1,
+};
+
+/* The DPDK backend
+Packets are mbufs of the pktmbuf pool, the rings and memzones are created on the EAL so that
+secondary processes can look them up by name.
+*/
+
+/// A packet, a mbuf of the pktmbuf pool
+// NOTE: transparent so that a slice of packets can be handed to DPDK as an array of mbuf pointers
+#[repr(transparent)]
+pub struct DpdkPkt(*mut rte_mbuf);
+
+impl DpdkPkt {
+	pub fn mbuf(&self) -> *mut rte_mbuf {
+		self.0
+	}
+}
+
+/// A rte_ring, holding mbuf pointers or pointers to message pool objects
+pub struct DpdkRing(*mut rte_ring);
+
+// NOTE: mbufs are handed over between lcores and rte_rings are multi-producer multi-consumer safe
+unsafe impl Send for DpdkPkt {}
+unsafe impl Send for DpdkRing {}
+unsafe impl Sync for DpdkRing {}
+
+pub struct DpdkBackend {
+	pub pktmbuf_pool: *mut rte_mempool,
+	pub nf_msg_pool: *mut rte_mempool,
+	pub nf_init_cfg_pool: *mut rte_mempool,
+	// port_conf points to the RSS key, the key lives as long as the backend
+	rss_key: Box<[u8; 40]>,
+	pub port_conf: rte_eth_conf,
+}
+
+// NOTE: the mempools are multi-producer multi-consumer safe and the port configuration is only read
+unsafe impl Send for DpdkBackend {}
+unsafe impl Sync for DpdkBackend {}
+
+impl DpdkBackend {
+	/// Creates the mempools shared with the NFs, the EAL has to be initialised first
+	/// Output : the backend or an error if a mempool can't be created
+	pub fn new() -> Result<Self, ExitFailure> {
+		let mut rss_key = Box::new(nflib::constants::RSS_SYMMETRIC_KEY);
+		let rss_key_ptr = rss_key.as_mut_ptr();
+		let mut backend = DpdkBackend {
+			pktmbuf_pool: ptr::null_mut(),
+			nf_msg_pool: ptr::null_mut(),
+			nf_init_cfg_pool: ptr::null_mut(),
+			rss_key,
+			port_conf: rte_eth_conf {
+				rxmode: rte_eth_rxmode {
+					mq_mode: ETH_MQ_RX_RSS_FLAG,
+					max_rx_pkt_len: RTE_ETHER_MAX_LEN,
+					split_hdr_size: 0,
+					offloads: (DEV_RX_OFFLOAD_IPV4_CKSUM
+						| DEV_RX_OFFLOAD_UDP_CKSUM
+						| DEV_RX_OFFLOAD_TCP_CKSUM) as u64,
+					..Default::default()
+				},
+				txmode: rte_eth_txmode {
+					mq_mode: rte_eth_tx_mq_mode::ETH_MQ_TX_NONE,
+					offloads: (DEV_TX_OFFLOAD_IPV4_CKSUM
+						| DEV_TX_OFFLOAD_UDP_CKSUM
+						| DEV_TX_OFFLOAD_TCP_CKSUM) as u64,
+					..Default::default()
+				},
+				rx_adv_conf: rte_eth_conf__bindgen_ty_1 {
+					rss_conf: rte_eth_rss_conf {
+						rss_key: rss_key_ptr,
+						rss_hf: (ETH_RSS_IP | ETH_RSS_UDP | ETH_RSS_TCP | ETH_RSS_L2_PAYLOAD)
+							as u64,
+						..Default::default()
+					},
+					..Default::default()
+				},
+				..Default::default()
+			},
+		};
+
+		/* initialise mbuf pools */
+		backend.init_mbuf_pools()?;
+
+		/* initialise nf info pool */
+		backend.init_nf_init_cfg_pool()?;
+
+		/* initialise pool for NF messages */
+		backend.init_nf_msg_pool()?;
+
+		Ok(backend)
+	}
+
+	/// Initialise the mbuf pool for packet reception for the NIC, and any other buffer pools needed by the app - currently none.
+	fn init_mbuf_pools(&mut self) -> Result<(), ExitFailure> {
+		println!(
+			"Creating mbuf pool '{}' [{} mbufs] ...\n",
+			nflib::constants::PKTMBUF_POOL_NAME,
+			nflib::constants::NUM_MBUFS
+		);
+
+		let name = CString::new(nflib::constants::PKTMBUF_POOL_NAME)?;
+		self.pktmbuf_pool = unsafe {
+			rte_mempool_create(
+				name.as_ptr(),
+				nflib::constants::NUM_MBUFS.into(),
+				constants::MBUF_SIZE as u32,
+				constants::MBUF_CACHE_SIZE as u32,
+				mem::size_of::<rte_pktmbuf_pool_private>() as u32,
+				Some(rte_pktmbuf_pool_init),
+				ptr::null_mut(),
+				Some(rte_pktmbuf_init),
+				ptr::null_mut(),
+				rte_socket_id() as i32,
+				nflib::constants::NO_FLAGS,
+			)
+		};
+		if self.pktmbuf_pool.is_null() {
+			return Ok(exit_on_failure(
+				"Cannot create needed mbuf pools".into(),
+				"Failed in the init_mbuf function",
+			)?);
+		}
+		Ok(())
+	}
+
+	/// Set up a mempool to store nf_msg structs
+	fn init_nf_msg_pool(&mut self) -> Result<(), ExitFailure> {
+		/* don't pass single-producer/single-consumer flags to mbuf
+		 * create as it seems faster to use a cache instead */
+		println!(
+			"Creating mbuf pool '{}' ...\n",
+			nflib::constants::_NF_MSG_POOL_NAME
+		);
+		let name = CString::new(nflib::constants::_NF_MSG_POOL_NAME)?;
+		self.nf_msg_pool = unsafe {
+			rte_mempool_create(
+				name.as_ptr(),
+				nflib::constants::MAX_NFS * constants::NF_MSG_QUEUE_SIZE as u32,
+				constants::NF_MSG_SIZE as u32,
+				constants::NF_MSG_CACHE_SIZE as u32,
+				0,
+				None,
+				ptr::null_mut(),
+				None,
+				ptr::null_mut(),
+				rte_socket_id() as i32,
+				nflib::constants::NO_FLAGS,
+			)
+		};
+		if self.nf_msg_pool.is_null() {
+			return Ok(exit_on_failure(
+				"Cannot create nf message pool".into(),
+				"Failed in the init_nf_msg_pool function",
+			)?);
+		}
+		Ok(())
+	}
+
+	/// Set up a mempool to store nf_init_cfg structs
+	fn init_nf_init_cfg_pool(&mut self) -> Result<(), ExitFailure> {
+		println!(
+			"Creating mbuf pool '{}' ...\n",
+			nflib::constants::_NF_MEMPOOL_NAME
+		);
+		let name = CString::new(nflib::constants::_NF_MEMPOOL_NAME)?;
+		self.nf_init_cfg_pool = unsafe {
+			rte_mempool_create(
+				name.as_ptr(),
+				nflib::constants::MAX_NFS,
+				constants::NF_INFO_SIZE as u32,
+				0,
+				0,
+				None,
+				ptr::null_mut(),
+				None,
+				ptr::null_mut(),
+				rte_socket_id() as i32,
+				nflib::constants::NO_FLAGS,
+			)
+		};
+		if self.nf_init_cfg_pool.is_null() {
+			return Ok(exit_on_failure(
+				"Cannot create nf info mbuf pool".into(),
+				"Failed in the init_nf_init_cfg_pool function",
+			)?);
+		}
+		Ok(())
+	}
+
+	fn ring_create(&self, name: &str, size: usize, flags: u32) -> Result<DpdkRing, ExitFailure> {
+		let c_name = CString::new(name)?;
+		let ring =
+			unsafe { rte_ring_create(c_name.as_ptr(), size as u32, rte_socket_id() as i32, flags) };
+		if ring.is_null() {
+			exit_on_failure(
+				format!("Cannot create ring {}", name),
+				"In the DpdkBackend::ring_create function",
+			)?;
+		}
+		Ok(DpdkRing(ring))
+	}
+}
+
+impl Backend for DpdkBackend {
+	type Pkt = DpdkPkt;
+	type PktRing = DpdkRing;
+	type MsgRing = DpdkRing;
+
+	fn pkt_ring_create(&self, name: &str, size: usize) -> Result<DpdkRing, ExitFailure> {
+		// the RX and TX threads and the NF all enqueue and dequeue
+		self.ring_create(name, size, 0)
+	}
+
+	fn msg_ring_create(&self, name: &str, size: usize) -> Result<DpdkRing, ExitFailure> {
+		// MP enqueue (default), SC dequeue
+		self.ring_create(name, size, constants::RING_F_SC_DEQ)
+	}
+
+	fn pkt_enqueue_burst(&self, ring: &DpdkRing, pkts: &mut Vec<DpdkPkt>) -> usize {
+		let sent = unsafe {
+			_rte_ring_enqueue_burst(
+				ring.0,
+				pkts.as_ptr() as *const *mut c_void,
+				pkts.len() as u32,
+				ptr::null_mut(),
+			)
+		} as usize;
+		pkts.drain(..sent);
+		sent
+	}
+
+	fn pkt_dequeue_burst(&self, ring: &DpdkRing, pkts: &mut Vec<DpdkPkt>, n: usize) -> usize {
+		let len = pkts.len();
+		pkts.reserve(n);
+		unsafe {
+			let count = _rte_ring_dequeue_burst(
+				ring.0,
+				pkts.as_mut_ptr().add(len) as *mut *mut c_void,
+				n as u32,
+				ptr::null_mut(),
+			) as usize;
+			pkts.set_len(len + count);
+			count
+		}
+	}
+
+	/// The message is copied into an object of the message pool, the ring carries the object
+	fn msg_enqueue(&self, ring: &DpdkRing, msg: OnvmNFMsg<Self>) -> i32 {
+		let mut obj: *mut c_void = ptr::null_mut();
+		let ret = unsafe { _rte_mempool_get(self.nf_msg_pool, &mut obj) };
+		if ret != 0 {
+			self.log(
+				LOG_ERR,
+				"Oh the huge manatee! Unable to allocate msg from pool\n",
+			);
+			return ret;
+		}
+		unsafe {
+			ptr::write(obj as *mut OnvmNFMsg<Self>, msg);
+			let ret = _rte_ring_enqueue(ring.0, obj);
+			if ret != 0 {
+				/* The receiver never gets the message, release it with its pool object */
+				ptr::drop_in_place(obj as *mut OnvmNFMsg<Self>);
+				_rte_mempool_put(self.nf_msg_pool, obj);
+			}
+			ret
+		}
+	}
+
+	/// Moves the message out of its pool object and gives the object back
+	fn msg_dequeue(&self, ring: &DpdkRing) -> Option<OnvmNFMsg<Self>> {
+		let mut obj: *mut c_void = ptr::null_mut();
+		unsafe {
+			if _rte_ring_dequeue(ring.0, &mut obj) != 0 {
+				return None;
+			}
+			let msg = ptr::read(obj as *mut OnvmNFMsg<Self>);
+			_rte_mempool_put(self.nf_msg_pool, obj);
+			Some(msg)
+		}
+	}
+
+	fn pkt_free(&self, pkt: DpdkPkt) {
+		unsafe { _rte_pktmbuf_free(pkt.0) };
+	}
+
+	/// The metadata is kept in the mbuf's udata64 field
+	fn pkt_meta<'a>(&self, pkt: &'a mut DpdkPkt) -> &'a mut OnvmPktMeta {
+		unsafe { &mut *(&mut (*pkt.0).__bindgen_anon_5.udata64 as *mut u64 as *mut OnvmPktMeta) }
+	}
+
+	fn pkt_data<'a>(&self, pkt: &'a mut DpdkPkt) -> &'a mut [u8] {
+		// NOTE: the pool's mbufs are big enough for a whole frame, a packet is a single segment
+		unsafe {
+			let mbuf = &*pkt.0;
+			slice::from_raw_parts_mut(
+				(mbuf.buf_addr as *mut u8).add(mbuf.data_off as usize),
+				mbuf.data_len as usize,
+			)
+		}
+	}
+
+	fn pkt_hash(&self, pkt: &DpdkPkt) -> u32 {
+		unsafe { (*pkt.0).__bindgen_anon_4.hash.rss }
+	}
+
+	fn nf_init_cfg_get(&self) -> Option<*mut OnvmNfInitCfg> {
+		let mut obj: *mut c_void = ptr::null_mut();
+		unsafe {
+			if _rte_mempool_get(self.nf_init_cfg_pool, &mut obj) != 0 {
+				return None;
+			}
+			ptr::write(obj as *mut OnvmNfInitCfg, Default::default());
+		}
+		Some(obj as *mut OnvmNfInitCfg)
+	}
+
+	unsafe fn nf_init_cfg_put(&self, cfg: *mut OnvmNfInitCfg) {
+		ptr::drop_in_place(cfg);
+		_rte_mempool_put(self.nf_init_cfg_pool, cfg as *mut c_void);
+	}
+
+	fn memzone_reserve<T: Send + Sync + 'static>(
+		&self,
+		name: &str,
+		objs: Vec<T>,
+	) -> Result<*mut T, ExitFailure> {
+		let c_name = CString::new(name)?;
+		let addr = unsafe {
+			let mz = rte_memzone_reserve(
+				c_name.as_ptr(),
+				(mem::size_of::<T>() * objs.len()) as _,
+				rte_socket_id() as i32,
+				constants::NO_FLAGS.into(),
+			);
+			if mz.is_null() {
+				exit_on_failure(
+					format!("Cannot reserve memory zone {}", name),
+					"In the DpdkBackend::memzone_reserve function",
+				)?;
+			}
+			(*mz).__bindgen_anon_2.addr as *mut T
+		};
+		for (i, obj) in objs.into_iter().enumerate() {
+			unsafe { ptr::write(addr.add(i), obj) };
+		}
+		Ok(addr)
+	}
+
+	fn memzone_lookup<T: Send + Sync + 'static>(&self, name: &str) -> Option<*mut T> {
+		let c_name = CString::new(name).ok()?;
+		let mz = unsafe { rte_memzone_lookup(c_name.as_ptr()) };
+		if mz.is_null() {
+			return None;
+		}
+		Some(unsafe { (*mz).__bindgen_anon_2.addr as *mut T })
+	}
+
+	fn port_rx_burst(&self, port: u16, queue_id: u16, pkts: &mut Vec<DpdkPkt>, n: usize) -> usize {
+		let len = pkts.len();
+		pkts.reserve(n);
+		unsafe {
+			let count = _rte_eth_rx_burst(
+				port,
+				queue_id,
+				pkts.as_mut_ptr().add(len) as *mut *mut rte_mbuf,
+				n as u16,
+			) as usize;
+			pkts.set_len(len + count);
+			count
+		}
+	}
+
+	fn port_tx_burst(&self, port: u16, queue_id: u16, pkts: &mut Vec<DpdkPkt>) -> usize {
+		let sent = unsafe {
+			_rte_eth_tx_burst(
+				port,
+				queue_id,
+				pkts.as_mut_ptr() as *mut *mut rte_mbuf,
+				pkts.len() as u16,
+			)
+		} as usize;
+		pkts.drain(..sent);
+		sent
+	}
+
+	fn num_cores(&self) -> usize {
+		num_cpus::get()
+	}
+
+	fn lcore_id(&self) -> u32 {
+		unsafe { _rte_lcore_id() }
+	}
+
+	fn thread_set_affinity(&self, core: u16) -> Result<(), ExitFailure> {
+		if let Err(e) = CoreId::new(core as usize).set_thread_affinity() {
+			return Ok(exit_on_failure(
+				format!("Unable to pin the thread to core {}: {}", core, e),
+				"In the DpdkBackend::thread_set_affinity function",
+			)?);
+		}
+		Ok(())
+	}
+
+	fn timer_cycles(&self) -> u64 {
+		unsafe { _rte_get_tsc_cycles() }
+	}
+
+	fn timer_hz(&self) -> u64 {
+		unsafe { _rte_get_timer_hz() }
+	}
+
+	fn log(&self, level: u32, msg: &str) {
+		// NOTE: the message is passed as an argument so that a '%' in it is not read as a format
+		let fmt = CString::new("%s").unwrap();
+		let msg = CString::new(msg.replace('\0', "")).unwrap();
+		unsafe { rte_log(level, RTE_LOGTYPE_USER1, fmt.as_ptr(), msg.as_ptr()) };
+	}
+}
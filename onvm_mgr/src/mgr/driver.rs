@@ -0,0 +1,164 @@
+/*
+ * Created on Fri Oct 23 2020:10:12:45
+ * Created by Ratnadeep Bhattacharya
+ */
+
+/* Runs the manager from the caller's thread
+poll() stands in for the master, RX and TX threads of main_run and NFs run as threads of the
+manager. The test harness drives the manager this way on DPDK as well as on the sim backend.
+*/
+
+use super::backend::Backend;
+use super::global::GlobalNFState;
+use super::pkt_funcs::{self, PktBufs};
+use super::{net_funcs, threads};
+use crate::nflib::constants::*;
+use crate::nflib::nf_funcs;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Function prototype for the packet handlers of NF threads.
+/// The handler sets the action and destination in the packet's metadata,
+/// the backend gives it access to the metadata and the bytes of the packet.
+pub type PktHandler<B> = Box<dyn FnMut(&B, &mut <B as Backend>::Pkt) + Send>;
+
+/// Runs the manager on an initialised global state.
+/// poll() performs one iteration of the master, RX and TX threads.
+pub struct MgrDriver<B: Backend> {
+	global_state: Arc<GlobalNFState<B>>,
+	rx_bufs: PktBufs<B::Pkt>,
+	tx_bufs: Vec<(u32, u32, PktBufs<B::Pkt>)>,
+}
+
+/// Handle of a NF thread
+pub struct NfHandle {
+	instance_id: Arc<AtomicU16>,
+	start_status: Arc<AtomicU16>,
+	keep_running: Arc<AtomicBool>,
+	thread: thread::JoinHandle<()>,
+}
+
+impl NfHandle {
+	/// The instance ID the manager gave the NF, None until the NF is started
+	pub fn instance_id(&self) -> Option<u16> {
+		match self.instance_id.load(Ordering::SeqCst) {
+			0 => None,
+			id => Some(id),
+		}
+	}
+
+	/// NF_WAITING_FOR_ID while the NF starts, NF_RUNNING once it runs,
+	/// or the status the manager rejected the NF with
+	pub fn start_status(&self) -> u16 {
+		self.start_status.load(Ordering::SeqCst)
+	}
+
+	/// Tells the NF to stop and waits for its thread to exit
+	pub fn stop(self) {
+		self.keep_running.store(false, Ordering::SeqCst);
+		let _ = self.thread.join();
+	}
+}
+
+impl<B: Backend + 'static> MgrDriver<B> {
+	/// Takes over a global state whose ports and default service chain are set up.
+	/// A single RX thread is polled, on RX queue 0, and every TX thread of the state.
+	pub fn from_state(global_state: GlobalNFState<B>) -> Self {
+		let num_tx_threads = global_state.num_tx_threads.max(1);
+		let tx_bufs = threads::onvm_threads_tx_ranges(num_tx_threads as u32)
+			.into_iter()
+			.enumerate()
+			.map(|(queue_id, (first_nf, last_nf))| {
+				(first_nf, last_nf, PktBufs::new(queue_id as u16))
+			})
+			.collect();
+		Self {
+			global_state: Arc::new(global_state),
+			// NOTE: the TX queues after the TX threads' ones belong to the RX threads
+			rx_bufs: PktBufs::new(num_tx_threads),
+			tx_bufs,
+		}
+	}
+
+	pub fn global_state(&self) -> &GlobalNFState<B> {
+		&self.global_state
+	}
+
+	pub fn backend(&self) -> &B {
+		&self.global_state.backend
+	}
+
+	pub fn num_nfs(&self) -> u32 {
+		self.global_state.num_nfs.load(Ordering::SeqCst)
+	}
+
+	pub fn nf_status(&self, instance_id: u16) -> u16 {
+		let nf = *self.global_state.nfs[instance_id as usize].clone();
+		unsafe { (*nf).status.load(Ordering::SeqCst) }
+	}
+
+	/// Number of packets dropped by the manager
+	pub fn dropped(&self) -> u64 {
+		self.global_state.pkts_dropped.load(Ordering::SeqCst)
+	}
+
+	/// Number of packets sent out of a port
+	pub fn port_sent(&self, port: u16) -> u64 {
+		self.global_state.ports().tx_stats.tx[port as usize].load(Ordering::SeqCst)
+	}
+
+	/// Asks the manager to move a NF to another core, the move happens on the next poll
+	pub fn relocate_nf(&self, instance_id: u16, core: u16) {
+		net_funcs::onvm_nf_request_relocate(instance_id, core, &self.global_state);
+	}
+
+	/// One iteration of the manager: NF messages, liveness and core moves, then RX, then TX
+	pub fn poll(&mut self) {
+		let global_state = &*self.global_state;
+		net_funcs::onvm_nf_check_status(global_state);
+		net_funcs::onvm_nf_check_liveness(global_state);
+		net_funcs::onvm_nf_check_relocations(global_state);
+		pkt_funcs::onvm_pkt_rx_poll(0, &mut self.rx_bufs, global_state);
+		for (first_nf, last_nf, bufs) in self.tx_bufs.iter_mut() {
+			pkt_funcs::onvm_pkt_tx_poll(*first_nf, *last_nf, bufs, global_state);
+		}
+	}
+
+	/// Starts a NF thread for service_id on core 0.
+	/// The NF goes through the same starting/ready/stopping messages as a NF process.
+	pub fn spawn_nf(&self, service_id: u16, mut handler: PktHandler<B>) -> NfHandle {
+		let instance_id = Arc::new(AtomicU16::new(0));
+		let start_status = Arc::new(AtomicU16::new(NF_WAITING_FOR_ID));
+		let keep_running = Arc::new(AtomicBool::new(true));
+
+		let (id, status, running) = (
+			instance_id.clone(),
+			start_status.clone(),
+			keep_running.clone(),
+		);
+		let global_state = self.global_state.clone();
+		let thread = thread::spawn(move || {
+			let nf_id = match nf_funcs::onvm_nflib_start_nf(service_id, 0, &running, &global_state)
+			{
+				Ok(nf_id) => nf_id,
+				Err(nf_status) => {
+					status.store(nf_status, Ordering::SeqCst);
+					return;
+				}
+			};
+			id.store(nf_id, Ordering::SeqCst);
+			status.store(NF_RUNNING, Ordering::SeqCst);
+			let backend = &global_state.backend;
+			nf_funcs::onvm_nflib_run(nf_id, &running, |pkt| handler(backend, pkt), &global_state);
+			nf_funcs::onvm_nflib_stop(nf_id, &global_state);
+		});
+
+		NfHandle {
+			instance_id,
+			start_status,
+			keep_running,
+			thread,
+		}
+	}
+}
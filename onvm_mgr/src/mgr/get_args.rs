@@ -3,17 +3,17 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+use super::backend::Backend;
 use super::global;
 use crate::error_handling;
 use exitfailure::ExitFailure;
 use getopts::Options;
 // use lazy_static::lazy_static;
 // use log;
-use num_cpus;
 use std::ffi::{CString, OsStr};
 use std::os::raw::{c_char, c_int};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex, RwLock};
-use std::{i64, u8};
 // use std::os::unix::ffi::OsStrExt;
 
 // pub static mut ONVM_NF_SHARE_CORES: Option<RwLock<u8>> = None;
@@ -31,9 +31,9 @@ static mut PROGNAME: Option<String> = None;
 // 	pub static ref DEFAULT_SERVICE_ID: Arc<RwLock<u32>> = Arc::new(RwLock::new(1));
 // }
 
-pub fn parse_app_args(
+pub fn parse_app_args<B: Backend>(
 	max_ports: u16,
-	global_state: &mut global::GlobalNFState,
+	global_state: &mut global::GlobalNFState<B>,
 	args: Vec<String>,
 ) -> Result<(), ExitFailure> {
 	// let option_index;
@@ -42,6 +42,8 @@ pub fn parse_app_args(
 	lgopts.optopt("p", "port-mask", "", "");
 	lgopts.optopt("r", "num-services", "", "");
 	lgopts.optopt("n", "nf-cores", "", "");
+	lgopts.optopt("R", "rx-threads", "", "");
+	lgopts.optopt("T", "tx-threads", "", "");
 	// let a = unsafe { std::slice::from_raw_parts(*argv, argc as usize) };
 	// std::mem::replace(&mut *PROGNAME, a[0].to_string());
 	// unsafe { PROGNAME = Some(a[0].to_string()) }
@@ -70,10 +72,20 @@ pub fn parse_app_args(
 	if let Some(n) = matches.opt_str("n") {
 		parse_nf_cores(n, global_state);
 	}
+	if let Some(r) = matches.opt_str("R") {
+		parse_num_threads(r, &mut global_state.num_rx_threads)?;
+	}
+	if let Some(t) = matches.opt_str("T") {
+		parse_num_threads(t, &mut global_state.num_tx_threads)?;
+	}
 	Ok(())
 }
 
-fn parse_portmask(max_ports: u16, portmask: String, global_state: &mut global::GlobalNFState) {
+fn parse_portmask<B: Backend>(
+	max_ports: u16,
+	portmask: String,
+	global_state: &mut global::GlobalNFState<B>,
+) {
 	let mut count = 0;
 	let mut pm = i64::from_str_radix(&portmask[..], 16).unwrap();
 
@@ -89,26 +101,46 @@ fn parse_portmask(max_ports: u16, portmask: String, global_state: &mut global::G
 				if count >= max_ports {
 					println!("Ignoring port: {}", count);
 				} else {
-					let n = *(global_state.ports.num_ports).borrow() as usize;
-					(*global_state.ports.id.borrow_mut())[n] = count as u8;
+					let n = global_state
+						.ports()
+						.num_ports
+						.fetch_add(1, Ordering::SeqCst) as usize;
+					global_state.ports().id[n].store(count as u8, Ordering::SeqCst);
 				}
 			}
-			pm = pm >> 1;
-			count += 1;
 		}
+		pm >>= 1;
+		count += 1;
 	}
 }
 
-fn parse_num_services(services: String, global_state: &mut global::GlobalNFState) {
-	let r = u8::from_str_radix(&services[..], 10).unwrap();
-	*global_state.num_services.borrow_mut() = r;
+fn parse_num_services<B: Backend>(services: String, global_state: &mut global::GlobalNFState<B>) {
+	let r = services.parse::<u8>().unwrap();
+	global_state.num_services = r;
+}
+
+fn parse_num_threads(threads: String, count: &mut u16) -> Result<(), ExitFailure> {
+	match threads.parse::<u16>() {
+		Ok(n) => {
+			*count = n;
+			Ok(())
+		}
+		Err(e) => {
+			let e = format!("Invalid thread count {}: {}", threads, e);
+			Ok(error_handling::exit_on_failure(
+				e,
+				"In the parse_num_threads function",
+			)?)
+		}
+	}
 }
 
-fn parse_nf_cores(nf_coremask: String, global_state: &mut global::GlobalNFState) {
-	let max_cores = num_cpus::get();
+fn parse_nf_cores<B: Backend>(nf_coremask: String, global_state: &mut global::GlobalNFState<B>) {
+	// only the cores with a status in the cores memzone can run NFs
+	let max_cores = global_state.cores.len();
 	let mut num_cores = 0;
 	let mut count = 0;
-	let mut pm = u8::from_str_radix(&nf_coremask[..], 10).unwrap();
+	let mut pm = nf_coremask.parse::<u8>().unwrap();
 	if pm == 0 {
 		println!("WARNING: No NF cores are being used.\n");
 		println!("         Restart onvm_mgr with a valid coremask to run NFs.\n");
@@ -125,15 +157,14 @@ fn parse_nf_cores(nf_coremask: String, global_state: &mut global::GlobalNFState)
 				// let core = unsafe { global_state.cores.clone()[count] };
 				// let core = **global_state.cores.clone()[count].borrow_mut();
 				unsafe {
-					*(*(*global_state.cores[count].clone())).enabled.borrow_mut() = true;
-					*(*(*global_state.cores[count].clone()))
-						.nf_count
-						.borrow_mut() = 0;
+					let core = *global_state.cores[count].clone();
+					(*core).enabled.store(true, Ordering::SeqCst);
+					(*core).nf_count.store(0, Ordering::SeqCst);
 				}
 				num_cores += 1;
 			}
 		}
-		pm = pm >> 1;
+		pm >>= 1;
 		count += 1;
 		if count == max_cores {
 			break;
@@ -143,7 +174,11 @@ fn parse_nf_cores(nf_coremask: String, global_state: &mut global::GlobalNFState)
 	count = 0;
 	println!("Registered {} cores for NFs: ", num_cores);
 	for i in 0..max_cores {
-		let enabled = unsafe { *(*(*global_state.cores[count].clone())).enabled.borrow() };
+		let enabled = unsafe {
+			(*(*global_state.cores[count].clone()))
+				.enabled
+				.load(Ordering::SeqCst)
+		};
 		if enabled {
 			print!("{}", i);
 			if count != num_cores - 1 {
@@ -152,5 +187,5 @@ fn parse_nf_cores(nf_coremask: String, global_state: &mut global::GlobalNFState)
 			count += 1;
 		}
 	}
-	println!("");
+	println!();
 }
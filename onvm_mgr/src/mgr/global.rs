@@ -3,125 +3,146 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+use super::backend::Backend;
+use super::{constants, net_funcs};
 use crate::nflib;
-// DPDK functions
-use capsule_ffi::{rte_eth_conf, rte_ring};
-// DPDK structs
-use capsule_ffi::{
-	rte_eth_conf__bindgen_ty_1, rte_eth_rss_conf, rte_eth_rxmode, rte_eth_tx_mq_mode,
-	rte_eth_txmode, rte_mempool,
-};
-// DPDK constants
-use capsule_ffi::{
-	DEV_RX_OFFLOAD_IPV4_CKSUM, DEV_RX_OFFLOAD_TCP_CKSUM, DEV_RX_OFFLOAD_UDP_CKSUM,
-	DEV_TX_OFFLOAD_IPV4_CKSUM, DEV_TX_OFFLOAD_TCP_CKSUM, DEV_TX_OFFLOAD_UDP_CKSUM,
-	ETH_MQ_RX_RSS_FLAG, ETH_RSS_IP, ETH_RSS_L2_PAYLOAD, ETH_RSS_TCP, ETH_RSS_UDP,
-	RTE_ETHER_MAX_LEN,
-};
-
-use capsule::dpdk::Mempool;
-
-use fragile::Fragile;
-use std::cell::RefCell;
-use std::ffi::c_void;
-use std::ptr;
-use std::sync::{Arc, RwLock};
+use exitfailure::ExitFailure;
+use std::sync::atomic::{AtomicU32, AtomicU64};
+use std::sync::{Arc, Mutex, RwLock};
 
 /* the struct denoting the global state */
-pub struct GlobalNFState {
-	// REVIEW: is the type correct? Do they need to be thread-safe (RwLock)?
+// NOTE: the global state is shared by the master, RX and TX threads, so every field that changes
+// after init is either an atomic, behind a lock, or a backend object that is safe to share (rings and mempools)
+pub struct GlobalNFState<B: Backend> {
 	// REVIEW: Still debating if global state fields should be Arc<RwLock<_>> or not. A speed vs safety debate.
-	// NOTE: the lifetime is static since we expect the global state to last throughout the program
-	// pub incoming_msg_queue: RefCell<*mut rte_ring>,
-	pub incoming_msg_queue: RefCell<rte_ring>,
-	// pub pktmbuf_pool: RefCell<*mut rte_mempool>,
-	// pub nf_msg_pool: RefCell<*mut rte_mempool>,
-	// pub nf_init_cfg_pool: RefCell<*mut rte_mempool>,
-	pub pktmbuf_pool: RefCell<Mempool>,     // RefCell<rte_mempool>,
-	pub nf_msg_pool: RefCell<Mempool>,      // RefCell<rte_mempool>,
-	pub nf_init_cfg_pool: RefCell<Mempool>, // RefCell<rte_mempool>,
-	pub services: Vec<RefCell<*mut c_void>>,
-	pub nf_per_service_count: Vec<RefCell<u32>>,
+	// the rings, mempools and memzones shared with the NFs
+	pub backend: B,
+	pub incoming_msg_queue: B::MsgRing, // MP enqueue, SC dequeue by the master thread
+	// instance IDs of the running NFs of every service
+	pub services: Vec<RwLock<Vec<u16>>>,
+	pub nf_per_service_count: Vec<AtomicU32>,
 	pub num_sockets: RwLock<u16>,
 	pub default_chain: RwLock<nflib::structs::OnvmServiceChain>,
-	pub onvm_config: Arc<nflib::structs::OnvmConfiguration>,
-	pub nfs: Vec<Arc<*mut nflib::structs::OnvmNF>>, // Arc<Vec<RefCell<&'static mut nflib::structs::OnvmNF>>>,
-	// pub ports: Vec<&'static nflib::structs::PortInfo>,
-	pub ports: Arc<nflib::structs::PortInfo>,
+	// NOTE: the config and the port information live in memzones of the backend, they are only
+	// lent out through onvm_config() and ports() so they can't outlive the state
+	onvm_config: *mut nflib::structs::OnvmConfiguration,
+	pub nfs: Vec<Arc<*mut nflib::structs::OnvmNF<B>>>,
+	ports: *mut nflib::structs::PortInfo,
 	pub cores: Vec<Arc<*mut nflib::structs::CoreStatus>>,
-	pub num_services: RefCell<u8>,
+	pub num_services: u8,
 	// pub global_stats_sleep_time: u8, // also used to run the main thread of onvm
 	// pub global_verbosity_level: u8,
 	// pub global_pkt_limit: u8,
 	// pub global_time_to_live: u8,
-	pub num_nfs: RefCell<u32>,
+	pub num_nfs: AtomicU32,
+	// the next instance ID handed out, wraps around to reuse the IDs of stopped NFs
+	pub next_instance_id: AtomicU32,
+	// packets dropped by the manager, by the service chain or because a ring was full
+	pub pkts_dropped: AtomicU64,
+	// core moves requested by the operator as (instance ID, core), carried out by the master thread
+	pub relocate_requests: Mutex<Vec<(u16, u16)>>,
+	// worker thread counts, set from the command line
+	pub num_rx_threads: u16,
+	pub num_tx_threads: u16,
 	pub default_service: u16,
 	pub default_service_id: u16,
 	pub onvm_nf_share_cores: bool,
-	pub port_conf: rte_eth_conf,
 }
 
-impl Default for GlobalNFState {
-	fn default() -> Self {
-		GlobalNFState {
-			// incoming_msg_queue: RefCell::new(ptr::null_mut()),
-			incoming_msg_queue: RefCell::(newptr::null_mut()),
-			pktmbuf_pool: Default::default(), //RefCell::new(ptr::null_mut()),
-			nf_msg_pool: Default::default(),  //RefCell::new(ptr::null_mut()),
-			// nf_msg_pool: Some(ptr::null_mut()),
-			nf_init_cfg_pool: Default::default(), //RefCell::new(ptr::null_mut()),
-			services: vec![],
-			nf_per_service_count: vec![],
+impl<B: Backend> GlobalNFState<B> {
+	/// Sets up the memory shared with the NFs on top of a backend
+	/// Input  : the backend the manager runs on
+	/// Output : the global state or an error if a memzone or a ring can't be created
+	pub fn new(backend: B) -> Result<Self, ExitFailure> {
+		/* set up array for NF tx data */
+		let mz_nf = backend.memzone_reserve::<nflib::structs::OnvmNF<B>>(
+			nflib::constants::MZ_NF_INFO,
+			(0..nflib::constants::MAX_NFS)
+				.map(|_| Default::default())
+				.collect(),
+		)?;
+		let nfs = (0..nflib::constants::MAX_NFS as usize)
+			.map(|i| Arc::new(unsafe { mz_nf.add(i) }))
+			.collect();
+
+		/* set up ports info */
+		let mz_port =
+			backend.memzone_reserve(nflib::constants::MZ_PORT_INFO, vec![Default::default()])?;
+
+		/* set up core status */
+		let num_cores = backend.num_cores();
+		let mz_cores = backend.memzone_reserve::<nflib::structs::CoreStatus>(
+			nflib::constants::MZ_CORES_STATUS,
+			(0..num_cores).map(|_| Default::default()).collect(),
+		)?;
+		// NOTE: the pointers are shared through the global state, which is Sync
+		#[allow(clippy::arc_with_non_send_sync)]
+		let cores = (0..num_cores)
+			.map(|i| Arc::new(unsafe { mz_cores.add(i) }))
+			.collect();
+
+		/* set up custom flags */
+		let mz_onvm_config =
+			backend.memzone_reserve(nflib::constants::MZ_ONVM_CONFIG, vec![Default::default()])?;
+
+		/* initialise a queue for newly created NFs */
+		let incoming_msg_queue = backend.msg_ring_create(
+			nflib::constants::_MGR_MSG_QUEUE_NAME,
+			nflib::constants::MAX_NFS as usize,
+		)?;
+
+		let mut global_state = GlobalNFState {
+			backend,
+			incoming_msg_queue,
+			services: (0..nflib::constants::MAX_SERVICES)
+				.map(|_| RwLock::new(vec![]))
+				.collect(),
+			nf_per_service_count: (0..nflib::constants::MAX_SERVICES)
+				.map(|_| AtomicU32::new(0))
+				.collect(),
 			num_sockets: RwLock::new(0),
 			default_chain: RwLock::new(Default::default()),
-			onvm_config: unsafe {
-				Arc::from_raw(ptr::null_mut() as *mut nflib::structs::OnvmConfiguration)
-			},
-			nfs: Vec::with_capacity(nflib::constants::MAX_NFS as usize),
-			ports: unsafe { Arc::from_raw(ptr::null_mut() as *mut nflib::structs::PortInfo) },
-			// cores: Arc::new(vec![]),
-			cores: vec![],
-			num_services: RefCell::new(nflib::constants::MAX_SERVICES),
-			// global_stats_sleep_time: 1,
-			// global_verbosity_level: 0,
-			// global_pkt_limit: 0,
-			// global_time_to_live: 0,
-			num_nfs: RefCell::new(0),
+			onvm_config: mz_onvm_config,
+			nfs,
+			ports: mz_port,
+			cores,
+			num_services: nflib::constants::MAX_SERVICES,
+			num_nfs: AtomicU32::new(0),
+			next_instance_id: AtomicU32::new(net_funcs::STARTING_INSTANCE_ID),
+			pkts_dropped: AtomicU64::new(0),
+			relocate_requests: Mutex::new(vec![]),
+			num_rx_threads: constants::ONVM_NUM_RX_THREADS.into(),
+			num_tx_threads: constants::ONVM_NUM_TX_THREADS.into(),
 			default_service: 0,
 			default_service_id: 0,
 			onvm_nf_share_cores: false,
-			port_conf: rte_eth_conf {
-				rxmode: rte_eth_rxmode {
-					mq_mode: ETH_MQ_RX_RSS_FLAG,
-					max_rx_pkt_len: RTE_ETHER_MAX_LEN,
-					split_hdr_size: 0,
-					offloads: (DEV_RX_OFFLOAD_IPV4_CKSUM
-						| DEV_RX_OFFLOAD_UDP_CKSUM
-						| DEV_RX_OFFLOAD_TCP_CKSUM) as u64,
-					..Default::default()
-				},
-				txmode: rte_eth_txmode {
-					mq_mode: rte_eth_tx_mq_mode::ETH_MQ_TX_NONE,
-					offloads: (DEV_TX_OFFLOAD_IPV4_CKSUM
-						| DEV_TX_OFFLOAD_UDP_CKSUM
-						| DEV_TX_OFFLOAD_TCP_CKSUM) as u64,
-					..Default::default()
-				},
-				rx_adv_conf: rte_eth_conf__bindgen_ty_1 {
-					rss_conf: rte_eth_rss_conf {
-						rss_key: nflib::constants::RSS_SYMMETRIC_KEY.get_mut() as *mut _ as *mut u8,
-						// rss_key: rss_symmetric_key.as_mut_ptr(),
-						rss_hf: (ETH_RSS_IP | ETH_RSS_UDP | ETH_RSS_TCP | ETH_RSS_L2_PAYLOAD)
-							as u64,
-						..Default::default()
-					},
-					..Default::default()
-				},
-				..Default::default()
-			},
-		}
+		};
+		set_default_config(global_state.onvm_config());
+
+		/* the NF rings have to exist before any NF looks them up */
+		net_funcs::onvm_nf_init_rings(&mut global_state)?;
+		Ok(global_state)
+	}
+
+	/// The flags shared with the NFs
+	pub fn onvm_config(&self) -> &nflib::structs::OnvmConfiguration {
+		unsafe { &*self.onvm_config }
 	}
+
+	/// The ports in use and their statistics, shared with the NFs
+	pub fn ports(&self) -> &nflib::structs::PortInfo {
+		unsafe { &*self.ports }
+	}
+}
+
+// Initialise the default onvm config structure
+fn set_default_config(config: &nflib::structs::OnvmConfiguration) {
+	match nflib::constants::ONVM_NF_SHARE_CORES_DEFAULT {
+		true => config.set_flag(0),
+		false => config.set_flag(1),
+	};
 }
 
-unsafe impl std::marker::Sync for GlobalNFState {}
-unsafe impl std::marker::Send for GlobalNFState {}
+// NOTE: the raw pointers point into memzones of the backend, whose contents are atomics and locks
+unsafe impl<B: Backend> std::marker::Sync for GlobalNFState<B> {}
+unsafe impl<B: Backend> std::marker::Send for GlobalNFState<B> {}
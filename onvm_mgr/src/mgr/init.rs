@@ -3,66 +3,40 @@
  * Created by Ratnadeep Bhattacharya
  */
 
-use super::{constants, get_args, global};
+use super::dpdk::DpdkBackend;
+use super::{constants, get_args, global, threads};
 use crate::error_handling::exit_on_failure;
 use crate::nflib;
 use exitfailure::ExitFailure;
-use failure;
-use fragile::Fragile;
-use libc::fflush;
-use num_cpus;
-use std::ffi::{c_void, CString};
-use std::os::raw::{c_char, c_int};
-// use std::rc::Rc;
-use std::cell::RefCell;
-use std::sync::Arc;
-use std::{mem, ptr};
+use std::mem;
+use std::sync::atomic::Ordering;
 // NOTE: don't depend on the actual values of ENOTSUP and ENODEV. These two are required in the init_port function
-use capsule::dpdk::{eal_cleanup, eal_init, Mempool};
+use capsule::dpdk::{eal_cleanup, eal_init};
 use libc::{EINVAL, EIO, ENODEV, ENOMEM, ENOTSUP};
 
 // DPDK structures
 use capsule_ffi::{
-	rte_eth_conf, rte_eth_conf__bindgen_ty_1, rte_eth_dev_info, rte_eth_link, rte_eth_rss_conf,
-	rte_eth_rxconf, rte_eth_rxmode, rte_eth_txconf, rte_eth_txmode, rte_ether_addr, rte_mbuf,
-	rte_mempool, rte_memzone, rte_pktmbuf_pool_private, rte_ring,
+	rte_eth_conf, rte_eth_dev_info, rte_eth_link, rte_eth_rxconf, rte_eth_txconf, rte_ether_addr,
 };
 // DPDK functions
 use capsule_ffi::{
-	_rte_errno, rte_calloc, rte_delay_us_sleep, rte_eal_init,
-	rte_eth_dev_adjust_nb_rx_tx_desc, rte_eth_dev_configure, rte_eth_dev_count_avail,
-	rte_eth_dev_info_get, rte_eth_dev_socket_id, rte_eth_dev_start, rte_eth_link_get_nowait,
-	rte_eth_macaddr_get, rte_eth_promiscuous_enable, rte_eth_rx_queue_setup,
-	rte_eth_tx_queue_setup, rte_exit, rte_lcore_count, rte_mempool_create, rte_memzone_reserve,
-	rte_pktmbuf_init, rte_pktmbuf_pool_init, rte_ring_create, rte_socket_id, rte_strerror,
+	rte_delay_us_sleep, rte_eth_dev_adjust_nb_rx_tx_desc, rte_eth_dev_configure,
+	rte_eth_dev_count_avail, rte_eth_dev_info_get, rte_eth_dev_socket_id, rte_eth_dev_start,
+	rte_eth_link_get_nowait, rte_eth_macaddr_get, rte_eth_promiscuous_enable,
+	rte_eth_rx_queue_setup, rte_eth_tx_queue_setup,
 };
 // DPDK constants
-use capsule_ffi::{
-	rte_eth_tx_mq_mode, DEV_RX_OFFLOAD_IPV4_CKSUM, DEV_RX_OFFLOAD_TCP_CKSUM,
-	DEV_RX_OFFLOAD_UDP_CKSUM, DEV_TX_OFFLOAD_IPV4_CKSUM, DEV_TX_OFFLOAD_MBUF_FAST_FREE,
-	DEV_TX_OFFLOAD_TCP_CKSUM, DEV_TX_OFFLOAD_UDP_CKSUM, ETH_LINK_FULL_DUPLEX, ETH_MQ_RX_RSS_FLAG,
-	ETH_RSS_IP, ETH_RSS_L2_PAYLOAD, ETH_RSS_TCP, ETH_RSS_UDP, RTE_ETHER_MAX_LEN, RTE_MEMZONE_2MB,
-};
+use capsule_ffi::{DEV_TX_OFFLOAD_MBUF_FAST_FREE, ETH_LINK_FULL_DUPLEX};
 
 /// Start the OpenNetVM manager
-pub fn init(mut args: Vec<String>) -> Result<(), ExitFailure> {
+pub fn init(args: Vec<String>) -> Result<global::GlobalNFState<DpdkBackend>, ExitFailure> {
 	// the entire global state struct is wrapped inside fragile
 	// REVIEW: Do they need to be thread-safe (Fragile)?
 	// NOTE: Fragile marker is taken out because GlobalState is now marked as Sync
 	println!("Inside init"); // DEBUG
-	let mut global_state: global::GlobalNFState = Default::default();
-
-	let retval: i32;
-	let mut mz_nf: rte_memzone;
-	let mut mz_port: rte_memzone;
-	let mut mz_cores: rte_memzone;
-	let mut mz_scp: rte_memzone;
-	let mut mz_services: rte_memzone;
-	let mut mz_nf_per_service: rte_memzone;
-	let mut mz_onvm_config: rte_memzone;
 	let total_ports: u16;
+	let onvm_args: Vec<String>;
 	let mut port_id: u8;
-	let i: u8;
 
 	unsafe {
 		// println!("Inside init: argc = {} and argv = {:?}", &argc, &*(*argv)); // DEBUG
@@ -109,16 +83,12 @@ pub fn init(mut args: Vec<String>) -> Result<(), ExitFailure> {
 			}
 		};
 		println!("dpdk_args: {:?}", dpdk_args);
-		let _o;
-		let onvm_args = match all_args.next() {
-			Some(s) => s,
-			None => {
-				_o = [("".to_string())];
-				&_o
-			}
+		onvm_args = match all_args.next() {
+			Some(s) => Vec::from(s),
+			None => vec!["".to_string()],
 		};
 		println!("onvm_args: {:?}", onvm_args);
-		if let Err(_) = eal_init(Vec::from(dpdk_args)) {
+		if eal_init(Vec::from(dpdk_args)).is_err() {
 			if let Err(e) = eal_cleanup() {
 				panic!("{:?}", e);
 			}
@@ -140,329 +110,51 @@ pub fn init(mut args: Vec<String>) -> Result<(), ExitFailure> {
 		/* get total number of ports */
 		total_ports = rte_eth_dev_count_avail();
 		println!("Got total ports: {}", total_ports);
-
-		/* set up array for NF tx data */
-		let mut tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_NF_INFO as *const _ as *const i8,
-			(mem::size_of::<nflib::structs::OnvmNF>() * nflib::constants::MAX_NFS as usize) as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			unsafe { println!("{:?}", *rte_strerror(_rte_errno())) };
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for nf information\n" as *const _ as *const i8,
-			);
-		}
-		mz_nf = *tmp;
-		mz_nf.__bindgen_anon_2.addr = mem::zeroed();
-		// convert this memory chunk into a vector of OnvmNF referenes
-		let mut addr = mz_nf.__bindgen_anon_2.addr as usize;
-		let mut v_tmp = vec![];
-		let sz = mem::size_of::<nflib::structs::OnvmNF>();
-		for i in 0..nflib::constants::MAX_NFS {
-			// v_tmp.push(RefCell::from(&mut *(addr as *mut nflib::structs::OnvmNF)));
-			v_tmp.push(Arc::new(addr as *mut nflib::structs::OnvmNF));
-			addr += sz;
-		}
-		global_state.nfs = v_tmp;
-
-		/* set up ports info */
-		tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_PORT_INFO as *const _ as *const i8,
-			mem::size_of::<nflib::structs::PortInfo>() as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for port information\n" as *const _ as *const i8,
-			);
-		}
-		mz_port = *tmp;
-		mz_port.__bindgen_anon_2.addr = mem::zeroed();
-		global_state.ports =
-			Arc::from_raw(mz_port.__bindgen_anon_2.addr as *mut nflib::structs::PortInfo);
-
-		/* set up core status */
-		let cores = num_cpus::get();
-		tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_CORES_STATUS as *const _ as *const i8,
-			(mem::size_of::<nflib::structs::CoreStatus>() * cores) as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for core information\n" as *const _ as *const i8,
-			);
-		}
-		mz_cores = *tmp;
-		mz_cores.__bindgen_anon_2.addr = mem::zeroed();
-		// convert this memory chunk into a vector of CoreStatus referenes
-		addr = mz_cores.__bindgen_anon_2.addr as usize;
-		let mut v_tmp = vec![];
-		let sz = mem::size_of::<nflib::structs::CoreStatus>();
-		for i in 0..cores {
-			let a = Arc::new(addr as *mut nflib::structs::CoreStatus);
-			// v_tmp.push(&mut *(addr as *mut nflib::structs::CoreStatus));
-			v_tmp.push(a);
-			addr += sz;
-		}
-		global_state.cores = v_tmp;
-
-		/* set up array for NF tx data */
-		// NOTE: get a chunk of memory for the services
-		tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_SERVICES_INFO as *const _ as *const i8,
-			(mem::size_of::<nflib::structs::CoreStatus>() * nflib::constants::MAX_SERVICES as usize)
-				as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for services information\n" as *const _ as *const i8,
-			);
-		}
-		mz_services = *tmp;
-		mz_services.__bindgen_anon_2.addr = mem::zeroed();
-		// convert this memory chunk into a vector of c_void pointers
-		addr = mz_services.__bindgen_anon_2.addr as usize;
-		let mut v_tmp = vec![];
-		let sz = mem::size_of::<c_void>();
-		for i in 0..cores {
-			v_tmp.push(RefCell::new(addr as *mut c_void));
-			addr += sz;
-		}
-		global_state.services = v_tmp;
-		// NOTE: allocate memory for NFs per service
-		for i in 0..nflib::constants::MAX_SERVICES {
-			global_state.services[i as usize] = RefCell::from(rte_calloc(
-				"one service NFs" as *const _ as *const i8,
-				nflib::constants::MAX_NFS_PER_SERVICE as u64,
-				mem::size_of::<c_void>() as u64,
-				0,
-			));
-		}
-
-		tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_NF_PER_SERVICE_INFO as *const _ as *const i8,
-			mem::size_of::<c_void>() as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for NF per service information.\n" as *const _
-					as *const i8,
-			);
-		}
-		mz_nf_per_service = *tmp;
-		// REVIEW: Is nf_per_service supposed to be a vector of u32s?
-		mz_nf_per_service.__bindgen_anon_2.addr = mem::zeroed();
-		global_state.nf_per_service_count = Vec::from_raw_parts(
-			&mut RefCell::from(*(mz_nf_per_service.__bindgen_anon_2.addr as *mut u32)),
-			0,
-			nflib::constants::MAX_NFS_PER_SERVICE as usize,
-		);
-
-		/* set up custom flags */
-		tmp = _rte_memzone_reserve(
-			nflib::constants::MZ_ONVM_CONFIG as *const _ as *const i8,
-			mem::size_of::<nflib::structs::OnvmConfiguration>() as u64,
-			rte_socket_id() as i32,
-			constants::NO_FLAGS.into(),
-		);
-		if tmp.is_null() {
-			rte_exit(
-				1,
-				"Cannot reserve memory zone for ONVM custom flags.\n" as *const _ as *const i8,
-			);
-		}
-		mz_onvm_config = *tmp;
-		mz_onvm_config.__bindgen_anon_2.addr = mem::zeroed();
-		// NOTE: OnvmConfiguration can be marked Copy for the next line to work but this will copy a memory region - not a wise move
-		// So use Arc
-		global_state.onvm_config = Arc::from_raw(
-			mz_onvm_config.__bindgen_anon_2.addr as *mut nflib::structs::OnvmConfiguration,
-		);
-		// global_state
-		// .onvm_config
-		// .set_flag(nflib::constants::ONVM_NF_SHARE_CORES_DEFAULT);
-		set_default_config(global_state.onvm_config.clone());
-
-		/* parse additional, application arguments */
-		// NOTE: parse_app_args return an ExitFailure and so does init. Thus we can simply use ? to pass an error up to whichever executable uses this lib
-		// get_args::parse_app_args(total_ports, &mut global_state, argc, argv)?;
-		get_args::parse_app_args(total_ports, &mut global_state, Vec::from(onvm_args))?;
-
-		/* initialise mbuf pools */
-		init_mbuf_pools(&mut global_state)?;
-
-		/* initialise nf info pool */
-		init_nf_init_cfg_pool(&mut global_state)?;
-
-		/* initialise pool for NF messages */
-		init_nf_msg_pool(&mut global_state)?;
-
-		/* now initialise the ports we will use */
-		let end = *(global_state.ports.num_ports).borrow() as usize; // immutable borrow has to end before init_port can borrow global_state mutably
-		for i in 0..end {
-			port_id = (*global_state.ports.id.borrow())[i];
-			let mut r = rte_ether_addr {
-				addr_bytes: (*global_state.ports.mac[port_id as usize].borrow_mut()).addr_bytes,
-			};
-			rte_eth_macaddr_get(port_id.into(), &mut r as *mut rte_ether_addr);
-			init_port(&mut global_state, port_id)?;
-			// onvm_stats_gen_event_info(event_msg_buf, ONVM_EVENT_PORT_INFO, NULL);
-		}
-
-		check_all_ports_link_status(!0x0, &mut global_state)?;
-
-		/* initialise a queue for newly created NFs */
-		init_info_queue(&mut global_state);
-
-		/* initialise the shared memory for shared core mode */
-		// init_shared_sem();
-		/*initialize a default service chain*/
-		// TODO: Implement service chains
-		// default_chain = onvm_sc_create();
-		// retval = onvm_sc_append_entry(default_chain, ONVM_NF_ACTION_TONF, 1);
-		/* set up service chain pointer shared to NFs*/
-		// TODO:
-		// mz_scp = rte_memzone_reserve(MZ_SCP_INFO, sizeof(struct onvm_service_chain *), rte_socket_id(), NO_FLAGS);
-		// if (mz_scp == NULL)
-		// rte_exit(EXIT_FAILURE, "Cannot reserve memory zone for service chain pointer\n");
-		// memset(mz_scp->addr, 0, sizeof(struct onvm_service_chain *));
-		// default_sc_p = mz_scp->addr;
-		// *default_sc_p = default_chain;
-		// onvm_sc_print(default_chain);
-
-		// onvm_flow_dir_init();
 	} // unsafe ends
-	Ok(())
-}
 
-// Initialise the default onvm config structure
-fn set_default_config(config: Arc<nflib::structs::OnvmConfiguration>) {
-	match nflib::constants::ONVM_NF_SHARE_CORES_DEFAULT {
-		true => config.set_flag(0),
-		false => config.set_flag(1),
-	};
-}
+	/* initialise the mbuf, nf info and NF message pools */
+	let backend = DpdkBackend::new()?;
 
-/// Initialise the mbuf pool for packet reception for the NIC, and any other buffer pools needed by the app - currently none.
-fn init_mbuf_pools(global_state: &mut global::GlobalNFState) -> Result<(), ExitFailure> {
-	println!(
-		"Creating mbuf pool '{}' [{} mbufs] ...\n",
-		nflib::constants::PKTMBUF_POOL_NAME,
-		nflib::constants::NUM_MBUFS
-	);
+	/* set up the memzones shared with the NFs, the NF rings and the queue for newly created NFs */
+	let mut global_state = global::GlobalNFState::new(backend)?;
 
-	*global_state.pktmbuf_pool.borrow_mut() = unsafe {
-		*rte_mempool_create(
-			nflib::constants::PKTMBUF_POOL_NAME as *const _ as *const i8,
-			nflib::constants::NUM_MBUFS.into(),
-			constants::MBUF_SIZE as u32,
-			constants::MBUF_CACHE_SIZE as u32,
-			mem::size_of::<rte_pktmbuf_pool_private>() as u32,
-			Some(rte_pktmbuf_pool_init),
-			ptr::null_mut() as *mut c_void,
-			Some(rte_pktmbuf_init),
-			ptr::null_mut() as *mut c_void,
-			rte_socket_id() as i32,
-			nflib::constants::NO_FLAGS,
-		)
-	};
-	// if (*global_state.pktmbuf_pool.borrow()).is_null() {
-	// 	return Ok(exit_on_failure(
-	// 		"Cannot create needed mbuf pools".into(),
-	// 		"Failed in the init_mbuf function",
-	// 	)?);
-	// }
-	Ok(())
-}
+	/* parse additional, application arguments */
+	// NOTE: parse_app_args return an ExitFailure and so does init. Thus we can simply use ? to pass an error up to whichever executable uses this lib
+	get_args::parse_app_args(total_ports, &mut global_state, onvm_args)?;
 
-/// Set up a mempool to store nf_msg structs
-fn init_nf_msg_pool(global_state: &mut global::GlobalNFState) -> Result<(), ExitFailure> {
-	/* don't pass single-producer/single-consumer flags to mbuf
-		* create as it seems faster to use a cache instead */
-	println!(
-		"Creating mbuf pool '{}' ...\n",
-		nflib::constants::_NF_MSG_POOL_NAME
-	);
-	*global_state.nf_msg_pool.borrow_mut() = unsafe {
-		*rte_mempool_create(
-			nflib::constants::_NF_MSG_POOL_NAME as *const _ as *const i8,
-			(nflib::constants::MAX_NFS as u32 * constants::NF_MSG_QUEUE_SIZE as u32).into(),
-			constants::NF_INFO_SIZE as u32,
-			constants::NF_MSG_CACHE_SIZE as u32,
-			0,
-			None,
-			ptr::null_mut() as *mut c_void,
-			None,
-			ptr::null_mut() as *mut c_void,
-			rte_socket_id() as i32,
-			nflib::constants::NO_FLAGS,
-		)
-	};
-	// if (*global_state.pktmbuf_pool.borrow()).is_null() {
-	// 	let f = unsafe {
-	// 		format!(
-	// 			"Cannot create nf info mbuf pool: {:?}",
-	// 			rte_strerror(_rte_errno())
-	// 		)
-	// 	};
-	// 	return Ok(exit_on_failure(
-	// 		f,
-	// 		"Failed in the init_nf_msg_pool function",
-	// 	)?);
-	// }
-	Ok(())
-}
+	/* the port queues depend on the number of RX and TX threads */
+	threads::onvm_threads_resolve_counts(&mut global_state)?;
 
-/// Set up a mempool to store nf_init_cfg structs
-fn init_nf_init_cfg_pool(global_state: &mut global::GlobalNFState) -> Result<(), ExitFailure> {
-	println!(
-		"Creating mbuf pool '{}' ...\n",
-		nflib::constants::_NF_MEMPOOL_NAME
-	);
+	/* now initialise the ports we will use */
+	let end = global_state.ports().num_ports.load(Ordering::SeqCst) as usize;
+	for i in 0..end {
+		port_id = global_state.ports().id[i].load(Ordering::SeqCst);
+		let mut r = rte_ether_addr {
+			addr_bytes: global_state.ports().mac[port_id as usize]
+				.lock()
+				.unwrap()
+				.addr_bytes,
+		};
+		unsafe { rte_eth_macaddr_get(port_id.into(), &mut r as *mut rte_ether_addr) };
+		init_port(&mut global_state, port_id)?;
+		// onvm_stats_gen_event_info(event_msg_buf, ONVM_EVENT_PORT_INFO, NULL);
+	}
 
-	*global_state.nf_init_cfg_pool.borrow_mut() = unsafe {
-		*rte_mempool_create(
-			nflib::constants::_NF_MEMPOOL_NAME as *const _ as *const i8,
-			nflib::constants::MAX_NFS.into(),
-			constants::NF_INFO_SIZE as u32,
-			0,
-			0,
-			None,
-			ptr::null_mut() as *mut c_void,
-			None,
-			ptr::null_mut() as *mut c_void,
-			rte_socket_id() as i32,
-			nflib::constants::NO_FLAGS,
-		)
-	};
-	// if (*global_state.nf_init_cfg_pool.borrow()).is_null() {
-	// 	let f = unsafe {
-	// 		format!(
-	// 			"Cannot create nf info mbuf pool: {:?}\n",
-	// 			rte_strerror(_rte_errno())
-	// 		)
-	// 	};
-	// 	return Ok(exit_on_failure(
-	// 		f,
-	// 		"Failed in the init_nf_msg_pool function",
-	// 	)?);
-	// }
-	// println!("Cannot create nf message pool: %s\n", rte_strerror(rte_errno));
-	Ok(())
+	check_all_ports_link_status(!0x0, &global_state)?;
+
+	/* initialise the shared memory for shared core mode */
+	// init_shared_sem();
+	/*initialize a default service chain*/
+	global_state
+		.default_chain
+		.write()
+		.unwrap()
+		.append_entry(nflib::structs::OnvmAction::TONF, 1)?;
+	// TODO: set up service chain pointer shared to NFs
+	// mz_scp = rte_memzone_reserve(MZ_SCP_INFO, sizeof(struct onvm_service_chain *), rte_socket_id(), NO_FLAGS);
+
+	// onvm_flow_dir_init();
+	Ok(global_state)
 }
 
 /// Initialise an individual port:
@@ -470,17 +162,21 @@ fn init_nf_init_cfg_pool(global_state: &mut global::GlobalNFState) -> Result<(),
 /// - set up each rx ring, to pull from the main mbuf pool
 /// - set up each tx ring
 /// - start the port and report its status to stdout
-fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(), ExitFailure> {
-	let rx_rings = constants::ONVM_NUM_RX_THREADS;
+fn init_port(
+	global_state: &mut global::GlobalNFState<DpdkBackend>,
+	port_num: u8,
+) -> Result<(), ExitFailure> {
+	/* One RSS queue per rx thread */
+	let rx_rings = global_state.num_rx_threads;
 	let mut rx_ring_size = constants::RTE_MP_RX_DESC_DEFAULT;
-	/* Set the number of tx_rings equal to the tx threads. This mimics the onvm_mgr tx thread calculation. */
-	let tx_rings =
-		unsafe { rte_lcore_count() - rx_rings as u32 - constants::ONVM_NUM_MGR_AUX_THREADS as u32 };
+	/* Every tx and rx thread owns a tx queue, the rx threads send the packets the default chain
+	 * sends out directly. The tx threads take queues [0, T) and the rx threads [T, T + R) */
+	let tx_rings = global_state.num_tx_threads + global_state.num_rx_threads;
 	let mut tx_ring_size = constants::RTE_MP_TX_DESC_DEFAULT;
 	let mut rxq_conf: rte_eth_rxconf;
 	let mut txq_conf: rte_eth_txconf;
-	let mut dev_info = unsafe { mem::MaybeUninit::<rte_eth_dev_info>::uninit().assume_init() };
-	let mut local_port_conf: rte_eth_conf = global_state.port_conf;
+	let mut dev_info: rte_eth_dev_info = unsafe { mem::zeroed() };
+	let mut local_port_conf: rte_eth_conf = global_state.backend.port_conf;
 	let q: u16;
 	let mut retval: i32;
 
@@ -518,16 +214,16 @@ fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(
 		as i64 & dev_info.flow_type_rss_offloads as i64)
 		as u64;
 	if local_port_conf.rx_adv_conf.rss_conf.rss_hf
-		!= global_state.port_conf.rx_adv_conf.rss_conf.rss_hf
+		!= global_state.backend.port_conf.rx_adv_conf.rss_conf.rss_hf
 	{
-		println!("Port {} modified RSS hash function based on hardware support, requested: {} configured: {}", port_num, global_state.port_conf.rx_adv_conf.rss_conf.rss_hf, local_port_conf.rx_adv_conf.rss_conf.rss_hf);
+		println!("Port {} modified RSS hash function based on hardware support, requested: {} configured: {}", port_num, global_state.backend.port_conf.rx_adv_conf.rss_conf.rss_hf, local_port_conf.rx_adv_conf.rss_conf.rss_hf);
 	}
 
 	retval = unsafe {
 		rte_eth_dev_configure(
 			port_num.into(),
-			rx_rings.into(),
-			tx_rings as u16,
+			rx_rings,
+			tx_rings,
 			&local_port_conf,
 		)
 	};
@@ -561,7 +257,7 @@ fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(
 				rx_ring_size,
 				rte_eth_dev_socket_id(port_num.into()) as u32,
 				&rxq_conf,
-				&mut global_state.pktmbuf_pool as *mut _ as *mut rte_mempool,
+				global_state.backend.pktmbuf_pool,
 			)
 		};
 		if retval == -EIO {
@@ -583,8 +279,8 @@ fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(
 	}
 
 	txq_conf = dev_info.default_txconf;
-	txq_conf.offloads = global_state.port_conf.txmode.offloads;
-	for q in 0..rx_rings as usize {
+	txq_conf.offloads = global_state.backend.port_conf.txmode.offloads;
+	for q in 0..tx_rings as usize {
 		retval = unsafe {
 			rte_eth_tx_queue_setup(
 				port_num.into(),
@@ -623,7 +319,7 @@ fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(
 		)?);
 	}
 
-	global_state.ports.init.borrow_mut()[port_num as usize] = 1;
+	global_state.ports().init[port_num as usize].store(1, Ordering::SeqCst);
 
 	println!("Initialised ports");
 	Ok(())
@@ -632,31 +328,25 @@ fn init_port(global_state: &mut global::GlobalNFState, port_num: u8) -> Result<(
 /// Check the link status of all ports in up to 9s, and print them finally
 fn check_all_ports_link_status(
 	port_mask: u32,
-	global_state: &global::GlobalNFState,
+	global_state: &global::GlobalNFState<DpdkBackend>,
 ) -> Result<(), ExitFailure> {
-	let port_num = *global_state.ports.num_ports.borrow();
+	let port_num = global_state.ports().num_ports.load(Ordering::SeqCst);
 	let portid: usize;
 	let count: u8;
 	let mut all_ports_up;
 	let mut retval: i32;
 	let mut print_flag = 0;
 
-	let mut link = unsafe { mem::MaybeUninit::<rte_eth_link>::uninit().assume_init() };
+	let mut link: rte_eth_link = unsafe { mem::zeroed() };
 
 	println!("Checking link status");
 	for count in 0..constants::MAX_CHECK_TIME as usize {
 		all_ports_up = 1;
 		for portid in 0..port_num {
-			if (port_mask as i64) & (1 << (*global_state.ports.id.borrow_mut())[portid as usize])
-				!= 0
-			{
+			let port = global_state.ports().id[portid as usize].load(Ordering::SeqCst);
+			if (port_mask as i64) & (1 << port) != 0 {
 				link = unsafe { mem::zeroed() };
-				retval = unsafe {
-					rte_eth_link_get_nowait(
-						(*global_state.ports.id.borrow_mut())[portid as usize].into(),
-						&mut link,
-					)
-				};
+				retval = unsafe { rte_eth_link_get_nowait(port.into(), &mut link) };
 				if retval == -ENOTSUP {
 					return Ok(exit_on_failure(
 						"function is not supported in PMD driver.".to_string(),
@@ -670,23 +360,17 @@ fn check_all_ports_link_status(
 				}
 				if print_flag == 1 {
 					if link.link_status() != 0 {
-						let duplex;
-						if link.link_duplex() == ETH_LINK_FULL_DUPLEX as u16 {
-							duplex = "full-duplex";
+						let duplex = if link.link_duplex() == ETH_LINK_FULL_DUPLEX as u16 {
+							"full-duplex"
 						} else {
-							duplex = "half-duplex";
-						}
+							"half-duplex"
+						};
 						println!(
 							"Port {} link up - speed {} Mbps - {}",
-							(*global_state.ports.id.borrow_mut())[portid as usize],
-							link.link_status(),
-							"full-duplex"
+							port, link.link_speed, duplex
 						);
 					} else {
-						println!(
-							"Port {} link down",
-							(*global_state.ports.id.borrow_mut())[portid as usize]
-						);
+						println!("Port {} link down", port);
 						continue;
 					}
 				}
@@ -714,23 +398,3 @@ fn check_all_ports_link_status(
 	} // outer for loop
 	Ok(())
 }
-
-/// Allocate a rte_ring for newly created NFs
-fn init_info_queue(global_state: &mut global::GlobalNFState) {
-	*global_state.incoming_msg_queue.borrow_mut() = unsafe {
-		*rte_ring_create(
-			nflib::constants::_MGR_MSG_QUEUE_NAME as *const _ as *const i8,
-			nflib::constants::MAX_NFS.into(),
-			rte_socket_id() as i32,
-			constants::RING_F_SC_DEQ,
-		)
-	}; // MP enqueue (default), SC dequeue
-	 // if (*global_state.incoming_msg_queue.borrow()).is_null() {
-	 // 	unsafe {
-	 // 		rte_exit(
-	 // 			1,
-	 // 			"Cannot create incoming msg queue" as *const _ as *const i8,
-	 // 		);
-	 // 	}
-	 // }
-}
@@ -3,18 +3,35 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+#[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
+// remove once the code stabilises
+pub mod backend;
 #[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
 // remove once the code stabilises
 pub mod constants;
 #[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
 // remove once the code stabilises
+#[cfg(feature = "dpdk")]
+pub mod dpdk;
+#[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
+// remove once the code stabilises
+pub mod driver;
+#[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
+// remove once the code stabilises
 pub mod get_args;
 #[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
 // remove once the code stabilises
 pub mod global;
 #[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
 // remove once the code stabilises
+#[cfg(feature = "dpdk")]
 pub mod init;
 #[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
 // remove once the code stabilises
 pub mod net_funcs;
+#[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
+// remove once the code stabilises
+pub mod pkt_funcs;
+#[allow(dead_code, unused_variables, unused_assignments, unused_imports)]
+// remove once the code stabilises
+pub mod threads;
@@ -3,508 +3,689 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+use super::backend::{Backend, LOG_ERR, LOG_INFO};
 use super::global;
 use crate::nflib;
 
-// DPDK functions
-use capsule_ffi::{
-	_rte_atomic16_dec, _rte_mempool_get, _rte_mempool_put, _rte_pktmbuf_free, _rte_ring_count,
-	_rte_ring_dequeue, _rte_ring_dequeue_bulk, _rte_ring_dequeue_burst, _rte_ring_enqueue,
-	rte_exit, rte_free, rte_log, rte_mempool_lookup,
-};
-
-// DPDK constants
-use capsule_ffi::{RTE_LOGTYPE_USER1, RTE_LOG_ERR, RTE_LOG_INFO};
-
-// DPDK structures
-use capsule_ffi::{rte_mbuf, rte_mempool};
-
 use crate::error_handling::exit_on_failure;
 use exitfailure::ExitFailure;
-use std::cell::RefCell;
-use std::ffi::c_void;
-use std::sync::Arc;
-use std::{mem, ptr};
+use libc::{kill, ESRCH};
+use std::io;
+use std::sync::atomic::Ordering;
 
-const NXT_INSTANCE_ID: RefCell<u32> = RefCell::new(1);
-const STARTING_INSTANCE_ID: RefCell<u32> = RefCell::new(1);
+pub const STARTING_INSTANCE_ID: u32 = 1;
 
 /******************************Internal functions*****************************/
 /// Function starting a NF
-/// Input  : a pointer to the NF's informations
+/// Input  : a pointer to the NF's init config, the instance ID and the status are written back to it
 /// Output : an error code
-fn onvm_nf_start(
-	nf_init_cfg: &mut nflib::structs::OnvmNfInitCfg,
-	global_state: &global::GlobalNFState,
+fn onvm_nf_start<B: Backend>(
+	nf_init_cfg: *mut nflib::structs::OnvmNfInitCfg,
+	global_state: &global::GlobalNFState<B>,
 ) -> Result<(), ExitFailure> {
-	let spawned_nf: &nflib::structs::OnvmNF;
-	let nf_id: u32;
-	let ret: i32;
+	let cfg = unsafe { &*nf_init_cfg };
 
-	if nf_init_cfg.service_id != nflib::constants::NF_WAITING_FOR_ID {
+	if cfg.status.load(Ordering::SeqCst) != nflib::constants::NF_WAITING_FOR_ID {
 		return Ok(exit_on_failure(
-			"NF waiting for ID".into(),
+			"NF is not waiting for an ID".into(),
 			"In the onvm_nf_start function",
 		)?);
 	}
-	// NOTE: In this case, user can't pass NF IDs but everything is assigned by the system
-	nf_id = onvm_nf_next_instance_id(global_state);
-	let spawned_nf = unsafe { &*global_state.nfs.clone()[nf_id as usize] };
-	// let spawned_nf = *(&global_state.nfs).clone()[nf_id as usize].borrow_mut();
-
-	// spawned_nf = *global_state.nfs[nf_id as usize].borrow_mut();
-
-	if nf_id >= nflib::constants::MAX_NFS {
-		// Service ID must be less than MAX_SERVICES and greater than 0
-		nf_init_cfg.status = nflib::constants::NF_SERVICE_MAX;
+	if cfg.service_id >= nflib::constants::MAX_SERVICES as u16 {
+		// Service ID must be less than MAX_SERVICES
+		cfg.status
+			.store(nflib::constants::NF_SERVICE_MAX, Ordering::SeqCst);
 		return Ok(exit_on_failure(
 			"NF Service Max".into(),
 			"In the onvm_nf_start function",
 		)?);
 	}
-
-	if *global_state.nf_per_service_count[nf_init_cfg.service_id as usize].borrow()
+	if global_state.nf_per_service_count[cfg.service_id as usize].load(Ordering::SeqCst)
 		>= nflib::constants::MAX_NFS_PER_SERVICE
 	{
-		nf_init_cfg.status = nflib::constants::NF_SERVICE_COUNT_MAX;
-		nf_init_cfg.status = nflib::constants::NF_SERVICE_MAX;
+		cfg.status
+			.store(nflib::constants::NF_SERVICE_COUNT_MAX, Ordering::SeqCst);
 		return Ok(exit_on_failure(
 			"Service per NF Count Max".into(),
 			"In the onvm_nf_start function",
 		)?);
 	}
+	if cfg.core as usize >= global_state.cores.len() {
+		cfg.status
+			.store(nflib::constants::NF_CORE_OUT_OF_RANGE, Ordering::SeqCst);
+		return Ok(exit_on_failure(
+			"NF Core out of range".into(),
+			"In the onvm_nf_start function",
+		)?);
+	}
 
-	// REVIEW: Since the system only is assigning IDs, is this truly necessary?
-	unsafe {
-		if nflib::funcs_macros::onvm_nf_is_valid(&*(*spawned_nf)) {
-			// This NF is trying to declare an ID already in use
-			nf_init_cfg.status = nflib::constants::NF_ID_CONFLICT;
-			return Ok(exit_on_failure(
-				"NF ID Conflict".into(),
-				"In the onvm_nf_start function",
-			)?);
-		}
+	// NOTE: In this case, user can't pass NF IDs but everything is assigned by the system
+	let nf_id = onvm_nf_next_instance_id(global_state);
+	if nf_id >= nflib::constants::MAX_NFS {
+		cfg.status
+			.store(nflib::constants::NF_NO_IDS, Ordering::SeqCst);
+		return Ok(exit_on_failure(
+			"No NF IDs available".into(),
+			"In the onvm_nf_start function",
+		)?);
 	}
+	let spawned_nf = unsafe { &*(*global_state.nfs[nf_id as usize].clone()) };
 
-	// Keep reference to this NF in the manager
-	nf_init_cfg.instance_id = nf_id as u16;
+	// REVIEW: Since the system only is assigning IDs, is this truly necessary?
+	if onvm_nf_is_in_use(spawned_nf) {
+		// This NF is trying to declare an ID already in use
+		cfg.status
+			.store(nflib::constants::NF_ID_CONFLICT, Ordering::SeqCst);
+		return Ok(exit_on_failure(
+			"NF ID Conflict".into(),
+			"In the onvm_nf_start function",
+		)?);
+	}
 
+	/* Keep reference to this NF in the manager */
+	spawned_nf.instance_id.store(nf_id as u16, Ordering::SeqCst);
+	spawned_nf
+		.service_id
+		.store(cfg.service_id, Ordering::SeqCst);
+	spawned_nf.thread_info.parent.store(0, Ordering::SeqCst);
+	// Start the liveness clock, a NF that dies before it reports ready is still reclaimed
+	spawned_nf
+		.heartbeat
+		.store(global_state.backend.timer_cycles(), Ordering::SeqCst);
+	// Secondary process NFs are also monitored through their pid
+	spawned_nf.pid.store(cfg.pid, Ordering::SeqCst);
+	spawned_nf.nf_init_cfg.store(nf_init_cfg, Ordering::SeqCst);
+
+	/* Place the NF on its core */
+	spawned_nf
+		.thread_info
+		.core
+		.store(cfg.core, Ordering::SeqCst);
+	unsafe {
+		(*(*global_state.cores[cfg.core as usize].clone()))
+			.nf_count
+			.fetch_add(1, Ordering::SeqCst);
+	}
+	spawned_nf
+		.status
+		.store(nflib::constants::NF_STARTING, Ordering::SeqCst);
+
+	/* Hand the instance ID to the NF, which waits on the status */
+	cfg.instance_id.store(nf_id as u16, Ordering::SeqCst);
+	cfg.status
+		.store(nflib::constants::NF_STARTING, Ordering::SeqCst);
 	Ok(())
 }
 
 /// Function to mark a NF as ready.
 /// Input  : a pointer to the NF's informations
 /// Output : an error code
-fn onvm_nf_ready(
-	ready: *mut nflib::structs::OnvmNF,
-	global_state: &global::GlobalNFState,
+fn onvm_nf_ready<B: Backend>(
+	ready: *mut nflib::structs::OnvmNF<B>,
+	global_state: &global::GlobalNFState<B>,
 ) -> Result<(), ExitFailure> {
+	let (nf_id, service_id) = unsafe {
+		if (*ready).status.load(Ordering::SeqCst) != nflib::constants::NF_STARTING {
+			return Ok(exit_on_failure(
+				"NF is not starting".into(),
+				"In the onvm_nf_ready function",
+			)?);
+		}
+		(
+			(*ready).instance_id.load(Ordering::SeqCst),
+			(*ready).service_id.load(Ordering::SeqCst),
+		)
+	};
+
+	// NfReady is a sign of life as well
+	unsafe {
+		(*ready)
+			.heartbeat
+			.store(global_state.backend.timer_cycles(), Ordering::SeqCst);
+	}
+
+	/* Register the NF with its service so that it starts getting packets */
+	global_state.services[service_id as usize]
+		.write()
+		.unwrap()
+		.push(nf_id);
+	global_state.nf_per_service_count[service_id as usize].fetch_add(1, Ordering::SeqCst);
+	global_state.num_nfs.fetch_add(1, Ordering::SeqCst);
+	unsafe {
+		(*ready)
+			.status
+			.store(nflib::constants::NF_RUNNING, Ordering::SeqCst);
+	}
 	Ok(())
 }
 
 /// Function stopping a NF.
 /// Input  : a pointer to the NF's informations
 /// Output : an error code
-fn onvm_nf_stop(
-	stop: *mut nflib::structs::OnvmNF,
-	global_state: &global::GlobalNFState,
+fn onvm_nf_stop<B: Backend>(
+	stop: *mut nflib::structs::OnvmNF<B>,
+	global_state: &global::GlobalNFState<B>,
+) -> Result<(), ExitFailure> {
+	onvm_nf_teardown(
+		stop,
+		nflib::constants::NF_STOPPED,
+		global_state,
+		"In the onvm_nf_stop function",
+	)
+}
+
+/// Releases everything a starting or running NF holds: its core, service slot,
+/// queued packets and messages, and its init config.
+/// Both NFs that stop and dead NFs that are reclaimed go through here.
+/// The NF is marked with its new status and fenced off its rings before they are drained,
+/// NF_STOPPED also frees its instance ID.
+/// Input  : a pointer to the NF's informations, the status it is left in and the context to report errors with
+/// Output : an error code
+fn onvm_nf_teardown<B: Backend>(
+	nf: *mut nflib::structs::OnvmNF<B>,
+	dead_status: u16,
+	global_state: &global::GlobalNFState<B>,
+	context: &str,
 ) -> Result<(), ExitFailure> {
 	let nf_id: u16;
 	let nf_status: u16;
 	let service_id: u16;
-	let mut nb_pkts: u16;
-	let mut msg: nflib::structs::OnvmNFMsg =
-		unsafe { mem::MaybeUninit::<nflib::structs::OnvmNFMsg>::uninit().assume_init() };
-	let nf_info_mp: *mut rte_mempool;
-	let mut pkts: Vec<*mut rte_mbuf> = Vec::with_capacity(nflib::constants::PACKET_READ_SIZE);
-	let candidate_nf_id: u16;
-	let candidate_core: u16;
-	let map_index: i32;
-
-	let nf_id = unsafe { *(*stop).instance_id.borrow_mut() };
-	// nf_id = unsafe { *(*stop).instance_id.borrow_mut() };
-	service_id = unsafe { *(*stop).service_id.borrow_mut() };
-	nf_status = unsafe { *(*stop).status.borrow_mut() };
-	candidate_core = unsafe { (*(*stop).thread_info.borrow_mut()).core };
-
-	/* Cleanup the allocated tag */
+	let core: u16;
+
 	unsafe {
-		rte_free(&mut (*(*stop).tag.borrow_mut())[..] as *mut _ as *mut c_void);
+		nf_id = (*nf).instance_id.load(Ordering::SeqCst);
+		service_id = (*nf).service_id.load(Ordering::SeqCst);
+		nf_status = (*nf).status.load(Ordering::SeqCst);
+		core = (*nf).thread_info.core.load(Ordering::SeqCst);
 
 		/* Cleanup should only happen if NF was starting or running */
-		if *(*stop).status.borrow() != nflib::constants::NF_STARTING
-			&& *(*stop).status.borrow() != nflib::constants::NF_RUNNING
-			&& *(*stop).status.borrow() != nflib::constants::NF_PAUSED
+		if nf_status != nflib::constants::NF_STARTING
+			&& nf_status != nflib::constants::NF_RUNNING
+			&& nf_status != nflib::constants::NF_PAUSED
 		{
 			return Ok(exit_on_failure(
 				"NF is not running or starting".into(),
-				"In the onvm_nf_stop function",
+				context,
 			)?);
 		}
 
-		*(*stop).status.borrow_mut() = nflib::constants::NF_STOPPED;
-		let parent: usize;
-		let mut children_cnt;
-		// unsafe {
-		*(*(*global_state.nfs[*(*stop).instance_id.borrow() as usize]))
-			.status
-			.borrow_mut() = nflib::constants::NF_STOPPED;
-		// *(*global_state.nfs.clone()[*stop.instance_id.borrow() as usize])
-		// 	.status
-		// 	.borrow_mut() = nflib::constants::NF_STOPPED
-		/* Tell parent we stopped running */
-		parent = (*(*(*global_state.nfs[nf_id as usize].clone()))
-			.thread_info
-			.borrow())
-		.parent as usize;
-		// let parent = (*(*global_state.nfs.clone()[nf_id as usize])
-		// 	.thread_info
-		// 	.borrow())
-		// .parent as usize;
-		children_cnt =
-			(*(*(*global_state.nfs[parent].clone())).thread_info.borrow()).children_count;
-		// let mut children_cnt =
-		// 	(*(*global_state.nfs.clone()[parent]).thread_info.borrow()).children_count;
+		/* A NF that is no longer valid stops using its rings, see onvm_nflib_run */
+		(*nf).status.store(dead_status, Ordering::SeqCst);
+		(*nf).heartbeat.store(0, Ordering::SeqCst);
+		/* A reclaimed NF is still watched through its pid until it exits */
+		if dead_status == nflib::constants::NF_STOPPED {
+			(*nf)
+				.pid
+				.store(nflib::constants::NF_NO_PID, Ordering::SeqCst);
+		}
+
+		/* Cleanup the allocated tag */
+		(*nf).tag.lock().unwrap().clear();
 
+		/* Tell parent we stopped running */
+		let parent = (*nf).thread_info.parent.load(Ordering::SeqCst) as usize;
 		if parent != 0 {
-			_rte_atomic16_dec(&mut children_cnt);
+			let parent_nf = *global_state.nfs[parent].clone();
+			(*parent_nf)
+				.thread_info
+				.children_count
+				.fetch_sub(1, Ordering::SeqCst);
 		}
 
 		/* Remove the NF from the core it was running on */
-		// unsafe {
-		// let mut core = *global_state.cores.clone()[(*stop.thread_info.borrow()).core as usize];
-		// let mut core = unsafe {**global_state.cores.clone()[stop.thread_info.core as usize].borrow_mut()};
-		// *core.nf_count.borrow_mut() -= 1;
-		// *core.is_dedicated_core.borrow_mut() = 0;
-		// }
-		*(*(*global_state.cores[(*(*stop).thread_info.borrow()).core as usize].clone()))
+		let core_status = *global_state.cores[core as usize].clone();
+		let _ = (*core_status)
 			.nf_count
-			.borrow_mut() -= 1;
-		// *(*global_state.cores.clone()[(*(*stop).thread_info.borrow()).core as usize])
-		// 	.nf_count
-		// 	.borrow_mut() -= 1;
-		*(*(*global_state.cores[(*(*stop).thread_info.borrow()).core as usize].clone()))
-			.is_dedicated_core
-			.borrow_mut() = 0;
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+		(*core_status).is_dedicated_core.store(0, Ordering::SeqCst);
+
+		/* Wait for the NF to let go of its rings, it can't take them again */
+		let _fence = (*nf).ring_lock.lock().unwrap();
 
 		/* Clean up possible left over objects in rings */
-		let rx_ring_opt = *(**global_state.nfs[nf_id as usize]).rx_q.borrow_mut();
-		match rx_ring_opt {
-			Some(rx_ring) => {
-				nb_pkts = _rte_ring_dequeue_burst(
-					rx_ring,
-					&mut (pkts.as_mut_ptr() as *mut c_void),
-					nflib::constants::PACKET_READ_SIZE as u32,
-					ptr::null_mut(),
-				) as u16;
-				while nb_pkts > 0 {
-					for i in 0..nb_pkts as usize {
-						_rte_pktmbuf_free(pkts[i]);
-					}
-					nb_pkts = _rte_ring_dequeue_burst(
-						rx_ring,
-						&mut (pkts.as_mut_ptr() as *mut c_void),
-						nflib::constants::PACKET_READ_SIZE as u32,
-						ptr::null_mut(),
-					) as u16;
-				}
-			}
-			None => {
-				rte_exit(1, "Missing rx packet mempool\n" as *const _ as *const i8);
-			}
+		if let Some(rx_ring) = &(*nf).rx_q {
+			onvm_nf_drain_ring(rx_ring, global_state);
+		}
+		if let Some(tx_ring) = &(*nf).tx_q {
+			onvm_nf_drain_ring(tx_ring, global_state);
+		}
+		/* Release the messages the NF will never read */
+		if let Some(msg_q) = &(*nf).msg_q {
+			onvm_nf_drain_msgs(msg_q, global_state);
 		}
 
-		let tx_ring_opt = *(**global_state.nfs[nf_id as usize]).tx_q.borrow_mut();
-		match tx_ring_opt {
-			Some(tx_ring) => {
-				nb_pkts = _rte_ring_dequeue_burst(
-					tx_ring,
-					&mut (pkts.as_mut_ptr() as *mut c_void),
-					nflib::constants::PACKET_READ_SIZE as u32,
-					ptr::null_mut(),
-				) as u16;
-				while nb_pkts > 0 {
-					for i in 0..nb_pkts as usize {
-						_rte_pktmbuf_free(pkts[i]);
-					}
-					nb_pkts = _rte_ring_dequeue_burst(
-						tx_ring,
-						&mut (pkts.as_mut_ptr() as *mut c_void),
-						nflib::constants::PACKET_READ_SIZE as u32,
-						ptr::null_mut(),
-					) as u16;
-				}
-			}
-			None => {
-				rte_exit(1, "Missing tx packet mempool\n" as *const _ as *const i8);
-			}
+		/* Free info struct */
+		let nf_init_cfg = (*nf)
+			.nf_init_cfg
+			.swap(std::ptr::null_mut(), Ordering::SeqCst);
+		if !nf_init_cfg.is_null() {
+			global_state.backend.nf_init_cfg_put(nf_init_cfg);
 		}
+	} // end of unsafe block
 
-		*global_state.nf_msg_pool.borrow_mut() =
-			*rte_mempool_lookup(&nflib::constants::_NF_MSG_POOL_NAME[..] as *const _ as *const i8);
-		// let a = *global_state.nf_msg_pool.borrow_mut();
-		let _msg_q = *(*(*global_state.nfs[nf_id as usize].clone()))
-			.msg_q
-			.borrow_mut();
-		// let _msg_q = unsafe { *((*global_state.nfs.clone()[dest as usize]).msg_q).borrow_mut() };
-		match _msg_q {
-			Some(msg_q) => {
-				let mut m = match ptr::NonNull::new(&mut msg) {
-					Some(p) => (p.as_ptr() as *mut _ as *mut c_void),
-					None => (ptr::null_mut() as *mut c_void),
-				};
-				while _rte_ring_dequeue(msg_q, &mut m) == 0 {
-					// while _rte_ring_dequeue(msg_q, m) == 0 {
-					// while _rte_ring_dequeue(msg_q, &mut (msg as *mut _ as *mut c_void)) == 0 {
-					// if let Some(msg_pool) = global_state.nf_msg_pool {
-					// 	_rte_mempool_put(msg_pool, m);
-					// _rte_mempool_put(global_state.nf_msg_pool, &mut msg as *mut _ as *mut c_void)
-					_rte_mempool_put(&mut *global_state.nf_msg_pool.borrow_mut(), m);
-					// }
-				}
-			}
-			None => rte_exit(1, "NF Msg pool unavailable" as *const _ as *const i8),
-		};
+	/* Further cleanup is only required if NF was succesfully started */
+	if nf_status != nflib::constants::NF_RUNNING && nf_status != nflib::constants::NF_PAUSED {
+		return Ok(());
+	}
 
-		/* Free info struct */
-		/* Lookup mempool for nf struct */
-		nf_info_mp =
-			rte_mempool_lookup(nflib::constants::_NF_MEMPOOL_NAME as *const _ as *const i8);
-		if nf_info_mp.is_null() {
+	/* Decrease the total number of RUNNING NFs */
+	global_state.num_nfs.fetch_sub(1, Ordering::SeqCst);
+
+	/* Reset stats */
+	// onvm_stats_clear_nf(nf_id);
+
+	/* Remove this NF from the service map, the remaining instances keep their order */
+	global_state.services[service_id as usize]
+		.write()
+		.unwrap()
+		.retain(|&id| id != nf_id);
+	global_state.nf_per_service_count[service_id as usize].fetch_sub(1, Ordering::SeqCst);
+
+	Ok(())
+}
+
+/// Frees all the packets still queued on an NF ring.
+/// Input  : the rx or tx ring of the NF
+fn onvm_nf_drain_ring<B: Backend>(ring: &B::PktRing, global_state: &global::GlobalNFState<B>) {
+	let mut pkts = Vec::with_capacity(nflib::constants::PACKET_READ_SIZE);
+	while global_state.backend.pkt_dequeue_burst(
+		ring,
+		&mut pkts,
+		nflib::constants::PACKET_READ_SIZE,
+	) > 0
+	{
+		for pkt in pkts.drain(..) {
+			global_state.backend.pkt_free(pkt);
+		}
+	}
+}
+
+/// Releases the messages still queued for a NF.
+/// Input  : the message ring of the NF
+fn onvm_nf_drain_msgs<B: Backend>(msg_q: &B::MsgRing, global_state: &global::GlobalNFState<B>) {
+	while global_state.backend.msg_dequeue(msg_q).is_some() {}
+}
+
+/// Function reclaiming the resources of a NF that died without sending NfStopping.
+/// The NF goes through the same teardown as a NF that stops. A NF that only stopped sending
+/// heartbeats may still be alive, it keeps its instance ID until it exits.
+/// Input  : the instance ID of the dead NF and whether its process is gone
+/// Output : an error code
+fn onvm_nf_reclaim<B: Backend>(
+	nf_id: u16,
+	process_gone: bool,
+	global_state: &global::GlobalNFState<B>,
+) -> Result<(), ExitFailure> {
+	let nf = *global_state.nfs[nf_id as usize].clone();
+	let dead_status = if process_gone {
+		nflib::constants::NF_STOPPED
+	} else {
+		nflib::constants::NF_RECLAIMED
+	};
+	onvm_nf_teardown(
+		nf,
+		dead_status,
+		global_state,
+		"In the onvm_nf_reclaim function",
+	)
+}
+
+/// A NF slot is in use from the moment its NF gets an instance ID until the NF is torn down,
+/// or until a reclaimed NF exits
+fn onvm_nf_is_in_use<B: Backend>(nf: &nflib::structs::OnvmNF<B>) -> bool {
+	let status = nf.status.load(Ordering::SeqCst);
+	status == nflib::constants::NF_STARTING
+		|| status == nflib::constants::NF_RUNNING
+		|| status == nflib::constants::NF_PAUSED
+		|| status == nflib::constants::NF_RECLAIMED
+}
+
+/// Function to move a NF to another core.
+/// The NF's core and the core bookkeeping are updated together before the NF is told to move,
+/// and rolled back if the message can't be delivered. A NF that stops before it handles the
+/// message is then torn down from its new core.
+/// Only the master thread calls this, operators go through onvm_nf_request_relocate.
+/// Input  : the instance ID of the NF and the core it should move to
+/// Output : an error code
+fn onvm_nf_relocate_nf<B: Backend>(
+	dest: u16,
+	new_core: u16,
+	global_state: &global::GlobalNFState<B>,
+) -> Result<(), ExitFailure> {
+	if dest as u32 >= nflib::constants::MAX_NFS {
+		return Ok(exit_on_failure(
+			"NF instance ID out of range".into(),
+			"In the onvm_nf_relocate_nf function",
+		)?);
+	}
+	if new_core as usize >= global_state.cores.len() {
+		return Ok(exit_on_failure(
+			"NF Core out of range".into(),
+			"In the onvm_nf_relocate_nf function",
+		)?);
+	}
+
+	let nf = *global_state.nfs[dest as usize].clone();
+	let old_core: u16;
+	unsafe {
+		if !nflib::funcs_macros::onvm_nf_is_valid(&*nf) {
 			return Ok(exit_on_failure(
-				"Failed to fetch memory for NF info pool\n".into(),
-				"In the onvm_nf_stop function",
+				"NF is not running".into(),
+				"In the onvm_nf_relocate_nf function",
 			)?);
 		}
+		old_core = (*nf).thread_info.core.load(Ordering::SeqCst);
+	}
+	if old_core == new_core {
+		return Ok(());
+	}
 
-		_rte_mempool_put(nf_info_mp, stop as *mut _ as *mut c_void);
+	let old_status = *global_state.cores[old_core as usize].clone();
+	let new_status = *global_state.cores[new_core as usize].clone();
+	let was_dedicated: u16;
+	unsafe {
+		if !(*new_status).enabled.load(Ordering::SeqCst) {
+			return Ok(exit_on_failure(
+				"NF Core not enabled".into(),
+				"In the onvm_nf_relocate_nf function",
+			)?);
+		}
+		if (*new_status).is_dedicated_core.load(Ordering::SeqCst) != 0 {
+			return Ok(exit_on_failure(
+				"NF Core busy".into(),
+				"In the onvm_nf_relocate_nf function",
+			)?);
+		}
 
-		/* Further cleanup is only required if NF was succesfully started */
-		if nf_status != nflib::constants::NF_RUNNING && nf_status != nflib::constants::NF_PAUSED {
-			return Ok(());
+		/* Move the NF in the core bookkeeping, a dedicated NF keeps its new core to itself if it is free */
+		was_dedicated = (*old_status).is_dedicated_core.load(Ordering::SeqCst);
+		(*old_status).nf_count.fetch_sub(1, Ordering::SeqCst);
+		(*old_status).is_dedicated_core.store(0, Ordering::SeqCst);
+		if was_dedicated != 0 && (*new_status).nf_count.load(Ordering::SeqCst) == 0 {
+			(*new_status).is_dedicated_core.store(1, Ordering::SeqCst);
 		}
+		(*new_status).nf_count.fetch_add(1, Ordering::SeqCst);
+		(*nf).thread_info.core.store(new_core, Ordering::SeqCst);
+	}
 
-		/* Decrease the total number of RUNNING NFs */
-		*global_state.num_nfs.borrow_mut() -= 1;
-
-		/* Reset stats */
-		// onvm_stats_clear_nf(nf_id);
-		// TODO: Rest of the function
-		// NOTE: Incomplete because I still don't understand the purpose of void **service
-		/* Remove this NF from the service map.
-		 * Need to shift all elements past it in the array left to avoid gaps */
-		*global_state.nf_per_service_count[service_id as usize].borrow_mut() -= 1;
-		for map_index in 0..nflib::constants::MAX_NFS_PER_SERVICE as usize {}
-	} // end of unsafe block
+	if onvm_nf_send_msg(
+		dest,
+		nflib::structs::OnvmNFMsg::ChangeCore(new_core),
+		global_state,
+	) != 0
+	{
+		/* Undo the move, the NF is still running on its old core */
+		unsafe {
+			(*nf).thread_info.core.store(old_core, Ordering::SeqCst);
+			(*new_status).nf_count.fetch_sub(1, Ordering::SeqCst);
+			(*new_status).is_dedicated_core.store(0, Ordering::SeqCst);
+			(*old_status).nf_count.fetch_add(1, Ordering::SeqCst);
+			(*old_status)
+				.is_dedicated_core
+				.store(was_dedicated, Ordering::SeqCst);
+		}
+		return Ok(exit_on_failure(
+			"Unable to send the change core message".into(),
+			"In the onvm_nf_relocate_nf function",
+		)?);
+	}
 
+	let f = format!(
+		"NF {} moving from core {} to core {}\n",
+		dest, old_core, new_core
+	);
+	global_state.backend.log(LOG_INFO, &f);
 	Ok(())
 }
 
-/// Function to move a NF to another core.
-// pub fn onvm_nf_relocate_nf(&global_state::global::GlobalState) {}
-
-/// Function that initializes an LPM object
+// Function that initializes an LPM object
 // pub fn onvm_nf_init_lpm_region(&global_state::global::GlobalState) {}
 
-/// Function that initializes a hashtable for a flow_table struct
+// Function that initializes a hashtable for a flow_table struct
 // pub fn onvm_nf_init_ft(&global_state::global::GlobalState) {}
 
-/// Set up the DPDK rings which will be used to pass packets, via
+/// Set up the rings which will be used to pass packets, via
 /// pointers, between the multi-process server and NF processes.
 /// Each NF needs one RX queue.
-/// Input: An nf struct
-/// Output: rte_exit if failed, none otherwise
-fn onvm_nf_init_rings(global_state: &global::GlobalNFState) {}
+/// Input  : the global state holding the NF structs
+/// Output : an error if a ring can't be created
+pub fn onvm_nf_init_rings<B: Backend>(
+	global_state: &mut global::GlobalNFState<B>,
+) -> Result<(), ExitFailure> {
+	for i in 0..nflib::constants::MAX_NFS as usize {
+		let nf = unsafe { &mut *(*global_state.nfs[i].clone()) };
+		nf.rx_q = Some(global_state.backend.pkt_ring_create(
+			&crate::get_rx_queue_name!(i),
+			nflib::constants::NF_QUEUE_RINGSIZE,
+		)?);
+		nf.tx_q = Some(global_state.backend.pkt_ring_create(
+			&crate::get_tx_queue_name!(i),
+			nflib::constants::NF_QUEUE_RINGSIZE,
+		)?);
+		nf.msg_q = Some(global_state.backend.msg_ring_create(
+			&crate::get_msg_queue_name!(i),
+			super::constants::NF_MSG_QUEUE_SIZE as usize,
+		)?);
+	}
+	Ok(())
+}
 
 //******************************Interfaces*****************************/
-pub fn onvm_nf_next_instance_id(global_state: &global::GlobalNFState) -> u32 {
-	let mut nf: *mut nflib::structs::OnvmNF;
+pub fn onvm_nf_next_instance_id<B: Backend>(global_state: &global::GlobalNFState<B>) -> u32 {
 	let mut instance_id: u32;
 
-	if *global_state.num_nfs.borrow() >= nflib::constants::MAX_NFS {
-		return nflib::constants::MAX_NFS.into();
+	if global_state.num_nfs.load(Ordering::SeqCst) >= nflib::constants::MAX_NFS {
+		return nflib::constants::MAX_NFS;
 	}
 	/* Do a first pass for NF IDs bigger than current next_instance_id */
-	while *NXT_INSTANCE_ID.borrow() < nflib::constants::MAX_NFS {
-		instance_id = *NXT_INSTANCE_ID.borrow();
-		*NXT_INSTANCE_ID.borrow_mut() += 1;
+	while global_state.next_instance_id.load(Ordering::SeqCst) < nflib::constants::MAX_NFS {
+		instance_id = global_state.next_instance_id.fetch_add(1, Ordering::SeqCst);
 		/* Check if this id is occupied by another NF */
-		let nf = unsafe { *global_state.nfs.clone()[instance_id as usize] };
-		// let nf = *(&global_state.nfs).clone()[instance_id as usize].borrow_mut();
-		// let g = &global_state.nfs.clone();
-		// let nf = g[instance_id as usize].borrow_mut();
-		unsafe {
-			if nflib::funcs_macros::onvm_nf_is_valid(&*nf) {
-				return instance_id;
-			}
+		let nf = unsafe { &*(*global_state.nfs[instance_id as usize].clone()) };
+		if !onvm_nf_is_in_use(nf) {
+			return instance_id;
+		}
+	}
+
+	/* Do a second pass for other NF IDs, this picks up the IDs of stopped and reclaimed NFs */
+	global_state
+		.next_instance_id
+		.store(STARTING_INSTANCE_ID, Ordering::SeqCst);
+	while global_state.next_instance_id.load(Ordering::SeqCst) < nflib::constants::MAX_NFS {
+		instance_id = global_state.next_instance_id.fetch_add(1, Ordering::SeqCst);
+		let nf = unsafe { &*(*global_state.nfs[instance_id as usize].clone()) };
+		if !onvm_nf_is_in_use(nf) {
+			return instance_id;
 		}
 	}
 	/* This should never happen, means our num_nfs counter is wrong */
-	unsafe {
-		rte_log(
-			RTE_LOG_ERR,
-			RTE_LOGTYPE_USER1,
-			"Tried to allocated a next instance ID but num_nfs is corrupted" as *const _
-				as *const i8,
-		)
-	};
+	global_state.backend.log(
+		LOG_ERR,
+		"Tried to allocated a next instance ID but num_nfs is corrupted\n",
+	);
 	nflib::constants::MAX_NFS
 }
 
-// struct _MSGS<T: nflib::structs::OnvmMfgTrait> {
-// 	data: T,
-// }
-
-// FIXME: This fiunction is not simple to code in Rust
-// REVIEW: Look at the OnvmMfgTrait
-pub fn onvm_nf_check_status(global_state: &global::GlobalNFState) {
-	// NOTE: We expect the data in the messages to be either OnvmNF or OnvmNfInitCfg which have been marked as OnvmMfgTrait. So here the msg variable is declared as a OnvmMfgTrait trait object
-	// let mut msg: mem::MaybeUninit<Arc<dyn nflib::structs::OnvmMfgTrait>> =
-	// mem::MaybeUninit::<Arc<dyn nflib::structs::OnvmMfgTrait>>::uninit();
+/// Handles the messages the NFs sent to the manager
+pub fn onvm_nf_check_status<B: Backend>(global_state: &global::GlobalNFState<B>) {
 	// NOTE: A simpler approach is to declare an enum and have a vector of the enums
 	// One can't have a vec of trait objects because trait objects are not sized
-	let mut msgs =
-		Vec::<nflib::structs::OnvmNFMsg>::with_capacity(nflib::constants::MAX_NFS as usize);
-	let num_msgs = unsafe { _rte_ring_count(&*global_state.incoming_msg_queue.borrow_mut()) };
-
-	if num_msgs == 0 {
-		return;
-	}
-
-	unsafe {
-		if _rte_ring_dequeue_bulk(
-			&mut *global_state.incoming_msg_queue.borrow_mut(),
-			&mut (msgs.as_mut_ptr() as *mut _ as *mut c_void),
-			num_msgs,
-			ptr::null_mut(),
-		) == 0
-		{
-			return;
-		}
-	}
-
-	for i in 0..num_msgs as usize {
-		let msg = &mut msgs[i];
+	while let Some(msg) = global_state
+		.backend
+		.msg_dequeue(&global_state.incoming_msg_queue)
+	{
 		match msg {
 			nflib::structs::OnvmNFMsg::NfStarting(start) => {
 				let retval = onvm_nf_start(start, global_state);
 				match retval {
 					Ok(()) => {
-						let f = format!("NF {} Starting\n", start.instance_id);
-						unsafe {
-							rte_log(
-								RTE_LOG_INFO,
-								RTE_LOGTYPE_USER1,
-								&f[..] as *const _ as *const i8,
-							);
-						}
+						let f = format!("NF {} Starting\n", unsafe {
+							(*start).instance_id.load(Ordering::SeqCst)
+						});
+						global_state.backend.log(LOG_INFO, &f);
 					} // successfully started NF
 					Err(e) => {
-						let f = format!("NF {} failed to start: {:?}\n", start.instance_id, e);
-						unsafe {
-							rte_log(
-								RTE_LOG_INFO,
-								RTE_LOGTYPE_USER1,
-								&f[..] as *const _ as *const i8,
-							);
-						}
+						let f = format!(
+							"NF of service {} failed to start: {:?}\n",
+							unsafe { (*start).service_id },
+							e
+						);
+						global_state.backend.log(LOG_INFO, &f);
 					} // error in starting NF
 				} // inner match
 			} // NF starting case
 			nflib::structs::OnvmNFMsg::NfReady(ready) => {
-				let retval = onvm_nf_ready(*ready, global_state);
+				let retval = onvm_nf_ready(ready, global_state);
+				let nf_id = unsafe { (*ready).instance_id.load(Ordering::SeqCst) };
 				match retval {
-					Ok(()) => unsafe {
-						let f = format!("NF {} Ready\n", *(*(*ready)).instance_id.borrow());
-						rte_log(
-							RTE_LOG_INFO,
-							RTE_LOGTYPE_USER1,
-							&f[..] as *const _ as *const i8,
-						);
-					}, // successfully registered NF
-					Err(e) => unsafe {
-						let f = format!(
-							"NF {} has a problem: {:?}\n",
-							*(*(*ready)).instance_id.borrow(),
-							e
-						);
-						rte_log(
-							RTE_LOG_INFO,
-							RTE_LOGTYPE_USER1,
-							&f[..] as *const _ as *const i8,
-						);
-					}, // error in getting NF status
+					Ok(()) => {
+						let f = format!("NF {} Ready\n", nf_id);
+						global_state.backend.log(LOG_INFO, &f);
+					} // successfully registered NF
+					Err(e) => {
+						let f = format!("NF {} has a problem: {:?}\n", nf_id, e);
+						global_state.backend.log(LOG_INFO, &f);
+					} // error in getting NF status
 				} // inner match
 			} // NF ready case
 			nflib::structs::OnvmNFMsg::NfStopping(stop) => {
-				let retval = onvm_nf_stop(*stop, global_state);
+				let retval = onvm_nf_stop(stop, global_state);
+				let nf_id = unsafe { (*stop).instance_id.load(Ordering::SeqCst) };
 				match retval {
-					Ok(()) => unsafe {
-						let f = format!("NF {} Stopping\n", *(*(*stop)).instance_id.borrow());
-						rte_log(
-							RTE_LOG_INFO,
-							RTE_LOGTYPE_USER1,
-							&f[..] as *const _ as *const i8,
-						);
-					}, // successfully stopped NF
-					Err(e) => unsafe {
-						let f = format!(
-							"NF {} failed to stop: {:?}\n",
-							*(*(*stop)).instance_id.borrow(),
-							e
-						);
-						rte_log(
-							RTE_LOG_INFO,
-							RTE_LOGTYPE_USER1,
-							&f[..] as *const _ as *const i8,
-						);
-					}, // error stopping NF
+					Ok(()) => {
+						let f = format!("NF {} Stopping\n", nf_id);
+						global_state.backend.log(LOG_INFO, &f);
+					} // successfully stopped NF
+					Err(e) => {
+						let f = format!("NF {} failed to stop: {:?}\n", nf_id, e);
+						global_state.backend.log(LOG_INFO, &f);
+					} // error stopping NF
 				} // inner match
 			} // NF Stopping case
+			// NOTE: change core messages only travel from the manager to the NFs
+			nflib::structs::OnvmNFMsg::ChangeCore(_) => {}
 		} // msg matching end
-		unsafe {
-			_rte_mempool_put(
-				&mut *global_state.nf_msg_pool.borrow_mut(),
-				msgs.as_mut_ptr() as *mut _ as *mut c_void,
+	} // end of while loop
+}
+
+/// Detects NFs that died without sending NfStopping and reclaims their resources.
+/// A NF is considered dead if its process no longer exists or if it has not sent a heartbeat
+/// for NF_HEARTBEAT_TIMEOUT seconds.
+pub fn onvm_nf_check_liveness<B: Backend>(global_state: &global::GlobalNFState<B>) {
+	let now = global_state.backend.timer_cycles();
+	let hz = global_state.backend.timer_hz();
+
+	for nf_id in STARTING_INSTANCE_ID..nflib::constants::MAX_NFS {
+		let nf = *global_state.nfs[nf_id as usize].clone();
+		let (status, heartbeat, pid) = unsafe {
+			(
+				(*nf).status.load(Ordering::SeqCst),
+				(*nf).heartbeat.load(Ordering::SeqCst),
+				(*nf).pid.load(Ordering::SeqCst),
+			)
+		};
+
+		if status != nflib::constants::NF_STARTING
+			&& status != nflib::constants::NF_RUNNING
+			&& status != nflib::constants::NF_PAUSED
+			&& status != nflib::constants::NF_RECLAIMED
+		{
+			continue;
+		}
+
+		// NOTE: kill with signal 0 only checks that the process exists
+		let process_gone = pid != nflib::constants::NF_NO_PID
+			&& unsafe { kill(pid, 0) } != 0
+			&& io::Error::last_os_error().raw_os_error() == Some(ESRCH);
+
+		/* A reclaimed NF frees its instance ID once its process is gone */
+		if status == nflib::constants::NF_RECLAIMED {
+			if process_gone {
+				unsafe {
+					let _ = (*nf).status.compare_exchange(
+						nflib::constants::NF_RECLAIMED,
+						nflib::constants::NF_STOPPED,
+						Ordering::SeqCst,
+						Ordering::SeqCst,
+					);
+				}
+			}
+			continue;
+		}
+
+		let heartbeat_expired =
+			now.saturating_sub(heartbeat) / hz >= nflib::constants::NF_HEARTBEAT_TIMEOUT;
+
+		if !process_gone && !heartbeat_expired {
+			continue;
+		}
+
+		let f = format!(
+			"NF {} (pid {}) stopped responding, reclaiming its resources\n",
+			nf_id, pid
+		);
+		global_state.backend.log(LOG_ERR, &f);
+		match onvm_nf_reclaim(nf_id as u16, process_gone, global_state) {
+			Ok(()) => {
+				let f = format!("NF {} marked as stopped\n", nf_id);
+				global_state.backend.log(LOG_INFO, &f);
+			}
+			Err(e) => {
+				let f = format!("NF {} could not be reclaimed: {:?}\n", nf_id, e);
+				global_state.backend.log(LOG_ERR, &f);
+			}
+		}
+	}
+}
+
+/// Asks the manager to move a running NF to another core.
+/// The move is carried out by the master thread in onvm_nf_check_relocations, so the core
+/// bookkeeping is never changed by two threads at once. Safe to call from any thread.
+/// Input  : the instance ID of the NF and the core it should move to
+pub fn onvm_nf_request_relocate<B: Backend>(
+	nf_id: u16,
+	new_core: u16,
+	global_state: &global::GlobalNFState<B>,
+) {
+	global_state
+		.relocate_requests
+		.lock()
+		.unwrap()
+		.push((nf_id, new_core));
+}
+
+/// Carries out the core moves requested since the last call.
+/// A move that fails is logged and dropped, the NF keeps running on its old core.
+pub fn onvm_nf_check_relocations<B: Backend>(global_state: &global::GlobalNFState<B>) {
+	let requests = std::mem::take(&mut *global_state.relocate_requests.lock().unwrap());
+	for (nf_id, new_core) in requests {
+		if let Err(e) = onvm_nf_relocate_nf(nf_id, new_core, global_state) {
+			let f = format!(
+				"NF {} could not be moved to core {}: {:?}\n",
+				nf_id, new_core, e
 			);
+			global_state.backend.log(LOG_ERR, &f);
 		}
-	} // end of for loop
+	}
 }
 
-pub fn onvm_nf_send_msg(
+/// Sends a message to a NF.
+/// The backend owns the message until the NF reads it.
+/// Input  : the instance ID of the NF and the message
+/// Output : 0 on success, an error code otherwise
+pub fn onvm_nf_send_msg<B: Backend>(
 	dest: u16,
-	msg: nflib::structs::OnvmNFMsg,
-	global_state: &global::GlobalNFState,
+	msg: nflib::structs::OnvmNFMsg<B>,
+	global_state: &global::GlobalNFState<B>,
 ) -> i32 {
-	let mut msg = Box::from(msg);
-	let ret = unsafe {
-		_rte_mempool_get(
-			&mut *global_state.nf_msg_pool.borrow_mut(),
-			&mut (msg.as_mut() as *mut _ as *mut c_void),
-		)
-	};
-	if ret != 0 {
-		unsafe {
-			rte_log(
-				RTE_LOG_INFO,
-				RTE_LOGTYPE_USER1,
-				"Oh the huge manatee! Unable to allocate msg from pool" as *const _ as *const i8,
-			)
-		};
-		return ret;
-	}
-	unsafe {
-		let _msg_q = *(*(*global_state.nfs[dest as usize].clone()))
-			.msg_q
-			.borrow_mut();
-		// let _msg_q = unsafe { *((*global_state.nfs.clone()[dest as usize]).msg_q).borrow_mut() };
-		match _msg_q {
-			Some(msg_q) => {
-				// this is what we want to happen
-				return _rte_ring_enqueue(msg_q, msg.as_mut() as *mut _ as *mut c_void);
-			}
-			None => rte_exit(1, "NF Msg pool unavailable" as *const _ as *const i8),
-		};
+	let nf = unsafe { &*(*global_state.nfs[dest as usize].clone()) };
+	match &nf.msg_q {
+		// this is what we want to happen
+		Some(msg_q) => global_state.backend.msg_enqueue(msg_q, msg),
+		None => {
+			let f = format!("NF {} has no message queue\n", dest);
+			global_state.backend.log(LOG_ERR, &f);
+			1
+		}
 	}
-	1_i32 // it gets here only if it did not hit the return statements
 }
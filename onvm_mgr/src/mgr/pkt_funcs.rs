@@ -0,0 +1,278 @@
+/*
+ * Created on Thu Oct 22 2020:10:05:13
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use super::backend::Backend;
+use super::global;
+use crate::nflib;
+use crate::nflib::constants::{MAX_NFS, PACKET_READ_SIZE, RTE_MAX_ETHPORTS};
+use crate::nflib::structs::OnvmAction;
+use std::mem;
+use std::sync::atomic::Ordering;
+
+/* Moving packets between the ports and the NFs
+The RX threads send the packets they receive along the default service chain, the TX threads
+follow the action each NF set in the packet's metadata. Packets are buffered per NF and per port
+and sent in bursts.
+*/
+
+/// Packets on their way to the NFs and the ports, owned by one RX or TX thread
+pub struct PktBufs<P> {
+	queue_id: u16, // the TX queue of every port the thread sends on
+	pkts: Vec<P>,  // the burst being processed
+	nfs: Vec<Vec<P>>,
+	ports: Vec<Vec<P>>,
+}
+
+impl<P> PktBufs<P> {
+	pub fn new(queue_id: u16) -> Self {
+		Self {
+			queue_id,
+			pkts: Vec::with_capacity(PACKET_READ_SIZE),
+			nfs: (0..MAX_NFS).map(|_| Vec::new()).collect(),
+			ports: (0..RTE_MAX_ETHPORTS).map(|_| Vec::new()).collect(),
+		}
+	}
+}
+
+/// One iteration of a RX thread: receives a burst on every port in use and sends the packets
+/// along the default service chain
+/// Input  : the RSS queue of the thread, its buffers and the global state
+pub fn onvm_pkt_rx_poll<B: Backend>(
+	rx_queue_id: u16,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	for i in 0..global_state.ports().num_ports.load(Ordering::Relaxed) as usize {
+		let port_id = global_state.ports().id[i].load(Ordering::Relaxed) as u16;
+		let rx_count = global_state.backend.port_rx_burst(
+			port_id,
+			rx_queue_id,
+			&mut bufs.pkts,
+			PACKET_READ_SIZE,
+		);
+		if rx_count == 0 {
+			continue;
+		}
+		global_state.ports().rx_stats.rx[port_id as usize]
+			.fetch_add(rx_count as u64, Ordering::Relaxed);
+		onvm_pkt_process_rx_batch(bufs, global_state);
+	}
+	onvm_pkt_flush_all_nfs(bufs, global_state);
+	onvm_pkt_flush_all_ports(bufs, global_state);
+}
+
+/// One iteration of a TX thread: drains the tx rings of the NFs in [first_nf, last_nf) and
+/// routes their packets
+/// Input  : the range of NFs of the thread, its buffers and the global state
+pub fn onvm_pkt_tx_poll<B: Backend>(
+	first_nf: u32,
+	last_nf: u32,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	for i in first_nf..last_nf {
+		let nf = unsafe { &*(*global_state.nfs[i as usize].clone()) };
+		if !nflib::funcs_macros::onvm_nf_is_valid(nf) {
+			continue;
+		}
+		let tx_q = match &nf.tx_q {
+			Some(tx_q) => tx_q,
+			None => continue,
+		};
+		if global_state
+			.backend
+			.pkt_dequeue_burst(tx_q, &mut bufs.pkts, PACKET_READ_SIZE)
+			== 0
+		{
+			continue;
+		}
+		onvm_pkt_process_tx_batch(bufs, global_state);
+	}
+	onvm_pkt_flush_all_nfs(bufs, global_state);
+	onvm_pkt_flush_all_ports(bufs, global_state);
+}
+
+/// Starts the received packets on the default service chain
+fn onvm_pkt_process_rx_batch<B: Backend>(
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	// NOTE: the burst is taken out of the buffers so that packets can be buffered while it is walked
+	let mut pkts = mem::take(&mut bufs.pkts);
+	for mut pkt in pkts.drain(..) {
+		let meta = global_state.backend.pkt_meta(&mut pkt);
+		meta.src = 0;
+		meta.chain_index = 0;
+		onvm_pkt_next_action(pkt, bufs, global_state);
+	}
+	bufs.pkts = pkts;
+}
+
+/// Routes the packets the NFs sent with the action in their metadata
+fn onvm_pkt_process_tx_batch<B: Backend>(
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let mut pkts = mem::take(&mut bufs.pkts);
+	for mut pkt in pkts.drain(..) {
+		let meta = global_state.backend.pkt_meta(&mut pkt);
+		let (action, destination) = (meta.action, meta.destination);
+		onvm_pkt_route(pkt, action, destination, bufs, global_state);
+	}
+	bufs.pkts = pkts;
+}
+
+/// Sends a packet to the step of the default service chain at its chain index
+fn onvm_pkt_next_action<B: Backend>(
+	mut pkt: B::Pkt,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let meta = global_state.backend.pkt_meta(&mut pkt);
+	let entry = global_state
+		.default_chain
+		.read()
+		.unwrap()
+		.entry(meta.chain_index)
+		.copied();
+	match entry {
+		Some(entry) => {
+			meta.chain_index += 1;
+			onvm_pkt_route(pkt, entry.action, entry.destination, bufs, global_state);
+		}
+		// the packet is past the end of the chain
+		None => onvm_pkt_drop(pkt, global_state),
+	}
+}
+
+fn onvm_pkt_route<B: Backend>(
+	pkt: B::Pkt,
+	action: OnvmAction,
+	destination: u16,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	match action {
+		OnvmAction::DROP => onvm_pkt_drop(pkt, global_state),
+		OnvmAction::NEXT => onvm_pkt_next_action(pkt, bufs, global_state),
+		OnvmAction::TONF => onvm_pkt_enqueue_nf(pkt, destination, bufs, global_state),
+		OnvmAction::OUT => onvm_pkt_enqueue_port(pkt, destination, bufs, global_state),
+	}
+}
+
+/// Buffers a packet for one of the running NFs of a service.
+/// The instance is picked with the packet's RSS hash, so the packets of a flow always reach the
+/// same instance.
+fn onvm_pkt_enqueue_nf<B: Backend>(
+	pkt: B::Pkt,
+	service_id: u16,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let dest = match global_state.services.get(service_id as usize) {
+		Some(service) => {
+			let instances = service.read().unwrap();
+			if instances.is_empty() {
+				None
+			} else {
+				let hash = global_state.backend.pkt_hash(&pkt) as usize;
+				Some(instances[hash % instances.len()])
+			}
+		}
+		None => None,
+	};
+	let dest = match dest {
+		Some(dest) => dest as usize,
+		None => return onvm_pkt_drop(pkt, global_state),
+	};
+	let nf = unsafe { &*(*global_state.nfs[dest].clone()) };
+	if !nflib::funcs_macros::onvm_nf_is_valid(nf) {
+		return onvm_pkt_drop(pkt, global_state);
+	}
+	bufs.nfs[dest].push(pkt);
+	if bufs.nfs[dest].len() == PACKET_READ_SIZE {
+		onvm_pkt_flush_nf_queue(dest, bufs, global_state);
+	}
+}
+
+/// Buffers a packet for a port
+fn onvm_pkt_enqueue_port<B: Backend>(
+	pkt: B::Pkt,
+	port: u16,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let port = port as usize;
+	if port >= bufs.ports.len() {
+		return onvm_pkt_drop(pkt, global_state);
+	}
+	bufs.ports[port].push(pkt);
+	if bufs.ports[port].len() == PACKET_READ_SIZE {
+		onvm_pkt_flush_port_queue(port, bufs, global_state);
+	}
+}
+
+/// Enqueues the packets buffered for a NF on its rx ring, the packets that don't fit are dropped
+fn onvm_pkt_flush_nf_queue<B: Backend>(
+	nf_id: usize,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let nf = unsafe { &*(*global_state.nfs[nf_id].clone()) };
+	let buf = &mut bufs.nfs[nf_id];
+	if let Some(rx_q) = &nf.rx_q {
+		global_state.backend.pkt_enqueue_burst(rx_q, buf);
+	}
+	for pkt in buf.drain(..) {
+		onvm_pkt_drop(pkt, global_state);
+	}
+}
+
+/// Sends the packets buffered for a port in one burst, on the thread's own TX queue.
+/// The packets the port did not take are freed and counted as TX drops.
+fn onvm_pkt_flush_port_queue<B: Backend>(
+	port: usize,
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	let buf = &mut bufs.ports[port];
+	let count = buf.len();
+	let sent = global_state
+		.backend
+		.port_tx_burst(port as u16, bufs.queue_id, buf);
+	for pkt in buf.drain(..) {
+		global_state.backend.pkt_free(pkt);
+	}
+	global_state.ports().tx_stats.tx[port].fetch_add(sent as u64, Ordering::Relaxed);
+	global_state.ports().tx_stats.tx_drop[port].fetch_add((count - sent) as u64, Ordering::Relaxed);
+}
+
+fn onvm_pkt_flush_all_nfs<B: Backend>(
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	for nf_id in 0..bufs.nfs.len() {
+		if !bufs.nfs[nf_id].is_empty() {
+			onvm_pkt_flush_nf_queue(nf_id, bufs, global_state);
+		}
+	}
+}
+
+fn onvm_pkt_flush_all_ports<B: Backend>(
+	bufs: &mut PktBufs<B::Pkt>,
+	global_state: &global::GlobalNFState<B>,
+) {
+	for port in 0..bufs.ports.len() {
+		if !bufs.ports[port].is_empty() {
+			onvm_pkt_flush_port_queue(port, bufs, global_state);
+		}
+	}
+}
+
+/// Frees a packet the manager could not deliver
+fn onvm_pkt_drop<B: Backend>(pkt: B::Pkt, global_state: &global::GlobalNFState<B>) {
+	global_state.pkts_dropped.fetch_add(1, Ordering::Relaxed);
+	global_state.backend.pkt_free(pkt);
+}
@@ -0,0 +1,217 @@
+/*
+ * Created on Mon Oct 19 2020:10:12:31
+ * Created by Ratnadeep Bhattacharya
+ */
+
+#[cfg(feature = "dpdk")]
+use super::dpdk::DpdkBackend;
+use super::{constants, global};
+use crate::error_handling::exit_on_failure;
+use crate::nflib;
+use exitfailure::ExitFailure;
+
+// DPDK functions
+#[cfg(feature = "dpdk")]
+use capsule_ffi::{rte_get_master_lcore, rte_get_next_lcore, rte_lcore_count};
+
+/* Layout of the manager's worker threads */
+
+/// Work assigned to a RX thread.
+/// Every RX thread polls the same RSS queue on all the ports in use.
+pub struct RxThreadInfo {
+	pub queue_id: u16,
+	pub lcore: u32,
+}
+
+/// Work assigned to a TX thread.
+/// Every TX thread drains the tx rings of the NFs in [first_nf, last_nf) and
+/// sends on its own queue of each port.
+pub struct TxThreadInfo {
+	pub queue_id: u16,
+	pub first_nf: u32,
+	pub last_nf: u32,
+	pub lcore: u32,
+}
+
+/// Resolves the number of RX and TX threads requested on the command line.
+/// A TX thread count of 0 means all the lcores left after the RX and auxiliary threads.
+/// Input  : the global state holding the parsed thread counts
+/// Output : an error code
+#[cfg(feature = "dpdk")]
+pub fn onvm_threads_resolve_counts(
+	global_state: &mut global::GlobalNFState<DpdkBackend>,
+) -> Result<(), ExitFailure> {
+	let lcores = unsafe { rte_lcore_count() };
+	let rx_threads = global_state.num_rx_threads as u32;
+	let mut tx_threads = global_state.num_tx_threads as u32;
+	// the auxiliary threads include the master thread running on the master lcore
+	let reserved = rx_threads + constants::ONVM_NUM_MGR_AUX_THREADS as u32;
+
+	if rx_threads == 0 {
+		return Ok(exit_on_failure(
+			"At least one RX thread is needed".into(),
+			"In the onvm_threads_resolve_counts function",
+		)?);
+	}
+	if reserved >= lcores {
+		let f = format!(
+			"{} lcores available, {} RX threads and {} auxiliary threads requested",
+			lcores,
+			rx_threads,
+			constants::ONVM_NUM_MGR_AUX_THREADS
+		);
+		return Ok(exit_on_failure(
+			f,
+			"In the onvm_threads_resolve_counts function",
+		)?);
+	}
+	if tx_threads == 0 {
+		tx_threads = lcores - reserved;
+	}
+	if reserved + tx_threads > lcores {
+		let f = format!(
+			"{} lcores available, {} RX, {} TX and {} auxiliary threads requested",
+			lcores,
+			rx_threads,
+			tx_threads,
+			constants::ONVM_NUM_MGR_AUX_THREADS
+		);
+		return Ok(exit_on_failure(
+			f,
+			"In the onvm_threads_resolve_counts function",
+		)?);
+	}
+
+	global_state.num_tx_threads = tx_threads as u16;
+	println!("{} RX threads, {} TX threads", rx_threads, tx_threads);
+	Ok(())
+}
+
+/// Splits the NF instance IDs among the TX threads.
+/// ID 0 is reserved so the ranges cover [1, MAX_NFS), the last range may be shorter.
+pub fn onvm_threads_tx_ranges(num_tx_threads: u32) -> Vec<(u32, u32)> {
+	let mut ranges = Vec::with_capacity(num_tx_threads as usize);
+	if num_tx_threads == 0 {
+		return ranges;
+	}
+	let usable = nflib::constants::MAX_NFS - 1;
+	// round up so no NF is left without a TX thread
+	let nfs_per_tx = usable.div_ceil(num_tx_threads);
+	for i in 0..num_tx_threads {
+		let first = (i * nfs_per_tx + 1).min(nflib::constants::MAX_NFS);
+		let last = ((i + 1) * nfs_per_tx + 1).min(nflib::constants::MAX_NFS);
+		ranges.push((first, last));
+	}
+	ranges
+}
+
+/// Assigns a lcore to each RX and TX thread, skipping the master lcore.
+/// Input  : the global state with resolved thread counts
+/// Output : the RX and TX thread layout
+#[cfg(feature = "dpdk")]
+pub fn onvm_threads_plan(
+	global_state: &global::GlobalNFState<DpdkBackend>,
+) -> Result<(Vec<RxThreadInfo>, Vec<TxThreadInfo>), ExitFailure> {
+	let rx_threads = global_state.num_rx_threads;
+	let tx_threads = global_state.num_tx_threads;
+	let mut rx = Vec::with_capacity(rx_threads as usize);
+	let mut tx = Vec::with_capacity(tx_threads as usize);
+	let mut cur_lcore = unsafe { rte_get_master_lcore() };
+
+	for queue_id in 0..rx_threads {
+		cur_lcore = unsafe { rte_get_next_lcore(cur_lcore, 1, 1) };
+		rx.push(RxThreadInfo {
+			queue_id,
+			lcore: cur_lcore,
+		});
+	}
+
+	for (queue_id, (first_nf, last_nf)) in onvm_threads_tx_ranges(tx_threads as u32)
+		.into_iter()
+		.enumerate()
+	{
+		cur_lcore = unsafe { rte_get_next_lcore(cur_lcore, 1, 1) };
+		tx.push(TxThreadInfo {
+			queue_id: queue_id as u16,
+			first_nf,
+			last_nf,
+			lcore: cur_lcore,
+		});
+	}
+
+	/* rte_get_next_lcore wraps around, so a repeated lcore means we ran out */
+	let mut lcores: Vec<u32> = rx
+		.iter()
+		.map(|r| r.lcore)
+		.chain(tx.iter().map(|t| t.lcore))
+		.collect();
+	lcores.sort();
+	lcores.dedup();
+	if lcores.len() != rx.len() + tx.len() {
+		exit_on_failure(
+			"Not enough lcores for the RX and TX threads".into(),
+			"In the onvm_threads_plan function",
+		)?;
+	}
+
+	Ok((rx, tx))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::nflib::constants::MAX_NFS;
+
+	/// Checks that the ranges are contiguous and cover [1, MAX_NFS) exactly once
+	fn assert_covers_all_nfs(ranges: &[(u32, u32)]) {
+		let mut next = 1;
+		for &(first, last) in ranges {
+			assert_eq!(next, first);
+			assert!(first <= last);
+			next = last;
+		}
+		assert_eq!(MAX_NFS, next);
+	}
+
+	#[test]
+	fn tx_ranges_cover_all_nfs() {
+		for num_tx_threads in 1..=MAX_NFS + 1 {
+			let ranges = onvm_threads_tx_ranges(num_tx_threads);
+			assert_eq!(num_tx_threads as usize, ranges.len());
+			assert_covers_all_nfs(&ranges);
+		}
+	}
+
+	#[test]
+	fn tx_ranges_exclude_id_0() {
+		for num_tx_threads in 1..=8 {
+			let ranges = onvm_threads_tx_ranges(num_tx_threads);
+			assert!(ranges.iter().all(|&(first, _)| first >= 1));
+		}
+	}
+
+	#[test]
+	fn tx_ranges_uneven_split() {
+		// 127 usable IDs over 4 threads, the first three take 32 IDs and the last one the rest
+		let ranges = onvm_threads_tx_ranges(4);
+		assert_eq!(vec![(1, 33), (33, 65), (65, 97), (97, 128)], ranges);
+
+		// a single thread serves every NF
+		assert_eq!(vec![(1, MAX_NFS)], onvm_threads_tx_ranges(1));
+	}
+
+	#[test]
+	fn tx_ranges_more_threads_than_nfs() {
+		// the threads past the last NF get an empty range
+		let ranges = onvm_threads_tx_ranges(MAX_NFS + 1);
+		assert_eq!((MAX_NFS - 1, MAX_NFS), ranges[MAX_NFS as usize - 2]);
+		assert!(ranges[MAX_NFS as usize - 1..]
+			.iter()
+			.all(|&(first, last)| first == last));
+	}
+
+	#[test]
+	fn tx_ranges_no_threads() {
+		assert!(onvm_threads_tx_ranges(0).is_empty());
+	}
+}
@@ -3,20 +3,17 @@
  * Created by Ratnadeep Bhattacharya
  */
 
+#[cfg(feature = "dpdk")]
 use capsule_ffi::RTE_LOGTYPE_USER1;
-use std::cell::RefCell;
 /* All the constants in the nflib submodule */
 
 /// message passing between mgr and nfs
 pub const MSG_NOOP: u8 = 0;
 pub const MSG_STOP: u8 = 1;
-pub const MSG_NF_STARTING: u8 = 2;
-pub const MSG_NF_STOPPING: u8 = 3;
-pub const MSG_NF_READY: u8 = 4;
+// NOTE: starting, stopping, ready and change core messages are the OnvmNFMsg variants
 pub const MSG_SCALE: u8 = 5;
 pub const MSG_FROM_NF: u8 = 6;
 pub const MSG_REQUEST_LPM_REGION: u8 = 7;
-pub const MSG_CHANGE_CORE: u8 = 8;
 pub const MSG_REQUEST_FT: u8 = 9;
 
 /// common to all nf features
@@ -64,6 +61,12 @@ const NF_TERM_WAIT_TIME: u8 = 1;
 const NF_TERM_INIT_ITER_TIMES: u8 = 3;
 const NF_TERM_STOP_ITER_TIMES: u8 = 10;
 
+// For NF crash detection
+// Seconds without a heartbeat before the manager considers an NF dead
+pub const NF_HEARTBEAT_TIMEOUT: u64 = 5;
+// pid value used when the NF runs as a thread and can't be monitored through kill(2)
+pub const NF_NO_PID: i32 = 0;
+
 /// interrupt semaphore specific updates
 pub const SHMSZ: u8 = 4; // size of shared memory segement (page_size)
 pub const KEY_PREFIX: u8 = 123; // prefix len for key
@@ -84,21 +87,29 @@ pub const NF_CORE_OUT_OF_RANGE: u16 = 11; // The manually selected core is out o
 pub const NF_CORE_BUSY: u16 = 12; // The manually selected core is busy
 pub const NF_WAITING_FOR_LPM: u16 = 13; // NF is waiting for a LPM request to be fulfilled
 pub const NF_WAITING_FOR_FT: u16 = 14; // NF is waiting for a flow-table request to be fulfilled
+pub const NF_RECLAIMED: u16 = 15; // NF stopped responding and was torn down, its ID is freed once it exits
 pub const NF_NO_ID: i16 = -1;
 
 pub const NO_FLAGS: u32 = 0;
 
-pub const RSS_SYMMETRIC_KEY: RefCell<[u8; 40]> = RefCell::new([
+pub const RSS_SYMMETRIC_KEY: [u8; 40] = [
 	0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a,
 	0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a,
 	0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a, 0x6d, 0x5a,
-]);
+];
 
+#[cfg(feature = "dpdk")]
 static RTE_LOGTYPE_APP: u32 = RTE_LOGTYPE_USER1;
 
+#[cfg(feature = "dpdk")]
+pub use capsule_ffi::RTE_MAX_ETHPORTS;
+// NOTE: the value DPDK is built with, used when the manager runs without DPDK
+#[cfg(not(feature = "dpdk"))]
+pub const RTE_MAX_ETHPORTS: u32 = 32;
+
 /// define common names for structures shared between server and NF
-pub const MP_NF_RXQ_NAME: RefCell<&str> = RefCell::new(""); // to be populated by get_msg_queue_name macro
-pub const MP_NF_TXQ_NAME: RefCell<&str> = RefCell::new(""); // to be populated by get_msg_queue_name macro
+pub const MP_NF_RXQ_NAME: &str = "MProc_Client_{}_RX"; // populated by the get_rx_queue_name macro
+pub const MP_NF_TXQ_NAME: &str = "MProc_Client_{}_TX"; // populated by the get_tx_queue_name macro
 pub const MP_CLIENT_SEM_NAME: &str = "MProc_Client_{}_SEM"; // REVIEW: some macro to come here
 pub const PKTMBUF_POOL_NAME: &str = "MProc_pktmbuf_pool";
 pub const MZ_PORT_INFO: &str = "MProc_port_info";
@@ -110,6 +121,6 @@ pub const MZ_ONVM_CONFIG: &str = "MProc_onvm_config";
 pub const MZ_SCP_INFO: &str = "MProc_scp_info";
 pub const MZ_FTP_INFO: &str = "MProc_ftp_info";
 pub const _MGR_MSG_QUEUE_NAME: &str = "MSG_MSG_QUEUE";
-pub const _NF_MSG_QUEUE_NAME: RefCell<&str> = RefCell::new(""); // to be populated by get_msg_queue_name macro
+pub const _NF_MSG_QUEUE_NAME: &str = "NF_{}_MSG_QUEUE"; // populated by the get_msg_queue_name macro
 pub const _NF_MEMPOOL_NAME: &str = "NF_INFO_MEMPOOL";
 pub const _NF_MSG_POOL_NAME: &str = "NF_MSG_MEMPOOL";
@@ -6,13 +6,18 @@
 /* functions and macros used throughout */
 use super::structs::EtherAddr;
 // DPDK functions
+#[cfg(feature = "dpdk")]
 use capsule_ffi::{rte_eth_dev_is_valid_port, rte_eth_macaddr_get};
 // DPDK Structures
 use super::constants;
 use super::structs;
+use crate::error_handling::exit_on_failure;
+use crate::mgr::backend::Backend;
 use bit_field::BitField;
+#[cfg(feature = "dpdk")]
 use capsule_ffi::rte_ether_addr;
-use capsule_ffi::rte_mbuf;
+use exitfailure::ExitFailure;
+use std::sync::atomic::Ordering;
 
 #[macro_export]
 macro_rules! get_rx_queue_name {
@@ -51,37 +56,27 @@ pub fn onvm_clear_bit(flags: &mut u8, n: usize) {
 }
 
 #[inline]
-pub fn onvm_get_pkt_name(pkt: &rte_mbuf) -> Option<&structs::OnvmPktMeta> {
-	unsafe {
-		let p = pkt.__bindgen_anon_5.udata64 as *const u64;
-		if !p.is_null() {
-			Some(&(*(p as *const structs::OnvmPktMeta)))
-		} else {
-			None
-		}
-	}
+pub fn onvm_get_pkt_name<'a, B: Backend>(
+	backend: &B,
+	pkt: &'a mut B::Pkt,
+) -> &'a mut structs::OnvmPktMeta {
+	backend.pkt_meta(pkt)
 }
 
 #[inline]
-pub fn onvm_get_pkt_chain_index(pkt: &rte_mbuf) -> Option<u8> {
-	if let Some(meta) = onvm_get_pkt_name(pkt) {
-		Some(meta.chain_index)
-	} else {
-		None
-	}
+pub fn onvm_get_pkt_chain_index<B: Backend>(backend: &B, pkt: &mut B::Pkt) -> u8 {
+	onvm_get_pkt_name(backend, pkt).chain_index
 }
 
 /// Updates the ether_addr struct with a fake, safe MAC address
-pub fn onvm_get_fake_macaddr(mac_addr: &EtherAddr) {
-	let mut mac_addr_bytes = mac_addr.get_mac();
-	mac_addr_bytes[0] = 2;
-	mac_addr_bytes[1] = 0;
-	mac_addr_bytes[2] = 0;
+pub fn onvm_get_fake_macaddr(mac_addr: &mut EtherAddr) {
+	mac_addr.addr_bytes = [2, 0, 0, 0, 0, 0];
 }
 
 /// Tries to fetch the MAC address of the port_id.
 /// Returns Result<(), u8>
 /// () if port is valid, 1 if port is invalid.
+#[cfg(feature = "dpdk")]
 pub fn onvm_get_macaddr(port_id: u16, mac_addr: &mut EtherAddr) -> Result<(), u8> {
 	unsafe {
 		if rte_eth_dev_is_valid_port(port_id) == 1 {
@@ -93,6 +88,47 @@ pub fn onvm_get_macaddr(port_id: u16, mac_addr: &mut EtherAddr) -> Result<(), u8
 	}
 }
 
-pub fn onvm_nf_is_valid(nf: &structs::OnvmNF) -> bool {
-	*nf.status.borrow_mut() == constants::NF_RUNNING
+pub fn onvm_nf_is_valid<B: Backend>(nf: &structs::OnvmNF<B>) -> bool {
+	nf.status.load(Ordering::SeqCst) == constants::NF_RUNNING
+}
+
+/// Records that the NF is still alive.
+/// NFs are expected to call this from their packet loop so the manager can detect crashed NFs.
+pub fn onvm_nf_heartbeat<B: Backend>(nf: &structs::OnvmNF<B>, backend: &B) {
+	nf.heartbeat.store(backend.timer_cycles(), Ordering::SeqCst);
+}
+
+/// Moves the calling NF thread to a new core.
+/// Called by the NF when it receives a ChangeCore message from the manager,
+/// the manager has already moved the NF to the new core in its bookkeeping by then.
+pub fn onvm_nf_change_core<B: Backend>(
+	nf: &structs::OnvmNF<B>,
+	new_core: u16,
+	backend: &B,
+) -> Result<(), ExitFailure> {
+	if let Err(e) = backend.thread_set_affinity(new_core) {
+		return Ok(exit_on_failure(
+			format!(
+				"Failed to move NF {} to core {}: {:?}",
+				nf.instance_id.load(Ordering::SeqCst),
+				new_core,
+				e
+			),
+			"In the onvm_nf_change_core function",
+		)?);
+	}
+	Ok(())
+}
+
+/// Handles the messages the manager sends to an NF.
+pub fn onvm_nf_handle_msg<B: Backend>(
+	nf: &structs::OnvmNF<B>,
+	msg: &structs::OnvmNFMsg<B>,
+	backend: &B,
+) -> Result<(), ExitFailure> {
+	match msg {
+		structs::OnvmNFMsg::ChangeCore(new_core) => onvm_nf_change_core(nf, *new_core, backend),
+		// NfStopping ends the NF loop, the remaining messages only travel from the NFs to the manager
+		_ => Ok(()),
+	}
 }
@@ -9,6 +9,7 @@ pub mod constants;
 #[allow(dead_code)] // remove once code stabilizes
 pub mod funcs_macros;
 #[allow(dead_code)] // remove once code stabilizes
+pub mod nf_funcs;
 // pub mod msg_common;
 #[allow(dead_code)] // remove once code stabilizes
 pub mod structs;
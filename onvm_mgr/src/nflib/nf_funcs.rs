@@ -0,0 +1,180 @@
+/*
+ * Created on Thu Oct 22 2020:11:20:36
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use super::constants::*;
+use super::funcs_macros;
+use super::structs::OnvmNFMsg;
+use crate::mgr::backend::{Backend, LOG_ERR};
+use crate::mgr::global::GlobalNFState;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/* The NF side of the manager protocol
+A NF asks the manager for an instance ID with NfStarting, reports NfReady once it knows its rings
+and NfStopping when it exits. NFs only go through the backend and the memory shared with the
+manager, so they run as threads of the manager on any backend.
+*/
+
+/// Registers a NF with the manager and waits until the manager marks it running
+/// NOTE: the manager answers every NfStarting, the NF only gives up once it has an instance ID
+/// Input  : the service and the core of the NF, and a flag cleared to abort the start
+/// Output : the instance ID of the NF, or the status the manager rejected the NF with
+pub fn onvm_nflib_start_nf<B: Backend>(
+	service_id: u16,
+	core: u16,
+	keep_running: &AtomicBool,
+	global_state: &GlobalNFState<B>,
+) -> Result<u16, u16> {
+	let nf_init_cfg = match global_state.backend.nf_init_cfg_get() {
+		Some(nf_init_cfg) => nf_init_cfg,
+		None => return Err(NF_NO_IDS),
+	};
+	let cfg = unsafe { &mut *nf_init_cfg };
+	cfg.service_id = service_id;
+	cfg.core = core;
+	// NOTE: the NF is a thread of the manager, the manager can't monitor it through a pid
+	cfg.pid = NF_NO_PID;
+	cfg.status.store(NF_WAITING_FOR_ID, Ordering::SeqCst);
+
+	if global_state.backend.msg_enqueue(
+		&global_state.incoming_msg_queue,
+		OnvmNFMsg::NfStarting(nf_init_cfg),
+	) != 0
+	{
+		/* The manager never saw the NF */
+		unsafe { global_state.backend.nf_init_cfg_put(nf_init_cfg) };
+		return Err(NF_WAITING_FOR_ID);
+	}
+
+	let mut status = cfg.status.load(Ordering::SeqCst);
+	while status == NF_WAITING_FOR_ID {
+		thread::yield_now();
+		status = cfg.status.load(Ordering::SeqCst);
+	}
+	if status != NF_STARTING {
+		/* The manager rejected the NF, the init config is still ours */
+		unsafe { global_state.backend.nf_init_cfg_put(nf_init_cfg) };
+		return Err(status);
+	}
+	// NOTE: from here on the init config belongs to the manager
+	let nf_id = cfg.instance_id.load(Ordering::SeqCst);
+	let nf = *global_state.nfs[nf_id as usize].clone();
+
+	if global_state.backend.thread_set_affinity(core).is_err()
+		|| global_state
+			.backend
+			.msg_enqueue(&global_state.incoming_msg_queue, OnvmNFMsg::NfReady(nf))
+			!= 0
+	{
+		onvm_nflib_stop(nf_id, global_state);
+		return Err(NF_STOPPED);
+	}
+
+	let nf = unsafe { &*nf };
+	while !funcs_macros::onvm_nf_is_valid(nf) {
+		let status = nf.status.load(Ordering::SeqCst);
+		if !keep_running.load(Ordering::SeqCst) || status == NF_STOPPED || status == NF_RECLAIMED {
+			onvm_nflib_stop(nf_id, global_state);
+			return Err(NF_STOPPED);
+		}
+		thread::yield_now();
+	}
+	Ok(nf_id)
+}
+
+/// Runs the packet loop of a NF until the NF is told to stop or the manager stops it.
+/// The handler sets the action and the destination in the metadata of every packet.
+/// The loop sends the heartbeats the manager checks in onvm_nf_check_liveness
+/// and handles the messages the manager sends to the NF.
+/// Input  : the instance ID of the NF, a flag cleared to stop the NF and the packet handler
+pub fn onvm_nflib_run<B: Backend, F: FnMut(&mut B::Pkt)>(
+	nf_id: u16,
+	keep_running: &AtomicBool,
+	mut handler: F,
+	global_state: &GlobalNFState<B>,
+) {
+	let nf = unsafe { &*(*global_state.nfs[nf_id as usize].clone()) };
+	let (rx_q, tx_q, msg_q) = match (&nf.rx_q, &nf.tx_q, &nf.msg_q) {
+		(Some(rx_q), Some(tx_q), Some(msg_q)) => (rx_q, tx_q, msg_q),
+		_ => return,
+	};
+	let mut pkts = Vec::with_capacity(PACKET_READ_SIZE);
+
+	while keep_running.load(Ordering::Relaxed) {
+		// NOTE: an idle NF is still alive, the heartbeat is sent on every iteration
+		funcs_macros::onvm_nf_heartbeat(nf, &global_state.backend);
+
+		/* The rings are only used while the NF holds them and is valid, the manager drains
+		them once it stopped or reclaimed the NF, see onvm_nf_teardown */
+		{
+			let _rings = nf.ring_lock.lock().unwrap();
+			if !funcs_macros::onvm_nf_is_valid(nf) {
+				return;
+			}
+			while let Some(msg) = global_state.backend.msg_dequeue(msg_q) {
+				/* The manager is shutting down */
+				if let OnvmNFMsg::NfStopping(_) = msg {
+					return;
+				}
+				if let Err(e) = funcs_macros::onvm_nf_handle_msg(nf, &msg, &global_state.backend) {
+					let f = format!("NF {} failed to handle a message: {:?}\n", nf_id, e);
+					global_state.backend.log(LOG_ERR, &f);
+				}
+			}
+			global_state
+				.backend
+				.pkt_dequeue_burst(rx_q, &mut pkts, PACKET_READ_SIZE);
+		}
+		if pkts.is_empty() {
+			thread::yield_now();
+			continue;
+		}
+
+		for pkt in pkts.iter_mut() {
+			handler(pkt);
+			global_state.backend.pkt_meta(pkt).src = nf_id;
+		}
+		let valid = {
+			let _rings = nf.ring_lock.lock().unwrap();
+			let valid = funcs_macros::onvm_nf_is_valid(nf);
+			if valid {
+				global_state.backend.pkt_enqueue_burst(tx_q, &mut pkts);
+			}
+			valid
+		};
+		/* the tx ring is full, or the NF was stopped while it handled the packets */
+		for pkt in pkts.drain(..) {
+			global_state.backend.pkt_free(pkt);
+		}
+		if !valid {
+			return;
+		}
+	}
+}
+
+/// Tells the manager the NF is exiting.
+/// A NF the manager already stopped has nothing left to release,
+/// a NF the manager reclaimed only gives its instance ID back.
+pub fn onvm_nflib_stop<B: Backend>(nf_id: u16, global_state: &GlobalNFState<B>) {
+	let nf = *global_state.nfs[nf_id as usize].clone();
+	let status = unsafe { (*nf).status.load(Ordering::SeqCst) };
+	if status == NF_RECLAIMED {
+		let _ = unsafe {
+			(*nf).status.compare_exchange(
+				NF_RECLAIMED,
+				NF_STOPPED,
+				Ordering::SeqCst,
+				Ordering::SeqCst,
+			)
+		};
+		return;
+	}
+	if status != NF_STARTING && status != NF_RUNNING && status != NF_PAUSED {
+		return;
+	}
+	global_state
+		.backend
+		.msg_enqueue(&global_state.incoming_msg_queue, OnvmNFMsg::NfStopping(nf));
+}
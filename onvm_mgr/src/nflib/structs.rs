@@ -5,15 +5,16 @@
 
 use super::constants::*;
 use crate::error_handling::exit_on_failure;
+use crate::mgr::backend::Backend;
 use exitfailure::ExitFailure;
-use std::cell::RefCell;
-use std::sync::Weak;
-// Functions
-use capsule_ffi::{rte_eth_dev_is_valid_port, rte_eth_macaddr_get};
+use std::ptr;
+use std::sync::atomic::{
+	AtomicBool, AtomicI16, AtomicI32, AtomicPtr, AtomicU16, AtomicU64, AtomicU8, Ordering,
+};
+use std::sync::{Mutex, Weak};
 // Structures
-use capsule_ffi::{rte_atomic16_t, rte_ether_addr, rte_mbuf, rte_ring};
-// Constants
-use capsule_ffi::{RTE_LOGTYPE_USER1, RTE_MAX_ETHPORTS};
+#[cfg(feature = "dpdk")]
+use capsule_ffi::rte_ether_addr;
 
 // contains all structs for use in nflib
 
@@ -24,30 +25,42 @@ use capsule_ffi::{RTE_LOGTYPE_USER1, RTE_MAX_ETHPORTS};
 // 	msg_data: String, // These should be rte_malloc'd so they're stored in hugepages
 // }
 
+// NOTE: the DPDK backend keeps the metadata in the mbuf's udata64 field, so it has to fit in 8 bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+#[derive(Default)]
 pub enum OnvmAction {
+	#[default]
 	DROP, // drop packet
 	NEXT, // to whatever the next action is configured
 	TONF, // // send to the NF specified in the argument field, if on the same host
 	OUT,  // send the packet out the NIC port set in the argument field
 }
 
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
 pub struct OnvmPktMeta {
-	action: OnvmAction,  // Action to be performed
-	destination: u16,    // where to go next
-	src: u16,            // who processed the packet last
-	pub chain_index: u8, // index of the current step in the service chain
-	flags: u8, // bits for custom NF data. Use with caution to prevent collisions from different NFs
+	pub action: OnvmAction, // Action to be performed
+	pub destination: u16,   // where to go next
+	pub src: u16,           // who processed the packet last
+	pub chain_index: u8,    // index of the current step in the service chain
+	pub flags: u8, // bits for custom NF data. Use with caution to prevent collisions from different NFs
 }
 
 /// Local buffers to put packets in, used to send packets in bursts to the NFs or to the NIC
 /// This buffer takes ownership of the packets
-#[derive(Default)]
-pub struct PacketBuf {
-	buffer: Vec<rte_mbuf>,
+pub struct PacketBuf<P> {
+	buffer: Vec<P>,
 	count: u16,
 }
 
-impl PacketBuf {
+impl<P> Default for PacketBuf<P> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<P> PacketBuf<P> {
 	pub fn new() -> Self {
 		Self {
 			buffer: Vec::with_capacity(PACKET_READ_SIZE),
@@ -55,7 +68,7 @@ impl PacketBuf {
 		}
 	}
 
-	pub fn add_mbuf(&mut self, pkt: rte_mbuf) {
+	pub fn add_mbuf(&mut self, pkt: P) {
 		self.buffer.push(pkt);
 		self.count += 1;
 	}
@@ -63,21 +76,25 @@ impl PacketBuf {
 	pub fn len(&self) -> usize {
 		self.buffer.len()
 	}
+
+	pub fn is_empty(&self) -> bool {
+		self.buffer.is_empty()
+	}
 }
 
 /// Packets may be transported by a tx thread or by an NF. This data structure encapsulates data specific to tx threads.
 // pub struct TxThreadInfo<'nf> {
-pub struct TxThreadInfo {
+pub struct TxThreadInfo<P> {
 	first_nf: u8,
 	last_nf: u8,
 	// the tx thread should know where the packets buffer is, so as to be able to fetch the packets
 	// port_tx_bufs: Option<&'nf PacketBuf>,
 	// REVIEW: A better way might be to move the ownership of the packets to the tx thread
-	port_tx_bufs: Weak<PacketBuf>,
+	port_tx_bufs: Weak<PacketBuf<P>>,
 }
 
-impl TxThreadInfo {
-	fn new(first_nf: u8, last_nf: u8, port_tx_bufs: &PacketBuf) -> Self {
+impl<P> TxThreadInfo<P> {
+	fn new(first_nf: u8, last_nf: u8, port_tx_bufs: &PacketBuf<P>) -> Self {
 		Self {
 			first_nf,
 			last_nf,
@@ -91,7 +108,7 @@ impl TxThreadInfo {
 	the program can do little else but exit
 	*/
 	// REVIEW: Ideally packets shouldn't be added to packet buffer through tx thread
-	pub fn add_mbuf(&mut self, pkt: rte_mbuf) -> Result<(), ExitFailure> {
+	pub fn add_mbuf(&mut self, pkt: P) -> Result<(), ExitFailure> {
 		// try to upgrade the weak reference to an arc
 		// this increments the Arc count for the inner value - the PacketBuf - preventing it from being dropped
 		match &mut self.port_tx_bufs.upgrade() {
@@ -125,32 +142,38 @@ pub enum QmgrType {
 
 type MgrTypeT = QmgrType;
 
-pub enum Qmgr {
-	Mgr(TxThreadInfo),
-	NF(PacketBuf),
+pub enum Qmgr<P> {
+	Mgr(TxThreadInfo<P>),
+	NF(PacketBuf<P>),
 }
 
 /// Generic data struct that tx threads and nfs both use. Allows pkt functions to be shared
 /// The queue manager takes ownership of the packet buffer or the tx thread
-pub struct QueueMgr {
+pub struct QueueMgr<P> {
 	id: u8,
 	mgr_type: MgrTypeT,
-	buf: Qmgr,
-	nf_rx_buf: PacketBuf,
+	buf: Qmgr<P>,
+	nf_rx_buf: PacketBuf<P>,
 }
 
-impl QueueMgr {
+impl<P> Default for QueueMgr<P> {
+	fn default() -> Self {
+		Self::get_self(0, QmgrType::NF, Qmgr::NF(PacketBuf::new()), PacketBuf::new())
+	}
+}
+
+impl<P> QueueMgr<P> {
 	#[inline]
-	fn get_self(id: u8, mgr_type: MgrTypeT, buf: Qmgr, nf_rx_buf: PacketBuf) -> Self {
+	fn get_self(id: u8, mgr_type: MgrTypeT, buf: Qmgr<P>, nf_rx_buf: PacketBuf<P>) -> Self {
 		Self {
 			id,
 			mgr_type,
 			buf,
-			nf_rx_buf: nf_rx_buf,
+			nf_rx_buf,
 		}
 	}
 
-	pub fn new(id: u8, mgr_type: MgrTypeT, buf: Qmgr, nf_rx_buf: PacketBuf) -> Option<Self> {
+	pub fn new(id: u8, mgr_type: MgrTypeT, buf: Qmgr<P>, nf_rx_buf: PacketBuf<P>) -> Option<Self> {
 		match mgr_type {
 			MgrTypeT::MGR => match buf {
 				Qmgr::Mgr(_) => Some(Self::get_self(id, mgr_type, buf, nf_rx_buf)),
@@ -175,15 +198,16 @@ pub struct WakeupThreadContext {
 // 	mutex: &RwLock,
 // }
 
+// NOTE: the stats are updated by the RX and TX threads concurrently
 #[derive(Default)]
 pub struct RxStats {
-	pub rx: [u64; RTE_MAX_ETHPORTS as usize],
+	pub rx: [AtomicU64; RTE_MAX_ETHPORTS as usize],
 }
 
 #[derive(Default)]
 pub struct TxStats {
-	tx: [u64; RTE_MAX_ETHPORTS as usize],
-	tx_drop: [u64; RTE_MAX_ETHPORTS as usize],
+	pub tx: [AtomicU64; RTE_MAX_ETHPORTS as usize],
+	pub tx_drop: [AtomicU64; RTE_MAX_ETHPORTS as usize],
 }
 
 #[derive(Default)]
@@ -196,6 +220,7 @@ impl EtherAddr {
 		Self { addr_bytes }
 	}
 
+	#[cfg(feature = "dpdk")]
 	pub fn from_rte_ether_addr(&self, addr: rte_ether_addr) -> Self {
 		Self::new(addr.addr_bytes)
 	}
@@ -205,71 +230,75 @@ impl EtherAddr {
 	}
 }
 
+// NOTE: the port layout is written during init and only read once the worker threads run
 #[derive(Default)]
 pub struct PortInfo {
-	pub num_ports: RefCell<u8>,
-	pub id: RefCell<[u8; RTE_MAX_ETHPORTS as usize]>,
-	pub init: RefCell<[u8; RTE_MAX_ETHPORTS as usize]>,
-	pub mac: [RefCell<EtherAddr>; RTE_MAX_ETHPORTS as usize],
+	pub num_ports: AtomicU8,
+	pub id: [AtomicU8; RTE_MAX_ETHPORTS as usize],
+	pub init: [AtomicU8; RTE_MAX_ETHPORTS as usize],
+	pub mac: [Mutex<EtherAddr>; RTE_MAX_ETHPORTS as usize],
 	pub rx_stats: RxStats,
 	pub tx_stats: TxStats,
 }
 
 #[derive(Default)]
 struct Flag {
-	onvm_nf_share_cores: u8,
+	onvm_nf_share_cores: AtomicU8,
 }
 
 #[derive(Default)]
 pub struct OnvmConfiguration {
-	flags: RefCell<Flag>,
+	flags: Flag,
 }
 
 impl OnvmConfiguration {
 	pub fn set_flag(&self, share: u8) {
-		self.flags.borrow_mut().onvm_nf_share_cores = share;
+		self.flags
+			.onvm_nf_share_cores
+			.store(share, Ordering::SeqCst);
 	}
 }
 
 #[derive(Default)]
 pub struct CoreStatus {
-	pub enabled: RefCell<bool>,
-	pub is_dedicated_core: RefCell<u16>,
-	pub nf_count: RefCell<u16>,
+	pub enabled: AtomicBool,
+	pub is_dedicated_core: AtomicU16,
+	pub nf_count: AtomicU16,
 }
 
 /// Function prototype for NF packet handlers
-type NfPktHandlerFn = fn(pkt: &rte_mbuf, meta: &OnvmPktMeta, _: &OnvmNfLocalCtx) -> i8;
+type NfPktHandlerFn<B> =
+	fn(pkt: &<B as Backend>::Pkt, meta: &OnvmPktMeta, _: &OnvmNfLocalCtx<B>) -> i8;
 
 /// Function prototype for NFs that want extra initalization/setup before running
-type NfSetupFn = fn(nf_local_ctx: &OnvmNfLocalCtx);
+type NfSetupFn<B> = fn(nf_local_ctx: &OnvmNfLocalCtx<B>);
 
 /// Function prototype for NF the callback
-type NfUserActionsFn = fn(_: &OnvmNfLocalCtx) -> i8;
+type NfUserActionsFn<B> = fn(_: &OnvmNfLocalCtx<B>) -> i8;
 
 /// Function prototype for NFs to handle custom messages
-type NfMsgHandlerFn = fn(msg_data: &str, nf_local_ctx: &OnvmNfLocalCtx);
+type NfMsgHandlerFn<B> = fn(msg_data: &str, nf_local_ctx: &OnvmNfLocalCtx<B>);
 
 /// Function prototype for NFs to signal handling
 type HandleSignalFn = fn(i8);
 
 /// Contains all functions the NF might use
-pub struct OnvmFunctionTable {
-	setup: NfSetupFn,
-	nf_msg_handler_fn: NfMsgHandlerFn,
-	nf_user_actions_fn: NfUserActionsFn,
-	nf_pkt_handler_fn: NfPktHandlerFn,
+pub struct OnvmFunctionTable<B: Backend> {
+	setup: NfSetupFn<B>,
+	nf_msg_handler_fn: NfMsgHandlerFn<B>,
+	nf_user_actions_fn: NfUserActionsFn<B>,
+	nf_pkt_handler_fn: NfPktHandlerFn<B>,
 }
 
 /// Information needed to initialize a new NF child thread
 pub struct OnvmScaleInfo {}
 
 /// The NF local context will own the NF struct
-pub struct OnvmNfLocalCtx {
-	nf: Option<OnvmNF>,
-	nf_init_finished: rte_atomic16_t,
-	keep_running: rte_atomic16_t,
-	nf_stopped: rte_atomic16_t,
+pub struct OnvmNfLocalCtx<B: Backend> {
+	nf: Option<OnvmNF<B>>,
+	nf_init_finished: AtomicI16,
+	keep_running: AtomicI16,
+	nf_stopped: AtomicI16,
 }
 
 #[derive(Default)]
@@ -296,9 +325,9 @@ struct Flags {
 
 #[derive(Default)]
 pub struct ThreadInfo {
-	pub core: u16, // Instance ID of parent NF or 0
-	pub parent: u16,
-	pub children_count: rte_atomic16_t,
+	pub core: AtomicU16,
+	pub parent: AtomicU16, // Instance ID of parent NF or 0
+	pub children_count: AtomicI16,
 }
 
 #[derive(Default)]
@@ -306,40 +335,80 @@ struct SharedCore {
 	// Sleep state (shared mem variable) to track state of NF and trigger wakeups
 	// sleep_state = 1 => NF sleeping (waiting on semaphore)
 	// sleep_state = 0 => NF running (not waiting on semaphore)
-	sleep_state: rte_atomic16_t,
+	sleep_state: AtomicI16,
 	// nf_mutex: std::sync::RwLock;
 }
 
 /// Define a NF structure with all needed info, including:
-/// 	thread information, function callbacks, flags, stats and shared core info.
+/// thread information, function callbacks, flags, stats and shared core info.
 /// This structure is available in the NF when processing packets or executing the callback.
 /// nf denotes the lifetime of the nf
-pub struct OnvmNF {
-	// REVIEW: we might want to change *mut rte_ring to something less risky!
-	pub rx_q: RefCell<Option<*mut rte_ring>>,
-	pub tx_q: RefCell<Option<*mut rte_ring>>,
-	pub msg_q: RefCell<Option<*mut rte_ring>>,
-	pub nf_tx_mgr: RefCell<QueueMgr>,
-	pub instance_id: RefCell<u16>,
-	pub service_id: RefCell<u16>,
-	pub status: RefCell<u16>,
-	pub tag: RefCell<String>,
+/// NOTE: the manager threads and the NF read and write the struct concurrently,
+/// the fields they share are atomics and the rings are set before the NF starts.
+pub struct OnvmNF<B: Backend> {
+	pub rx_q: Option<B::PktRing>,
+	pub tx_q: Option<B::PktRing>,
+	pub msg_q: Option<B::MsgRing>,
+	pub nf_tx_mgr: QueueMgr<B::Pkt>, // only used by the NF itself
+	pub instance_id: AtomicU16,
+	pub service_id: AtomicU16,
+	pub status: AtomicU16,
+	pub tag: Mutex<String>,
 	// FIXME: we need to figure out what msg_data should be
 	// Connected to msg_common_rs::OnvmNfMsg
 	// void *data;
-	pub thread_info: RefCell<ThreadInfo>,
+	pub thread_info: ThreadInfo,
+	// Liveness tracking: TSC cycles at the last heartbeat and the pid of secondary process NFs
+	pub heartbeat: AtomicU64,
+	pub pid: AtomicI32,
+	// The init config the NF started with, it goes back to the init config pool on teardown
+	pub nf_init_cfg: AtomicPtr<OnvmNfInitCfg>,
+	// Held by the NF while it uses its rings, the manager takes it to fence the NF off on teardown
+	pub ring_lock: Mutex<()>,
 	flags: Flags,
-	function_table: OnvmFunctionTable,
+	function_table: Option<OnvmFunctionTable<B>>,
 	// stats: Option<&'nf String>,
 	shared_core: SharedCore,
 }
 
-pub enum OnvmNFMsg {
-	NfStarting(OnvmNfInitCfg),
-	NfReady(*mut OnvmNF),
-	NfStopping(*mut OnvmNF),
+impl<B: Backend> Default for OnvmNF<B> {
+	fn default() -> Self {
+		Self {
+			rx_q: None,
+			tx_q: None,
+			msg_q: None,
+			nf_tx_mgr: Default::default(),
+			instance_id: AtomicU16::new(0),
+			service_id: AtomicU16::new(0),
+			status: AtomicU16::new(NF_WAITING_FOR_ID),
+			tag: Mutex::new(String::new()),
+			thread_info: Default::default(),
+			heartbeat: AtomicU64::new(0),
+			pid: AtomicI32::new(NF_NO_PID),
+			nf_init_cfg: AtomicPtr::new(ptr::null_mut()),
+			ring_lock: Mutex::new(()),
+			flags: Default::default(),
+			function_table: None,
+			shared_core: Default::default(),
+		}
+	}
+}
+
+// NOTE: the rings are shared with the manager threads, everything else they touch is atomic
+unsafe impl<B: Backend> Sync for OnvmNF<B> {}
+unsafe impl<B: Backend> Send for OnvmNF<B> {}
+
+// NOTE: the NF structs and init configs travel as pointers, they live in memzones and mempools
+pub enum OnvmNFMsg<B: Backend> {
+	NfStarting(*mut OnvmNfInitCfg),
+	NfReady(*mut OnvmNF<B>),
+	NfStopping(*mut OnvmNF<B>),
+	// Sent by the manager to an NF, carries the lcore the NF has to move to
+	ChangeCore(u16),
 }
 
+unsafe impl<B: Backend> Send for OnvmNFMsg<B> {}
+
 // // NOTE: This is a marker trait that simply indicates that types that can be sent as message data
 // pub trait OnvmMfgTrait {}
 
@@ -348,25 +417,28 @@ pub enum OnvmNFMsg {
 // impl OnvmMfgTrait for OnvmNF {}
 
 /// The config structure to inialize the NF with onvm_mgr
+/// NOTE: the NF waits on status while the manager fills in the instance ID
 #[derive(Default)]
 pub struct OnvmNfInitCfg {
-	pub instance_id: u16,
+	pub instance_id: AtomicU16,
 	pub service_id: u16,
-	core: u16,
+	pub core: u16,
 	init_options: u16,
-	pub status: u16,
+	pub status: AtomicU16,
 	tag: Option<String>,
 	// If set NF will stop after time reaches time_to_live
 	time_to_live: u16,
 	// If set NF will stop after pkts TX reach pkt_limit
 	pkt_limit: u16,
+	// pid of the NF process, NF_NO_PID for NFs running as threads of the manager
+	pub pid: i32,
 }
 
 /// Define a structure to describe a service chain entry
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct OnvmServiceChainEntry {
-	destination: u16,
-	action: u8,
+	pub destination: u16,
+	pub action: OnvmAction,
 }
 
 #[derive(Default)]
@@ -376,6 +448,42 @@ pub struct OnvmServiceChain {
 	ref_cnt: u8,
 }
 
+impl OnvmServiceChain {
+	/// Adds a step at the end of the chain
+	/// Output : an error if the chain is already ONVM_MAX_CHAIN_LENGTH long
+	pub fn append_entry(
+		&mut self,
+		action: OnvmAction,
+		destination: u16,
+	) -> Result<(), ExitFailure> {
+		if self.chain_length >= ONVM_MAX_CHAIN_LENGTH {
+			return Ok(exit_on_failure(
+				"Service chain is full".into(),
+				"In the OnvmServiceChain::append_entry function",
+			)?);
+		}
+		self.sc[self.chain_length as usize] = OnvmServiceChainEntry {
+			destination,
+			action,
+		};
+		self.chain_length += 1;
+		Ok(())
+	}
+
+	/// The step at index, None past the end of the chain
+	pub fn entry(&self, index: u8) -> Option<&OnvmServiceChainEntry> {
+		self.sc[..self.chain_length as usize].get(index as usize)
+	}
+
+	pub fn len(&self) -> u8 {
+		self.chain_length
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.chain_length == 0
+	}
+}
+
 pub struct LpmRequest {
 	name: String,
 	max_num_rules: u32,
@@ -0,0 +1,221 @@
+/*
+ * Created on Thu Oct 22 2020:14:02:19
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use super::{SimMbuf, SimMempool, SimMemzones, SimRing};
+use crate::mgr::backend::Backend;
+use crate::nflib::constants::{
+	_NF_MEMPOOL_NAME, MAX_NFS, NF_QUEUE_RINGSIZE, NUM_MBUFS, PKTMBUF_POOL_NAME,
+};
+use crate::nflib::structs::{OnvmNFMsg, OnvmNfInitCfg, OnvmPktMeta};
+use exitfailure::ExitFailure;
+use libc::ENOBUFS;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Timer frequency of the simulated clock
+pub const SIM_TIMER_HZ: u64 = 1000;
+/// Number of cores NFs can be placed on
+pub const SIM_NUM_CORES: usize = 4;
+
+/// A simulated NIC port, the test feeds the rx ring and reads the tx ring.
+/// A port has a single queue shared by all the RX and TX threads.
+struct SimPort {
+	rx: SimRing<SimMbuf>,
+	tx: SimRing<SimMbuf>,
+}
+
+/// The sim backend.
+/// Time only moves when the test advances the clock, so heartbeat timeouts are deterministic.
+pub struct SimBackend {
+	memzones: SimMemzones,
+	pktmbuf_pool: SimMempool<SimMbuf>,
+	nf_init_cfg_pool: SimMempool<Box<OnvmNfInitCfg>>,
+	ports: Vec<SimPort>,
+	cycles: AtomicU64,
+}
+
+impl SimBackend {
+	pub fn new(num_ports: u16) -> Self {
+		Self {
+			memzones: Default::default(),
+			pktmbuf_pool: SimMempool::new(PKTMBUF_POOL_NAME, NUM_MBUFS as usize),
+			nf_init_cfg_pool: SimMempool::new(_NF_MEMPOOL_NAME, MAX_NFS as usize),
+			ports: (0..num_ports)
+				.map(|i| SimPort {
+					rx: SimRing::new(&format!("port_{}_rx", i), NF_QUEUE_RINGSIZE),
+					tx: SimRing::new(&format!("port_{}_tx", i), NF_QUEUE_RINGSIZE),
+				})
+				.collect(),
+			// NOTE: starts at 1, a heartbeat of 0 means the NF never reported
+			cycles: AtomicU64::new(1),
+		}
+	}
+
+	/// Moves the simulated clock forward
+	pub fn advance(&self, secs: u64) {
+		self.cycles.fetch_add(secs * SIM_TIMER_HZ, Ordering::SeqCst);
+	}
+
+	/// Puts a packet on a port as if the NIC had received it
+	/// Output : false if the pktmbuf pool or the port's rx ring is full
+	pub fn port_rx(&self, port: u16, data: &[u8]) -> bool {
+		let mut pkt = match self.pktmbuf_pool.get() {
+			Some(pkt) => pkt,
+			None => return false,
+		};
+		pkt.data.extend_from_slice(data);
+		pkt.port = port;
+		pkt.hash = data.iter().fold(0x811c_9dc5_u32, |h, b| {
+			(h ^ *b as u32).wrapping_mul(0x0100_0193)
+		});
+		match self.ports.get(port as usize) {
+			Some(p) => match p.rx.enqueue(pkt) {
+				Ok(()) => true,
+				Err(pkt) => {
+					self.pktmbuf_pool.put(pkt);
+					false
+				}
+			},
+			None => {
+				self.pktmbuf_pool.put(pkt);
+				false
+			}
+		}
+	}
+
+	/// Takes the packets sent out of a port
+	pub fn port_tx(&self, port: u16) -> Vec<Vec<u8>> {
+		let pkts = self.ports[port as usize]
+			.tx
+			.dequeue_burst(NF_QUEUE_RINGSIZE);
+		pkts.into_iter()
+			.map(|mut pkt| {
+				let data = std::mem::take(&mut pkt.data);
+				self.pktmbuf_pool.put(pkt);
+				data
+			})
+			.collect()
+	}
+
+	/// Number of packet buffers not returned to the pool
+	pub fn pktmbuf_in_use(&self) -> usize {
+		self.pktmbuf_pool.in_use()
+	}
+
+	/// Number of NF init configs not returned to the pool
+	pub fn nf_init_cfg_in_use(&self) -> usize {
+		self.nf_init_cfg_pool.in_use()
+	}
+}
+
+impl Backend for SimBackend {
+	type Pkt = SimMbuf;
+	type PktRing = SimRing<SimMbuf>;
+	type MsgRing = SimRing<OnvmNFMsg<SimBackend>>;
+
+	fn pkt_ring_create(&self, name: &str, size: usize) -> Result<Self::PktRing, ExitFailure> {
+		Ok(SimRing::new(name, size))
+	}
+
+	fn msg_ring_create(&self, name: &str, size: usize) -> Result<Self::MsgRing, ExitFailure> {
+		Ok(SimRing::new(name, size))
+	}
+
+	fn pkt_enqueue_burst(&self, ring: &Self::PktRing, pkts: &mut Vec<SimMbuf>) -> usize {
+		ring.enqueue_burst(pkts)
+	}
+
+	fn pkt_dequeue_burst(&self, ring: &Self::PktRing, pkts: &mut Vec<SimMbuf>, n: usize) -> usize {
+		let burst = ring.dequeue_burst(n);
+		let count = burst.len();
+		pkts.extend(burst);
+		count
+	}
+
+	fn msg_enqueue(&self, ring: &Self::MsgRing, msg: OnvmNFMsg<Self>) -> i32 {
+		match ring.enqueue(msg) {
+			Ok(()) => 0,
+			Err(_) => -ENOBUFS,
+		}
+	}
+
+	fn msg_dequeue(&self, ring: &Self::MsgRing) -> Option<OnvmNFMsg<Self>> {
+		ring.dequeue()
+	}
+
+	fn pkt_free(&self, pkt: SimMbuf) {
+		self.pktmbuf_pool.put(pkt);
+	}
+
+	fn pkt_meta<'a>(&self, pkt: &'a mut SimMbuf) -> &'a mut OnvmPktMeta {
+		&mut pkt.meta
+	}
+
+	fn pkt_data<'a>(&self, pkt: &'a mut SimMbuf) -> &'a mut [u8] {
+		&mut pkt.data
+	}
+
+	fn pkt_hash(&self, pkt: &SimMbuf) -> u32 {
+		pkt.hash
+	}
+
+	fn nf_init_cfg_get(&self) -> Option<*mut OnvmNfInitCfg> {
+		self.nf_init_cfg_pool.get().map(Box::into_raw)
+	}
+
+	unsafe fn nf_init_cfg_put(&self, cfg: *mut OnvmNfInitCfg) {
+		self.nf_init_cfg_pool.put(Box::from_raw(cfg));
+	}
+
+	fn memzone_reserve<T: Send + Sync + 'static>(
+		&self,
+		name: &str,
+		objs: Vec<T>,
+	) -> Result<*mut T, ExitFailure> {
+		self.memzones.reserve(name, objs)
+	}
+
+	fn memzone_lookup<T: Send + Sync + 'static>(&self, name: &str) -> Option<*mut T> {
+		self.memzones.lookup(name)
+	}
+
+	fn port_rx_burst(&self, port: u16, _queue_id: u16, pkts: &mut Vec<SimMbuf>, n: usize) -> usize {
+		match self.ports.get(port as usize) {
+			Some(p) => self.pkt_dequeue_burst(&p.rx, pkts, n),
+			None => 0,
+		}
+	}
+
+	fn port_tx_burst(&self, port: u16, _queue_id: u16, pkts: &mut Vec<SimMbuf>) -> usize {
+		match self.ports.get(port as usize) {
+			Some(p) => p.tx.enqueue_burst(pkts),
+			None => 0,
+		}
+	}
+
+	fn num_cores(&self) -> usize {
+		SIM_NUM_CORES
+	}
+
+	fn lcore_id(&self) -> u32 {
+		0
+	}
+
+	/// NFs are threads of the test, they are not pinned
+	fn thread_set_affinity(&self, _core: u16) -> Result<(), ExitFailure> {
+		Ok(())
+	}
+
+	fn timer_cycles(&self) -> u64 {
+		self.cycles.load(Ordering::SeqCst)
+	}
+
+	fn timer_hz(&self) -> u64 {
+		SIM_TIMER_HZ
+	}
+
+	fn log(&self, _level: u32, msg: &str) {
+		print!("{}", msg);
+	}
+}
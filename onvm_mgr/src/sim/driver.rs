@@ -0,0 +1,340 @@
+/*
+ * Created on Thu Oct 22 2020:14:40:56
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use super::SimBackend;
+use crate::error_handling::exit_on_failure;
+use crate::mgr::driver::{MgrDriver, NfHandle, PktHandler};
+use crate::mgr::global::GlobalNFState;
+use crate::nflib::structs::OnvmServiceChainEntry;
+use exitfailure::ExitFailure;
+use std::sync::atomic::Ordering;
+
+/// Function prototype for simulated NF packet handlers.
+/// The handler sets the action and destination in the packet's metadata.
+pub type SimPktHandler = PktHandler<SimBackend>;
+
+/// Runs the manager on the sim backend.
+/// poll() performs one iteration of the master, RX and TX threads.
+pub type SimDriver = MgrDriver<SimBackend>;
+
+/// Handle of a simulated NF thread
+pub type SimNfHandle = NfHandle;
+
+impl MgrDriver<SimBackend> {
+	/// Sets up the manager on the sim backend the way init does on DPDK
+	/// Input  : the number of ports, the default service chain and the number of TX threads
+	pub fn new(
+		num_ports: u16,
+		default_chain: &[OnvmServiceChainEntry],
+		num_tx_threads: u32,
+	) -> Result<Self, ExitFailure> {
+		if default_chain.is_empty() {
+			exit_on_failure(
+				"The default service chain is empty".into(),
+				"In the SimDriver::new function",
+			)?;
+		}
+
+		let mut global_state = GlobalNFState::new(SimBackend::new(num_ports))?;
+		for port in 0..num_ports {
+			let n = global_state
+				.ports()
+				.num_ports
+				.fetch_add(1, Ordering::SeqCst) as usize;
+			global_state.ports().id[n].store(port as u8, Ordering::SeqCst);
+		}
+		for core in global_state.cores.iter() {
+			let core = **core;
+			unsafe { (*core).enabled.store(true, Ordering::SeqCst) };
+		}
+		{
+			let mut chain = global_state.default_chain.write().unwrap();
+			for entry in default_chain {
+				chain.append_entry(entry.action, entry.destination)?;
+			}
+		}
+		global_state.num_rx_threads = 1;
+		global_state.num_tx_threads = num_tx_threads.max(1) as u16;
+		Ok(Self::from_state(global_state))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mgr::backend::Backend;
+	use crate::nflib::constants::*;
+	use crate::nflib::structs::OnvmAction;
+	use crate::sim::{SimMbuf, SIM_NUM_CORES};
+	use std::sync::atomic::AtomicBool;
+	use std::sync::Arc;
+	use std::thread;
+	use std::time::{Duration, Instant};
+
+	/// Polls the manager until cond holds, fails the test after a second
+	fn poll_until(driver: &mut SimDriver, mut cond: impl FnMut(&SimDriver) -> bool) {
+		let start = Instant::now();
+		while !cond(driver) {
+			assert!(start.elapsed() < Duration::from_secs(1), "timed out");
+			driver.poll();
+			thread::yield_now();
+		}
+	}
+
+	/// Polls the manager until the NF runs
+	fn wait_running(driver: &mut SimDriver, nf: &SimNfHandle) -> u16 {
+		poll_until(driver, |_| nf.start_status() != NF_WAITING_FOR_ID);
+		assert_eq!(NF_RUNNING, nf.start_status());
+		nf.instance_id().unwrap()
+	}
+
+	fn to_nf(service_id: u16) -> Vec<OnvmServiceChainEntry> {
+		vec![OnvmServiceChainEntry {
+			action: OnvmAction::TONF,
+			destination: service_id,
+		}]
+	}
+
+	fn out(port: u16) -> SimPktHandler {
+		Box::new(move |_, pkt: &mut SimMbuf| {
+			pkt.meta.action = OnvmAction::OUT;
+			pkt.meta.destination = port;
+		})
+	}
+
+	#[test]
+	fn nf_lifecycle() {
+		let mut driver = SimDriver::new(1, &to_nf(1), 1).unwrap();
+		let nf = driver.spawn_nf(1, out(0));
+		let id = wait_running(&mut driver, &nf);
+		assert_eq!(1, driver.num_nfs());
+
+		nf.stop();
+		poll_until(&mut driver, |d| d.nf_status(id) == NF_STOPPED);
+		assert_eq!(0, driver.num_nfs());
+		assert_eq!(0, driver.backend().nf_init_cfg_in_use());
+
+		/* the NF started again gets the next instance ID */
+		let nf = driver.spawn_nf(1, out(0));
+		assert_eq!(id + 1, wait_running(&mut driver, &nf));
+		nf.stop();
+	}
+
+	#[test]
+	fn rx_to_nf_to_tx() {
+		let mut driver = SimDriver::new(2, &to_nf(1), 1).unwrap();
+		let nf = driver.spawn_nf(1, out(1));
+		let id = wait_running(&mut driver, &nf);
+
+		assert!(driver.backend().port_rx(0, &[1, 2, 3]));
+		let mut sent = vec![];
+		poll_until(&mut driver, |d| {
+			sent.extend(d.backend().port_tx(1));
+			!sent.is_empty()
+		});
+		assert_eq!(vec![vec![1, 2, 3]], sent);
+		assert!(driver.backend().port_tx(0).is_empty());
+
+		nf.stop();
+		poll_until(&mut driver, |d| d.nf_status(id) == NF_STOPPED);
+		assert_eq!(0, driver.backend().pktmbuf_in_use());
+	}
+
+	#[test]
+	fn service_chain() {
+		let chain = vec![
+			OnvmServiceChainEntry {
+				action: OnvmAction::TONF,
+				destination: 1,
+			},
+			OnvmServiceChainEntry {
+				action: OnvmAction::TONF,
+				destination: 2,
+			},
+		];
+		let mut driver = SimDriver::new(1, &chain, 4).unwrap();
+		let first = driver.spawn_nf(
+			1,
+			Box::new(|_, pkt: &mut SimMbuf| {
+				pkt.data.push(1);
+				pkt.meta.action = OnvmAction::NEXT;
+			}),
+		);
+		let second = driver.spawn_nf(
+			2,
+			Box::new(|_, pkt: &mut SimMbuf| {
+				pkt.data.push(2);
+				pkt.meta.action = OnvmAction::OUT;
+				pkt.meta.destination = 0;
+			}),
+		);
+		poll_until(&mut driver, |d| d.num_nfs() == 2);
+
+		assert!(driver.backend().port_rx(0, &[0]));
+		let mut sent = vec![];
+		poll_until(&mut driver, |d| {
+			sent.extend(d.backend().port_tx(0));
+			!sent.is_empty()
+		});
+		assert_eq!(vec![vec![0, 1, 2]], sent);
+
+		first.stop();
+		second.stop();
+	}
+
+	#[test]
+	fn drop_without_nf() {
+		let mut driver = SimDriver::new(1, &to_nf(3), 1).unwrap();
+		assert!(driver.backend().port_rx(0, &[1]));
+		poll_until(&mut driver, |d| d.dropped() == 1);
+		assert_eq!(0, driver.backend().pktmbuf_in_use());
+	}
+
+	#[test]
+	fn silent_nf_reclaimed() {
+		let mut driver = SimDriver::new(1, &to_nf(2), 1).unwrap();
+		let pkts_in_use = driver.backend().pktmbuf_in_use();
+		let live = driver.spawn_nf(1, out(0));
+		let live_id = wait_running(&mut driver, &live);
+
+		/* the silent NF gets stuck handling its first packet and stops sending heartbeats */
+		let stuck = Arc::new(AtomicBool::new(false));
+		let release = Arc::new(AtomicBool::new(false));
+		let (is_stuck, is_released) = (stuck.clone(), release.clone());
+		let silent = driver.spawn_nf(
+			2,
+			Box::new(move |_, _: &mut SimMbuf| {
+				is_stuck.store(true, Ordering::SeqCst);
+				while !is_released.load(Ordering::SeqCst) {
+					thread::yield_now();
+				}
+			}),
+		);
+		let silent_id = wait_running(&mut driver, &silent);
+		assert!(driver.backend().port_rx(0, &[1]));
+		poll_until(&mut driver, |_| stuck.load(Ordering::SeqCst));
+
+		/* the live NF keeps sending heartbeats once the timeout has passed */
+		driver.backend().advance(NF_HEARTBEAT_TIMEOUT);
+		let now = driver.backend().timer_cycles();
+		let heartbeat = |d: &SimDriver| {
+			let nf = *d.global_state().nfs[live_id as usize].clone();
+			unsafe { (*nf).heartbeat.load(Ordering::SeqCst) }
+		};
+		let start = Instant::now();
+		while heartbeat(&driver) < now {
+			assert!(start.elapsed() < Duration::from_secs(1), "timed out");
+			thread::yield_now();
+		}
+
+		driver.poll();
+		assert_eq!(NF_RUNNING, driver.nf_status(live_id));
+		assert_eq!(NF_RECLAIMED, driver.nf_status(silent_id));
+		assert_eq!(1, driver.num_nfs());
+		assert_eq!(1, driver.backend().nf_init_cfg_in_use());
+
+		/* the silent NF drops the packet it held and gives its instance ID back once it exits */
+		release.store(true, Ordering::SeqCst);
+		silent.stop();
+		assert_eq!(NF_STOPPED, driver.nf_status(silent_id));
+		live.stop();
+		poll_until(&mut driver, |d| d.nf_status(live_id) == NF_STOPPED);
+		assert_eq!(0, driver.num_nfs());
+		assert_eq!(0, driver.backend().nf_init_cfg_in_use());
+		assert_eq!(pkts_in_use, driver.backend().pktmbuf_in_use());
+	}
+
+	#[test]
+	fn nf_relocated() {
+		let mut driver = SimDriver::new(1, &to_nf(1), 1).unwrap();
+		let nf = driver.spawn_nf(1, out(0));
+		let id = wait_running(&mut driver, &nf);
+		let nf_core = |d: &SimDriver| {
+			let nf = *d.global_state().nfs[id as usize].clone();
+			unsafe { (*nf).thread_info.core.load(Ordering::SeqCst) }
+		};
+		let nf_count = |d: &SimDriver, core: usize| {
+			let core = *d.global_state().cores[core].clone();
+			unsafe { (*core).nf_count.load(Ordering::SeqCst) }
+		};
+		assert_eq!(0, nf_core(&driver));
+
+		/* the manager moves the NF in its bookkeeping when it sends the change core message */
+		driver.relocate_nf(id, 1);
+		driver.poll();
+		assert_eq!(1, nf_core(&driver));
+		assert_eq!(0, nf_count(&driver, 0));
+		assert_eq!(1, nf_count(&driver, 1));
+		assert_eq!(NF_RUNNING, driver.nf_status(id));
+
+		/* a move to a core that doesn't exist leaves the NF where it is */
+		driver.relocate_nf(id, SIM_NUM_CORES as u16);
+		driver.poll();
+		assert_eq!(1, nf_core(&driver));
+		assert_eq!(1, nf_count(&driver, 1));
+
+		nf.stop();
+		poll_until(&mut driver, |d| d.nf_status(id) == NF_STOPPED);
+		assert_eq!(0, nf_count(&driver, 1));
+	}
+
+	#[test]
+	fn nf_stopped_before_moving() {
+		let mut driver = SimDriver::new(1, &to_nf(1), 1).unwrap();
+
+		/* the NF gets stuck handling its first packet and never reads the change core message */
+		let stuck = Arc::new(AtomicBool::new(false));
+		let release = Arc::new(AtomicBool::new(false));
+		let (is_stuck, is_released) = (stuck.clone(), release.clone());
+		let nf = driver.spawn_nf(
+			1,
+			Box::new(move |_, _: &mut SimMbuf| {
+				is_stuck.store(true, Ordering::SeqCst);
+				while !is_released.load(Ordering::SeqCst) {
+					thread::yield_now();
+				}
+			}),
+		);
+		let id = wait_running(&mut driver, &nf);
+		assert!(driver.backend().port_rx(0, &[1]));
+		poll_until(&mut driver, |_| stuck.load(Ordering::SeqCst));
+
+		let nf_count = |d: &SimDriver, core: usize| {
+			let core = *d.global_state().cores[core].clone();
+			unsafe { (*core).nf_count.load(Ordering::SeqCst) }
+		};
+		driver.relocate_nf(id, 1);
+		driver.poll();
+		assert_eq!(0, nf_count(&driver, 0));
+		assert_eq!(1, nf_count(&driver, 1));
+
+		/* the NF is reclaimed from its new core */
+		driver.backend().advance(NF_HEARTBEAT_TIMEOUT);
+		driver.poll();
+		assert_eq!(NF_RECLAIMED, driver.nf_status(id));
+		for core in 0..SIM_NUM_CORES {
+			assert_eq!(0, nf_count(&driver, core));
+		}
+
+		release.store(true, Ordering::SeqCst);
+		nf.stop();
+		assert_eq!(NF_STOPPED, driver.nf_status(id));
+	}
+
+	#[test]
+	fn invalid_service_rejected() {
+		let chain = vec![OnvmServiceChainEntry {
+			action: OnvmAction::DROP,
+			destination: 0,
+		}];
+		let mut driver = SimDriver::new(1, &chain, 1).unwrap();
+		let nf = driver.spawn_nf(MAX_SERVICES as u16, out(0));
+		poll_until(&mut driver, |_| nf.start_status() == NF_SERVICE_MAX);
+		assert!(nf.instance_id().is_none());
+		nf.stop();
+		assert_eq!(0, driver.num_nfs());
+		assert_eq!(0, driver.backend().nf_init_cfg_in_use());
+	}
+}
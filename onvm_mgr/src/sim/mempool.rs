@@ -0,0 +1,85 @@
+/*
+ * Created on Tue Oct 20 2020:10:02:55
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use crate::nflib::structs::OnvmPktMeta;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A packet buffer handed out by the pktmbuf pool of the sim backend
+#[derive(Debug, Default)]
+pub struct SimMbuf {
+	pub data: Vec<u8>,
+	pub port: u16,
+	pub hash: u32, // stands in for the RSS hash the NIC would compute
+	pub meta: OnvmPktMeta,
+}
+
+/// A fixed size mempool standing in for rte_mempool.
+/// Objects are created on get, only the number of objects in use is tracked so pool exhaustion
+/// and leaks show up in tests.
+pub struct SimMempool<T> {
+	name: String,
+	size: usize,
+	in_use: AtomicUsize,
+	_marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: Default> SimMempool<T> {
+	pub fn new(name: &str, size: usize) -> Self {
+		Self {
+			name: name.to_string(),
+			size,
+			in_use: AtomicUsize::new(0),
+			_marker: std::marker::PhantomData,
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Gets a default object from the pool, None once the pool is exhausted
+	pub fn get(&self) -> Option<T> {
+		let mut cur = self.in_use.load(Ordering::SeqCst);
+		loop {
+			if cur >= self.size {
+				return None;
+			}
+			match self
+				.in_use
+				.compare_exchange(cur, cur + 1, Ordering::SeqCst, Ordering::SeqCst)
+			{
+				Ok(_) => return Some(Default::default()),
+				Err(actual) => cur = actual,
+			}
+		}
+	}
+
+	/// Returns an object to the pool
+	pub fn put(&self, obj: T) {
+		drop(obj);
+		self.in_use.fetch_sub(1, Ordering::SeqCst);
+	}
+
+	/// Number of objects currently handed out
+	pub fn in_use(&self) -> usize {
+		self.in_use.load(Ordering::SeqCst)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mempool_exhaustion() {
+		let pool = SimMempool::<SimMbuf>::new("test", 2);
+		let a = pool.get().unwrap();
+		let _b = pool.get().unwrap();
+		assert!(pool.get().is_none());
+		pool.put(a);
+		assert_eq!(1, pool.in_use());
+		assert!(pool.get().is_some());
+	}
+}
@@ -0,0 +1,81 @@
+/*
+ * Created on Tue Oct 20 2020:10:15:08
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use crate::error_handling::exit_on_failure;
+use exitfailure::ExitFailure;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The objects of a memzone, they stay at the same address until the memzones are dropped
+struct Zone<T> {
+	ptr: *mut T,
+	len: usize,
+}
+
+impl<T> Drop for Zone<T> {
+	fn drop(&mut self) {
+		unsafe {
+			drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+				self.ptr, self.len,
+			)))
+		};
+	}
+}
+
+// NOTE: like a rte_memzone, a zone is shared memory, the objects in it are Send and Sync
+unsafe impl<T: Send + Sync> Send for Zone<T> {}
+unsafe impl<T: Send + Sync> Sync for Zone<T> {}
+
+/// Named shared memory, standing in for rte_memzone_reserve and rte_memzone_lookup
+#[derive(Default)]
+pub struct SimMemzones {
+	zones: Mutex<HashMap<String, Box<dyn Any + Send + Sync>>>,
+}
+
+impl SimMemzones {
+	/// Reserves a memzone holding objs
+	/// Output : a pointer to the first object, or an error if the name is already taken
+	pub fn reserve<T: Send + Sync + 'static>(
+		&self,
+		name: &str,
+		objs: Vec<T>,
+	) -> Result<*mut T, ExitFailure> {
+		let mut zones = self.zones.lock().unwrap();
+		if zones.contains_key(name) {
+			let f = format!("Memzone {} already exists", name);
+			exit_on_failure(f, "In the SimMemzones::reserve function")?;
+		}
+		let len = objs.len();
+		let ptr = Box::into_raw(objs.into_boxed_slice()) as *mut T;
+		zones.insert(name.to_string(), Box::new(Zone { ptr, len }));
+		Ok(ptr)
+	}
+
+	/// Looks up a memzone by name, None if it does not exist or holds another type
+	pub fn lookup<T: Send + Sync + 'static>(&self, name: &str) -> Option<*mut T> {
+		let zones = self.zones.lock().unwrap();
+		zones
+			.get(name)
+			.and_then(|zone| zone.downcast_ref::<Zone<T>>())
+			.map(|zone| zone.ptr)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn memzone_reserve_and_lookup() {
+		let zones = SimMemzones::default();
+		let zone = zones.reserve("zone", vec![42_u32, 43]).unwrap();
+		assert_eq!(zone, zones.lookup::<u32>("zone").unwrap());
+		assert_eq!(43, unsafe { *zones.lookup::<u32>("zone").unwrap().add(1) });
+		assert!(zones.lookup::<u64>("zone").is_none());
+		assert!(zones.lookup::<u32>("missing").is_none());
+		assert!(zones.reserve("zone", vec![1_u32]).is_err());
+	}
+}
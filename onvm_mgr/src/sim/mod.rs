@@ -0,0 +1,22 @@
+/*
+ * Created on Tue Oct 20 2020:09:41:12
+ * Created by Ratnadeep Bhattacharya
+ */
+
+/* In-process simulation backend for the manager
+Rings, mempools and memzones are backed by ordinary Rust memory and NFs run as threads,
+so the NF lifecycle and the RX -> NF -> TX routing of the real manager can be tested without
+the DPDK EAL. Enabled with the "sim" feature.
+*/
+
+mod backend;
+mod driver;
+mod memzone;
+mod mempool;
+mod ring;
+
+pub use self::backend::{SimBackend, SIM_NUM_CORES, SIM_TIMER_HZ};
+pub use self::driver::{SimDriver, SimNfHandle, SimPktHandler};
+pub use self::memzone::SimMemzones;
+pub use self::mempool::{SimMbuf, SimMempool};
+pub use self::ring::SimRing;
@@ -0,0 +1,93 @@
+/*
+ * Created on Tue Oct 20 2020:09:48:37
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// A bounded multi-producer multi-consumer ring standing in for rte_ring
+pub struct SimRing<T> {
+	name: String,
+	capacity: usize,
+	slots: Mutex<VecDeque<T>>,
+}
+
+impl<T> SimRing<T> {
+	pub fn new(name: &str, capacity: usize) -> Self {
+		Self {
+			name: name.to_string(),
+			capacity,
+			// NOTE: not preallocated, the manager creates a ring per NF up front
+			slots: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Number of objects in the ring
+	pub fn count(&self) -> usize {
+		self.slots.lock().unwrap().len()
+	}
+
+	/// Enqueues one object, the object is handed back if the ring is full
+	pub fn enqueue(&self, obj: T) -> Result<(), T> {
+		let mut slots = self.slots.lock().unwrap();
+		if slots.len() >= self.capacity {
+			return Err(obj);
+		}
+		slots.push_back(obj);
+		Ok(())
+	}
+
+	/// Enqueues as many objects as fit, the rest stay in objs
+	/// Output : the number of objects enqueued
+	pub fn enqueue_burst(&self, objs: &mut Vec<T>) -> usize {
+		let mut slots = self.slots.lock().unwrap();
+		let n = objs.len().min(self.capacity - slots.len());
+		slots.extend(objs.drain(..n));
+		n
+	}
+
+	pub fn dequeue(&self) -> Option<T> {
+		self.slots.lock().unwrap().pop_front()
+	}
+
+	/// Dequeues up to n objects
+	pub fn dequeue_burst(&self, n: usize) -> Vec<T> {
+		let mut slots = self.slots.lock().unwrap();
+		let n = n.min(slots.len());
+		slots.drain(..n).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ring_is_bounded() {
+		let ring = SimRing::new("test", 2);
+		assert!(ring.enqueue(1).is_ok());
+		assert!(ring.enqueue(2).is_ok());
+		assert_eq!(Err(3), ring.enqueue(3));
+		assert_eq!(2, ring.count());
+	}
+
+	#[test]
+	fn ring_bursts() {
+		let ring = SimRing::new("test", 3);
+		let mut objs = vec![1, 2, 3, 4];
+		assert_eq!(3, ring.enqueue_burst(&mut objs));
+		assert_eq!(vec![4], objs);
+		assert_eq!(vec![1, 2], ring.dequeue_burst(2));
+		assert_eq!(Some(3), ring.dequeue());
+		assert_eq!(None, ring.dequeue());
+	}
+}
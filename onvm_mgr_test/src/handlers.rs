@@ -0,0 +1,59 @@
+/*
+ * Created on Wed Oct 21 2020:12:05:30
+ * Created by Ratnadeep Bhattacharya
+ */
+
+/* Built-in NF packet handlers scenarios can refer to by name */
+
+use crate::scenario::NfCfg;
+use onvm_mgr::mgr::backend::Backend;
+use onvm_mgr::mgr::driver::PktHandler;
+use onvm_mgr::nflib::structs::OnvmAction;
+
+/// Builds the packet handler a NF of the scenario runs
+pub fn handler<B: Backend>(nf: &NfCfg) -> Result<PktHandler<B>, String> {
+    match &nf.handler[..] {
+        // send every packet out of a port
+        "forward" => {
+            let port = nf
+                .port
+                .ok_or_else(|| format!("service {}: forward needs a port", nf.service_id))?;
+            Ok(Box::new(move |backend: &B, pkt: &mut B::Pkt| {
+                let meta = backend.pkt_meta(pkt);
+                meta.action = OnvmAction::OUT;
+                meta.destination = port;
+            }))
+        }
+        // send every packet to another service
+        "to_nf" => {
+            let service = nf
+                .service
+                .ok_or_else(|| format!("service {}: to_nf needs a service", nf.service_id))?;
+            Ok(Box::new(move |backend: &B, pkt: &mut B::Pkt| {
+                let meta = backend.pkt_meta(pkt);
+                meta.action = OnvmAction::TONF;
+                meta.destination = service;
+            }))
+        }
+        // hand the packet to the next entry of the service chain
+        "next" => Ok(Box::new(|backend: &B, pkt: &mut B::Pkt| {
+            backend.pkt_meta(pkt).action = OnvmAction::NEXT;
+        })),
+        "drop" => Ok(Box::new(|backend: &B, pkt: &mut B::Pkt| {
+            backend.pkt_meta(pkt).action = OnvmAction::DROP;
+        })),
+        // swap the Ethernet source and destination and follow the service chain
+        "swap_mac" => Ok(Box::new(|backend: &B, pkt: &mut B::Pkt| {
+            let data = backend.pkt_data(pkt);
+            if data.len() >= 12 {
+                let (dst, src) = data[..12].split_at_mut(6);
+                dst.swap_with_slice(src);
+            }
+            backend.pkt_meta(pkt).action = OnvmAction::NEXT;
+        })),
+        other => Err(format!(
+            "service {}: unknown handler {}",
+            nf.service_id, other
+        )),
+    }
+}
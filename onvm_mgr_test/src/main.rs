@@ -3,20 +3,95 @@
  * Created by Ratnadeep Bhattacharya
  */
 
-use onvm_mgr;
-// use onvm_mgr::mgr::init;
+/* Scenario driven integration harness for the manager
+Usage: onvm_mgr_test [--sim] [--eal-args] <scenario.toml>...
+Every scenario is run on the DPDK manager, with its ports as net_ring and net_pcap virtual
+devices, and its expectations are checked. With --sim, or when the harness is built with the
+sim feature only, the scenarios run on the simulated manager instead.
+With --eal-args the EAL arguments creating the scenario's ports are printed instead.
+*/
+
+mod handlers;
+mod pcap;
+mod runner;
+mod scenario;
+
+use scenario::Scenario;
 use std::env;
-// use std::ffi::CString;
-use std::os::raw::c_int;
+use std::path::Path;
+use std::process::{self, Command};
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
-    println!("Args received: {:?}", &args);
-    // onvm_mgr::main_run(args.len() as c_int, &mut (&mut args as *mut _ as *mut i8));
-    // match onvm_mgr::mgr::init::init(args.len() as c_int, &mut (&mut args as *mut _ as *mut i8)) {
-    match onvm_mgr::mgr::init::init(args) {
-        Ok(()) => println!("Successfully init"),
-        Err(e) => println!("Init failed: {:?}", e),
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let eal_args = args.iter().any(|a| a == "--eal-args");
+    let sim = args.iter().any(|a| a == "--sim") || !cfg!(feature = "dpdk");
+    args.retain(|a| a != "--eal-args" && a != "--sim");
+    if args.is_empty() {
+        println!("Usage: onvm_mgr_test [--sim] [--eal-args] <scenario.toml>...");
+        process::exit(2);
+    }
+    // NOTE: the EAL can only be initialised once per process, every DPDK scenario gets its own
+    let in_child = !sim && !eal_args && args.len() > 1;
+
+    let mut failed = 0;
+    for path in &args {
+        let scenario = match Scenario::load(Path::new(path)) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                println!("ERROR {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        if eal_args {
+            println!("{}: {}", scenario.name, scenario.eal_args().join(" "));
+            continue;
+        }
+        if in_child {
+            if !run_child(path) {
+                failed += 1;
+            }
+            continue;
+        }
+
+        match runner::run(&scenario, sim) {
+            Ok(failures) if failures.is_empty() => println!("PASS {}", scenario.name),
+            Ok(failures) => {
+                println!("FAIL {}", scenario.name);
+                for f in failures {
+                    println!("    {}", f);
+                }
+                failed += 1;
+            }
+            Err(e) => {
+                println!("ERROR {}: {}", scenario.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        println!("{} of {} scenarios failed", failed, args.len());
+        process::exit(1);
+    }
+}
+
+/// Runs a single DPDK scenario in a new process of the harness, which prints the result
+/// Output : true if the scenario passed
+fn run_child(path: &str) -> bool {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            println!("ERROR {}: {}", path, e);
+            return false;
+        }
+    };
+    match Command::new(exe).arg(path).status() {
+        Ok(status) => status.success(),
+        Err(e) => {
+            println!("ERROR {}: {}", path, e);
+            false
+        }
     }
-    println!("Hello, world!");
 }
@@ -0,0 +1,88 @@
+/*
+ * Created on Wed Oct 21 2020:11:40:52
+ * Created by Ratnadeep Bhattacharya
+ */
+
+/* Minimal reader and writer for libpcap capture files
+Only Ethernet captures are supported, timestamps are not kept.
+*/
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: u32 = 0xa1b2_c3d4;
+const MAGIC_NSEC: u32 = 0xa1b2_3c4d;
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+const LINKTYPE_ETHERNET: u32 = 1;
+#[cfg(feature = "sim")]
+const SNAPLEN: u32 = 65535;
+
+/// Reads all the packets of a capture file
+pub fn read(path: &Path) -> Result<Vec<Vec<u8>>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse(bytes: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    if bytes.len() < GLOBAL_HEADER_LEN {
+        return Err("truncated pcap header".into());
+    }
+    let magic = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    // the capture is in the byte order of the machine that wrote it
+    let le = match magic {
+        MAGIC | MAGIC_NSEC => true,
+        _ if magic.swap_bytes() == MAGIC || magic.swap_bytes() == MAGIC_NSEC => false,
+        _ => return Err("not a pcap file".into()),
+    };
+    let u32_at = |off: usize| {
+        let b: [u8; 4] = bytes[off..off + 4].try_into().unwrap();
+        if le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    };
+    if u32_at(20) != LINKTYPE_ETHERNET {
+        return Err("only Ethernet captures are supported".into());
+    }
+
+    let mut pkts = vec![];
+    let mut off = GLOBAL_HEADER_LEN;
+    while off < bytes.len() {
+        if off + RECORD_HEADER_LEN > bytes.len() {
+            return Err(format!("truncated record header at offset {}", off));
+        }
+        let incl_len = u32_at(off + 8) as usize;
+        off += RECORD_HEADER_LEN;
+        if off + incl_len > bytes.len() {
+            return Err(format!("truncated packet at offset {}", off));
+        }
+        pkts.push(bytes[off..off + incl_len].to_vec());
+        off += incl_len;
+    }
+    Ok(pkts)
+}
+
+/// Writes the packets to a capture file, in little endian with zeroed timestamps
+/// NOTE: on DPDK the net_pcap ports write their own captures
+#[cfg(feature = "sim")]
+pub fn write(path: &Path, pkts: &[Vec<u8>]) -> Result<(), String> {
+    let mut bytes = Vec::with_capacity(GLOBAL_HEADER_LEN);
+    bytes.extend_from_slice(&MAGIC.to_le_bytes());
+    bytes.extend_from_slice(&2_u16.to_le_bytes());
+    bytes.extend_from_slice(&4_u16.to_le_bytes());
+    bytes.extend_from_slice(&0_i32.to_le_bytes());
+    bytes.extend_from_slice(&0_u32.to_le_bytes());
+    bytes.extend_from_slice(&SNAPLEN.to_le_bytes());
+    bytes.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    for pkt in pkts {
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&(pkt.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(pkt.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(pkt);
+    }
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
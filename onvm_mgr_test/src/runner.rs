@@ -0,0 +1,310 @@
+/*
+ * Created on Wed Oct 21 2020:12:31:04
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use crate::scenario::Scenario;
+use crate::{handlers, pcap};
+use onvm_mgr::mgr::backend::Backend;
+use onvm_mgr::mgr::driver::{MgrDriver, NfHandle};
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "dpdk")]
+use onvm_mgr::mgr::init;
+#[cfg(feature = "sim")]
+use onvm_mgr::sim::SimDriver;
+#[cfg(feature = "dpdk")]
+use std::fs;
+
+// EAL arguments of every DPDK run, the scenario adds its --vdev ports
+#[cfg(feature = "dpdk")]
+const EAL_ARGS: [&str; 6] = ["-l", "0", "-n", "2", "--proc-type=primary", "--no-pci"];
+
+/// What the manager did with the packets of a scenario
+struct Outcome {
+    // number of packets sent out of every port
+    sent: HashMap<u16, u64>,
+    // the packets sent out of the ports whose output is captured
+    captures: HashMap<u16, Vec<Vec<u8>>>,
+    dropped: u64,
+    num_nfs: u32,
+}
+
+/// Runs a scenario on the simulated or on the DPDK manager
+/// Output : the failed expectations, empty if the scenario passed
+pub fn run(scenario: &Scenario, sim: bool) -> Result<Vec<String>, String> {
+    let outcome = if sim {
+        run_sim(scenario)?
+    } else {
+        run_dpdk(scenario)?
+    };
+    check(scenario, &outcome)
+}
+
+/// Starts the NFs of a scenario and polls the manager until they all run
+fn start_nfs<B: Backend + 'static>(
+    driver: &mut MgrDriver<B>,
+    scenario: &Scenario,
+) -> Result<Vec<NfHandle>, String> {
+    let mut nfs = vec![];
+    for nf in &scenario.nfs {
+        for _ in 0..nf.instances {
+            nfs.push(driver.spawn_nf(nf.service_id, handlers::handler(nf)?));
+        }
+    }
+    let timeout = Duration::from_millis(scenario.timeout_ms);
+    let start = Instant::now();
+    while driver.num_nfs() < nfs.len() as u32 {
+        if start.elapsed() > timeout {
+            return Err(format!(
+                "only {} of {} NFs started",
+                driver.num_nfs(),
+                nfs.len()
+            ));
+        }
+        driver.poll();
+        thread::yield_now();
+    }
+    Ok(nfs)
+}
+
+/// Polls the manager until done holds or the scenario times out
+fn poll_until<B: Backend + 'static>(
+    driver: &mut MgrDriver<B>,
+    scenario: &Scenario,
+    mut done: impl FnMut(&MgrDriver<B>) -> bool,
+) {
+    let timeout = Duration::from_millis(scenario.timeout_ms);
+    let start = Instant::now();
+    loop {
+        driver.poll();
+        if done(driver) || start.elapsed() > timeout {
+            break;
+        }
+        thread::yield_now();
+    }
+}
+
+/// Runs a scenario on the simulated manager, the harness feeds and drains the ports itself
+#[cfg(feature = "sim")]
+fn run_sim(scenario: &Scenario) -> Result<Outcome, String> {
+    let mut driver = SimDriver::new(
+        scenario.num_ports(),
+        &scenario.default_chain(),
+        scenario.tx_threads,
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    let nfs = start_nfs(&mut driver, scenario)?;
+
+    /* feed the ports */
+    let mut injected = 0;
+    for port in &scenario.ports {
+        if let Some(rx) = &port.rx_pcap {
+            for pkt in pcap::read(&scenario.path(rx))? {
+                if !driver.backend().port_rx(port.id, &pkt) {
+                    return Err(format!("port {}: rx ring or mbuf pool full", port.id));
+                }
+                injected += 1;
+            }
+        }
+    }
+
+    /* run until every packet is sent out or dropped */
+    let mut captures: HashMap<u16, Vec<Vec<u8>>> = HashMap::new();
+    poll_until(&mut driver, scenario, |d| {
+        for port in &scenario.ports {
+            captures
+                .entry(port.id)
+                .or_default()
+                .extend(d.backend().port_tx(port.id));
+        }
+        let out: usize = captures.values().map(Vec::len).sum();
+        out as u64 + d.dropped() >= injected
+    });
+
+    let num_nfs = driver.num_nfs();
+    for nf in nfs {
+        nf.stop();
+    }
+
+    for port in &scenario.ports {
+        if let Some(tx) = &port.tx_pcap {
+            pcap::write(&scenario.path(tx), &captures[&port.id])?;
+        }
+    }
+
+    Ok(Outcome {
+        sent: captures
+            .iter()
+            .map(|(port, pkts)| (*port, pkts.len() as u64))
+            .collect(),
+        captures,
+        dropped: driver.dropped(),
+        num_nfs,
+    })
+}
+
+#[cfg(not(feature = "sim"))]
+fn run_sim(_: &Scenario) -> Result<Outcome, String> {
+    Err("the harness is built without the sim feature".into())
+}
+
+/// Runs a scenario on the DPDK manager, started with the scenario's ports as virtual devices
+/// NOTE: the EAL can only be initialised once, so a process runs a single DPDK scenario
+#[cfg(feature = "dpdk")]
+fn run_dpdk(scenario: &Scenario) -> Result<Outcome, String> {
+    for (i, port) in scenario.ports.iter().enumerate() {
+        if port.id as usize != i {
+            return Err(format!(
+                "port {}: DPDK numbers the ports in order, list them as ports 0 to {}",
+                port.id,
+                scenario.ports.len() - 1
+            ));
+        }
+    }
+
+    /* a capture left over from an earlier run must not pass for this run's output */
+    for port in &scenario.ports {
+        if let Some(tx) = scenario.tx_capture(port) {
+            let _ = fs::remove_file(tx);
+        }
+    }
+
+    let mut args = vec!["onvm_mgr_test".to_string()];
+    args.extend(EAL_ARGS.iter().map(|arg| arg.to_string()));
+    args.extend(scenario.eal_args());
+    args.push("--".into());
+    let port_mask = (1u64 << scenario.ports.len()) - 1;
+    args.extend(vec![
+        "-p".to_string(),
+        format!("{:x}", port_mask),
+        // NFs run on core 0, next to the harness
+        "-n".to_string(),
+        "1".to_string(),
+        "-R".to_string(),
+        "1".to_string(),
+        "-T".to_string(),
+        scenario.tx_threads.to_string(),
+    ]);
+    let global_state = init::init(args).map_err(|e| format!("{:?}", e))?;
+
+    /* init sets up a default service chain, the scenario brings its own */
+    {
+        let mut chain = global_state.default_chain.write().unwrap();
+        *chain = Default::default();
+        for entry in scenario.default_chain() {
+            chain
+                .append_entry(entry.action, entry.destination)
+                .map_err(|e| format!("{:?}", e))?;
+        }
+    }
+    let mut driver = MgrDriver::from_state(global_state);
+    let nfs = start_nfs(&mut driver, scenario)?;
+
+    /* net_pcap ports replay their rx_pcap once, run until every packet is sent out or dropped */
+    let mut injected = 0;
+    for port in &scenario.ports {
+        if let Some(rx) = &port.rx_pcap {
+            injected += pcap::read(&scenario.path(rx))?.len() as u64;
+        }
+    }
+    poll_until(&mut driver, scenario, |d| {
+        let out: u64 = scenario.ports.iter().map(|p| d.port_sent(p.id)).sum();
+        out + d.dropped() >= injected
+    });
+
+    let num_nfs = driver.num_nfs();
+    for nf in nfs {
+        nf.stop();
+    }
+
+    // NOTE: net_pcap flushes its tx_pcap after every burst, the captures are complete once sent
+    let mut captures = HashMap::new();
+    for port in &scenario.ports {
+        if let Some(tx) = scenario.tx_capture(port) {
+            captures.insert(port.id, pcap::read(&tx)?);
+        }
+    }
+
+    Ok(Outcome {
+        sent: scenario
+            .ports
+            .iter()
+            .map(|port| (port.id, driver.port_sent(port.id)))
+            .collect(),
+        captures,
+        dropped: driver.dropped(),
+        num_nfs,
+    })
+}
+
+#[cfg(not(feature = "dpdk"))]
+fn run_dpdk(_: &Scenario) -> Result<Outcome, String> {
+    Err("the harness is built without the dpdk feature".into())
+}
+
+fn check(scenario: &Scenario, outcome: &Outcome) -> Result<Vec<String>, String> {
+    let mut failures = vec![];
+
+    for expect in &scenario.expect.ports {
+        let sent = outcome.sent.get(&expect.port).copied().unwrap_or(0);
+        if let Some(n) = expect.packets {
+            if sent != n as u64 {
+                failures.push(format!(
+                    "port {}: expected {} packets, got {}",
+                    expect.port, n, sent
+                ));
+            }
+        }
+        if let Some(file) = &expect.pcap {
+            let pkts = match outcome.captures.get(&expect.port) {
+                Some(pkts) => pkts,
+                None => {
+                    failures.push(format!(
+                        "port {}: the sent packets aren't captured, the port needs a {} vdev",
+                        expect.port,
+                        crate::scenario::VDEV_PCAP
+                    ));
+                    continue;
+                }
+            };
+            let expected = pcap::read(&scenario.path(file))?;
+            if expected.len() != pkts.len() {
+                failures.push(format!(
+                    "port {}: {} has {} packets, got {}",
+                    expect.port,
+                    file,
+                    expected.len(),
+                    pkts.len()
+                ));
+            }
+            for (i, (want, got)) in expected.iter().zip(pkts.iter()).enumerate() {
+                if want != got {
+                    failures.push(format!(
+                        "port {}: packet {} differs from {}",
+                        expect.port, i, file
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(n) = scenario.expect.dropped {
+        if outcome.dropped != n {
+            failures.push(format!(
+                "expected {} dropped packets, got {}",
+                n, outcome.dropped
+            ));
+        }
+    }
+    if let Some(n) = scenario.expect.num_nfs {
+        if outcome.num_nfs != n {
+            failures.push(format!(
+                "expected {} running NFs, got {}",
+                n, outcome.num_nfs
+            ));
+        }
+    }
+    Ok(failures)
+}
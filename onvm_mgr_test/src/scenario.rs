@@ -0,0 +1,244 @@
+/*
+ * Created on Wed Oct 21 2020:11:02:17
+ * Created by Ratnadeep Bhattacharya
+ */
+
+use onvm_mgr::nflib::structs::{OnvmAction, OnvmServiceChainEntry};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A test scenario, read from a toml file
+///
+/// ```toml
+/// name = "forward"
+///
+/// [[ports]]
+/// id = 0
+/// vdev = "net_pcap"
+/// rx_pcap = "forward_in.pcap"
+///
+/// [[ports]]
+/// id = 1
+/// vdev = "net_pcap"
+///
+/// [[nfs]]
+/// service_id = 1
+/// handler = "forward"
+/// port = 1
+///
+/// [[chain]]
+/// action = "tonf"
+/// destination = 1
+///
+/// [expect]
+/// dropped = 0
+///
+/// [[expect.ports]]
+/// port = 1
+/// packets = 3
+/// pcap = "forward_in.pcap"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub ports: Vec<PortCfg>,
+    pub nfs: Vec<NfCfg>,
+    pub chain: Vec<ChainCfg>,
+    #[serde(default = "default_tx_threads")]
+    pub tx_threads: u32,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub expect: Expect,
+    // directory of the scenario file, paths in the scenario are relative to it
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// A port, backed by a DPDK virtual device
+#[derive(Debug, Deserialize)]
+pub struct PortCfg {
+    pub id: u16,
+    pub vdev: String,
+    // packets received on the port, net_pcap only
+    pub rx_pcap: Option<String>,
+    // packets sent out of the port are written here, net_pcap only.
+    // On DPDK a port without one writes to a file in the temp directory
+    pub tx_pcap: Option<String>,
+}
+
+/// A NF and the built-in handler it runs
+#[derive(Debug, Deserialize)]
+pub struct NfCfg {
+    pub service_id: u16,
+    pub handler: String,
+    // destination port of the forward handler
+    pub port: Option<u16>,
+    // destination service of the to_nf handler
+    pub service: Option<u16>,
+    #[serde(default = "default_instances")]
+    pub instances: usize,
+}
+
+/// An entry of the default service chain
+#[derive(Debug, Deserialize)]
+pub struct ChainCfg {
+    pub action: String,
+    #[serde(default)]
+    pub destination: u16,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Expect {
+    #[serde(default)]
+    pub ports: Vec<ExpectPort>,
+    pub dropped: Option<u64>,
+    pub num_nfs: Option<u32>,
+}
+
+/// Expected output of a port
+#[derive(Debug, Deserialize)]
+pub struct ExpectPort {
+    pub port: u16,
+    pub packets: Option<usize>,
+    // the packets sent out of the port must match this capture, in order
+    pub pcap: Option<String>,
+}
+
+fn default_tx_threads() -> u32 {
+    1
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_instances() -> usize {
+    1
+}
+
+pub const VDEV_RING: &str = "net_ring";
+pub const VDEV_PCAP: &str = "net_pcap";
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut scenario: Scenario =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        scenario.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for port in &self.ports {
+            if port.vdev != VDEV_RING && port.vdev != VDEV_PCAP {
+                return Err(format!("port {}: unknown vdev {}", port.id, port.vdev));
+            }
+            if port.rx_pcap.is_some() && port.vdev != VDEV_PCAP {
+                return Err(format!(
+                    "port {}: rx_pcap needs a {} vdev",
+                    port.id, VDEV_PCAP
+                ));
+            }
+            if port.tx_pcap.is_some() && port.vdev != VDEV_PCAP {
+                return Err(format!(
+                    "port {}: tx_pcap needs a {} vdev",
+                    port.id, VDEV_PCAP
+                ));
+            }
+        }
+        /* only the packets sent out of a net_pcap port are captured on DPDK */
+        for expect in &self.expect.ports {
+            if expect.pcap.is_none() {
+                continue;
+            }
+            let captured = self
+                .ports
+                .iter()
+                .any(|port| port.id == expect.port && port.vdev == VDEV_PCAP);
+            if !captured {
+                return Err(format!(
+                    "port {}: an expected pcap needs a {} vdev",
+                    expect.port, VDEV_PCAP
+                ));
+            }
+        }
+        if self.chain.is_empty() {
+            return Err("the service chain is empty".into());
+        }
+        for entry in &self.chain {
+            parse_action(&entry.action)?;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    /// Number of ports the manager needs, port IDs don't have to be contiguous
+    #[cfg(feature = "sim")]
+    pub fn num_ports(&self) -> u16 {
+        self.ports.iter().map(|p| p.id + 1).max().unwrap_or(0)
+    }
+
+    pub fn default_chain(&self) -> Vec<OnvmServiceChainEntry> {
+        self.chain
+            .iter()
+            .map(|entry| OnvmServiceChainEntry {
+                action: parse_action(&entry.action).unwrap(),
+                destination: entry.destination,
+            })
+            .collect()
+    }
+
+    /// The file the packets sent out of a net_pcap port are written to on DPDK
+    pub fn tx_capture(&self, port: &PortCfg) -> Option<PathBuf> {
+        if port.vdev != VDEV_PCAP {
+            return None;
+        }
+        Some(match &port.tx_pcap {
+            Some(tx) => self.path(tx),
+            None => env::temp_dir().join(format!("onvm_mgr_test_{}_{}.pcap", self.name, port.id)),
+        })
+    }
+
+    /// The EAL arguments creating the scenario's ports, for running it against the DPDK manager
+    /// NOTE: DPDK numbers the ports in the order of the --vdev arguments
+    pub fn eal_args(&self) -> Vec<String> {
+        let mut ring = 0;
+        let mut pcap = 0;
+        let mut args = vec![];
+        for port in &self.ports {
+            let vdev = if port.vdev == VDEV_RING {
+                ring += 1;
+                format!("{}{}", VDEV_RING, ring - 1)
+            } else {
+                pcap += 1;
+                let mut vdev = format!("{}{}", VDEV_PCAP, pcap - 1);
+                if let Some(rx) = &port.rx_pcap {
+                    vdev.push_str(&format!(",rx_pcap={}", self.path(rx).display()));
+                }
+                if let Some(tx) = self.tx_capture(port) {
+                    vdev.push_str(&format!(",tx_pcap={}", tx.display()));
+                }
+                vdev
+            };
+            args.push(format!("--vdev={}", vdev));
+        }
+        args
+    }
+}
+
+pub fn parse_action(action: &str) -> Result<OnvmAction, String> {
+    match action {
+        "drop" => Ok(OnvmAction::DROP),
+        "next" => Ok(OnvmAction::NEXT),
+        "tonf" => Ok(OnvmAction::TONF),
+        "out" => Ok(OnvmAction::OUT),
+        _ => Err(format!("unknown action {}", action)),
+    }
+}